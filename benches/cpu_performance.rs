@@ -5,7 +5,7 @@ fn benchmark_step(c: &mut Criterion) {
     let mut group = c.benchmark_group("simulation_step");
 
     // Test different grid sizes
-    for size in [50, 100, 200].iter() {
+    for size in [50, 100, 200, 400].iter() {
         group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
             let mut sim = InteractiveFluid::new(size, size);
 
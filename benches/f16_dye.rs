@@ -0,0 +1,82 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use itsliquid::{HalfDyeFluid, InteractiveFluid};
+
+fn seed_f32(sim: &mut InteractiveFluid, size: usize) {
+    sim.add_dye(size / 3, size / 2, (10.0, 5.0, 3.0));
+    sim.add_dye(2 * size / 3, size / 2, (2.0, 8.0, 6.0));
+    sim.add_force(size / 3, size / 2, glam::Vec2::new(20.0, 5.0), 4.0);
+    sim.add_force(2 * size / 3, size / 2, glam::Vec2::new(-20.0, -5.0), 4.0);
+}
+
+fn seed_f16(sim: &mut HalfDyeFluid, size: usize) {
+    sim.add_dye(size / 3, size / 2, (10.0, 5.0, 3.0));
+    sim.add_dye(2 * size / 3, size / 2, (2.0, 8.0, 6.0));
+    sim.add_force(size / 3, size / 2, glam::Vec2::new(20.0, 5.0), 4.0);
+    sim.add_force(2 * size / 3, size / 2, glam::Vec2::new(-20.0, -5.0), 4.0);
+}
+
+fn benchmark_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dye_storage_step");
+    group.sample_size(20);
+
+    for size in [128, 256, 512].iter() {
+        group.bench_with_input(BenchmarkId::new("f32", size), size, |b, &size| {
+            let mut sim = InteractiveFluid::new(size, size);
+            seed_f32(&mut sim, size);
+            b.iter(|| sim.step());
+        });
+
+        group.bench_with_input(BenchmarkId::new("f16", size), size, |b, &size| {
+            let mut sim = HalfDyeFluid::new(size, size);
+            seed_f16(&mut sim, size);
+            b.iter(|| sim.step());
+        });
+    }
+    group.finish();
+}
+
+// Not a timing benchmark: runs both storage modes side by side and reports
+// how far the f16 dye drifts from the f32 reference
+fn report_accuracy(_c: &mut Criterion) {
+    let size = 128;
+    let steps = 300;
+
+    let mut reference = InteractiveFluid::new(size, size);
+    let mut half = HalfDyeFluid::new(size, size);
+    seed_f32(&mut reference, size);
+    seed_f16(&mut half, size);
+
+    for _ in 0..steps {
+        reference.step();
+        half.step();
+    }
+
+    let widened = half.dye_to_f32();
+    let channels = [&reference.dye_r, &reference.dye_g, &reference.dye_b];
+    for (c, name) in ["R", "G", "B"].iter().enumerate() {
+        let max_abs = channels[c]
+            .iter()
+            .zip(&widened[c])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0f32, f32::max);
+        let mass_ref: f32 = channels[c].iter().sum();
+        let mass_half: f32 = widened[c].iter().sum();
+        println!(
+            "f16 accuracy {}x{} after {} steps, channel {}: max abs error {:.6}, mass error {:+.4}%",
+            size,
+            size,
+            steps,
+            name,
+            max_abs,
+            (mass_half - mass_ref) / mass_ref * 100.0
+        );
+    }
+    println!(
+        "Dye memory: f32 {} KiB, f16 {} KiB",
+        6 * size * size * 4 / 1024,
+        half.dye_bytes() / 1024
+    );
+}
+
+criterion_group!(benches, report_accuracy, benchmark_step);
+criterion_main!(benches);
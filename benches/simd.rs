@@ -0,0 +1,80 @@
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use itsliquid::kernels;
+
+// A smooth swirl, so backtraces land all over the grid like in a real run
+fn fields(size: usize) -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+    let mut vx = vec![0.0; size * size];
+    let mut vy = vec![0.0; size * size];
+    let mut dye = vec![0.0; size * size];
+    for y in 0..size {
+        for x in 0..size {
+            let (fx, fy) = (x as f32 / size as f32 - 0.5, y as f32 / size as f32 - 0.5);
+            vx[y * size + x] = -fy * 40.0;
+            vy[y * size + x] = fx * 40.0;
+            dye[y * size + x] = (fx * 12.0).sin() * (fy * 9.0).cos();
+        }
+    }
+    (vx, vy, dye)
+}
+
+fn benchmark_relax(c: &mut Criterion) {
+    let mut group = c.benchmark_group("relax_sweep");
+    group.sample_size(20);
+
+    for size in [128, 256, 512].iter() {
+        let (_, _, source) = fields(*size);
+        let mut field = source.clone();
+        let snapshot = source.clone();
+
+        group.bench_with_input(BenchmarkId::new("scalar", size), size, |b, &size| {
+            b.iter(|| {
+                for (i, row) in field[size..(size - 1) * size].chunks_mut(size).enumerate() {
+                    black_box(kernels::relax_row_scalar(row, &snapshot, &source, i + 1, size, [0.5, 0.5], 3.0, 0));
+                }
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("simd", size), size, |b, &size| {
+            b.iter(|| {
+                for (i, row) in field[size..(size - 1) * size].chunks_mut(size).enumerate() {
+                    black_box(kernels::relax_row_simd(row, &snapshot, &source, i + 1, size, [0.5, 0.5], 3.0, 0));
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_advect(c: &mut Criterion) {
+    let mut group = c.benchmark_group("advect_dye");
+    group.sample_size(20);
+
+    for size in [128, 256, 512].iter() {
+        let (vx, vy, dye) = fields(*size);
+        let (mut r, mut g, mut b) = (dye.clone(), dye.clone(), dye.clone());
+
+        group.bench_with_input(BenchmarkId::new("scalar", size), size, |bench, &size| {
+            bench.iter(|| {
+                let rows = r[size..(size - 1) * size].chunks_mut(size);
+                let rows = rows.zip(g[size..(size - 1) * size].chunks_mut(size));
+                for (i, ((row_r, row_g), row_b)) in rows.zip(b[size..(size - 1) * size].chunks_mut(size)).enumerate() {
+                    kernels::advect_row_scalar([row_r, row_g, row_b], [&dye, &dye, &dye], &vx, &vy, i + 1, size, size, [0.1, 0.1]);
+                }
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("simd", size), size, |bench, &size| {
+            bench.iter(|| {
+                let rows = r[size..(size - 1) * size].chunks_mut(size);
+                let rows = rows.zip(g[size..(size - 1) * size].chunks_mut(size));
+                for (i, ((row_r, row_g), row_b)) in rows.zip(b[size..(size - 1) * size].chunks_mut(size)).enumerate() {
+                    kernels::advect_row_simd([row_r, row_g, row_b], [&dye, &dye, &dye], &vx, &vy, i + 1, size, size, [0.1, 0.1]);
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_relax, benchmark_advect);
+criterion_main!(benches);
@@ -1,5 +1,11 @@
 use crate::export::FluidData;
+use crate::fluid_interactive::{BoundaryLeak, SanitizeEvents};
+use crate::InteractiveFluid;
+use crate::gates::{GateFlux, GateSet};
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
 
 #[derive(Debug, Clone)]
 pub struct FluidMetrics {
@@ -13,6 +19,109 @@ pub struct FluidMetrics {
     pub velocity_divergence: f32,
     pub vorticity: f32,
     pub frame: usize,
+    pub gate_flux: Vec<(String, GateFlux)>,
+    pub boundary_leak: Option<BoundaryLeak>,
+    /// Dye that left through the edges this frame, all channels; 0 without
+    /// a boundary balance
+    pub mass_lost: f32,
+    /// Cells the solver's sanitize pass had repaired by this frame
+    pub sanitize_events: Option<SanitizeEvents>,
+    /// Per-channel statistics, for solvers with colored dye
+    pub dye: Option<DyeMetrics>,
+}
+
+/// Rec. 709 weights of red, green and blue in perceived brightness
+pub const LUMINANCE: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// Statistics of RGB dye, channel by channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DyeMetrics {
+    /// Dye summed over every cell
+    pub total: [f32; 3],
+    /// Where each channel's dye is centered, in cells; `None` for a channel
+    /// with no dye
+    pub center_of_mass: [Option<(f32, f32)>; 3],
+    /// How evenly the dye's brightness is spread: the entropy in bits of
+    /// each cell's share of the total luminance. 0 when it all sits in one
+    /// cell, log2 of the cell count when it is uniform
+    pub luminance_entropy: f32,
+}
+
+impl DyeMetrics {
+    pub fn analyze(width: usize, channels: [&[f32]; 3]) -> Self {
+        let mut total = [0.0f32; 3];
+        let mut moment = [(0.0f32, 0.0f32); 3];
+        let mut weight = [0.0f32; 3];
+        let mut luminance = Vec::with_capacity(channels[0].len());
+        for idx in 0..channels[0].len() {
+            let (x, y) = ((idx % width) as f32 + 0.5, (idx / width) as f32 + 0.5);
+            let mut cell_luminance = 0.0;
+            for (c, channel) in channels.iter().enumerate() {
+                let value = channel[idx];
+                total[c] += value;
+                // Negative dye would pull the center outside the dye
+                let value = value.max(0.0);
+                weight[c] += value;
+                moment[c].0 += value * x;
+                moment[c].1 += value * y;
+                cell_luminance += LUMINANCE[c] * value;
+            }
+            luminance.push(cell_luminance);
+        }
+
+        let center_of_mass =
+            std::array::from_fn(|c| (weight[c] > 0.0).then(|| (moment[c].0 / weight[c], moment[c].1 / weight[c])));
+
+        let total_luminance: f32 = luminance.iter().sum();
+        let mut luminance_entropy = 0.0;
+        if total_luminance > 0.0 {
+            for &l in &luminance {
+                let share = l / total_luminance;
+                if share > 0.0 {
+                    luminance_entropy -= share * share.log2();
+                }
+            }
+        }
+
+        Self {
+            total,
+            center_of_mass,
+            luminance_entropy,
+        }
+    }
+}
+
+// `FluidData` for an `InteractiveFluid`, with the summed dye as density
+struct InteractiveView<'a> {
+    fluid: &'a InteractiveFluid,
+    density: Vec<f32>,
+}
+
+impl FluidData for InteractiveView<'_> {
+    fn width(&self) -> usize {
+        self.fluid.width
+    }
+    fn height(&self) -> usize {
+        self.fluid.height
+    }
+    fn density(&self) -> &[f32] {
+        &self.density
+    }
+    fn velocity_x(&self) -> &[f32] {
+        &self.fluid.velocity_x
+    }
+    fn velocity_y(&self) -> &[f32] {
+        &self.fluid.velocity_y
+    }
+    fn boundary_leak(&self) -> Option<BoundaryLeak> {
+        Some(self.fluid.boundary_leak)
+    }
+    fn sanitize_events(&self) -> Option<SanitizeEvents> {
+        Some(self.fluid.sanitize_events)
+    }
+    fn dye(&self) -> Option<[&[f32]; 3]> {
+        Some([&self.fluid.dye_r[..], &self.fluid.dye_g[..], &self.fluid.dye_b[..]])
+    }
 }
 
 impl FluidMetrics {
@@ -80,6 +189,7 @@ impl FluidMetrics {
         let velocity_divergence = total_divergence / size as f32;
         let vorticity = total_vorticity / size as f32;
 
+        let boundary_leak = simulation.boundary_leak();
         Self {
             total_mass,
             max_density,
@@ -91,9 +201,45 @@ impl FluidMetrics {
             velocity_divergence,
             vorticity,
             frame,
+            gate_flux: Vec::new(),
+            boundary_leak,
+            mass_lost: boundary_leak.map_or(0.0, |leak| leak.mass_lost()),
+            sanitize_events: simulation.sanitize_events(),
+            dye: simulation.dye().map(|channels| DyeMetrics::analyze(simulation.width(), channels)),
         }
     }
 
+    /// `analyze` for the RGB solver: density is the dye of all channels, and
+    /// the boundary balance, sanitize count and per-channel dye come with it.
+    pub fn analyze_interactive(fluid: &InteractiveFluid, frame: usize) -> Self {
+        let density = fluid.dye_r.iter().zip(&fluid.dye_g).zip(&fluid.dye_b).map(|((r, g), b)| r + g + b).collect();
+        Self::analyze(&InteractiveView { fluid, density }, frame)
+    }
+
+    /// Attach the boundary dye balance of a solver that does not implement
+    /// `FluidData`, e.g. `InteractiveFluid::boundary_leak`.
+    pub fn with_boundary_leak(mut self, leak: BoundaryLeak) -> Self {
+        self.boundary_leak = Some(leak);
+        self.mass_lost = leak.mass_lost();
+        self
+    }
+
+    /// Attach `InteractiveFluid::sanitize_events`, like `with_boundary_leak`.
+    pub fn with_sanitize_events(mut self, events: SanitizeEvents) -> Self {
+        self.sanitize_events = Some(events);
+        self
+    }
+
+    /// Attach the most recent flux sample of each gate to these metrics.
+    pub fn with_gates(mut self, gates: &GateSet) -> Self {
+        self.gate_flux = gates
+            .gates
+            .iter()
+            .filter_map(|gate| gate.latest().map(|flux| (gate.name.clone(), *flux)))
+            .collect();
+        self
+    }
+
     pub fn print_summary(&self) {
         println!("Frame {} Metrics:", self.frame);
         println!("  Total Mass: {:.6}", self.total_mass);
@@ -105,6 +251,42 @@ impl FluidMetrics {
         println!("  Density Entropy: {:.6}", self.density_entropy);
         println!("  Velocity Divergence: {:.6}", self.velocity_divergence);
         println!("  Vorticity: {:.6}", self.vorticity);
+        if self.mass_lost != 0.0 {
+            println!("  Mass Lost Through Edges: {:.6}", self.mass_lost);
+        }
+        if let Some(leak) = &self.boundary_leak {
+            for (side, name) in ["Left", "Right", "Top", "Bottom"].iter().enumerate() {
+                let out = leak.outflow[side];
+                if out.iter().any(|&v| v != 0.0) {
+                    println!(
+                        "  Outflow {}: ({:.6}, {:.6}, {:.6})",
+                        name, out[0], out[1], out[2]
+                    );
+                }
+            }
+            println!(
+                "  Numerical Correction: ({:+.6}, {:+.6}, {:+.6})",
+                leak.numerical[0], leak.numerical[1], leak.numerical[2]
+            );
+        }
+        if let Some(events) = self.sanitize_events.filter(|e| e.total() > 0) {
+            println!("  Sanitized Cells: {} clamped, {} reset", events.clamped, events.reset);
+        }
+        if let Some(dye) = &self.dye {
+            println!("  Dye (RGB): ({:.6}, {:.6}, {:.6})", dye.total[0], dye.total[1], dye.total[2]);
+            for (name, center) in ["Red", "Green", "Blue"].iter().zip(dye.center_of_mass) {
+                if let Some((x, y)) = center {
+                    println!("  {} Center: ({:.2}, {:.2})", name, x, y);
+                }
+            }
+            println!("  Luminance Entropy: {:.6}", dye.luminance_entropy);
+        }
+        for (name, flux) in &self.gate_flux {
+            println!(
+                "  Gate {} Flux: volume {:+.6}, dye ({:+.6}, {:+.6}, {:+.6})",
+                name, flux.volume, flux.dye[0], flux.dye[1], flux.dye[2]
+            );
+        }
         println!();
     }
 }
@@ -125,6 +307,76 @@ impl AnalysisRecorder {
         self.metrics_history.push(metrics);
     }
 
+    /// Record metrics built elsewhere, e.g. with attached boundary leak or gates.
+    pub fn record_metrics(&mut self, metrics: FluidMetrics) {
+        self.metrics_history.push(metrics);
+    }
+
+    /// Sum of the recorded boundary balances, or `None` if no frame carried one.
+    pub fn cumulative_boundary_leak(&self) -> Option<BoundaryLeak> {
+        let mut total: Option<BoundaryLeak> = None;
+        for leak in self.metrics_history.iter().filter_map(|m| m.boundary_leak.as_ref()) {
+            total.get_or_insert_with(BoundaryLeak::default).accumulate(leak);
+        }
+        total
+    }
+
+    /// Flux through each gate summed over the recorded frames, in the order
+    /// the gates first appear; `frame` is the last frame each was seen in.
+    pub fn cumulative_gate_flux(&self) -> Vec<(String, GateFlux)> {
+        let mut totals: Vec<(String, GateFlux)> = Vec::new();
+        for (name, flux) in self.metrics_history.iter().flat_map(|m| &m.gate_flux) {
+            let index = match totals.iter().position(|(n, _)| n == name) {
+                Some(index) => index,
+                None => {
+                    totals.push((name.clone(), GateFlux::default()));
+                    totals.len() - 1
+                }
+            };
+            let total = &mut totals[index].1;
+            total.frame = flux.frame;
+            total.volume += flux.volume;
+            for c in 0..3 {
+                total.dye[c] += flux.dye[c];
+            }
+        }
+        totals
+    }
+
+    /// Dye lost through the edges over all recorded frames.
+    pub fn total_mass_lost(&self) -> f32 {
+        self.metrics_history.iter().map(|m| m.mass_lost).sum()
+    }
+
+    /// Write one row per recorded frame.
+    pub fn export_csv(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(
+            out,
+            "frame,total_mass,max_density,avg_density,kinetic_energy,max_velocity,avg_velocity,entropy,divergence,vorticity,mass_lost,sanitized"
+        )?;
+        for m in &self.metrics_history {
+            writeln!(
+                out,
+                "{},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{}",
+                m.frame,
+                m.total_mass,
+                m.max_density,
+                m.avg_density,
+                m.total_kinetic_energy,
+                m.max_velocity,
+                m.avg_velocity,
+                m.density_entropy,
+                m.velocity_divergence,
+                m.vorticity,
+                m.mass_lost,
+                m.sanitize_events.map_or(0, |e| e.total())
+            )?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
     pub fn print_trends(&self) {
         if self.metrics_history.len() < 2 {
             return;
@@ -155,5 +407,27 @@ impl AnalysisRecorder {
             (last.density_entropy - first.density_entropy) / first.density_entropy.max(0.001)
                 * 100.0
         );
+
+        // Split mass change into physical outflow and numerical correction
+        if let Some(leak) = self.cumulative_boundary_leak() {
+            let outflow = leak.total_outflow();
+            println!(
+                "Boundary outflow (RGB): ({:.6}, {:.6}, {:.6})",
+                outflow[0], outflow[1], outflow[2]
+            );
+            println!(
+                "Numerical correction (RGB): ({:+.6}, {:+.6}, {:+.6})",
+                leak.numerical[0], leak.numerical[1], leak.numerical[2]
+            );
+        }
+        if let Some(events) = last.sanitize_events.filter(|e| e.total() > 0) {
+            println!("Sanitized cells: {} clamped, {} reset", events.clamped, events.reset);
+        }
+        for (name, flux) in self.cumulative_gate_flux() {
+            println!(
+                "Gate {} flux: volume {:+.6}, dye ({:+.6}, {:+.6}, {:+.6})",
+                name, flux.volume, flux.dye[0], flux.dye[1], flux.dye[2]
+            );
+        }
     }
 }
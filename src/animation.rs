@@ -0,0 +1,229 @@
+//! Offline animation renders: a scene, with its timeline and optionally a
+//! recording of strokes, stepped a fixed number of solver steps per output
+//! frame and written as a PNG sequence or video. Nothing depends on how
+//! fast frames are produced, so the same export always gives the same
+//! frames.
+
+use crate::export::{VideoCodec, VideoEncoder, VideoOptions};
+use crate::render::{RenderMode, Renderer};
+use crate::replay::{Recording, ReplayTarget};
+use crate::scene::{PersistentElement, Scene};
+use crate::timeline::Timeline;
+use crate::InteractiveFluid;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    /// `frame_0001.png` and on, in a directory
+    Png,
+    Mp4,
+    Webm,
+}
+
+impl AnimationFormat {
+    pub const ALL: [AnimationFormat; 3] = [AnimationFormat::Png, AnimationFormat::Mp4, AnimationFormat::Webm];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AnimationFormat::Png => "PNG sequence",
+            AnimationFormat::Mp4 => "MP4 video",
+            AnimationFormat::Webm => "WebM video",
+        }
+    }
+
+    fn codec(self) -> Option<VideoCodec> {
+        match self {
+            AnimationFormat::Png => None,
+            AnimationFormat::Mp4 => Some(VideoCodec::H264),
+            AnimationFormat::Webm => Some(VideoCodec::Vp9),
+        }
+    }
+}
+
+/// Settings for one export. Each frame applies the elements once, posed by
+/// the scene's timeline, then takes `substeps` steps splitting the scene's
+/// dt, as the app does. A recording replaces the elements for the steps it
+/// covers, since it already holds their inputs, and the elements take over
+/// where it ends.
+#[derive(Debug, Clone)]
+pub struct AnimationExport {
+    pub scene: Scene,
+    /// Strokes to replay; the render runs on its grid, as its inputs are
+    /// in cells
+    pub recording: Option<Recording>,
+    pub frames: usize,
+    pub substeps: usize,
+    /// Output width in pixels; the height follows the grid
+    pub width: u32,
+    pub mode: RenderMode,
+    pub format: AnimationFormat,
+    pub fps: u32,
+    /// Directory for PNG frames, or the video file
+    pub out: PathBuf,
+}
+
+impl AnimationExport {
+    pub fn new(scene: Scene, out: impl Into<PathBuf>) -> Self {
+        Self {
+            scene,
+            recording: None,
+            frames: 300,
+            substeps: 1,
+            width: 1280,
+            mode: RenderMode::Dye,
+            format: AnimationFormat::Png,
+            fps: 30,
+            out: out.into(),
+        }
+    }
+
+    /// Grid the render runs on.
+    pub fn grid(&self) -> (usize, usize) {
+        match &self.recording {
+            Some(recording) => (recording.width(), recording.height()),
+            None => (self.scene.width, self.scene.height),
+        }
+    }
+
+    /// Output size in pixels, at least 1x1.
+    pub fn dimensions(&self) -> (u32, u32) {
+        let (width, height) = self.grid();
+        let w = self.width.max(1);
+        (w, (w as usize * height / width.max(1)).max(1) as u32)
+    }
+
+    /// Step through the animation, handing each frame's fields to
+    /// `on_frame` with its index from 0. Returns the frames played, fewer
+    /// than `frames` if `on_frame` returned false.
+    pub fn play(&self, mut on_frame: impl FnMut(usize, &InteractiveFluid) -> bool) -> usize {
+        let (width, height) = self.grid();
+        let (mut fluid, mut elements) = match &self.recording {
+            Some(recording) => (recording.simulation(), self.scene.elements_for(width, height)),
+            None => (self.scene.simulation(), self.scene.elements.clone()),
+        };
+        let mut timeline = self
+            .scene
+            .timeline_for(width, height)
+            .map(|timeline| Timeline { frame: 0, playing: true, ..timeline });
+        let mut recorded = 0;
+
+        for index in 0..self.frames {
+            match &self.recording {
+                Some(recording) if recorded < recording.frames => {
+                    for _ in 0..self.substeps.max(1) {
+                        replay_step(recording, recorded, &mut fluid);
+                        recorded += 1;
+                        if recorded == recording.frames {
+                            break;
+                        }
+                    }
+                }
+                _ => {
+                    let forces = apply_elements(&mut elements, timeline.as_mut(), &mut fluid);
+                    let dt = fluid.dt;
+                    fluid.dt = dt / self.substeps.max(1) as f32;
+                    for _ in 0..self.substeps.max(1) {
+                        for elem in &forces {
+                            elem.apply_force(&mut fluid);
+                        }
+                        fluid.step();
+                    }
+                    fluid.dt = dt;
+                }
+            }
+            if !on_frame(index, &fluid) {
+                return index + 1;
+            }
+        }
+        self.frames
+    }
+
+    /// Play the animation into `out`. `progress` gets the frames written
+    /// so far after each one and can return false to stop early; the
+    /// frames written until then are kept.
+    pub fn render(&self, mut progress: impl FnMut(usize) -> bool) -> Result<usize, Box<dyn std::error::Error>> {
+        let (w, h) = self.dimensions();
+        let renderer = Renderer::new(w, h);
+        let mut failure = None;
+
+        let written = match self.format.codec() {
+            Some(codec) => {
+                if let Some(dir) = self.out.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let options = VideoOptions {
+                    fps: self.fps.max(1),
+                    codec,
+                    ..Default::default()
+                };
+                let mut encoder = VideoEncoder::start(&self.out, w, h, &options)?;
+                let written = self.play(|index, fluid| match encoder.write_frame(&renderer.render_interactive(fluid, self.mode)) {
+                    Ok(()) => progress(index + 1),
+                    Err(e) => {
+                        failure = Some(e);
+                        false
+                    }
+                });
+                encoder.finish()?;
+                written
+            }
+            None => {
+                std::fs::create_dir_all(&self.out)?;
+                self.play(|index, fluid| {
+                    let path = self.out.join(format!("frame_{:04}.png", index + 1));
+                    match renderer.render_interactive(fluid, self.mode).save(path) {
+                        Ok(()) => progress(index + 1),
+                        Err(e) => {
+                            failure = Some(e.into());
+                            false
+                        }
+                    }
+                })
+            }
+        };
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(written),
+        }
+    }
+}
+
+// One recorded step, with the inputs logged after the last step on the
+// way out
+fn replay_step(recording: &Recording, step: u64, fluid: &mut InteractiveFluid) {
+    for input in recording.events_at(step) {
+        fluid.apply_input(input);
+    }
+    fluid.step();
+    if step + 1 == recording.frames {
+        for input in recording.events_at(recording.frames) {
+            fluid.apply_input(input);
+        }
+    }
+}
+
+// Inject the elements' dye and heat for a frame, returning the forces for
+// the substeps to apply
+fn apply_elements(
+    elements: &mut [PersistentElement],
+    mut timeline: Option<&mut Timeline>,
+    fluid: &mut InteractiveFluid,
+) -> Vec<PersistentElement> {
+    if let Some(timeline) = timeline.as_deref_mut() {
+        timeline.pose(elements);
+    }
+    let mut forces = Vec::new();
+    for (i, elem) in elements.iter_mut().enumerate() {
+        if timeline.as_deref().is_none_or(|timeline| timeline.enabled(i)) {
+            elem.apply_source(fluid, None);
+            if elem.is_force() {
+                forces.push(*elem);
+            }
+        }
+        elem.advance(fluid.dt);
+    }
+    if let Some(timeline) = timeline {
+        timeline.advance();
+    }
+    forces
+}
@@ -0,0 +1,191 @@
+//! Audio-reactive forcing: bass, mid and treble levels from live input,
+//! and links that make a persistent element pulse with one of them. The
+//! levels and links are plain data; capturing from a device needs the
+//! `audio` feature.
+
+use serde::{Deserialize, Serialize};
+
+// Crossover frequencies between the bands, in Hz
+const BASS_CUTOFF: f32 = 250.0;
+const TREBLE_CUTOFF: f32 = 4000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioBand {
+    /// Below 250 Hz: kicks and bass lines
+    Bass,
+    Mid,
+    /// Above 4 kHz: hats, cymbals and sibilance
+    Treble,
+}
+
+impl AudioBand {
+    pub const ALL: [AudioBand; 3] = [AudioBand::Bass, AudioBand::Mid, AudioBand::Treble];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AudioBand::Bass => "Bass",
+            AudioBand::Mid => "Mid",
+            AudioBand::Treble => "Treble",
+        }
+    }
+}
+
+/// Ties an element's strength to a band: it is scaled by
+/// `1 + gain * level` each step, so silence leaves it as placed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AudioLink {
+    pub band: AudioBand,
+    pub gain: f32,
+}
+
+/// Band amplitudes, with a full-scale sine reading 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioLevels {
+    pub bass: f32,
+    pub mid: f32,
+    pub treble: f32,
+}
+
+impl AudioLevels {
+    pub fn get(&self, band: AudioBand) -> f32 {
+        match band {
+            AudioBand::Bass => self.bass,
+            AudioBand::Mid => self.mid,
+            AudioBand::Treble => self.treble,
+        }
+    }
+
+    /// Levels of a mono buffer, split into bands by one-pole crossovers.
+    pub fn analyze(samples: &[f32], sample_rate: f32) -> Self {
+        if samples.is_empty() || sample_rate <= 0.0 {
+            return Self::default();
+        }
+        let coefficient = |cutoff: f32| 1.0 - (-2.0 * std::f32::consts::PI * cutoff / sample_rate).exp();
+        let (low_k, high_k) = (coefficient(BASS_CUTOFF), coefficient(TREBLE_CUTOFF));
+        let (mut low, mut below_treble) = (0.0f32, 0.0f32);
+        let mut sums = [0.0f32; 3];
+        for &sample in samples {
+            low += low_k * (sample - low);
+            below_treble += high_k * (sample - below_treble);
+            let bands = [low, below_treble - low, sample - below_treble];
+            for (sum, band) in sums.iter_mut().zip(bands) {
+                *sum += band * band;
+            }
+        }
+        // RMS scaled so a full-scale sine reads 1
+        let level = |sum: f32| (sum / samples.len() as f32).sqrt() * std::f32::consts::SQRT_2;
+        Self {
+            bass: level(sums[0]),
+            mid: level(sums[1]),
+            treble: level(sums[2]),
+        }
+    }
+
+    /// Every band times `sensitivity`, clamped to `0..=1`.
+    pub fn scaled(self, sensitivity: f32) -> Self {
+        let scale = |level: f32| (level * sensitivity).clamp(0.0, 1.0);
+        Self {
+            bass: scale(self.bass),
+            mid: scale(self.mid),
+            treble: scale(self.treble),
+        }
+    }
+
+    /// Envelope follower: jump up to louder `input` at once, fall towards
+    /// quieter input by `release` (in `0..=1`) per call, so pulses decay
+    /// smoothly between beats.
+    pub fn follow(&mut self, input: Self, release: f32) {
+        let release = release.clamp(0.0, 1.0);
+        for (level, target) in [(&mut self.bass, input.bass), (&mut self.mid, input.mid), (&mut self.treble, input.treble)] {
+            *level = if target >= *level { target } else { *level + (target - *level) * release };
+        }
+    }
+}
+
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+pub use capture::AudioInput;
+
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+mod capture {
+    use super::AudioLevels;
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use std::sync::{Arc, Mutex};
+
+    /// Live capture from the default input device (a microphone, or a
+    /// loopback/monitor source to follow system audio). Samples are
+    /// downmixed to mono and buffered until `poll` analyzes them.
+    pub struct AudioInput {
+        pub device: String,
+        sample_rate: f32,
+        samples: Arc<Mutex<Vec<f32>>>,
+        // Capture stops when the stream is dropped
+        _stream: cpal::Stream,
+    }
+
+    impl AudioInput {
+        pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+            let host = cpal::default_host();
+            let device = host.default_input_device().ok_or("no audio input device")?;
+            let name = device.name().unwrap_or_else(|_| String::from("default input"));
+            let config = device.default_input_config()?;
+            let channels = config.channels().max(1) as usize;
+            let sample_rate = config.sample_rate().0 as f32;
+
+            let samples = Arc::new(Mutex::new(Vec::new()));
+            let buffer = Arc::clone(&samples);
+            // At most a second is kept if nothing polls
+            let capacity = sample_rate as usize;
+            let push = move |data: &mut dyn Iterator<Item = f32>| {
+                let Ok(mut buffer) = buffer.lock() else { return };
+                let frames: Vec<f32> = data.collect();
+                buffer.extend(frames.chunks(channels).map(|frame| frame.iter().sum::<f32>() / frame.len() as f32));
+                let excess = buffer.len().saturating_sub(capacity);
+                buffer.drain(..excess);
+            };
+            let on_error = |e: cpal::StreamError| eprintln!("Audio input error: {}", e);
+            let stream_config = config.config();
+            let stream = match config.sample_format() {
+                cpal::SampleFormat::F32 => device.build_input_stream(
+                    &stream_config,
+                    move |data: &[f32], _: &cpal::InputCallbackInfo| push(&mut data.iter().copied()),
+                    on_error,
+                    None,
+                )?,
+                cpal::SampleFormat::I16 => device.build_input_stream(
+                    &stream_config,
+                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                        push(&mut data.iter().map(|&s| s as f32 / i16::MAX as f32))
+                    },
+                    on_error,
+                    None,
+                )?,
+                cpal::SampleFormat::U16 => device.build_input_stream(
+                    &stream_config,
+                    move |data: &[u16], _: &cpal::InputCallbackInfo| {
+                        push(&mut data.iter().map(|&s| (s as f32 - 32768.0) / 32768.0))
+                    },
+                    on_error,
+                    None,
+                )?,
+                format => return Err(format!("unsupported sample format {:?}", format).into()),
+            };
+            stream.play()?;
+
+            Ok(Self {
+                device: name,
+                sample_rate,
+                samples,
+                _stream: stream,
+            })
+        }
+
+        /// Levels of everything captured since the last call.
+        pub fn poll(&self) -> AudioLevels {
+            let samples = match self.samples.lock() {
+                Ok(mut buffer) => std::mem::take(&mut *buffer),
+                Err(_) => return AudioLevels::default(),
+            };
+            AudioLevels::analyze(&samples, self.sample_rate)
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! The brush shared by the painting tools: a radius in grid cells, a falloff
+//! from center to edge, spacing between dabs along a drag, stylus pressure
+//! scaling the intensity, and how its dye blends with what is there.
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// How a dab's strength fades from its center to its edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Falloff {
+    /// Straight down to zero at the edge
+    Linear,
+    /// Soft bell, about 1% of the center strength at the edge
+    Gaussian,
+}
+
+impl Falloff {
+    pub const ALL: [Falloff; 2] = [Falloff::Linear, Falloff::Gaussian];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Falloff::Linear => "Linear",
+            Falloff::Gaussian => "Gaussian",
+        }
+    }
+
+    /// Strength at `t`, the distance from the center as a fraction of the
+    /// radius. Zero beyond the edge.
+    pub fn weight(self, t: f32) -> f32 {
+        if t > 1.0 {
+            return 0.0;
+        }
+        match self {
+            Falloff::Linear => 1.0 - t,
+            Falloff::Gaussian => (-4.6 * t * t).exp(),
+        }
+    }
+}
+
+/// How painted dye combines with the dye already in a cell. `color` is the
+/// dye color times intensity and `weight` the dab's coverage of the cell.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// Pile dye on top; black removes dye instead
+    #[default]
+    Add,
+    /// Set the cell to the color wherever the dab reaches
+    Replace,
+    /// Tint by the color, fading to no change at the dab's edge
+    Multiply,
+    /// Take the color away, down to no dye
+    Subtract,
+    /// Cover with the color at `opacity`, like paint over paint
+    Over { opacity: f32 },
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 5] =
+        [BlendMode::Add, BlendMode::Replace, BlendMode::Multiply, BlendMode::Subtract, BlendMode::Over { opacity: 0.5 }];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlendMode::Add => "Add",
+            BlendMode::Replace => "Replace",
+            BlendMode::Multiply => "Multiply",
+            BlendMode::Subtract => "Subtract",
+            BlendMode::Over { .. } => "Over",
+        }
+    }
+
+    /// Same mode, whatever the opacity.
+    pub fn same_kind(self, other: BlendMode) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+
+    /// One channel's new value from the `old` dye, the painted `color` and
+    /// the dab `weight`. Never negative.
+    pub fn blend(self, old: f32, color: f32, weight: f32) -> f32 {
+        let weight = weight.clamp(0.0, 1.0);
+        let new = match self {
+            BlendMode::Add => old + color * weight,
+            BlendMode::Replace if weight > 0.0 => color,
+            BlendMode::Replace => old,
+            BlendMode::Multiply => old * (1.0 + (color - 1.0) * weight),
+            BlendMode::Subtract => old - color * weight,
+            BlendMode::Over { opacity } => old + (color - old) * weight * opacity.clamp(0.0, 1.0),
+        };
+        new.max(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Brush {
+    /// In grid cells
+    pub radius: f32,
+    pub falloff: Falloff,
+    /// Distance between dabs along a drag, as a fraction of the radius
+    pub spacing: f32,
+    /// Scale intensity by stylus pressure when the device reports it
+    pub pressure: bool,
+    #[serde(default)]
+    pub blend: BlendMode,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            radius: 2.0,
+            falloff: Falloff::Linear,
+            spacing: 0.25,
+            pressure: true,
+            blend: BlendMode::Add,
+        }
+    }
+}
+
+impl Brush {
+    /// Cells covered by a dab centered on `(x, y)`, in grid units where cell
+    /// `(i, j)` spans `i..i + 1`, with the weight of each. Cells off the
+    /// grid are left out, and the cell under the center is always covered
+    /// however small the radius.
+    pub fn dab(&self, x: f32, y: f32, width: usize, height: usize) -> Vec<(usize, usize, f32)> {
+        // Wide enough to reach the corners of the cell under the center
+        let radius = self.radius.max(0.75);
+        let mut cells = Vec::new();
+        let (x0, x1) = ((x - radius).floor().max(0.0) as usize, (x + radius).ceil().max(0.0) as usize);
+        let (y0, y1) = ((y - radius).floor().max(0.0) as usize, (y + radius).ceil().max(0.0) as usize);
+        for py in y0..y1.min(height) {
+            for px in x0..x1.min(width) {
+                let dx = px as f32 + 0.5 - x;
+                let dy = py as f32 + 0.5 - y;
+                let weight = self.falloff.weight((dx * dx + dy * dy).sqrt() / radius);
+                if weight > 0.0 {
+                    cells.push((px, py, weight));
+                }
+            }
+        }
+        cells
+    }
+
+    /// Intensity scale for a stylus reading in `0..=1`: 1 without one, or
+    /// when pressure is turned off.
+    pub fn pressure_scale(&self, pressure: Option<f32>) -> f32 {
+        match pressure {
+            Some(p) if self.pressure => p.clamp(0.0, 1.0),
+            _ => 1.0,
+        }
+    }
+
+    /// Grid distance between dabs along a drag.
+    pub fn step(&self) -> f32 {
+        (self.radius * self.spacing).max(0.1)
+    }
+}
+
+/// Where the last dab of a drag landed, so that dabs are spaced by
+/// distance moved rather than by frame, and fill in the path between
+/// pointer samples however fast the pointer moves.
+#[derive(Debug, Clone, Default)]
+pub struct BrushStroke {
+    last: Option<Vec2>,
+}
+
+impl BrushStroke {
+    /// Forget the last dab, so the next one starts a new stroke.
+    pub fn end(&mut self) {
+        self.last = None;
+    }
+
+    /// Dabs for the pointer moving to `pos`: just `pos` at the start of a
+    /// stroke, then one every brush step along the segment from the last
+    /// dab, which may be none if the pointer hasn't moved a step yet.
+    pub fn dabs(&mut self, brush: &Brush, pos: Vec2) -> Vec<Vec2> {
+        let Some(mut last) = self.last else {
+            self.last = Some(pos);
+            return vec![pos];
+        };
+        let step = brush.step();
+        let distance = last.distance(pos);
+        let count = (distance / step) as usize;
+        let direction = (pos - last) / distance.max(f32::EPSILON);
+        let mut dabs = Vec::with_capacity(count);
+        for _ in 0..count {
+            last += direction * step;
+            dabs.push(last);
+        }
+        self.last = Some(last);
+        dabs
+    }
+}
+
+/// Latest stylus pressure among this frame's touch events, if the device
+/// reports one.
+pub fn touch_pressure(events: &[egui::Event]) -> Option<f32> {
+    events.iter().rev().find_map(|event| match event {
+        egui::Event::Touch { force, .. } => *force,
+        _ => None,
+    })
+}
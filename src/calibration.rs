@@ -0,0 +1,164 @@
+//! First-run calibration: time a short solver benchmark and pick the grid
+//! density, substeps per frame and quality preset the device can sustain.
+
+use crate::timing::now_seconds;
+use crate::InteractiveFluid;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Side of the square grid the benchmark steps.
+pub const BENCH_SIZE: usize = 96;
+
+// Cells at 1x on a 1920x1080 canvas with the app's 8 px cells
+const REFERENCE_CELLS: f32 = 240.0 * 135.0;
+
+// Share of a 60 fps frame the solver may use; rendering and UI need the rest
+const FRAME_BUDGET_SECONDS: f32 = 0.008;
+
+const MAX_SUBSTEPS: usize = 3;
+
+/// Performance tier the measured speed falls in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceClass {
+    /// Phone or tablet browser
+    Phone,
+    /// Laptop or integrated graphics
+    Integrated,
+    /// Desktop with a discrete GPU and a fast CPU
+    Desktop,
+}
+
+impl DeviceClass {
+    /// Classify by solver throughput in million cell-steps per second.
+    /// Touch devices are treated as phones whatever their speed.
+    pub fn from_throughput(throughput: f32, touch: bool) -> Self {
+        if touch || throughput < 3.0 {
+            DeviceClass::Phone
+        } else if throughput < 12.0 {
+            DeviceClass::Integrated
+        } else {
+            DeviceClass::Desktop
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DeviceClass::Phone => "Phone",
+            DeviceClass::Integrated => "Integrated GPU",
+            DeviceClass::Desktop => "Desktop GPU",
+        }
+    }
+
+    pub fn quality(self) -> Quality {
+        match self {
+            DeviceClass::Phone => Quality::Low,
+            DeviceClass::Integrated => Quality::Medium,
+            DeviceClass::Desktop => Quality::High,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+}
+
+impl Quality {
+    pub const ALL: [Quality; 3] = [Quality::Low, Quality::Medium, Quality::High];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quality::Low => "Low",
+            Quality::Medium => "Medium",
+            Quality::High => "High",
+        }
+    }
+
+    /// Upper bound on pressure sweeps per projection.
+    pub fn pressure_iterations(self) -> usize {
+        match self {
+            Quality::Low => 10,
+            Quality::Medium => 20,
+            Quality::High => 30,
+        }
+    }
+
+    /// Tracer particles kept alive at once.
+    pub fn max_particles(self) -> usize {
+        match self {
+            Quality::Low => 5000,
+            Quality::Medium => 20000,
+            Quality::High => 40000,
+        }
+    }
+}
+
+/// Defaults chosen for this device, stored so calibration runs once.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub device: DeviceClass,
+    /// Measured solver speed in million cell-steps per second
+    pub throughput: f32,
+    /// Grid density, as picked by the 1x/2x/4x/8x buttons
+    pub resolution_scale: usize,
+    /// Solver steps per rendered frame, each advancing `dt / substeps`
+    pub substeps: usize,
+    pub quality: Quality,
+}
+
+impl Calibration {
+    /// Benchmark the solver for about `seconds` and derive the defaults.
+    pub fn run(seconds: f64, touch: bool) -> Self {
+        Self::from_throughput(measure(seconds, now_seconds), touch)
+    }
+
+    /// The densest grid, then the most substeps, that fit the frame budget on
+    /// a full-HD canvas.
+    pub fn from_throughput(throughput: f32, touch: bool) -> Self {
+        let device = DeviceClass::from_throughput(throughput, touch);
+        let budget = throughput * 1e6 * FRAME_BUDGET_SECONDS;
+
+        let mut resolution_scale = 1;
+        for scale in [2, 4] {
+            if REFERENCE_CELLS * (scale * scale) as f32 <= budget {
+                resolution_scale = scale;
+            }
+        }
+        let cells = REFERENCE_CELLS * (resolution_scale * resolution_scale) as f32;
+        let substeps = ((budget / cells) as usize).clamp(1, MAX_SUBSTEPS);
+
+        Self {
+            device,
+            throughput,
+            resolution_scale,
+            substeps,
+            quality: device.quality(),
+        }
+    }
+}
+
+/// Solver throughput in million cell-steps per second, stepping a stirred
+/// `BENCH_SIZE` grid until `seconds` have passed on `clock` (in seconds).
+pub fn measure(seconds: f64, mut clock: impl FnMut() -> f64) -> f32 {
+    let mut fluid = InteractiveFluid::new(BENCH_SIZE, BENCH_SIZE);
+    let center = BENCH_SIZE / 2;
+    fluid.add_force(center, center, Vec2::new(5.0, 2.0), 8.0);
+    fluid.add_dye(center, center, (1.0, 0.5, 0.25));
+    // One untimed step so first-touch allocation isn't measured
+    fluid.step();
+
+    let start = clock();
+    let mut steps = 0;
+    let mut elapsed = 0.0;
+    // The step cap keeps a stalled clock from spinning forever
+    while steps < 3 || (elapsed < seconds && steps < 300) {
+        fluid.step();
+        steps += 1;
+        elapsed = clock() - start;
+    }
+
+    let cells = (BENCH_SIZE * BENCH_SIZE * steps) as f64;
+    (cells / elapsed.max(1e-6) / 1e6) as f32
+}
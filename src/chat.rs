@@ -0,0 +1,82 @@
+//! Twitch chat adapter: reads a channel anonymously over IRC and turns chat
+//! messages such as `!dye red 0.3 0.7` into text for the ingestion layer.
+//!
+//! Channel point redemptions and YouTube live chat need authenticated
+//! websocket/HTTP APIs and are not covered; a bot that relays them as chat
+//! messages works with this adapter.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+const TWITCH_IRC: &str = "irc.chat.twitch.tv:6667";
+
+/// A `!` command message and the chat user who sent it. The text is
+/// unvalidated; pass it through an `ingest::Ingestor` before applying.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub user: String,
+    pub text: String,
+}
+
+/// Background reader for one Twitch channel. Messages are queued until
+/// `poll` drains them, typically once per frame.
+pub struct TwitchChat {
+    pub channel: String,
+    receiver: Receiver<ChatMessage>,
+}
+
+impl TwitchChat {
+    pub fn connect(channel: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let channel = channel.trim_start_matches('#').to_ascii_lowercase();
+        if channel.is_empty() {
+            return Err("channel name is empty".into());
+        }
+
+        let mut stream = TcpStream::connect(TWITCH_IRC)?;
+        // justinfan* nicks are Twitch's read-only anonymous login
+        write!(stream, "NICK justinfan{}\r\nJOIN #{}\r\n", rand::random::<u32>() % 100_000, channel)?;
+
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else { break };
+                if line.starts_with("PING") {
+                    let reply = line.replacen("PING", "PONG", 1);
+                    if write!(stream, "{}\r\n", reply).is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                // Ordinary chat is skipped; only `!` messages are forwarded
+                if let Some((user, text)) = parse_privmsg(&line) {
+                    let text = text.to_string();
+                    if sender.send(ChatMessage { user, text }).is_err() {
+                        break; // adapter dropped
+                    }
+                }
+            }
+        });
+
+        Ok(Self { channel, receiver })
+    }
+
+    /// Take every command message received since the last call.
+    pub fn poll(&self) -> Vec<ChatMessage> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+/// Split an IRC `PRIVMSG` line into sender and message text.
+pub fn parse_privmsg(line: &str) -> Option<(String, &str)> {
+    // :user!user@user.tmi.twitch.tv PRIVMSG #channel :message
+    let rest = line.strip_prefix(':')?;
+    let (prefix, rest) = rest.split_once(' ')?;
+    let rest = rest.strip_prefix("PRIVMSG ")?;
+    let (_, text) = rest.split_once(" :")?;
+    let user = prefix.split('!').next()?.to_string();
+    text.starts_with('!').then_some((user, text.trim_end()))
+}
@@ -0,0 +1,77 @@
+//! Checkpoints of long headless runs: the solver state, the elements acting
+//! on it and the frame counter, written every few frames so a crash or a
+//! killed job only loses the frames since the last one. Headless runs draw
+//! no random numbers, so there is no generator state to keep; a resumed
+//! run continues bit for bit as if it had never stopped.
+
+use crate::history::FluidSnapshot;
+use crate::scene::{PersistentElement, SceneParams};
+use crate::InteractiveFluid;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Format version written by `Checkpoint::save`.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    /// Steps run when it was written
+    pub frame: u64,
+    pub params: SceneParams,
+    pub fields: FluidSnapshot,
+    /// Elements as the run had them, after any script changed them
+    pub elements: Vec<PersistentElement>,
+}
+
+impl Checkpoint {
+    pub fn capture(frame: u64, fluid: &InteractiveFluid, elements: &[PersistentElement]) -> Self {
+        Self {
+            version: CHECKPOINT_VERSION,
+            frame,
+            params: SceneParams::capture(fluid),
+            fields: FluidSnapshot::capture(fluid),
+            elements: elements.to_vec(),
+        }
+    }
+
+    /// Put the saved settings and fields back into `fluid`, which must have
+    /// the checkpoint's grid size, and return the saved elements.
+    pub fn restore(&self, fluid: &mut InteractiveFluid) -> Result<Vec<PersistentElement>, Box<dyn std::error::Error>> {
+        self.fields.restore(fluid)?;
+        self.params.apply(fluid);
+        Ok(self.elements.clone())
+    }
+
+    /// Write the checkpoint next to `path` first and then move it over, so a
+    /// crash while writing leaves the previous checkpoint intact.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, serde_json::to_vec(self)?)?;
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let checkpoint: Checkpoint = serde_json::from_slice(&std::fs::read(path)?)?;
+        checkpoint.validate()?;
+        Ok(checkpoint)
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.version > CHECKPOINT_VERSION {
+            return Err(format!(
+                "checkpoint version {} is newer than supported version {}",
+                self.version, CHECKPOINT_VERSION
+            )
+            .into());
+        }
+        let (w, h) = (self.fields.width, self.fields.height);
+        let size = w * h;
+        let mut fields = self.fields.dye.iter().chain(&self.fields.velocity).chain([&self.fields.temperature]);
+        if fields.any(|field| field.len() != size) {
+            return Err(format!("checkpoint fields don't match its {}x{} grid", w, h).into());
+        }
+        Ok(())
+    }
+}
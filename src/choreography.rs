@@ -0,0 +1,160 @@
+//! Generative auto-play for unattended installations: a library of scripted
+//! routines (dye bursts, roaming attractors, force sweeps) chained in a
+//! seeded random order.
+
+use crate::commands::SimCommand;
+use crate::stress::apply_attractor;
+use crate::InteractiveFluid;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Bright colors routines pick from; dark dye would be invisible on black
+const PALETTE: [(f32, f32, f32); 6] = [
+    (1.0, 0.2, 0.3),
+    (0.1, 0.6, 1.0),
+    (1.0, 0.7, 0.1),
+    (0.3, 1.0, 0.5),
+    (0.8, 0.3, 1.0),
+    (0.1, 1.0, 0.9),
+];
+
+/// One scripted routine from the preset library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Routine {
+    /// Dye bursts at random points, each with a push in a random direction
+    Bloom,
+    /// An attractor drifting on a Lissajous path, fed by an orbiting dye source
+    Orbit,
+    /// A column of force and dye sweeping across the canvas
+    Sweep,
+    /// A wobbling upward jet from the bottom edge
+    Fountain,
+}
+
+impl Routine {
+    pub const ALL: [Routine; 4] = [Routine::Bloom, Routine::Orbit, Routine::Sweep, Routine::Fountain];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Routine::Bloom => "Bloom",
+            Routine::Orbit => "Orbit",
+            Routine::Sweep => "Sweep",
+            Routine::Fountain => "Fountain",
+        }
+    }
+
+    /// Length in seconds.
+    pub fn duration(self) -> f32 {
+        match self {
+            Routine::Bloom => 8.0,
+            Routine::Orbit => 12.0,
+            Routine::Sweep => 6.0,
+            Routine::Fountain => 10.0,
+        }
+    }
+}
+
+/// Plays routines back to back. Call `update` once per frame while idle and
+/// `reset` when someone interacts, so the next idle period starts fresh.
+pub struct Choreography {
+    /// Routines to choose from; the preset library by default
+    pub routines: Vec<Routine>,
+    rng: StdRng,
+    current: Routine,
+    color: (f32, f32, f32),
+    // Seconds into the current routine
+    elapsed: f32,
+    // Bursts fired so far in the current routine
+    beats: u32,
+}
+
+impl Choreography {
+    pub fn new(seed: u64) -> Self {
+        let mut choreography = Self {
+            routines: Routine::ALL.to_vec(),
+            rng: StdRng::seed_from_u64(seed),
+            current: Routine::Bloom,
+            color: PALETTE[0],
+            elapsed: 0.0,
+            beats: 0,
+        };
+        choreography.next_routine();
+        choreography
+    }
+
+    pub fn current(&self) -> Routine {
+        self.current
+    }
+
+    /// Seconds into the current routine.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Drop the routine in progress and pick a new one.
+    pub fn reset(&mut self) {
+        self.next_routine();
+    }
+
+    /// Advance by `dt` seconds and apply this frame's part of the routine.
+    pub fn update(&mut self, sim: &mut InteractiveFluid, dt: f32) {
+        self.elapsed += dt.max(0.0);
+        if self.elapsed >= self.current.duration() {
+            self.next_routine();
+        }
+
+        let t = self.elapsed;
+        let progress = t / self.current.duration();
+        let color = self.color;
+        match self.current {
+            Routine::Bloom => {
+                // Two bursts a second
+                let beat = (t * 2.0) as u32;
+                while self.beats <= beat {
+                    self.beats += 1;
+                    let (x, y) = (self.rng.gen_range(0.15..=0.85), self.rng.gen_range(0.15..=0.85));
+                    let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+                    SimCommand::Dye { x, y, color }.apply(sim);
+                    SimCommand::Force { x, y, dx: angle.cos(), dy: angle.sin() }.apply(sim);
+                }
+            }
+            Routine::Orbit => {
+                let (x, y) = (0.5 + 0.3 * (0.5 * t).sin(), 0.5 + 0.3 * (0.7 * t).sin());
+                let radius = 0.25 * sim.width.min(sim.height) as f32;
+                apply_attractor(sim, x, y, radius, 5.0);
+                let angle = 1.5 * t;
+                SimCommand::Dye {
+                    x: x + 0.15 * angle.cos(),
+                    y: y + 0.15 * angle.sin(),
+                    color: scale(color, 0.3),
+                }
+                .apply(sim);
+            }
+            Routine::Sweep => {
+                for row in 0..5 {
+                    let y = 0.2 + 0.15 * row as f32;
+                    SimCommand::Force { x: progress, y, dx: 0.3, dy: 0.0 }.apply(sim);
+                    SimCommand::Dye { x: progress, y, color: scale(color, 0.2) }.apply(sim);
+                }
+            }
+            Routine::Fountain => {
+                let x = 0.5 + 0.2 * (1.3 * t).sin();
+                SimCommand::Force { x, y: 0.95, dx: 0.1 * (2.1 * t).cos(), dy: -0.5 }.apply(sim);
+                SimCommand::Dye { x, y: 0.95, color: scale(color, 0.3) }.apply(sim);
+            }
+        }
+    }
+
+    fn next_routine(&mut self) {
+        if !self.routines.is_empty() {
+            self.current = self.routines[self.rng.gen_range(0..self.routines.len())];
+        }
+        self.color = PALETTE[self.rng.gen_range(0..PALETTE.len())];
+        self.elapsed = 0.0;
+        self.beats = 0;
+    }
+}
+
+fn scale(color: (f32, f32, f32), amount: f32) -> (f32, f32, f32) {
+    (color.0 * amount, color.1 * amount, color.2 * amount)
+}
@@ -0,0 +1,140 @@
+//! Text commands that drive a simulation, e.g. `!dye red 0.3 0.7` from chat.
+//! Positions are normalized to [0, 1] so commands don't depend on grid size.
+
+use crate::InteractiveFluid;
+use glam::Vec2;
+
+// Brush sizes in cells for remote commands
+const DYE_RADIUS: i32 = 3;
+// Dye added per cell, relative to the command color
+const DYE_AMOUNT: f32 = 0.5;
+const FORCE_RADIUS: f32 = 4.0;
+const HEAT_RADIUS: f32 = 4.0;
+// Force per unit of command direction
+const FORCE_SCALE: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimCommand {
+    Dye { x: f32, y: f32, color: (f32, f32, f32) },
+    Force { x: f32, y: f32, dx: f32, dy: f32 },
+    Heat { x: f32, y: f32, amount: f32 },
+    Clear,
+}
+
+impl SimCommand {
+    /// Parse one command. Accepted forms:
+    ///
+    /// - `!dye <color> <x> <y>` where color is a name or `#rrggbb`
+    /// - `!force <x> <y> <dx> <dy>`
+    /// - `!heat <x> <y> [amount]`
+    /// - `!clear`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut words = text.split_whitespace();
+        let name = words.next().ok_or("empty command")?;
+        let args: Vec<&str> = words.collect();
+
+        let number = |i: usize| -> Result<f32, String> {
+            let word = args.get(i).ok_or_else(|| format!("{}: missing argument {}", name, i + 1))?;
+            let value: f32 = word.parse().map_err(|_| format!("{}: '{}' is not a number", name, word))?;
+            if value.is_finite() {
+                Ok(value)
+            } else {
+                Err(format!("{}: '{}' is not finite", name, word))
+            }
+        };
+
+        match name.to_ascii_lowercase().as_str() {
+            "!dye" => {
+                let word = args.first().ok_or("!dye: missing color")?;
+                let color = parse_color(word).ok_or_else(|| format!("!dye: unknown color '{}'", word))?;
+                Ok(SimCommand::Dye {
+                    x: number(1)?,
+                    y: number(2)?,
+                    color,
+                })
+            }
+            "!force" => Ok(SimCommand::Force {
+                x: number(0)?,
+                y: number(1)?,
+                dx: number(2)?,
+                dy: number(3)?,
+            }),
+            "!heat" => Ok(SimCommand::Heat {
+                x: number(0)?,
+                y: number(1)?,
+                amount: if args.len() > 2 { number(2)? } else { 2.0 },
+            }),
+            "!clear" => Ok(SimCommand::Clear),
+            _ => Err(format!("unknown command '{}'", name)),
+        }
+    }
+
+    pub fn apply(&self, sim: &mut InteractiveFluid) {
+        match *self {
+            SimCommand::Dye { x, y, color } => {
+                let (cx, cy) = to_cell(sim, x, y);
+                for dy in -DYE_RADIUS..=DYE_RADIUS {
+                    for dx in -DYE_RADIUS..=DYE_RADIUS {
+                        if dx * dx + dy * dy <= DYE_RADIUS * DYE_RADIUS {
+                            let px = cx as i32 + dx;
+                            let py = cy as i32 + dy;
+                            if px >= 0 && py >= 0 {
+                                sim.add_dye(
+                                    px as usize,
+                                    py as usize,
+                                    (color.0 * DYE_AMOUNT, color.1 * DYE_AMOUNT, color.2 * DYE_AMOUNT),
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+            SimCommand::Force { x, y, dx, dy } => {
+                let (cx, cy) = to_cell(sim, x, y);
+                sim.add_force(cx, cy, Vec2::new(dx, dy) * FORCE_SCALE, FORCE_RADIUS);
+            }
+            SimCommand::Heat { x, y, amount } => {
+                let (cx, cy) = to_cell(sim, x, y);
+                sim.add_heat(cx, cy, amount, HEAT_RADIUS);
+            }
+            SimCommand::Clear => sim.clear(),
+        }
+    }
+}
+
+/// A named color or `#rrggbb` hex, as RGB in [0, 1].
+pub fn parse_color(word: &str) -> Option<(f32, f32, f32)> {
+    if let Some(hex) = word.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some((
+            channel(0)? as f32 / 255.0,
+            channel(2)? as f32 / 255.0,
+            channel(4)? as f32 / 255.0,
+        ));
+    }
+
+    let color = match word.to_ascii_lowercase().as_str() {
+        "red" => (1.0, 0.0, 0.0),
+        "green" => (0.0, 1.0, 0.0),
+        "blue" => (0.0, 0.0, 1.0),
+        "yellow" => (1.0, 1.0, 0.0),
+        "magenta" => (1.0, 0.0, 1.0),
+        "cyan" => (0.0, 1.0, 1.0),
+        "white" => (1.0, 1.0, 1.0),
+        "orange" => (1.0, 0.5, 0.0),
+        "purple" => (0.5, 0.0, 1.0),
+        "pink" => (1.0, 0.4, 0.7),
+        _ => return None,
+    };
+    Some(color)
+}
+
+// Normalized position to the nearest cell, kept off the boundary ring
+fn to_cell(sim: &InteractiveFluid, x: f32, y: f32) -> (usize, usize) {
+    let cx = (x.clamp(0.0, 1.0) * (sim.width - 1) as f32).round() as usize;
+    let cy = (y.clamp(0.0, 1.0) * (sim.height - 1) as f32).round() as usize;
+    (cx.clamp(1, sim.width - 2), cy.clamp(1, sim.height - 2))
+}
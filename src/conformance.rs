@@ -0,0 +1,436 @@
+//! Conformance scenarios every `FluidSimulation` backend must pass: fixed
+//! grids, inputs and step counts with expected ranges for unit-free physical
+//! metrics, so swapping the CPU solver for the GPU one (or a future FLIP or
+//! 3D backend) keeps the same qualitative behaviour.
+//!
+//! Backends use different timesteps and velocity units, so metrics are
+//! ratios, displacements in cells, or exact zeros. Inputs use normalized
+//! positions and seeded randomness, so a scenario is the same on any grid
+//! and any run.
+
+use crate::FluidSimulation;
+use glam::Vec2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Field readback the harness needs on top of `FluidSimulation`.
+pub trait Observe {
+    fn fields(&mut self) -> Result<Fields, Box<dyn std::error::Error>>;
+}
+
+/// Row-major snapshot of a backend's state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fields {
+    pub width: usize,
+    pub height: usize,
+    /// Dye summed over channels
+    pub dye: Vec<f32>,
+    pub velocity_x: Vec<f32>,
+    pub velocity_y: Vec<f32>,
+}
+
+impl Fields {
+    pub fn total_dye(&self) -> f32 {
+        self.dye.iter().sum()
+    }
+
+    pub fn peak_dye(&self) -> f32 {
+        self.dye.iter().fold(0.0, |a, &b| a.max(b))
+    }
+
+    /// Dye-weighted mean position in cells, or `None` without dye.
+    pub fn dye_centroid(&self) -> Option<Vec2> {
+        let total = self.total_dye();
+        if total <= 1e-12 {
+            return None;
+        }
+        let mut sum = Vec2::ZERO;
+        for (i, &d) in self.dye.iter().enumerate() {
+            sum += Vec2::new((i % self.width) as f32, (i / self.width) as f32) * d;
+        }
+        Some(sum / total)
+    }
+
+    pub fn max_speed(&self) -> f32 {
+        self.velocity_x
+            .iter()
+            .zip(&self.velocity_y)
+            .map(|(x, y)| (x * x + y * y).sqrt())
+            .fold(0.0, f32::max)
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        self.velocity_x.iter().zip(&self.velocity_y).map(|(x, y)| 0.5 * (x * x + y * y)).sum()
+    }
+
+    /// Mean |∇·v| over interior cells relative to the mean speed, so a
+    /// projected field scores near zero whatever its units.
+    pub fn relative_divergence(&self) -> f32 {
+        let (w, h) = (self.width, self.height);
+        let (mut divergence, mut speed) = (0.0f32, 0.0f32);
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                let idx = y * w + x;
+                divergence += (0.5
+                    * (self.velocity_x[idx + 1] - self.velocity_x[idx - 1] + self.velocity_y[idx + w]
+                        - self.velocity_y[idx - w]))
+                    .abs();
+                speed += (self.velocity_x[idx].powi(2) + self.velocity_y[idx].powi(2)).sqrt();
+            }
+        }
+        if speed <= 1e-12 { 0.0 } else { divergence / speed }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.dye.iter().chain(&self.velocity_x).chain(&self.velocity_y).all(|v| v.is_finite())
+    }
+}
+
+/// Something done to the fluid on every step of an event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    /// Dye at a normalized position
+    Dye { x: f32, y: f32, color: (f32, f32, f32) },
+    /// Force at a normalized position, in the backend's `add_force` units
+    Force { x: f32, y: f32, force: Vec2 },
+    /// `count` dye drops at interior positions drawn from `seed`
+    Scatter { seed: u64, count: usize, color: (f32, f32, f32) },
+}
+
+/// An input applied before each step in `steps`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub steps: std::ops::Range<usize>,
+    pub input: Input,
+}
+
+/// Unit-free quantities a scenario constrains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Total dye at the end
+    TotalDye,
+    /// Total dye at the end over total dye when the last input finished
+    DyeRetained,
+    /// Highest dye concentration at the end over its value when inputs finished
+    PeakRetained,
+    /// Movement of the dye centroid in cells since inputs finished
+    CentroidShiftX,
+    CentroidShiftY,
+    /// Largest speed at the end; only meaningful as an exact bound near zero
+    MaxSpeed,
+    /// Kinetic energy at the end over its value when inputs finished
+    EnergyRetained,
+    /// See `Fields::relative_divergence`
+    RelativeDivergence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Expectation {
+    pub metric: Metric,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Expectation {
+    pub fn new(metric: Metric, min: f32, max: f32) -> Self {
+        Self { metric, min, max }
+    }
+}
+
+/// A reproducible experiment: grid size, inputs, step count and the ranges
+/// every backend's metrics must fall in.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub width: usize,
+    pub height: usize,
+    pub steps: usize,
+    pub events: Vec<Event>,
+    pub expectations: Vec<Expectation>,
+}
+
+impl Scenario {
+    /// First step with no more inputs; the reference point for ratios.
+    pub fn inputs_end(&self) -> usize {
+        self.events.iter().map(|e| e.steps.end).max().unwrap_or(0).min(self.steps)
+    }
+
+    /// Apply the inputs scheduled for `step`, before it is taken.
+    pub fn apply_inputs(&self, sim: &mut impl FluidSimulation, step: usize) {
+        let (w, h) = (sim.width(), sim.height());
+        let cell = |x: f32, y: f32| {
+            (
+                ((x * w as f32) as usize).clamp(1, w - 2),
+                ((y * h as f32) as usize).clamp(1, h - 2),
+            )
+        };
+        for event in self.events.iter().filter(|e| e.steps.contains(&step)) {
+            match event.input {
+                Input::Dye { x, y, color } => {
+                    let (x, y) = cell(x, y);
+                    sim.add_dye(x, y, color);
+                }
+                Input::Force { x, y, force } => {
+                    let (x, y) = cell(x, y);
+                    sim.add_force(x, y, force);
+                }
+                Input::Scatter { seed, count, color } => {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    for _ in 0..count {
+                        let (x, y) = cell(rng.gen_range(0.1..0.9), rng.gen_range(0.1..0.9));
+                        sim.add_dye(x, y, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub expectation: Expectation,
+    pub value: f32,
+}
+
+impl Measurement {
+    pub fn passed(&self) -> bool {
+        self.value >= self.expectation.min && self.value <= self.expectation.max
+    }
+}
+
+/// Outcome of one scenario on one backend.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub scenario: &'static str,
+    pub measurements: Vec<Measurement>,
+    /// Every field value stayed finite
+    pub finite: bool,
+    /// Final state, for reproducibility checks
+    pub fields: Fields,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.finite && self.measurements.iter().all(Measurement::passed)
+    }
+
+    /// One line per violated expectation.
+    pub fn failures(&self) -> Vec<String> {
+        let mut failures = Vec::new();
+        if !self.finite {
+            failures.push(format!("{}: non-finite values", self.scenario));
+        }
+        for m in self.measurements.iter().filter(|m| !m.passed()) {
+            failures.push(format!(
+                "{}: {:?} = {} outside [{}, {}]",
+                self.scenario, m.expectation.metric, m.value, m.expectation.min, m.expectation.max
+            ));
+        }
+        failures
+    }
+}
+
+/// The conformance set. Ranges are loose enough for different integrators
+/// and timesteps but tight enough to catch lost mass, missing projection,
+/// wrong force direction or energy blow-up.
+pub fn scenarios() -> Vec<Scenario> {
+    use Metric::*;
+    vec![
+        Scenario {
+            name: "still",
+            description: "An empty tank with no input stays exactly at rest",
+            width: 32,
+            height: 32,
+            steps: 60,
+            events: vec![],
+            expectations: vec![
+                Expectation::new(TotalDye, 0.0, 0.0),
+                Expectation::new(MaxSpeed, 0.0, 0.0),
+            ],
+        },
+        Scenario {
+            name: "dye_at_rest",
+            description: "A dye blob in still fluid keeps its mass and position and never sharpens",
+            width: 48,
+            height: 48,
+            steps: 120,
+            events: vec![Event {
+                steps: 0..3,
+                input: Input::Dye { x: 0.5, y: 0.5, color: (1.0, 0.5, 0.25) },
+            }],
+            expectations: vec![
+                Expectation::new(DyeRetained, 0.95, 1.05),
+                Expectation::new(PeakRetained, 0.0, 1.001),
+                Expectation::new(CentroidShiftX, -0.5, 0.5),
+                Expectation::new(CentroidShiftY, -0.5, 0.5),
+                Expectation::new(MaxSpeed, 0.0, 0.0),
+            ],
+        },
+        Scenario {
+            name: "jet",
+            description: "A rightward jet carries dye downstream without losing it",
+            width: 64,
+            height: 32,
+            steps: 150,
+            events: vec![
+                Event { steps: 0..10, input: Input::Dye { x: 0.25, y: 0.5, color: (1.0, 1.0, 1.0) } },
+                Event { steps: 0..10, input: Input::Force { x: 0.25, y: 0.5, force: Vec2::new(20.0, 0.0) } },
+            ],
+            expectations: vec![
+                Expectation::new(DyeRetained, 0.9, 1.1),
+                Expectation::new(CentroidShiftX, 1.0, 40.0),
+                Expectation::new(CentroidShiftY, -2.0, 2.0),
+                Expectation::new(EnergyRetained, 0.0, 1.0),
+            ],
+        },
+        Scenario {
+            name: "opposed_jets",
+            description: "Mirror-image jets colliding in the middle keep the dye centred",
+            width: 64,
+            height: 64,
+            steps: 120,
+            events: vec![
+                Event { steps: 0..10, input: Input::Dye { x: 0.25, y: 0.5, color: (1.0, 0.0, 0.0) } },
+                Event { steps: 0..10, input: Input::Dye { x: 0.75, y: 0.5, color: (0.0, 0.0, 1.0) } },
+                Event { steps: 0..10, input: Input::Force { x: 0.25, y: 0.5, force: Vec2::new(20.0, 0.0) } },
+                Event { steps: 0..10, input: Input::Force { x: 0.75, y: 0.5, force: Vec2::new(-20.0, 0.0) } },
+            ],
+            expectations: vec![
+                Expectation::new(DyeRetained, 0.9, 1.1),
+                Expectation::new(CentroidShiftX, -1.0, 1.0),
+                Expectation::new(CentroidShiftY, -1.0, 1.0),
+            ],
+        },
+        Scenario {
+            name: "decay",
+            description: "A single push dies out under viscosity and projection",
+            width: 48,
+            height: 48,
+            steps: 200,
+            events: vec![Event {
+                steps: 0..1,
+                input: Input::Force { x: 0.5, y: 0.5, force: Vec2::new(20.0, 10.0) },
+            }],
+            expectations: vec![
+                Expectation::new(EnergyRetained, 0.0, 0.5),
+                Expectation::new(RelativeDivergence, 0.0, 0.5),
+            ],
+        },
+        Scenario {
+            name: "scatter",
+            description: "Seeded drops stirred by a push keep their mass and stay bounded",
+            width: 48,
+            height: 48,
+            steps: 100,
+            events: vec![
+                Event { steps: 0..1, input: Input::Scatter { seed: 7, count: 24, color: (0.5, 0.3, 0.2) } },
+                Event { steps: 0..5, input: Input::Force { x: 0.3, y: 0.6, force: Vec2::new(10.0, -10.0) } },
+            ],
+            expectations: vec![
+                Expectation::new(DyeRetained, 0.9, 1.1),
+                Expectation::new(EnergyRetained, 0.0, 1.0),
+                Expectation::new(RelativeDivergence, 0.0, 0.5),
+            ],
+        },
+    ]
+}
+
+/// Run `scenario` on a fresh backend of the scenario's size.
+pub fn run<S: FluidSimulation + Observe>(
+    sim: &mut S,
+    scenario: &Scenario,
+) -> Result<Report, Box<dyn std::error::Error>> {
+    if sim.width() != scenario.width || sim.height() != scenario.height {
+        return Err(format!(
+            "{} needs a {}x{} grid, got {}x{}",
+            scenario.name,
+            scenario.width,
+            scenario.height,
+            sim.width(),
+            sim.height()
+        )
+        .into());
+    }
+
+    let inputs_end = scenario.inputs_end();
+    let mut reference = None;
+    for step in 0..scenario.steps {
+        if step == inputs_end {
+            reference = Some(sim.fields()?);
+        }
+        scenario.apply_inputs(sim, step);
+        sim.step();
+    }
+    let last = sim.fields()?;
+    let reference = match reference {
+        Some(fields) => fields,
+        None => last.clone(),
+    };
+
+    let ratio = |end: f32, start: f32| if start.abs() <= 1e-12 { 0.0 } else { end / start };
+    let shift = match (reference.dye_centroid(), last.dye_centroid()) {
+        (Some(start), Some(end)) => end - start,
+        _ => Vec2::ZERO,
+    };
+    let measurements = scenario
+        .expectations
+        .iter()
+        .map(|&expectation| {
+            let value = match expectation.metric {
+                Metric::TotalDye => last.total_dye(),
+                Metric::DyeRetained => ratio(last.total_dye(), reference.total_dye()),
+                Metric::PeakRetained => ratio(last.peak_dye(), reference.peak_dye()),
+                Metric::CentroidShiftX => shift.x,
+                Metric::CentroidShiftY => shift.y,
+                Metric::MaxSpeed => last.max_speed(),
+                Metric::EnergyRetained => ratio(last.kinetic_energy(), reference.kinetic_energy()),
+                Metric::RelativeDivergence => last.relative_divergence(),
+            };
+            Measurement { expectation, value }
+        })
+        .collect();
+
+    Ok(Report {
+        scenario: scenario.name,
+        measurements,
+        finite: last.is_finite(),
+        fields: last,
+    })
+}
+
+/// Run every scenario on backends built by `make(width, height)`.
+pub fn run_all<S: FluidSimulation + Observe>(
+    mut make: impl FnMut(usize, usize) -> Result<S, Box<dyn std::error::Error>>,
+) -> Result<Vec<Report>, Box<dyn std::error::Error>> {
+    scenarios()
+        .iter()
+        .map(|scenario| run(&mut make(scenario.width, scenario.height)?, scenario))
+        .collect()
+}
+
+impl Observe for crate::InteractiveFluid {
+    fn fields(&mut self) -> Result<Fields, Box<dyn std::error::Error>> {
+        Ok(Fields {
+            width: self.width,
+            height: self.height,
+            dye: self.dye_r.iter().zip(&self.dye_g).zip(&self.dye_b).map(|((r, g), b)| r + g + b).collect(),
+            velocity_x: self.velocity_x.clone(),
+            velocity_y: self.velocity_y.clone(),
+        })
+    }
+}
+
+#[cfg(feature = "f16-dye")]
+impl Observe for crate::HalfDyeFluid {
+    fn fields(&mut self) -> Result<Fields, Box<dyn std::error::Error>> {
+        let [r, g, b] = self.dye_to_f32();
+        Ok(Fields {
+            width: self.fluid.width,
+            height: self.fluid.height,
+            dye: r.iter().zip(&g).zip(&b).map(|((r, g), b)| r + g + b).collect(),
+            velocity_x: self.fluid.velocity_x.clone(),
+            velocity_y: self.fluid.velocity_y.clone(),
+        })
+    }
+}
@@ -0,0 +1,107 @@
+//! Containers: the fluid domain as a circle, ring, rounded rectangle or
+//! polygon instead of the whole grid. Cells outside the shape become
+//! obstacles, so the flow and dye stay inside as they do around a mask.
+//! Shapes are in shares of the grid, so they survive a change of resolution.
+
+use crate::mask::Mask;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape")]
+pub enum Container {
+    /// `center` in shares of the grid's width and height, `radius` in
+    /// shares of its shorter side, so the circle stays round
+    Circle { center: (f32, f32), radius: f32 },
+    /// Fluid between two circles around `center`, radii as for `Circle`
+    Ring { center: (f32, f32), inner: f32, outer: f32 },
+    /// Rectangle from `min` to `max` in shares of the grid, with corners
+    /// rounded by `corner`, a share of the shorter side
+    RoundedRect { min: (f32, f32), max: (f32, f32), corner: f32 },
+    /// Vertices in shares of the grid, in order; the inside follows the
+    /// even-odd rule, so a crossing outline still makes sense
+    Polygon { points: Vec<(f32, f32)> },
+}
+
+impl Container {
+    /// Presets offered in the app, with their names.
+    pub fn presets() -> Vec<(&'static str, Container)> {
+        vec![
+            ("Petri dish", Self::petri_dish()),
+            ("Ring", Self::ring()),
+            ("Letterbox", Self::letterbox()),
+            ("Rounded", Self::RoundedRect { min: (0.04, 0.04), max: (0.96, 0.96), corner: 0.2 }),
+            ("Hexagon", Self::regular_polygon(6, 0.47)),
+        ]
+    }
+
+    /// A circle filling the grid's shorter side, less a small rim.
+    pub fn petri_dish() -> Self {
+        Self::Circle { center: (0.5, 0.5), radius: 0.47 }
+    }
+
+    pub fn ring() -> Self {
+        Self::Ring { center: (0.5, 0.5), inner: 0.2, outer: 0.47 }
+    }
+
+    /// A wide band across the middle half of the grid.
+    pub fn letterbox() -> Self {
+        Self::RoundedRect { min: (0.0, 0.25), max: (1.0, 0.75), corner: 0.0 }
+    }
+
+    /// A polygon of `sides` corners `radius` from the center, the first
+    /// pointing up. Like all polygons it stretches with the grid, so it is
+    /// only regular on a square one.
+    pub fn regular_polygon(sides: usize, radius: f32) -> Self {
+        let points = (0..sides)
+            .map(|i| {
+                let angle = std::f32::consts::TAU * i as f32 / sides as f32 - std::f32::consts::FRAC_PI_2;
+                (0.5 + radius * angle.cos(), 0.5 + radius * angle.sin())
+            })
+            .collect();
+        Self::Polygon { points }
+    }
+
+    /// Whether the point `(x, y)`, in grid cells, is inside on a `width` x
+    /// `height` grid.
+    pub fn contains(&self, x: f32, y: f32, width: usize, height: usize) -> bool {
+        let (w, h) = (width as f32, height as f32);
+        let side = w.min(h);
+        // Distance from a center in shares of the shorter side
+        let from = |center: (f32, f32)| ((x - center.0 * w) / side).hypot((y - center.1 * h) / side);
+        match self {
+            Self::Circle { center, radius } => from(*center) <= *radius,
+            Self::Ring { center, inner, outer } => (*inner..=*outer).contains(&from(*center)),
+            Self::RoundedRect { min, max, corner } => {
+                let (x0, x1, y0, y1) = (min.0 * w, max.0 * w, min.1 * h, max.1 * h);
+                let corner = (corner * side).min((x1 - x0) / 2.0).min((y1 - y0) / 2.0).max(0.0);
+                // Distance outside the rectangle shrunk by the corner radius
+                let dx = (x0 + corner - x).max(x - (x1 - corner)).max(0.0);
+                let dy = (y0 + corner - y).max(y - (y1 - corner)).max(0.0);
+                (x0..=x1).contains(&x) && (y0..=y1).contains(&y) && dx.hypot(dy) <= corner
+            }
+            Self::Polygon { points } => {
+                let mut inside = false;
+                for (i, a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+                    let (ax, ay, bx, by) = (a.0 * w, a.1 * h, b.0 * w, b.1 * h);
+                    if (ay > y) != (by > y) && x < ax + (y - ay) / (by - ay) * (bx - ax) {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+
+    /// Cells outside the container, judged at their centers, as an
+    /// obstacle mask for a `width` x `height` grid.
+    pub fn mask(&self, width: usize, height: usize) -> Mask {
+        let mut mask = Mask::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                mask.cells[y * width + x] = !self.contains(x as f32 + 0.5, y as f32 + 0.5, width, height);
+            }
+        }
+        mask
+    }
+}
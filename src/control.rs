@@ -0,0 +1,288 @@
+//! Remote control for live sets: OSC messages from TouchOSC, Ableton (via
+//! Max for Live or Connection Kit) and similar tools, and MIDI CC with the
+//! `midi` feature, bound to a few named app parameters.
+//!
+//! Incoming values are taken as normalized `0..=1`, as faders send them,
+//! and scaled to each parameter's range.
+
+use std::net::UdpSocket;
+use std::ops::RangeInclusive;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+pub const DEFAULT_OSC_PORT: u16 = 9000;
+
+/// Ranges of the app's sliders, so a full fader matches a full slider.
+pub const FORCE_INTENSITY: RangeInclusive<f32> = 0.01..=3.0;
+pub const ATTRACTOR_STRENGTH: RangeInclusive<f32> = 0.1..=100.0;
+
+/// A parameter that can be bound to an OSC address or a MIDI CC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlTarget {
+    /// Three values set red, green and blue; one value picks a hue
+    DyeColor,
+    ForceIntensity,
+    AttractorStrength,
+    /// Above half pauses, below resumes
+    Paused,
+    /// Clears on a press (above half); the release is ignored
+    Clear,
+}
+
+impl ControlTarget {
+    pub const ALL: [ControlTarget; 5] = [
+        ControlTarget::DyeColor,
+        ControlTarget::ForceIntensity,
+        ControlTarget::AttractorStrength,
+        ControlTarget::Paused,
+        ControlTarget::Clear,
+    ];
+
+    pub fn osc_address(self) -> &'static str {
+        match self {
+            ControlTarget::DyeColor => "/itsliquid/dye/color",
+            ControlTarget::ForceIntensity => "/itsliquid/force/intensity",
+            ControlTarget::AttractorStrength => "/itsliquid/attractor/strength",
+            ControlTarget::Paused => "/itsliquid/paused",
+            ControlTarget::Clear => "/itsliquid/clear",
+        }
+    }
+
+    pub fn from_osc_address(address: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|target| target.osc_address() == address)
+    }
+
+    /// Default MIDI CC numbers, 20 to 24 on any channel: undefined in the
+    /// MIDI spec, so they don't clash with mod wheels or sustain pedals.
+    pub fn default_cc(self) -> u8 {
+        match self {
+            ControlTarget::DyeColor => 20,
+            ControlTarget::ForceIntensity => 21,
+            ControlTarget::AttractorStrength => 22,
+            ControlTarget::Paused => 23,
+            ControlTarget::Clear => 24,
+        }
+    }
+
+    pub fn from_cc(cc: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|target| target.default_cc() == cc)
+    }
+
+    /// The change normalized `values` ask for, or `None` if there are too
+    /// few of them or a button was released.
+    pub fn control(self, values: &[f32]) -> Option<Control> {
+        let unit = |v: f32| v.clamp(0.0, 1.0);
+        let scale = |range: RangeInclusive<f32>, v: f32| range.start() + unit(v) * (range.end() - range.start());
+        match (self, values) {
+            (ControlTarget::DyeColor, [r, g, b, ..]) => Some(Control::DyeColor((unit(*r), unit(*g), unit(*b)))),
+            (ControlTarget::DyeColor, [hue, ..]) => Some(Control::DyeColor(hue_to_rgb(unit(*hue)))),
+            (ControlTarget::ForceIntensity, [v, ..]) => Some(Control::ForceIntensity(scale(FORCE_INTENSITY, *v))),
+            (ControlTarget::AttractorStrength, [v, ..]) => Some(Control::AttractorStrength(scale(ATTRACTOR_STRENGTH, *v))),
+            (ControlTarget::Paused, [v, ..]) => Some(Control::Paused(*v > 0.5)),
+            // A bare `/itsliquid/clear` with no arguments also clears
+            (ControlTarget::Clear, []) => Some(Control::Clear),
+            (ControlTarget::Clear, [v, ..]) => (*v > 0.5).then_some(Control::Clear),
+            _ => None,
+        }
+    }
+}
+
+/// A parameter change for the app to apply.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Control {
+    DyeColor((f32, f32, f32)),
+    ForceIntensity(f32),
+    AttractorStrength(f32),
+    Paused(bool),
+    Clear,
+}
+
+// Fully saturated color for a hue in 0..=1
+fn hue_to_rgb(hue: f32) -> (f32, f32, f32) {
+    let h = hue.fract() * 6.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    }
+}
+
+/// One OSC message with its numeric arguments; booleans read as 0 or 1
+/// and strings are dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<f32>,
+}
+
+/// Every message in an OSC packet, unpacking bundles. Malformed packets
+/// yield what could be read before the fault.
+pub fn parse_osc(packet: &[u8]) -> Vec<OscMessage> {
+    let mut messages = Vec::new();
+    parse_osc_into(packet, &mut messages);
+    messages
+}
+
+fn parse_osc_into(packet: &[u8], messages: &mut Vec<OscMessage>) {
+    if let Some(mut rest) = packet.strip_prefix(b"#bundle\0") {
+        // 8-byte time tag, then size-prefixed elements; timing is ignored
+        rest = rest.get(8..).unwrap_or_default();
+        while let Some(size) = rest.get(..4) {
+            let size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize;
+            let Some(element) = rest.get(4..4 + size) else { return };
+            parse_osc_into(element, messages);
+            rest = &rest[4 + size..];
+        }
+        return;
+    }
+
+    let mut reader = OscReader { data: packet };
+    let Some(address) = reader.string().filter(|a| a.starts_with('/')) else { return };
+    let mut args = Vec::new();
+    // Old senders may leave the type tags out entirely
+    let tags = reader.string().unwrap_or_default();
+    for tag in tags.strip_prefix(',').unwrap_or_default().chars() {
+        let value = match tag {
+            'f' => reader.take::<4>().map(f32::from_be_bytes),
+            'i' => reader.take::<4>().map(|b| i32::from_be_bytes(b) as f32),
+            'd' => reader.take::<8>().map(|b| f64::from_be_bytes(b) as f32),
+            'h' => reader.take::<8>().map(|b| i64::from_be_bytes(b) as f32),
+            'T' => Some(1.0),
+            'F' => Some(0.0),
+            's' | 'S' => {
+                if reader.string().is_none() {
+                    break;
+                }
+                continue;
+            }
+            'N' | 'I' => continue,
+            // Blobs, MIDI and other types end the readable arguments
+            _ => break,
+        };
+        match value {
+            Some(value) => args.push(value),
+            None => break,
+        }
+    }
+    messages.push(OscMessage { address, args });
+}
+
+struct OscReader<'a> {
+    data: &'a [u8],
+}
+
+impl OscReader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.data.get(..N)?.try_into().ok()?;
+        self.data = &self.data[N..];
+        Some(bytes)
+    }
+
+    // Null-terminated, padded to a multiple of four bytes
+    fn string(&mut self) -> Option<String> {
+        let end = self.data.iter().position(|&b| b == 0)?;
+        let text = std::str::from_utf8(&self.data[..end]).ok()?.to_string();
+        let padded = (end + 4) & !3;
+        self.data = self.data.get(padded..).unwrap_or_default();
+        Some(text)
+    }
+}
+
+/// Split a raw MIDI message into channel (0-15), controller number and a
+/// normalized value, if it is a control change.
+pub fn parse_midi_cc(message: &[u8]) -> Option<(u8, u8, f32)> {
+    match message {
+        [status, cc, value, ..] if status & 0xf0 == 0xb0 => Some((status & 0x0f, *cc & 0x7f, (*value & 0x7f) as f32 / 127.0)),
+        _ => None,
+    }
+}
+
+/// Background listener on a UDP port. Bound messages are queued until
+/// `poll` drains them, typically once per frame.
+pub struct OscListener {
+    pub port: u16,
+    receiver: Receiver<Control>,
+}
+
+impl OscListener {
+    pub fn bind(port: u16) -> Result<Self, Box<dyn std::error::Error>> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        let port = socket.local_addr()?.port();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut buffer = [0u8; 4096];
+            while let Ok(len) = socket.recv(&mut buffer) {
+                let controls = parse_osc(&buffer[..len]).into_iter().filter_map(|message| {
+                    ControlTarget::from_osc_address(&message.address)?.control(&message.args)
+                });
+                for control in controls {
+                    if sender.send(control).is_err() {
+                        return; // listener dropped
+                    }
+                }
+            }
+        });
+
+        Ok(Self { port, receiver })
+    }
+
+    /// Take every change received since the last call.
+    pub fn poll(&self) -> Vec<Control> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+#[cfg(feature = "midi")]
+pub use midi::MidiListener;
+
+#[cfg(feature = "midi")]
+mod midi {
+    use super::{Control, ControlTarget, parse_midi_cc};
+    use std::sync::mpsc::{self, Receiver};
+
+    /// Control changes from the first MIDI input port, on the CC numbers
+    /// in `ControlTarget::default_cc`.
+    pub struct MidiListener {
+        pub port_name: String,
+        receiver: Receiver<Control>,
+        // Input stops when the connection is dropped
+        _connection: midir::MidiInputConnection<()>,
+    }
+
+    impl MidiListener {
+        pub fn open_first() -> Result<Self, Box<dyn std::error::Error>> {
+            let input = midir::MidiInput::new("itsliquid")?;
+            let port = input.ports().into_iter().next().ok_or("no MIDI input ports")?;
+            let port_name = input.port_name(&port)?;
+            let (sender, receiver) = mpsc::channel();
+            let connection = input
+                .connect(
+                    &port,
+                    "itsliquid-control",
+                    move |_, message, _| {
+                        let Some((_, cc, value)) = parse_midi_cc(message) else { return };
+                        if let Some(control) = ControlTarget::from_cc(cc).and_then(|target| target.control(&[value])) {
+                            let _ = sender.send(control);
+                        }
+                    },
+                    (),
+                )
+                .map_err(|e| e.to_string())?;
+            Ok(Self {
+                port_name,
+                receiver,
+                _connection: connection,
+            })
+        }
+
+        /// Take every change received since the last call.
+        pub fn poll(&self) -> Vec<Control> {
+            self.receiver.try_iter().collect()
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use crate::FluidSolver;
-use crate::export::ImageExporter;
-use crate::render::Renderer;
+use crate::ConfigurableSimulation;
+use crate::export::{FluidData, ImageExporter};
+use crate::solver::{Scheme, Solver};
+use crate::render::{Colormap, Renderer};
 use eframe::egui;
 
 #[derive(PartialEq)]
@@ -13,7 +14,7 @@ pub enum FlowPattern {
 }
 
 pub struct DesktopApp {
-    simulation: FluidSolver,
+    simulation: Solver,
     exporter: ImageExporter,
     paused: bool,
     show_velocity: bool,
@@ -22,12 +23,12 @@ pub struct DesktopApp {
     flow_pattern: FlowPattern,
     flow_strength: f32,
     projection_angle: f32, // Angle in degrees for fluid projection
-    diffusion_strength: f32,
+    colormap: Colormap,
 }
 
 impl DesktopApp {
     pub fn new(width: usize, height: usize) -> Self {
-        let mut simulation = FluidSolver::new(width, height);
+        let mut simulation = Solver::with_scheme(Scheme::Buoyant, width, height);
 
         // Add some initial fluid
         for i in 0..10 {
@@ -45,7 +46,7 @@ impl DesktopApp {
             flow_pattern: FlowPattern::Circular,
             flow_strength: 2.0,
             projection_angle: 0.0, // Default: straight up
-            diffusion_strength: 0.0001,
+            colormap: Colormap::default(),
         }
     }
 }
@@ -62,8 +63,8 @@ impl eframe::App for DesktopApp {
 
                 if ui.button("Add Fluid").clicked() {
                     // Add fluid at a random position with configured flow
-                    let x = rand::random::<usize>() % self.simulation.width;
-                    let y = rand::random::<usize>() % self.simulation.height;
+                    let x = rand::random::<usize>() % self.simulation.width();
+                    let y = rand::random::<usize>() % self.simulation.height();
                     self.simulation.add_density(x, y, 1.0);
                     self.add_velocity_pattern(x, y);
                 }
@@ -80,15 +81,35 @@ impl eframe::App for DesktopApp {
                 }
 
                 ui.checkbox(&mut self.show_velocity, "Show Velocity");
+
+                let previous = self.colormap.clone();
+                egui::ComboBox::from_label("Colormap")
+                    .selected_text(self.colormap.name())
+                    .show_ui(ui, |ui| {
+                        for colormap in Colormap::builtin() {
+                            let name = colormap.name();
+                            ui.selectable_value(&mut self.colormap, colormap, name);
+                        }
+                    });
+                if self.colormap != previous {
+                    // Keep exported PNGs consistent with the canvas
+                    self.exporter.set_colormap(self.colormap.clone());
+                }
             });
 
             ui.horizontal(|ui| {
                 ui.add(egui::Slider::new(&mut self.cell_size, 1.0..=10.0).text("Cell Size"));
                 ui.add(egui::Slider::new(&mut self.flow_strength, 0.1..=5.0).text("Flow Strength"));
-                ui.add(
-                    egui::Slider::new(&mut self.diffusion_strength, 0.00000001..=0.00001)
-                        .text("Diffusion"),
-                );
+            });
+
+            // One slider per parameter the solver supports
+            ui.horizontal(|ui| {
+                for (param, mut value) in self.simulation.params() {
+                    let slider = egui::Slider::new(&mut value, param.range()).logarithmic(true).text(param.name());
+                    if ui.add(slider).changed() {
+                        let _ = self.simulation.set_param(param, value);
+                    }
+                }
             });
 
             ui.horizontal(|ui| {
@@ -121,8 +142,8 @@ impl eframe::App for DesktopApp {
             ui.separator();
 
             // Calculate canvas size based on simulation dimensions and cell size
-            let canvas_width = self.simulation.width as f32 * self.cell_size;
-            let canvas_height = self.simulation.height as f32 * self.cell_size;
+            let canvas_width = self.simulation.width() as f32 * self.cell_size;
+            let canvas_height = self.simulation.height() as f32 * self.cell_size;
 
             // Simulation canvas
             let (rect, response) = ui.allocate_exact_size(
@@ -136,12 +157,12 @@ impl eframe::App for DesktopApp {
                     let x = ((pos.x - rect.left()) / self.cell_size) as usize;
                     let y = ((pos.y - rect.top()) / self.cell_size) as usize;
 
-                    if x < self.simulation.width && y < self.simulation.height {
+                    if x < self.simulation.width() && y < self.simulation.height() {
                         // Add fluid with natural flow (upward buoyancy)
                         let amount = 1.0;
                         // Create circular flow pattern from mouse position
-                        let dx = x as f32 - (self.simulation.width as f32 / 2.0);
-                        let dy = y as f32 - (self.simulation.height as f32 / 2.0);
+                        let dx = x as f32 - (self.simulation.width() as f32 / 2.0);
+                        let dy = y as f32 - (self.simulation.height() as f32 / 2.0);
                         let vel_x = -dy * 0.01; // Rotational flow
                         let vel_y = dx * 0.01; // Rotational flow
 
@@ -155,19 +176,18 @@ impl eframe::App for DesktopApp {
             // Render simulation
             let painter = ui.painter();
 
-            for y in 0..self.simulation.height {
-                for x in 0..self.simulation.width {
-                    let idx = y * self.simulation.width + x;
-                    let density = self.simulation.density[idx].min(1.0).max(0.0);
+            for y in 0..self.simulation.height() {
+                for x in 0..self.simulation.width() {
+                    let idx = y * self.simulation.width() + x;
+                    let density = self.simulation.density()[idx].clamp(0.0, 1.0);
 
                     let color = if self.show_velocity {
-                        let vel_x = self.simulation.velocity_x[idx].abs().min(1.0);
-                        let vel_y = self.simulation.velocity_y[idx].abs().min(1.0);
+                        let vel_x = self.simulation.velocity_x()[idx].abs().min(1.0);
+                        let vel_y = self.simulation.velocity_y()[idx].abs().min(1.0);
                         egui::Color32::from_rgb((vel_x * 255.0) as u8, (vel_y * 255.0) as u8, 128)
                     } else {
-                        // Blue to white gradient based on density
-                        let intensity = (density * 255.0) as u8;
-                        egui::Color32::from_rgb(intensity, intensity, 255)
+                        let [r, g, b] = self.colormap.map(density);
+                        egui::Color32::from_rgb(r, g, b)
                     };
 
                     let rect = egui::Rect::from_min_size(
@@ -183,7 +203,7 @@ impl eframe::App for DesktopApp {
             }
 
             // Draw grid lines for better visibility
-            for x in 0..=self.simulation.width {
+            for x in 0..=self.simulation.width() {
                 let line_x = rect.left() + x as f32 * self.cell_size;
                 painter.line_segment(
                     [
@@ -194,7 +214,7 @@ impl eframe::App for DesktopApp {
                 );
             }
 
-            for y in 0..=self.simulation.height {
+            for y in 0..=self.simulation.height() {
                 let line_y = rect.top() + y as f32 * self.cell_size;
                 painter.line_segment(
                     [
@@ -237,8 +257,8 @@ impl DesktopApp {
             }
             FlowPattern::Circular => {
                 // Create vortex around the center
-                let dx = x as f32 - (self.simulation.width as f32 / 2.0);
-                let dy = y as f32 - (self.simulation.height as f32 / 2.0);
+                let dx = x as f32 - (self.simulation.width() as f32 / 2.0);
+                let dy = y as f32 - (self.simulation.height() as f32 / 2.0);
                 let vel_x = -dy * strength * 0.01;
                 let vel_y = dx * strength * 0.01;
                 self.simulation
@@ -250,8 +270,8 @@ impl DesktopApp {
             }
             FlowPattern::Radial => {
                 // Radial flow from center
-                let dx = x as f32 - (self.simulation.width as f32 / 2.0);
-                let dy = y as f32 - (self.simulation.height as f32 / 2.0);
+                let dx = x as f32 - (self.simulation.width() as f32 / 2.0);
+                let dy = y as f32 - (self.simulation.height() as f32 / 2.0);
                 let dist = (dx * dx + dy * dy).sqrt().max(0.1);
                 let vel_x = (dx / dist) * strength;
                 let vel_y = (dy / dist) * strength;
@@ -1,7 +1,135 @@
 //! GPU-accelerated interactive fluid simulation
 
-use crate::{FluidSimulation, gpu_functional::FunctionalGPUFluid};
+use crate::brush::{Brush, BrushStroke};
+use crate::{ConfigurableSimulation, FluidSimulation, Param, gpu_functional::FunctionalGPUFluid};
+use crate::particles::ParticleSystem;
+use crate::desktop_interactive::{hud_panel, step_pace_ui};
+use crate::timing::{PerfStats, StageTimes, StepClock, format_bytes, now_seconds};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::particles::VelocityGrid;
 use eframe::egui;
+use eframe::egui_wgpu;
+
+// Fullscreen quad over the callback viewport that reads the dye texture
+// texel for texel, clamped the same way as the readback path
+const DYE_SHADER: &str = r"
+    @group(0) @binding(0)
+    var dye: texture_2d<f32>;
+
+    struct VertexOut {
+        @builtin(position) position: vec4<f32>,
+        @location(0) uv: vec2<f32>,
+    }
+
+    @vertex
+    fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+        let uv = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+        var out: VertexOut;
+        out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+        out.uv = uv;
+        return out;
+    }
+
+    @fragment
+    fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+        let size = textureDimensions(dye);
+        let texel = min(vec2<u32>(in.uv * vec2<f32>(size)), size - vec2<u32>(1u, 1u));
+        let color = textureLoad(dye, texel, 0);
+        return vec4<f32>(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+    }
+";
+
+// Stored in egui's callback resources; rebuilt when the dye texture changes
+struct DyeRenderResources {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl DyeRenderResources {
+    fn register(render_state: &egui_wgpu::RenderState, simulation: &FunctionalGPUFluid) {
+        let device = &render_state.device;
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Dye Display Shader"),
+            source: wgpu::ShaderSource::Wgsl(DYE_SHADER.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Dye Display Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    // rgba32float can't be filtered everywhere; textureLoad doesn't need it
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Dye Display Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(simulation.get_dye_texture_view()),
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Dye Display Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Dye Display Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(render_state.target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(DyeRenderResources { pipeline, bind_group });
+    }
+}
+
+struct DyePaintCallback;
+
+impl egui_wgpu::CallbackTrait for DyePaintCallback {
+    fn paint<'a>(
+        &'a self,
+        _info: egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a egui_wgpu::CallbackResources,
+    ) {
+        if let Some(resources) = callback_resources.get::<DyeRenderResources>() {
+            render_pass.set_pipeline(&resources.pipeline);
+            render_pass.set_bind_group(0, &resources.bind_group, &[]);
+            render_pass.draw(0..4, 0..1);
+        }
+    }
+}
 
 pub struct GPUInteractiveApp {
     simulation: FunctionalGPUFluid,
@@ -16,14 +144,37 @@ pub struct GPUInteractiveApp {
     resolution_scale: usize,
     base_width: usize,
     base_height: usize,
+    particles: ParticleSystem,
+    show_particles: bool,
+    // Frame rate and per-stage GPU time; stages are only split while shown
+    show_perf: bool,
+    perf: PerfStats,
+    // Paces steps by wall-clock time instead of the display's refresh
+    clock: StepClock,
+    // Last droplet of a right-drag, to fill in the path between frames
+    dye_stroke: BrushStroke,
+    // Set when the simulation shares eframe's device; the dye texture is then
+    // drawn directly instead of being read back every frame
+    render_state: Option<egui_wgpu::RenderState>,
 }
 
 impl GPUInteractiveApp {
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn new(width: usize, height: usize) -> Self {
         // Use tokio runtime to block on async initialization
         let rt = tokio::runtime::Runtime::new().unwrap();
         let simulation = rt.block_on(FunctionalGPUFluid::new(width as u32, height as u32)).unwrap();
-        
+        Self::new_with_simulation(simulation, width, height)
+    }
+
+    fn new_with_simulation(simulation: FunctionalGPUFluid, width: usize, height: usize) -> Self {
+        // Debug builds pick up edits to the WGSL file as it is saved
+        #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+        let simulation = {
+            let mut simulation = simulation;
+            simulation.watch_shader(crate::gpu_functional::SHADER_PATH);
+            simulation
+        };
         Self {
             simulation,
             paused: false,
@@ -44,7 +195,67 @@ impl GPUInteractiveApp {
             resolution_scale: 1,
             base_width: width,
             base_height: height,
+            particles: ParticleSystem::new(5000),
+            show_particles: false,
+            show_perf: false,
+            perf: PerfStats::new(),
+            clock: StepClock::default(),
+            dye_stroke: BrushStroke::default(),
+            render_state: None,
+        }
+    }
+
+    /// Run the simulation on eframe's wgpu device and draw it with a paint
+    /// callback. Falls back to `new` if that device can't run the solver.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_render_state(render_state: egui_wgpu::RenderState, width: usize, height: usize) -> Self {
+        Self::try_with_render_state(render_state, width, height).unwrap_or_else(|e| {
+            eprintln!("Drawing via readback, can't share the render device: {}", e);
+            Self::new(width, height)
+        })
+    }
+
+    /// Like `with_render_state`, but reports a device that can't run the
+    /// solver instead of falling back. The web build uses this to choose
+    /// between the GPU and CPU apps.
+    pub fn try_with_render_state(
+        render_state: egui_wgpu::RenderState,
+        width: usize,
+        height: usize,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let simulation = Self::shared_simulation(&render_state, width, height)?;
+        DyeRenderResources::register(&render_state, &simulation);
+
+        let mut app = Self::new_with_simulation(simulation, width, height);
+        app.render_state = Some(render_state);
+        Ok(app)
+    }
+
+    fn shared_simulation(
+        render_state: &egui_wgpu::RenderState,
+        width: usize,
+        height: usize,
+    ) -> Result<FunctionalGPUFluid, Box<dyn std::error::Error>> {
+        FunctionalGPUFluid::with_device(
+            &render_state.adapter,
+            render_state.device.clone(),
+            render_state.queue.clone(),
+            width as u32,
+            height as u32,
+        )
+    }
+
+    // A droplet of the current colour: `center` of it on the cell and `edge`
+    // on each neighbour, added as one batch. Neighbours off the grid wrap
+    // to huge coordinates, which the batch skips.
+    fn drop_dye(&mut self, x: usize, y: usize, center: f32, edge: f32) {
+        let (r, g, b) = self.dye_colors[self.current_dye_index];
+        let (x, y) = (x as u32, y as u32);
+        let mut dye = vec![(x, y, (r * center, g * center, b * center))];
+        for (nx, ny) in [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)] {
+            dye.push((nx, ny, (r * edge, g * edge, b * edge)));
         }
+        self.simulation.add_dye_batch(&dye);
     }
 
     fn change_resolution(&mut self, scale: usize) {
@@ -53,21 +264,44 @@ impl GPUInteractiveApp {
             let new_width = self.base_width * scale;
             let new_height = self.base_height * scale;
 
-            // Recreate GPU simulation with new resolution
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            self.simulation = rt.block_on(FunctionalGPUFluid::new(new_width as u32, new_height as u32)).unwrap();
+            // Recreate GPU simulation with new resolution, keeping the fade
+            // and solver parameters
+            let decay = self.simulation.dye_decay();
+            let params = self.simulation.params();
+            if let Some(render_state) = &self.render_state {
+                self.simulation = Self::shared_simulation(render_state, new_width, new_height).unwrap();
+                DyeRenderResources::register(render_state, &self.simulation);
+            } else {
+                // Only native builds run without a shared device
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    self.simulation = rt.block_on(FunctionalGPUFluid::new(new_width as u32, new_height as u32)).unwrap();
+                }
+            }
+
+            self.simulation.set_dye_decay(decay);
+            for (param, value) in params {
+                let _ = self.simulation.set_param(param, value);
+            }
+            self.simulation.set_profiling(self.show_perf);
+            #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+            self.simulation.watch_shader(crate::gpu_functional::SHADER_PATH);
 
             // Reset simulation state
             self.mouse_dragging = false;
             self.mouse_start_pos = None;
             self.mouse_current_pos = None;
             self.frame_count = 0;
+            self.particles.clear();
         }
     }
 }
 
 impl eframe::App for GPUInteractiveApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("frame").entered();
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("itsliquid - GPU Fluid Simulation");
 
@@ -75,9 +309,21 @@ impl eframe::App for GPUInteractiveApp {
                 if ui.button("Pause/Resume").clicked() {
                     self.paused = !self.paused;
                 }
+                if self.paused && ui.button("⏭").on_hover_text("Run one step").clicked() {
+                    self.simulation.step();
+                    self.frame_count += 1;
+                }
+                step_pace_ui(ui, &mut self.clock);
+                ui.add(egui::Slider::new(&mut self.clock.steps_per_second, 1.0..=240.0).text("Steps/s"))
+                    .on_hover_text("Steps run each second whatever the display's refresh rate");
 
                 ui.add(egui::Slider::new(&mut self.cell_size, 1.0..=10.0).text("Cell Size"));
 
+                let mut fade = self.simulation.dye_decay()[0];
+                if ui.add(egui::Slider::new(&mut fade, 0.0..=2.0).logarithmic(true).text("Fade")).changed() {
+                    self.simulation.set_dye_decay([fade; 3]);
+                }
+
                 ui.label("Dye Color:");
                 for (i, _) in self.dye_colors.iter().enumerate() {
                     if ui.radio_value(&mut self.current_dye_index, i, format!("Color {}", i + 1)).clicked() {
@@ -86,6 +332,19 @@ impl eframe::App for GPUInteractiveApp {
                 }
             });
 
+            // One slider per parameter the shaders take, written to the
+            // uniform buffer before the next step
+            ui.horizontal(|ui| {
+                for (param, mut value) in self.simulation.params() {
+                    let slider = egui::Slider::new(&mut value, param.range())
+                        .logarithmic(param != Param::Dt)
+                        .text(param.name());
+                    if ui.add(slider).changed() {
+                        let _ = self.simulation.set_param(param, value);
+                    }
+                }
+            });
+
             ui.horizontal(|ui| {
                 ui.label("Resolution Scale:");
 
@@ -102,6 +361,26 @@ impl eframe::App for GPUInteractiveApp {
                 ui.label(format!(" ({}x{} cells)", self.simulation.width(), self.simulation.height()));
             });
 
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_particles, "Particles");
+                if ui.button("Seed grid").clicked() {
+                    self.show_particles = true;
+                    self.particles.seed_grid(self.simulation.width(), self.simulation.height(), 4.0, (1.0, 1.0, 1.0));
+                }
+                if ui.button("Clear particles").clicked() {
+                    self.particles.clear();
+                }
+                ui.label(format!("{} particles", self.particles.len()));
+                ui.separator();
+                if ui.checkbox(&mut self.show_perf, "Performance").on_hover_text("Frame rate, GPU time per stage and memory").changed() {
+                    self.simulation.set_profiling(self.show_perf);
+                }
+            });
+
+            if let Some(error) = self.simulation.shader_error() {
+                ui.colored_label(egui::Color32::RED, format!("Shader reload failed, running the previous one:\n{}", error));
+            }
+
             ui.separator();
 
             // Calculate canvas size
@@ -150,7 +429,8 @@ impl eframe::App for GPUInteractiveApp {
                         // Create vortex by applying rotational force
                         let vortex_strength = 10.0;
 
-                        // Apply vortex force in a larger area
+                        // Apply vortex force in a larger area, as one batch
+                        let mut forces = Vec::new();
                         for dy in -5..=5 {
                             for dx in -5..=5 {
                                 let px = (x as i32 + dx) as usize;
@@ -164,11 +444,12 @@ impl eframe::App for GPUInteractiveApp {
                                         let force_y = dx as f32 * vortex_strength;
                                         let falloff = 1.0 - dist_sq / 25.0;
 
-                                        self.simulation.add_force(px, py, glam::Vec2::new(force_x * falloff, force_y * falloff));
+                                        forces.push((px as u32, py as u32, glam::Vec2::new(force_x * falloff, force_y * falloff)));
                                     }
                                 }
                             }
                         }
+                        self.simulation.add_force_batch(&forces);
                     }
                 }
 
@@ -185,89 +466,80 @@ impl eframe::App for GPUInteractiveApp {
 
                     if x < self.simulation.width() && y < self.simulation.height() {
                         // Add dye droplet - very small concentrated point
-                        let dye_color = self.dye_colors[self.current_dye_index];
-
-                        // Just add at the center point - like a tiny droplet
-                        self.simulation.add_dye(x, y, dye_color);
-
-                        // Optional: add tiny bit to immediate neighbors for smooth appearance
-                        if x > 0 {
-                            self.simulation.add_dye(x - 1, y, (dye_color.0 * 0.3, dye_color.1 * 0.3, dye_color.2 * 0.3));
-                        }
-                        if x < self.simulation.width() - 1 {
-                            self.simulation.add_dye(x + 1, y, (dye_color.0 * 0.3, dye_color.1 * 0.3, dye_color.2 * 0.3));
-                        }
-                        if y > 0 {
-                            self.simulation.add_dye(x, y - 1, (dye_color.0 * 0.3, dye_color.1 * 0.3, dye_color.2 * 0.3));
-                        }
-                        if y < self.simulation.height() - 1 {
-                            self.simulation.add_dye(x, y + 1, (dye_color.0 * 0.3, dye_color.1 * 0.3, dye_color.2 * 0.3));
-                        }
+                        self.drop_dye(x, y, 1.0, 0.3);
                     }
                 }
             }
 
-            // Continuous dye injection while right button is held and dragged
+            // Middle-click emits tracer particles
+            if self.show_particles
+                && response.clicked_by(egui::PointerButton::Middle)
+                && let Some(pos) = response.interact_pointer_pos()
+            {
+                let x = (pos.x - rect.left()) / self.cell_size;
+                let y = (pos.y - rect.top()) / self.cell_size;
+                self.particles.emit(x, y, 3.0, 50, (1.0, 1.0, 1.0));
+            }
+
+            // Continuous dye injection while right button is held and dragged,
+            // a droplet every cell along the path so fast drags stay unbroken
             if response.dragged_by(egui::PointerButton::Secondary) {
                 if let Some(pos) = response.interact_pointer_pos() {
-                    let x = ((pos.x - rect.left()) / self.cell_size) as usize;
-                    let y = ((pos.y - rect.top()) / self.cell_size) as usize;
-
-                    if x < self.simulation.width() && y < self.simulation.height() {
-                        // Add dye droplet - very small concentrated point
-                        let dye_color = self.dye_colors[self.current_dye_index];
-
-                        // Just add at the center point - like a tiny droplet (reduced for continuous stream)
-                        self.simulation.add_dye(x, y, (dye_color.0 * 0.5, dye_color.1 * 0.5, dye_color.2 * 0.5));
-
-                        // Optional: add tiny bit to immediate neighbors
-                        if x > 0 {
-                            self.simulation.add_dye(x - 1, y, (dye_color.0 * 0.15, dye_color.1 * 0.15, dye_color.2 * 0.15));
-                        }
-                        if x < self.simulation.width() - 1 {
-                            self.simulation.add_dye(x + 1, y, (dye_color.0 * 0.15, dye_color.1 * 0.15, dye_color.2 * 0.15));
-                        }
-                        if y > 0 {
-                            self.simulation.add_dye(x, y - 1, (dye_color.0 * 0.15, dye_color.1 * 0.15, dye_color.2 * 0.15));
-                        }
-                        if y < self.simulation.height() - 1 {
-                            self.simulation.add_dye(x, y + 1, (dye_color.0 * 0.15, dye_color.1 * 0.15, dye_color.2 * 0.15));
+                    let grid_pos = glam::Vec2::new((pos.x - rect.left()) / self.cell_size, (pos.y - rect.top()) / self.cell_size);
+                    let brush = Brush { radius: 1.0, spacing: 1.0, ..Brush::default() };
+                    for dab in self.dye_stroke.dabs(&brush, grid_pos) {
+                        let (x, y) = (dab.x as usize, dab.y as usize);
+                        if x < self.simulation.width() && y < self.simulation.height() {
+                            // Smaller droplets for a continuous stream
+                            self.drop_dye(x, y, 0.5, 0.15);
                         }
                     }
                 }
+            } else {
+                self.dye_stroke.end();
             }
 
             // Render GPU texture to screen
             let painter = ui.painter();
 
-            // Read dye data from GPU
+            #[cfg(not(target_arch = "wasm32"))]
             let rt = tokio::runtime::Runtime::new().unwrap();
-            let dye_data = rt.block_on(self.simulation.read_dye_data()).unwrap();
-
-            // Draw fluid simulation
-            for y in 0..self.simulation.height() {
-                for x in 0..self.simulation.width() {
-                    let idx = (y * self.simulation.width() + x) * 4; // RGBA format
-                    if idx + 3 < dye_data.len() {
-                        let r = dye_data[idx];
-                        let g = dye_data[idx + 1];
-                        let b = dye_data[idx + 2];
-                        let a = dye_data[idx + 3];
-
-                        // Create color from dye data
-                        let color = egui::Color32::from_rgb(
-                            (r * 255.0) as u8,
-                            (g * 255.0) as u8,
-                            (b * 255.0) as u8
-                        );
-
-                        let cell_rect = egui::Rect::from_min_size(
-                            egui::Pos2::new(rect.left() + x as f32 * self.cell_size,
-                                           rect.top() + y as f32 * self.cell_size),
-                            egui::Vec2::new(self.cell_size, self.cell_size)
-                        );
-
-                        painter.rect_filled(cell_rect, 0.0, color);
+
+            if self.render_state.is_some() {
+                // The dye texture is sampled in egui's render pass, no readback
+                painter.add(egui_wgpu::Callback::new_paint_callback(rect, DyePaintCallback));
+            }
+            // Blocking readback needs a runtime, so the web build always uses the callback
+            #[cfg(not(target_arch = "wasm32"))]
+            if self.render_state.is_none() {
+                // Read dye data from GPU
+                let dye_data = rt.block_on(self.simulation.read_dye_data()).unwrap();
+
+                // Draw fluid simulation
+                for y in 0..self.simulation.height() {
+                    for x in 0..self.simulation.width() {
+                        let idx = (y * self.simulation.width() + x) * 4; // RGBA format
+                        if idx + 3 < dye_data.len() {
+                            let r = dye_data[idx];
+                            let g = dye_data[idx + 1];
+                            let b = dye_data[idx + 2];
+                            let a = dye_data[idx + 3];
+
+                            // Create color from dye data
+                            let color = egui::Color32::from_rgb(
+                                (r * 255.0) as u8,
+                                (g * 255.0) as u8,
+                                (b * 255.0) as u8
+                            );
+
+                            let cell_rect = egui::Rect::from_min_size(
+                                egui::Pos2::new(rect.left() + x as f32 * self.cell_size,
+                                               rect.top() + y as f32 * self.cell_size),
+                                egui::Vec2::new(self.cell_size, self.cell_size)
+                            );
+
+                            painter.rect_filled(cell_rect, 0.0, color);
+                        }
                     }
                 }
             }
@@ -281,6 +553,21 @@ impl eframe::App for GPUInteractiveApp {
                 );
             }
 
+            // Draw tracer particles
+            if self.show_particles {
+                for p in &self.particles.particles {
+                    let center = egui::Pos2::new(
+                        rect.left() + p.x * self.cell_size,
+                        rect.top() + p.y * self.cell_size,
+                    );
+                    painter.circle_filled(center, 1.5, egui::Color32::from_rgb(
+                        (p.color.0 * 255.0) as u8,
+                        (p.color.1 * 255.0) as u8,
+                        (p.color.2 * 255.0) as u8,
+                    ));
+                }
+            }
+
             // Draw drag indicator if dragging
             if let (Some(start), Some(current)) = (self.mouse_start_pos, self.mouse_current_pos) {
                 painter.line_segment(
@@ -291,6 +578,17 @@ impl eframe::App for GPUInteractiveApp {
                 painter.circle_filled(current, 3.0, egui::Color32::from_rgb(255, 255, 255));
             }
 
+            if self.show_perf {
+                let text = format!(
+                    "{}\nmemory   {}\ngrid     {}x{}",
+                    self.perf.summary(),
+                    format_bytes(self.simulation.memory_bytes()),
+                    self.simulation.width(),
+                    self.simulation.height()
+                );
+                hud_panel(painter, rect.right_top() + egui::vec2(-8.0, 8.0), egui::Align2::RIGHT_TOP, text);
+            }
+
             for i in 0..=self.simulation.height() {
                 let y = rect.top() + i as f32 * self.cell_size;
                 painter.line_segment(
@@ -299,11 +597,40 @@ impl eframe::App for GPUInteractiveApp {
                 );
             }
 
-            // Update simulation if not paused
-            if !self.paused {
+            // Steps are paced by the clock rather than the display, so the
+            // flow runs at the same speed at any refresh rate
+            let mut solver = StageTimes::default();
+            let steps = if self.paused {
+                self.clock.pause();
+                0
+            } else {
+                self.clock.steps(now_seconds())
+            };
+            for _ in 0..steps {
                 self.simulation.step();
+                solver.accumulate(&self.simulation.stage_times());
                 self.frame_count += 1;
             }
+            if steps > 0 {
+                // Particles follow a CPU copy of the GPU velocity field,
+                // which the web build can't read back synchronously
+                #[cfg(not(target_arch = "wasm32"))]
+                if self.show_particles
+                    && !self.particles.is_empty()
+                    && let Ok(data) = rt.block_on(self.simulation.read_velocity_data())
+                {
+                    let field = VelocityGrid::from_rgba(
+                        self.simulation.width(),
+                        self.simulation.height(),
+                        &data,
+                        self.simulation.gpu_dt(),
+                    );
+                    for _ in 0..steps {
+                        self.particles.step(&field);
+                    }
+                }
+            }
+            self.perf.frame(now_seconds(), solver);
 
             ui.label(format!("Frame: {} | Resolution: {}x{} | GPU Mode | Left-click+drag: Pull fluid | Right-click+hold: Stream dye | Cell Size: {:.1}",
                 self.frame_count, self.simulation.width(), self.simulation.height(), self.cell_size));
@@ -1,38 +1,112 @@
-use crate::InteractiveFluid;
-#[cfg(target_arch = "wasm32")]
-use base64::engine::general_purpose::URL_SAFE_NO_PAD;
-#[cfg(target_arch = "wasm32")]
-use base64::Engine as _;
-#[cfg(target_arch = "wasm32")]
-use serde::{Deserialize, Serialize};
-#[cfg(target_arch = "wasm32")]
-use serde_json;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::animation::{AnimationExport, AnimationFormat};
+use crate::audio::AudioLevels;
+use crate::brush::{BlendMode, Brush, BrushStroke, Falloff, touch_pressure};
+use crate::gestures::{CanvasView, Touches};
+use crate::calibration::{Calibration, Quality};
+use crate::chemistry::Chemistry;
+use crate::choreography::Choreography;
+use crate::container::Container;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::gallery::GalleryCard;
+use crate::gallery::qr_modules;
+use crate::gates::GateSet;
+use crate::history::{FluidSnapshot, UndoStack};
+use crate::mask::Mask;
+use crate::obstacle::{MovingObstacle, ObstacleShape};
+use crate::palette::{self, Palette};
+use crate::particles::{Integrator, ParticleSystem};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::replay::Recording;
+use crate::rheology::{PowerLaw, VISCOSITY_FACTOR_RANGE};
+use crate::turbulence::Turbulence;
+use crate::scenarios::Scenario;
+use crate::scene::{Emission, PersistentElement, PersistentElementType, Scene, SceneParams};
+use crate::analysis::FluidMetrics;
+use crate::export::ImageExporter;
+use crate::still::{Bloom, StillExport};
+use crate::render::{Colormap, RenderMode, diverging_color, dye_intensity, trace_streamline};
+use crate::session::SessionStats;
+use crate::timeline::{Interpolation, Timeline, Track};
+use crate::timing::{DEFAULT_STEPS_PER_SECOND, PerfStats, StageTimes, StepClock, format_bytes, now_seconds};
+#[cfg(any(all(feature = "ndi", not(target_arch = "wasm32")), all(feature = "webcam", target_os = "linux")))]
+use crate::frame::FrameBuffer;
+#[cfg(any(all(feature = "ndi", not(target_arch = "wasm32")), all(feature = "webcam", target_os = "linux")))]
+use crate::output::FrameSink;
+#[cfg(all(feature = "ndi", not(target_arch = "wasm32")))]
+use crate::output::NdiSender;
+#[cfg(all(feature = "webcam", target_os = "linux"))]
+use crate::output::VirtualCamera;
+#[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+use crate::chat::TwitchChat;
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+use crate::audio::{AudioBand, AudioInput, AudioLink};
+#[cfg(all(feature = "control", not(target_arch = "wasm32")))]
+use crate::control::{Control, OscListener, DEFAULT_OSC_PORT};
+#[cfg(feature = "scripting")]
+use crate::script::Script;
+#[cfg(all(feature = "midi", not(target_arch = "wasm32")))]
+use crate::control::MidiListener;
+#[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+use crate::ingest::Ingestor;
+use crate::fluid_interactive::ABSORB_RATE;
+use crate::{ConfigurableSimulation, InteractiveFluid, Param, Scheme};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
 #[cfg(target_arch = "wasm32")]
 use web_sys;
 use eframe::egui;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Starting point for the script console
+#[cfg(feature = "scripting")]
+const SCRIPT_EXAMPLE: &str = r#"// Runs once; frame(n) then runs before every step
+place_element(#{
+    element_type: #{ type: "VortexSource", strength: 3.0, falloff: "Gaussian" },
+    x: width() / 2, y: height() / 2, radius: 15,
+});
+
+fn frame(n) {
+    let y = height() / 2 + 10 * (n * 0.05).sin();
+    add_dye(4, y, 1.0, 0.4, 0.1);
+    add_force(4, y, 15, 0, 3);
+}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 enum Tool {
     Dye,
     Force,
     Eyedropper,
     Attractor,
     Eraser,
+    Select,
+    Gate,
+    Heat,
+    Emitter,
+    Vortex,
+    Fan,
+    Turbulence,
+    Obstacle,
+    Viscosity,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum PersistentElementType {
-    DyeSource { color: (f32, f32, f32), intensity: f32 },
-    ForceSource { direction: (f32, f32), intensity: f32 },
-    AttractorSource { strength: f32 },
+// Canvas state saved before each edit, for undo
+// An animation export rendering on a worker thread
+#[cfg(not(target_arch = "wasm32"))]
+struct ExportJob {
+    frames: usize,
+    done: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+    handle: std::thread::JoinHandle<Result<usize, String>>,
 }
 
-#[derive(Debug, Clone, Copy)]
-struct PersistentElement {
-    element_type: PersistentElementType,
-    x: f32,
-    y: f32,
-    radius: f32,
+struct EditSnapshot {
+    fluid: FluidSnapshot,
+    elements: Vec<PersistentElement>,
+    // Keys follow the elements they belong to
+    tracks: Vec<Track>,
 }
 
 pub struct InteractiveApp {
@@ -42,34 +116,252 @@ pub struct InteractiveApp {
     selected_tool: Tool,
     mouse_start_pos: Option<egui::Pos2>,
     mouse_current_pos: Option<egui::Pos2>,
-    dye_colors: Vec<(f32, f32, f32)>,
-    current_dye_index: usize,
+    palette: Palette,
+    // Color picker state for the selected palette slot
+    picker_color: egui::ecolor::Hsva,
     dye_intensity: f32,
     force_intensity: f32,
     attractor_radius: f32,
     attractor_strength: f32,
-    spiral_angle: f32, // 0-90 degrees: 0=pure inward, 90=pure tangential
+    // Push fluid out from the cursor instead of pulling it in
+    attractor_repel: bool,
+    // Dye pulled into the attractor disappears
+    attractor_absorb: bool,
     resolution_scale: usize,
     base_width: usize,
     base_height: usize,
     continuous_color_pos: Option<(usize, usize)>,
     last_window_size: Option<egui::Vec2>,
     sampled_color: Option<(f32, f32, f32)>,
+    // Editable hex of the brush color taken from the sample
+    sample_hex: String,
     attractor_pos: Option<egui::Pos2>,
     attractor_grid_pos: Option<(f32, f32)>, // Grid coordinates for dye trap
     persistent_elements: Vec<PersistentElement>,
     placement_mode: bool,
-    eraser_radius: f32,
+    // Radius, falloff, spacing and pressure for the dye, force and eraser tools
+    brush: Brush,
+    brush_stroke: BrushStroke,
+    // Latest stylus pressure while a touch with pressure is down
+    stylus_pressure: Option<f32>,
+    // Pinch zoom and pan of the canvas, and the fingers driving them
+    view: CanvasView,
+    touches: Touches,
+    // Every finger paints its own dye stroke instead of two panning and zooming
+    multi_finger_paint: bool,
     eraser_pos: Option<egui::Pos2>,
+    // Eraser clears dye under the brush instead of placed elements
+    eraser_dye: bool,
+    // How much of the dye each pass takes, 1 clearing it outright
+    eraser_fade: f32,
+    eraser_velocity: bool,
+    // Element picked with the Select tool, shown in the inspector
+    selected_element: Option<usize>,
+    // Keyframes for the elements, stepped with the simulation while playing
+    timeline: Timeline,
+    show_timeline: bool,
+    // Whether an undo point was saved since the element was selected, so a
+    // drag or slider tweak undoes as one edit
+    selection_edited: bool,
     copy_feedback_until_frame: Option<usize>,
+    show_share_qr: bool,
+    // Link the QR texture was built for; no texture if it didn't fit
+    share_qr: Option<(String, Option<egui::TextureHandle>)>,
+    // Where the web build is hosted, for links made on desktop
+    #[cfg(not(target_arch = "wasm32"))]
+    share_base_url: String,
+    heat_amount: f32,
+    heat_radius: f32,
+    heat_pos: Option<egui::Pos2>,
+    // Radius in grid cells
+    vortex_radius: f32,
+    vortex_strength: f32,
+    vortex_clockwise: bool,
+    vortex_falloff: Falloff,
+    vortex_pos: Option<egui::Pos2>,
+    // Half-width in grid cells
+    fan_width: f32,
+    fan_speed: f32,
+    // Blow along the drawn line like a conveyor instead of across it
+    fan_along: bool,
+    // New moving obstacles: a paddle instead of a disc, its size in grid
+    // cells and its spin, and whether the flow carries it and how heavy it is
+    obstacle_paddle: bool,
+    obstacle_size: f32,
+    obstacle_spin: f32,
+    obstacle_floating: bool,
+    obstacle_density: f32,
+    // Moving obstacle under the pointer and where it is being dragged to
+    dragged_obstacle: Option<usize>,
+    obstacle_target: Option<glam::Vec2>,
+    // Settings kept while turbulence is switched off
+    parked_turbulence: Option<Turbulence>,
+    turbulence_erase: bool,
+    // Viscosity factor the viscosity brush paints toward
+    viscosity_target: f32,
+    // Dye painting and dye sources also inject heat, giving rising smoke
+    smoke_mode: bool,
+    // Flux gates drawn with the Gate tool, measured after every step
+    gates: GateSet,
+    // Tracer particles advected with the flow, seeded by the Emitter tool
+    particles: ParticleSystem,
+    show_particles: bool,
+    // Streamline overlay for inspecting flow structure
+    show_streamlines: bool,
+    // Subsampled velocity arrows: one every quiver_spacing cells, quiver_scale
+    // cells long per unit of velocity
+    show_quiver: bool,
+    // Draw the projection's pressure field instead of dye
+    show_pressure: bool,
+    // Dye totals per channel, centers of mass and spread over the canvas
+    show_stats: bool,
+    // Performance HUD, fed the solver's stage times every frame
+    show_perf: bool,
+    perf: PerfStats,
+    // Solver time of this frame's substeps so far
+    frame_solver: StageTimes,
+    // Paces steps by wall-clock time instead of the display's refresh
+    clock: StepClock,
+    // PNG export: bicubic upscaling, and bloom on top of it
+    png_smooth: bool,
+    png_bloom: bool,
+    png_bloom_settings: Bloom,
+    // None draws dye in its own colors; Some maps total dye through a colormap
+    dye_colormap: Option<Colormap>,
+    #[cfg(not(target_arch = "wasm32"))]
+    lut_path: String,
+    // Scene file for Save/Load scene, optionally with the dye field
+    #[cfg(not(target_arch = "wasm32"))]
+    scene_path: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    scene_include_dye: bool,
+    // Black-and-white stencils kept at image size, resampled whenever the
+    // grid changes: white blocks the flow, or pours the current dye color
+    #[cfg(not(target_arch = "wasm32"))]
+    mask_path: String,
+    obstacle_image: Option<image::GrayImage>,
+    // Shape of the fluid domain, solid outside
+    container: Option<Container>,
+    emitter_image: Option<image::GrayImage>,
+    emitter_mask: Option<Mask>,
+    mask_threshold: f32,
+    mask_invert: bool,
+    // Dye each emitter cell adds per step, times the current color
+    mask_emit_rate: f32,
+    // Where an input recording is written when it stops
+    #[cfg(not(target_arch = "wasm32"))]
+    recording_path: String,
+    // Export animation dialog; the recording is read from recording_path
+    #[cfg(not(target_arch = "wasm32"))]
+    show_export: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_path: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_format: AnimationFormat,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_frames: usize,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_width: u32,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_include_dye: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_use_recording: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_job: Option<ExportJob>,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_status: String,
+    quiver_spacing: usize,
+    quiver_scale: f32,
+    // Field colors for the current frame, uploaded as a single texture
+    canvas_texture: Option<egui::TextureHandle>,
+    emit_count: usize,
+    emit_radius: f32,
+    emitter_pos: Option<egui::Pos2>,
+    // Live NDI output of the dye, published after every step while enabled
+    #[cfg(all(feature = "ndi", not(target_arch = "wasm32")))]
+    ndi: Option<NdiSender>,
+    #[cfg(all(feature = "ndi", not(target_arch = "wasm32")))]
+    ndi_frame: FrameBuffer,
+    // Virtual camera output at a fixed size, since call apps can't follow
+    // the responsive grid resizing
+    #[cfg(all(feature = "webcam", target_os = "linux"))]
+    webcam: Option<VirtualCamera>,
+    #[cfg(all(feature = "webcam", target_os = "linux"))]
+    webcam_frame: FrameBuffer,
+    // Twitch chat commands applied before each step, after sanitizing and
+    // per-user rate limiting
+    #[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+    chat: Option<TwitchChat>,
+    #[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+    chat_channel: String,
+    #[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+    chat_ingest: Ingestor,
+    // OSC (and MIDI CC) remote control, applied every frame even while paused
+    #[cfg(all(feature = "control", not(target_arch = "wasm32")))]
+    osc: Option<OscListener>,
+    #[cfg(all(feature = "control", not(target_arch = "wasm32")))]
+    osc_port: u16,
+    #[cfg(all(feature = "midi", not(target_arch = "wasm32")))]
+    midi: Option<MidiListener>,
+    // Script console; a running script's frame(n) is called before each step
+    #[cfg(feature = "scripting")]
+    show_script: bool,
+    #[cfg(feature = "scripting")]
+    script_source: String,
+    #[cfg(feature = "scripting")]
+    script: Option<Script>,
+    #[cfg(feature = "scripting")]
+    script_log: Vec<String>,
+    // Band levels that audio-linked elements pulse with; silent without input
+    audio_levels: AudioLevels,
+    #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+    audio: Option<AudioInput>,
+    #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+    audio_sensitivity: f32,
     // Docking preference for tool panels
     controls_dock: ControlsDockMode,
+    theme: Theme,
+    // Theme last handed to egui, so visuals are only set when it changes
+    applied_theme: Option<Theme>,
     #[cfg(target_arch = "wasm32")]
     url_state_loaded: bool,
     #[cfg(target_arch = "wasm32")]
     last_share_hash: Option<String>,
     // Hide tool-specific panels to maximize canvas
     ui_hide_controls: bool,
+    // Kiosk view: canvas only, pointer leaves a trail in the fluid instead
+    // of using the selected tool
+    presentation: bool,
+    presentation_hide_cursor: bool,
+    trail_strength: f32,
+    trail_pos: Option<egui::Pos2>,
+    // Auto-play after idle_seconds without input while presenting
+    autoplay: bool,
+    idle_seconds: f32,
+    last_input_time: f64,
+    autoplaying: bool,
+    choreography: Choreography,
+    // Second native window, restored between sessions
+    #[cfg(not(target_arch = "wasm32"))]
+    detached: Option<DetachedView>,
+    // Total dye and kinetic energy per frame while the analysis window is open
+    #[cfg(not(target_arch = "wasm32"))]
+    stats_history: std::collections::VecDeque<[f32; 2]>,
+    // Strokes, colors and time for the take-home gallery card
+    session: SessionStats,
+    gallery_caption: String,
+    // The QR code encodes the share link unless another link is entered
+    #[cfg(not(target_arch = "wasm32"))]
+    gallery_qr: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    gallery_link: String,
+    // Device defaults from the first-run benchmark, kept in storage
+    calibration: Option<Calibration>,
+    // Solver steps per rendered frame, each advancing dt / substeps
+    substeps: usize,
+    quality: Quality,
+    // Paint strokes, eraser actions, placed elements and clears, for Ctrl+Z
+    history: UndoStack<EditSnapshot>,
 }
 
 impl InteractiveApp {
@@ -81,238 +373,2625 @@ impl InteractiveApp {
             selected_tool: Tool::Dye,
             mouse_start_pos: None,
             mouse_current_pos: None,
-            dye_colors: vec![
-                (1.0, 0.0, 0.0), // Red
-                (0.0, 1.0, 0.0), // Green
-                (0.0, 0.0, 1.0), // Blue
-                (1.0, 1.0, 0.0), // Yellow
-                (1.0, 0.0, 1.0), // Magenta
-                (0.0, 1.0, 1.0), // Cyan
-                (1.0, 1.0, 1.0), // White
-                (0.0, 0.0, 0.0), // Black (negative dye - removes color)
-            ],
-            current_dye_index: 0,
+            palette: Palette::default(),
+            picker_color: egui::ecolor::Hsva::from_srgb([255, 0, 0]),
             dye_intensity: 0.5,
             force_intensity: 0.5,
             attractor_radius: 50.0,
             attractor_strength: 5.0,
-            spiral_angle: 70.0, // No longer used - keeping for backward compat
             resolution_scale: 1,
             base_width: width,
             base_height: height,
             continuous_color_pos: None,
             last_window_size: None,
             sampled_color: None,
+            sample_hex: String::new(),
             attractor_pos: None,
+            attractor_repel: false,
+            attractor_absorb: false,
             attractor_grid_pos: None,
             persistent_elements: Vec::new(),
             placement_mode: false,
-            eraser_radius: 30.0,
+            brush: Brush::default(),
+            brush_stroke: BrushStroke::default(),
+            stylus_pressure: None,
+            view: CanvasView::default(),
+            touches: Touches::default(),
+            multi_finger_paint: false,
+            selected_element: None,
+            timeline: Timeline::default(),
+            show_timeline: false,
+            selection_edited: false,
             eraser_pos: None,
+            eraser_dye: false,
+            eraser_fade: 1.0,
+            eraser_velocity: false,
             copy_feedback_until_frame: None,
+            show_share_qr: false,
+            share_qr: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            share_base_url: String::from(SHARE_BASE_URL),
+            heat_amount: 2.0,
+            heat_radius: 4.0,
+            heat_pos: None,
+            vortex_radius: 12.0,
+            vortex_strength: 2.0,
+            vortex_clockwise: false,
+            vortex_falloff: Falloff::Linear,
+            vortex_pos: None,
+            fan_width: 3.0,
+            fan_speed: 10.0,
+            fan_along: false,
+            obstacle_paddle: false,
+            obstacle_size: 6.0,
+            obstacle_spin: 0.0,
+            obstacle_floating: false,
+            obstacle_density: 0.5,
+            dragged_obstacle: None,
+            obstacle_target: None,
+            parked_turbulence: None,
+            turbulence_erase: false,
+            viscosity_target: 10.0,
+            smoke_mode: false,
+            gates: GateSet {
+                max_history: 600,
+                ..GateSet::default()
+            },
+            particles: ParticleSystem::new(20000),
+            show_particles: true,
+            show_streamlines: false,
+            show_quiver: false,
+            show_pressure: false,
+            show_stats: false,
+            show_perf: false,
+            perf: PerfStats::new(),
+            frame_solver: StageTimes::default(),
+            clock: StepClock::default(),
+            png_smooth: false,
+            png_bloom: false,
+            png_bloom_settings: Bloom::default(),
+            dye_colormap: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            lut_path: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            scene_path: String::from("scene.json"),
+            #[cfg(not(target_arch = "wasm32"))]
+            scene_include_dye: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            mask_path: String::new(),
+            obstacle_image: None,
+            container: None,
+            emitter_image: None,
+            emitter_mask: None,
+            mask_threshold: 0.5,
+            mask_invert: false,
+            mask_emit_rate: 0.05,
+            #[cfg(not(target_arch = "wasm32"))]
+            recording_path: String::from("session.replay.json"),
+            #[cfg(not(target_arch = "wasm32"))]
+            show_export: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_path: String::from("animation"),
+            #[cfg(not(target_arch = "wasm32"))]
+            export_format: AnimationFormat::Png,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_frames: 300,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_width: 1280,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_include_dye: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_use_recording: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_job: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_status: String::new(),
+            quiver_spacing: 6,
+            quiver_scale: 0.5,
+            canvas_texture: None,
+            emit_count: 20,
+            emit_radius: 3.0,
+            emitter_pos: None,
+            #[cfg(all(feature = "ndi", not(target_arch = "wasm32")))]
+            ndi: None,
+            #[cfg(all(feature = "ndi", not(target_arch = "wasm32")))]
+            ndi_frame: FrameBuffer::new(0, 0),
+            #[cfg(all(feature = "webcam", target_os = "linux"))]
+            webcam: None,
+            #[cfg(all(feature = "webcam", target_os = "linux"))]
+            webcam_frame: FrameBuffer::new(1280, 720),
+            #[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+            chat: None,
+            #[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+            chat_channel: String::new(),
+            #[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+            chat_ingest: Ingestor::default(),
+            #[cfg(all(feature = "control", not(target_arch = "wasm32")))]
+            osc: None,
+            #[cfg(all(feature = "control", not(target_arch = "wasm32")))]
+            osc_port: DEFAULT_OSC_PORT,
+            #[cfg(all(feature = "midi", not(target_arch = "wasm32")))]
+            midi: None,
+            #[cfg(feature = "scripting")]
+            show_script: false,
+            #[cfg(feature = "scripting")]
+            script_source: String::from(SCRIPT_EXAMPLE),
+            #[cfg(feature = "scripting")]
+            script: None,
+            #[cfg(feature = "scripting")]
+            script_log: Vec::new(),
+            audio_levels: AudioLevels::default(),
+            #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+            audio: None,
+            #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+            audio_sensitivity: 4.0,
             controls_dock: ControlsDockMode::Auto,
+            theme: Theme::System,
+            applied_theme: None,
             #[cfg(target_arch = "wasm32")]
             url_state_loaded: false,
             #[cfg(target_arch = "wasm32")]
             last_share_hash: None,
             ui_hide_controls: false,
+            presentation: false,
+            presentation_hide_cursor: true,
+            trail_strength: 0.3,
+            trail_pos: None,
+            autoplay: true,
+            idle_seconds: 30.0,
+            last_input_time: 0.0,
+            autoplaying: false,
+            choreography: Choreography::new(rand::random()),
+            #[cfg(not(target_arch = "wasm32"))]
+            detached: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            stats_history: std::collections::VecDeque::new(),
+            session: SessionStats::new(),
+            gallery_caption: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            gallery_qr: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            gallery_link: String::new(),
+            calibration: None,
+            substeps: 1,
+            quality: Quality::Medium,
+            history: UndoStack::new(MAX_UNDO_DEPTH),
         }
     }
 
-    fn change_resolution(&mut self, scale: usize) {
-        if scale != self.resolution_scale && scale >= 1 && scale <= 8 {
-            self.resolution_scale = scale;
-            let new_width = self.base_width * scale;
-            let new_height = self.base_height * scale;
+    /// Reopen the second window if it was open when the app last closed, and
+    /// bring back the web address used for share links and the tool, slider,
+    /// color, panel and theme choices. Window size and position are restored
+    /// by eframe itself. Device defaults come from the stored calibration,
+    /// which is measured on first run.
+    pub fn restore(&mut self, storage: &dyn eframe::Storage) {
+        if let Some(state) = eframe::get_value::<UiState>(storage, UI_STATE_KEY) {
+            state.apply(self);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.detached = eframe::get_value(storage, DETACHED_VIEW_KEY).flatten();
+            if let Some(url) = eframe::get_value(storage, SHARE_BASE_URL_KEY) {
+                self.share_base_url = url;
+            }
+        }
+        match eframe::get_value(storage, CALIBRATION_KEY) {
+            Some(calibration) => self.apply_calibration(calibration),
+            None => self.calibrate(),
+        }
+    }
 
-            // Create new simulation with scaled resolution
-            self.simulation = InteractiveFluid::new(new_width, new_height);
+    /// Benchmark this device (about a third of a second) and use the
+    /// defaults it suggests.
+    pub fn calibrate(&mut self) {
+        self.apply_calibration(Calibration::run(0.3, touch_device()));
+    }
 
-            // Reset simulation state
-            self.mouse_start_pos = None;
-            self.mouse_current_pos = None;
-            self.continuous_color_pos = None;
+    /// Take grid density, substeps and quality from a calibration.
+    pub fn apply_calibration(&mut self, calibration: Calibration) {
+        self.change_resolution(calibration.resolution_scale);
+        self.substeps = calibration.substeps.max(1);
+        self.set_quality(calibration.quality);
+        self.calibration = Some(calibration);
+    }
 
-            // Clear persistent elements since they have coordinates for old grid
-            self.persistent_elements.clear();
-        }
+    fn set_quality(&mut self, quality: Quality) {
+        self.quality = quality;
+        self.simulation.pressure_iterations = quality.pressure_iterations();
+        self.particles.max_particles = quality.max_particles();
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ControlsDockMode {
-    Auto,
-    Top,
-    Bottom,
-}
+    fn quality_menu_ui(&mut self, ui: &mut egui::Ui) {
+        for quality in Quality::ALL {
+            if ui.radio(self.quality == quality, quality.name()).clicked() {
+                self.set_quality(quality);
+            }
+        }
+        ui.add(egui::Slider::new(&mut self.substeps, 1..=4).text("Substeps"));
+        ui.add(egui::Slider::new(&mut self.clock.steps_per_second, 1.0..=240.0).text("Steps per second"))
+            .on_hover_text("Steps run each second whatever the display's refresh rate");
+        ui.label(format!("{:.2} time units per second", self.clock.time_scale(self.simulation.dt)));
+        ui.separator();
+        if let Some(calibration) = &self.calibration {
+            ui.label(format!("{} · {:.1} Mcells/s", calibration.device.name(), calibration.throughput));
+        }
+        if ui.button("Recalibrate").on_hover_text("Time the solver again and pick new defaults").clicked() {
+            self.calibrate();
+            ui.close_menu();
+        }
+    }
 
-impl eframe::App for InteractiveApp {
-    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        // WASM: on first frame, try to load share state from URL
-        #[cfg(target_arch = "wasm32")]
+    // Solver parameters, applied to the running simulation as they change
+    fn simulation_panel_ui(&mut self, ui: &mut egui::Ui) {
+        for (param, mut value) in self.simulation.params() {
+            let slider = egui::Slider::new(&mut value, param.range())
+                .logarithmic(param != Param::Dt)
+                .text(param.name());
+            if ui.add(slider).changed() {
+                let _ = self.simulation.set_param(param, value);
+            }
+        }
+        ui.add(egui::Slider::new(&mut self.simulation.velocity_damping, 0.0..=2.0).text("Damping"))
+            .on_hover_text("Share of the velocity lost per unit time, so the scene settles down by itself");
+        ui.add(egui::Slider::new(&mut self.simulation.surface_tension, 0.0..=5.0).text("Surface tension"))
+            .on_hover_text("Pulls the edges of the dye inward where they bulge, so painted blobs round up into drops");
+        self.liquid_ui(ui);
+        ui.add(egui::Slider::new(&mut self.simulation.pressure_iterations, 1..=60).text("Pressure iterations"))
+            .on_hover_text("More sweeps make the flow more incompressible but cost time");
+        let mut adaptive = self.simulation.divergence_tolerance.is_some();
+        if ui
+            .checkbox(&mut adaptive, "Adaptive pressure")
+            .on_hover_text("Keep sweeping until the divergence left is below the tolerance")
+            .changed()
         {
-            if !self.url_state_loaded {
-                self.try_load_share_state_from_url();
-                self.url_state_loaded = true;
+            self.simulation.divergence_tolerance = adaptive.then_some(0.05);
+        }
+        if let Some(tolerance) = &mut self.simulation.divergence_tolerance {
+            ui.add(egui::Slider::new(tolerance, 0.001..=1.0).logarithmic(true).text("Divergence tolerance"));
+        }
+        ui.collapsing("Fade per channel", |ui| {
+            for (decay, name) in self.simulation.dye_decay.iter_mut().zip(["Red", "Green", "Blue"]) {
+                ui.add(egui::Slider::new(decay, 0.0..=2.0).logarithmic(true).text(name))
+                    .on_hover_text("Extra fade on top of Dissipation, so long sessions don't saturate to white");
+            }
+        });
+        egui::ComboBox::from_label("Edges")
+            .selected_text(self.simulation.boundary.name())
+            .show_ui(ui, |ui| {
+                for &mode in Scheme::Interactive.boundaries() {
+                    ui.selectable_value(&mut self.simulation.boundary, mode, mode.name());
+                }
+            })
+            .response
+            .on_hover_text("Periodic edges wrap flow and dye around, for seamless loops");
+        #[cfg(feature = "spectral")]
+        if self.simulation.boundary == crate::BoundaryMode::Periodic {
+            let mut exact = self.simulation.spectral.is_some();
+            let toggle = ui.checkbox(&mut exact, "FFT solver")
+                .on_hover_text("Exact pressure and diffusion solves, faster than the sweeps on large grids");
+            if toggle.changed() {
+                if exact {
+                    self.simulation.enable_spectral();
+                } else {
+                    self.simulation.spectral = None;
+                }
             }
         }
-        // Responsive sizing handled after panels are laid out using available rect.
+        ui.add(egui::Slider::new(&mut self.simulation.max_velocity, 10.0..=10000.0).logarithmic(true).text("Max speed"))
+            .on_hover_text("Faster cells are slowed to this, so strong attractors can't blow up the flow");
+        ui.separator();
+        if ui.button("Reset to defaults").clicked() {
+            let defaults = InteractiveFluid::new(1, 1);
+            for (param, value) in defaults.params() {
+                let _ = self.simulation.set_param(param, value);
+            }
+            self.simulation.pressure_iterations = self.quality.pressure_iterations();
+            self.simulation.boundary = defaults.boundary;
+            self.simulation.dye_decay = defaults.dye_decay;
+            self.simulation.max_velocity = defaults.max_velocity;
+            self.simulation.velocity_damping = defaults.velocity_damping;
+            self.simulation.surface_tension = defaults.surface_tension;
+            self.simulation.shear_model = defaults.shear_model;
+            self.simulation.divergence_tolerance = defaults.divergence_tolerance;
+        }
+    }
 
-        // Toolbar at the top - organized in multiple rows to prevent overflow
-        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
-            ui.vertical(|ui| {
-                // Row 1: Title and Help
-                ui.horizontal(|ui| {
-                    ui.heading("itsliquid");
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        if ui.hyperlink_to("📖 Help", "https://github.com/ekg/itsliquid#readme").clicked() {
-                            // Link opens in browser
-                        }
-                    });
-                });
+    // Newtonian or a shear-dependent viscosity, from the presets or tuned
+    fn liquid_ui(&mut self, ui: &mut egui::Ui) {
+        let presets = PowerLaw::presets();
+        let current = presets
+            .iter()
+            .find(|(_, viscosity, law)| Some(*law) == self.simulation.shear_model && *viscosity == self.simulation.viscosity)
+            .map(|(name, _, _)| *name);
+        let selected = match (self.simulation.shear_model, current) {
+            (None, _) => "Newtonian",
+            (Some(_), Some(name)) => name,
+            (Some(_), None) => "Custom",
+        };
+        egui::ComboBox::from_label("Liquid")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(self.simulation.shear_model.is_none(), "Newtonian").clicked() {
+                    self.simulation.shear_model = None;
+                }
+                for (name, viscosity, law) in presets {
+                    if ui.selectable_label(current == Some(name), name).clicked() {
+                        let _ = self.simulation.set_param(Param::Viscosity, viscosity);
+                        self.simulation.shear_model = Some(law);
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Honey is just thick, oobleck stiffens when stirred fast, paint runs once it moves");
+        if let Some(law) = &mut self.simulation.shear_model {
+            ui.add(egui::Slider::new(&mut law.index, 0.2..=2.0).text("Flow index"))
+                .on_hover_text("Below 1 the liquid thins where it is stirred, above 1 it thickens");
+            ui.add(egui::Slider::new(&mut law.reference_shear, 0.5..=50.0).logarithmic(true).text("Reference shear"))
+                .on_hover_text("Shear rate at which the viscosity is the slider's value");
+        }
+    }
 
-                ui.add_space(2.0);
+    fn snapshot(&self) -> EditSnapshot {
+        EditSnapshot {
+            fluid: FluidSnapshot::capture(&self.simulation),
+            elements: self.persistent_elements.clone(),
+            tracks: self.timeline.tracks.clone(),
+        }
+    }
 
-                // Row 2: Tool selection
-                ui.horizontal(|ui| {
-                    if ui.selectable_label(self.selected_tool == Tool::Dye, "🎨").clicked() {
-                        self.selected_tool = Tool::Dye;
-                    }
-                    if ui.selectable_label(self.selected_tool == Tool::Force, "💨").clicked() {
-                        self.selected_tool = Tool::Force;
-                    }
-                    if ui.selectable_label(self.selected_tool == Tool::Eyedropper, "🔍").clicked() {
-                        self.selected_tool = Tool::Eyedropper;
-                    }
-                    if ui.selectable_label(self.selected_tool == Tool::Attractor, "🌀").clicked() {
-                        self.selected_tool = Tool::Attractor;
-                    }
-                    if ui.selectable_label(self.selected_tool == Tool::Eraser, "🗑").clicked() {
-                        self.selected_tool = Tool::Eraser;
-                    }
+    // Save the canvas before an edit, keeping the history within its budget
+    fn record_edit(&mut self) {
+        let snapshot = self.snapshot();
+        self.history.max_depth = (UNDO_BUDGET_BYTES / snapshot.fluid.bytes().max(1)).clamp(1, MAX_UNDO_DEPTH);
+        self.history.push(snapshot);
+    }
 
-                    ui.separator();
+    fn undo(&mut self) {
+        let current = self.snapshot();
+        if let Some(snapshot) = self.history.undo(current) {
+            self.apply_snapshot(snapshot);
+        }
+    }
 
-                    // Placement mode toggle
-                    if ui.selectable_label(self.placement_mode, "📌").clicked() {
-                        self.placement_mode = !self.placement_mode;
-                    }
-                });
+    fn redo(&mut self) {
+        let current = self.snapshot();
+        if let Some(snapshot) = self.history.redo(current) {
+            self.apply_snapshot(snapshot);
+        }
+    }
 
-                // Row 3: Controls
-                ui.horizontal(|ui| {
-                    if ui.button(if self.paused { "▶ Resume" } else { "⏸ Pause" }).clicked() {
-                        self.paused = !self.paused;
-                    }
+    // Elements come back even if the window resized the grid since; the
+    // fluid then keeps its current state
+    fn apply_snapshot(&mut self, snapshot: EditSnapshot) {
+        let _ = snapshot.fluid.restore(&mut self.simulation);
+        self.persistent_elements = snapshot.elements;
+        self.timeline.tracks = snapshot.tracks;
+        self.select_element(None);
+    }
 
-                    if ui.button("🗑 Clear").clicked() {
-                        // Clear all dye and velocity
-                        for i in 0..self.simulation.dye_r.len() {
-                            self.simulation.dye_r[i] = 0.0;
-                            self.simulation.dye_g[i] = 0.0;
-                            self.simulation.dye_b[i] = 0.0;
-                            self.simulation.velocity_x[i] = 0.0;
-                            self.simulation.velocity_y[i] = 0.0;
-                        }
-                    }
+    fn select_element(&mut self, index: Option<usize>) {
+        self.selected_element = index;
+        self.selection_edited = false;
+    }
 
-                    ui.separator();
+    // One step of everything that moves with the simulation: scripts, the
+    // timeline, placed elements, the solver's substeps, gates and particles
+    fn step_simulation(&mut self) {
+        let smoke_heat = self.smoke_mode.then_some(self.heat_amount);
+        #[cfg(feature = "scripting")]
+        if let Some(script) = &mut self.script {
+            let result = script.run_frame(&mut self.simulation, &mut self.persistent_elements);
+            self.script_log.extend(script.take_output());
+            if let Err(e) = result {
+                self.script_log.push(format!("Stopped: {}", e));
+                self.script = None;
+            }
+        }
+        if self.timeline.playing {
+            self.timeline.pose(&mut self.persistent_elements);
+        }
+        let mut forces = Vec::new();
+        for (i, elem) in self.persistent_elements.iter_mut().enumerate() {
+            if self.timeline.enabled(i) {
+                let active = elem.modulated(&self.audio_levels);
+                active.apply_source(&mut self.simulation, smoke_heat);
+                if active.is_force() {
+                    forces.push(active);
+                }
+            }
+            elem.advance(self.simulation.dt);
+        }
+        self.remove_expired_elements();
+        self.timeline.advance();
+        if let Some(mask) = &self.emitter_mask {
+            let (r, g, b) = self.palette.current();
+            let rate = self.mask_emit_rate;
+            mask.emit_dye(&mut self.simulation, (r * rate, g * rate, b * rate));
+        }
 
-                    for &scale in &[1, 2, 4, 8] {
-                        if ui.selectable_label(self.resolution_scale == scale, format!("{}x", scale)).clicked() {
-                            self.change_resolution(scale);
-                        }
-                    }
+        // A dragged obstacle reaches the pointer over this step
+        let dragged = self.dragged_obstacle.and_then(|i| self.simulation.moving_obstacles.get_mut(i));
+        if let (Some(target), Some(obstacle)) = (self.obstacle_target, dragged) {
+            obstacle.steer_to(target, self.simulation.dt);
+        }
 
-                    ui.separator();
+        // Substeps split the frame's dt, so more of them only add accuracy
+        let dt = self.simulation.dt;
+        self.simulation.dt = dt / self.substeps as f32;
+        for _ in 0..self.substeps {
+            // Forces push a share of their strength on every substep
+            for elem in &forces {
+                elem.apply_force(&mut self.simulation);
+            }
+            self.simulation.step();
+            self.frame_solver.accumulate(&self.simulation.stage_times);
+            self.session.record_step(self.simulation.dt);
+        }
+        self.simulation.dt = dt;
+        self.frame_count += 1;
+        self.gates.measure(&self.simulation, self.frame_count);
+        self.particles.step(&self.simulation);
+    }
 
-                    ui.label(format!("Grid: {}x{}", self.simulation.width, self.simulation.height));
+    // Drop dye sources past their lifetime, last first so the timeline
+    // renumbers the rest correctly
+    fn remove_expired_elements(&mut self) {
+        let count = self.persistent_elements.len();
+        for index in (0..count).rev() {
+            if self.persistent_elements[index].expired() {
+                self.persistent_elements.remove(index);
+                self.timeline.element_removed(index);
+            }
+        }
+        if self.persistent_elements.len() != count {
+            self.select_element(None);
+        }
+    }
 
-                    ui.separator();
+    // The topmost element under a grid position, grabbing small elements
+    // from a little outside their radius
+    fn element_at(&self, x: f32, y: f32, grab_radius: f32) -> Option<usize> {
+        self.persistent_elements.iter().rposition(|elem| {
+            let (dx, dy) = (elem.x - x, elem.y - y);
+            (dx * dx + dy * dy).sqrt() <= elem.radius.max(grab_radius)
+        })
+    }
 
-                    // Share link button (WASM only)
-                    #[cfg(target_arch = "wasm32")]
-                    if ui.button("🔗 Copy link").clicked() {
-                        if let Some(url) = self.compose_share_url() {
-                            ui.output_mut(|o| o.copied_text = url);
-                            // show a brief feedback label for ~2 seconds
-                            self.copy_feedback_until_frame = Some(self.frame_count.saturating_add(120));
-                        }
+    // Replace the selected element, saving an undo point on its first edit
+    fn update_selected_element(&mut self, index: usize, elem: PersistentElement) {
+        if self.persistent_elements[index] == elem {
+            return;
+        }
+        if !self.selection_edited {
+            self.record_edit();
+            self.selection_edited = true;
+        }
+        self.persistent_elements[index] = elem;
+    }
+
+    fn element_inspector_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(index) = self.selected_element.filter(|&i| i < self.persistent_elements.len()) else {
+            ui.label("Click an element to select it, drag to move it");
+            return;
+        };
+        let mut elem = self.persistent_elements[index];
+        let name = match elem.element_type {
+            PersistentElementType::DyeSource { .. } => "Dye source",
+            PersistentElementType::ForceSource { .. } => "Force source",
+            PersistentElementType::AttractorSource { .. } => "Attractor",
+            PersistentElementType::RepulsorSource { .. } => "Repulsor",
+            PersistentElementType::VortexSource { .. } => "Vortex",
+            PersistentElementType::FanStrip { .. } => "Fan strip",
+            PersistentElementType::HeatSource { .. } => "Heat source",
+        };
+        ui.label(format!("{} at ({:.0}, {:.0})", name, elem.x, elem.y));
+        ui.horizontal(|ui| {
+            ui.label("Radius:");
+            ui.add(egui::Slider::new(&mut elem.radius, 0.5..=100.0).logarithmic(true).show_value(true));
+        });
+        match &mut elem.element_type {
+            PersistentElementType::DyeSource { color, intensity, blend, emission } => {
+                ui.horizontal(|ui| {
+                    ui.label("Color:");
+                    let mut rgb = [color.0, color.1, color.2];
+                    if ui.color_edit_button_rgb(&mut rgb).changed() {
+                        *color = (rgb[0], rgb[1], rgb[2]);
                     }
-                    #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        // Desktop build has no URL to copy; disable button
-                        let _disabled = ui.add_enabled(false, egui::Button::new("🔗 Copy link")).on_hover_text("Available on the web version");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Intensity:");
+                    ui.add(egui::Slider::new(intensity, 0.1..=100.0).show_value(true).step_by(0.1));
+                });
+                blend_mode_ui(ui, blend);
+                emission_ui(ui, emission);
+            }
+            PersistentElementType::ForceSource { direction, intensity } => {
+                // Direction is edited as an angle, keeping the drawn length
+                let length = (direction.0 * direction.0 + direction.1 * direction.1).sqrt().max(1.0);
+                let mut angle = direction.1.atan2(direction.0).to_degrees();
+                ui.horizontal(|ui| {
+                    ui.label("Direction:");
+                    if ui.add(egui::Slider::new(&mut angle, -180.0..=180.0).suffix("°")).changed() {
+                        let radians = angle.to_radians();
+                        *direction = (radians.cos() * length, radians.sin() * length);
                     }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Intensity:");
+                    ui.add(egui::Slider::new(intensity, 0.01..=3.0).show_value(true).step_by(0.01));
+                });
+            }
+            PersistentElementType::AttractorSource { strength, absorb } => {
+                ui.horizontal(|ui| {
+                    ui.label("Strength:");
+                    ui.add(egui::Slider::new(strength, 0.1..=100.0).show_value(true).step_by(0.1));
+                });
+                ui.checkbox(absorb, "Absorb dye").on_hover_text("Dye drawn into the center disappears");
+            }
+            PersistentElementType::RepulsorSource { strength } => {
+                ui.horizontal(|ui| {
+                    ui.label("Strength:");
+                    ui.add(egui::Slider::new(strength, 0.1..=100.0).show_value(true).step_by(0.1));
+                });
+            }
+            PersistentElementType::VortexSource { strength, falloff } => {
+                ui.horizontal(|ui| {
+                    ui.label("Spin:");
+                    ui.add(egui::Slider::new(strength, -20.0..=20.0).show_value(true).step_by(0.1))
+                        .on_hover_text("Positive turns clockwise");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Falloff:");
+                    for option in Falloff::ALL {
+                        ui.selectable_value(falloff, option, option.name());
+                    }
+                });
+            }
+            PersistentElementType::FanStrip { velocity, .. } => {
+                // Edited as a speed and an angle, like force directions
+                let mut speed = (velocity.0 * velocity.0 + velocity.1 * velocity.1).sqrt();
+                let mut angle = velocity.1.atan2(velocity.0).to_degrees();
+                let mut changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("Direction:");
+                    changed |= ui.add(egui::Slider::new(&mut angle, -180.0..=180.0).suffix("°")).changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Speed:");
+                    changed |= ui.add(egui::Slider::new(&mut speed, 0.0..=50.0).show_value(true).step_by(0.5)).changed();
+                });
+                if changed {
+                    let radians = angle.to_radians();
+                    *velocity = (radians.cos() * speed, radians.sin() * speed);
+                }
+            }
+            PersistentElementType::HeatSource { amount } => {
+                ui.horizontal(|ui| {
+                    ui.label("Amount:");
+                    ui.add(egui::Slider::new(amount, -10.0..=10.0).show_value(true).step_by(0.1));
+                });
+            }
+        }
+        #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+        ui.horizontal(|ui| {
+            ui.label("Audio:");
+            let mut band = elem.audio.map(|link| link.band);
+            egui::ComboBox::from_id_source("element_audio_band")
+                .selected_text(band.map_or("Off", AudioBand::name))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut band, None, "Off");
+                    for option in AudioBand::ALL {
+                        ui.selectable_value(&mut band, Some(option), option.name());
+                    }
+                });
+            let mut gain = elem.audio.map_or(2.0, |link| link.gain);
+            if band.is_some() {
+                ui.add(egui::Slider::new(&mut gain, -1.0..=10.0).text("Gain"))
+                    .on_hover_text("Strength is scaled by 1 + gain × level");
+            }
+            elem.audio = band.map(|band| AudioLink { band, gain });
+        });
+        self.update_selected_element(index, elem);
 
-                    ui.separator();
-
-                    // Controls docking preference
-                    egui::ComboBox::from_label("Controls")
-                        .selected_text(match self.controls_dock {
-                            ControlsDockMode::Auto => "Auto",
-                            ControlsDockMode::Top => "Top",
-                            ControlsDockMode::Bottom => "Bottom",
-                        })
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(&mut self.controls_dock, ControlsDockMode::Auto, "Auto");
-                            ui.selectable_value(&mut self.controls_dock, ControlsDockMode::Top, "Top");
-                            ui.selectable_value(&mut self.controls_dock, ControlsDockMode::Bottom, "Bottom");
-                        });
-
-                    ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Delete").clicked() {
+                self.record_edit();
+                self.persistent_elements.remove(index);
+                self.timeline.element_removed(index);
+                self.select_element(None);
+            }
+            if ui.button("Deselect").clicked() {
+                self.select_element(None);
+            }
+        });
+    }
 
-                    // Hide tool panels toggle (max canvas)
-                    let hide_lbl = if self.ui_hide_controls { "🎛 Show Controls" } else { "🎛 Hide Controls" };
-                    if ui.button(hide_lbl).clicked() {
-                        self.ui_hide_controls = !self.ui_hide_controls;
-                    }
+    // Play and scrub controls, and keys for the selected element at the
+    // playhead. Scrubbing poses the elements without stepping the fluid.
+    fn timeline_ui(&mut self, ui: &mut egui::Ui) {
+        let before = self.timeline.frame;
+        ui.horizontal(|ui| {
+            if ui.button("⏮").on_hover_text("Back to the first frame").clicked() {
+                self.timeline.frame = 0;
+            }
+            let play_label = if self.timeline.playing { "⏸" } else { "▶" };
+            if ui.button(play_label).clicked() {
+                self.timeline.playing = !self.timeline.playing;
+            }
+            let keys = self.timeline.key_frames();
+            if ui.button("⏪").on_hover_text("Previous key").clicked()
+                && let Some(&frame) = keys.iter().rev().find(|&&frame| frame < self.timeline.frame)
+            {
+                self.timeline.frame = frame;
+            }
+            if ui.button("⏩").on_hover_text("Next key").clicked()
+                && let Some(&frame) = keys.iter().find(|&&frame| frame > self.timeline.frame)
+            {
+                self.timeline.frame = frame;
+            }
+            let last = self.timeline.length.saturating_sub(1);
+            ui.add(egui::Slider::new(&mut self.timeline.frame, 0..=last).text("Frame"));
+            ui.add(egui::DragValue::new(&mut self.timeline.length).clamp_range(1..=100_000).prefix("Length "));
+            ui.checkbox(&mut self.timeline.looping, "Loop");
+        });
 
-                    ui.separator();
+        // Key marks under the scrubber
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 10.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let x_of = |frame: u32| rect.left() + rect.width() * frame as f32 / self.timeline.length.max(1) as f32;
+        for frame in self.timeline.key_frames() {
+            painter.circle_filled(egui::pos2(x_of(frame), rect.center().y), 3.0, egui::Color32::GOLD);
+        }
+        painter.vline(x_of(self.timeline.frame), rect.y_range(), egui::Stroke::new(1.0, egui::Color32::WHITE));
 
-                    // Fullscreen toggle
-                    #[cfg(target_arch = "wasm32")]
-                    {
-                        let fs = self.is_fullscreen_web();
-                        let label = if fs { "🗗 Exit Fullscreen" } else { "⛶ Fullscreen" };
-                        if ui.button(label).clicked() {
-                            if fs {
-                                let _ = self.exit_fullscreen_web();
-                            } else {
-                                let _ = self.request_fullscreen_web();
-                            }
-                        }
-                    }
-                    #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        // Desktop: approximate by maximizing the window; true OS fullscreen may vary per platform
-                        let label = "⛶ Fullscreen";
-                        if ui.button(label).clicked() {
-                            // Try toggling fullscreen if available, else maximize
-                            #[allow(unused_must_use)]
-                            {
-                                // eframe 0.27: use frame.set_fullscreen if available via cfg attr
-                                frame.set_fullscreen(true);
-                            }
+        let selected = self.selected_element.filter(|&i| i < self.persistent_elements.len());
+        ui.horizontal(|ui| {
+            let Some(index) = selected else {
+                ui.label("Select an element to key it at this frame");
+                return;
+            };
+            let frame = self.timeline.frame;
+            let track = self.timeline.track(index);
+            let keyed = track.is_some_and(|track| track.keys.iter().any(|key| key.frame == frame));
+            let mut enabled = self.timeline.enabled(index);
+            if ui.button(if keyed { "◆ Update key" } else { "◇ Add key" }).on_hover_text("Key position, strength, color and on/off").clicked() {
+                self.record_edit();
+                self.timeline.set_key(index, &self.persistent_elements[index], enabled);
+            }
+            if keyed && ui.button("Remove key").clicked() {
+                self.record_edit();
+                self.timeline.remove_key(index);
+            }
+            if ui.checkbox(&mut enabled, "On").on_hover_text("Keys the element on or off from this frame").changed() {
+                self.record_edit();
+                self.timeline.set_key(index, &self.persistent_elements[index], enabled);
+            }
+            // The key at or before the playhead sets how values move to the next
+            let key = self
+                .timeline
+                .tracks
+                .iter_mut()
+                .find(|track| track.element == index)
+                .and_then(|track| track.keys.iter_mut().rev().find(|key| key.frame <= frame));
+            if let Some(key) = key {
+                egui::ComboBox::from_id_source("key_interpolation")
+                    .selected_text(key.interpolation.name())
+                    .show_ui(ui, |ui| {
+                        for option in Interpolation::ALL {
+                            ui.selectable_value(&mut key.interpolation, option, option.name());
                         }
-                    }
-                });
-            });
+                    });
+            }
         });
 
-        // Tool-specific panels - decide docking (top/bottom or side in landscape)
-        let screen_rect = ctx.screen_rect();
-        let is_landscape = screen_rect.width() >= screen_rect.height();
-        let dock_top = match self.controls_dock {
-            ControlsDockMode::Auto => is_landscape,
-            ControlsDockMode::Top => true,
-            ControlsDockMode::Bottom => false,
-        };
-        let use_side_panel = is_landscape; // prefer sidebar in landscape for full-height canvas
+        self.timeline.frame = self.timeline.frame.min(self.timeline.length.saturating_sub(1));
+        if self.timeline.frame != before {
+            self.timeline.pose(&mut self.persistent_elements);
+        }
+    }
 
-        // Show panels BEFORE CentralPanel to reserve space
-        if !self.ui_hide_controls && use_side_panel {
-            // Right side controls in landscape
+    // Replace the elements and parameters with a scene's, as an undoable
+    // edit. Its dye is kept only if the grids match.
+    fn apply_scene(&mut self, scene: &Scene) {
+        self.record_edit();
+        scene.params.apply(&mut self.simulation);
+        self.persistent_elements = scene.elements_for(self.simulation.width, self.simulation.height);
+        self.timeline = scene.timeline_for(self.simulation.width, self.simulation.height).unwrap_or_default();
+        self.timeline.pose(&mut self.persistent_elements);
+        self.select_element(None);
+        self.container = scene.container.clone();
+        self.refresh_masks();
+        if let Some(dye) = &scene.dye
+            && (scene.width, scene.height) == (self.simulation.width, self.simulation.height)
+        {
+            // Through a snapshot so an input recording sees the new dye
+            let mut snapshot = FluidSnapshot::capture(&self.simulation);
+            snapshot.dye = [dye.r.clone(), dye.g.clone(), dye.b.clone()];
+            let _ = snapshot.restore(&mut self.simulation);
+        }
+    }
+
+    // Stop recording inputs, saving what was recorded
+    fn finish_recording(&mut self) {
+        if let Some(recording) = self.simulation.recording.take() {
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Err(e) = recording.save(std::path::Path::new(&self.recording_path)) {
+                eprintln!("Failed to save recording {}: {}", self.recording_path, e);
+            }
+            // Recording can't be started in the browser
+            #[cfg(target_arch = "wasm32")]
+            let _ = recording;
+        }
+    }
+
+    // Resample the stencil images to the current grid
+    fn refresh_masks(&mut self) {
+        let (width, height) = (self.simulation.width, self.simulation.height);
+        let stencil = |img: &image::GrayImage| {
+            let mut mask = Mask::from_gray(img, width, height, self.mask_threshold);
+            if self.mask_invert {
+                mask.invert();
+            }
+            mask
+        };
+        let mut obstacles = self.obstacle_image.as_ref().map(stencil);
+        if let Some(container) = &self.container {
+            let outside = container.mask(width, height);
+            match &mut obstacles {
+                Some(mask) => mask.union(&outside),
+                None => obstacles = Some(outside),
+            }
+        }
+        self.simulation.obstacles = obstacles;
+        self.emitter_mask = self.emitter_image.as_ref().map(stencil);
+    }
+
+    fn container_menu_ui(&mut self, ui: &mut egui::Ui) {
+        let mut choice = self.container.clone();
+        ui.selectable_value(&mut choice, None, "Full grid");
+        for (name, preset) in Container::presets() {
+            ui.selectable_value(&mut choice, Some(preset), name);
+        }
+        if choice != self.container {
+            self.container = choice;
+            self.refresh_masks();
+        }
+        ui.label("Cells outside the shape are solid, like obstacles");
+    }
+
+    fn chemistry_menu_ui(&mut self, ui: &mut egui::Ui) {
+        let kind = |chemistry: Option<Chemistry>| match chemistry {
+            None => 0,
+            Some(Chemistry::Combine { .. }) => 1,
+            Some(Chemistry::GrayScott { .. }) => 2,
+        };
+        let mut choice = kind(self.simulation.chemistry);
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut choice, 0, "Off");
+            ui.selectable_value(&mut choice, 1, "A + B → C");
+            ui.selectable_value(&mut choice, 2, "Gray-Scott");
+        });
+        if choice != kind(self.simulation.chemistry) {
+            self.simulation.chemistry = match choice {
+                1 => Some(Chemistry::combine()),
+                2 => Some(Chemistry::gray_scott()),
+                _ => None,
+            };
+        }
+        match &mut self.simulation.chemistry {
+            None => {
+                ui.label("Colors only mix");
+            }
+            Some(Chemistry::Combine { a, b, product, rate }) => {
+                ui.horizontal(|ui| {
+                    dye_channel_ui(ui, "chemistry_a", a);
+                    ui.label("+");
+                    dye_channel_ui(ui, "chemistry_b", b);
+                    ui.label("→");
+                    dye_channel_ui(ui, "chemistry_product", product);
+                });
+                ui.add(egui::Slider::new(rate, 0.0..=20.0).text("Rate"))
+                    .on_hover_text("How fast the two colors turn into the product where they meet");
+            }
+            Some(Chemistry::GrayScott { substrate, activator, feed, kill, speed }) => {
+                ui.horizontal(|ui| {
+                    ui.label("Substrate");
+                    dye_channel_ui(ui, "chemistry_substrate", substrate);
+                    ui.label("Activator");
+                    dye_channel_ui(ui, "chemistry_activator", activator);
+                });
+                ui.horizontal(|ui| {
+                    for (name, preset_feed, preset_kill) in Chemistry::gray_scott_presets() {
+                        if ui.selectable_label(*feed == preset_feed && *kill == preset_kill, name).clicked() {
+                            *feed = preset_feed;
+                            *kill = preset_kill;
+                        }
+                    }
+                });
+                ui.add(egui::Slider::new(feed, 0.0..=0.1).text("Feed"))
+                    .on_hover_text("How fast the substrate is replenished");
+                ui.add(egui::Slider::new(kill, 0.0..=0.1).text("Kill"))
+                    .on_hover_text("How fast the activator dies off");
+                ui.add(egui::Slider::new(speed, 0.0..=50.0).text("Speed"))
+                    .on_hover_text("Reaction time per unit of simulated time");
+                ui.label("Paint the activator color into the substrate to seed patterns");
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn mask_menu_ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::TextEdit::singleline(&mut self.mask_path).hint_text("Black-and-white image").desired_width(180.0));
+        let path = std::path::PathBuf::from(&self.mask_path);
+        ui.horizontal(|ui| {
+            if ui.button("⬛ Obstacles").on_hover_text("White areas block the flow").clicked() {
+                match Mask::load_image(&path) {
+                    Ok(img) => self.obstacle_image = Some(img),
+                    Err(e) => eprintln!("Failed to load mask {}: {}", self.mask_path, e),
+                }
+                self.refresh_masks();
+            }
+            if ui.button("✴ Emitter").on_hover_text("White areas pour the current dye color").clicked() {
+                match Mask::load_image(&path) {
+                    Ok(img) => self.emitter_image = Some(img),
+                    Err(e) => eprintln!("Failed to load mask {}: {}", self.mask_path, e),
+                }
+                self.refresh_masks();
+            }
+        });
+        let mut changed = ui.add(egui::Slider::new(&mut self.mask_threshold, 0.0..=1.0).text("Threshold")).changed();
+        changed |= ui.checkbox(&mut self.mask_invert, "Invert").on_hover_text("Use the black areas instead").changed();
+        if changed {
+            self.refresh_masks();
+        }
+        ui.add(egui::Slider::new(&mut self.mask_emit_rate, 0.0..=1.0).text("Emit rate"));
+        if let Some(mask) = &self.simulation.obstacles {
+            ui.label(format!("Obstacles: {} cells", mask.count()));
+        }
+        if let Some(mask) = &self.emitter_mask {
+            ui.label(format!("Emitter: {} cells", mask.count()));
+        }
+        if (self.obstacle_image.is_some() || self.emitter_image.is_some()) && ui.button("Clear masks").clicked() {
+            self.obstacle_image = None;
+            self.emitter_image = None;
+            self.refresh_masks();
+        }
+    }
+
+    // A fresh grid that keeps the running simulation's parameters
+    fn resized_simulation(&self, width: usize, height: usize) -> InteractiveFluid {
+        let mut simulation = InteractiveFluid::new(width, height);
+        SceneParams::capture(&self.simulation).apply(&mut simulation);
+        simulation.resample_from(&self.simulation);
+        simulation.moving_obstacles = self.simulation.moving_obstacles.clone();
+        // Painted regions don't carry over to the new grid
+        simulation.turbulence = self.simulation.turbulence.clone().map(|turbulence| Turbulence { region: Vec::new(), ..turbulence });
+        #[cfg(feature = "spectral")]
+        if self.simulation.spectral.is_some() {
+            simulation.enable_spectral();
+        }
+        simulation
+    }
+
+    // Move to a new grid, resampling the fields and scaling the elements
+    // and their keys along, so the artwork survives the change
+    fn resize_grid(&mut self, width: usize, height: usize) {
+        let sx = width as f32 / self.simulation.width as f32;
+        let sy = height as f32 / self.simulation.height as f32;
+        // A recording only replays on the grid it was made on
+        self.finish_recording();
+        self.simulation = self.resized_simulation(width, height);
+        self.refresh_masks();
+        for obstacle in &mut self.simulation.moving_obstacles {
+            *obstacle = obstacle.rescaled(sx, sy);
+        }
+        for elem in &mut self.persistent_elements {
+            *elem = elem.rescaled(sx, sy);
+        }
+        self.timeline = self.timeline.rescaled(sx, sy);
+        self.particles.clear();
+    }
+
+    fn change_resolution(&mut self, scale: usize) {
+        if scale != self.resolution_scale && scale >= 1 && scale <= 8 {
+            self.resolution_scale = scale;
+            self.resize_grid(self.base_width * scale, self.base_height * scale);
+
+            // Reset simulation state
+            self.mouse_start_pos = None;
+            self.mouse_current_pos = None;
+            self.continuous_color_pos = None;
+
+            // Gate history and undo snapshots belong to the old grid
+            self.gates.clear();
+            self.history.clear();
+        }
+    }
+
+    // Enter or leave presentation mode, going fullscreen while presenting
+    fn set_presentation(&mut self, ctx: &egui::Context, on: bool) {
+        self.presentation = on;
+        self.trail_pos = None;
+        self.autoplaying = false;
+        self.last_input_time = ctx.input(|i| i.time);
+        // Tool overlays would otherwise stay frozen on the canvas
+        self.mouse_start_pos = None;
+        self.mouse_current_pos = None;
+        self.attractor_pos = None;
+        self.eraser_pos = None;
+        self.heat_pos = None;
+        self.vortex_pos = None;
+        self.emitter_pos = None;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(on));
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = ctx;
+            let _ = if on { self.request_fullscreen_web() } else { self.exit_fullscreen_web() };
+        }
+    }
+
+    // Presentation trail: the pointer or a touch pushes the fluid along its
+    // motion, like a finger drawn through water
+    // Pan and zoom the view by pinching with two fingers, ctrl+scrolling or
+    // dragging with the middle button. Returns whether one of these holds
+    // the canvas, so the single-pointer tools sit it out.
+    fn canvas_gestures(&mut self, ui: &egui::Ui, response: &egui::Response, canvas: egui::Rect) -> bool {
+        ui.input(|i| self.touches.update(&i.events));
+        self.view.fit(canvas);
+        if self.touches.multi() {
+            if !self.multi_finger_paint
+                && let Some((anchor, factor, translation)) = self.touches.pinch()
+            {
+                self.view.gesture(canvas, anchor, factor, translation);
+            }
+            return true;
+        }
+        if let Some(pos) = response.hover_pos() {
+            let zoom = ui.input(|i| i.zoom_delta());
+            if zoom != 1.0 {
+                self.view.gesture(canvas, pos, zoom, egui::Vec2::ZERO);
+            }
+        }
+        if response.dragged_by(egui::PointerButton::Middle) {
+            self.view.gesture(canvas, canvas.center(), 1.0, response.drag_delta());
+            return true;
+        }
+        false
+    }
+
+    // Add the current dye color under each dab, or take dye away when the
+    // color is black
+    fn paint_dye(&mut self, dabs: &[glam::Vec2], strength: f32) {
+        let dye_color = self.palette.current();
+        let blend = self.brush.blend;
+        let is_negative = blend == BlendMode::Add && dye_color.0 == 0.0 && dye_color.1 == 0.0 && dye_color.2 == 0.0;
+        let scaled = (dye_color.0 * strength, dye_color.1 * strength, dye_color.2 * strength);
+        let cells = dabs.iter().flat_map(|dab| self.brush.dab(dab.x, dab.y, self.simulation.width, self.simulation.height));
+        for (px, py, weight) in cells.collect::<Vec<_>>() {
+            let intensity = weight * strength;
+            if is_negative {
+                self.simulation.remove_dye(px, py, intensity);
+            } else if blend == BlendMode::Add {
+                self.simulation.add_dye(px, py, (dye_color.0 * intensity, dye_color.1 * intensity, dye_color.2 * intensity));
+            } else {
+                self.simulation.blend_dye(px, py, scaled, weight, blend);
+            }
+        }
+    }
+
+    fn apply_presentation_trail(&mut self, response: &egui::Response, rect: egui::Rect, cell_size: f32) {
+        let Some(pos) = response.hover_pos() else {
+            self.trail_pos = None;
+            return;
+        };
+        if let Some(last) = self.trail_pos {
+            let delta = (pos - last) / cell_size;
+            if delta.length() > 0.0 && self.trail_strength > 0.0 {
+                let x = ((pos.x - rect.left()) / cell_size) as usize;
+                let y = ((pos.y - rect.top()) / cell_size) as usize;
+                let force = glam::Vec2::new(delta.x, delta.y) * self.trail_strength;
+                self.simulation.add_force(x, y, force, 3.0 * self.resolution_scale as f32);
+            }
+        }
+        self.trail_pos = Some(pos);
+    }
+
+    // Wipe parts of the canvas separately, so placed elements can keep
+    // running on a clean fluid. Each is one undo step
+    fn clear_menu_ui(&mut self, ui: &mut egui::Ui) {
+        if ui.button("Dye").on_hover_text("Remove all dye, keeping the flow and elements").clicked() {
+            self.record_edit();
+            self.simulation.clear_dye();
+            ui.close_menu();
+        }
+        if ui.button("Velocity").on_hover_text("Still the fluid and cool it to ambient, keeping the dye").clicked() {
+            self.record_edit();
+            self.simulation.clear_velocity();
+            ui.close_menu();
+        }
+        let has_elements = !self.persistent_elements.is_empty();
+        if ui.add_enabled(has_elements, egui::Button::new("Elements")).on_hover_text("Remove placed elements and their keys").clicked() {
+            self.record_edit();
+            self.clear_elements();
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui.button("Reset all").on_hover_text("Dye, flow, elements and tracer particles").clicked() {
+            self.record_edit();
+            self.simulation.clear();
+            self.clear_elements();
+            self.particles.clear();
+            ui.close_menu();
+        }
+    }
+
+    fn clear_elements(&mut self) {
+        self.persistent_elements.clear();
+        self.timeline.tracks.clear();
+        self.select_element(None);
+    }
+
+    // Start over from one of the built-in scenarios, like Reset all but
+    // with flow and dye already in motion
+    fn presets_menu_ui(&mut self, ui: &mut egui::Ui) {
+        for scenario in Scenario::ALL {
+            if ui.button(scenario.name()).on_hover_text(scenario.description()).clicked() {
+                self.record_edit();
+                // A recording can't replay the scenario's direct setup
+                self.finish_recording();
+                scenario.setup(&mut self.simulation);
+                self.clear_elements();
+                self.particles.clear();
+                ui.close_menu();
+            }
+        }
+        ui.label("Sets the edges and dye weights the scenario needs");
+    }
+
+    fn png_menu_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Save the canvas as shown");
+        ui.add_enabled(!self.show_pressure, egui::Checkbox::new(&mut self.png_smooth, "Smooth"))
+            .on_hover_text("Bicubic between cells instead of square blocks, for print-quality stills");
+        ui.add_enabled(self.png_smooth && !self.show_pressure, egui::Checkbox::new(&mut self.png_bloom, "Bloom"))
+            .on_hover_text("Glow around the brightest dye");
+        if self.png_smooth && self.png_bloom && !self.show_pressure {
+            let bloom = &mut self.png_bloom_settings;
+            ui.add(egui::Slider::new(&mut bloom.threshold, 0.0..=5.0).text("Threshold").step_by(0.05));
+            ui.add(egui::Slider::new(&mut bloom.strength, 0.0..=3.0).text("Strength").step_by(0.05));
+            ui.add(egui::Slider::new(&mut bloom.radius, 0.5..=12.0).text("Spread").step_by(0.5));
+        }
+        ui.separator();
+        for scale in [1, 2, 4, 8] {
+            let (width, height) = (self.simulation.width * scale, self.simulation.height * scale);
+            let label = if scale == 1 { format!("Grid size ({}×{})", width, height) } else { format!("{}x ({}×{})", scale, width, height) };
+            if ui.button(label).clicked() {
+                if let Err(e) = self.export_png(scale as u32) {
+                    eprintln!("Failed to export PNG: {}", e);
+                }
+                ui.close_menu();
+            }
+        }
+    }
+
+    // The canvas in its current view at `scale` pixels per cell, written
+    // next to the app on desktop and downloaded on the web
+    fn export_png(&self, scale: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let (width, height) = (self.simulation.width as u32 * scale, self.simulation.height as u32 * scale);
+        let png = if self.png_smooth && !self.show_pressure {
+            let still = StillExport {
+                scale,
+                bloom: self.png_bloom.then_some(self.png_bloom_settings),
+                colormap: self.dye_colormap.clone(),
+            };
+            still.png(&self.simulation)?
+        } else {
+            let mut exporter = ImageExporter::new(width, height);
+            let mode = if self.show_pressure {
+                RenderMode::Pressure
+            } else if let Some(colormap) = &self.dye_colormap {
+                exporter.set_colormap(colormap.clone());
+                RenderMode::DyeIntensity
+            } else {
+                RenderMode::Dye
+            };
+            exporter.canvas_png(&self.simulation, mode)?
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let seconds = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            std::fs::write(format!("itsliquid_{}.png", seconds), png)?;
+        }
+        #[cfg(target_arch = "wasm32")]
+        download_web(&format!("itsliquid_{}.png", self.frame_count), "image/png", &png)
+            .map_err(|e| format!("download failed: {:?}", e))?;
+        Ok(())
+    }
+
+    fn gallery_menu_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(self.session.summary());
+        ui.add(egui::TextEdit::singleline(&mut self.gallery_caption).hint_text("Caption").desired_width(180.0));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.checkbox(&mut self.gallery_qr, "QR code").on_hover_text("Link back to the scene from the card");
+            if self.gallery_qr {
+                ui.add(egui::TextEdit::singleline(&mut self.gallery_link).hint_text("Link (default: share link)").desired_width(180.0));
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("💾 Export card").clicked() {
+            let caption = self.gallery_caption.trim().to_string();
+            let link = match self.gallery_link.trim() {
+                "" => self.share_url(),
+                link => Some(link.to_string()),
+            };
+            let card = GalleryCard {
+                caption: (!caption.is_empty()).then_some(caption),
+                link: link.filter(|_| self.gallery_qr),
+                ..GalleryCard::default()
+            };
+            let seconds = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let path = format!("gallery_{}.png", seconds);
+            if let Err(e) = card.save(&self.simulation, &self.session, std::path::Path::new(&path)) {
+                eprintln!("Failed to export gallery card: {}", e);
+            }
+            ui.close_menu();
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            // No file to write to on the web
+            let _disabled = ui.add_enabled(false, egui::Button::new("💾 Export card")).on_hover_text("Available on the desktop version");
+        }
+
+        if ui.button("New session").on_hover_text("Reset the counters for the next participant").clicked() {
+            self.session.reset();
+        }
+    }
+
+    // Color every cell and upload the result, reusing the texture so only
+    // its contents change between frames
+    fn update_canvas_texture(&mut self, ctx: &egui::Context) {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("canvas_texture").entered();
+        let sim = &self.simulation;
+
+        let mut pixels: Vec<egui::Color32> = if self.show_pressure {
+            // Pressure view is normalized to the largest magnitude this frame
+            let max_abs = sim.pressure.iter().fold(0.0f32, |m, p| m.max(p.abs()));
+            let scale = if max_abs > 0.0 { 1.0 / max_abs } else { 0.0 };
+            sim.pressure
+                .iter()
+                .map(|&p| {
+                    let [r, g, b] = diverging_color(p * scale);
+                    egui::Color32::from_rgb(r, g, b)
+                })
+                .collect()
+        } else {
+            let dye = sim.dye_r.iter().zip(&sim.dye_g).zip(&sim.dye_b);
+            dye.map(|((&r_raw, &g_raw), &b_raw)| {
+                if let Some(colormap) = &self.dye_colormap {
+                    let [r, g, b] = colormap.map(dye_intensity(r_raw, g_raw, b_raw));
+                    egui::Color32::from_rgb(r, g, b)
+                } else {
+                    // Reinhard tone mapping x / (1 + x) maps HDR dye [0, ∞) to [0, 1)
+                    let r = (r_raw / (1.0 + r_raw)).max(0.0);
+                    let g = (g_raw / (1.0 + g_raw)).max(0.0);
+                    let b = (b_raw / (1.0 + b_raw)).max(0.0);
+                    egui::Color32::from_rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+                }
+            })
+            .collect()
+        };
+
+        // Painted turbulence tinted while its tool is out
+        if let Some(turbulence) = sim.turbulence.as_ref().filter(|t| t.region.len() == pixels.len())
+            && self.selected_tool == Tool::Turbulence
+            && !turbulence.everywhere
+        {
+            for (pixel, &weight) in pixels.iter_mut().zip(&turbulence.region) {
+                let tint = (weight * 60.0) as u8;
+                *pixel = egui::Color32::from_rgb(pixel.r().saturating_add(tint / 2), pixel.g(), pixel.b().saturating_add(tint));
+            }
+        }
+
+        // Painted viscosity tinted while its tool is out: amber where thick,
+        // blue where runny
+        if self.selected_tool == Tool::Viscosity && sim.viscosity_field.len() == pixels.len() {
+            for (pixel, &factor) in pixels.iter_mut().zip(&sim.viscosity_field) {
+                let tint = (factor.log10().abs() * 30.0).min(60.0) as u8;
+                *pixel = if factor > 1.0 {
+                    egui::Color32::from_rgb(pixel.r().saturating_add(tint), pixel.g().saturating_add(tint / 2), pixel.b())
+                } else {
+                    egui::Color32::from_rgb(pixel.r(), pixel.g(), pixel.b().saturating_add(tint))
+                };
+            }
+        }
+
+        // Obstacles drawn solid over whatever field is shown
+        if let Some(mask) = &sim.obstacles {
+            for (pixel, _) in pixels.iter_mut().zip(&mask.cells).filter(|(_, solid)| **solid) {
+                *pixel = egui::Color32::from_gray(90);
+            }
+        }
+        for obstacle in &sim.moving_obstacles {
+            let shade = if obstacle.density.is_some() { 200 } else { 150 };
+            for idx in obstacle.cells(sim.width, sim.height) {
+                if let Some(pixel) = pixels.get_mut(idx) {
+                    *pixel = egui::Color32::from_gray(shade);
+                }
+            }
+        }
+
+        let image = egui::ColorImage {
+            size: [sim.width, sim.height],
+            pixels,
+        };
+        match &mut self.canvas_texture {
+            Some(texture) => texture.set(image, egui::TextureOptions::NEAREST),
+            None => self.canvas_texture = Some(ctx.load_texture("fluid", image, egui::TextureOptions::NEAREST)),
+        }
+    }
+
+    fn particle_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Count:");
+            ui.add(egui::Slider::new(&mut self.emit_count, 1..=200).show_value(true));
+            ui.label("Radius:");
+            ui.add(egui::Slider::new(&mut self.emit_radius, 0.5..=20.0).show_value(true).step_by(0.5));
+        });
+        ui.horizontal_wrapped(|ui| {
+            egui::ComboBox::from_label("Integrator")
+                .selected_text(format!("{:?}", self.particles.integrator))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.particles.integrator, Integrator::Euler, "Euler");
+                    ui.selectable_value(&mut self.particles.integrator, Integrator::Rk2, "Rk2");
+                    ui.selectable_value(&mut self.particles.integrator, Integrator::Rk4, "Rk4");
+                });
+
+            // 0 means particles live until cleared or pushed out by max_particles
+            let mut lifetime = self.particles.lifetime.unwrap_or(0.0);
+            ui.label("Lifetime:");
+            if ui.add(egui::Slider::new(&mut lifetime, 0.0..=100.0).show_value(true).step_by(0.5)).changed() {
+                self.particles.lifetime = if lifetime > 0.0 { Some(lifetime) } else { None };
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Seed grid").clicked() {
+                let color = self.palette.current();
+                self.particles.seed_grid(self.simulation.width, self.simulation.height, 4.0, color);
+                self.show_particles = true;
+            }
+            if ui.button("Clear particles").clicked() {
+                self.particles.clear();
+            }
+            ui.label(format!("{} / {}", self.particles.len(), self.particles.max_particles));
+        });
+    }
+
+    // Dye swatches with the color picker for the selected one. Right-click a
+    // swatch to remove it, + adds a copy of the selected color to edit
+    fn palette_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Color:");
+            let mut remove = None;
+            for (i, &color) in self.palette.colors.iter().enumerate() {
+                let [r, g, b] = srgb(color);
+                let size = egui::Vec2::new(26.0, 26.0);
+                let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+                // Always draw a gray border so black/white are visible
+                ui.painter().rect_stroke(rect, 1.5, egui::Stroke::new(1.2, egui::Color32::GRAY));
+                ui.painter().rect_filled(rect.shrink(2.0), 1.5, egui::Color32::from_rgb(r, g, b));
+                if self.palette.current == i {
+                    ui.painter().rect_stroke(rect.shrink(0.5), 1.5, egui::Stroke::new(2.0, egui::Color32::WHITE));
+                }
+                if response.clicked() {
+                    self.palette.current = i;
+                }
+                if response.secondary_clicked() {
+                    remove = Some(i);
+                }
+            }
+            if let Some(i) = remove {
+                self.palette.remove(i);
+            }
+            if self.palette.colors.len() < palette::MAX_COLORS
+                && ui.button("+").on_hover_text("Add a slot with the selected color").clicked()
+            {
+                self.palette.insert(self.palette.current());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Edit:");
+            // Kept apart from the palette so hue survives dragging to gray
+            if self.picker_color.to_srgb() != srgb(self.palette.current()) {
+                self.picker_color = egui::ecolor::Hsva::from_srgb(srgb(self.palette.current()));
+            }
+            let response =
+                egui::color_picker::color_edit_button_hsva(ui, &mut self.picker_color, egui::color_picker::Alpha::Opaque);
+            if response.changed() {
+                let [r, g, b] = self.picker_color.to_srgb();
+                self.palette.set_current((r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0));
+            }
+            if ui.small_button("Reset palette").clicked() {
+                self.palette = Palette::default();
+            }
+        });
+    }
+
+    // The eyedropper's brush color as hex: seeded from the sample, editable,
+    // copied to the clipboard, and made the dye color in one click
+    fn sample_hex_ui(&mut self, ui: &mut egui::Ui) {
+        let parsed = palette::parse_hex(&self.sample_hex);
+        ui.label("Hex:");
+        ui.add(egui::TextEdit::singleline(&mut self.sample_hex).desired_width(80.0).text_color_opt(
+            parsed.is_none().then_some(ui.visuals().error_fg_color),
+        ))
+        .on_hover_text("Brush color for this sample at full brightness; edit it before using");
+        if ui.small_button("📋").on_hover_text("Copy hex").clicked() {
+            ui.output_mut(|o| o.copied_text = self.sample_hex.clone());
+        }
+        if let Some(color) = parsed {
+            let [r, g, b] = srgb(color);
+            let (rect, _) = ui.allocate_exact_size(egui::Vec2::new(18.0, 18.0), egui::Sense::hover());
+            ui.painter().rect_stroke(rect, 1.5, egui::Stroke::new(1.2, egui::Color32::GRAY));
+            ui.painter().rect_filled(rect.shrink(2.0), 1.5, egui::Color32::from_rgb(r, g, b));
+        }
+        if ui
+            .add_enabled(parsed.is_some(), egui::Button::new("🖌 Use"))
+            .on_hover_text("Add this color to the palette and paint with it")
+            .clicked()
+            && let Some(color) = parsed
+        {
+            self.palette.add(color);
+            self.selected_tool = Tool::Dye;
+        }
+    }
+
+    fn brush_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Size:");
+            ui.add(egui::Slider::new(&mut self.brush.radius, 0.5..=30.0).show_value(true).step_by(0.5))
+                .on_hover_text("Brush radius in grid cells");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Falloff:");
+            for falloff in Falloff::ALL {
+                ui.selectable_value(&mut self.brush.falloff, falloff, falloff.name());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Spacing:");
+            ui.add(egui::Slider::new(&mut self.brush.spacing, 0.05..=2.0).show_value(true).step_by(0.05))
+                .on_hover_text("Distance between dabs along a drag, as a fraction of the size");
+        });
+        ui.checkbox(&mut self.brush.pressure, "Stylus pressure")
+            .on_hover_text("Pen pressure scales the intensity on devices that report it");
+        ui.checkbox(&mut self.multi_finger_paint, "Multi-finger painting")
+            .on_hover_text("Each finger paints its own dye stroke, instead of two fingers panning and zooming");
+    }
+
+    fn eraser_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Erase:");
+            ui.selectable_value(&mut self.eraser_dye, false, "Elements");
+            ui.selectable_value(&mut self.eraser_dye, true, "Dye")
+                .on_hover_text("Clear fluid color under the brush, leaving placed elements");
+        });
+        if self.eraser_dye {
+            ui.horizontal(|ui| {
+                ui.label("Fade:");
+                ui.add(egui::Slider::new(&mut self.eraser_fade, 0.01..=1.0).show_value(true).step_by(0.01))
+                    .on_hover_text("Share of the dye taken per dab; 1 clears it outright");
+            });
+            ui.checkbox(&mut self.eraser_velocity, "Calm the flow too")
+                .on_hover_text("Fade the velocity under the brush along with the dye");
+        }
+        self.brush_panel_ui(ui);
+    }
+
+    // Dye totals, spread and centers in the canvas corner, with a ring on
+    // each channel's center of mass
+    fn stats_overlay(&self, painter: &egui::Painter, canvas: egui::Rect, rect: egui::Rect, cell_size: f32) {
+        let metrics = FluidMetrics::analyze_interactive(&self.simulation, self.frame_count);
+        let mut text = format!(
+            "Frame    {}\nDye      {:.2}\nEnergy   {:.2}\nMax vel  {:.2}",
+            metrics.frame, metrics.total_mass, metrics.total_kinetic_energy, metrics.max_velocity
+        );
+        if let Some(dye) = &metrics.dye {
+            let colors = [egui::Color32::from_rgb(255, 90, 90), egui::Color32::from_rgb(90, 255, 90), egui::Color32::from_rgb(110, 140, 255)];
+            for (c, name) in ["R", "G", "B"].iter().enumerate() {
+                match dye.center_of_mass[c] {
+                    Some((x, y)) => {
+                        text += &format!("\n{}  {:>8.2} @ {:.0},{:.0}", name, dye.total[c], x, y);
+                        let center = egui::pos2(rect.left() + x * cell_size, rect.top() + y * cell_size);
+                        painter.circle_stroke(center, 5.0, egui::Stroke::new(1.5, colors[c]));
+                    }
+                    None => text += &format!("\n{}  {:>8.2}", name, dye.total[c]),
+                }
+            }
+            text += &format!("\nSpread   {:.2} bits", dye.luminance_entropy);
+        }
+        hud_panel(painter, canvas.left_top() + egui::vec2(8.0, 8.0), egui::Align2::LEFT_TOP, text);
+    }
+
+    // Frame rate, solver milliseconds per stage and grid memory in the
+    // other corner
+    fn perf_overlay(&self, painter: &egui::Painter, canvas: egui::Rect) {
+        let text = format!(
+            "{}\npressure {} sweeps\nmemory   {}\ngrid     {}x{}",
+            self.perf.summary(),
+            self.simulation.pressure_iterations_used,
+            format_bytes(self.simulation.memory_bytes()),
+            self.simulation.width,
+            self.simulation.height
+        );
+        hud_panel(painter, canvas.right_top() + egui::vec2(-8.0, 8.0), egui::Align2::RIGHT_TOP, text);
+    }
+
+    fn attractor_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Mode:");
+            ui.selectable_value(&mut self.attractor_repel, false, "Attract");
+            ui.selectable_value(&mut self.attractor_repel, true, "Repel")
+                .on_hover_text("Push fluid outward, for explosions and fountains");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Radius:");
+            ui.add(egui::Slider::new(&mut self.attractor_radius, 1.0..=200.0).show_value(true).step_by(1.0));
+        });
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.label("Strength:");
+            ui.add(egui::Slider::new(&mut self.attractor_strength, 0.1..=100.0).show_value(true).step_by(0.1));
+        });
+        if !self.attractor_repel {
+            ui.checkbox(&mut self.attractor_absorb, "Absorb dye").on_hover_text("Dye drawn into the center disappears");
+        }
+    }
+
+    fn vortex_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Spin:");
+            ui.selectable_value(&mut self.vortex_clockwise, false, "⟲ Counter-clockwise");
+            ui.selectable_value(&mut self.vortex_clockwise, true, "⟳ Clockwise");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Radius:");
+            ui.add(egui::Slider::new(&mut self.vortex_radius, 2.0..=60.0).show_value(true).step_by(0.5));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Strength:");
+            ui.add(egui::Slider::new(&mut self.vortex_strength, 0.1..=20.0).show_value(true).step_by(0.1));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Falloff:");
+            for falloff in Falloff::ALL {
+                ui.selectable_value(&mut self.vortex_falloff, falloff, falloff.name());
+            }
+        });
+    }
+
+    // Signed strength for the vortex tool: positive turns clockwise
+    fn vortex_spin(&self) -> f32 {
+        if self.vortex_clockwise { self.vortex_strength } else { -self.vortex_strength }
+    }
+
+    fn fan_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Drag to draw a strip that holds a steady current");
+        ui.horizontal(|ui| {
+            ui.label("Direction:");
+            ui.selectable_value(&mut self.fan_along, false, "Across")
+                .on_hover_text("Blow to the right of the drawn direction, like a fan");
+            ui.selectable_value(&mut self.fan_along, true, "Along")
+                .on_hover_text("Carry fluid along the drawn direction, like a conveyor");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Width:");
+            ui.add(egui::Slider::new(&mut self.fan_width, 0.5..=20.0).show_value(true).step_by(0.5));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Speed:");
+            ui.add(egui::Slider::new(&mut self.fan_speed, 0.5..=50.0).show_value(true).step_by(0.5));
+        });
+    }
+
+    fn obstacle_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Press to drop an obstacle, or drag one through the fluid to stir it");
+        ui.horizontal(|ui| {
+            ui.label("Shape:");
+            ui.selectable_value(&mut self.obstacle_paddle, false, "Disc");
+            ui.selectable_value(&mut self.obstacle_paddle, true, "Paddle");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Size:");
+            ui.add(egui::Slider::new(&mut self.obstacle_size, 1.0..=40.0).show_value(true).step_by(0.5));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Spin:");
+            ui.add(egui::Slider::new(&mut self.obstacle_spin, -5.0..=5.0).show_value(true).step_by(0.1))
+                .on_hover_text("Turns per unit time for new obstacles, in radians; positive is clockwise");
+        });
+        ui.checkbox(&mut self.obstacle_floating, "Floating")
+            .on_hover_text("New obstacles drift with the flow and can be thrown; drag one to move it");
+        if self.obstacle_floating {
+            ui.horizontal(|ui| {
+                ui.label("Density:");
+                ui.add(egui::Slider::new(&mut self.obstacle_density, 0.05..=10.0).logarithmic(true).show_value(true))
+                    .on_hover_text("Weight against the fluid it covers: light bodies follow every eddy, heavy ones coast");
+            });
+        }
+        let count = self.simulation.moving_obstacles.len();
+        if count > 0 && ui.button(format!("Remove {} obstacles", count)).clicked() {
+            self.simulation.moving_obstacles.clear();
+            self.dragged_obstacle = None;
+            self.obstacle_target = None;
+        }
+    }
+
+    // A new moving obstacle with the tool's settings
+    fn new_obstacle(&self, center: glam::Vec2) -> MovingObstacle {
+        let shape = if self.obstacle_paddle {
+            ObstacleShape::Paddle { length: 2.0 * self.obstacle_size, width: 0.4 * self.obstacle_size }
+        } else {
+            ObstacleShape::Disc { radius: self.obstacle_size }
+        };
+        let density = self.obstacle_floating.then_some(self.obstacle_density);
+        MovingObstacle { spin: self.obstacle_spin, density, ..MovingObstacle::new(shape, center) }
+    }
+
+    fn viscosity_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Paint where the fluid is thick or runny");
+        ui.horizontal(|ui| {
+            ui.label("Paint:");
+            ui.selectable_value(&mut self.viscosity_target, 10.0, "Thick");
+            ui.selectable_value(&mut self.viscosity_target, 0.1, "Runny");
+            ui.selectable_value(&mut self.viscosity_target, 1.0, "Normal");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Factor:");
+            let (min, max) = VISCOSITY_FACTOR_RANGE;
+            ui.add(egui::Slider::new(&mut self.viscosity_target, min..=max).logarithmic(true).show_value(true))
+                .on_hover_text("Times the Simulation panel's viscosity where the brush paints");
+        });
+        if !self.simulation.viscosity_field.is_empty() && ui.button("Clear painted").clicked() {
+            self.simulation.viscosity_field.clear();
+        }
+        ui.separator();
+        self.brush_panel_ui(ui);
+    }
+
+    fn turbulence_panel_ui(&mut self, ui: &mut egui::Ui) {
+        let mut on = self.simulation.turbulence.is_some();
+        if ui.checkbox(&mut on, "Stir the fluid").on_hover_text("Curl noise keeps the fluid moving on its own").changed() {
+            if on {
+                self.simulation.turbulence = Some(self.parked_turbulence.take().unwrap_or_default());
+            } else {
+                self.parked_turbulence = self.simulation.turbulence.take();
+            }
+        }
+        if let Some(turbulence) = &mut self.simulation.turbulence {
+            ui.horizontal(|ui| {
+                ui.label("Where:");
+                ui.selectable_value(&mut turbulence.everywhere, true, "Everywhere");
+                ui.selectable_value(&mut turbulence.everywhere, false, "Painted regions");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Scale:");
+                ui.add(egui::Slider::new(&mut turbulence.scale, 2.0..=64.0).logarithmic(true).show_value(true));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Strength:");
+                ui.add(egui::Slider::new(&mut turbulence.strength, 0.0..=30.0).show_value(true).step_by(0.1));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Speed:");
+                ui.add(egui::Slider::new(&mut turbulence.speed, 0.0..=5.0).show_value(true).step_by(0.05))
+                    .on_hover_text("How fast the swirls change; 0 freezes them");
+            });
+            if turbulence.has_region() && ui.button("Clear painted").clicked() {
+                turbulence.region.clear();
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Paint:");
+            ui.selectable_value(&mut self.turbulence_erase, false, "Add");
+            ui.selectable_value(&mut self.turbulence_erase, true, "Erase");
+        });
+        self.brush_panel_ui(ui);
+    }
+
+    #[cfg(all(feature = "control", not(target_arch = "wasm32")))]
+    fn apply_control(&mut self, control: Control) {
+        match control {
+            Control::DyeColor(color) => self.palette.set_current(color),
+            Control::ForceIntensity(intensity) => self.force_intensity = intensity,
+            Control::AttractorStrength(strength) => self.attractor_strength = strength,
+            Control::Paused(paused) => self.paused = paused,
+            Control::Clear => {
+                self.record_edit();
+                self.simulation.clear();
+            }
+        }
+    }
+
+    fn heat_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Amount:");
+            ui.add(egui::Slider::new(&mut self.heat_amount, -10.0..=10.0).show_value(true).step_by(0.1))
+                .on_hover_text("Negative values cool the fluid so it sinks");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Radius:");
+            ui.add(egui::Slider::new(&mut self.heat_radius, 1.0..=20.0).show_value(true).step_by(0.5));
+        });
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.label("Buoyancy:");
+            ui.add(egui::Slider::new(&mut self.simulation.buoyancy, 0.0..=5.0).show_value(true).step_by(0.05));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Cooling:");
+            ui.add(egui::Slider::new(&mut self.simulation.cooling, 0.0..=1.0).show_value(true).step_by(0.01));
+        });
+        ui.add_space(4.0);
+        ui.checkbox(&mut self.smoke_mode, "Smoke mode (dye carries heat)");
+        ui.collapsing("Dye weight", |ui| {
+            for (density, name) in self.simulation.dye_density.iter_mut().zip(["Red", "Green", "Blue"]) {
+                ui.add(egui::Slider::new(density, -5.0..=5.0).step_by(0.1).text(name))
+                    .on_hover_text("Positive colors are heavy and sink through the rest, negative ones float up");
+            }
+            if ui.button("Passive dye").clicked() {
+                self.simulation.dye_density = [0.0; 3];
+            }
+        });
+    }
+
+    fn gate_panel_ui(&mut self, ui: &mut egui::Ui) {
+        if self.gates.is_empty() {
+            ui.label("Drag on the canvas to draw a gate");
+            return;
+        }
+
+        for gate in &self.gates.gates {
+            let latest = gate.latest().copied().unwrap_or_default();
+            ui.label(egui::RichText::new(&gate.name).strong());
+            ui.label(format!("Volume: {:+.4}/frame (total {:+.3})", latest.volume, gate.total_volume));
+            ui.label(format!(
+                "Dye: ({:+.3}, {:+.3}, {:+.3})/frame",
+                latest.dye[0], latest.dye[1], latest.dye[2]
+            ));
+            ui.label(format!(
+                "Dye total: ({:+.2}, {:+.2}, {:+.2})",
+                gate.total_dye[0], gate.total_dye[1], gate.total_dye[2]
+            ));
+
+            // Sparkline of recent volume flux, symmetric around zero
+            let (rect, _response) = ui.allocate_exact_size(egui::Vec2::new(200.0, 32.0), egui::Sense::hover());
+            let painter = ui.painter();
+            painter.rect_stroke(rect, 1.0, egui::Stroke::new(1.0, egui::Color32::DARK_GRAY));
+            painter.line_segment(
+                [rect.left_center(), rect.right_center()],
+                egui::Stroke::new(1.0, egui::Color32::DARK_GRAY),
+            );
+            let max_abs = gate.history.iter().map(|f| f.volume.abs()).fold(0.0f32, f32::max);
+            if gate.history.len() >= 2 && max_abs > 0.0 {
+                let n = gate.history.len() - 1;
+                let points: Vec<egui::Pos2> = gate
+                    .history
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| {
+                        egui::Pos2::new(
+                            rect.left() + rect.width() * i as f32 / n as f32,
+                            rect.center().y - f.volume / max_abs * rect.height() * 0.5,
+                        )
+                    })
+                    .collect();
+                painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 230, 80))));
+            }
+            ui.add_space(6.0);
+        }
+
+        // Mean time for dye to get from each gate to the next
+        for pair in self.gates.gates.windows(2) {
+            if let Some(frames) = self.gates.residence_frames(&pair[0].name, &pair[1].name) {
+                ui.label(format!("{} → {} residence: {:.1} frames", pair[0].name, pair[1].name, frames));
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Reset totals").clicked() {
+                self.gates.reset_totals();
+            }
+            if ui.button("Clear gates").clicked() {
+                self.gates.clear();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.button("Export CSV").clicked()
+                && let Err(e) = self.gates.export_csv(std::path::Path::new("gates.csv"))
+            {
+                eprintln!("Failed to export gates: {}", e);
+            }
+        });
+    }
+}
+
+// What the second native window shows
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum DetachedView {
+    // Just the canvas, for a projector or second monitor
+    Canvas,
+    // Field statistics and gate plots
+    Analysis,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+const DETACHED_VIEW_KEY: &str = "detached_view";
+
+// Hosted web build that desktop share links and QR codes point to
+#[cfg(not(target_arch = "wasm32"))]
+const SHARE_BASE_URL: &str = "https://hypervolu.me/~erik/itsliquid/";
+
+#[cfg(not(target_arch = "wasm32"))]
+const SHARE_BASE_URL_KEY: &str = "share_base_url";
+
+const CALIBRATION_KEY: &str = "calibration";
+
+// Touch screens get the phone preset; the browser reports them, native
+// builds assume mouse and keyboard
+#[cfg(target_arch = "wasm32")]
+fn touch_device() -> bool {
+    web_sys::window().is_some_and(|window| window.navigator().max_touch_points() > 0)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn touch_device() -> bool {
+    false
+}
+
+// Frames of statistics kept for the analysis window's plots
+#[cfg(not(target_arch = "wasm32"))]
+const STATS_HISTORY: usize = 600;
+
+// Undo keeps up to this many edits, fewer on grids where that would pass
+// the memory budget
+const MAX_UNDO_DEPTH: usize = 32;
+const UNDO_BUDGET_BYTES: usize = 128 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum ControlsDockMode {
+    Auto,
+    Top,
+    Bottom,
+}
+
+// Look of the panels; System follows the light or dark setting of the OS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum Theme {
+    System,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    const ALL: [Theme; 3] = [Theme::System, Theme::Dark, Theme::Light];
+
+    fn name(self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Dark => "🌙 Dark",
+            Theme::Light => "☀ Light",
+        }
+    }
+
+    fn visuals(self, system: Option<eframe::Theme>) -> egui::Visuals {
+        match self {
+            Theme::System => system.unwrap_or(eframe::Theme::Dark).egui_visuals(),
+            Theme::Dark => egui::Visuals::dark(),
+            Theme::Light => egui::Visuals::light(),
+        }
+    }
+}
+
+const UI_STATE_KEY: &str = "ui_state";
+
+// How painted dye mixes with what's there, and the opacity for Over
+fn blend_mode_ui(ui: &mut egui::Ui, blend: &mut BlendMode) {
+    ui.horizontal(|ui| {
+        ui.label("Blend:");
+        for mode in BlendMode::ALL {
+            if ui.selectable_label(blend.same_kind(mode), mode.name()).clicked() && !blend.same_kind(mode) {
+                *blend = mode;
+            }
+        }
+    });
+    if let BlendMode::Over { opacity } = blend {
+        ui.horizontal(|ui| {
+            ui.label("Opacity:");
+            ui.add(egui::Slider::new(opacity, 0.0..=1.0).show_value(true).step_by(0.01));
+        });
+    }
+}
+
+// Speed in real time, or a fixed number of steps per frame for
+// fast-forwarding; both apps show it beside their pause button
+pub(crate) fn step_pace_ui(ui: &mut egui::Ui, clock: &mut StepClock) {
+    let mut fast = clock.per_frame.is_some();
+    ui.checkbox(&mut fast, "⏩").on_hover_text("Run a fixed number of steps every frame, as fast as the machine goes");
+    match (fast, clock.per_frame) {
+        (true, None) => clock.per_frame = Some(4),
+        (false, Some(_)) => clock.per_frame = None,
+        _ => {}
+    }
+    if let Some(steps) = &mut clock.per_frame {
+        ui.add(egui::Slider::new(steps, 1..=32).text("Steps/frame"));
+    } else {
+        ui.add(egui::Slider::new(&mut clock.speed, 0.01..=4.0).logarithmic(true).text("Speed"))
+            .on_hover_text("Below 1 is slow motion; the flow runs at the same speed at any frame rate");
+    }
+}
+
+// Rate, pulses and lifetime of a dye source, in the fluid's time units
+fn emission_ui(ui: &mut egui::Ui, emission: &mut Emission) {
+    ui.horizontal(|ui| {
+        let mut limited = emission.rate.is_some();
+        ui.checkbox(&mut limited, "Rate:")
+            .on_hover_text("Dye per unit of time, the same at any timestep; off adds the intensity every step");
+        if limited != emission.rate.is_some() {
+            emission.rate = limited.then_some(10.0);
+        }
+        if let Some(rate) = &mut emission.rate {
+            ui.add(egui::Slider::new(rate, 0.1..=100.0).logarithmic(true).show_value(true));
+        }
+    });
+    ui.horizontal(|ui| {
+        let mut pulsing = emission.period > 0.0;
+        ui.checkbox(&mut pulsing, "Pulse:").on_hover_text("Emit for part of each cycle");
+        if pulsing != (emission.period > 0.0) {
+            emission.period = if pulsing { 2.0 } else { 0.0 };
+        }
+        if pulsing {
+            ui.add(egui::Slider::new(&mut emission.period, 0.1..=20.0).logarithmic(true).text("Period"));
+        }
+    });
+    if emission.period > 0.0 {
+        ui.add(egui::Slider::new(&mut emission.duty, 0.05..=1.0).text("On share"));
+    }
+    ui.horizontal(|ui| {
+        let mut expires = emission.lifetime.is_some();
+        ui.checkbox(&mut expires, "Lifetime:").on_hover_text("Remove the source once it has run this long");
+        if expires != emission.lifetime.is_some() {
+            // Counted from now, so switching it on never removes the source at once
+            emission.lifetime = expires.then_some(10.0);
+            emission.age = 0.0;
+        }
+        if let Some(lifetime) = &mut emission.lifetime {
+            ui.add(egui::Slider::new(lifetime, 0.1..=120.0).logarithmic(true).show_value(true));
+            ui.label(format!("{:.1} left", (*lifetime - emission.age).max(0.0)));
+        }
+    });
+}
+
+// Which of the red, green and blue dye channels a reaction uses
+fn dye_channel_ui(ui: &mut egui::Ui, id: &str, channel: &mut usize) {
+    const NAMES: [&str; 3] = ["Red", "Green", "Blue"];
+    egui::ComboBox::from_id_source(id)
+        .width(70.0)
+        .selected_text(NAMES[(*channel).min(2)])
+        .show_ui(ui, |ui| {
+            for (c, name) in NAMES.iter().enumerate() {
+                ui.selectable_value(channel, c, *name);
+            }
+        });
+}
+
+/// Monospace text on a dark rounded box, placed by `align` at `anchor`; the
+/// apps' canvas overlays.
+pub(crate) fn hud_panel(painter: &egui::Painter, anchor: egui::Pos2, align: egui::Align2, text: String) {
+    let galley = painter.layout_no_wrap(text, egui::FontId::monospace(11.0), egui::Color32::WHITE);
+    let rect = align.anchor_size(anchor, galley.size());
+    painter.rect_filled(rect.expand(4.0), 4.0, egui::Color32::from_black_alpha(160));
+    painter.galley(rect.min, galley, egui::Color32::WHITE);
+}
+
+// Palette colors are display values in 0..1
+fn srgb((r, g, b): (f32, f32, f32)) -> [u8; 3] {
+    [(r * 255.0).round() as u8, (g * 255.0).round() as u8, (b * 255.0).round() as u8]
+}
+
+// Tool, slider values, colors, panel layout and theme, kept between
+// sessions. Stored state that no longer parses is dropped for the defaults
+#[derive(serde::Serialize, serde::Deserialize)]
+struct UiState {
+    theme: Theme,
+    tool: Tool,
+    palette: Palette,
+    dye_intensity: f32,
+    force_intensity: f32,
+    smoke_mode: bool,
+    brush: Brush,
+    multi_finger_paint: bool,
+    attractor_radius: f32,
+    attractor_strength: f32,
+    attractor_repel: bool,
+    #[serde(default)]
+    attractor_absorb: bool,
+    heat_amount: f32,
+    heat_radius: f32,
+    vortex_radius: f32,
+    vortex_strength: f32,
+    vortex_clockwise: bool,
+    vortex_falloff: Falloff,
+    fan_width: f32,
+    fan_speed: f32,
+    fan_along: bool,
+    #[serde(default)]
+    obstacle_paddle: bool,
+    #[serde(default = "default_obstacle_size")]
+    obstacle_size: f32,
+    #[serde(default)]
+    obstacle_spin: f32,
+    #[serde(default)]
+    obstacle_floating: bool,
+    #[serde(default = "default_obstacle_density")]
+    obstacle_density: f32,
+    #[serde(default = "default_viscosity_target")]
+    viscosity_target: f32,
+    emit_count: usize,
+    emit_radius: f32,
+    show_particles: bool,
+    show_streamlines: bool,
+    show_quiver: bool,
+    quiver_spacing: usize,
+    quiver_scale: f32,
+    #[serde(default)]
+    show_stats: bool,
+    #[serde(default)]
+    show_perf: bool,
+    #[serde(default = "default_steps_per_second")]
+    steps_per_second: f32,
+    #[serde(default = "full_speed")]
+    speed: f32,
+    #[serde(default)]
+    steps_per_frame: Option<usize>,
+    show_timeline: bool,
+    #[serde(default)]
+    eraser_dye: bool,
+    #[serde(default = "full_fade")]
+    eraser_fade: f32,
+    #[serde(default)]
+    eraser_velocity: bool,
+    controls_dock: ControlsDockMode,
+    ui_hide_controls: bool,
+    presentation_hide_cursor: bool,
+    trail_strength: f32,
+    autoplay: bool,
+    idle_seconds: f32,
+}
+
+// Settings saved before the dye eraser existed get its default fade
+fn full_fade() -> f32 {
+    1.0
+}
+
+// Settings saved before fixed-timestep pacing ran one step per frame
+fn default_steps_per_second() -> f32 {
+    DEFAULT_STEPS_PER_SECOND
+}
+
+fn full_speed() -> f32 {
+    1.0
+}
+
+fn default_obstacle_size() -> f32 {
+    6.0
+}
+
+fn default_obstacle_density() -> f32 {
+    0.5
+}
+
+fn default_viscosity_target() -> f32 {
+    10.0
+}
+
+impl UiState {
+    fn capture(app: &InteractiveApp) -> Self {
+        Self {
+            theme: app.theme,
+            tool: app.selected_tool,
+            palette: app.palette.clone(),
+            dye_intensity: app.dye_intensity,
+            force_intensity: app.force_intensity,
+            smoke_mode: app.smoke_mode,
+            brush: app.brush,
+            multi_finger_paint: app.multi_finger_paint,
+            attractor_radius: app.attractor_radius,
+            attractor_strength: app.attractor_strength,
+            attractor_repel: app.attractor_repel,
+            attractor_absorb: app.attractor_absorb,
+            heat_amount: app.heat_amount,
+            heat_radius: app.heat_radius,
+            vortex_radius: app.vortex_radius,
+            vortex_strength: app.vortex_strength,
+            vortex_clockwise: app.vortex_clockwise,
+            vortex_falloff: app.vortex_falloff,
+            fan_width: app.fan_width,
+            fan_speed: app.fan_speed,
+            fan_along: app.fan_along,
+            obstacle_paddle: app.obstacle_paddle,
+            obstacle_size: app.obstacle_size,
+            obstacle_spin: app.obstacle_spin,
+            obstacle_floating: app.obstacle_floating,
+            obstacle_density: app.obstacle_density,
+            viscosity_target: app.viscosity_target,
+            emit_count: app.emit_count,
+            emit_radius: app.emit_radius,
+            show_particles: app.show_particles,
+            show_streamlines: app.show_streamlines,
+            show_quiver: app.show_quiver,
+            quiver_spacing: app.quiver_spacing,
+            quiver_scale: app.quiver_scale,
+            show_stats: app.show_stats,
+            show_perf: app.show_perf,
+            steps_per_second: app.clock.steps_per_second,
+            speed: app.clock.speed,
+            steps_per_frame: app.clock.per_frame,
+            show_timeline: app.show_timeline,
+            eraser_dye: app.eraser_dye,
+            eraser_fade: app.eraser_fade,
+            eraser_velocity: app.eraser_velocity,
+            controls_dock: app.controls_dock,
+            ui_hide_controls: app.ui_hide_controls,
+            presentation_hide_cursor: app.presentation_hide_cursor,
+            trail_strength: app.trail_strength,
+            autoplay: app.autoplay,
+            idle_seconds: app.idle_seconds,
+        }
+    }
+
+    fn apply(self, app: &mut InteractiveApp) {
+        app.theme = self.theme;
+        app.selected_tool = self.tool;
+        app.palette = self.palette.sanitized();
+        app.dye_intensity = self.dye_intensity;
+        app.force_intensity = self.force_intensity;
+        app.smoke_mode = self.smoke_mode;
+        app.brush = self.brush;
+        app.multi_finger_paint = self.multi_finger_paint;
+        app.attractor_radius = self.attractor_radius;
+        app.attractor_strength = self.attractor_strength;
+        app.attractor_repel = self.attractor_repel;
+        app.attractor_absorb = self.attractor_absorb;
+        app.heat_amount = self.heat_amount;
+        app.heat_radius = self.heat_radius;
+        app.vortex_radius = self.vortex_radius;
+        app.vortex_strength = self.vortex_strength;
+        app.vortex_clockwise = self.vortex_clockwise;
+        app.vortex_falloff = self.vortex_falloff;
+        app.fan_width = self.fan_width;
+        app.fan_speed = self.fan_speed;
+        app.fan_along = self.fan_along;
+        app.obstacle_paddle = self.obstacle_paddle;
+        app.obstacle_size = self.obstacle_size.clamp(1.0, 40.0);
+        app.obstacle_spin = self.obstacle_spin;
+        app.obstacle_floating = self.obstacle_floating;
+        app.obstacle_density = self.obstacle_density.clamp(0.05, 10.0);
+        app.viscosity_target = self.viscosity_target.clamp(VISCOSITY_FACTOR_RANGE.0, VISCOSITY_FACTOR_RANGE.1);
+        app.emit_count = self.emit_count;
+        app.emit_radius = self.emit_radius;
+        app.show_particles = self.show_particles;
+        app.show_streamlines = self.show_streamlines;
+        app.show_quiver = self.show_quiver;
+        app.quiver_spacing = self.quiver_spacing.max(1);
+        app.quiver_scale = self.quiver_scale;
+        app.show_stats = self.show_stats;
+        app.show_perf = self.show_perf;
+        app.clock.steps_per_second = self.steps_per_second.clamp(1.0, 240.0);
+        app.clock.speed = self.speed.clamp(0.01, 4.0);
+        app.clock.per_frame = self.steps_per_frame.map(|steps| steps.clamp(1, 32));
+        app.show_timeline = self.show_timeline;
+        app.eraser_dye = self.eraser_dye;
+        app.eraser_fade = self.eraser_fade;
+        app.eraser_velocity = self.eraser_velocity;
+        app.controls_dock = self.controls_dock;
+        app.ui_hide_controls = self.ui_hide_controls;
+        app.presentation_hide_cursor = self.presentation_hide_cursor;
+        app.trail_strength = self.trail_strength;
+        app.autoplay = self.autoplay;
+        app.idle_seconds = self.idle_seconds;
+    }
+}
+
+impl eframe::App for InteractiveApp {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("frame").entered();
+        // WASM: on first frame, try to load share state from URL
+        #[cfg(target_arch = "wasm32")]
+        {
+            if !self.url_state_loaded {
+                self.try_load_share_state_from_url();
+                self.url_state_loaded = true;
+            }
+        }
+        // Responsive sizing handled after panels are laid out using available rect.
+
+        if self.applied_theme != Some(self.theme) {
+            ctx.set_visuals(self.theme.visuals(frame.info().system_theme));
+            self.applied_theme = Some(self.theme);
+        }
+
+        // Toolbar at the top - organized in multiple rows to prevent overflow
+        // Presentation mode: Escape brings the UI back
+        if self.presentation && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.set_presentation(ctx, false);
+        }
+        if self.presentation && self.presentation_hide_cursor {
+            ctx.set_cursor_icon(egui::CursorIcon::None);
+        }
+
+        // Ctrl+Z undoes, Ctrl+Shift+Z or Ctrl+Y redoes. Redo is checked first
+        // since Ctrl+Z also matches with Shift held. Text fields keep their own undo
+        if !self.presentation && !ctx.wants_keyboard_input() {
+            let redo_shift = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z);
+            let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+            let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+            if ctx.input_mut(|i| i.consume_shortcut(&redo_shift) || i.consume_shortcut(&redo)) {
+                self.redo();
+            } else if ctx.input_mut(|i| i.consume_shortcut(&undo)) {
+                self.undo();
+            }
+        }
+
+        // Any input stops auto-play at once; it resumes after idle_seconds
+        let now = ctx.input(|i| i.time);
+        if ctx.input(|i| !i.events.is_empty()) {
+            self.last_input_time = now;
+            if self.autoplaying {
+                self.autoplaying = false;
+                self.choreography.reset();
+            }
+        }
+        if self.presentation && self.autoplay && now - self.last_input_time > self.idle_seconds as f64 {
+            self.autoplaying = true;
+        }
+
+        egui::TopBottomPanel::top("toolbar").show_animated(ctx, !self.presentation, |ui| {
+            ui.vertical(|ui| {
+                // Row 1: Title and Help
+                ui.horizontal(|ui| {
+                    ui.heading("itsliquid");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.hyperlink_to("📖 Help", "https://github.com/ekg/itsliquid#readme").clicked() {
+                            // Link opens in browser
+                        }
+                    });
+                });
+
+                ui.add_space(2.0);
+
+                // Row 2: Tool selection
+                ui.horizontal(|ui| {
+                    if ui.selectable_label(self.selected_tool == Tool::Dye, "🎨").clicked() {
+                        self.selected_tool = Tool::Dye;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Force, "💨").clicked() {
+                        self.selected_tool = Tool::Force;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Eyedropper, "🔍").clicked() {
+                        self.selected_tool = Tool::Eyedropper;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Attractor, "🌀").on_hover_text("Attractor or repulsor").clicked() {
+                        self.selected_tool = Tool::Attractor;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Eraser, "🗑").clicked() {
+                        self.selected_tool = Tool::Eraser;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Select, "👆").on_hover_text("Select and move elements").clicked() {
+                        self.selected_tool = Tool::Select;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Gate, "📏").on_hover_text("Flux gate").clicked() {
+                        self.selected_tool = Tool::Gate;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Heat, "🔥").on_hover_text("Heat").clicked() {
+                        self.selected_tool = Tool::Heat;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Emitter, "💫").on_hover_text("Particle emitter").clicked() {
+                        self.selected_tool = Tool::Emitter;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Vortex, "🌪").on_hover_text("Vortex").clicked() {
+                        self.selected_tool = Tool::Vortex;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Fan, "🌬").on_hover_text("Fan strip").clicked() {
+                        self.selected_tool = Tool::Fan;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Turbulence, "〰").on_hover_text("Turbulence").clicked() {
+                        self.selected_tool = Tool::Turbulence;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Obstacle, "⭕").on_hover_text("Moving obstacle").clicked() {
+                        self.selected_tool = Tool::Obstacle;
+                    }
+                    if ui.selectable_label(self.selected_tool == Tool::Viscosity, "🍯").on_hover_text("Paint viscosity").clicked() {
+                        self.selected_tool = Tool::Viscosity;
+                    }
+
+                    ui.separator();
+
+                    // Placement mode toggle
+                    if ui.selectable_label(self.placement_mode, "📌").clicked() {
+                        self.placement_mode = !self.placement_mode;
+                    }
+
+                    // Particle visualization toggle
+                    if ui.selectable_label(self.show_particles, "✨").on_hover_text("Show particles").clicked() {
+                        self.show_particles = !self.show_particles;
+                    }
+                    if ui.selectable_label(self.show_streamlines, "〰").on_hover_text("Show streamlines").clicked() {
+                        self.show_streamlines = !self.show_streamlines;
+                    }
+                    if ui.selectable_label(self.show_quiver, "➶").on_hover_text("Show velocity arrows").clicked() {
+                        self.show_quiver = !self.show_quiver;
+                    }
+                    if ui.selectable_label(self.show_pressure, "◐").on_hover_text("Show pressure (blue low, red high)").clicked() {
+                        self.show_pressure = !self.show_pressure;
+                    }
+                    if ui.selectable_label(self.show_stats, "📊").on_hover_text("Show dye statistics").clicked() {
+                        self.show_stats = !self.show_stats;
+                    }
+                    if ui.selectable_label(self.show_perf, "⏱").on_hover_text("Show frame rate, solver time per stage and memory").clicked() {
+                        self.show_perf = !self.show_perf;
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        ui.separator();
+                        for (view, label, hover) in [
+                            (DetachedView::Canvas, "🗗", "Open the canvas in a second window"),
+                            (DetachedView::Analysis, "📈", "Open plots in a second window"),
+                        ] {
+                            let open = self.detached == Some(view);
+                            if ui.selectable_label(open, label).on_hover_text(hover).clicked() {
+                                self.detached = if open { None } else { Some(view) };
+                                self.stats_history.clear();
+                            }
+                        }
+                    }
+                    if self.show_quiver {
+                        ui.add(egui::Slider::new(&mut self.quiver_spacing, 2..=16).text("Spacing"));
+                        ui.add(egui::Slider::new(&mut self.quiver_scale, 0.05..=5.0).logarithmic(true).text("Scale"));
+                    }
+                });
+
+                // Row 3: Controls
+                ui.horizontal(|ui| {
+                    if ui.button(if self.paused { "▶ Resume" } else { "⏸ Pause" }).clicked() {
+                        self.paused = !self.paused;
+                    }
+                    if self.paused && ui.button("⏭").on_hover_text("Run one step").clicked() {
+                        self.step_simulation();
+                    }
+                    step_pace_ui(ui, &mut self.clock);
+
+                    if ui.add_enabled(self.history.can_undo(), egui::Button::new("↶")).on_hover_text("Undo (Ctrl+Z)").clicked() {
+                        self.undo();
+                    }
+                    if ui.add_enabled(self.history.can_redo(), egui::Button::new("↷")).on_hover_text("Redo (Ctrl+Shift+Z)").clicked() {
+                        self.redo();
+                    }
+
+                    ui.menu_button("🗑 Clear", |ui| self.clear_menu_ui(ui));
+                    ui.menu_button("✨ Presets", |ui| self.presets_menu_ui(ui));
+
+                    ui.separator();
+
+                    for &scale in &[1, 2, 4, 8] {
+                        if ui.selectable_label(self.resolution_scale == scale, format!("{}x", scale)).clicked() {
+                            self.change_resolution(scale);
+                        }
+                    }
+                    ui.menu_button(format!("⚡ {}", self.quality.name()), |ui| self.quality_menu_ui(ui));
+                    ui.menu_button("⚙ Simulation", |ui| self.simulation_panel_ui(ui));
+
+                    ui.separator();
+
+                    ui.label(format!("Grid: {}x{}", self.simulation.width, self.simulation.height));
+
+                    ui.separator();
+
+                    egui::ComboBox::from_label("Colors")
+                        .selected_text(self.dye_colormap.as_ref().map_or("Dye RGB", Colormap::name))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.dye_colormap, None, "Dye RGB");
+                            for colormap in Colormap::builtin() {
+                                let name = colormap.name();
+                                ui.selectable_value(&mut self.dye_colormap, Some(colormap), name);
+                            }
+                        });
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        ui.add(egui::TextEdit::singleline(&mut self.lut_path).hint_text("LUT image").desired_width(90.0));
+                        if ui.button("Load LUT").clicked() {
+                            match Colormap::from_image(std::path::Path::new(&self.lut_path)) {
+                                Ok(colormap) => self.dye_colormap = Some(colormap),
+                                Err(e) => eprintln!("Failed to load LUT {}: {}", self.lut_path, e),
+                            }
+                        }
+
+                        ui.separator();
+
+                        ui.add(egui::TextEdit::singleline(&mut self.scene_path).hint_text("Scene file").desired_width(90.0));
+                        if ui.button("💾 Save scene").clicked() {
+                            let mut scene = Scene::capture(&self.simulation, &self.persistent_elements, self.scene_include_dye);
+                            scene.timeline = (!self.timeline.is_empty()).then(|| self.timeline.clone());
+                            scene.container = self.container.clone();
+                            if let Err(e) = scene.save(std::path::Path::new(&self.scene_path)) {
+                                eprintln!("Failed to save scene {}: {}", self.scene_path, e);
+                            }
+                        }
+                        if ui.button("📂 Load scene").clicked() {
+                            match Scene::load(std::path::Path::new(&self.scene_path)) {
+                                Ok(scene) => self.apply_scene(&scene),
+                                Err(e) => eprintln!("Failed to load scene {}: {}", self.scene_path, e),
+                            }
+                        }
+                        ui.checkbox(&mut self.scene_include_dye, "Dye").on_hover_text("Save the dye field with the scene");
+
+                        ui.separator();
+
+                        ui.add(egui::TextEdit::singleline(&mut self.recording_path).hint_text("Recording file").desired_width(90.0));
+                        if self.simulation.recording.is_some() {
+                            if ui.button("⏹ Stop recording").on_hover_text("Save the recorded inputs").clicked() {
+                                self.finish_recording();
+                            }
+                        } else if ui.button("⏺ Record").on_hover_text("Log every input for `itsliquid replay`").clicked() {
+                            self.simulation.recording = Some(Recording::start(&self.simulation));
+                        }
+                        if ui.selectable_label(self.show_export, "🎬 Export").on_hover_text("Render the scene offline as frames or video").clicked() {
+                            self.show_export = !self.show_export;
+                        }
+                    }
+
+                    ui.separator();
+
+                    // Share link; desktop links open the scene in the web version
+                    if ui.button("🔗 Copy link").clicked()
+                        && let Some(url) = self.share_url()
+                    {
+                        ui.output_mut(|o| o.copied_text = url);
+                        // show a brief feedback label for ~2 seconds
+                        self.copy_feedback_until_frame = Some(self.frame_count.saturating_add(120));
+                    }
+                    if ui.selectable_label(self.show_share_qr, "▦ QR").on_hover_text("Show the share link as a QR code").clicked() {
+                        self.show_share_qr = !self.show_share_qr;
+                    }
+                    if ui.selectable_label(self.show_timeline, "🎞 Timeline").on_hover_text("Keyframe placed elements over frames").clicked() {
+                        self.show_timeline = !self.show_timeline;
+                    }
+                    #[cfg(feature = "scripting")]
+                    if ui.selectable_label(self.show_script, "📜 Script").on_hover_text("Build and animate scenes with Rhai").clicked() {
+                        self.show_script = !self.show_script;
+                    }
+                    if !self.view.is_identity()
+                        && ui.button(format!("🔍 {:.1}x", self.view.zoom)).on_hover_text("Reset the zoom and pan").clicked()
+                    {
+                        self.view = CanvasView::default();
+                    }
+
+                    ui.separator();
+
+                    #[cfg(all(feature = "ndi", not(target_arch = "wasm32")))]
+                    {
+                        if ui.selectable_label(self.ndi.is_some(), "📡 NDI").on_hover_text("Publish as an NDI source").clicked() {
+                            if self.ndi.is_some() {
+                                self.ndi = None;
+                            } else {
+                                match NdiSender::new("itsliquid") {
+                                    Ok(sender) => self.ndi = Some(sender),
+                                    Err(e) => eprintln!("Failed to start NDI output: {}", e),
+                                }
+                            }
+                        }
+                        ui.separator();
+                    }
+
+                    #[cfg(all(feature = "webcam", target_os = "linux"))]
+                    {
+                        if ui.selectable_label(self.webcam.is_some(), "🎥 Webcam").on_hover_text("Output to a v4l2loopback virtual camera").clicked() {
+                            if self.webcam.is_some() {
+                                self.webcam = None;
+                            } else {
+                                let (w, h) = self.webcam_frame.dimensions();
+                                match VirtualCamera::start(w, h, 30) {
+                                    Ok(camera) => self.webcam = Some(camera),
+                                    Err(e) => eprintln!("Failed to start virtual camera: {}", e),
+                                }
+                            }
+                        }
+                        ui.separator();
+                    }
+
+                    #[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+                    {
+                        if let Some(chat) = &self.chat {
+                            if ui.button(format!("💬 Leave #{}", chat.channel)).clicked() {
+                                self.chat = None;
+                            }
+                            let rejected = ui.label(format!("{} rejected", self.chat_ingest.rejected));
+                            if let Some(last) = self.chat_ingest.recent_rejects().last() {
+                                rejected.on_hover_text(format!("{}: {} ({})", last.source, last.reason, last.input));
+                            }
+                        } else {
+                            ui.add(egui::TextEdit::singleline(&mut self.chat_channel).hint_text("twitch channel").desired_width(110.0));
+                            if ui.button("💬 Join").on_hover_text("Apply !dye/!force/!heat/!clear commands from chat").clicked() {
+                                match TwitchChat::connect(&self.chat_channel) {
+                                    Ok(chat) => self.chat = Some(chat),
+                                    Err(e) => eprintln!("Failed to join chat: {}", e),
+                                }
+                            }
+                        }
+                        ui.separator();
+                    }
+
+                    #[cfg(all(feature = "control", not(target_arch = "wasm32")))]
+                    {
+                        if let Some(osc) = &self.osc {
+                            if ui.button(format!("🎛 Stop OSC :{}", osc.port)).clicked() {
+                                self.osc = None;
+                            }
+                        } else {
+                            ui.add(egui::DragValue::new(&mut self.osc_port).prefix("port "));
+                            if ui.button("🎛 OSC").on_hover_text("Listen for /itsliquid/... messages over UDP").clicked() {
+                                match OscListener::bind(self.osc_port) {
+                                    Ok(osc) => self.osc = Some(osc),
+                                    Err(e) => eprintln!("Failed to listen for OSC on port {}: {}", self.osc_port, e),
+                                }
+                            }
+                        }
+                        #[cfg(feature = "midi")]
+                        if let Some(midi) = &self.midi {
+                            if ui.button("🎹 Stop MIDI").on_hover_text(format!("Reading {}", midi.port_name)).clicked() {
+                                self.midi = None;
+                            }
+                        } else if ui.button("🎹 MIDI").on_hover_text("Read CC 20-24 from the first MIDI input").clicked() {
+                            match MidiListener::open_first() {
+                                Ok(midi) => self.midi = Some(midi),
+                                Err(e) => eprintln!("Failed to open MIDI input: {}", e),
+                            }
+                        }
+                        ui.separator();
+                    }
+
+                    #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+                    {
+                        if let Some(audio) = &self.audio {
+                            if ui.button("🎤 Stop").on_hover_text(format!("Listening to {}", audio.device)).clicked() {
+                                self.audio = None;
+                                self.audio_levels = AudioLevels::default();
+                            }
+                            for band in AudioBand::ALL {
+                                ui.add(egui::ProgressBar::new(self.audio_levels.get(band)).desired_width(30.0))
+                                    .on_hover_text(band.name());
+                            }
+                            ui.add(egui::Slider::new(&mut self.audio_sensitivity, 0.5..=50.0).logarithmic(true).text("Gain"));
+                        } else if ui.button("🎤 Listen").on_hover_text("Pulse audio-linked elements with the default input").clicked() {
+                            match AudioInput::open_default() {
+                                Ok(audio) => self.audio = Some(audio),
+                                Err(e) => eprintln!("Failed to open audio input: {}", e),
+                            }
+                        }
+                        ui.separator();
+                    }
+
+                    // Controls docking preference
+                    egui::ComboBox::from_label("Controls")
+                        .selected_text(match self.controls_dock {
+                            ControlsDockMode::Auto => "Auto",
+                            ControlsDockMode::Top => "Top",
+                            ControlsDockMode::Bottom => "Bottom",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.controls_dock, ControlsDockMode::Auto, "Auto");
+                            ui.selectable_value(&mut self.controls_dock, ControlsDockMode::Top, "Top");
+                            ui.selectable_value(&mut self.controls_dock, ControlsDockMode::Bottom, "Bottom");
+                        });
+                    egui::ComboBox::from_label("Theme").selected_text(self.theme.name()).show_ui(ui, |ui| {
+                        for theme in Theme::ALL {
+                            ui.selectable_value(&mut self.theme, theme, theme.name());
+                        }
+                    });
+
+                    ui.separator();
+
+                    // Hide tool panels toggle (max canvas)
+                    let hide_lbl = if self.ui_hide_controls { "🎛 Show Controls" } else { "🎛 Hide Controls" };
+                    if ui.button(hide_lbl).clicked() {
+                        self.ui_hide_controls = !self.ui_hide_controls;
+                    }
+
+                    ui.menu_button("🖼 Present", |ui| {
+                        ui.checkbox(&mut self.presentation_hide_cursor, "Hide cursor");
+                        ui.horizontal(|ui| {
+                            ui.label("Trail:");
+                            ui.add(egui::Slider::new(&mut self.trail_strength, 0.0..=2.0).step_by(0.05))
+                                .on_hover_text("Force left behind by the pointer; 0 disables it");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.autoplay, "Auto-play after");
+                            ui.add(egui::DragValue::new(&mut self.idle_seconds).clamp_range(5.0..=600.0).suffix(" s"));
+                        })
+                        .response
+                        .on_hover_text("Run dye bursts, roaming attractors and sweeps while nobody is interacting");
+                        if ui.button("Start (Esc exits)").clicked() {
+                            self.set_presentation(ui.ctx(), true);
+                            ui.close_menu();
+                        }
+                    });
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.menu_button("▣ Masks", |ui| self.mask_menu_ui(ui));
+                    ui.menu_button("◯ Container", |ui| self.container_menu_ui(ui));
+                    ui.menu_button("⚗ Chemistry", |ui| self.chemistry_menu_ui(ui));
+                    ui.menu_button("🎴 Gallery", |ui| {
+                        self.gallery_menu_ui(ui);
+                    });
+                    ui.menu_button("📷 PNG", |ui| self.png_menu_ui(ui));
+
+                    ui.separator();
+
+                    // Fullscreen toggle
+                    #[cfg(target_arch = "wasm32")]
+                    {
+                        let fs = self.is_fullscreen_web();
+                        let label = if fs { "🗗 Exit Fullscreen" } else { "⛶ Fullscreen" };
+                        if ui.button(label).clicked() {
+                            if fs {
+                                let _ = self.exit_fullscreen_web();
+                            } else {
+                                let _ = self.request_fullscreen_web();
+                            }
+                        }
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        // Desktop: the same viewport command presentation mode uses
+                        let fs = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+                        let label = if fs { "🗗 Exit Fullscreen" } else { "⛶ Fullscreen" };
+                        if ui.button(label).clicked() {
+                            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fs));
+                        }
+                    }
+                });
+            });
+        });
+
+        // Tool-specific panels - decide docking (top/bottom or side in landscape)
+        let screen_rect = ctx.screen_rect();
+        let is_landscape = screen_rect.width() >= screen_rect.height();
+        let dock_top = match self.controls_dock {
+            ControlsDockMode::Auto => is_landscape,
+            ControlsDockMode::Top => true,
+            ControlsDockMode::Bottom => false,
+        };
+        let use_side_panel = is_landscape; // prefer sidebar in landscape for full-height canvas
+
+        // Full width under everything else
+        egui::TopBottomPanel::bottom("timeline").show_animated(ctx, self.show_timeline && !self.presentation, |ui| {
+            self.timeline_ui(ui);
+        });
+
+        // Show panels BEFORE CentralPanel to reserve space
+        if self.presentation {
+            // No tool panels while presenting
+        } else if !self.ui_hide_controls && use_side_panel {
+            // Right side controls in landscape
             egui::SidePanel::right("tool_controls_side")
                 .resizable(true)
                 .default_width(260.0)
@@ -323,31 +3002,15 @@ impl eframe::App for InteractiveApp {
                             Tool::Dye => {
                                 ui.heading("Dye");
                                 ui.add_space(6.0);
-                                ui.horizontal_wrapped(|ui| {
-                                    ui.label("Color:");
-                                    for (i, &color) in self.dye_colors.iter().enumerate() {
-                                        let color_32 = egui::Color32::from_rgb(
-                                            (color.0 * 255.0) as u8,
-                                            (color.1 * 255.0) as u8,
-                                            (color.2 * 255.0) as u8,
-                                        );
-                                        let size = egui::Vec2::new(26.0, 26.0);
-                                        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
-                                        ui.painter().rect_stroke(rect, 1.5, egui::Stroke::new(1.2, egui::Color32::GRAY));
-                                        ui.painter().rect_filled(rect.shrink(2.0), 1.5, color_32);
-                                        if self.current_dye_index == i {
-                                            ui.painter().rect_stroke(rect.shrink(0.5), 1.5, egui::Stroke::new(2.0, egui::Color32::WHITE));
-                                        }
-                                        if response.clicked() {
-                                            self.current_dye_index = i;
-                                        }
-                                    }
-                                });
+                                self.palette_ui(ui);
+                                blend_mode_ui(ui, &mut self.brush.blend);
                                 ui.add_space(8.0);
                                 ui.horizontal(|ui| {
                                     ui.label("Intensity:");
                                     ui.add(egui::Slider::new(&mut self.dye_intensity, 0.1..=100.0).show_value(true).step_by(0.1));
                                 });
+                                ui.add_space(4.0);
+                                self.brush_panel_ui(ui);
                             }
                             Tool::Force => {
                                 ui.heading("Force");
@@ -356,6 +3019,8 @@ impl eframe::App for InteractiveApp {
                                     ui.label("Intensity:");
                                     ui.add(egui::Slider::new(&mut self.force_intensity, 0.01..=3.0).show_value(true).step_by(0.01));
                                 });
+                                ui.add_space(4.0);
+                                self.brush_panel_ui(ui);
                             }
                             Tool::Eyedropper => {
                                 ui.heading("Eyedropper");
@@ -376,136 +3041,418 @@ impl eframe::App for InteractiveApp {
                                         ui.separator();
                                         ui.label(format!("RGB: ({}, {}, {})", r_255, g_255, b_255));
                                         ui.separator();
-                                        let hex_string = format!("#{:02X}{:02X}{:02X}", r_255, g_255, b_255);
-                                        ui.label("Hex:");
-                                        let mut hex_text = hex_string.clone();
-                                        ui.add(egui::TextEdit::singleline(&mut hex_text).desired_width(80.0).interactive(false));
-                                        ui.separator();
                                         ui.label(format!("HDR: ({:.3}, {:.3}, {:.3})", r, g, b));
+                                        ui.separator();
+                                        self.sample_hex_ui(ui);
                                     });
                                 } else {
                                     ui.label("Click on a cell to sample its color");
                                 }
                             }
                             Tool::Attractor => {
-                                ui.heading("Attractor");
+                                ui.heading(if self.attractor_repel { "Repulsor" } else { "Attractor" });
+                                ui.add_space(6.0);
+                                self.attractor_panel_ui(ui);
+                            }
+                            Tool::Eraser => {
+                                ui.heading("Eraser");
+                                ui.add_space(6.0);
+                                self.eraser_panel_ui(ui);
+                            }
+                            Tool::Select => {
+                                ui.heading("Element");
+                                ui.add_space(6.0);
+                                self.element_inspector_ui(ui);
+                            }
+                            Tool::Gate => {
+                                ui.heading("Flux Gates");
+                                ui.add_space(6.0);
+                                self.gate_panel_ui(ui);
+                            }
+                            Tool::Heat => {
+                                ui.heading("Heat");
+                                ui.add_space(6.0);
+                                self.heat_panel_ui(ui);
+                            }
+                            Tool::Emitter => {
+                                ui.heading("Particles");
+                                ui.add_space(6.0);
+                                self.particle_panel_ui(ui);
+                            }
+                            Tool::Vortex => {
+                                ui.heading("Vortex");
+                                ui.add_space(6.0);
+                                self.vortex_panel_ui(ui);
+                            }
+                            Tool::Fan => {
+                                ui.heading("Fan");
+                                ui.add_space(6.0);
+                                self.fan_panel_ui(ui);
+                            }
+                            Tool::Turbulence => {
+                                ui.heading("Turbulence");
+                                ui.add_space(6.0);
+                                self.turbulence_panel_ui(ui);
+                            }
+                            Tool::Obstacle => {
+                                ui.heading("Moving obstacle");
+                                ui.add_space(6.0);
+                                self.obstacle_panel_ui(ui);
+                            }
+                            Tool::Viscosity => {
+                                ui.heading("Viscosity");
+                                ui.add_space(6.0);
+                                self.viscosity_panel_ui(ui);
+                            }
+                        }
+                    });
+                });
+        } else if !self.ui_hide_controls {
+            match self.selected_tool {
+                Tool::Dye => {
+                let panel_id = "color_controls";
+                if dock_top {
+                    egui::TopBottomPanel::top(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                ui.vertical(|ui| {
+                        // Color swatches - one row
+                        self.palette_ui(ui);
+                        blend_mode_ui(ui, &mut self.brush.blend);
+
+                        ui.add_space(4.0);
+
+                        // Dye intensity slider
+                        ui.horizontal(|ui| {
+                            ui.label("Intensity:");
+                            ui.add(egui::Slider::new(&mut self.dye_intensity, 0.1..=100.0)
+                                .show_value(true)
+                                .step_by(0.1));
+                        });
+                        ui.add_space(4.0);
+                        self.brush_panel_ui(ui);
+                                });
+                            });
+                        });
+                } else {
+                    egui::TopBottomPanel::bottom(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                ui.vertical(|ui| {
+                                    self.palette_ui(ui);
+                                    blend_mode_ui(ui, &mut self.brush.blend);
+                                    ui.add_space(4.0);
+                                    ui.horizontal(|ui| {
+                                        ui.label("Intensity:");
+                                        ui.add(egui::Slider::new(&mut self.dye_intensity, 0.1..=100.0)
+                                            .show_value(true)
+                                            .step_by(0.1));
+                                    });
+                                    ui.add_space(4.0);
+                                    self.brush_panel_ui(ui);
+                                });
+                            });
+                        });
+                }
+            },
+            Tool::Force => {
+                let panel_id = "force_controls";
+                if dock_top {
+                    egui::TopBottomPanel::top(panel_id)
+                        .min_height(100.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
                                 ui.horizontal(|ui| {
-                                    ui.label("Radius:");
-                                    ui.add(egui::Slider::new(&mut self.attractor_radius, 1.0..=200.0).show_value(true).step_by(1.0));
+                                    ui.label("Force Intensity:");
+                                    ui.add(egui::Slider::new(&mut self.force_intensity, 0.01..=3.0)
+                                        .show_value(true)
+                                        .step_by(0.01));
                                 });
                                 ui.add_space(4.0);
-                                ui.horizontal(|ui| {
-                                    ui.label("Strength:");
-                                    ui.add(egui::Slider::new(&mut self.attractor_strength, 0.1..=100.0).show_value(true).step_by(0.1));
-                                });
-                            }
-                            Tool::Eraser => {
-                                ui.heading("Eraser");
+                                self.brush_panel_ui(ui);
+                            });
+                        });
+                } else {
+                    egui::TopBottomPanel::bottom(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
                                 ui.horizontal(|ui| {
-                                    ui.label("Radius:");
-                                    ui.add(egui::Slider::new(&mut self.eraser_radius, 10.0..=100.0).show_value(true).step_by(1.0));
+                                    ui.label("Force Intensity:");
+                                    ui.add(egui::Slider::new(&mut self.force_intensity, 0.01..=3.0)
+                                        .show_value(true)
+                                        .step_by(0.01));
                                 });
-                            }
-                        }
-                    });
-                });
-        } else if !self.ui_hide_controls {
-            match self.selected_tool {
-                Tool::Dye => {
-                let panel_id = "color_controls";
+                                ui.add_space(4.0);
+                                self.brush_panel_ui(ui);
+                            });
+                        });
+                }
+            },
+            Tool::Eyedropper => {
+                let panel_id = "eyedropper_info";
                 if dock_top {
                     egui::TopBottomPanel::top(panel_id)
-                        .min_height(130.0)
+                        .min_height(110.0)
                         .show_separator_line(true)
                         .show(ctx, |ui| {
                             egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
                                 ui.add_space(6.0);
                                 ui.vertical(|ui| {
-                        // Color swatches - one row
-                        ui.horizontal(|ui| {
-                            ui.label("Color:");
-                            for (i, &color) in self.dye_colors.iter().enumerate() {
-                                let color_32 = egui::Color32::from_rgb(
-                                    (color.0 * 255.0) as u8,
-                                    (color.1 * 255.0) as u8,
-                                    (color.2 * 255.0) as u8
-                                );
-                                let size = egui::Vec2::new(26.0, 26.0);
-                                let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+                        if let Some((r, g, b)) = self.sampled_color {
+                            // Apply Reinhard tone mapping for display
+                            let r_display = r / (1.0 + r);
+                            let g_display = g / (1.0 + g);
+                            let b_display = b / (1.0 + b);
 
-                                // Always draw a gray border so black/white are visible
-                                ui.painter().rect_stroke(rect, 1.5, egui::Stroke::new(1.2, egui::Color32::GRAY));
+                            // Convert to 0-255 range for display
+                            let r_255 = (r_display * 255.0).round() as u8;
+                            let g_255 = (g_display * 255.0).round() as u8;
+                            let b_255 = (b_display * 255.0).round() as u8;
 
-                                // Fill with color
+                            ui.horizontal(|ui| {
+                                // Color preview swatch - same size as dye swatches
+                                let color_32 = egui::Color32::from_rgb(r_255, g_255, b_255);
+                                let size = egui::Vec2::new(26.0, 26.0);
+                                let (rect, _response) = ui.allocate_exact_size(size, egui::Sense::hover());
+                                ui.painter().rect_stroke(rect, 1.5, egui::Stroke::new(1.2, egui::Color32::GRAY));
                                 ui.painter().rect_filled(rect.shrink(2.0), 1.5, color_32);
 
-                                // Selection indicator on top
-                                if self.current_dye_index == i {
-                                    ui.painter().rect_stroke(rect.shrink(0.5), 1.5, egui::Stroke::new(2.0, egui::Color32::WHITE));
-                                }
+                                ui.separator();
 
-                                if response.clicked() {
-                                    self.current_dye_index = i;
-                                }
-                            }
-                        });
+                                // RGB values
+                                ui.label(format!("RGB: ({}, {}, {})", r_255, g_255, b_255));
 
-                        ui.add_space(4.0);
+                                ui.separator();
 
-                        // Dye intensity slider
-                        ui.horizontal(|ui| {
-                            ui.label("Intensity:");
-                            ui.add(egui::Slider::new(&mut self.dye_intensity, 0.1..=100.0)
-                                .show_value(true)
-                                .step_by(0.1));
+
+                                // Raw HDR values
+                                ui.label(format!("HDR: ({:.3}, {:.3}, {:.3})", r, g, b));
+                                ui.separator();
+                                self.sample_hex_ui(ui);
+                            });
+                        } else {
+                            ui.label("Click on a cell to sample its color");
+                        }
+                                });
+                            });
                         });
+                } else {
+                    egui::TopBottomPanel::bottom(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                ui.vertical(|ui| {
+                                    if let Some((r, g, b)) = self.sampled_color {
+                                        let r_display = r / (1.0 + r);
+                                        let g_display = g / (1.0 + g);
+                                        let b_display = b / (1.0 + b);
+                                        let r_255 = (r_display * 255.0).round() as u8;
+                                        let g_255 = (g_display * 255.0).round() as u8;
+                                        let b_255 = (b_display * 255.0).round() as u8;
+                                        ui.horizontal(|ui| {
+                                            let color_32 = egui::Color32::from_rgb(r_255, g_255, b_255);
+                                            let size = egui::Vec2::new(26.0, 26.0);
+                                            let (rect, _response) = ui.allocate_exact_size(size, egui::Sense::hover());
+                                            ui.painter().rect_stroke(rect, 1.5, egui::Stroke::new(1.2, egui::Color32::GRAY));
+                                            ui.painter().rect_filled(rect.shrink(2.0), 1.5, color_32);
+                                            ui.separator();
+                                            ui.label(format!("RGB: ({}, {}, {})", r_255, g_255, b_255));
+                                            ui.separator();
+                                            ui.label(format!("HDR: ({:.3}, {:.3}, {:.3})", r, g, b));
+                                            ui.separator();
+                                            self.sample_hex_ui(ui);
+                                        });
+                                    } else {
+                                        ui.label("Click on a cell to sample its color");
+                                    }
                                 });
                             });
                         });
+                }
+            },
+            Tool::Attractor => {
+                let panel_id = "attractor_controls";
+                if dock_top {
+                    egui::TopBottomPanel::top(panel_id)
+                        .min_height(120.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.attractor_panel_ui(ui);
+                            });
+                        });
+                } else {
+                    egui::TopBottomPanel::bottom(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.attractor_panel_ui(ui);
+                            });
+                        });
+                }
+            },
+            Tool::Eraser => {
+                let panel_id = "eraser_controls";
+                if dock_top {
+                    egui::TopBottomPanel::top(panel_id)
+                        .min_height(100.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.eraser_panel_ui(ui);
+                            });
+                        });
+                } else {
+                    egui::TopBottomPanel::bottom(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.eraser_panel_ui(ui);
+                            });
+                        });
+                }
+            },
+            Tool::Gate => {
+                let panel_id = "gate_controls";
+                if dock_top {
+                    egui::TopBottomPanel::top(panel_id)
+                        .min_height(100.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.gate_panel_ui(ui);
+                            });
+                        });
+                } else {
+                    egui::TopBottomPanel::bottom(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.gate_panel_ui(ui);
+                            });
+                        });
+                }
+            },
+            Tool::Select => {
+                let panel_id = "element_inspector";
+                if dock_top {
+                    egui::TopBottomPanel::top(panel_id)
+                        .min_height(100.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.element_inspector_ui(ui);
+                            });
+                        });
+                } else {
+                    egui::TopBottomPanel::bottom(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.element_inspector_ui(ui);
+                            });
+                        });
+                }
+            },
+            Tool::Heat => {
+                let panel_id = "heat_controls";
+                if dock_top {
+                    egui::TopBottomPanel::top(panel_id)
+                        .min_height(100.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.heat_panel_ui(ui);
+                            });
+                        });
+                } else {
+                    egui::TopBottomPanel::bottom(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.heat_panel_ui(ui);
+                            });
+                        });
+                }
+            },
+            Tool::Emitter => {
+                let panel_id = "particle_controls";
+                if dock_top {
+                    egui::TopBottomPanel::top(panel_id)
+                        .min_height(100.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.particle_panel_ui(ui);
+                            });
+                        });
+                } else {
+                    egui::TopBottomPanel::bottom(panel_id)
+                        .min_height(130.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.particle_panel_ui(ui);
+                            });
+                        });
+                }
+            },
+            Tool::Vortex => {
+                let panel_id = "vortex_controls";
+                if dock_top {
+                    egui::TopBottomPanel::top(panel_id)
+                        .min_height(100.0)
+                        .show_separator_line(true)
+                        .show(ctx, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
+                                ui.add_space(6.0);
+                                self.vortex_panel_ui(ui);
+                            });
+                        });
                 } else {
                     egui::TopBottomPanel::bottom(panel_id)
                         .min_height(130.0)
                         .show_separator_line(true)
                         .show(ctx, |ui| {
-                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
-                                ui.vertical(|ui| {
-                                    ui.horizontal(|ui| {
-                                        ui.label("Color:");
-                                        for (i, &color) in self.dye_colors.iter().enumerate() {
-                                            let color_32 = egui::Color32::from_rgb(
-                                                (color.0 * 255.0) as u8,
-                                                (color.1 * 255.0) as u8,
-                                                (color.2 * 255.0) as u8
-                                            );
-                                            let size = egui::Vec2::new(26.0, 26.0);
-                                            let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
-                                            ui.painter().rect_stroke(rect, 1.5, egui::Stroke::new(1.2, egui::Color32::GRAY));
-                                            ui.painter().rect_filled(rect.shrink(2.0), 1.5, color_32);
-                                            if self.current_dye_index == i {
-                                                ui.painter().rect_stroke(rect.shrink(0.5), 1.5, egui::Stroke::new(2.0, egui::Color32::WHITE));
-                                            }
-                                            if response.clicked() {
-                                                self.current_dye_index = i;
-                                            }
-                                        }
-                                    });
-                                    ui.add_space(4.0);
-                                    ui.horizontal(|ui| {
-                                        ui.label("Intensity:");
-                                        ui.add(egui::Slider::new(&mut self.dye_intensity, 0.1..=100.0)
-                                            .show_value(true)
-                                            .step_by(0.1));
-                                    });
-                                });
+                                self.vortex_panel_ui(ui);
                             });
                         });
                 }
             },
-            Tool::Force => {
-                let panel_id = "force_controls";
+            Tool::Fan => {
+                let panel_id = "fan_controls";
                 if dock_top {
                     egui::TopBottomPanel::top(panel_id)
                         .min_height(100.0)
@@ -513,12 +3460,7 @@ impl eframe::App for InteractiveApp {
                         .show(ctx, |ui| {
                             egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
-                                ui.horizontal(|ui| {
-                                    ui.label("Force Intensity:");
-                                    ui.add(egui::Slider::new(&mut self.force_intensity, 0.01..=3.0)
-                                        .show_value(true)
-                                        .step_by(0.01));
-                                });
+                                self.fan_panel_ui(ui);
                             });
                         });
                 } else {
@@ -528,69 +3470,21 @@ impl eframe::App for InteractiveApp {
                         .show(ctx, |ui| {
                             egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
-                                ui.horizontal(|ui| {
-                                    ui.label("Force Intensity:");
-                                    ui.add(egui::Slider::new(&mut self.force_intensity, 0.01..=3.0)
-                                        .show_value(true)
-                                        .step_by(0.01));
-                                });
+                                self.fan_panel_ui(ui);
                             });
                         });
                 }
             },
-            Tool::Eyedropper => {
-                let panel_id = "eyedropper_info";
+            Tool::Viscosity => {
+                let panel_id = "viscosity_controls";
                 if dock_top {
                     egui::TopBottomPanel::top(panel_id)
-                        .min_height(110.0)
+                        .min_height(100.0)
                         .show_separator_line(true)
                         .show(ctx, |ui| {
-                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
-                                ui.vertical(|ui| {
-                        if let Some((r, g, b)) = self.sampled_color {
-                            // Apply Reinhard tone mapping for display
-                            let r_display = r / (1.0 + r);
-                            let g_display = g / (1.0 + g);
-                            let b_display = b / (1.0 + b);
-
-                            // Convert to 0-255 range for display
-                            let r_255 = (r_display * 255.0).round() as u8;
-                            let g_255 = (g_display * 255.0).round() as u8;
-                            let b_255 = (b_display * 255.0).round() as u8;
-
-                            ui.horizontal(|ui| {
-                                // Color preview swatch - same size as dye swatches
-                                let color_32 = egui::Color32::from_rgb(r_255, g_255, b_255);
-                                let size = egui::Vec2::new(26.0, 26.0);
-                                let (rect, _response) = ui.allocate_exact_size(size, egui::Sense::hover());
-                                ui.painter().rect_stroke(rect, 1.5, egui::Stroke::new(1.2, egui::Color32::GRAY));
-                                ui.painter().rect_filled(rect.shrink(2.0), 1.5, color_32);
-
-                                ui.separator();
-
-                                // RGB values
-                                ui.label(format!("RGB: ({}, {}, {})", r_255, g_255, b_255));
-
-                                ui.separator();
-
-                                // Hex value - selectable
-                                let hex_string = format!("#{:02X}{:02X}{:02X}", r_255, g_255, b_255);
-                                ui.label("Hex:");
-                                let mut hex_text = hex_string.clone();
-                                ui.add(egui::TextEdit::singleline(&mut hex_text)
-                                    .desired_width(80.0)
-                                    .interactive(false));
-
-                                ui.separator();
-
-                                // Raw HDR values
-                                ui.label(format!("HDR: ({:.3}, {:.3}, {:.3})", r, g, b));
-                            });
-                        } else {
-                            ui.label("Click on a cell to sample its color");
-                        }
-                                });
+                                self.viscosity_panel_ui(ui);
                             });
                         });
                 } else {
@@ -598,64 +3492,23 @@ impl eframe::App for InteractiveApp {
                         .min_height(130.0)
                         .show_separator_line(true)
                         .show(ctx, |ui| {
-                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
-                                ui.vertical(|ui| {
-                                    if let Some((r, g, b)) = self.sampled_color {
-                                        let r_display = r / (1.0 + r);
-                                        let g_display = g / (1.0 + g);
-                                        let b_display = b / (1.0 + b);
-                                        let r_255 = (r_display * 255.0).round() as u8;
-                                        let g_255 = (g_display * 255.0).round() as u8;
-                                        let b_255 = (b_display * 255.0).round() as u8;
-                                        ui.horizontal(|ui| {
-                                            let color_32 = egui::Color32::from_rgb(r_255, g_255, b_255);
-                                            let size = egui::Vec2::new(26.0, 26.0);
-                                            let (rect, _response) = ui.allocate_exact_size(size, egui::Sense::hover());
-                                            ui.painter().rect_stroke(rect, 1.5, egui::Stroke::new(1.2, egui::Color32::GRAY));
-                                            ui.painter().rect_filled(rect.shrink(2.0), 1.5, color_32);
-                                            ui.separator();
-                                            ui.label(format!("RGB: ({}, {}, {})", r_255, g_255, b_255));
-                                            ui.separator();
-                                            let hex_string = format!("#{:02X}{:02X}{:02X}", r_255, g_255, b_255);
-                                            ui.label("Hex:");
-                                            let mut hex_text = hex_string.clone();
-                                            ui.add(egui::TextEdit::singleline(&mut hex_text)
-                                                .desired_width(80.0)
-                                                .interactive(false));
-                                            ui.separator();
-                                            ui.label(format!("HDR: ({:.3}, {:.3}, {:.3})", r, g, b));
-                                        });
-                                    } else {
-                                        ui.label("Click on a cell to sample its color");
-                                    }
-                                });
+                                self.viscosity_panel_ui(ui);
                             });
                         });
                 }
             },
-            Tool::Attractor => {
-                let panel_id = "attractor_controls";
+            Tool::Turbulence => {
+                let panel_id = "turbulence_controls";
                 if dock_top {
                     egui::TopBottomPanel::top(panel_id)
-                        .min_height(120.0)
+                        .min_height(100.0)
                         .show_separator_line(true)
                         .show(ctx, |ui| {
-                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
-                                ui.horizontal(|ui| {
-                                    ui.label("Radius:");
-                                    ui.add(egui::Slider::new(&mut self.attractor_radius, 1.0..=200.0)
-                                        .show_value(true)
-                                        .step_by(1.0));
-                                });
-                                ui.add_space(4.0);
-                                ui.horizontal(|ui| {
-                                    ui.label("Strength:");
-                                    ui.add(egui::Slider::new(&mut self.attractor_strength, 0.1..=100.0)
-                                        .show_value(true)
-                                        .step_by(0.1));
-                                });
+                                self.turbulence_panel_ui(ui);
                             });
                         });
                 } else {
@@ -663,27 +3516,15 @@ impl eframe::App for InteractiveApp {
                         .min_height(130.0)
                         .show_separator_line(true)
                         .show(ctx, |ui| {
-                            egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                            egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
-                                ui.horizontal(|ui| {
-                                    ui.label("Radius:");
-                                    ui.add(egui::Slider::new(&mut self.attractor_radius, 1.0..=200.0)
-                                        .show_value(true)
-                                        .step_by(1.0));
-                                });
-                                ui.add_space(4.0);
-                                ui.horizontal(|ui| {
-                                    ui.label("Strength:");
-                                    ui.add(egui::Slider::new(&mut self.attractor_strength, 0.1..=100.0)
-                                        .show_value(true)
-                                        .step_by(0.1));
-                                });
+                                self.turbulence_panel_ui(ui);
                             });
                         });
                 }
             },
-            Tool::Eraser => {
-                let panel_id = "eraser_controls";
+            Tool::Obstacle => {
+                let panel_id = "obstacle_controls";
                 if dock_top {
                     egui::TopBottomPanel::top(panel_id)
                         .min_height(100.0)
@@ -691,12 +3532,7 @@ impl eframe::App for InteractiveApp {
                         .show(ctx, |ui| {
                             egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
-                                ui.horizontal(|ui| {
-                                    ui.label("Radius:");
-                                    ui.add(egui::Slider::new(&mut self.eraser_radius, 10.0..=100.0)
-                                        .show_value(true)
-                                        .step_by(1.0));
-                                });
+                                self.obstacle_panel_ui(ui);
                             });
                         });
                 } else {
@@ -706,12 +3542,7 @@ impl eframe::App for InteractiveApp {
                         .show(ctx, |ui| {
                             egui::ScrollArea::vertical().max_height(140.0).show(ui, |ui| {
                                 ui.add_space(6.0);
-                                ui.horizontal(|ui| {
-                                    ui.label("Radius:");
-                                    ui.add(egui::Slider::new(&mut self.eraser_radius, 10.0..=100.0)
-                                        .show_value(true)
-                                        .step_by(1.0));
-                                });
+                                self.obstacle_panel_ui(ui);
                             });
                         });
                 }
@@ -732,16 +3563,19 @@ impl eframe::App for InteractiveApp {
             new_h = new_h.max(50);
             let (new_w, new_h) = (new_w as usize, new_h as usize);
             if new_w != self.simulation.width || new_h != self.simulation.height {
-                self.simulation = InteractiveFluid::new(new_w, new_h);
+                self.resize_grid(new_w, new_h);
                 self.base_width = new_w / self.resolution_scale;
                 self.base_height = new_h / self.resolution_scale;
-                // Note: Don't clear persistent elements here - only on manual resolution change
-                // Responsive resize from window/panel changes should preserve elements
             }
         }
 
-        // Canvas fills the entire remaining space
-        egui::CentralPanel::default().show(ctx, |ui| {
+        // Canvas fills the entire remaining space, edge to edge when presenting
+        let canvas_frame = if self.presentation {
+            egui::Frame::none().fill(egui::Color32::BLACK)
+        } else {
+            egui::Frame::central_panel(&ctx.style())
+        };
+        egui::CentralPanel::default().frame(canvas_frame).show(ctx, |ui| {
             // Use all available space
             let available_size = ui.available_size();
 
@@ -755,267 +3589,540 @@ impl eframe::App for InteractiveApp {
             let canvas_height = self.simulation.height as f32 * cell_size;
 
             // Simulation canvas - centered in available space
-            let (rect, response) = ui.allocate_exact_size(
+            let (canvas, response) = ui.allocate_exact_size(
                 egui::Vec2::new(canvas_width, canvas_height),
                 egui::Sense::click_and_drag()
             );
 
-            // TOOL-BASED INTERACTION
-            match self.selected_tool {
-                Tool::Dye => {
-                    if self.placement_mode {
-                        // In placement mode: click or drag to place persistent dye sources
-                        let is_interacting = response.clicked() || response.dragged();
+            // Tools map pointers through the zoomed grid
+            let gesturing = self.canvas_gestures(ui, &response, canvas);
+            let rect = self.view.apply(canvas);
+            let cell_size = cell_size * self.view.zoom;
+
+            // TOOL-BASED INTERACTION (presentation mode only leaves a trail)
+            if self.presentation {
+                self.apply_presentation_trail(&response, rect, cell_size);
+            } else if gesturing {
+                // The view or the fingers own the canvas until they let go
+                self.mouse_start_pos = None;
+                self.mouse_current_pos = None;
+                self.brush_stroke.end();
+                if self.touches.multi() && self.multi_finger_paint && self.selected_tool == Tool::Dye && !self.placement_mode {
+                    let (width, height) = (self.simulation.width as f32, self.simulation.height as f32);
+                    let dabs: Vec<glam::Vec2> = self
+                        .touches
+                        .dabs(&self.brush, |pos| glam::Vec2::new((pos.x - rect.left()) / cell_size, (pos.y - rect.top()) / cell_size))
+                        .into_iter()
+                        .filter(|dab| dab.x >= 0.0 && dab.y >= 0.0 && dab.x < width && dab.y < height)
+                        .collect();
+                    self.paint_dye(&dabs, self.dye_intensity * 0.6);
+                }
+            } else {
+                if response.drag_started() || response.clicked() {
+                    let color = match self.selected_tool {
+                        Tool::Dye | Tool::Emitter => Some(self.palette.current()),
+                        _ => None,
+                    };
+                    self.session.record_stroke(color);
+                    // Eyedropper only reads, gates and particles have their own clear buttons,
+                    // and Select saves one only once an element actually changes
+                    if !matches!(self.selected_tool, Tool::Eyedropper | Tool::Gate | Tool::Emitter | Tool::Select | Tool::Obstacle) {
+                        self.record_edit();
+                    }
+                }
+                // Pen pressure is held from its last reading until the touch lifts
+                if let Some(pressure) = ui.input(|i| touch_pressure(&i.events)) {
+                    self.stylus_pressure = Some(pressure);
+                }
+
+                match self.selected_tool {
+                    Tool::Dye => {
+                        if self.placement_mode {
+                            // In placement mode: click or drag to place persistent dye sources
+                            let is_interacting = response.clicked() || response.dragged();
 
-                        if is_interacting {
+                            if is_interacting {
+                                if let Some(pos) = response.interact_pointer_pos() {
+                                    let grid_x = ((pos.x - rect.left()) / cell_size) as f32;
+                                    let grid_y = ((pos.y - rect.top()) / cell_size) as f32;
+
+                                    // Only add if not too close to existing elements (avoid overlap)
+                                    let min_spacing = 5.0; // Grid cells
+                                    let should_add = self.persistent_elements.iter().all(|elem| {
+                                        let dx = elem.x - grid_x;
+                                        let dy = elem.y - grid_y;
+                                        let dist = (dx * dx + dy * dy).sqrt();
+                                        dist > min_spacing
+                                    });
+
+                                    if should_add {
+                                        self.persistent_elements.push(PersistentElement {
+                                            element_type: PersistentElementType::DyeSource {
+                                                color: self.palette.current(),
+                                                intensity: self.dye_intensity,
+                                                blend: self.brush.blend,
+                                                emission: Emission::default(),
+                                            },
+                                            x: grid_x,
+                                            y: grid_y,
+                                            radius: 3.0,
+                                            audio: None,
+                                        });
+                                    }
+                                }
+                            }
+
+                            // Placement mode stays on - user toggles it off manually
+                        } else {
+                            // Normal mode: Click/tap to add dye, hold to paint continuously
+                            if response.clicked() || response.dragged() {
+                                if let Some(pos) = response.interact_pointer_pos() {
+                                    let grid_pos = glam::Vec2::new((pos.x - rect.left()) / cell_size, (pos.y - rect.top()) / cell_size);
+                                    let x = grid_pos.x as usize;
+                                    let y = grid_pos.y as usize;
+
+                                    // Dabs fill the whole segment since the last frame, so fast drags leave no gaps
+                                    let dabs = if x < self.simulation.width && y < self.simulation.height {
+                                        self.brush_stroke.dabs(&self.brush, grid_pos)
+                                    } else {
+                                        Vec::new()
+                                    };
+                                    if !dabs.is_empty() {
+                                        // Add/remove dye under the brush
+                                        let drag_factor = if response.dragged() { 0.6 } else { 1.0 };
+                                        let strength = self.dye_intensity * drag_factor * self.brush.pressure_scale(self.stylus_pressure);
+                                        self.paint_dye(&dabs, strength);
+
+                                        let dye_color = self.palette.current();
+                                        let is_black = dye_color.0 == 0.0 && dye_color.1 == 0.0 && dye_color.2 == 0.0;
+                                        let removes = self.brush.blend == BlendMode::Subtract
+                                            || (self.brush.blend == BlendMode::Add && is_black);
+                                        if self.smoke_mode && !removes {
+                                            self.simulation.add_heat(x, y, self.heat_amount, 2.0);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    Tool::Force => {
+                        // Force tool: Click and drag to create force
+                        if response.drag_started() {
                             if let Some(pos) = response.interact_pointer_pos() {
-                                let grid_x = ((pos.x - rect.left()) / cell_size) as f32;
-                                let grid_y = ((pos.y - rect.top()) / cell_size) as f32;
-
-                                // Only add if not too close to existing elements (avoid overlap)
-                                let min_spacing = 5.0; // Grid cells
-                                let should_add = self.persistent_elements.iter().all(|elem| {
-                                    let dx = elem.x - grid_x;
-                                    let dy = elem.y - grid_y;
-                                    let dist = (dx * dx + dy * dy).sqrt();
-                                    dist > min_spacing
-                                });
+                                self.mouse_start_pos = Some(pos);
+                                self.mouse_current_pos = Some(pos);
+                            }
+                        } else if response.dragged() {
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                self.mouse_current_pos = Some(pos);
+
+                                // Apply force continuously while dragging (only if not in placement mode)
+                                if !self.placement_mode {
+                                    if let Some(start) = self.mouse_start_pos {
+                                        let x = ((start.x - rect.left()) / cell_size) as usize;
+                                        let y = ((start.y - rect.top()) / cell_size) as usize;
+
+                                        if x < self.simulation.width && y < self.simulation.height {
+                                            let force_vec = pos - start;
+                                            let scale = self.force_intensity * self.brush.pressure_scale(self.stylus_pressure);
+                                            let force = glam::Vec2::new(force_vec.x * scale, force_vec.y * scale);
+
+                                            // Apply force under the brush at the start location
+                                            let grid_x = (start.x - rect.left()) / cell_size;
+                                            let grid_y = (start.y - rect.top()) / cell_size;
+                                            for (px, py, weight) in self.brush.dab(grid_x, grid_y, self.simulation.width, self.simulation.height) {
+                                                self.simulation.add_force(px, py, force * weight, 0.0);
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        } else if response.drag_stopped() {
+                            // In placement mode, create persistent element on drag stop
+                            if self.placement_mode {
+                                if let (Some(start), Some(current)) = (self.mouse_start_pos, self.mouse_current_pos) {
+                                    let grid_x = ((start.x - rect.left()) / cell_size) as f32;
+                                    let grid_y = ((start.y - rect.top()) / cell_size) as f32;
+
+                                    let dx = current.x - start.x;
+                                    let dy = current.y - start.y;
 
-                                if should_add {
                                     self.persistent_elements.push(PersistentElement {
-                                        element_type: PersistentElementType::DyeSource {
-                                            color: self.dye_colors[self.current_dye_index],
-                                            intensity: self.dye_intensity,
+                                        element_type: PersistentElementType::ForceSource {
+                                            direction: (dx, dy),
+                                            intensity: self.force_intensity,
                                         },
                                         x: grid_x,
                                         y: grid_y,
-                                        radius: 3.0,
+                                        radius: self.brush.radius,
+                                        audio: None,
                                     });
+                                    // Placement mode stays on
                                 }
                             }
-                        }
 
-                        // Placement mode stays on - user toggles it off manually
-                    } else {
-                        // Normal mode: Click/tap to add dye, hold to paint continuously
-                        if response.clicked() || response.dragged() {
+                            self.mouse_start_pos = None;
+                            self.mouse_current_pos = None;
+                        }
+                    },
+                    Tool::Eyedropper => {
+                        // Eyedropper tool: Click to sample color (no placement mode)
+                        if response.clicked() {
                             if let Some(pos) = response.interact_pointer_pos() {
                                 let x = ((pos.x - rect.left()) / cell_size) as usize;
                                 let y = ((pos.y - rect.top()) / cell_size) as usize;
 
                                 if x < self.simulation.width && y < self.simulation.height {
-                                    let dye_color = self.dye_colors[self.current_dye_index];
-
-                                    // Check if black (negative dye) is selected
-                                    let is_negative = dye_color.0 == 0.0 && dye_color.1 == 0.0 && dye_color.2 == 0.0;
-
-                                    // Add/remove dye in a small circular pattern
-                                    for dy in -2..=2 {
-                                        for dx in -2..=2 {
-                                            let px = (x as i32 + dx) as usize;
-                                            let py = (y as i32 + dy) as usize;
-
-                                            if px < self.simulation.width && py < self.simulation.height {
-                                                let dist_sq = (dx * dx + dy * dy) as f32;
-                                                if dist_sq <= 4.0 {
-                                                    let falloff = 1.0 - dist_sq / 4.0;
-                                                    let drag_factor = if response.dragged() { 0.6 } else { 1.0 };
-                                                    let intensity = falloff * self.dye_intensity * drag_factor;
-
-                                                    let idx = py * self.simulation.width + px;
-
-                                                    if is_negative {
-                                                        // Black removes dye
-                                                        self.simulation.dye_r[idx] = (self.simulation.dye_r[idx] - intensity).max(0.0);
-                                                        self.simulation.dye_g[idx] = (self.simulation.dye_g[idx] - intensity).max(0.0);
-                                                        self.simulation.dye_b[idx] = (self.simulation.dye_b[idx] - intensity).max(0.0);
-                                                    } else {
-                                                        // Normal colors add dye
-                                                        self.simulation.add_dye(px, py, (
-                                                            dye_color.0 * intensity,
-                                                            dye_color.1 * intensity,
-                                                            dye_color.2 * intensity
-                                                        ));
-                                                    }
-                                                }
-                                            }
+                                    let idx = y * self.simulation.width + x;
+                                    let r = self.simulation.dye_r[idx];
+                                    let g = self.simulation.dye_g[idx];
+                                    let b = self.simulation.dye_b[idx];
+
+                                    // Store the raw color values for display
+                                    self.sampled_color = Some((r, g, b));
+                                    self.sample_hex = palette::to_hex(palette::color_from_sample((r, g, b)));
+                                }
+                            }
+                        }
+                    },
+                    Tool::Attractor => {
+                        if self.placement_mode {
+                            // In placement mode: click to place persistent attractor
+                            if response.clicked() {
+                                if let Some(pos) = response.interact_pointer_pos() {
+                                    let grid_x = ((pos.x - rect.left()) / cell_size) as f32;
+                                    let grid_y = ((pos.y - rect.top()) / cell_size) as f32;
+
+                                    let strength = self.attractor_strength;
+                                    self.persistent_elements.push(PersistentElement {
+                                        element_type: if self.attractor_repel {
+                                            PersistentElementType::RepulsorSource { strength }
+                                        } else {
+                                            PersistentElementType::AttractorSource { strength, absorb: self.attractor_absorb }
+                                        },
+                                        x: grid_x,
+                                        y: grid_y,
+                                        radius: self.attractor_radius / cell_size,
+                                        audio: None,
+                                    });
+                                    // Placement mode stays on
+                                }
+                            }
+                        } else {
+                            // Normal mode: Apply temporary attractor while holding
+                            if response.clicked() || response.dragged() {
+                                if let Some(pos) = response.interact_pointer_pos() {
+                                    self.attractor_pos = Some(pos);
+
+                                    let attractor_x = ((pos.x - rect.left()) / cell_size) as f32;
+                                    let attractor_y = ((pos.y - rect.top()) / cell_size) as f32;
+
+                                    // Store grid position
+                                    self.attractor_grid_pos = Some((attractor_x, attractor_y));
+
+                                    let radius_cells = self.attractor_radius / cell_size;
+                                    if self.attractor_repel {
+                                        self.simulation.add_repulsor(attractor_x, attractor_y, radius_cells, self.attractor_strength);
+                                    } else {
+                                        self.simulation.add_attractor(attractor_x, attractor_y, radius_cells, self.attractor_strength);
+                                        if self.attractor_absorb {
+                                            let center = glam::Vec2::new(attractor_x, attractor_y);
+                                            self.simulation.absorb_dye(center, radius_cells, ABSORB_RATE);
                                         }
                                     }
                                 }
+                            } else if response.drag_stopped() || !response.hovered() {
+                                self.attractor_pos = None;
+                                self.attractor_grid_pos = None;
                             }
                         }
-                    }
-                },
-                Tool::Force => {
-                    // Force tool: Click and drag to create force
-                    if response.drag_started() {
-                        if let Some(pos) = response.interact_pointer_pos() {
-                            self.mouse_start_pos = Some(pos);
-                            self.mouse_current_pos = Some(pos);
+                    },
+                    Tool::Eraser if self.eraser_dye => {
+                        // Dye eraser: fade dye under the brush along the stroke
+                        if response.clicked() || response.dragged() {
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                self.eraser_pos = Some(pos);
+                                let grid_pos = glam::Vec2::new((pos.x - rect.left()) / cell_size, (pos.y - rect.top()) / cell_size);
+                                let (width, height) = (self.simulation.width, self.simulation.height);
+                                let dabs = self.brush_stroke.dabs(&self.brush, grid_pos);
+                                let cells = dabs.iter().flat_map(|dab| self.brush.dab(dab.x, dab.y, width, height));
+                                for (px, py, weight) in cells.collect::<Vec<_>>() {
+                                    self.simulation.fade_dye(px, py, weight * self.eraser_fade, self.eraser_velocity);
+                                }
+                            }
+                        } else if response.drag_stopped() || !response.hovered() {
+                            self.eraser_pos = None;
                         }
-                    } else if response.dragged() {
-                        if let Some(pos) = response.interact_pointer_pos() {
-                            self.mouse_current_pos = Some(pos);
-
-                            // Apply force continuously while dragging (only if not in placement mode)
-                            if !self.placement_mode {
-                                if let Some(start) = self.mouse_start_pos {
-                                    let x = ((start.x - rect.left()) / cell_size) as usize;
-                                    let y = ((start.y - rect.top()) / cell_size) as usize;
+                    },
+                    Tool::Eraser => {
+                        // Eraser tool: Remove persistent elements within radius (no placement mode)
+                        if response.clicked() || response.dragged() {
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                self.eraser_pos = Some(pos);
 
-                                    if x < self.simulation.width && y < self.simulation.height {
-                                        let force_vec = pos - start;
-                                        let force = glam::Vec2::new(force_vec.x * self.force_intensity, force_vec.y * self.force_intensity);
+                                let erase_x = ((pos.x - rect.left()) / cell_size) as f32;
+                                let erase_y = ((pos.y - rect.top()) / cell_size) as f32;
+                                let erase_radius = self.brush.radius;
 
-                                        // Apply force at start location
-                                        self.simulation.add_force(x, y, force, 3.0);
+                                // Remove elements within eraser radius, last first so
+                                // the timeline renumbers the rest correctly
+                                let count = self.persistent_elements.len();
+                                for index in (0..count).rev() {
+                                    let elem = &self.persistent_elements[index];
+                                    let dx = elem.x - erase_x;
+                                    let dy = elem.y - erase_y;
+                                    if (dx * dx + dy * dy).sqrt() <= erase_radius {
+                                        self.persistent_elements.remove(index);
+                                        self.timeline.element_removed(index);
                                     }
                                 }
+                                if self.persistent_elements.len() != count {
+                                    self.select_element(None);
+                                }
+                            }
+                        } else if response.drag_stopped() || !response.hovered() {
+                            self.eraser_pos = None;
+                        }
+                    },
+                    Tool::Select => {
+                        if let Some(pos) = response.interact_pointer_pos() {
+                            let grid_x = (pos.x - rect.left()) / cell_size;
+                            let grid_y = (pos.y - rect.top()) / cell_size;
+                            if response.drag_started() || response.clicked() {
+                                let picked = self.element_at(grid_x, grid_y, 12.0 / cell_size);
+                                if picked != self.selected_element {
+                                    self.select_element(picked);
+                                }
+                            } else if response.dragged()
+                                && let Some(index) = self.selected_element.filter(|&i| i < self.persistent_elements.len())
+                            {
+                                let mut elem = self.persistent_elements[index];
+                                let delta = response.drag_delta() / cell_size;
+                                elem.x = (elem.x + delta.x).clamp(0.0, self.simulation.width as f32 - 1.0);
+                                elem.y = (elem.y + delta.y).clamp(0.0, self.simulation.height as f32 - 1.0);
+                                self.update_selected_element(index, elem);
+                            }
+                        }
+                    },
+                    Tool::Heat => {
+                        if self.placement_mode {
+                            // In placement mode: click to place a persistent heat source
+                            if response.clicked()
+                                && let Some(pos) = response.interact_pointer_pos()
+                            {
+                                let grid_x = (pos.x - rect.left()) / cell_size;
+                                let grid_y = (pos.y - rect.top()) / cell_size;
+
+                                self.persistent_elements.push(PersistentElement {
+                                    element_type: PersistentElementType::HeatSource {
+                                        amount: self.heat_amount,
+                                    },
+                                    x: grid_x,
+                                    y: grid_y,
+                                    radius: self.heat_radius,
+                                    audio: None,
+                                });
+                            }
+                        } else if response.clicked() || response.dragged() {
+                            // Normal mode: heat (or cool) under the pointer while held
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                self.heat_pos = Some(pos);
+                                let x = ((pos.x - rect.left()) / cell_size) as usize;
+                                let y = ((pos.y - rect.top()) / cell_size) as usize;
+                                self.simulation.add_heat(x, y, self.heat_amount, self.heat_radius);
+                            }
+                        } else if response.drag_stopped() || !response.hovered() {
+                            self.heat_pos = None;
+                        }
+                    },
+                    Tool::Vortex => {
+                        if self.placement_mode {
+                            // In placement mode: click to place a persistent vortex
+                            if response.clicked()
+                                && let Some(pos) = response.interact_pointer_pos()
+                            {
+                                let grid_x = (pos.x - rect.left()) / cell_size;
+                                let grid_y = (pos.y - rect.top()) / cell_size;
+
+                                self.persistent_elements.push(PersistentElement {
+                                    element_type: PersistentElementType::VortexSource {
+                                        strength: self.vortex_spin(),
+                                        falloff: self.vortex_falloff,
+                                    },
+                                    x: grid_x,
+                                    y: grid_y,
+                                    radius: self.vortex_radius,
+                                    audio: None,
+                                });
+                            }
+                        } else if response.clicked() || response.dragged() {
+                            // Normal mode: spin the fluid under the pointer while held
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                self.vortex_pos = Some(pos);
+                                let grid_x = (pos.x - rect.left()) / cell_size;
+                                let grid_y = (pos.y - rect.top()) / cell_size;
+                                let spin = self.vortex_spin();
+                                self.simulation.add_vortex(grid_x, grid_y, self.vortex_radius, spin, self.vortex_falloff);
+                            }
+                        } else if response.drag_stopped() || !response.hovered() {
+                            self.vortex_pos = None;
+                        }
+                    },
+                    Tool::Fan => {
+                        // Fan tool: drag to draw a strip, which is always placed
+                        if response.drag_started() {
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                self.mouse_start_pos = Some(pos);
+                                self.mouse_current_pos = Some(pos);
+                            }
+                        } else if response.dragged() {
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                self.mouse_current_pos = Some(pos);
                             }
-                        }
-                    } else if response.drag_stopped() {
-                        // In placement mode, create persistent element on drag stop
-                        if self.placement_mode {
+                        } else if response.drag_stopped() {
                             if let (Some(start), Some(current)) = (self.mouse_start_pos, self.mouse_current_pos) {
-                                let grid_x = ((start.x - rect.left()) / cell_size) as f32;
-                                let grid_y = ((start.y - rect.top()) / cell_size) as f32;
+                                let a = glam::Vec2::new((start.x - rect.left()) / cell_size, (start.y - rect.top()) / cell_size);
+                                let b = glam::Vec2::new((current.x - rect.left()) / cell_size, (current.y - rect.top()) / cell_size);
 
-                                let dx = current.x - start.x;
-                                let dy = current.y - start.y;
+                                // Ignore accidental clicks shorter than a cell
+                                if (b - a).length() >= 1.0 {
+                                    let along = (b - a).normalize();
+                                    let direction = if self.fan_along { along } else { along.perp() };
+                                    let velocity = direction * self.fan_speed;
+                                    let center = (a + b) * 0.5;
+                                    self.persistent_elements.push(PersistentElement {
+                                        element_type: PersistentElementType::FanStrip {
+                                            span: (b.x - a.x, b.y - a.y),
+                                            velocity: (velocity.x, velocity.y),
+                                        },
+                                        x: center.x,
+                                        y: center.y,
+                                        radius: self.fan_width,
+                                        audio: None,
+                                    });
+                                }
+                            }
 
-                                self.persistent_elements.push(PersistentElement {
-                                    element_type: PersistentElementType::ForceSource {
-                                        direction: (dx, dy),
-                                        intensity: self.force_intensity,
-                                    },
-                                    x: grid_x,
-                                    y: grid_y,
-                                    radius: 3.0,
+                            self.mouse_start_pos = None;
+                            self.mouse_current_pos = None;
+                        }
+                    },
+                    Tool::Obstacle => {
+                        // Grab the obstacle under the pointer, or drop a new one there,
+                        // and steer it toward the pointer until released
+                        if let Some(pos) = response.interact_pointer_pos() {
+                            let point = glam::Vec2::new((pos.x - rect.left()) / cell_size, (pos.y - rect.top()) / cell_size);
+                            if response.drag_started() || response.clicked() {
+                                let grabbed = self.simulation.moving_obstacles.iter().rposition(|obstacle| obstacle.contains(point));
+                                let index = grabbed.unwrap_or_else(|| {
+                                    self.simulation.moving_obstacles.push(self.new_obstacle(point));
+                                    self.simulation.moving_obstacles.len() - 1
                                 });
-                                // Placement mode stays on
+                                self.dragged_obstacle = Some(index);
+                            }
+                            if response.dragged() && self.dragged_obstacle.is_some() {
+                                self.obstacle_target = Some(point);
+                                // Paused, it just follows; running, the steps carry it
+                                let dragged = self.dragged_obstacle.and_then(|i| self.simulation.moving_obstacles.get_mut(i));
+                                if let (true, Some(obstacle)) = (self.paused, dragged) {
+                                    obstacle.center = point;
+                                }
                             }
                         }
-
-                        self.mouse_start_pos = None;
-                        self.mouse_current_pos = None;
-                    }
-                },
-                Tool::Eyedropper => {
-                    // Eyedropper tool: Click to sample color (no placement mode)
-                    if response.clicked() {
-                        if let Some(pos) = response.interact_pointer_pos() {
-                            let x = ((pos.x - rect.left()) / cell_size) as usize;
-                            let y = ((pos.y - rect.top()) / cell_size) as usize;
-
-                            if x < self.simulation.width && y < self.simulation.height {
-                                let idx = y * self.simulation.width + x;
-                                let r = self.simulation.dye_r[idx];
-                                let g = self.simulation.dye_g[idx];
-                                let b = self.simulation.dye_b[idx];
-
-                                // Store the raw color values for display
-                                self.sampled_color = Some((r, g, b));
+                        if response.drag_stopped() || response.clicked() {
+                            // Driven obstacles stop where they are left, floating ones are thrown
+                            if let Some(obstacle) = self.dragged_obstacle.and_then(|i| self.simulation.moving_obstacles.get_mut(i))
+                                && obstacle.density.is_none()
+                            {
+                                obstacle.velocity = glam::Vec2::ZERO;
                             }
+                            self.dragged_obstacle = None;
+                            self.obstacle_target = None;
                         }
-                    }
-                },
-                Tool::Attractor => {
-                    if self.placement_mode {
-                        // In placement mode: click to place persistent attractor
-                        if response.clicked() {
+                    },
+                    Tool::Viscosity => {
+                        // Viscosity tool: paint thick and runny regions
+                        if (response.clicked() || response.dragged())
+                            && let Some(pos) = response.interact_pointer_pos()
+                        {
+                            let grid_pos = glam::Vec2::new((pos.x - rect.left()) / cell_size, (pos.y - rect.top()) / cell_size);
+                            for dab in self.brush_stroke.dabs(&self.brush, grid_pos) {
+                                self.simulation.paint_viscosity(&self.brush, dab.x, dab.y, self.viscosity_target);
+                            }
+                        }
+                    },
+                    Tool::Turbulence => {
+                        // Turbulence tool: paint where the fluid gets stirred
+                        if (response.clicked() || response.dragged())
+                            && let Some(pos) = response.interact_pointer_pos()
+                        {
+                            let grid_pos = glam::Vec2::new((pos.x - rect.left()) / cell_size, (pos.y - rect.top()) / cell_size);
+                            let (width, height) = (self.simulation.width, self.simulation.height);
+                            let amount = if self.turbulence_erase { -0.25 } else { 0.25 };
+                            let dabs = self.brush_stroke.dabs(&self.brush, grid_pos);
+                            // Painting switches to regions, turning turbulence on if needed
+                            let turbulence = self.simulation.turbulence.get_or_insert_with(|| {
+                                self.parked_turbulence.take().unwrap_or_default()
+                            });
+                            turbulence.everywhere = false;
+                            for dab in dabs {
+                                turbulence.paint(&self.brush, dab.x, dab.y, width, height, amount);
+                            }
+                        }
+                    },
+                    Tool::Gate => {
+                        // Gate tool: drag to draw a measurement line
+                        if response.drag_started() {
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                self.mouse_start_pos = Some(pos);
+                                self.mouse_current_pos = Some(pos);
+                            }
+                        } else if response.dragged() {
                             if let Some(pos) = response.interact_pointer_pos() {
-                                let grid_x = ((pos.x - rect.left()) / cell_size) as f32;
-                                let grid_y = ((pos.y - rect.top()) / cell_size) as f32;
+                                self.mouse_current_pos = Some(pos);
+                            }
+                        } else if response.drag_stopped() {
+                            if let (Some(start), Some(current)) = (self.mouse_start_pos, self.mouse_current_pos) {
+                                let a = glam::Vec2::new((start.x - rect.left()) / cell_size, (start.y - rect.top()) / cell_size);
+                                let b = glam::Vec2::new((current.x - rect.left()) / cell_size, (current.y - rect.top()) / cell_size);
 
-                                self.persistent_elements.push(PersistentElement {
-                                    element_type: PersistentElementType::AttractorSource {
-                                        strength: self.attractor_strength,
-                                    },
-                                    x: grid_x,
-                                    y: grid_y,
-                                    radius: self.attractor_radius / cell_size,
-                                });
-                                // Placement mode stays on
+                                // Ignore accidental clicks shorter than a cell
+                                if (b - a).length() >= 1.0 {
+                                    let name = format!("G{}", self.gates.len() + 1);
+                                    self.gates.add(name, a, b);
+                                }
                             }
+
+                            self.mouse_start_pos = None;
+                            self.mouse_current_pos = None;
                         }
-                    } else {
-                        // Normal mode: Apply temporary attractor while holding
+                    },
+                    Tool::Emitter => {
+                        // Emitter tool: spray tracer particles while held
                         if response.clicked() || response.dragged() {
                             if let Some(pos) = response.interact_pointer_pos() {
-                                self.attractor_pos = Some(pos);
-
-                                let attractor_x = ((pos.x - rect.left()) / cell_size) as f32;
-                                let attractor_y = ((pos.y - rect.top()) / cell_size) as f32;
-
-                                // Store grid position
-                                self.attractor_grid_pos = Some((attractor_x, attractor_y));
-
-                                let radius_cells = self.attractor_radius / cell_size;
-
-                                // Point sink with proper fluid dynamics formula
-                                let smoothing = 2.0;
-                                let dead_zone = radius_cells * 0.2;
-
-                                for y in 0..self.simulation.height {
-                                    for x in 0..self.simulation.width {
-                                        let dx = x as f32 - attractor_x;
-                                        let dy = y as f32 - attractor_y;
-                                        let r_squared = dx * dx + dy * dy;
-                                        let r = r_squared.sqrt();
-
-                                        if r > dead_zone && r < radius_cells {
-                                            let idx = y * self.simulation.width + x;
-
-                                            let factor = -self.attractor_strength /
-                                                (2.0 * std::f32::consts::PI * (r_squared + smoothing * smoothing));
-
-                                            self.simulation.velocity_x[idx] += factor * dx;
-                                            self.simulation.velocity_y[idx] += factor * dy;
-
-                                            let inner_radius = radius_cells * 0.8;
-                                            if r > inner_radius {
-                                                let damping_factor = ((r - inner_radius) / (radius_cells - inner_radius)).powi(2);
-                                                let damping_coeff = 1.0 - damping_factor * 0.2;
-
-                                                self.simulation.velocity_x[idx] *= damping_coeff;
-                                                self.simulation.velocity_y[idx] *= damping_coeff;
-                                            }
-                                        }
-                                    }
-                                }
+                                self.emitter_pos = Some(pos);
+                                let grid_x = (pos.x - rect.left()) / cell_size;
+                                let grid_y = (pos.y - rect.top()) / cell_size;
+                                let color = self.palette.current();
+                                self.particles.emit(grid_x, grid_y, self.emit_radius, self.emit_count, color);
+                                self.show_particles = true;
                             }
                         } else if response.drag_stopped() || !response.hovered() {
-                            self.attractor_pos = None;
-                            self.attractor_grid_pos = None;
-                        }
-                    }
-                },
-                Tool::Eraser => {
-                    // Eraser tool: Remove persistent elements within radius (no placement mode)
-                    if response.clicked() || response.dragged() {
-                        if let Some(pos) = response.interact_pointer_pos() {
-                            self.eraser_pos = Some(pos);
-
-                            let erase_x = ((pos.x - rect.left()) / cell_size) as f32;
-                            let erase_y = ((pos.y - rect.top()) / cell_size) as f32;
-                            let erase_radius = self.eraser_radius / cell_size;
-
-                            // Remove elements within eraser radius
-                            self.persistent_elements.retain(|elem| {
-                                let dx = elem.x - erase_x;
-                                let dy = elem.y - erase_y;
-                                let dist = (dx * dx + dy * dy).sqrt();
-                                dist > erase_radius // Keep if outside eraser radius
-                            });
+                            self.emitter_pos = None;
                         }
-                    } else if response.drag_stopped() || !response.hovered() {
-                        self.eraser_pos = None;
-                    }
-                },
+                    },
+                }
+
+                if !ui.input(|i| i.pointer.any_down()) {
+                    self.brush_stroke.end();
+                    self.stylus_pressure = None;
+                }
             }
 
-            // Render simulation
-            let painter = ui.painter();
+            // Render simulation, clipped to the canvas when zoomed in
+            self.update_canvas_texture(ui.ctx());
+            let painter = ui.painter_at(canvas);
 
             // Render persistent elements (draw first, under the fluid)
             for elem in &self.persistent_elements {
@@ -1060,42 +4167,175 @@ impl eframe::App for InteractiveApp {
                             egui::Stroke::new(1.0, egui::Color32::from_rgba_premultiplied(255, 200, 100, 128)));
                         painter.circle_filled(pos, 3.0, egui::Color32::from_rgb(255, 200, 100));
                     },
+                    PersistentElementType::VortexSource { strength, .. } => {
+                        // Render as circle with an arrow along the spin
+                        let color = egui::Color32::from_rgb(180, 140, 255);
+                        let radius = elem.radius * cell_size;
+                        let tangent = if strength >= 0.0 { egui::vec2(0.0, 1.0) } else { egui::vec2(0.0, -1.0) };
+                        painter.circle_stroke(pos, radius, egui::Stroke::new(2.0, color));
+                        painter.arrow(pos + egui::vec2(radius, 0.0), tangent * 10.0, egui::Stroke::new(2.0, color));
+                        painter.circle_filled(pos, 3.0, color);
+                    },
+                    PersistentElementType::FanStrip { span, velocity } => {
+                        // Render as a translucent band with an arrow for the current
+                        let color = egui::Color32::from_rgb(120, 200, 255);
+                        let half = egui::vec2(span.0, span.1) * cell_size * 0.5;
+                        painter.line_segment(
+                            [pos - half, pos + half],
+                            egui::Stroke::new(elem.radius * cell_size * 2.0, egui::Color32::from_rgba_unmultiplied(120, 200, 255, 50)),
+                        );
+                        painter.line_segment([pos - half, pos + half], egui::Stroke::new(1.0, color));
+                        let arrow = egui::vec2(velocity.0, velocity.1).normalized() * (elem.radius * cell_size).max(12.0);
+                        painter.arrow(pos, arrow, egui::Stroke::new(2.0, color));
+                    },
+                    PersistentElementType::RepulsorSource { .. } => {
+                        // Render as circle with short outward spokes
+                        let color = egui::Color32::from_rgb(120, 230, 160);
+                        let radius = elem.radius * cell_size;
+                        painter.circle_stroke(pos, radius, egui::Stroke::new(2.0, color));
+                        for i in 0..8 {
+                            let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+                            let dir = egui::Vec2::angled(angle);
+                            painter.line_segment([pos + dir * radius * 0.3, pos + dir * radius * 0.6], egui::Stroke::new(1.0, color));
+                        }
+                        painter.circle_filled(pos, 3.0, color);
+                    },
+                    PersistentElementType::HeatSource { amount } => {
+                        // Warm sources in orange, cooling sources in blue
+                        let color = if amount >= 0.0 {
+                            egui::Color32::from_rgb(255, 120, 40)
+                        } else {
+                            egui::Color32::from_rgb(80, 160, 255)
+                        };
+                        painter.circle_stroke(pos, elem.radius * cell_size, egui::Stroke::new(2.0, color));
+                        painter.circle_filled(pos, 3.0, color);
+                    },
+                }
+            }
+
+            // The whole field is one texture, scaled up with nearest filtering
+            if let Some(texture) = &self.canvas_texture {
+                let uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::Pos2::new(1.0, 1.0));
+                painter.image(texture.id(), rect, uv, egui::Color32::WHITE);
+            }
+
+            // Strike through elements the timeline has switched off
+            if !self.presentation {
+                for (i, elem) in self.persistent_elements.iter().enumerate() {
+                    if !self.timeline.enabled(i) {
+                        let pos = egui::Pos2::new(rect.left() + elem.x * cell_size, rect.top() + elem.y * cell_size);
+                        let r = elem.radius * cell_size;
+                        let stroke = egui::Stroke::new(1.5, egui::Color32::GRAY);
+                        painter.circle_stroke(pos, r, stroke);
+                        painter.line_segment([pos + egui::vec2(-r, r) * 0.7, pos + egui::vec2(r, -r) * 0.7], stroke);
+                    }
                 }
             }
 
-            // Render each cell
-            for y in 0..self.simulation.height {
-                for x in 0..self.simulation.width {
-                    let idx = y * self.simulation.width + x;
+            // Outline the selected element above the fluid
+            if self.selected_tool == Tool::Select
+                && !self.presentation
+                && let Some(elem) = self.selected_element.and_then(|i| self.persistent_elements.get(i))
+            {
+                let pos = egui::Pos2::new(rect.left() + elem.x * cell_size, rect.top() + elem.y * cell_size);
+                let radius = elem.radius * cell_size + 3.0;
+                painter.circle_stroke(pos, radius, egui::Stroke::new(2.0, egui::Color32::YELLOW));
+            }
 
-                    // Get dye color with Reinhard tone mapping for HDR values
-                    // Maps [0, ∞) to [0, 1) smoothly
-                    let r_raw = self.simulation.dye_r[idx];
-                    let g_raw = self.simulation.dye_g[idx];
-                    let b_raw = self.simulation.dye_b[idx];
+            // Draw streamlines seeded every few cells
+            if self.show_streamlines && !self.presentation {
+                let spacing = 4.0 * self.resolution_scale as f32;
+                let stroke = egui::Stroke::new(1.0, egui::Color32::from_white_alpha(140));
+                let mut y = spacing * 0.5;
+                while y < self.simulation.height as f32 - 1.0 {
+                    let mut x = spacing * 0.5;
+                    while x < self.simulation.width as f32 - 1.0 {
+                        let line = trace_streamline(&self.simulation, glam::Vec2::new(x, y), 20);
+                        if line.len() > 1 {
+                            let points = line
+                                .iter()
+                                .map(|p| egui::Pos2::new(rect.left() + p.x * cell_size, rect.top() + p.y * cell_size))
+                                .collect();
+                            painter.add(egui::Shape::line(points, stroke));
+                        }
+                        x += spacing;
+                    }
+                    y += spacing;
+                }
+            }
 
-                    // Reinhard tone mapping: x / (1 + x)
-                    let r = (r_raw / (1.0 + r_raw)).max(0.0);
-                    let g = (g_raw / (1.0 + g_raw)).max(0.0);
-                    let b = (b_raw / (1.0 + b_raw)).max(0.0);
+            // Draw velocity arrows at cell centers of a subsampled lattice
+            if self.show_quiver && !self.presentation {
+                let spacing = self.quiver_spacing.max(1);
+                let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 255, 255));
+                for y in (spacing / 2..self.simulation.height).step_by(spacing) {
+                    for x in (spacing / 2..self.simulation.width).step_by(spacing) {
+                        let idx = y * self.simulation.width + x;
+                        let v = egui::Vec2::new(self.simulation.velocity_x[idx], self.simulation.velocity_y[idx]);
+                        let arrow = v * self.quiver_scale * cell_size;
+                        // Skip sub-pixel arrows so still fluid stays clean
+                        if arrow.length() < 1.0 {
+                            continue;
+                        }
+                        let origin = egui::Pos2::new(
+                            rect.left() + (x as f32 + 0.5) * cell_size,
+                            rect.top() + (y as f32 + 0.5) * cell_size,
+                        );
+                        painter.arrow(origin, arrow, stroke);
+                    }
+                }
+            }
 
-                    // Create color based on dye concentration
-                    let color = egui::Color32::from_rgb(
-                        (r * 255.0) as u8,
-                        (g * 255.0) as u8,
-                        (b * 255.0) as u8
-                    );
+            // Draw flux gates with their positive direction and latest volume flux
+            let visible_gates = if self.presentation { &[][..] } else { &self.gates.gates[..] };
+            for gate in visible_gates {
+                let a = egui::Pos2::new(rect.left() + gate.start.x * cell_size, rect.top() + gate.start.y * cell_size);
+                let b = egui::Pos2::new(rect.left() + gate.end.x * cell_size, rect.top() + gate.end.y * cell_size);
+                let gate_color = egui::Color32::from_rgb(255, 230, 80);
+                painter.line_segment([a, b], egui::Stroke::new(2.0, gate_color));
 
-                    let cell_rect = egui::Rect::from_min_size(
-                        egui::Pos2::new(rect.left() + x as f32 * cell_size,
-                                       rect.top() + y as f32 * cell_size),
-                        egui::Vec2::new(cell_size.ceil() + 0.5, cell_size.ceil() + 0.5)
-                    );
+                let mid = egui::Pos2::new((a.x + b.x) * 0.5, (a.y + b.y) * 0.5);
+                let n = gate.normal();
+                painter.arrow(mid, egui::Vec2::new(n.x * 12.0, n.y * 12.0), egui::Stroke::new(1.5, gate_color));
 
-                    painter.rect_filled(cell_rect, 0.0, color);
+                let flux = gate.latest().map_or(0.0, |f| f.volume);
+                painter.text(
+                    a,
+                    egui::Align2::LEFT_BOTTOM,
+                    format!("{} {:+.3}", gate.name, flux),
+                    egui::FontId::monospace(11.0),
+                    gate_color,
+                );
+            }
+
+            // Draw tracer particles
+            if self.show_particles {
+                let dot = (cell_size * 0.3).clamp(1.0, 3.0);
+                for p in &self.particles.particles {
+                    let center = egui::Pos2::new(rect.left() + p.x * cell_size, rect.top() + p.y * cell_size);
+                    // Black dye would make invisible particles; draw those white
+                    let color = if p.color.0 + p.color.1 + p.color.2 < 0.1 {
+                        egui::Color32::WHITE
+                    } else {
+                        egui::Color32::from_rgb(
+                            (p.color.0 * 255.0) as u8,
+                            (p.color.1 * 255.0) as u8,
+                            (p.color.2 * 255.0) as u8,
+                        )
+                    };
+                    painter.circle_filled(center, dot, color);
                 }
             }
 
+            // Draw emitter radius
+            if let Some(pos) = self.emitter_pos {
+                painter.circle_stroke(
+                    pos,
+                    self.emit_radius * cell_size,
+                    egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 255, 255))
+                );
+            }
+
             // Draw drag indicator if dragging
             if let (Some(start), Some(current)) = (self.mouse_start_pos, self.mouse_current_pos) {
                 painter.line_segment(
@@ -1116,110 +4356,109 @@ impl eframe::App for InteractiveApp {
                 painter.circle_filled(pos, 3.0, egui::Color32::from_rgb(255, 255, 255));
             }
 
+            // Draw heat brush radius
+            if let Some(pos) = self.heat_pos {
+                painter.circle_stroke(
+                    pos,
+                    self.heat_radius * cell_size,
+                    egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 120, 40))
+                );
+            }
+
+            // Draw vortex radius with an arrow along the spin
+            if let Some(pos) = self.vortex_pos {
+                let radius = self.vortex_radius * cell_size;
+                let color = egui::Color32::from_rgb(180, 140, 255);
+                let tangent = if self.vortex_clockwise { egui::vec2(0.0, 1.0) } else { egui::vec2(0.0, -1.0) };
+                painter.circle_stroke(pos, radius, egui::Stroke::new(2.0, color));
+                painter.arrow(pos + egui::vec2(radius, 0.0), tangent * 12.0, egui::Stroke::new(2.0, color));
+            }
+
             // Draw eraser radius circle
             if let Some(pos) = self.eraser_pos {
                 painter.circle_stroke(
                     pos,
-                    self.eraser_radius,
+                    self.brush.radius * cell_size,
                     egui::Stroke::new(2.0, egui::Color32::from_rgb(255, 100, 100))
                 );
                 painter.circle_filled(pos, 3.0, egui::Color32::from_rgb(255, 100, 100));
             }
 
-            // Update simulation if not paused
-            // Run 1 step per frame at all resolutions
+            if self.show_stats && !self.presentation {
+                self.stats_overlay(&painter, canvas, rect, cell_size);
+            }
+            if self.show_perf && !self.presentation {
+                self.perf_overlay(&painter, canvas);
+            }
+
+            #[cfg(all(feature = "control", not(target_arch = "wasm32")))]
+            {
+                let controls = self.osc.as_ref().map(|osc| osc.poll()).unwrap_or_default();
+                #[cfg(feature = "midi")]
+                let controls = [controls, self.midi.as_ref().map(|midi| midi.poll()).unwrap_or_default()].concat();
+                for control in controls {
+                    self.apply_control(control);
+                }
+            }
+
+            // Steps are paced by the clock rather than the display, so the
+            // flow runs at the same speed at any refresh rate
             if !self.paused {
-                // Apply all persistent elements
-                for elem in &self.persistent_elements {
-                    match elem.element_type {
-                        PersistentElementType::DyeSource { color, intensity } => {
-                            let x = elem.x.round() as usize;
-                            let y = elem.y.round() as usize;
-                            if x < self.simulation.width && y < self.simulation.height {
-                                // Check if black (negative dye) is selected
-                                let is_negative = color.0 == 0.0 && color.1 == 0.0 && color.2 == 0.0;
-
-                                if is_negative {
-                                    // Black removes dye - apply in a small area
-                                    for dy in -2..=2 {
-                                        for dx in -2..=2 {
-                                            let px = (x as i32 + dx) as usize;
-                                            let py = (y as i32 + dy) as usize;
-
-                                            if px < self.simulation.width && py < self.simulation.height {
-                                                let dist_sq = (dx * dx + dy * dy) as f32;
-                                                if dist_sq <= 4.0 {
-                                                    let falloff = 1.0 - dist_sq / 4.0;
-                                                    let remove_intensity = falloff * intensity * 0.3; // Scale down for persistent
-
-                                                    let idx = py * self.simulation.width + px;
-                                                    self.simulation.dye_r[idx] = (self.simulation.dye_r[idx] - remove_intensity).max(0.0);
-                                                    self.simulation.dye_g[idx] = (self.simulation.dye_g[idx] - remove_intensity).max(0.0);
-                                                    self.simulation.dye_b[idx] = (self.simulation.dye_b[idx] - remove_intensity).max(0.0);
-                                                }
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    // Normal colors add dye
-                                    self.simulation.add_dye(x, y, (
-                                        color.0 * intensity,
-                                        color.1 * intensity,
-                                        color.2 * intensity,
-                                    ));
-                                }
-                            }
-                        },
-                        PersistentElementType::ForceSource { direction, intensity } => {
-                            let x = elem.x.round() as usize;
-                            let y = elem.y.round() as usize;
-                            if x < self.simulation.width && y < self.simulation.height {
-                                let force = glam::Vec2::new(
-                                    direction.0 * intensity,
-                                    direction.1 * intensity,
-                                );
-                                self.simulation.add_force(x, y, force, elem.radius);
-                            }
-                        },
-                        PersistentElementType::AttractorSource { strength } => {
-                            // Apply point sink attractor
-                            let smoothing = 2.0;
-                            let dead_zone = elem.radius * 0.2;
-
-                            for y in 0..self.simulation.height {
-                                for x in 0..self.simulation.width {
-                                    let dx = x as f32 - elem.x;
-                                    let dy = y as f32 - elem.y;
-                                    let r_squared = dx * dx + dy * dy;
-                                    let r = r_squared.sqrt();
-
-                                    if r > dead_zone && r < elem.radius {
-                                        let idx = y * self.simulation.width + x;
-
-                                        let factor = -strength /
-                                            (2.0 * std::f32::consts::PI * (r_squared + smoothing * smoothing));
-
-                                        self.simulation.velocity_x[idx] += factor * dx;
-                                        self.simulation.velocity_y[idx] += factor * dy;
-
-                                        // Sponge layer
-                                        let inner_radius = elem.radius * 0.8;
-                                        if r > inner_radius {
-                                            let damping_factor = ((r - inner_radius) / (elem.radius - inner_radius)).powi(2);
-                                            let damping_coeff = 1.0 - damping_factor * 0.2;
-
-                                            self.simulation.velocity_x[idx] *= damping_coeff;
-                                            self.simulation.velocity_y[idx] *= damping_coeff;
-                                        }
-                                    }
-                                }
-                            }
-                        },
+                #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+                if let Some(audio) = &self.audio {
+                    self.audio_levels.follow(audio.poll().scaled(self.audio_sensitivity), 0.15);
+                }
+                for _ in 0..self.clock.steps(now_seconds()) {
+                    self.step_simulation();
+                }
+
+                if self.autoplaying {
+                    let dt = ui.input(|i| i.stable_dt);
+                    self.choreography.update(&mut self.simulation, dt);
+                }
+
+                #[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+                if let Some(chat) = &self.chat {
+                    let now = std::time::Instant::now();
+                    for message in chat.poll() {
+                        if let Ok(command) = self.chat_ingest.submit_text(&message.user, &message.text, now) {
+                            command.apply(&mut self.simulation);
+                        }
+                    }
+                    self.chat_ingest.prune(now);
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if self.detached == Some(DetachedView::Analysis) {
+                    self.record_stats();
+                }
+
+                #[cfg(all(feature = "ndi", not(target_arch = "wasm32")))]
+                if let Some(sender) = self.ndi.as_mut() {
+                    // 4 output pixels per cell keeps edges crisp in mixers
+                    let size = (self.simulation.width as u32 * 4, self.simulation.height as u32 * 4);
+                    if self.ndi_frame.dimensions() != size {
+                        self.ndi_frame.resize(size.0, size.1);
+                    }
+                    let rgba = self.ndi_frame.update(&self.simulation);
+                    if let Err(e) = sender.send_rgba(size.0, size.1, rgba) {
+                        eprintln!("NDI send failed: {}", e);
+                        self.ndi = None;
                     }
                 }
 
-                self.simulation.step();
-                self.frame_count += 1;
+                #[cfg(all(feature = "webcam", target_os = "linux"))]
+                if let Some(camera) = self.webcam.as_mut() {
+                    let (w, h) = self.webcam_frame.dimensions();
+                    let rgba = self.webcam_frame.update(&self.simulation);
+                    if let Err(e) = camera.send_rgba(w, h, rgba) {
+                        eprintln!("Virtual camera write failed: {}", e);
+                        self.webcam = None;
+                    }
+                }
+            } else {
+                // Resuming starts the clock over instead of catching up
+                self.clock.pause();
             }
         });
 
@@ -1229,6 +4468,18 @@ impl eframe::App for InteractiveApp {
             self.update_url_hash_if_needed();
         }
 
+        #[cfg(not(target_arch = "wasm32"))]
+        self.show_detached_viewport(ctx);
+
+        self.share_qr_window(ctx);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.export_window(ctx);
+
+        #[cfg(feature = "scripting")]
+        self.script_window(ctx);
+
+        self.perf.frame(now_seconds(), std::mem::take(&mut self.frame_solver));
         ctx.request_repaint();
 
         // Show copy feedback message briefly
@@ -1245,160 +4496,374 @@ impl eframe::App for InteractiveApp {
             }
         }
     }
-}
-
-#[cfg(target_arch = "wasm32")]
-#[derive(Serialize, Deserialize, Debug)]
-struct ShareState {
-    v: u8,            // schema version
-    w: u32,           // base width at encoding time
-    h: u32,           // base height at encoding time
-    e: Vec<ShareElem> // elements
-}
 
-#[cfg(target_arch = "wasm32")]
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "t")]
-enum ShareElem {
-    #[serde(rename = "d")]
-    Dye { x: f32, y: f32, r: f32, c: [f32; 3], i: f32 },
-    #[serde(rename = "f")]
-    Force { x: f32, y: f32, r: f32, d: [f32; 2], i: f32 },
-    #[serde(rename = "a")]
-    Attr { x: f32, y: f32, r: f32, s: f32 },
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, UI_STATE_KEY, &UiState::capture(self));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            eframe::set_value(storage, DETACHED_VIEW_KEY, &self.detached);
+            eframe::set_value(storage, SHARE_BASE_URL_KEY, &self.share_base_url);
+        }
+        if let Some(calibration) = &self.calibration {
+            eframe::set_value(storage, CALIBRATION_KEY, calibration);
+        }
+    }
 }
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(not(target_arch = "wasm32"))]
 impl InteractiveApp {
-    // Encode current persistent elements to a base64url string
-    fn encode_share_state(&self) -> Option<String> {
-        // Nothing to share
-        if self.persistent_elements.is_empty() {
-            return Some(String::from("s="));
-        }
-
-        let width = self.simulation.width as f32;
-        let height = self.simulation.height as f32;
-        let cell_size = 8.0_f32; // matches UI layout assumptions
-
-        let mut elems: Vec<ShareElem> = Vec::with_capacity(self.persistent_elements.len());
-        for elem in &self.persistent_elements {
-            match elem.element_type {
-                PersistentElementType::DyeSource { color, intensity } => {
-                    elems.push(ShareElem::Dye {
-                        x: (elem.x / width).clamp(0.0, 1.0),
-                        y: (elem.y / height).clamp(0.0, 1.0),
-                        r: (elem.radius / width).min(elem.radius / height),
-                        c: [color.0, color.1, color.2],
-                        i: intensity,
-                    });
-                }
-                PersistentElementType::ForceSource { direction, intensity } => {
-                    // Store direction in grid-cell units for portability
-                    let dir_cells = [direction.0 as f32 / cell_size, direction.1 as f32 / cell_size];
-                    elems.push(ShareElem::Force {
-                        x: (elem.x / width).clamp(0.0, 1.0),
-                        y: (elem.y / height).clamp(0.0, 1.0),
-                        r: (elem.radius / width).min(elem.radius / height),
-                        d: dir_cells,
-                        i: intensity,
-                    });
+    fn record_stats(&mut self) {
+        let sim = &self.simulation;
+        let dye: f32 = sim.dye_r.iter().chain(&sim.dye_g).chain(&sim.dye_b).sum();
+        let energy: f32 = sim
+            .velocity_x
+            .iter()
+            .zip(&sim.velocity_y)
+            .map(|(vx, vy)| 0.5 * (vx * vx + vy * vy))
+            .sum();
+        if self.stats_history.len() == STATS_HISTORY {
+            self.stats_history.pop_front();
+        }
+        self.stats_history.push_back([dye, energy]);
+    }
+
+    // The second window is an immediate viewport so it can borrow the app
+    // directly. Backends without multi-viewport support embed it instead.
+    fn show_detached_viewport(&mut self, ctx: &egui::Context) {
+        let Some(view) = self.detached else { return };
+        let title = match view {
+            DetachedView::Canvas => "itsliquid - Canvas",
+            DetachedView::Analysis => "itsliquid - Analysis",
+        };
+        let builder = egui::ViewportBuilder::default()
+            .with_title(title)
+            .with_inner_size([600.0, 600.0]);
+
+        ctx.show_viewport_immediate(egui::ViewportId::from_hash_of("detached"), builder, |ctx, class| {
+            if matches!(class, egui::ViewportClass::Embedded) {
+                let mut open = true;
+                egui::Window::new(title).open(&mut open).show(ctx, |ui| self.detached_view_ui(ui, view));
+                if !open {
+                    self.detached = None;
                 }
-                PersistentElementType::AttractorSource { strength } => {
-                    elems.push(ShareElem::Attr {
-                        x: (elem.x / width).clamp(0.0, 1.0),
-                        y: (elem.y / height).clamp(0.0, 1.0),
-                        r: (elem.radius / width).min(elem.radius / height),
-                        s: strength,
-                    });
+                return;
+            }
+
+            let frame = match view {
+                DetachedView::Canvas => egui::Frame::none().fill(egui::Color32::BLACK),
+                DetachedView::Analysis => egui::Frame::central_panel(&ctx.style()),
+            };
+            egui::CentralPanel::default().frame(frame).show(ctx, |ui| self.detached_view_ui(ui, view));
+            if ctx.input(|i| i.viewport().close_requested()) {
+                self.detached = None;
+            }
+        });
+    }
+
+    fn detached_view_ui(&mut self, ui: &mut egui::Ui, view: DetachedView) {
+        match view {
+            DetachedView::Canvas => {
+                // Letterboxed, keeping the grid's aspect ratio
+                let available = ui.available_rect_before_wrap();
+                let (w, h) = (self.simulation.width as f32, self.simulation.height as f32);
+                let scale = (available.width() / w).min(available.height() / h);
+                let rect = egui::Rect::from_center_size(available.center(), egui::Vec2::new(w * scale, h * scale));
+                if let Some(texture) = &self.canvas_texture {
+                    let uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::Pos2::new(1.0, 1.0));
+                    ui.painter().image(texture.id(), rect, uv, egui::Color32::WHITE);
                 }
             }
+            DetachedView::Analysis => {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let latest = self.stats_history.back().copied().unwrap_or_default();
+                    ui.label(format!("Frame {}", self.frame_count));
+                    ui.label(format!("Total dye: {:.3}", latest[0]));
+                    stats_plot(ui, self.stats_history.iter().map(|s| s[0]), egui::Color32::from_rgb(120, 200, 255));
+                    ui.label(format!("Kinetic energy: {:.3}", latest[1]));
+                    stats_plot(ui, self.stats_history.iter().map(|s| s[1]), egui::Color32::from_rgb(255, 150, 80));
+                    let events = self.simulation.sanitize_events;
+                    ui.label(format!("Sanitized cells: {} clamped, {} reset", events.clamped, events.reset))
+                        .on_hover_text("Cells slowed to Max speed, and NaN or infinite values reset to rest");
+                    ui.separator();
+                    ui.heading("Gates");
+                    self.gate_panel_ui(ui);
+                });
+            }
         }
+    }
+}
 
-        let state = ShareState {
-            v: 1,
-            w: self.base_width as u32,
-            h: self.base_height as u32,
-            e: elems,
-        };
+// Line plot of a non-negative series, scaled to its maximum
+#[cfg(not(target_arch = "wasm32"))]
+fn stats_plot(ui: &mut egui::Ui, values: impl ExactSizeIterator<Item = f32> + Clone, color: egui::Color32) {
+    let (rect, _response) = ui.allocate_exact_size(egui::Vec2::new(ui.available_width(), 60.0), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_stroke(rect, 1.0, egui::Stroke::new(1.0, egui::Color32::DARK_GRAY));
+    let max = values.clone().fold(0.0f32, f32::max);
+    if values.len() >= 2 && max > 0.0 {
+        let n = values.len() - 1;
+        let points: Vec<egui::Pos2> = values
+            .enumerate()
+            .map(|(i, v)| {
+                egui::Pos2::new(
+                    rect.left() + rect.width() * i as f32 / n as f32,
+                    rect.bottom() - v / max * rect.height(),
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+    }
+}
 
-        if let Ok(json) = serde_json::to_string(&state) {
-            let b64 = URL_SAFE_NO_PAD.encode(json.as_bytes());
-            Some(format!("s={}", b64))
-        } else {
-            None
+impl InteractiveApp {
+    // The share link fragment for the current elements, container and
+    // parameters
+    fn encode_share_state(&self) -> Option<String> {
+        let mut scene = Scene::capture(&self.simulation, &self.persistent_elements, false);
+        scene.container = self.container.clone();
+        Some(scene.to_link())
+    }
+
+    // Full share URL: the page's own address on the web, the configured
+    // web build address on desktop
+    fn share_url(&mut self) -> Option<String> {
+        #[cfg(target_arch = "wasm32")]
+        return self.compose_share_url();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let hash = self.encode_share_state()?;
+            let base = self.share_base_url.trim();
+            Some(format!("{}#{}", base.split('#').next().unwrap_or(base), hash))
         }
     }
 
-    // Try to load share state from window.location.hash
-    fn try_load_share_state_from_url(&mut self) {
-        let window = match web_sys::window() {
-            Some(w) => w,
-            None => return,
-        };
-        let location = window.location();
-        let hash = location.hash().unwrap_or_default();
-        // Expect forms: "#s=..." or "s=..."
-        let trimmed = hash.strip_prefix('#').unwrap_or(hash.as_str());
-        if trimmed.is_empty() {
+    // The scene as it stands, with its timeline, rendered offline on a
+    // worker thread so the canvas keeps running meanwhile
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_export(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut scene = Scene::capture(&self.simulation, &self.persistent_elements, self.export_include_dye);
+        scene.timeline = (!self.timeline.is_empty()).then(|| self.timeline.clone());
+        scene.container = self.container.clone();
+        let mut export = AnimationExport::new(scene, &self.export_path);
+        if self.export_use_recording {
+            export.recording = Some(Recording::load(&self.recording_path)?);
+        }
+        export.frames = self.export_frames.max(1);
+        export.substeps = self.substeps;
+        export.width = self.export_width;
+        export.format = self.export_format;
+
+        let frames = export.frames;
+        let done = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (job_done, job_cancel) = (Arc::clone(&done), Arc::clone(&cancel));
+        let handle = std::thread::spawn(move || {
+            export
+                .render(|frames| {
+                    job_done.store(frames, Ordering::Relaxed);
+                    !job_cancel.load(Ordering::Relaxed)
+                })
+                .map_err(|e| e.to_string())
+        });
+        self.export_job = Some(ExportJob {
+            frames,
+            done,
+            cancel,
+            handle,
+        });
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_window(&mut self, ctx: &egui::Context) {
+        if self.export_job.as_ref().is_some_and(|job| job.handle.is_finished())
+            && let Some(job) = self.export_job.take()
+        {
+            self.export_status = match job.handle.join() {
+                Ok(Ok(frames)) => format!("Wrote {} frames to {}", frames, self.export_path),
+                Ok(Err(e)) => format!("Export failed: {}", e),
+                Err(_) => String::from("Export failed"),
+            };
+        }
+        if !self.show_export {
             return;
         }
-        // Find s= parameter (support multiple params)
-        let mut b64 = None;
-        for part in trimmed.split('&') {
-            if let Some(val) = part.strip_prefix("s=") {
-                if !val.is_empty() {
-                    b64 = Some(val);
-                    break;
+
+        let mut open = self.show_export;
+        egui::Window::new("🎬 Export animation").open(&mut open).resizable(false).show(ctx, |ui| {
+            let idle = self.export_job.is_none();
+            ui.add_enabled_ui(idle, |ui| {
+                egui::ComboBox::from_label("Format")
+                    .selected_text(self.export_format.name())
+                    .show_ui(ui, |ui| {
+                        for format in AnimationFormat::ALL {
+                            ui.selectable_value(&mut self.export_format, format, format.name());
+                        }
+                    });
+                let hint = if self.export_format == AnimationFormat::Png { "Output directory" } else { "Video file" };
+                ui.add(egui::TextEdit::singleline(&mut self.export_path).hint_text(hint));
+                ui.add(egui::DragValue::new(&mut self.export_frames).clamp_range(1..=100_000).prefix("Frames "));
+                ui.add(egui::DragValue::new(&mut self.export_width).clamp_range(16..=7680).prefix("Width ").suffix(" px"));
+                ui.checkbox(&mut self.export_include_dye, "Start from the current dye");
+                ui.checkbox(&mut self.export_use_recording, "Replay recorded strokes")
+                    .on_hover_text(format!("From {}, on the grid it was recorded on", self.recording_path));
+                ui.label(format!("{} steps per frame, timeline from frame 0", self.substeps));
+            });
+
+            ui.separator();
+            match &self.export_job {
+                Some(job) => {
+                    let done = job.done.load(Ordering::Relaxed);
+                    ui.add(egui::ProgressBar::new(done as f32 / job.frames.max(1) as f32).text(format!("{} / {}", done, job.frames)));
+                    if ui.button("Cancel").clicked() {
+                        job.cancel.store(true, Ordering::Relaxed);
+                    }
+                    ctx.request_repaint();
+                }
+                None => {
+                    if ui.button("Render").clicked()
+                        && let Err(e) = self.start_export()
+                    {
+                        self.export_status = format!("Export failed: {}", e);
+                    }
                 }
             }
-        }
-        let Some(b64val) = b64 else { return; };
-        let data = match URL_SAFE_NO_PAD.decode(b64val) {
-            Ok(d) => d,
-            Err(_) => return,
-        };
-        let Ok(state) = serde_json::from_slice::<ShareState>(&data) else { return; };
-        self.apply_share_state(state);
-        log::info!("Applied share state from URL: {} elements", self.persistent_elements.len());
+            if !self.export_status.is_empty() {
+                ui.label(&self.export_status);
+            }
+        });
+        self.show_export = open;
     }
 
-    fn apply_share_state(&mut self, state: ShareState) {
-        let width = self.simulation.width as f32;
-        let height = self.simulation.height as f32;
-        let cell_size = 8.0_f32;
-
-        self.persistent_elements.clear();
-        for se in state.e.into_iter() {
-            match se {
-                ShareElem::Dye { x, y, r, c, i } => {
-                    self.persistent_elements.push(PersistentElement {
-                        element_type: PersistentElementType::DyeSource { color: (c[0], c[1], c[2]), intensity: i },
-                        x: (x * width).clamp(0.0, width - 1.0),
-                        y: (y * height).clamp(0.0, height - 1.0),
-                        radius: (r * width).max(1e-3),
-                    });
+    // Console for Rhai scripts: Run executes the top level against the
+    // current scene, and a script with frame(n) keeps running until stopped
+    #[cfg(feature = "scripting")]
+    fn script_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_script;
+        egui::Window::new("📜 Script").open(&mut open).default_width(420.0).show(ctx, |ui| {
+            egui::ScrollArea::vertical().id_source("script_source").max_height(260.0).show(ui, |ui| {
+                ui.add(egui::TextEdit::multiline(&mut self.script_source).code_editor().desired_rows(12).desired_width(f32::INFINITY));
+            });
+            ui.horizontal(|ui| {
+                if ui.button("▶ Run").clicked() {
+                    self.record_edit();
+                    self.script_log.clear();
+                    match Script::compile(&self.script_source) {
+                        Ok(mut script) => {
+                            let result = script.setup(&mut self.simulation, &mut self.persistent_elements);
+                            self.script_log.extend(script.take_output());
+                            match result {
+                                Ok(()) if script.animates() => self.script = Some(script),
+                                Ok(()) => self.script = None,
+                                Err(e) => {
+                                    self.script_log.push(format!("Error: {}", e));
+                                    self.script = None;
+                                }
+                            }
+                        }
+                        Err(e) => self.script_log.push(format!("Error: {}", e)),
+                    }
                 }
-                ShareElem::Force { x, y, r, d, i } => {
-                    // Convert direction from cells back to pixel delta to preserve current behavior
-                    let dir_pixels = (d[0] * cell_size, d[1] * cell_size);
-                    self.persistent_elements.push(PersistentElement {
-                        element_type: PersistentElementType::ForceSource { direction: dir_pixels, intensity: i },
-                        x: (x * width).clamp(0.0, width - 1.0),
-                        y: (y * height).clamp(0.0, height - 1.0),
-                        radius: (r * width).max(1e-3),
-                    });
+                if let Some(script) = &self.script {
+                    if ui.button("⏹ Stop").clicked() {
+                        self.script = None;
+                    } else {
+                        ui.label(format!("frame {}", script.frame));
+                    }
                 }
-                ShareElem::Attr { x, y, r, s } => {
-                    self.persistent_elements.push(PersistentElement {
-                        element_type: PersistentElementType::AttractorSource { strength: s },
-                        x: (x * width).clamp(0.0, width - 1.0),
-                        y: (y * height).clamp(0.0, height - 1.0),
-                        radius: (r * width).max(1e-3),
-                    });
+            });
+            ui.separator();
+            egui::ScrollArea::vertical().id_source("script_log").max_height(120.0).stick_to_bottom(true).show(ui, |ui| {
+                for line in &self.script_log {
+                    ui.monospace(line);
                 }
-            }
+            });
+        });
+        self.show_script = open;
+    }
+
+    // Popup with a QR code for the share link, so the scene can be picked up
+    // on a phone. The texture is rebuilt only when the link changes.
+    fn share_qr_window(&mut self, ctx: &egui::Context) {
+        if !self.show_share_qr {
+            return;
+        }
+        let Some(url) = self.share_url() else { return; };
+        if self.share_qr.as_ref().map(|(cached, _)| cached) != Some(&url) {
+            let texture = match qr_modules(&url) {
+                Ok((modules, dark)) => {
+                    // Four modules of quiet zone on each side
+                    let size = modules + 8;
+                    let mut image = egui::ColorImage::new([size, size], egui::Color32::WHITE);
+                    for (i, &dark) in dark.iter().enumerate() {
+                        if dark {
+                            image[(i % modules + 4, i / modules + 4)] = egui::Color32::BLACK;
+                        }
+                    }
+                    Some(ctx.load_texture("share_qr", image, egui::TextureOptions::NEAREST))
+                }
+                Err(e) => {
+                    eprintln!("Failed to encode share link as QR code: {}", e);
+                    None
+                }
+            };
+            self.share_qr = Some((url.clone(), texture));
         }
+
+        let mut open = self.show_share_qr;
+        egui::Window::new("📱 Continue on your phone")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    match self.share_qr.as_ref().and_then(|(_, texture)| texture.as_ref()) {
+                        Some(texture) => {
+                            ui.image((texture.id(), egui::Vec2::splat(280.0)));
+                        }
+                        None => {
+                            ui.label("This scene is too large for a QR code");
+                        }
+                    }
+                    ui.label("Scan to open this scene in the web version");
+                });
+                #[cfg(not(target_arch = "wasm32"))]
+                ui.horizontal(|ui| {
+                    ui.label("Web address:");
+                    ui.text_edit_singleline(&mut self.share_base_url);
+                });
+                ui.small(url.as_str());
+            });
+        self.show_share_qr = open;
+    }
+}
+
+// Hand `bytes` to the browser as a file download through a data URL
+#[cfg(target_arch = "wasm32")]
+fn download_web(name: &str, mime: &str, bytes: &[u8]) -> Result<(), wasm_bindgen::JsValue> {
+    use base64::Engine as _;
+    use wasm_bindgen::JsCast;
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| wasm_bindgen::JsValue::from_str("no document"))?;
+    let link = document.create_element("a")?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+    link.set_attribute("href", &format!("data:{};base64,{}", mime, encoded))?;
+    link.set_attribute("download", name)?;
+    link.unchecked_into::<web_sys::HtmlElement>().click();
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+impl InteractiveApp {
+    // Load the scene in window.location.hash, e.g. "#s=...", if any
+    fn try_load_share_state_from_url(&mut self) {
+        let Some(window) = web_sys::window() else { return; };
+        let hash = window.location().hash().unwrap_or_default();
+        let Ok(scene) = Scene::from_link(&hash) else { return; };
+        self.apply_scene(&scene);
+        log::info!("Applied share state from URL: {} elements", self.persistent_elements.len());
     }
 
     fn update_url_hash_if_needed(&mut self) {
@@ -0,0 +1,335 @@
+//! Half-precision dye storage: velocity, pressure and temperature stay f32 in
+//! an inner `InteractiveFluid`, while the three dye channels are stored as f16
+//! and widened to f32 inside the kernels.
+//!
+//! f16 keeps ~3 significant digits and flushes values below ~6e-8 to zero, so
+//! faint dye tails lose detail. Mass renormalization still runs in f32 and
+//! keeps totals close, but not bitwise equal, to the f32 solver.
+//!
+//! Stepping goes through `InteractiveFluid::step_with`, with the f16
+//! channels as its `DyeStorage`, so everything but the dye passes is the
+//! f32 solver's own. The dye passes aren't broken out in `stage_times`.
+
+use crate::fluid_interactive::{boundary_outflow, BoundaryLeak, DyeStorage};
+use crate::kernels;
+use crate::{BoundaryMode, ConfigurableSimulation, FluidSimulation, InteractiveFluid, Param};
+use glam::Vec2;
+use half::f16;
+
+#[derive(Debug, Clone)]
+pub struct HalfDyeFluid {
+    /// Velocity/pressure/temperature solver. Its f32 dye buffers are released
+    /// and must not be read; use the f16 channels below instead.
+    pub fluid: InteractiveFluid,
+    pub dye_r: Vec<f16>,
+    pub dye_g: Vec<f16>,
+    pub dye_b: Vec<f16>,
+    pub dye_r_prev: Vec<f16>,
+    pub dye_g_prev: Vec<f16>,
+    pub dye_b_prev: Vec<f16>,
+}
+
+impl FluidSimulation for HalfDyeFluid {
+    fn step(&mut self) {
+        self.step()
+    }
+
+    fn add_force(&mut self, x: usize, y: usize, force: Vec2) {
+        self.add_force(x, y, force, 3.0)
+    }
+
+    fn add_dye(&mut self, x: usize, y: usize, color: (f32, f32, f32)) {
+        self.add_dye(x, y, color)
+    }
+
+    fn width(&self) -> usize {
+        self.fluid.width
+    }
+
+    fn height(&self) -> usize {
+        self.fluid.height
+    }
+}
+
+impl ConfigurableSimulation for HalfDyeFluid {
+    fn params(&self) -> Vec<(Param, f32)> {
+        self.fluid.params()
+    }
+
+    fn set_param(&mut self, param: Param, value: f32) -> Result<(), Box<dyn std::error::Error>> {
+        self.fluid.set_param(param, value)
+    }
+}
+
+impl HalfDyeFluid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut fluid = InteractiveFluid::new(width, height);
+        fluid.dye_r = Vec::new();
+        fluid.dye_g = Vec::new();
+        fluid.dye_b = Vec::new();
+        fluid.dye_r_prev = Vec::new();
+        fluid.dye_g_prev = Vec::new();
+        fluid.dye_b_prev = Vec::new();
+
+        let size = width * height;
+        Self {
+            fluid,
+            dye_r: vec![f16::ZERO; size],
+            dye_g: vec![f16::ZERO; size],
+            dye_b: vec![f16::ZERO; size],
+            dye_r_prev: vec![f16::ZERO; size],
+            dye_g_prev: vec![f16::ZERO; size],
+            dye_b_prev: vec![f16::ZERO; size],
+        }
+    }
+
+    pub fn add_dye(&mut self, x: usize, y: usize, color: (f32, f32, f32)) {
+        if x < self.fluid.width && y < self.fluid.height {
+            let idx = y * self.fluid.width + x;
+            self.dye_r[idx] = f16::from_f32(self.dye_r[idx].to_f32() + color.0);
+            self.dye_g[idx] = f16::from_f32(self.dye_g[idx].to_f32() + color.1);
+            self.dye_b[idx] = f16::from_f32(self.dye_b[idx].to_f32() + color.2);
+        }
+    }
+
+    pub fn add_force(&mut self, x: usize, y: usize, force: Vec2, radius: f32) {
+        self.fluid.add_force(x, y, force, radius);
+    }
+
+    pub fn step(&mut self) {
+        let (fluid, mut dye) = self.split();
+        fluid.step_with(&mut dye);
+    }
+
+    /// Same passes as `InteractiveFluid::step_dye`, reading and writing f16.
+    pub fn step_dye(&mut self) {
+        let (fluid, mut dye) = self.split();
+        dye.step(fluid);
+    }
+
+    // The solver and the f16 channels, borrowed apart
+    fn split(&mut self) -> (&mut InteractiveFluid, HalfDye<'_>) {
+        let Self { fluid, dye_r, dye_g, dye_b, dye_r_prev, dye_g_prev, dye_b_prev } = self;
+        let dye = HalfDye { r: dye_r, g: dye_g, b: dye_b, r_prev: dye_r_prev, g_prev: dye_g_prev, b_prev: dye_b_prev };
+        (fluid, dye)
+    }
+
+    /// Widen the dye channels to f32, e.g. for rendering or export.
+    pub fn dye_to_f32(&self) -> [Vec<f32>; 3] {
+        [to_f32(&self.dye_r), to_f32(&self.dye_g), to_f32(&self.dye_b)]
+    }
+
+    /// Bytes held by the dye channels (current and previous).
+    pub fn dye_bytes(&self) -> usize {
+        6 * self.dye_r.len() * std::mem::size_of::<f16>()
+    }
+}
+
+// The f16 channels as a `DyeStorage`, borrowed apart from the solver so
+// `step_with` can hand it the solver
+struct HalfDye<'a> {
+    r: &'a mut [f16],
+    g: &'a mut [f16],
+    b: &'a mut [f16],
+    r_prev: &'a mut [f16],
+    g_prev: &'a mut [f16],
+    b_prev: &'a mut [f16],
+}
+
+impl DyeStorage for HalfDye<'_> {
+    fn dye(&self, _fluid: &InteractiveFluid, idx: usize) -> [f32; 3] {
+        [self.r[idx].to_f32(), self.g[idx].to_f32(), self.b[idx].to_f32()]
+    }
+
+    fn step(&mut self, fluid: &mut InteractiveFluid) {
+        self.r_prev.copy_from_slice(self.r);
+        self.g_prev.copy_from_slice(self.g);
+        self.b_prev.copy_from_slice(self.b);
+
+        let w = fluid.width;
+        let h = fluid.height;
+        let boundary = fluid.boundary;
+        let wrap = boundary == BoundaryMode::Periodic;
+
+        // Diffuse, then renormalize each channel to its pre-diffusion mass
+        let before = [sum(self.r), sum(self.g), sum(self.b)];
+        let (a, denominator) = fluid.diffusion_weights(fluid.dt * fluid.dye_diffusion * (w * h) as f32);
+        for _ in 0..2 {
+            diffuse_channel(self.r, self.r_prev, w, h, a, denominator);
+            diffuse_channel(self.g, self.g_prev, w, h, a, denominator);
+            diffuse_channel(self.b, self.b_prev, w, h, a, denominator);
+            set_channel_boundaries(self.r, w, h, boundary);
+            set_channel_boundaries(self.g, w, h, boundary);
+            set_channel_boundaries(self.b, w, h, boundary);
+        }
+        renormalize(self.r, before[0]);
+        renormalize(self.g, before[1]);
+        renormalize(self.b, before[2]);
+
+        // Advect the diffused dye, as the f32 solver does, noting what
+        // leaves on the wrapped solver's boundary balance
+        self.r_prev.copy_from_slice(self.r);
+        self.g_prev.copy_from_slice(self.g);
+        self.b_prev.copy_from_slice(self.b);
+        fluid.boundary_leak = BoundaryLeak::default();
+        if !wrap {
+            let (r, g, b) = (&*self.r_prev, &*self.g_prev, &*self.b_prev);
+            fluid.boundary_leak.outflow = boundary_outflow(
+                &fluid.velocity_x,
+                &fluid.velocity_y,
+                w,
+                h,
+                fluid.advect_dt(),
+                |i| [r[i].to_f32(), g[i].to_f32(), b[i].to_f32()],
+            );
+        }
+        let before = [
+            sum(self.r_prev),
+            sum(self.g_prev),
+            sum(self.b_prev),
+        ];
+        let vx = &fluid.velocity_x;
+        let vy = &fluid.velocity_y;
+        let dt = fluid.advect_dt();
+        advect_channel(self.r, self.r_prev, vx, vy, w, h, dt, wrap);
+        advect_channel(self.g, self.g_prev, vx, vy, w, h, dt, wrap);
+        advect_channel(self.b, self.b_prev, vx, vy, w, h, dt, wrap);
+        set_channel_boundaries(self.r, w, h, boundary);
+        set_channel_boundaries(self.g, w, h, boundary);
+        set_channel_boundaries(self.b, w, h, boundary);
+
+        // Like the f32 solver, rescale each channel that holds dye, less
+        // what left through the edges
+        let lost = fluid.boundary_leak.total_outflow();
+        renormalize(self.r, before[0] - lost[0]);
+        renormalize(self.g, before[1] - lost[1]);
+        renormalize(self.b, before[2] - lost[2]);
+
+        let rates = fluid.dye_fade_rates();
+        for (channel, rate) in [&mut *self.r, &mut *self.g, &mut *self.b].into_iter().zip(rates) {
+            if rate > 0.0 {
+                let keep = (-rate * fluid.dt).exp();
+                for v in channel.iter_mut() {
+                    *v = f16::from_f32(v.to_f32() * keep);
+                }
+            }
+        }
+
+        // Reactions run in f32 and are stored back rounded
+        if let Some(chemistry) = fluid.chemistry {
+            let [mut r, mut g, mut b] = [to_f32(self.r), to_f32(self.g), to_f32(self.b)];
+            chemistry.react([&mut r[..], &mut g[..], &mut b[..]], w, h, fluid.dt);
+            for (channel, values) in [(&mut *self.r, r), (&mut *self.g, g), (&mut *self.b, b)] {
+                for (v, value) in channel.iter_mut().zip(values) {
+                    *v = f16::from_f32(value);
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, fluid: &mut InteractiveFluid) {
+        let (w, h, boundary) = (fluid.width, fluid.height, fluid.boundary);
+        for channel in [&mut *self.r, &mut *self.g, &mut *self.b] {
+            set_channel_boundaries(channel, w, h, boundary);
+            for value in channel.iter_mut().filter(|v| !v.is_finite()) {
+                *value = f16::ZERO;
+                fluid.sanitize_events.reset += 1;
+            }
+        }
+    }
+}
+
+fn to_f32(field: &[f16]) -> Vec<f32> {
+    field.iter().map(|v| v.to_f32()).collect()
+}
+
+fn sum(field: &[f16]) -> f32 {
+    field.iter().map(|v| v.to_f32()).sum()
+}
+
+fn renormalize(field: &mut [f16], target: f32) {
+    let current = sum(field);
+    if current > 1e-10 {
+        let scale = target / current;
+        for v in field.iter_mut() {
+            *v = f16::from_f32(v.to_f32() * scale);
+        }
+    }
+}
+
+fn diffuse_channel(field: &mut [f16], prev: &[f16], w: usize, h: usize, a: [f32; 2], denominator: f32) {
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let idx = y * w + x;
+            let value = (prev[idx].to_f32()
+                + a[0] * (field[idx - 1].to_f32() + field[idx + 1].to_f32())
+                + a[1] * (field[idx - w].to_f32() + field[idx + w].to_f32()))
+                / denominator;
+            field[idx] = f16::from_f32(value);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn advect_channel(
+    field: &mut [f16],
+    prev: &[f16],
+    velocity_x: &[f32],
+    velocity_y: &[f32],
+    w: usize,
+    h: usize,
+    dt: [f32; 2],
+    wrap: bool,
+) {
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let idx = y * w + x;
+
+            // Backtrace using current velocity field
+            let (src_x, src_y) = (x as f32 - dt[0] * velocity_x[idx], y as f32 - dt[1] * velocity_y[idx]);
+            let (src_x, src_y) = if wrap {
+                (kernels::wrap_interior(src_x, w), kernels::wrap_interior(src_y, h))
+            } else {
+                (src_x.max(0.5).min((w - 1) as f32 - 0.5), src_y.max(0.5).min((h - 1) as f32 - 0.5))
+            };
+
+            let x0 = src_x.floor() as usize;
+            let y0 = src_y.floor() as usize;
+            let sx = src_x - x0 as f32;
+            let sy = src_y - y0 as f32;
+
+            let value = (1.0 - sx) * (1.0 - sy) * prev[y0 * w + x0].to_f32()
+                + sx * (1.0 - sy) * prev[y0 * w + x0 + 1].to_f32()
+                + (1.0 - sx) * sy * prev[(y0 + 1) * w + x0].to_f32()
+                + sx * sy * prev[(y0 + 1) * w + x0 + 1].to_f32();
+            field[idx] = f16::from_f32(value);
+        }
+    }
+}
+
+// Edge cells copy their interior neighbour, or the far side when wrapping;
+// open edges are cleared so dye reaching them leaves
+fn set_channel_boundaries(field: &mut [f16], w: usize, h: usize, boundary: BoundaryMode) {
+    if boundary == BoundaryMode::Open {
+        for x in 0..w {
+            field[x] = f16::ZERO;
+            field[(h - 1) * w + x] = f16::ZERO;
+        }
+        for y in 0..h {
+            field[y * w] = f16::ZERO;
+            field[y * w + w - 1] = f16::ZERO;
+        }
+        return;
+    }
+    let wrap = boundary == BoundaryMode::Periodic;
+    let (near, far) = if wrap { (h - 2, 1) } else { (1, h - 2) };
+    for x in 0..w {
+        field[x] = field[near * w + x];
+        field[(h - 1) * w + x] = field[far * w + x];
+    }
+    let (near, far) = if wrap { (w - 2, 1) } else { (1, w - 2) };
+    for y in 0..h {
+        field[y * w] = field[y * w + near];
+        field[y * w + w - 1] = field[y * w + far];
+    }
+}
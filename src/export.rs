@@ -1,6 +1,13 @@
-use crate::render::Renderer;
-use crate::{FluidFinal, FluidSimulation, FluidSolver, WorkingFluid};
+use crate::render::{Colormap, RenderMode, Renderer};
+use crate::fluid_interactive::{BoundaryLeak, SanitizeEvents};
+#[allow(deprecated)]
+use crate::{FluidFinal, FluidSimulation, FluidSolver, InteractiveFluid, WorkingFluid};
+use image::{Rgb32FImage, RgbImage};
 use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
+#[cfg(not(target_arch = "wasm32"))]
+use std::process::{Child, ChildStdin, Command, Stdio};
 
 pub trait FluidData {
     fn width(&self) -> usize;
@@ -8,11 +15,28 @@ pub trait FluidData {
     fn density(&self) -> &[f32];
     fn velocity_x(&self) -> &[f32];
     fn velocity_y(&self) -> &[f32];
+
+    /// Dye balance at the domain edges for the last step, if the solver tracks it.
+    fn boundary_leak(&self) -> Option<BoundaryLeak> {
+        None
+    }
+
+    /// Cells repaired by the solver's sanitize pass so far, if it has one.
+    fn sanitize_events(&self) -> Option<SanitizeEvents> {
+        None
+    }
+
+    /// Red, green and blue dye, for solvers with colored dye; `density` is
+    /// then their sum.
+    fn dye(&self) -> Option<[&[f32]; 3]> {
+        None
+    }
 }
 
 // These implementations were incorrect and have been removed
 // The export functionality needs to be reworked for the trait-based system
 
+#[allow(deprecated)]
 impl FluidData for FluidSolver {
     fn width(&self) -> usize {
         self.width
@@ -31,6 +55,7 @@ impl FluidData for FluidSolver {
     }
 }
 
+#[allow(deprecated)]
 impl FluidData for WorkingFluid {
     fn width(&self) -> usize {
         self.width
@@ -49,6 +74,7 @@ impl FluidData for WorkingFluid {
     }
 }
 
+#[allow(deprecated)]
 impl FluidData for FluidFinal {
     fn width(&self) -> usize {
         self.width
@@ -78,6 +104,16 @@ impl ImageExporter {
         }
     }
 
+    /// Colormap for density exports and videos.
+    pub fn with_colormap(mut self, colormap: Colormap) -> Self {
+        self.renderer.set_colormap(colormap);
+        self
+    }
+
+    pub fn set_colormap(&mut self, colormap: Colormap) {
+        self.renderer.set_colormap(colormap);
+    }
+
     pub fn export_density_png(
         &self,
         simulation: &impl FluidData,
@@ -88,6 +124,32 @@ impl ImageExporter {
         Ok(())
     }
 
+    /// An interactive simulation the way the canvas shows it: `mode` at the
+    /// exporter's size, dye with the screen's Reinhard tone mapping and
+    /// obstacles in gray.
+    pub fn render_canvas(&self, simulation: &InteractiveFluid, mode: RenderMode) -> RgbImage {
+        let mut img = self.renderer.render_interactive(simulation, mode);
+        let (width, height) = self.renderer.dimensions();
+        if let Some(mask) = simulation.obstacles.as_ref().filter(|mask| mask.cells.len() == simulation.width * simulation.height) {
+            for (x, y, pixel) in img.enumerate_pixels_mut() {
+                let sim_x = x as usize * simulation.width / width as usize;
+                let sim_y = y as usize * simulation.height / height as usize;
+                if mask.cells[sim_y * simulation.width + sim_x] {
+                    *pixel = image::Rgb([90, 90, 90]);
+                }
+            }
+        }
+        img
+    }
+
+    /// `render_canvas` encoded as a PNG file's bytes, for writing to disk or
+    /// handing to the browser.
+    pub fn canvas_png(&self, simulation: &InteractiveFluid, mode: RenderMode) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut png = Vec::new();
+        self.render_canvas(simulation, mode).write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+        Ok(png)
+    }
+
     pub fn export_velocity_png(
         &self,
         simulation: &impl FluidData,
@@ -115,6 +177,300 @@ impl ImageExporter {
         }
         Ok(())
     }
+
+    /// Step the simulation and encode each rendered density frame straight
+    /// into a video file instead of writing one PNG per frame.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_video(
+        &self,
+        simulation: &mut (impl FluidData + Step),
+        steps: usize,
+        path: &Path,
+        options: &VideoOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (width, height) = self.renderer.dimensions();
+        let mut encoder = VideoEncoder::start(path, width, height, options)?;
+
+        for _ in 0..steps {
+            simulation.step();
+            encoder.write_frame(&self.renderer.render_to_image(simulation))?;
+        }
+
+        encoder.finish()
+    }
+
+    /// Write raw HDR dye RGB at grid resolution, without tone mapping or
+    /// clamping. The format follows the extension: `.exr` or `.tif`/`.tiff`.
+    pub fn export_dye_float(
+        &self,
+        simulation: &InteractiveFluid,
+        path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        save_float_rgb(
+            simulation.width,
+            simulation.height,
+            [&simulation.dye_r, &simulation.dye_g, &simulation.dye_b],
+            path,
+        )
+    }
+
+    /// Write raw velocity components at grid resolution: R = x, G = y, B = 0.
+    pub fn export_velocity_float(
+        &self,
+        simulation: &impl FluidData,
+        path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let zeros = vec![0.0; simulation.width() * simulation.height()];
+        save_float_rgb(
+            simulation.width(),
+            simulation.height(),
+            [simulation.velocity_x(), simulation.velocity_y(), &zeros],
+            path,
+        )
+    }
+
+    /// Write raw density replicated across all three channels.
+    pub fn export_density_float(
+        &self,
+        simulation: &impl FluidData,
+        path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let density = simulation.density();
+        save_float_rgb(
+            simulation.width(),
+            simulation.height(),
+            [density, density, density],
+            path,
+        )
+    }
+
+    /// Write `<prefix>_dye` and `<prefix>_velocity` float images of an
+    /// interactive simulation into `output_dir`, using `extension` ("exr" or "tif").
+    pub fn export_fields_float(
+        &self,
+        simulation: &InteractiveFluid,
+        output_dir: &Path,
+        prefix: &str,
+        extension: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.export_dye_float(
+            simulation,
+            &output_dir.join(format!("{}_dye.{}", prefix, extension)),
+        )?;
+
+        let zeros = vec![0.0; simulation.width * simulation.height];
+        save_float_rgb(
+            simulation.width,
+            simulation.height,
+            [&simulation.velocity_x, &simulation.velocity_y, &zeros],
+            &output_dir.join(format!("{}_velocity.{}", prefix, extension)),
+        )
+    }
+}
+
+fn save_float_rgb(
+    width: usize,
+    height: usize,
+    channels: [&[f32]; 3],
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("exr") | Some("tif") | Some("tiff") => {}
+        _ => {
+            return Err(format!(
+                "unsupported float image format for {} (use .exr, .tif or .tiff)",
+                path.display()
+            )
+            .into());
+        }
+    }
+
+    let size = width * height;
+    if channels.iter().any(|c| c.len() != size) {
+        return Err("field size does not match simulation dimensions".into());
+    }
+
+    let mut data = Vec::with_capacity(size * 3);
+    for ((r, g), b) in channels[0].iter().zip(channels[1]).zip(channels[2]) {
+        data.extend([*r, *g, *b]);
+    }
+
+    let img = Rgb32FImage::from_raw(width as u32, height as u32, data)
+        .ok_or("failed to build float image")?;
+    img.save(path)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    /// H.264 via libx264, for .mp4 output
+    H264,
+    /// VP9 via libvpx, for .webm output
+    Vp9,
+}
+
+impl VideoCodec {
+    fn ffmpeg_args(&self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::H264 => &["-c:v", "libx264", "-preset", "medium", "-crf", "18"],
+            VideoCodec::Vp9 => &["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "30"],
+        }
+    }
+}
+
+/// Layout of the raw frames piped into the encoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgb24,
+    /// Matches `frame::FrameBuffer`, so its bytes can be written as-is
+    Rgba,
+}
+
+impl PixelFormat {
+    fn ffmpeg_name(&self) -> &'static str {
+        match self {
+            PixelFormat::Rgb24 => "rgb24",
+            PixelFormat::Rgba => "rgba",
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Rgb24 => 3,
+            PixelFormat::Rgba => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoOptions {
+    pub fps: u32,
+    pub codec: VideoCodec,
+    /// ffmpeg executable to pipe frames into
+    pub ffmpeg_path: String,
+    pub pixel_format: PixelFormat,
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        Self {
+            fps: 30,
+            codec: VideoCodec::H264,
+            ffmpeg_path: String::from("ffmpeg"),
+            pixel_format: PixelFormat::Rgb24,
+        }
+    }
+}
+
+/// Streams raw RGB or RGBA frames into an ffmpeg child process.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct VideoEncoder {
+    // Taken by `finish`; anything left when dropped is killed
+    child: Option<Child>,
+    stdin: Option<ChildStdin>,
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl VideoEncoder {
+    pub fn start(
+        path: &Path,
+        width: u32,
+        height: u32,
+        options: &VideoOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut child = Command::new(&options.ffmpeg_path)
+            .args(["-y", "-loglevel", "error", "-f", "rawvideo"])
+            .args(["-pix_fmt", options.pixel_format.ffmpeg_name()])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-r", &options.fps.to_string()])
+            .args(["-i", "-"])
+            // yuv420p needs even dimensions
+            .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p"])
+            .args(options.codec.ffmpeg_args())
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("failed to launch {}: {}", options.ffmpeg_path, e))?;
+
+        let stdin = child.stdin.take();
+        Ok(Self {
+            child: Some(child),
+            stdin,
+            width,
+            height,
+            pixel_format: options.pixel_format,
+        })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    pub fn write_frame(&mut self, frame: &RgbImage) -> Result<(), Box<dyn std::error::Error>> {
+        if self.pixel_format != PixelFormat::Rgb24 {
+            return Err("write_frame needs an Rgb24 encoder; use write_raw for RGBA".into());
+        }
+        if frame.width() != self.width || frame.height() != self.height {
+            return Err(format!(
+                "frame is {}x{}, encoder expects {}x{}",
+                frame.width(),
+                frame.height(),
+                self.width,
+                self.height
+            )
+            .into());
+        }
+        self.write_raw(frame.as_raw())
+    }
+
+    /// Write one tightly packed frame in the encoder's pixel format, e.g.
+    /// straight from `FrameBuffer::update` without copying.
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let expected =
+            self.width as usize * self.height as usize * self.pixel_format.bytes_per_pixel();
+        if bytes.len() != expected {
+            return Err(format!("frame has {} bytes, encoder expects {}", bytes.len(), expected).into());
+        }
+        let stdin = self.stdin.as_mut().ok_or("encoder already finished")?;
+        stdin.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Close the pipe and wait for ffmpeg to finalize the file.
+    pub fn finish(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        drop(self.stdin.take());
+        let mut child = self.child.take().ok_or("encoder already finished")?;
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(format!("ffmpeg exited with {}", status).into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for VideoEncoder {
+    /// An encoder dropped without `finish`, e.g. after a failed write, kills
+    /// ffmpeg and reaps it rather than leaving a zombie behind.
+    fn drop(&mut self) {
+        drop(self.stdin.take());
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
 }
 
 pub trait Step {
@@ -123,6 +479,7 @@ pub trait Step {
 
 // Step implementation removed - needs to be implemented per concrete type
 
+#[allow(deprecated)]
 impl Step for FluidSolver {
     fn step(&mut self) {
         self.step();
@@ -0,0 +1,467 @@
+//! A 3D grid solver: stable-fluids velocity with RGB dye in a walled box,
+//! plus slice and raymarched previews that turn the volume into images.
+//! Distances are in cells and y points down, as in the 2D solvers.
+
+use glam::Vec3;
+use image::{Rgb, RgbImage};
+use rayon::prelude::*;
+use std::path::Path;
+
+/// The 3D counterpart of `FluidSimulation`.
+pub trait FluidSimulation3D {
+    fn step(&mut self);
+    fn add_force(&mut self, x: usize, y: usize, z: usize, force: Vec3);
+    fn add_dye(&mut self, x: usize, y: usize, z: usize, color: (f32, f32, f32));
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn depth(&self) -> usize;
+}
+
+/// Axis a preview looks along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Debug, Clone)]
+pub struct Fluid3D {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    /// Cell (x, y, z) is at `(z * height + y) * width + x`
+    pub velocity_x: Vec<f32>,
+    pub velocity_y: Vec<f32>,
+    pub velocity_z: Vec<f32>,
+    pub velocity_x_prev: Vec<f32>,
+    pub velocity_y_prev: Vec<f32>,
+    pub velocity_z_prev: Vec<f32>,
+    pub dye_r: Vec<f32>,
+    pub dye_g: Vec<f32>,
+    pub dye_b: Vec<f32>,
+    pub dye_prev: Vec<f32>,
+    pub pressure: Vec<f32>,
+    pub divergence: Vec<f32>,
+    pub dt: f32,
+    pub viscosity: f32,
+    pub dye_diffusion: f32,
+    pub dye_dissipation: f32,
+    /// Upward acceleration per unit of dye, so dense dye rises like smoke
+    pub buoyancy: f32,
+    pub pressure_iterations: usize,
+}
+
+impl Fluid3D {
+    /// A box walled by its outer shell of cells.
+    pub fn new(width: usize, height: usize, depth: usize) -> Self {
+        let size = width * height * depth;
+        Self {
+            width,
+            height,
+            depth,
+            velocity_x: vec![0.0; size],
+            velocity_y: vec![0.0; size],
+            velocity_z: vec![0.0; size],
+            velocity_x_prev: vec![0.0; size],
+            velocity_y_prev: vec![0.0; size],
+            velocity_z_prev: vec![0.0; size],
+            dye_r: vec![0.0; size],
+            dye_g: vec![0.0; size],
+            dye_b: vec![0.0; size],
+            dye_prev: vec![0.0; size],
+            pressure: vec![0.0; size],
+            divergence: vec![0.0; size],
+            dt: 0.1,
+            viscosity: 0.0,
+            dye_diffusion: 0.0,
+            dye_dissipation: 0.0,
+            buoyancy: 0.0,
+            pressure_iterations: 30,
+        }
+    }
+
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.height + y) * self.width + x
+    }
+
+    fn is_interior(&self, x: usize, y: usize, z: usize) -> bool {
+        x > 0 && y > 0 && z > 0 && x < self.width - 1 && y < self.height - 1 && z < self.depth - 1
+    }
+
+    /// Interior cells within `radius` of (x, y, z).
+    fn ball(&self, x: usize, y: usize, z: usize, radius: f32) -> Vec<(usize, f32)> {
+        let reach = radius.ceil() as i64;
+        let mut cells = Vec::new();
+        for dz in -reach..=reach {
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let dist_sq = (dx * dx + dy * dy + dz * dz) as f32;
+                    let (px, py, pz) = (x as i64 + dx, y as i64 + dy, z as i64 + dz);
+                    if dist_sq > radius * radius || px < 0 || py < 0 || pz < 0 {
+                        continue;
+                    }
+                    let (px, py, pz) = (px as usize, py as usize, pz as usize);
+                    if self.is_interior(px, py, pz) {
+                        cells.push((self.index(px, py, pz), 1.0 - dist_sq / (radius * radius + 1.0)));
+                    }
+                }
+            }
+        }
+        cells
+    }
+
+    pub fn add_force_ball(&mut self, x: usize, y: usize, z: usize, force: Vec3, radius: f32) {
+        for (idx, weight) in self.ball(x, y, z, radius) {
+            self.velocity_x[idx] += force.x * weight;
+            self.velocity_y[idx] += force.y * weight;
+            self.velocity_z[idx] += force.z * weight;
+        }
+    }
+
+    pub fn add_dye_ball(&mut self, x: usize, y: usize, z: usize, color: (f32, f32, f32), radius: f32) {
+        for (idx, weight) in self.ball(x, y, z, radius) {
+            self.dye_r[idx] += color.0 * weight;
+            self.dye_g[idx] += color.1 * weight;
+            self.dye_b[idx] += color.2 * weight;
+        }
+    }
+
+    pub fn total_dye(&self) -> [f32; 3] {
+        [self.dye_r.iter().sum(), self.dye_g.iter().sum(), self.dye_b.iter().sum()]
+    }
+
+    /// Largest velocity divergence over the interior, in cells per time unit.
+    pub fn max_divergence(&self) -> f32 {
+        let (w, plane) = (self.width, self.width * self.height);
+        let mut max = 0.0f32;
+        for z in 1..self.depth - 1 {
+            for y in 1..self.height - 1 {
+                for x in 1..w - 1 {
+                    let idx = self.index(x, y, z);
+                    let div = 0.5
+                        * (self.velocity_x[idx + 1] - self.velocity_x[idx - 1] + self.velocity_y[idx + w]
+                            - self.velocity_y[idx - w]
+                            + self.velocity_z[idx + plane]
+                            - self.velocity_z[idx - plane]);
+                    max = max.max(div.abs());
+                }
+            }
+        }
+        max
+    }
+
+    pub fn clear(&mut self) {
+        for field in [
+            &mut self.velocity_x,
+            &mut self.velocity_y,
+            &mut self.velocity_z,
+            &mut self.dye_r,
+            &mut self.dye_g,
+            &mut self.dye_b,
+            &mut self.pressure,
+        ] {
+            field.fill(0.0);
+        }
+    }
+
+    pub fn step(&mut self) {
+        if self.buoyancy != 0.0 {
+            let lift = self.buoyancy * self.dt / 3.0;
+            let dye = self.dye_r.iter().zip(&self.dye_g).zip(&self.dye_b);
+            for (vy, ((r, g), b)) in self.velocity_y.iter_mut().zip(dye) {
+                *vy -= lift * (r + g + b);
+            }
+        }
+
+        if self.viscosity > 0.0 {
+            let a = self.dt * self.viscosity;
+            let dims = (self.width, self.height, self.depth);
+            for (field, prev) in [
+                (&mut self.velocity_x, &mut self.velocity_x_prev),
+                (&mut self.velocity_y, &mut self.velocity_y_prev),
+                (&mut self.velocity_z, &mut self.velocity_z_prev),
+            ] {
+                prev.copy_from_slice(field);
+                diffuse(field, prev, dims, a);
+            }
+            self.set_velocity_walls();
+        }
+        self.project();
+
+        self.velocity_x_prev.copy_from_slice(&self.velocity_x);
+        self.velocity_y_prev.copy_from_slice(&self.velocity_y);
+        self.velocity_z_prev.copy_from_slice(&self.velocity_z);
+        let dims = (self.width, self.height, self.depth);
+        let flow = [&self.velocity_x_prev, &self.velocity_y_prev, &self.velocity_z_prev];
+        for (field, prev) in [
+            (&mut self.velocity_x, &self.velocity_x_prev),
+            (&mut self.velocity_y, &self.velocity_y_prev),
+            (&mut self.velocity_z, &self.velocity_z_prev),
+        ] {
+            advect(field, prev, flow, dims, self.dt);
+        }
+        self.set_velocity_walls();
+        self.project();
+
+        self.step_dye();
+    }
+
+    // Advect, diffuse and fade each channel; the closed box keeps all dye
+    // that advection's interpolation would otherwise lose
+    fn step_dye(&mut self) {
+        let dims = (self.width, self.height, self.depth);
+        let flow = [&self.velocity_x, &self.velocity_y, &self.velocity_z];
+        let keep = (-self.dye_dissipation * self.dt).exp();
+        for channel in [&mut self.dye_r, &mut self.dye_g, &mut self.dye_b] {
+            let before: f32 = channel.iter().sum();
+            if before <= 0.0 {
+                continue;
+            }
+            self.dye_prev.copy_from_slice(channel);
+            advect(channel, &self.dye_prev, flow, dims, self.dt);
+            if self.dye_diffusion > 0.0 {
+                self.dye_prev.copy_from_slice(channel);
+                diffuse(channel, &self.dye_prev, dims, self.dt * self.dye_diffusion);
+            }
+            let after: f32 = channel.iter().sum();
+            let scale = if after > 1e-10 { before / after * keep } else { keep };
+            channel.iter_mut().for_each(|v| *v = (*v * scale).max(0.0));
+        }
+    }
+
+    fn set_velocity_walls(&mut self) {
+        let dims = (self.width, self.height, self.depth);
+        for field in [&mut self.velocity_x, &mut self.velocity_y, &mut self.velocity_z] {
+            for_each_wall(dims, |idx, _| field[idx] = 0.0);
+        }
+    }
+
+    fn project(&mut self) {
+        let (w, h, d) = (self.width, self.height, self.depth);
+        let plane = w * h;
+        let (vx, vy, vz) = (&self.velocity_x, &self.velocity_y, &self.velocity_z);
+        for z in 1..d - 1 {
+            for y in 1..h - 1 {
+                for x in 1..w - 1 {
+                    let idx = (z * h + y) * w + x;
+                    self.divergence[idx] = -0.5
+                        * (vx[idx + 1] - vx[idx - 1] + vy[idx + w] - vy[idx - w] + vz[idx + plane] - vz[idx - plane]);
+                }
+            }
+        }
+        self.pressure.fill(0.0);
+
+        // Red-black Gauss-Seidel, walls mirroring their inner neighbour
+        for _ in 0..self.pressure_iterations {
+            for parity in 0..2 {
+                for z in 1..d - 1 {
+                    for y in 1..h - 1 {
+                        let start = 1 + (y + z + parity) % 2;
+                        for x in (start..w - 1).step_by(2) {
+                            let idx = (z * h + y) * w + x;
+                            let p = &self.pressure;
+                            let sum = p[idx - 1] + p[idx + 1] + p[idx - w] + p[idx + w] + p[idx - plane] + p[idx + plane];
+                            self.pressure[idx] = (self.divergence[idx] + sum) / 6.0;
+                        }
+                    }
+                }
+            }
+            let pressure = &mut self.pressure;
+            for_each_wall((w, h, d), |idx, inner| pressure[idx] = pressure[inner]);
+        }
+
+        let p = &self.pressure;
+        for z in 1..d - 1 {
+            for y in 1..h - 1 {
+                for x in 1..w - 1 {
+                    let idx = (z * h + y) * w + x;
+                    self.velocity_x[idx] -= 0.5 * (p[idx + 1] - p[idx - 1]);
+                    self.velocity_y[idx] -= 0.5 * (p[idx + w] - p[idx - w]);
+                    self.velocity_z[idx] -= 0.5 * (p[idx + plane] - p[idx - plane]);
+                }
+            }
+        }
+        self.set_velocity_walls();
+    }
+
+    fn image_size(&self, axis: Axis) -> (usize, usize, usize) {
+        match axis {
+            Axis::X => (self.depth, self.height, self.width),
+            Axis::Y => (self.width, self.depth, self.height),
+            Axis::Z => (self.width, self.height, self.depth),
+        }
+    }
+
+    // Cell at image pixel (u, v) and distance `t` along the viewing axis
+    fn voxel(&self, axis: Axis, u: usize, v: usize, t: usize) -> usize {
+        match axis {
+            Axis::X => self.index(t, v, u),
+            Axis::Y => self.index(u, t, v),
+            Axis::Z => self.index(u, v, t),
+        }
+    }
+
+    /// The dye in one plane across `axis`, clamped to [0, 1] per channel.
+    /// Looking along X the image is depth × height, along Y width × depth.
+    pub fn slice_image(&self, axis: Axis, index: usize) -> RgbImage {
+        let (iw, ih, len) = self.image_size(axis);
+        let index = index.min(len - 1);
+        RgbImage::from_fn(iw as u32, ih as u32, |u, v| {
+            let idx = self.voxel(axis, u as usize, v as usize, index);
+            let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0) as u8;
+            Rgb([channel(self.dye_r[idx]), channel(self.dye_g[idx]), channel(self.dye_b[idx])])
+        })
+    }
+
+    /// Emission-absorption raymarch along `axis`, front to back from index 0.
+    /// Each cell's dye both glows in its own hue and hides what lies behind
+    /// it, by `absorption` per unit of dye.
+    pub fn volume_image(&self, axis: Axis, absorption: f32) -> RgbImage {
+        let (iw, ih, len) = self.image_size(axis);
+        RgbImage::from_fn(iw as u32, ih as u32, |u, v| {
+            let (mut color, mut transmit) = ([0.0f32; 3], 1.0f32);
+            for t in 0..len {
+                let idx = self.voxel(axis, u as usize, v as usize, t);
+                let dye = [self.dye_r[idx], self.dye_g[idx], self.dye_b[idx]];
+                let density: f32 = dye.iter().sum();
+                if density <= 0.0 {
+                    continue;
+                }
+                let alpha = 1.0 - (-absorption * density).exp();
+                for (c, d) in color.iter_mut().zip(dye) {
+                    *c += transmit * alpha * d / density;
+                }
+                transmit *= 1.0 - alpha;
+                if transmit < 0.01 {
+                    break;
+                }
+            }
+            Rgb(color.map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8))
+        })
+    }
+
+    /// Every slice across `axis` as `slice_000.png`, `slice_001.png`, ... in
+    /// `output_dir`; returns how many were written.
+    pub fn export_slices(&self, axis: Axis, output_dir: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(output_dir)?;
+        let (_, _, len) = self.image_size(axis);
+        for index in 0..len {
+            self.slice_image(axis, index).save(output_dir.join(format!("slice_{:03}.png", index)))?;
+        }
+        Ok(len)
+    }
+
+    pub fn export_volume_png(&self, axis: Axis, absorption: f32, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.volume_image(axis, absorption).save(path)?;
+        Ok(())
+    }
+}
+
+// Calls `f(wall, inner)` for each cell on the box's shell with the nearest
+// interior cell
+fn for_each_wall(dims: (usize, usize, usize), mut f: impl FnMut(usize, usize)) {
+    let (w, h, d) = dims;
+    let inner = |c: usize, n: usize| c.clamp(1, n - 2);
+    for z in 0..d {
+        for y in 0..h {
+            let shell_row = z == 0 || y == 0 || z == d - 1 || y == h - 1;
+            let xs: Vec<usize> = if shell_row { (0..w).collect() } else { vec![0, w - 1] };
+            for x in xs {
+                f((z * h + y) * w + x, (inner(z, d) * h + inner(y, h)) * w + inner(x, w));
+            }
+        }
+    }
+}
+
+// Gauss-Seidel solve of (1 + 6a) x - a Σ neighbours = x0 over the interior
+fn diffuse(field: &mut [f32], prev: &[f32], dims: (usize, usize, usize), a: f32) {
+    let (w, h, d) = dims;
+    let plane = w * h;
+    for _ in 0..4 {
+        for z in 1..d - 1 {
+            for y in 1..h - 1 {
+                for x in 1..w - 1 {
+                    let idx = (z * h + y) * w + x;
+                    let sum = field[idx - 1]
+                        + field[idx + 1]
+                        + field[idx - w]
+                        + field[idx + w]
+                        + field[idx - plane]
+                        + field[idx + plane];
+                    field[idx] = (prev[idx] + a * sum) / (1.0 + 6.0 * a);
+                }
+            }
+        }
+    }
+}
+
+// Semi-Lagrangian backtrace through `flow`, one z plane per task
+fn advect(field: &mut [f32], prev: &[f32], flow: [&Vec<f32>; 3], dims: (usize, usize, usize), dt: f32) {
+    let (w, h, d) = dims;
+    field.par_chunks_mut(w * h).enumerate().for_each(|(z, plane)| {
+        if z == 0 || z == d - 1 {
+            return;
+        }
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                let idx = (z * h + y) * w + x;
+                let back = Vec3::new(x as f32, y as f32, z as f32)
+                    - dt * Vec3::new(flow[0][idx], flow[1][idx], flow[2][idx]);
+                plane[y * w + x] = sample(prev, dims, back);
+            }
+        }
+    });
+}
+
+// Trilinear sample, clamped to the interior
+fn sample(field: &[f32], dims: (usize, usize, usize), p: Vec3) -> f32 {
+    let (w, h, d) = dims;
+    let axis = |c: f32, n: usize| {
+        let c = c.clamp(0.5, n as f32 - 1.5);
+        let i = c as usize;
+        (i, c - i as f32)
+    };
+    let ((x0, fx), (y0, fy), (z0, fz)) = (axis(p.x, w), axis(p.y, h), axis(p.z, d));
+    let at = |x: usize, y: usize, z: usize| field[(z * h + y) * w + x];
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let front = lerp(
+        lerp(at(x0, y0, z0), at(x0 + 1, y0, z0), fx),
+        lerp(at(x0, y0 + 1, z0), at(x0 + 1, y0 + 1, z0), fx),
+        fy,
+    );
+    let back = lerp(
+        lerp(at(x0, y0, z0 + 1), at(x0 + 1, y0, z0 + 1), fx),
+        lerp(at(x0, y0 + 1, z0 + 1), at(x0 + 1, y0 + 1, z0 + 1), fx),
+        fy,
+    );
+    lerp(front, back, fz)
+}
+
+impl FluidSimulation3D for Fluid3D {
+    fn step(&mut self) {
+        Fluid3D::step(self);
+    }
+
+    /// Pushes the cells within two of (x, y, z), fading to the edge.
+    fn add_force(&mut self, x: usize, y: usize, z: usize, force: Vec3) {
+        self.add_force_ball(x, y, z, force, 2.0);
+    }
+
+    /// Adds dye to the cells within two of (x, y, z), fading to the edge.
+    fn add_dye(&mut self, x: usize, y: usize, z: usize, color: (f32, f32, f32)) {
+        self.add_dye_ball(x, y, z, color, 2.0);
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn depth(&self) -> usize {
+        self.depth
+    }
+}
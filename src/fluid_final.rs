@@ -1,5 +1,8 @@
+#![allow(deprecated)]
+
 use glam::Vec2;
 
+#[deprecated(note = "use solver::Solver with Scheme::ForwardMove")]
 #[derive(Debug, Clone)]
 pub struct FluidFinal {
     pub width: usize,
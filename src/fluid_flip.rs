@@ -0,0 +1,428 @@
+//! FLIP/PIC liquid: velocity rides on particles, so splashes keep their
+//! detail, while a staggered (MAC) grid does the pressure projection. Each
+//! step moves particle velocity to the grid, makes the liquid cells
+//! incompressible with zero pressure in the air, and brings the result back
+//! to the particles as a blend of the new grid velocity (PIC, stable but
+//! smooth) and the particle velocity plus the grid's change (FLIP, lively but
+//! noisy).
+
+use crate::export::FluidData;
+use crate::{ConfigurableSimulation, FluidSimulation, Param};
+use glam::Vec2;
+
+/// Particles seeded per axis in each liquid cell, e.g. 2 gives 4 per cell.
+pub const SEEDS_PER_AXIS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlipParticle {
+    /// In cells; cell (x, y) spans [x, x + 1) × [y, y + 1)
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Solid,
+    Liquid,
+    Air,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlipFluid {
+    pub width: usize,
+    pub height: usize,
+    pub particles: Vec<FlipParticle>,
+    /// Horizontal velocity on the left face of each cell, `(width + 1) * height`
+    pub u: Vec<f32>,
+    /// Vertical velocity on the top face of each cell, `width * (height + 1)`
+    pub v: Vec<f32>,
+    pub cells: Vec<Cell>,
+    pub pressure: Vec<f32>,
+    /// Velocity at cell centres, for rendering and metrics
+    pub velocity_x: Vec<f32>,
+    pub velocity_y: Vec<f32>,
+    /// Liquid in each cell, from particle counts, in [0, 1]
+    pub fraction: Vec<f32>,
+    pub dt: f32,
+    /// Downward acceleration in cells per time unit squared
+    pub gravity: f32,
+    /// 0 is pure PIC, 1 pure FLIP
+    pub flip_ratio: f32,
+    pub pressure_iterations: usize,
+    u_prev: Vec<f32>,
+    v_prev: Vec<f32>,
+}
+
+impl FlipFluid {
+    /// An empty tank walled by its outer ring of cells.
+    pub fn new(width: usize, height: usize) -> Self {
+        let size = width * height;
+        let mut fluid = Self {
+            width,
+            height,
+            particles: Vec::new(),
+            u: vec![0.0; (width + 1) * height],
+            v: vec![0.0; width * (height + 1)],
+            cells: vec![Cell::Air; size],
+            pressure: vec![0.0; size],
+            velocity_x: vec![0.0; size],
+            velocity_y: vec![0.0; size],
+            fraction: vec![0.0; size],
+            dt: 0.1,
+            gravity: 20.0,
+            flip_ratio: 0.95,
+            pressure_iterations: 40,
+            u_prev: vec![0.0; (width + 1) * height],
+            v_prev: vec![0.0; width * (height + 1)],
+        };
+        fluid.mark_cells();
+        fluid
+    }
+
+    /// Seed liquid at rest in cells `min..max`, clipped to the tank's interior.
+    pub fn add_liquid_box(&mut self, min: (usize, usize), max: (usize, usize)) {
+        for y in min.1.max(1)..max.1.min(self.height - 1) {
+            for x in min.0.max(1)..max.0.min(self.width - 1) {
+                self.seed_cell(x, y, Vec2::ZERO);
+            }
+        }
+        self.mark_cells();
+        self.update_fields();
+    }
+
+    /// Liquid up to `level` of the tank's inner height, 0 empty to 1 full.
+    pub fn fill_below(&mut self, level: f32) {
+        let depth = (level.clamp(0.0, 1.0) * (self.height - 2) as f32).round() as usize;
+        self.add_liquid_box((1, self.height - 1 - depth), (self.width - 1, self.height - 1));
+    }
+
+    fn seed_cell(&mut self, x: usize, y: usize, velocity: Vec2) {
+        let spacing = 1.0 / SEEDS_PER_AXIS as f32;
+        for j in 0..SEEDS_PER_AXIS {
+            for i in 0..SEEDS_PER_AXIS {
+                // Jitter off the lattice so particles don't stay in lockstep
+                let jitter = 0.2 * spacing * hash_unit(x * 31 + i, y * 17 + j);
+                let offset = Vec2::new((i as f32 + 0.5) * spacing + jitter, (j as f32 + 0.5) * spacing - jitter);
+                self.particles.push(FlipParticle {
+                    position: Vec2::new(x as f32, y as f32) + offset,
+                    velocity,
+                });
+            }
+        }
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[y * self.width + x]
+    }
+
+    /// Highest liquid cell in each column (smallest y), or `height` where a
+    /// column is dry.
+    pub fn surface_heights(&self) -> Vec<usize> {
+        (0..self.width)
+            .map(|x| (1..self.height - 1).find(|&y| self.cell(x, y) == Cell::Liquid).unwrap_or(self.height))
+            .collect()
+    }
+
+    pub fn kinetic_energy(&self) -> f32 {
+        self.particles.iter().map(|p| 0.5 * p.velocity.length_squared()).sum()
+    }
+
+    pub fn step(&mut self) {
+        self.particles_to_grid();
+        self.mark_cells();
+        self.u_prev.copy_from_slice(&self.u);
+        self.v_prev.copy_from_slice(&self.v);
+        let pull = self.dt * self.gravity;
+        self.v.iter_mut().for_each(|v| *v += pull);
+        self.enforce_walls();
+        self.project();
+        self.grid_to_particles();
+        self.advect_particles();
+        self.mark_cells();
+        self.update_fields();
+    }
+
+    // Particle velocity onto the faces around each particle, weighted bilinearly
+    fn particles_to_grid(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let mut u_weight = vec![0.0f32; self.u.len()];
+        let mut v_weight = vec![0.0f32; self.v.len()];
+        self.u.fill(0.0);
+        self.v.fill(0.0);
+        for particle in &self.particles {
+            let p = particle.position;
+            splat(&mut self.u, &mut u_weight, w + 1, h, p.x, p.y - 0.5, particle.velocity.x);
+            splat(&mut self.v, &mut v_weight, w, h + 1, p.x - 0.5, p.y, particle.velocity.y);
+        }
+        for (value, weight) in self.u.iter_mut().zip(&u_weight).chain(self.v.iter_mut().zip(&v_weight)) {
+            if *weight > 0.0 {
+                *value /= weight;
+            }
+        }
+    }
+
+    fn mark_cells(&mut self) {
+        let (w, h) = (self.width, self.height);
+        for y in 0..h {
+            for x in 0..w {
+                let wall = x == 0 || y == 0 || x == w - 1 || y == h - 1;
+                self.cells[y * w + x] = if wall { Cell::Solid } else { Cell::Air };
+            }
+        }
+        for particle in &self.particles {
+            let (x, y) = (particle.position.x as usize, particle.position.y as usize);
+            if x < w && y < h && self.cells[y * w + x] == Cell::Air {
+                self.cells[y * w + x] = Cell::Liquid;
+            }
+        }
+    }
+
+    // Nothing flows through a face touching a wall
+    fn enforce_walls(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let solid = |x: usize, y: usize| x >= w || y >= h || self.cells[y * w + x] == Cell::Solid;
+        for y in 0..h {
+            for x in 0..=w {
+                if (x > 0 && solid(x - 1, y)) || solid(x, y) {
+                    self.u[y * (w + 1) + x] = 0.0;
+                }
+            }
+        }
+        for y in 0..=h {
+            for x in 0..w {
+                if (y > 0 && solid(x, y - 1)) || solid(x, y) {
+                    self.v[y * w + x] = 0.0;
+                }
+            }
+        }
+    }
+
+    // Pressure in liquid cells only; air holds zero and walls are excluded
+    fn project(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let cells = &self.cells;
+        for (p, cell) in self.pressure.iter_mut().zip(cells) {
+            if *cell != Cell::Liquid {
+                *p = 0.0;
+            }
+        }
+
+        let divergence: Vec<f32> = (0..w * h)
+            .map(|idx| {
+                let (x, y) = (idx % w, idx / w);
+                if cells[idx] != Cell::Liquid {
+                    return 0.0;
+                }
+                self.u[y * (w + 1) + x + 1] - self.u[y * (w + 1) + x] + self.v[(y + 1) * w + x] - self.v[y * w + x]
+            })
+            .collect();
+
+        for _ in 0..self.pressure_iterations {
+            for y in 1..h - 1 {
+                for x in 1..w - 1 {
+                    let idx = y * w + x;
+                    if cells[idx] != Cell::Liquid {
+                        continue;
+                    }
+                    let (mut sum, mut count) = (-divergence[idx], 0.0);
+                    for n in [idx - 1, idx + 1, idx - w, idx + w] {
+                        if cells[n] != Cell::Solid {
+                            sum += self.pressure[n];
+                            count += 1.0;
+                        }
+                    }
+                    self.pressure[idx] = if count > 0.0 { sum / count } else { 0.0 };
+                }
+            }
+        }
+
+        // Faces between two non-solid cells with liquid on either side
+        let pressure = &self.pressure;
+        for y in 1..h - 1 {
+            for x in 1..w {
+                let (left, right) = (y * w + x - 1, y * w + x);
+                let fluid = cells[left] == Cell::Liquid || cells[right] == Cell::Liquid;
+                if fluid && cells[left] != Cell::Solid && cells[right] != Cell::Solid {
+                    self.u[y * (w + 1) + x] -= pressure[right] - pressure[left];
+                }
+            }
+        }
+        for y in 1..h {
+            for x in 1..w - 1 {
+                let (top, bottom) = ((y - 1) * w + x, y * w + x);
+                let fluid = cells[top] == Cell::Liquid || cells[bottom] == Cell::Liquid;
+                if fluid && cells[top] != Cell::Solid && cells[bottom] != Cell::Solid {
+                    self.v[y * w + x] -= pressure[bottom] - pressure[top];
+                }
+            }
+        }
+        self.enforce_walls();
+    }
+
+    fn grid_to_particles(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let ratio = self.flip_ratio.clamp(0.0, 1.0);
+        for particle in &mut self.particles {
+            let p = particle.position;
+            let pic = Vec2::new(sample(&self.u, w + 1, h, p.x, p.y - 0.5), sample(&self.v, w, h + 1, p.x - 0.5, p.y));
+            let before = Vec2::new(
+                sample(&self.u_prev, w + 1, h, p.x, p.y - 0.5),
+                sample(&self.v_prev, w, h + 1, p.x - 0.5, p.y),
+            );
+            let flip = particle.velocity + pic - before;
+            particle.velocity = ratio * flip + (1.0 - ratio) * pic;
+        }
+    }
+
+    /// Grid velocity at a point in cells.
+    pub fn velocity_at(&self, position: Vec2) -> Vec2 {
+        let (w, h) = (self.width, self.height);
+        Vec2::new(
+            sample(&self.u, w + 1, h, position.x, position.y - 0.5),
+            sample(&self.v, w, h + 1, position.x - 0.5, position.y),
+        )
+    }
+
+    // Midpoint steps through the projected grid, kept off the walls
+    fn advect_particles(&mut self) {
+        let (max_x, max_y) = (self.width as f32 - 1.001, self.height as f32 - 1.001);
+        let dt = self.dt;
+        let moved: Vec<Vec2> = self
+            .particles
+            .iter()
+            .map(|particle| {
+                let mid = particle.position + 0.5 * dt * self.velocity_at(particle.position);
+                let moved = particle.position + dt * self.velocity_at(mid);
+                Vec2::new(moved.x.clamp(1.001, max_x), moved.y.clamp(1.001, max_y))
+            })
+            .collect();
+        for (particle, position) in self.particles.iter_mut().zip(moved) {
+            particle.position = position;
+        }
+    }
+
+    fn update_fields(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let per_cell = (SEEDS_PER_AXIS * SEEDS_PER_AXIS) as f32;
+        self.fraction.fill(0.0);
+        for particle in &self.particles {
+            let (x, y) = (particle.position.x as usize, particle.position.y as usize);
+            if x < w && y < h {
+                self.fraction[y * w + x] += 1.0 / per_cell;
+            }
+        }
+        self.fraction.iter_mut().for_each(|f| *f = f.min(1.0));
+        for y in 0..h {
+            for x in 0..w {
+                let idx = y * w + x;
+                self.velocity_x[idx] = 0.5 * (self.u[y * (w + 1) + x] + self.u[y * (w + 1) + x + 1]);
+                self.velocity_y[idx] = 0.5 * (self.v[y * w + x] + self.v[(y + 1) * w + x]);
+            }
+        }
+    }
+}
+
+// Deterministic value in [-1, 1) for seeding jitter
+fn hash_unit(a: usize, b: usize) -> f32 {
+    let mut n = (a as u32).wrapping_mul(0x9E37_79B1) ^ (b as u32).wrapping_mul(0x85EB_CA77);
+    n ^= n >> 15;
+    n = n.wrapping_mul(0x2C1B_3C6D);
+    n ^= n >> 12;
+    (n & 0xFFFF) as f32 / 32768.0 - 1.0
+}
+
+// Bilinear footprint of (x, y) on a grid of `gw` × `gh` samples
+fn footprint(gw: usize, gh: usize, x: f32, y: f32) -> [(usize, f32); 4] {
+    let x = x.clamp(0.0, (gw - 1) as f32);
+    let y = y.clamp(0.0, (gh - 1) as f32);
+    let (x0, y0) = ((x as usize).min(gw.saturating_sub(2)), (y as usize).min(gh.saturating_sub(2)));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    [
+        (y0 * gw + x0, (1.0 - fx) * (1.0 - fy)),
+        (y0 * gw + x0 + 1, fx * (1.0 - fy)),
+        ((y0 + 1) * gw + x0, (1.0 - fx) * fy),
+        ((y0 + 1) * gw + x0 + 1, fx * fy),
+    ]
+}
+
+fn splat(grid: &mut [f32], weights: &mut [f32], gw: usize, gh: usize, x: f32, y: f32, value: f32) {
+    for (idx, weight) in footprint(gw, gh, x, y) {
+        grid[idx] += weight * value;
+        weights[idx] += weight;
+    }
+}
+
+fn sample(grid: &[f32], gw: usize, gh: usize, x: f32, y: f32) -> f32 {
+    footprint(gw, gh, x, y).iter().map(|&(idx, weight)| weight * grid[idx]).sum()
+}
+
+impl FluidSimulation for FlipFluid {
+    fn step(&mut self) {
+        FlipFluid::step(self);
+    }
+
+    /// Push the particles within three cells of (x, y).
+    fn add_force(&mut self, x: usize, y: usize, force: Vec2) {
+        let centre = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+        for particle in &mut self.particles {
+            if (particle.position - centre).length_squared() <= 9.0 {
+                particle.velocity += force;
+            }
+        }
+    }
+
+    /// Pours liquid into the cell at (x, y); particles carry no dye, so the
+    /// color is ignored.
+    fn add_dye(&mut self, x: usize, y: usize, _color: (f32, f32, f32)) {
+        if x > 0 && y > 0 && x < self.width - 1 && y < self.height - 1 {
+            self.seed_cell(x, y, Vec2::ZERO);
+            self.mark_cells();
+            self.update_fields();
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+}
+
+impl ConfigurableSimulation for FlipFluid {
+    fn params(&self) -> Vec<(Param, f32)> {
+        vec![(Param::Dt, self.dt)]
+    }
+
+    fn set_param(&mut self, param: Param, value: f32) -> Result<(), Box<dyn std::error::Error>> {
+        match param {
+            Param::Dt => self.dt = param.validate(value)?,
+            _ => return Err(format!("the FLIP solver has no {} parameter", param.name()).into()),
+        }
+        Ok(())
+    }
+}
+
+/// Density is the liquid fraction, so renderers and metrics show the water.
+impl FluidData for FlipFluid {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn density(&self) -> &[f32] {
+        &self.fraction
+    }
+
+    fn velocity_x(&self) -> &[f32] {
+        &self.velocity_x
+    }
+
+    fn velocity_y(&self) -> &[f32] {
+        &self.velocity_y
+    }
+}
@@ -1,9 +1,149 @@
-use crate::FluidSimulation;
+use crate::{BoundaryMode, ConfigurableSimulation, FluidSimulation, Param};
+use crate::brush::{BlendMode, Brush, Falloff};
+use crate::chemistry::Chemistry;
+use crate::kernels;
+use crate::mask::Mask;
+use crate::obstacle::MovingObstacle;
+use crate::replay::{Input, Recording};
+use crate::rheology::{self, PowerLaw, VISCOSITY_FACTOR_RANGE};
+use crate::scalar::ScalarField;
+use crate::timing::{now_seconds, Stage, StageTimes};
+use crate::turbulence::Turbulence;
+#[cfg(feature = "spectral")]
+use crate::spectral::SpectralSolver;
+use crate::SceneParams;
 use glam::Vec2;
 
 #[cfg(not(target_arch = "wasm32"))]
 use rayon::prelude::*;
 
+// Chunk length for parallel reductions; fixed so summation order never
+// depends on how work is split across threads
+#[cfg(not(target_arch = "wasm32"))]
+const SUM_CHUNK: usize = 4096;
+
+/// Per-frame dye balance at the domain edges. Sides are indexed with the
+/// `LEFT`, `RIGHT`, `TOP` and `BOTTOM` constants; channels are RGB.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BoundaryLeak {
+    /// Dye carried out through each side by the boundary velocity
+    pub outflow: [[f32; 3]; 4],
+    /// Dye added back by mass renormalization to cancel numerical loss
+    /// (negative when the correction removed dye)
+    pub numerical: [f32; 3],
+}
+
+impl BoundaryLeak {
+    pub const LEFT: usize = 0;
+    pub const RIGHT: usize = 1;
+    pub const TOP: usize = 2;
+    pub const BOTTOM: usize = 3;
+
+    /// Outflow through all sides, per channel.
+    pub fn total_outflow(&self) -> [f32; 3] {
+        let mut total = [0.0; 3];
+        for side in &self.outflow {
+            for c in 0..3 {
+                total[c] += side[c];
+            }
+        }
+        total
+    }
+
+    /// Dye that left the domain, summed over sides and channels.
+    pub fn mass_lost(&self) -> f32 {
+        self.total_outflow().iter().sum()
+    }
+
+    pub fn accumulate(&mut self, other: &BoundaryLeak) {
+        for side in 0..4 {
+            for c in 0..3 {
+                self.outflow[side][c] += other.outflow[side][c];
+            }
+        }
+        for c in 0..3 {
+            self.numerical[c] += other.numerical[c];
+        }
+    }
+}
+
+/// Speed limit of a new `InteractiveFluid`, in cells per unit time: far
+/// above anything the tools produce, so only a blow-up reaches it.
+pub const DEFAULT_MAX_VELOCITY: f32 = 1000.0;
+
+/// Most relaxation sweeps one adaptive projection may run before it gives up
+/// on `divergence_tolerance`.
+pub const ADAPTIVE_PRESSURE_LIMIT: usize = 400;
+
+/// Share of the dye at the center of an absorbing attractor that vanishes
+/// per unit of time.
+pub const ABSORB_RATE: f32 = 2.0;
+
+/// Velocity divergence over the fluid cells, in cells per unit time.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DivergenceStats {
+    pub max: f32,
+    pub mean: f32,
+}
+
+/// Cells repaired by `InteractiveFluid::sanitize`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SanitizeEvents {
+    /// Cells whose speed was scaled back to `max_velocity`
+    pub clamped: u64,
+    /// NaN or infinite values reset to rest
+    pub reset: u64,
+}
+
+impl SanitizeEvents {
+    pub fn total(&self) -> u64 {
+        self.clamped + self.reset
+    }
+
+    pub fn accumulate(&mut self, other: &SanitizeEvents) {
+        self.clamped += other.clamped;
+        self.reset += other.reset;
+    }
+}
+
+/// Where `InteractiveFluid::step_with` keeps the dye. `step` uses the
+/// fluid's own f32 channels (`OwnDye`); `HalfDyeFluid` stores them as f16
+/// and releases the f32 ones, so every pass that touches dye goes through
+/// here rather than the fluid's fields.
+pub trait DyeStorage {
+    /// Red, green and blue dye in cell `idx`, as the dye-driven forces
+    /// (weight, surface tension) read it.
+    fn dye(&self, fluid: &InteractiveFluid, idx: usize) -> [f32; 3];
+
+    /// Diffuse, advect, fade and react the dye through the fluid's velocity,
+    /// recording what leaves in `fluid.boundary_leak`.
+    fn step(&mut self, fluid: &mut InteractiveFluid);
+
+    /// Closing pass of the step: fill the dye's edge ring for the fluid's
+    /// boundary mode and reset NaN or infinite dye, counting the repairs in
+    /// `fluid.sanitize_events`.
+    fn finish(&mut self, fluid: &mut InteractiveFluid);
+}
+
+/// The fluid's own f32 dye channels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OwnDye;
+
+impl DyeStorage for OwnDye {
+    fn dye(&self, fluid: &InteractiveFluid, idx: usize) -> [f32; 3] {
+        [fluid.dye_r[idx], fluid.dye_g[idx], fluid.dye_b[idx]]
+    }
+
+    fn step(&mut self, fluid: &mut InteractiveFluid) {
+        fluid.step_dye();
+    }
+
+    // NaN dye is reset by the `sanitize` that follows
+    fn finish(&mut self, fluid: &mut InteractiveFluid) {
+        fluid.set_dye_boundaries();
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct InteractiveFluid {
     pub width: usize,
@@ -21,8 +161,90 @@ pub struct InteractiveFluid {
     pub pressure: Vec<f32>,
     pub divergence: Vec<f32>,
     pub dt: f32,
+    /// Height of a cell over its width, for grids whose cells aren't square
+    /// on screen. Velocities are in cell widths per unit time on both axes,
+    /// so a flow keeps its speed and direction whatever the grid's shape;
+    /// see `set_domain_size`
+    pub cell_aspect: f32,
     pub viscosity: f32,
+    /// Shear-thinning or -thickening: the viscosity each cell diffuses with
+    /// follows its shear rate. `None` is Newtonian. Velocity diffusion runs
+    /// on the sweeps while it is set, even with the FFT solver enabled
+    pub shear_model: Option<PowerLaw>,
+    /// Factor on `viscosity` per cell, row-major, for thick and runny
+    /// regions; see `paint_viscosity`. Empty, or of another grid's size,
+    /// counts as 1 everywhere. Like `shear_model`, a painted field keeps
+    /// velocity diffusion on the sweeps
+    pub viscosity_field: Vec<f32>,
+    /// Global drag: velocity decays by `exp(-velocity_damping * dt)` each
+    /// step, so a stirred scene settles instead of sloshing forever; 0 turns
+    /// it off
+    pub velocity_damping: f32,
+    /// Strength of a surface-tension-like force along the edges of the dye,
+    /// pulling painted blobs round instead of letting them smear; 0 turns it
+    /// off. See `apply_surface_tension`
+    pub surface_tension: f32,
     pub dye_diffusion: f32,
+    /// Rate at which dye fades, per unit time; 0 keeps it forever
+    pub dye_dissipation: f32,
+    /// Extra fade rate per RGB channel on top of `dye_dissipation`, so one
+    /// colour can clear faster than the others
+    pub dye_decay: [f32; 3],
+    /// Reactions between the dye channels, run after every advection
+    pub chemistry: Option<Chemistry>,
+    /// Upper bound on pressure relaxation sweeps per projection, or the
+    /// sweeps between divergence checks while `divergence_tolerance` is set
+    pub pressure_iterations: usize,
+    /// Adaptive projection: keep sweeping until the largest divergence left
+    /// after projecting is below this, up to `ADAPTIVE_PRESSURE_LIMIT`
+    /// sweeps. `None` runs at most `pressure_iterations`
+    pub divergence_tolerance: Option<f32>,
+    /// Relaxation sweeps the last projection ran
+    pub pressure_iterations_used: usize,
+    /// What happens at the edges; `Absorbing` is treated as `NoSlip`.
+    /// Dye leaving through `Open` edges is removed and reported in
+    /// `boundary_leak`
+    pub boundary: BoundaryMode,
+    // Temperature drives buoyancy: cells hotter than ambient rise, cooler sink
+    pub temperature: Vec<f32>,
+    pub temperature_prev: Vec<f32>,
+    pub ambient_temperature: f32,
+    pub buoyancy: f32,
+    /// Weight of each RGB dye channel per unit of concentration, as a
+    /// Boussinesq density: positive channels sink, negative ones rise, so a
+    /// heavy color poured over a light one falls in plumes and fingers. All
+    /// zero keeps the dye passive
+    pub dye_density: [f32; 3],
+    /// Fraction of the excess temperature lost to the surroundings per unit time
+    pub cooling: f32,
+    /// Dye balance of the most recent step
+    pub boundary_leak: BoundaryLeak,
+    /// Speed limit enforced by `sanitize`; `f32::INFINITY` turns clamping off
+    pub max_velocity: f32,
+    /// Cells repaired by `sanitize` since the simulation was created
+    pub sanitize_events: SanitizeEvents,
+    /// Inputs and steps are logged here while set
+    pub recording: Option<Recording>,
+    /// Extra quantities advected with the dye, each with its own diffusion
+    /// and fade; see `add_scalar`
+    pub scalars: Vec<ScalarField>,
+    /// Cells the flow can't enter: velocity and dye in them are held at zero
+    /// through every step, so dye reaching one is lost. See `Mask::from_gray`
+    pub obstacles: Option<Mask>,
+    /// Rigid obstacles that travel and spin; the fluid they cover moves
+    /// with their surface on every step, and floating ones are carried by it
+    pub moving_obstacles: Vec<MovingObstacle>,
+    /// Curl noise stirred into the velocity at the start of every step
+    pub turbulence: Option<Turbulence>,
+    /// Wall-clock time of each stage of the last `step`
+    pub stage_times: StageTimes,
+    /// FFT projection and diffusion, used in place of the sweeps while
+    /// `boundary` is `Periodic`; see `enable_spectral`
+    #[cfg(feature = "spectral")]
+    pub spectral: Option<SpectralSolver>,
+    // Copy of the field being relaxed, so red-black sweeps can read
+    // neighbours while rows are written in parallel
+    scratch: Vec<f32>,
 }
 
 impl FluidSimulation for InteractiveFluid {
@@ -47,6 +269,28 @@ impl FluidSimulation for InteractiveFluid {
     }
 }
 
+impl ConfigurableSimulation for InteractiveFluid {
+    fn params(&self) -> Vec<(Param, f32)> {
+        vec![
+            (Param::Dt, self.dt),
+            (Param::Viscosity, self.viscosity),
+            (Param::Diffusion, self.dye_diffusion),
+            (Param::Dissipation, self.dye_dissipation),
+        ]
+    }
+
+    fn set_param(&mut self, param: Param, value: f32) -> Result<(), Box<dyn std::error::Error>> {
+        let value = param.validate(value)?;
+        match param {
+            Param::Dt => self.dt = value,
+            Param::Viscosity => self.viscosity = value,
+            Param::Diffusion => self.dye_diffusion = value,
+            Param::Dissipation => self.dye_dissipation = value,
+        }
+        Ok(())
+    }
+}
+
 impl InteractiveFluid {
     pub fn new(width: usize, height: usize) -> Self {
         let size = width * height;
@@ -66,12 +310,71 @@ impl InteractiveFluid {
             pressure: vec![0.0; size],
             divergence: vec![0.0; size],
             dt: 0.1,
+            cell_aspect: 1.0,
             viscosity: 0.001,
+            shear_model: None,
+            viscosity_field: Vec::new(),
+            velocity_damping: 0.0,
+            surface_tension: 0.0,
             dye_diffusion: 0.0001,
+            dye_dissipation: 0.0,
+            dye_decay: [0.0; 3],
+            chemistry: None,
+            pressure_iterations: 20,
+            divergence_tolerance: None,
+            pressure_iterations_used: 0,
+            boundary: BoundaryMode::NoSlip,
+            temperature: vec![0.0; size],
+            temperature_prev: vec![0.0; size],
+            ambient_temperature: 0.0,
+            buoyancy: 1.0,
+            dye_density: [0.0; 3],
+            cooling: 0.05,
+            boundary_leak: BoundaryLeak::default(),
+            max_velocity: DEFAULT_MAX_VELOCITY,
+            sanitize_events: SanitizeEvents::default(),
+            recording: None,
+            scalars: Vec::new(),
+            obstacles: None,
+            moving_obstacles: Vec::new(),
+            turbulence: None,
+            stage_times: StageTimes::default(),
+            #[cfg(feature = "spectral")]
+            spectral: None,
+            scratch: vec![0.0; size],
+        }
+    }
+
+    /// Solve pressure and diffusion exactly with FFTs whenever the edges are
+    /// periodic; other edges keep the Gauss-Seidel sweeps.
+    #[cfg(feature = "spectral")]
+    pub fn enable_spectral(&mut self) {
+        self.spectral = Some(SpectralSolver::new(self.width, self.height));
+    }
+
+    /// Stretch the grid over a `width` × `height` domain, in any units,
+    /// setting `cell_aspect` from the two cell sizes.
+    pub fn set_domain_size(&mut self, width: f32, height: f32) {
+        let (dx, dy) = (width / self.width as f32, height / self.height as f32);
+        if dx > 0.0 && dy > 0.0 {
+            self.cell_aspect = dy / dx;
         }
     }
 
+    // Backtrace distance in cells per unit velocity along each axis
+    pub(crate) fn advect_dt(&self) -> [f32; 2] {
+        [self.dt, self.dt / self.cell_aspect]
+    }
+
+    // Neighbour weights and denominator of the implicit diffusion solve
+    // with x weight `a`; taller cells couple more weakly along y
+    pub(crate) fn diffusion_weights(&self, a: f32) -> ([f32; 2], f32) {
+        let ay = a / (self.cell_aspect * self.cell_aspect);
+        ([a, ay], 1.0 + 2.0 * a + 2.0 * ay)
+    }
+
     pub fn add_dye(&mut self, x: usize, y: usize, color: (f32, f32, f32)) {
+        self.record(Input::Dye { x, y, color });
         if x < self.width && y < self.height {
             let idx = y * self.width + x;
             self.dye_r[idx] += color.0;
@@ -81,6 +384,7 @@ impl InteractiveFluid {
     }
 
     pub fn add_force(&mut self, x: usize, y: usize, force: Vec2, radius: f32) {
+        self.record(Input::Force { x, y, force: (force.x, force.y), radius });
         if x < self.width && y < self.height {
             // Apply force in a circular area
             let _center_x = x as f32;
@@ -97,7 +401,8 @@ impl InteractiveFluid {
                         let dist_sq = (dx * dx + dy * dy) as f32;
                         if dist_sq <= r_sq {
                             let idx = py * self.width + px;
-                            let falloff = 1.0 - dist_sq / r_sq;
+                            // A radius under one cell pushes just the center
+                            let falloff = 1.0 - dist_sq / r_sq.max(1.0);
 
                             self.velocity_x[idx] += force.x * falloff;
                             self.velocity_y[idx] += force.y * falloff;
@@ -108,57 +413,726 @@ impl InteractiveFluid {
         }
     }
 
+    /// Add heat in a circular area with the same falloff as `add_force`.
+    /// Negative amounts cool the fluid.
+    pub fn add_heat(&mut self, x: usize, y: usize, amount: f32, radius: f32) {
+        self.record(Input::Heat { x, y, amount, radius });
+        if x < self.width && y < self.height {
+            let r_sq = radius * radius;
+
+            for dy in (-radius as i32)..=(radius as i32) {
+                for dx in (-radius as i32)..=(radius as i32) {
+                    let px = (x as i32 + dx) as usize;
+                    let py = (y as i32 + dy) as usize;
+
+                    if px < self.width && py < self.height {
+                        let dist_sq = (dx * dx + dy * dy) as f32;
+                        if dist_sq <= r_sq {
+                            let idx = py * self.width + px;
+                            self.temperature[idx] += amount * (1.0 - dist_sq / r_sq.max(1.0));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Subtract `amount` from every dye channel of one cell, stopping at zero.
+    /// Paint `color` into a cell with `mode`, covering it by `weight` in
+    /// `0..=1`. Adding goes through `add_dye`.
+    pub fn blend_dye(&mut self, x: usize, y: usize, color: (f32, f32, f32), weight: f32, mode: BlendMode) {
+        if mode == BlendMode::Add {
+            self.add_dye(x, y, (color.0 * weight, color.1 * weight, color.2 * weight));
+            return;
+        }
+        self.record(Input::BlendDye { x, y, color, weight, mode });
+        if x < self.width && y < self.height {
+            let idx = y * self.width + x;
+            self.dye_r[idx] = mode.blend(self.dye_r[idx], color.0, weight);
+            self.dye_g[idx] = mode.blend(self.dye_g[idx], color.1, weight);
+            self.dye_b[idx] = mode.blend(self.dye_b[idx], color.2, weight);
+        }
+    }
+
+    /// Fade a cell's dye by `amount`, from 0 for no change to 1 for none
+    /// left, and its velocity with it when `velocity` is set.
+    pub fn fade_dye(&mut self, x: usize, y: usize, amount: f32, velocity: bool) {
+        self.record(Input::FadeDye { x, y, amount, velocity });
+        if x < self.width && y < self.height {
+            let idx = y * self.width + x;
+            let keep = 1.0 - amount.clamp(0.0, 1.0);
+            self.dye_r[idx] *= keep;
+            self.dye_g[idx] *= keep;
+            self.dye_b[idx] *= keep;
+            if velocity {
+                self.velocity_x[idx] *= keep;
+                self.velocity_y[idx] *= keep;
+            }
+        }
+    }
+
+    pub fn remove_dye(&mut self, x: usize, y: usize, amount: f32) {
+        self.record(Input::RemoveDye { x, y, amount });
+        if x < self.width && y < self.height {
+            let idx = y * self.width + x;
+            self.dye_r[idx] = (self.dye_r[idx] - amount).max(0.0);
+            self.dye_g[idx] = (self.dye_g[idx] - amount).max(0.0);
+            self.dye_b[idx] = (self.dye_b[idx] - amount).max(0.0);
+        }
+    }
+
+    /// Pull fluid towards a point with a smoothed point sink. Cells near the
+    /// center are left alone and the outer fifth of `radius` is damped so the
+    /// sink doesn't leave a ring behind.
+    pub fn add_attractor(&mut self, x: f32, y: f32, radius: f32, strength: f32) {
+        self.record(Input::Attractor { x, y, radius, strength });
+        self.apply_radial_force(Vec2::new(x, y), radius, strength);
+    }
+
+    /// The attractor's smoothed point sink around `center`, or a source for
+    /// negative `strength`, without recording it. Only cells within
+    /// `radius` are visited.
+    pub fn apply_radial_force(&mut self, center: Vec2, radius: f32, strength: f32) {
+        let smoothing = 2.0;
+        let dead_zone = radius * 0.2;
+        let inner_radius = radius * 0.8;
+        let (x0, x1) = Self::span(center.x, radius, self.width);
+        let (y0, y1) = Self::span(center.y, radius, self.height);
+
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let dx = px as f32 - center.x;
+                let dy = py as f32 - center.y;
+                let r_squared = dx * dx + dy * dy;
+                let r = r_squared.sqrt();
+
+                if r > dead_zone && r < radius {
+                    let idx = py * self.width + px;
+                    let factor = -strength / (2.0 * std::f32::consts::PI * (r_squared + smoothing * smoothing));
+
+                    self.velocity_x[idx] += factor * dx;
+                    self.velocity_y[idx] += factor * dy;
+
+                    // Sponge layer
+                    if r > inner_radius {
+                        let damping_factor = ((r - inner_radius) / (radius - inner_radius)).powi(2);
+                        let damping_coeff = 1.0 - damping_factor * 0.2;
+
+                        self.velocity_x[idx] *= damping_coeff;
+                        self.velocity_y[idx] *= damping_coeff;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Let dye vanish into an attractor: within the dead zone at the center
+    /// of an attractor of `radius` (at least a cell), a share `rate` of the
+    /// dye disappears per unit of time.
+    pub fn absorb_dye(&mut self, center: Vec2, radius: f32, rate: f32) {
+        self.record(Input::Absorb { x: center.x, y: center.y, radius, rate });
+        let core = (radius * 0.2).max(1.0);
+        let keep = (1.0 - rate * self.dt).clamp(0.0, 1.0);
+        let (x0, x1) = Self::span(center.x, core, self.width);
+        let (y0, y1) = Self::span(center.y, core, self.height);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                if Vec2::new(px as f32, py as f32).distance_squared(center) <= core * core {
+                    let idx = py * self.width + px;
+                    self.dye_r[idx] *= keep;
+                    self.dye_g[idx] *= keep;
+                    self.dye_b[idx] *= keep;
+                }
+            }
+        }
+    }
+
+    // Cells from `center - reach` to `center + reach` along an axis of `len`
+    fn span(center: f32, reach: f32, len: usize) -> (usize, usize) {
+        ((center - reach).floor().max(0.0) as usize, ((center + reach).ceil().max(0.0) as usize + 1).min(len))
+    }
+
+    /// Push fluid away from a point: the attractor's smoothed sink run as a
+    /// source, with the same dead zone and damped rim. Recorded as an
+    /// attractor of negative strength.
+    pub fn add_repulsor(&mut self, x: f32, y: f32, radius: f32, strength: f32) {
+        self.add_attractor(x, y, radius, -strength);
+    }
+
+    /// Spin the fluid around a point. The tangential speed rises through a
+    /// solid-body core out to a fifth of `radius`, then fades to the edge
+    /// along `falloff`. Positive strength turns clockwise on screen (y
+    /// down), negative counter-clockwise.
+    pub fn add_vortex(&mut self, x: f32, y: f32, radius: f32, strength: f32, falloff: Falloff) {
+        self.record(Input::Vortex { x, y, radius, strength, falloff });
+        if radius <= 0.0 {
+            return;
+        }
+        let core = radius * 0.2;
+        let (x0, x1) = ((x - radius).floor().max(0.0) as usize, ((x + radius).ceil().max(0.0) as usize + 1).min(self.width));
+        let (y0, y1) = ((y - radius).floor().max(0.0) as usize, ((y + radius).ceil().max(0.0) as usize + 1).min(self.height));
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let dx = px as f32 - x;
+                let dy = py as f32 - y;
+                let r = (dx * dx + dy * dy).sqrt();
+                if r == 0.0 || r >= radius {
+                    continue;
+                }
+                let speed = strength * (r / core).min(1.0) * falloff.weight((r - core).max(0.0) / (radius - core));
+                let idx = py * self.width + px;
+                self.velocity_x[idx] -= dy / r * speed;
+                self.velocity_y[idx] += dx / r * speed;
+            }
+        }
+    }
+
+    /// Hold the velocity at `velocity` in every cell within `half_width` of
+    /// the segment from `from` to `to`, like a fan or conveyor belt. Cells
+    /// keep the velocity they're given, so the strip drives a steady current
+    /// rather than accelerating without bound.
+    pub fn add_fan(&mut self, from: Vec2, to: Vec2, half_width: f32, velocity: Vec2) {
+        self.record(Input::Fan {
+            from: (from.x, from.y),
+            to: (to.x, to.y),
+            half_width,
+            velocity: (velocity.x, velocity.y),
+        });
+        let reach = half_width.max(0.5);
+        let (lo, hi) = (from.min(to) - Vec2::splat(reach), from.max(to) + Vec2::splat(reach));
+        let (x0, x1) = (lo.x.floor().max(0.0) as usize, (hi.x.ceil().max(0.0) as usize + 1).min(self.width));
+        let (y0, y1) = (lo.y.floor().max(0.0) as usize, (hi.y.ceil().max(0.0) as usize + 1).min(self.height));
+        let segment = to - from;
+        let length_sq = segment.length_squared();
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let p = Vec2::new(px as f32, py as f32);
+                // Closest point on the segment, or `from` for a zero-length strip
+                let t = if length_sq > 0.0 { ((p - from).dot(segment) / length_sq).clamp(0.0, 1.0) } else { 0.0 };
+                if p.distance_squared(from + segment * t) <= reach * reach {
+                    let idx = py * self.width + px;
+                    self.velocity_x[idx] = velocity.x;
+                    self.velocity_y[idx] = velocity.y;
+                }
+            }
+        }
+    }
+
+    /// Remove all dye and motion and return the temperature to ambient.
+    pub fn clear(&mut self) {
+        self.record(Input::Clear);
+        self.wipe_dye();
+        self.wipe_flow();
+    }
+
+    /// Remove all dye and extra scalars, keeping the flow.
+    pub fn clear_dye(&mut self) {
+        self.record(Input::ClearDye);
+        self.wipe_dye();
+    }
+
+    /// Still the fluid: velocity to zero and temperature back to ambient so
+    /// buoyancy doesn't start it again. Dye stays where it is.
+    pub fn clear_velocity(&mut self) {
+        self.record(Input::ClearVelocity);
+        self.wipe_flow();
+    }
+
+    fn wipe_dye(&mut self) {
+        for field in [&mut self.dye_r, &mut self.dye_g, &mut self.dye_b] {
+            field.fill(0.0);
+        }
+        for field in &mut self.scalars {
+            field.values.fill(0.0);
+        }
+    }
+
+    fn wipe_flow(&mut self) {
+        self.velocity_x.fill(0.0);
+        self.velocity_y.fill(0.0);
+        let ambient = self.ambient_temperature;
+        self.temperature.fill(ambient);
+    }
+
+    /// Carry `field` with the flow from the next step on, returning its
+    /// index in `scalars`. Errors if its grid differs or the name is taken.
+    pub fn add_scalar(&mut self, field: ScalarField) -> Result<usize, Box<dyn std::error::Error>> {
+        if (field.width, field.height) != (self.width, self.height) || field.values.len() != self.width * self.height {
+            return Err(format!(
+                "{} is {}x{}, the simulation is {}x{}",
+                field.name, field.width, field.height, self.width, self.height
+            )
+            .into());
+        }
+        if self.scalar(&field.name).is_some() {
+            return Err(format!("there is already a scalar named {}", field.name).into());
+        }
+        self.scalars.push(field);
+        Ok(self.scalars.len() - 1)
+    }
+
+    pub fn scalar(&self, name: &str) -> Option<&ScalarField> {
+        self.scalars.iter().find(|field| field.name == name)
+    }
+
+    pub fn scalar_mut(&mut self, name: &str) -> Option<&mut ScalarField> {
+        self.scalars.iter_mut().find(|field| field.name == name)
+    }
+
+    fn record(&mut self, input: Input) {
+        if let Some(recording) = &mut self.recording {
+            recording.push(input);
+        }
+    }
+
+    /// Bilinearly sample the velocity field at a fractional grid position.
+    pub fn sample_velocity(&self, x: f32, y: f32) -> Vec2 {
+        Vec2::new(
+            self.sample_field(&self.velocity_x, x, y),
+            self.sample_field(&self.velocity_y, x, y),
+        )
+    }
+
+    /// Bilinearly sample the dye concentration at a fractional grid position.
+    pub fn sample_dye(&self, x: f32, y: f32) -> (f32, f32, f32) {
+        (
+            self.sample_field(&self.dye_r, x, y),
+            self.sample_field(&self.dye_g, x, y),
+            self.sample_field(&self.dye_b, x, y),
+        )
+    }
+
+    fn sample_field(&self, field: &[f32], x: f32, y: f32) -> f32 {
+        sample_grid(field, self.width, self.height, x, y)
+    }
+
+    /// Take on `source`'s dye, velocity, temperature and scalars, resampled
+    /// bilinearly from its grid onto this one, so a resolution change keeps
+    /// the picture. Velocities are scaled with the grid, as they are in
+    /// cells per unit time.
+    pub fn resample_from(&mut self, source: &InteractiveFluid) {
+        let (width, height) = (self.width, self.height);
+        let sx = source.width as f32 / width as f32;
+        let sy = source.height as f32 / height as f32;
+        // Cell centers line up, so edges map to edges
+        let resample = |field: &[f32], source_width: usize, source_height: usize, scale: f32| -> Vec<f32> {
+            (0..width * height)
+                .map(|i| {
+                    let x = ((i % width) as f32 + 0.5) * sx - 0.5;
+                    let y = ((i / width) as f32 + 0.5) * sy - 0.5;
+                    sample_grid(field, source_width, source_height, x, y) * scale
+                })
+                .collect()
+        };
+        let (w, h) = (source.width, source.height);
+        self.dye_r = resample(&source.dye_r, w, h, 1.0);
+        self.dye_g = resample(&source.dye_g, w, h, 1.0);
+        self.dye_b = resample(&source.dye_b, w, h, 1.0);
+        self.velocity_x = resample(&source.velocity_x, w, h, 1.0 / sx);
+        self.velocity_y = resample(&source.velocity_y, w, h, 1.0 / sy);
+        self.temperature = resample(&source.temperature, w, h, 1.0);
+        self.scalars = source
+            .scalars
+            .iter()
+            .map(|field| ScalarField {
+                width,
+                height,
+                values: resample(&field.values, field.width, field.height, 1.0),
+                prev: vec![0.0; width * height],
+                ..field.clone()
+            })
+            .collect();
+        self.set_velocity_boundaries();
+    }
+
     pub fn step(&mut self) {
+        self.step_with(&mut OwnDye);
+    }
+
+    /// `step` with the dye kept in `dye` rather than the fluid's own
+    /// channels. Velocity, temperature, scalars, recording, stage times and
+    /// the closing boundary and sanitize passes are shared, so a dye store
+    /// only supplies the dye passes.
+    pub fn step_with(&mut self, dye: &mut impl DyeStorage) {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("step", width = self.width, height = self.height).entered();
+        self.stage_times = StageTimes::default();
+        let start = now_seconds();
+        if self.recording.is_some() {
+            // Settings changed since the last step are logged with it
+            let params = SceneParams::capture(self);
+            if let Some(recording) = &mut self.recording {
+                recording.step(params);
+            }
+        }
+        self.step_velocity(dye);
+        dye.step(self);
+        self.step_temperature();
+        self.step_scalars();
+
+        // Apply boundary conditions
+        self.set_velocity_boundaries();
+        dye.finish(self);
+        self.sanitize();
+        self.stage_times.total = now_seconds() - start;
+    }
+
+    // Run one stage of the step, adding its time to `stage_times`
+    fn timed(&mut self, stage: Stage, run: impl FnOnce(&mut Self)) {
+        #[cfg(feature = "profiling")]
+        let _span = stage.span().entered();
+        let start = now_seconds();
+        run(self);
+        self.stage_times.add(stage, now_seconds() - start);
+    }
+
+    /// Bytes held by the grid's fields, extra scalars included.
+    pub fn memory_bytes(&self) -> usize {
+        let fields = [
+            &self.velocity_x,
+            &self.velocity_y,
+            &self.velocity_x_prev,
+            &self.velocity_y_prev,
+            &self.dye_r,
+            &self.dye_g,
+            &self.dye_b,
+            &self.dye_r_prev,
+            &self.dye_g_prev,
+            &self.dye_b_prev,
+            &self.pressure,
+            &self.divergence,
+            &self.temperature,
+            &self.temperature_prev,
+            &self.scratch,
+        ];
+        let scalars: usize = self.scalars.iter().map(|field| field.values.len() + field.prev.len()).sum();
+        (fields.iter().map(|field| field.len()).sum::<usize>() + scalars) * std::mem::size_of::<f32>()
+    }
+
+    /// Scale back cells faster than `max_velocity` and reset NaN or infinite
+    /// velocity, dye, temperature and scalars, so one blown-up cell can't
+    /// spread over the whole canvas. Runs before the velocity solve and at
+    /// the end of every step; returns this pass's repairs, which are also
+    /// added to `sanitize_events`.
+    pub fn sanitize(&mut self) -> SanitizeEvents {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("sanitize").entered();
+        let mut events = SanitizeEvents::default();
+        let max = self.max_velocity;
+        for (vx, vy) in self.velocity_x.iter_mut().zip(self.velocity_y.iter_mut()) {
+            if !(vx.is_finite() && vy.is_finite()) {
+                (*vx, *vy) = (0.0, 0.0);
+                events.reset += 1;
+                continue;
+            }
+            let speed = vx.hypot(*vy);
+            if speed > max {
+                let scale = max / speed;
+                *vx *= scale;
+                *vy *= scale;
+                events.clamped += 1;
+            }
+        }
+
+        let mut reset = |field: &mut [f32], rest: f32| {
+            for value in field.iter_mut().filter(|v| !v.is_finite()) {
+                *value = rest;
+                events.reset += 1;
+            }
+        };
+        reset(&mut self.dye_r, 0.0);
+        reset(&mut self.dye_g, 0.0);
+        reset(&mut self.dye_b, 0.0);
+        reset(&mut self.temperature, self.ambient_temperature);
+        for field in &mut self.scalars {
+            reset(&mut field.values, 0.0);
+        }
+
+        self.sanitize_events.accumulate(&events);
+        events
+    }
+
+    /// True when any cell differs from the ambient temperature.
+    pub fn heat_active(&self) -> bool {
+        self.temperature.iter().any(|&t| t != self.ambient_temperature)
+    }
+
+    /// Velocity half of `step`: buoyancy, diffusion, projection and
+    /// advection, with the dye-driven forces reading `dye`.
+    pub fn step_velocity(&mut self, dye: &impl DyeStorage) {
+        // Forces added since the last step may be out of range
+        self.sanitize();
+
+        // Buoyancy goes in before the state is saved so diffusion sees it
+        if self.heat_active() {
+            self.apply_buoyancy();
+        }
+        if self.dye_density != [0.0; 3] {
+            self.apply_dye_weight(dye);
+        }
+        if let Some(turbulence) = &mut self.turbulence {
+            turbulence.apply(&mut self.velocity_x, &mut self.velocity_y, self.width, self.dt);
+        }
+        if self.surface_tension > 0.0 {
+            self.apply_surface_tension(dye);
+        }
+        if self.velocity_damping > 0.0 {
+            let keep = (-self.velocity_damping * self.dt).exp();
+            self.velocity_x.iter_mut().chain(self.velocity_y.iter_mut()).for_each(|v| *v *= keep);
+        }
+        // Obstacles move first, so diffusion spreads their push from where
+        // they are this step
+        if !self.moving_obstacles.is_empty() {
+            for obstacle in &mut self.moving_obstacles {
+                obstacle.advance(self.dt);
+                if obstacle.density.is_some() {
+                    obstacle.keep_inside(self.width, self.height);
+                }
+            }
+            self.impose_obstacle_velocity();
+        }
+
         // Save current state
         self.velocity_x_prev.copy_from_slice(&self.velocity_x);
         self.velocity_y_prev.copy_from_slice(&self.velocity_y);
-        self.dye_r_prev.copy_from_slice(&self.dye_r);
-        self.dye_g_prev.copy_from_slice(&self.dye_g);
-        self.dye_b_prev.copy_from_slice(&self.dye_b);
 
         // Step 1: Diffuse velocity
-        self.diffuse_velocity();
+        self.timed(Stage::Diffuse, Self::diffuse_velocity);
 
         // Step 2: Project velocity (make divergence-free)
-        self.project_velocity();
+        self.timed(Stage::Project, Self::project_velocity);
+        self.block_velocity();
 
-        // Step 3: Advect velocity
-        self.advect_velocity();
+        // Step 3: Advect the diffused, projected field through itself;
+        // advecting the pre-step copy would discard viscosity
+        self.velocity_x_prev.copy_from_slice(&self.velocity_x);
+        self.velocity_y_prev.copy_from_slice(&self.velocity_y);
+        self.timed(Stage::Advect, Self::advect_velocity);
 
         // Step 4: Project velocity again
-        self.project_velocity();
+        self.timed(Stage::Project, Self::project_velocity);
+        // Floating obstacles take up the projected flow before imposing it back
+        for obstacle in &mut self.moving_obstacles {
+            obstacle.float(&self.velocity_x, &self.velocity_y, self.width, self.height);
+        }
+        self.block_velocity();
+    }
+
+    // Stop all flow inside obstacles, and move the fluid under moving ones
+    // with them
+    fn block_velocity(&mut self) {
+        if let Some(mask) = &self.obstacles {
+            let cells = self.velocity_x.iter_mut().zip(self.velocity_y.iter_mut()).zip(&mask.cells);
+            for ((vx, vy), _) in cells.filter(|(_, solid)| **solid) {
+                (*vx, *vy) = (0.0, 0.0);
+            }
+        }
+        self.impose_obstacle_velocity();
+    }
+
+    fn impose_obstacle_velocity(&mut self) {
+        for obstacle in &self.moving_obstacles {
+            for idx in obstacle.cells(self.width, self.height) {
+                let point = Vec2::new((idx % self.width) as f32, (idx / self.width) as f32);
+                let velocity = obstacle.surface_velocity(point);
+                self.velocity_x[idx] = velocity.x;
+                self.velocity_y[idx] = velocity.y;
+            }
+        }
+    }
+
+    /// Dye half of `step`: diffusion and advection through the current velocity.
+    pub fn step_dye(&mut self) {
+        self.dye_r_prev.copy_from_slice(&self.dye_r);
+        self.dye_g_prev.copy_from_slice(&self.dye_g);
+        self.dye_b_prev.copy_from_slice(&self.dye_b);
+        self.boundary_leak = BoundaryLeak::default();
 
         // Step 5: Diffuse dye
-        self.diffuse_dye();
+        self.timed(Stage::Diffuse, Self::diffuse_dye);
+
+        // Step 6: Advect the diffused dye; advecting the pre-step copy would
+        // discard diffusion
+        self.dye_r_prev.copy_from_slice(&self.dye_r);
+        self.dye_g_prev.copy_from_slice(&self.dye_g);
+        self.dye_b_prev.copy_from_slice(&self.dye_b);
+        self.timed(Stage::Advect, Self::advect_dye);
 
-        // Step 6: Advect dye
-        self.advect_dye();
+        self.dissipate_dye();
 
-        // Apply boundary conditions
-        self.set_boundaries();
+        if let Some(chemistry) = self.chemistry {
+            let (w, h, dt) = (self.width, self.height, self.dt);
+            chemistry.react([&mut self.dye_r[..], &mut self.dye_g[..], &mut self.dye_b[..]], w, h, dt);
+        }
+
+        if let Some(mask) = &self.obstacles {
+            for channel in [&mut self.dye_r, &mut self.dye_g, &mut self.dye_b] {
+                for (value, _) in channel.iter_mut().zip(&mask.cells).filter(|(_, solid)| **solid) {
+                    *value = 0.0;
+                }
+            }
+        }
+    }
+
+    /// Fade each dye channel by `exp(-(dye_dissipation + dye_decay[c]) * dt)`.
+    pub fn dissipate_dye(&mut self) {
+        let (dissipation, dt) = (self.dye_dissipation, self.dt);
+        for (channel, decay) in [&mut self.dye_r, &mut self.dye_g, &mut self.dye_b].into_iter().zip(self.dye_decay) {
+            let rate = dissipation + decay;
+            if rate <= 0.0 {
+                continue;
+            }
+            let keep = (-rate * dt).exp();
+            channel.par_iter_mut().for_each(|v| *v *= keep);
+        }
+    }
+
+    /// Fade rate of each dye channel, as `dissipate_dye` applies it.
+    pub fn dye_fade_rates(&self) -> [f32; 3] {
+        self.dye_decay.map(|decay| self.dye_dissipation + decay)
+    }
+
+    /// Diffuse, advect and fade each of `scalars` through the current
+    /// velocity, keeping each one's mass apart from what leaves through the
+    /// edges, as for dye.
+    pub fn step_scalars(&mut self) {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("scalars", fields = self.scalars.len()).entered();
+        let (w, h, dt, advect_dt) = (self.width, self.height, self.dt, self.advect_dt());
+        let aspect = self.cell_aspect;
+        let periodic = self.boundary == BoundaryMode::Periodic;
+        let ring = self.dye_ring();
+        self.scratch.resize(w * h, 0.0);
+        let (vx, vy, scratch) = (&self.velocity_x, &self.velocity_y, &mut self.scratch);
+
+        for field in &mut self.scalars {
+            let before: f32 = field.values.iter().sum();
+            field.prev.copy_from_slice(&field.values);
+            let a = dt * field.diffusion * (w * h) as f32;
+            if a > 0.0 {
+                let ay = a / (aspect * aspect);
+                for _ in 0..2 {
+                    for parity in 0..2 {
+                        scratch.copy_from_slice(&field.values);
+                        relax_color(&mut field.values, scratch, &field.prev, w, h, [a, ay], 1.0 + 2.0 * a + 2.0 * ay, parity);
+                    }
+                    fill_ring(&mut field.values, w, h, ring, ring);
+                }
+                field.prev.copy_from_slice(&field.values);
+            }
+
+            let prev = &field.prev;
+            rows_mut(&mut field.values[w..(h - 1) * w], w).enumerate().for_each(|(i, row)| {
+                if periodic {
+                    kernels::advect_row_periodic([row], [prev], vx, vy, i + 1, w, h, advect_dt);
+                } else {
+                    kernels::advect_row([row], [prev], vx, vy, i + 1, w, h, advect_dt);
+                }
+            });
+            fill_ring(&mut field.values, w, h, ring, ring);
+
+            let lost: f32 = if periodic {
+                0.0
+            } else {
+                boundary_outflow(vx, vy, w, h, advect_dt, |i| [prev[i], 0.0, 0.0]).iter().map(|side| side[0]).sum()
+            };
+            let after: f32 = field.values.iter().sum();
+            if after > 1e-10 {
+                let scale = (before - lost).max(0.0) / after;
+                field.values.iter_mut().for_each(|v| *v *= scale);
+            }
+
+            if field.dissipation > 0.0 {
+                let keep = (-field.dissipation * dt).exp();
+                field.values.iter_mut().for_each(|v| *v *= keep);
+            }
+        }
+    }
+
+    /// Carry heat with the flow and let it relax toward ambient. Skipped
+    /// entirely when nothing is hot or cold.
+    pub fn step_temperature(&mut self) {
+        if self.heat_active() {
+            self.timed(Stage::Advect, Self::advect_temperature);
+            self.cool_temperature();
+        }
     }
 
     pub fn diffuse_velocity(&mut self) {
         let a = self.dt * self.viscosity * (self.width * self.height) as f32;
+        if let Some(factors) = self.viscosity_factors() {
+            self.diffuse_velocity_varying(a, &factors);
+            return;
+        }
+        #[cfg(feature = "spectral")]
+        if let (Some(spectral), BoundaryMode::Periodic) = (&mut self.spectral, self.boundary) {
+            spectral.aspect = self.cell_aspect;
+            spectral.diffuse(&mut self.velocity_x, a);
+            spectral.diffuse(&mut self.velocity_y, a);
+            self.set_velocity_boundaries();
+            return;
+        }
+        let (w, h) = (self.width, self.height);
+        let (a, denominator) = self.diffusion_weights(a);
+        self.scratch.resize(w * h, 0.0);
 
         for _ in 0..4 {
-            for y in 1..self.height - 1 {
-                for x in 1..self.width - 1 {
-                    let idx = y * self.width + x;
-                    self.velocity_x[idx] = (self.velocity_x_prev[idx]
-                        + a * (self.velocity_x[idx - 1]
-                            + self.velocity_x[idx + 1]
-                            + self.velocity_x[idx - self.width]
-                            + self.velocity_x[idx + self.width]))
-                        / (1.0 + 4.0 * a);
-
-                    self.velocity_y[idx] = (self.velocity_y_prev[idx]
-                        + a * (self.velocity_y[idx - 1]
-                            + self.velocity_y[idx + 1]
-                            + self.velocity_y[idx - self.width]
-                            + self.velocity_y[idx + self.width]))
-                        / (1.0 + 4.0 * a);
-                }
+            for parity in 0..2 {
+                self.scratch.copy_from_slice(&self.velocity_x);
+                relax_color(&mut self.velocity_x, &self.scratch, &self.velocity_x_prev, w, h, a, denominator, parity);
+                self.scratch.copy_from_slice(&self.velocity_y);
+                relax_color(&mut self.velocity_y, &self.scratch, &self.velocity_y_prev, w, h, a, denominator, parity);
+            }
+            self.set_velocity_boundaries();
+        }
+    }
+
+    /// Move the painted viscosity under a dab toward `target` times the
+    /// fluid's own, by the brush's weight at each cell. Painting on an
+    /// empty field starts it at 1 everywhere.
+    pub fn paint_viscosity(&mut self, brush: &Brush, x: f32, y: f32, target: f32) {
+        let (w, h) = (self.width, self.height);
+        if self.viscosity_field.len() != w * h {
+            self.viscosity_field = vec![1.0; w * h];
+        }
+        let target = target.clamp(VISCOSITY_FACTOR_RANGE.0, VISCOSITY_FACTOR_RANGE.1);
+        for (px, py, weight) in brush.dab(x, y, w, h) {
+            let cell = &mut self.viscosity_field[py * w + px];
+            *cell += (target - *cell) * weight.min(1.0);
+        }
+    }
+
+    /// Factor on `viscosity` per cell, the shear model's times the painted
+    /// field's, or `None` while it is the same everywhere.
+    pub fn viscosity_factors(&self) -> Option<Vec<f32>> {
+        let (w, h) = (self.width, self.height);
+        let painted = (self.viscosity_field.len() == w * h).then_some(&self.viscosity_field);
+        let mut factors = match (self.shear_model, painted) {
+            (None, None) => return None,
+            (None, Some(field)) => return Some(field.clone()),
+            (Some(model), _) => {
+                let rates = rheology::shear_rates(&self.velocity_x, &self.velocity_y, w, h, self.cell_aspect);
+                rates.into_iter().map(|rate| model.factor(rate)).collect::<Vec<f32>>()
+            }
+        };
+        if let Some(field) = painted {
+            factors.iter_mut().zip(field).for_each(|(factor, painted)| *factor *= painted);
+        }
+        Some(factors)
+    }
+
+    // Velocity diffusion with each cell's rate scaled by `factors`
+    fn diffuse_velocity_varying(&mut self, a: f32, factors: &[f32]) {
+        let (w, h) = (self.width, self.height);
+        let (a, _) = self.diffusion_weights(a);
+        self.scratch.resize(w * h, 0.0);
+
+        for _ in 0..4 {
+            for parity in 0..2 {
+                self.scratch.copy_from_slice(&self.velocity_x);
+                relax_color_varying(&mut self.velocity_x, &self.scratch, &self.velocity_x_prev, factors, w, h, a, parity);
+                self.scratch.copy_from_slice(&self.velocity_y);
+                relax_color_varying(&mut self.velocity_y, &self.scratch, &self.velocity_y_prev, factors, w, h, a, parity);
             }
             self.set_velocity_boundaries();
         }
@@ -172,32 +1146,29 @@ impl InteractiveFluid {
 
         let a = self.dt * self.dye_diffusion * (self.width * self.height) as f32;
 
+        // The FFT solve keeps each channel's total, so needs no correction
+        #[cfg(feature = "spectral")]
+        if let (Some(spectral), BoundaryMode::Periodic) = (&mut self.spectral, self.boundary) {
+            spectral.aspect = self.cell_aspect;
+            for channel in [&mut self.dye_r, &mut self.dye_g, &mut self.dye_b] {
+                spectral.diffuse(channel, a);
+            }
+            self.set_dye_boundaries();
+            return;
+        }
+
+        let (w, h) = (self.width, self.height);
+        let (a, denominator) = self.diffusion_weights(a);
+        self.scratch.resize(w * h, 0.0);
+
         for _ in 0..2 {
-            for y in 1..self.height - 1 {
-                for x in 1..self.width - 1 {
-                    let idx = y * self.width + x;
-
-                    self.dye_r[idx] = (self.dye_r_prev[idx]
-                        + a * (self.dye_r[idx - 1]
-                            + self.dye_r[idx + 1]
-                            + self.dye_r[idx - self.width]
-                            + self.dye_r[idx + self.width]))
-                        / (1.0 + 4.0 * a);
-
-                    self.dye_g[idx] = (self.dye_g_prev[idx]
-                        + a * (self.dye_g[idx - 1]
-                            + self.dye_g[idx + 1]
-                            + self.dye_g[idx - self.width]
-                            + self.dye_g[idx + self.width]))
-                        / (1.0 + 4.0 * a);
-
-                    self.dye_b[idx] = (self.dye_b_prev[idx]
-                        + a * (self.dye_b[idx - 1]
-                            + self.dye_b[idx + 1]
-                            + self.dye_b[idx - self.width]
-                            + self.dye_b[idx + self.width]))
-                        / (1.0 + 4.0 * a);
-                }
+            for parity in 0..2 {
+                self.scratch.copy_from_slice(&self.dye_r);
+                relax_color(&mut self.dye_r, &self.scratch, &self.dye_r_prev, w, h, a, denominator, parity);
+                self.scratch.copy_from_slice(&self.dye_g);
+                relax_color(&mut self.dye_g, &self.scratch, &self.dye_g_prev, w, h, a, denominator, parity);
+                self.scratch.copy_from_slice(&self.dye_b);
+                relax_color(&mut self.dye_b, &self.scratch, &self.dye_b_prev, w, h, a, denominator, parity);
             }
             self.set_dye_boundaries();
         }
@@ -212,72 +1183,54 @@ impl InteractiveFluid {
             for val in self.dye_r.iter_mut() {
                 *val *= scale_r;
             }
+            self.boundary_leak.numerical[0] += total_r_before - total_r_after;
         }
         if total_g_after > 1e-10 {
             let scale_g = total_g_before / total_g_after;
             for val in self.dye_g.iter_mut() {
                 *val *= scale_g;
             }
+            self.boundary_leak.numerical[1] += total_g_before - total_g_after;
         }
         if total_b_after > 1e-10 {
             let scale_b = total_b_before / total_b_after;
             for val in self.dye_b.iter_mut() {
                 *val *= scale_b;
             }
+            self.boundary_leak.numerical[2] += total_b_before - total_b_after;
         }
     }
 
     pub fn advect_velocity(&mut self) {
-        for y in 1..self.height - 1 {
-            for x in 1..self.width - 1 {
-                let idx = y * self.width + x;
-
-                // Backtrace using previous velocity field
-                let src_x = x as f32 - self.dt * self.velocity_x_prev[idx];
-                let src_y = y as f32 - self.dt * self.velocity_y_prev[idx];
-
-                // Clamp to valid range
-                let src_x = src_x.max(0.5).min((self.width - 1) as f32 - 0.5);
-                let src_y = src_y.max(0.5).min((self.height - 1) as f32 - 0.5);
-
-                // Bilinear interpolation
-                let x0 = src_x.floor() as usize;
-                let x1 = x0 + 1;
-                let y0 = src_y.floor() as usize;
-                let y1 = y0 + 1;
-
-                let sx = src_x - x0 as f32;
-                let sy = src_y - y0 as f32;
-
-                let idx00 = y0 * self.width + x0;
-                let idx01 = y0 * self.width + x1;
-                let idx10 = y1 * self.width + x0;
-                let idx11 = y1 * self.width + x1;
-
-                // Advect velocity
-                self.velocity_x[idx] = (1.0 - sx) * (1.0 - sy) * self.velocity_x_prev[idx00]
-                    + sx * (1.0 - sy) * self.velocity_x_prev[idx01]
-                    + (1.0 - sx) * sy * self.velocity_x_prev[idx10]
-                    + sx * sy * self.velocity_x_prev[idx11];
-
-                self.velocity_y[idx] = (1.0 - sx) * (1.0 - sy) * self.velocity_y_prev[idx00]
-                    + sx * (1.0 - sy) * self.velocity_y_prev[idx01]
-                    + (1.0 - sx) * sy * self.velocity_y_prev[idx10]
-                    + sx * sy * self.velocity_y_prev[idx11];
+        let (w, h, dt) = (self.width, self.height, self.advect_dt());
+        let (vx_prev, vy_prev) = (&self.velocity_x_prev, &self.velocity_y_prev);
+        let vx_rows = rows_mut(&mut self.velocity_x[w..(h - 1) * w], w);
+        let vy_rows = rows_mut(&mut self.velocity_y[w..(h - 1) * w], w);
+
+        // Backtrace using previous velocity field
+        let periodic = self.boundary == BoundaryMode::Periodic;
+        vx_rows.zip(vy_rows).enumerate().for_each(|(i, (row_x, row_y))| {
+            if periodic {
+                kernels::advect_row_periodic([row_x, row_y], [vx_prev, vy_prev], vx_prev, vy_prev, i + 1, w, h, dt);
+            } else {
+                kernels::advect_row([row_x, row_y], [vx_prev, vy_prev], vx_prev, vy_prev, i + 1, w, h, dt);
             }
-        }
+        });
         self.set_velocity_boundaries();
     }
 
     pub fn advect_dye(&mut self) {
         // Calculate total dye mass before advection for conservation
+        // Sum fixed-size chunks in parallel, then combine them in index order,
+        // so the total is bitwise identical for any thread count
         #[cfg(not(target_arch = "wasm32"))]
         let (total_r_before, total_g_before, total_b_before) = {
-            self.dye_r_prev.par_iter()
-                .zip(&self.dye_g_prev)
-                .zip(&self.dye_b_prev)
-                .map(|((&r, &g), &b)| (r, g, b))
-                .reduce(|| (0.0, 0.0, 0.0), |(r1, g1, b1), (r2, g2, b2)| (r1 + r2, g1 + g2, b1 + b2))
+            let partials: Vec<(f32, f32, f32)> = self.dye_r_prev.par_chunks(SUM_CHUNK)
+                .zip(self.dye_g_prev.par_chunks(SUM_CHUNK))
+                .zip(self.dye_b_prev.par_chunks(SUM_CHUNK))
+                .map(|((r, g), b)| (r.iter().sum(), g.iter().sum(), b.iter().sum()))
+                .collect();
+            partials.iter().fold((0.0, 0.0, 0.0), |(r1, g1, b1), &(r2, g2, b2)| (r1 + r2, g1 + g2, b1 + b2))
         };
 
         #[cfg(target_arch = "wasm32")]
@@ -288,196 +1241,494 @@ impl InteractiveFluid {
             (r, g, b)
         };
 
-        // Advection (serial for WASM compatibility)
-        for y in 1..self.height - 1 {
-            for x in 1..self.width - 1 {
-                let idx = y * self.width + x;
-
-                // Backtrace using current velocity field
-                let src_x = x as f32 - self.dt * self.velocity_x[idx];
-                let src_y = y as f32 - self.dt * self.velocity_y[idx];
-
-                // Clamp to valid range
-                let src_x = src_x.max(0.5).min((self.width - 1) as f32 - 0.5);
-                let src_y = src_y.max(0.5).min((self.height - 1) as f32 - 0.5);
+        // Dye physically leaving the domain this step is not restored below
+        let outflow = self.measure_boundary_outflow();
+        let total = outflow.iter().fold([0.0f32; 3], |acc, side| {
+            [acc[0] + side[0], acc[1] + side[1], acc[2] + side[2]]
+        });
+        self.boundary_leak.outflow = outflow;
+        let total_r_before = total_r_before - total[0];
+        let total_g_before = total_g_before - total[1];
+        let total_b_before = total_b_before - total[2];
+
+        // Advection, row-parallel: each row only reads the previous dye
+        let (w, h, dt) = (self.width, self.height, self.advect_dt());
+        let (vx, vy) = (&self.velocity_x, &self.velocity_y);
+        let (r_prev, g_prev, b_prev) = (&self.dye_r_prev, &self.dye_g_prev, &self.dye_b_prev);
+        let r_rows = rows_mut(&mut self.dye_r[w..(h - 1) * w], w);
+        let g_rows = rows_mut(&mut self.dye_g[w..(h - 1) * w], w);
+        let b_rows = rows_mut(&mut self.dye_b[w..(h - 1) * w], w);
+
+        // Backtrace using current velocity field
+        let periodic = self.boundary == BoundaryMode::Periodic;
+        r_rows.zip(g_rows).zip(b_rows).enumerate().for_each(|(i, ((row_r, row_g), row_b))| {
+            let sources: [&[f32]; 3] = [r_prev, g_prev, b_prev];
+            let rows = [row_r, row_g, row_b];
+            if periodic {
+                kernels::advect_row_periodic(rows, sources, vx, vy, i + 1, w, h, dt);
+            } else {
+                kernels::advect_row(rows, sources, vx, vy, i + 1, w, h, dt);
+            }
+        });
 
-                // Bilinear interpolation
-                let x0 = src_x.floor() as usize;
-                let x1 = x0 + 1;
-                let y0 = src_y.floor() as usize;
-                let y1 = y0 + 1;
+        self.set_dye_boundaries();
 
-                let sx = src_x - x0 as f32;
-                let sy = src_y - y0 as f32;
+        // Apply mass conservation correction
+        let total_r_after: f32 = self.dye_r.iter().sum();
+        let total_g_after: f32 = self.dye_g.iter().sum();
+        let total_b_after: f32 = self.dye_b.iter().sum();
 
-                let idx00 = y0 * self.width + x0;
-                let idx01 = y0 * self.width + x1;
-                let idx10 = y1 * self.width + x0;
-                let idx11 = y1 * self.width + x1;
+        // Rescale each channel holding dye to conserve mass; requiring all
+        // three would skip the correction whenever one channel is empty
+        let before = [total_r_before, total_g_before, total_b_before];
+        let after = [total_r_after, total_g_after, total_b_after];
+        for (c, channel) in [&mut self.dye_r, &mut self.dye_g, &mut self.dye_b].into_iter().enumerate() {
+            if after[c] > 1e-10 {
+                let scale = before[c] / after[c];
+                for value in channel.iter_mut() {
+                    *value *= scale;
+                }
+                self.boundary_leak.numerical[c] += before[c] - after[c];
+            }
+        }
+    }
 
-                // Advect dye with bilinear interpolation
-                self.dye_r[idx] = (1.0 - sx) * (1.0 - sy) * self.dye_r_prev[idx00]
-                    + sx * (1.0 - sy) * self.dye_r_prev[idx01]
-                    + (1.0 - sx) * sy * self.dye_r_prev[idx10]
-                    + sx * sy * self.dye_r_prev[idx11];
+    /// Dye carried out through each side during one step, using the outward
+    /// normal velocity on the boundary ring and the dye of the adjacent
+    /// interior cell. Closed walls hold zero velocity, so this is zero until
+    /// a boundary lets fluid through.
+    fn measure_boundary_outflow(&self) -> [[f32; 3]; 4] {
+        // Dye leaving one side comes straight back in the other
+        if self.boundary == BoundaryMode::Periodic {
+            return [[0.0; 3]; 4];
+        }
+        let (r, g, b) = (&self.dye_r_prev, &self.dye_g_prev, &self.dye_b_prev);
+        boundary_outflow(&self.velocity_x, &self.velocity_y, self.width, self.height, self.advect_dt(), |i| [r[i], g[i], b[i]])
+    }
 
-                self.dye_g[idx] = (1.0 - sx) * (1.0 - sy) * self.dye_g_prev[idx00]
-                    + sx * (1.0 - sy) * self.dye_g_prev[idx01]
-                    + (1.0 - sx) * sy * self.dye_g_prev[idx10]
-                    + sx * sy * self.dye_g_prev[idx11];
+    pub fn apply_buoyancy(&mut self) {
+        // y grows downward, so hot fluid gets a negative vertical push
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let idx = y * self.width + x;
+                let excess = self.temperature[idx] - self.ambient_temperature;
+                self.velocity_y[idx] -= self.dt * self.buoyancy * excess;
+            }
+        }
+    }
 
-                self.dye_b[idx] = (1.0 - sx) * (1.0 - sy) * self.dye_b_prev[idx00]
-                    + sx * (1.0 - sy) * self.dye_b_prev[idx01]
-                    + (1.0 - sx) * sy * self.dye_b_prev[idx10]
-                    + sx * sy * self.dye_b_prev[idx11];
+    /// Pull each cell down by the weight of its dye, `dye_density` per
+    /// channel; y grows downward, so heavy dye gets a positive push.
+    pub fn apply_dye_weight(&mut self, dye: &impl DyeStorage) {
+        let [wr, wg, wb] = self.dye_density;
+        // Taken out so the dye store can read the fluid while it's written
+        let mut velocity_y = std::mem::take(&mut self.velocity_y);
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let idx = y * self.width + x;
+                let [r, g, b] = dye.dye(self, idx);
+                velocity_y[idx] += self.dt * (wr * r + wg * g + wb * b);
             }
         }
+        self.velocity_y = velocity_y;
+    }
 
-        self.set_dye_boundaries();
+    /// Push the fluid along the curvature of the dye's edge, like surface
+    /// tension on a liquid: with `c` the dye amount saturating toward 1 and
+    /// `n` the unit vector along its gradient, each cell gains
+    /// `surface_tension * -div(n) * grad(c)`. Bulges and corners are pulled
+    /// in, so blobs round themselves up; the projection that follows turns
+    /// the pull into flow.
+    pub fn apply_surface_tension(&mut self, dye: &impl DyeStorage) {
+        let (w, h) = (self.width, self.height);
+        if w < 3 || h < 3 {
+            return;
+        }
+        // Faint and thick dye both get an edge, without the thick dye's
+        // inside counting as one
+        let color: Vec<f32> = (0..w * h)
+            .map(|i| 1.0 - (-dye.dye(self, i).iter().sum::<f32>().max(0.0)).exp())
+            .collect();
+        let mut gradient = vec![Vec2::ZERO; w * h];
+        let mut normal = vec![Vec2::ZERO; w * h];
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                let idx = y * w + x;
+                let g = 0.5 * Vec2::new(color[idx + 1] - color[idx - 1], color[idx + w] - color[idx - w]);
+                gradient[idx] = g;
+                // Too flat to have a direction; noise would give it a random one
+                if g.length() > 1e-4 {
+                    normal[idx] = g.normalize();
+                }
+            }
+        }
+        let strength = self.surface_tension * self.dt;
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                let idx = y * w + x;
+                let curvature = -0.5 * (normal[idx + 1].x - normal[idx - 1].x + normal[idx + w].y - normal[idx - w].y);
+                let push = strength * curvature * gradient[idx];
+                self.velocity_x[idx] += push.x;
+                self.velocity_y[idx] += push.y;
+            }
+        }
+    }
 
-        // Apply mass conservation correction
-        let total_r_after: f32 = self.dye_r.iter().sum();
-        let total_g_after: f32 = self.dye_g.iter().sum();
-        let total_b_after: f32 = self.dye_b.iter().sum();
+    pub fn advect_temperature(&mut self) {
+        self.temperature_prev.copy_from_slice(&self.temperature);
+
+        let (w, h, dt) = (self.width, self.height, self.advect_dt());
+        let (vx, vy, prev) = (&self.velocity_x, &self.velocity_y, &self.temperature_prev);
+        let periodic = self.boundary == BoundaryMode::Periodic;
+        rows_mut(&mut self.temperature[w..(h - 1) * w], w).enumerate().for_each(|(i, row)| {
+            if periodic {
+                kernels::advect_row_periodic([row], [prev], vx, vy, i + 1, w, h, dt);
+            } else {
+                kernels::advect_row([row], [prev], vx, vy, i + 1, w, h, dt);
+            }
+        });
 
-        // Rescale to conserve mass
-        if total_r_after > 1e-10 && total_g_after > 1e-10 && total_b_after > 1e-10 {
-            let scale_r = total_r_before / total_r_after;
-            let scale_g = total_g_before / total_g_after;
-            let scale_b = total_b_before / total_b_after;
+        self.set_temperature_boundaries();
+    }
 
-            for i in 0..self.dye_r.len() {
-                self.dye_r[i] *= scale_r;
-                self.dye_g[i] *= scale_g;
-                self.dye_b[i] *= scale_b;
+    pub fn cool_temperature(&mut self) {
+        let keep = (1.0 - self.cooling * self.dt).clamp(0.0, 1.0);
+        let ambient = self.ambient_temperature;
+        for t in self.temperature.iter_mut() {
+            *t = ambient + (*t - ambient) * keep;
+            // Snap negligible excess back to ambient so the heat passes can switch off
+            if (*t - ambient).abs() < 1e-6 {
+                *t = ambient;
             }
         }
     }
 
     pub fn project_velocity(&mut self) {
-        let h = 1.0 / self.width as f32;
+        let (w, h, aspect) = (self.width, self.height, self.cell_aspect);
+        // Cell width; a cell is `aspect` times as tall
+        let cell = 1.0 / w as f32;
 
         // Calculate divergence
-        for y in 1..self.height - 1 {
-            for x in 1..self.width - 1 {
-                let idx = y * self.width + x;
-                self.divergence[idx] = -0.5
-                    * h
-                    * (self.velocity_x[idx + 1] - self.velocity_x[idx - 1]
-                        + self.velocity_y[idx + self.width]
-                        - self.velocity_y[idx - self.width]);
-                self.pressure[idx] = 0.0;
-            }
+        {
+            let (vx, vy) = (&self.velocity_x, &self.velocity_y);
+            let div_rows = rows_mut(&mut self.divergence[w..(h - 1) * w], w);
+            let p_rows = rows_mut(&mut self.pressure[w..(h - 1) * w], w);
+            div_rows.zip(p_rows).enumerate().for_each(|(i, (row_div, row_p))| {
+                let y = i + 1;
+                for x in 1..w - 1 {
+                    let idx = y * w + x;
+                    row_div[x] = -0.5 * cell * (vx[idx + 1] - vx[idx - 1] + (vy[idx + w] - vy[idx - w]) / aspect);
+                    row_p[x] = 0.0;
+                }
+            });
         }
 
         self.set_pressure_boundaries();
 
-        // Solve for pressure with adaptive convergence
-        let convergence_threshold = 0.001;
-        let max_iterations = 20;
+        #[cfg(feature = "spectral")]
+        if let (Some(spectral), BoundaryMode::Periodic) = (&mut self.spectral, self.boundary) {
+            spectral.aspect = aspect;
+            spectral.project(&mut self.velocity_x, &mut self.velocity_y, &mut self.pressure);
+            self.set_pressure_boundaries();
+            self.set_velocity_boundaries();
+            self.pressure_iterations_used = 0;
+            return;
+        }
 
-        for iter in 0..max_iterations {
-            let mut max_change = 0.0f32;
+        if let Some(tolerance) = self.divergence_tolerance {
+            self.pressure_iterations_used = self.project_to_tolerance(tolerance);
+            return;
+        }
+        self.pressure_iterations_used = self.relax_pressure(self.pressure_iterations, true);
+        self.subtract_pressure_gradient();
+    }
 
-            for y in 1..self.height - 1 {
-                for x in 1..self.width - 1 {
-                    let idx = y * self.width + x;
-                    let old_pressure = self.pressure[idx];
-                    self.pressure[idx] = (self.divergence[idx]
-                        + self.pressure[idx - 1]
-                        + self.pressure[idx + 1]
-                        + self.pressure[idx - self.width]
-                        + self.pressure[idx + self.width])
-                        / 4.0;
-
-                    // Track convergence
-                    let change = (self.pressure[idx] - old_pressure).abs();
-                    if change > max_change {
-                        max_change = change;
-                    }
+    /// Divergence of the current velocity, as left by the last projection
+    /// once `step` has run. Obstacle cells are skipped.
+    pub fn divergence_stats(&self) -> DivergenceStats {
+        let (w, h, aspect) = (self.width, self.height, self.cell_aspect);
+        let (vx, vy) = (&self.velocity_x, &self.velocity_y);
+        let obstacles = self.obstacles.as_ref().filter(|mask| mask.cells.len() == w * h);
+        let (mut max, mut sum, mut cells) = (0.0f32, 0.0f64, 0usize);
+        for y in 1..h.saturating_sub(1) {
+            for x in 1..w - 1 {
+                let idx = y * w + x;
+                if obstacles.is_some_and(|mask| mask.cells[idx]) {
+                    continue;
                 }
+                let div = (0.5 * (vx[idx + 1] - vx[idx - 1] + (vy[idx + w] - vy[idx - w]) / aspect)).abs();
+                max = max.max(div);
+                sum += div as f64;
+                cells += 1;
+            }
+        }
+        let mean = if cells > 0 { (sum / cells as f64) as f32 } else { 0.0 };
+        DivergenceStats { max, mean }
+    }
+
+    // Sweep the pressure up to `iterations` times, red-black ordered so rows
+    // relax in parallel, stopping early once it settles if `early_exit`.
+    // Returns the sweeps run
+    fn relax_pressure(&mut self, iterations: usize, early_exit: bool) -> usize {
+        let (w, h) = (self.width, self.height);
+        let convergence_threshold = 0.001;
+        let ay = 1.0 / (self.cell_aspect * self.cell_aspect);
+        self.scratch.resize(w * h, 0.0);
+
+        for iter in 0..iterations {
+            let mut max_change = 0.0f32;
+            for parity in 0..2 {
+                self.scratch.copy_from_slice(&self.pressure);
+                let change = relax_color(&mut self.pressure, &self.scratch, &self.divergence, w, h, [1.0, ay], 2.0 + 2.0 * ay, parity);
+                max_change = max_change.max(change);
             }
             self.set_pressure_boundaries();
 
             // Early exit if converged
-            if iter > 5 && max_change < convergence_threshold {
-                break;
+            if early_exit && iter > 5 && max_change < convergence_threshold {
+                return iter + 1;
             }
         }
+        iterations
+    }
 
-        // Subtract pressure gradient
-        for y in 1..self.height - 1 {
-            for x in 1..self.width - 1 {
-                let idx = y * self.width + x;
-                self.velocity_x[idx] -= 0.5 * (self.pressure[idx + 1] - self.pressure[idx - 1]) / h;
-                self.velocity_y[idx] -=
-                    0.5 * (self.pressure[idx + self.width] - self.pressure[idx - self.width]) / h;
+    // Sweep `pressure_iterations` at a time, checking the divergence the
+    // pressure would leave, until it is under `tolerance`, the sweeps stop
+    // helping or the limit is reached. Returns the sweeps run
+    fn project_to_tolerance(&mut self, tolerance: f32) -> usize {
+        // Velocity before the correction, put back before each retry
+        let (vx, vy) = (self.velocity_x.clone(), self.velocity_y.clone());
+        let batch = self.pressure_iterations.max(1);
+        let mut used = 0;
+        let mut previous = f32::INFINITY;
+        loop {
+            used += self.relax_pressure(batch.min(ADAPTIVE_PRESSURE_LIMIT - used), false);
+            self.subtract_pressure_gradient();
+            let divergence = self.divergence_stats().max;
+            // Centered differences leave a little divergence no pressure
+            // removes, so a tolerance below it would only burn the limit
+            if divergence <= tolerance || used >= ADAPTIVE_PRESSURE_LIMIT || divergence > previous * 0.99 {
+                return used;
             }
+            previous = divergence;
+            self.velocity_x.copy_from_slice(&vx);
+            self.velocity_y.copy_from_slice(&vy);
         }
-
-        self.set_velocity_boundaries();
     }
 
-    fn set_boundaries(&mut self) {
+    fn subtract_pressure_gradient(&mut self) {
+        let (w, h, aspect) = (self.width, self.height, self.cell_aspect);
+        let cell = 1.0 / w as f32;
+        {
+            let pressure = &self.pressure;
+            let vx_rows = rows_mut(&mut self.velocity_x[w..(h - 1) * w], w);
+            let vy_rows = rows_mut(&mut self.velocity_y[w..(h - 1) * w], w);
+            vx_rows.zip(vy_rows).enumerate().for_each(|(i, (row_x, row_y))| {
+                let y = i + 1;
+                for x in 1..w - 1 {
+                    let idx = y * w + x;
+                    row_x[x] -= 0.5 * (pressure[idx + 1] - pressure[idx - 1]) / cell;
+                    row_y[x] -= 0.5 * (pressure[idx + w] - pressure[idx - w]) / (cell * aspect);
+                }
+            });
+        }
+
         self.set_velocity_boundaries();
-        self.set_dye_boundaries();
     }
 
     fn set_velocity_boundaries(&mut self) {
-        for x in 0..self.width {
-            self.velocity_x[x] = 0.0;
-            self.velocity_y[x] = 0.0;
-            self.velocity_x[(self.height - 1) * self.width + x] = 0.0;
-            self.velocity_y[(self.height - 1) * self.width + x] = 0.0;
-        }
+        let (w, h) = (self.width, self.height);
+        // Rings for (velocity_x, velocity_y), each as (top/bottom rows, left/right columns)
+        let (x_ring, y_ring) = match self.boundary {
+            BoundaryMode::NoSlip | BoundaryMode::Absorbing => ((Ring::Zero, Ring::Zero), (Ring::Zero, Ring::Zero)),
+            // Only the component into each wall is removed
+            BoundaryMode::FreeSlip => ((Ring::Copy, Ring::Zero), (Ring::Zero, Ring::Copy)),
+            BoundaryMode::Periodic => ((Ring::Wrap, Ring::Wrap), (Ring::Wrap, Ring::Wrap)),
+            BoundaryMode::Open => ((Ring::Copy, Ring::Copy), (Ring::Copy, Ring::Copy)),
+        };
+        fill_ring(&mut self.velocity_x, w, h, x_ring.0, x_ring.1);
+        fill_ring(&mut self.velocity_y, w, h, y_ring.0, y_ring.1);
+    }
+
+    // Edge copies of the interior, or its far side when wrapping
+    fn scalar_ring(&self) -> Ring {
+        if self.boundary == BoundaryMode::Periodic { Ring::Wrap } else { Ring::Copy }
+    }
 
-        for y in 0..self.height {
-            self.velocity_x[y * self.width] = 0.0;
-            self.velocity_y[y * self.width] = 0.0;
-            self.velocity_x[y * self.width + self.width - 1] = 0.0;
-            self.velocity_y[y * self.width + self.width - 1] = 0.0;
+    // Open edges hold clean fluid, so dye carried onto them is gone
+    fn dye_ring(&self) -> Ring {
+        match self.boundary {
+            BoundaryMode::Open => Ring::Zero,
+            _ => self.scalar_ring(),
         }
     }
 
     fn set_dye_boundaries(&mut self) {
-        for x in 0..self.width {
-            self.dye_r[x] = self.dye_r[self.width + x];
-            self.dye_g[x] = self.dye_g[self.width + x];
-            self.dye_b[x] = self.dye_b[self.width + x];
-
-            self.dye_r[(self.height - 1) * self.width + x] =
-                self.dye_r[(self.height - 2) * self.width + x];
-            self.dye_g[(self.height - 1) * self.width + x] =
-                self.dye_g[(self.height - 2) * self.width + x];
-            self.dye_b[(self.height - 1) * self.width + x] =
-                self.dye_b[(self.height - 2) * self.width + x];
+        let (w, h, ring) = (self.width, self.height, self.dye_ring());
+        for channel in [&mut self.dye_r, &mut self.dye_g, &mut self.dye_b] {
+            fill_ring(channel, w, h, ring, ring);
         }
+    }
 
-        for y in 0..self.height {
-            self.dye_r[y * self.width] = self.dye_r[y * self.width + 1];
-            self.dye_g[y * self.width] = self.dye_g[y * self.width + 1];
-            self.dye_b[y * self.width] = self.dye_b[y * self.width + 1];
-
-            self.dye_r[y * self.width + self.width - 1] =
-                self.dye_r[y * self.width + self.width - 2];
-            self.dye_g[y * self.width + self.width - 1] =
-                self.dye_g[y * self.width + self.width - 2];
-            self.dye_b[y * self.width + self.width - 1] =
-                self.dye_b[y * self.width + self.width - 2];
-        }
+    fn set_temperature_boundaries(&mut self) {
+        let (w, h, ring) = (self.width, self.height, self.scalar_ring());
+        fill_ring(&mut self.temperature, w, h, ring, ring);
     }
 
     fn set_pressure_boundaries(&mut self) {
-        for x in 0..self.width {
-            self.pressure[x] = self.pressure[self.width + x];
-            self.pressure[(self.height - 1) * self.width + x] =
-                self.pressure[(self.height - 2) * self.width + x];
-        }
+        let (w, h) = (self.width, self.height);
+        // Open edges sit at ambient pressure so flow can leave through them
+        let ring = match self.boundary {
+            BoundaryMode::Open => Ring::Zero,
+            _ => self.scalar_ring(),
+        };
+        fill_ring(&mut self.pressure, w, h, ring, ring);
+    }
+}
 
-        for y in 0..self.height {
-            self.pressure[y * self.width] = self.pressure[y * self.width + 1];
-            self.pressure[y * self.width + self.width - 1] =
-                self.pressure[y * self.width + self.width - 2];
+/// Outflow per side of the dye `dye(index)` returns, as measured by
+/// `InteractiveFluid` for its `boundary_leak`. `dt` is per axis, as for
+/// advection.
+pub(crate) fn boundary_outflow(
+    velocity_x: &[f32],
+    velocity_y: &[f32],
+    w: usize,
+    h: usize,
+    dt: [f32; 2],
+    dye: impl Fn(usize) -> [f32; 3],
+) -> [[f32; 3]; 4] {
+    let mut outflow = [[0.0f32; 3]; 4];
+    let mut add = |side: usize, interior: usize, normal_velocity: f32, dt: f32| {
+        if normal_velocity > 0.0 {
+            let amount = dt * normal_velocity;
+            let dye = dye(interior);
+            for c in 0..3 {
+                outflow[side][c] += amount * dye[c];
+            }
         }
+    };
+
+    for y in 1..h - 1 {
+        let left = y * w;
+        let right = y * w + w - 1;
+        add(BoundaryLeak::LEFT, left + 1, -velocity_x[left], dt[0]);
+        add(BoundaryLeak::RIGHT, right - 1, velocity_x[right], dt[0]);
+    }
+    for x in 1..w - 1 {
+        let top = x;
+        let bottom = (h - 1) * w + x;
+        add(BoundaryLeak::TOP, top + w, -velocity_y[top], dt[1]);
+        add(BoundaryLeak::BOTTOM, bottom - w, velocity_y[bottom], dt[1]);
     }
+
+    outflow
+}
+
+/// How the boundary ring of a field is filled from the interior.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ring {
+    Zero,
+    /// The adjacent interior cell
+    Copy,
+    /// The interior cell on the opposite side
+    Wrap,
+}
+
+// Bilinear sample of a row-major field at a fractional cell position
+fn sample_grid(field: &[f32], width: usize, height: usize, x: f32, y: f32) -> f32 {
+    // Clamp to the same range the advection backtrace uses
+    let x = x.max(0.5).min((width - 1) as f32 - 0.5);
+    let y = y.max(0.5).min((height - 1) as f32 - 0.5);
+
+    let x0 = x.floor() as usize;
+    let y0 = y.floor() as usize;
+    let x1 = x0 + 1;
+    let y1 = y0 + 1;
+
+    let sx = x - x0 as f32;
+    let sy = y - y0 as f32;
+
+    (1.0 - sx) * (1.0 - sy) * field[y0 * width + x0]
+        + sx * (1.0 - sy) * field[y0 * width + x1]
+        + (1.0 - sx) * sy * field[y1 * width + x0]
+        + sx * sy * field[y1 * width + x1]
+}
+
+// Fill the top and bottom rows, then the left and right columns, so corners
+// take the column rule applied to the already filled rows
+fn fill_ring(field: &mut [f32], width: usize, height: usize, rows: Ring, columns: Ring) {
+    let (w, h) = (width, height);
+    for x in 0..w {
+        let (top, bottom) = match rows {
+            Ring::Zero => (0.0, 0.0),
+            Ring::Copy => (field[w + x], field[(h - 2) * w + x]),
+            Ring::Wrap => (field[(h - 2) * w + x], field[w + x]),
+        };
+        field[x] = top;
+        field[(h - 1) * w + x] = bottom;
+    }
+    for y in 0..h {
+        let row = y * w;
+        let (left, right) = match columns {
+            Ring::Zero => (0.0, 0.0),
+            Ring::Copy => (field[row + 1], field[row + w - 2]),
+            Ring::Wrap => (field[row + w - 2], field[row + 1]),
+        };
+        field[row] = left;
+        field[row + w - 1] = right;
+    }
+}
+
+// Interior rows of a field for row-parallel loops. Serial on wasm, which has
+// no threads.
+#[cfg(not(target_arch = "wasm32"))]
+fn rows_mut(field: &mut [f32], width: usize) -> rayon::slice::ChunksMut<'_, f32> {
+    field.par_chunks_mut(width)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn rows_mut(field: &mut [f32], width: usize) -> std::slice::ChunksMut<'_, f32> {
+    field.chunks_mut(width)
+}
+
+/// One red-black Gauss-Seidel half sweep of
+/// `(source + a[0] * (left + right) + a[1] * (up + down)) / denominator` over the interior cells whose
+/// `x + y` has the given parity. Neighbours all have the other parity, so
+/// reading them from `snapshot` gives the same result as an in-place sweep
+/// while letting rows update independently. Returns the largest change.
+#[allow(clippy::too_many_arguments)]
+fn relax_color(
+    field: &mut [f32],
+    snapshot: &[f32],
+    source: &[f32],
+    width: usize,
+    height: usize,
+    a: [f32; 2],
+    denominator: f32,
+    parity: usize,
+) -> f32 {
+    let changes: Vec<f32> = rows_mut(&mut field[width..(height - 1) * width], width)
+        .enumerate()
+        .map(|(i, row)| kernels::relax_row(row, snapshot, source, i + 1, width, a, denominator, parity))
+        .collect();
+    changes.into_iter().fold(0.0, f32::max)
+}
+
+/// `relax_color` with the weights scaled per cell by `factor`.
+#[allow(clippy::too_many_arguments)]
+fn relax_color_varying(
+    field: &mut [f32],
+    snapshot: &[f32],
+    source: &[f32],
+    factor: &[f32],
+    width: usize,
+    height: usize,
+    a: [f32; 2],
+    parity: usize,
+) {
+    rows_mut(&mut field[width..(height - 1) * width], width)
+        .enumerate()
+        .for_each(|(i, row)| {
+            kernels::relax_row_varying(row, snapshot, source, factor, i + 1, width, a, parity);
+        });
 }
@@ -0,0 +1,470 @@
+//! Free-surface liquid in a closed tank. A level set `phi` (signed distance
+//! in cells, negative inside the liquid) tracks where the liquid is; gravity
+//! pulls it down and pressure is solved only inside it, with zero pressure in
+//! the air above, so it sloshes instead of filling the box like dye.
+
+use crate::export::FluidData;
+use crate::kernels;
+use crate::{ConfigurableSimulation, FluidSimulation, Param};
+use glam::Vec2;
+
+// Air cells next to the liquid that receive extrapolated velocity, so the
+// surface is advected by the flow beneath it
+const EXTRAPOLATION_LAYERS: usize = 4;
+// Distance assigned to cells far from the surface before redistancing
+const FAR: f32 = 1.0e6;
+
+#[derive(Debug, Clone)]
+pub struct LevelSetFluid {
+    pub width: usize,
+    pub height: usize,
+    pub velocity_x: Vec<f32>,
+    pub velocity_y: Vec<f32>,
+    /// Signed distance to the surface in cells, negative inside the liquid
+    pub phi: Vec<f32>,
+    /// How much of each cell is liquid, in [0, 1], from `phi`
+    pub fraction: Vec<f32>,
+    pub pressure: Vec<f32>,
+    pub dt: f32,
+    /// Downward acceleration in cells per time unit squared
+    pub gravity: f32,
+    pub pressure_iterations: usize,
+    /// Liquid volume in cells that each step restores, since advecting a
+    /// level set slowly loses or gains liquid; `None` lets it drift
+    pub target_volume: Option<f32>,
+    scratch: Vec<f32>,
+}
+
+impl LevelSetFluid {
+    /// An empty tank; add liquid with `fill_below`, `add_liquid_box` or
+    /// `add_liquid_disc`.
+    pub fn new(width: usize, height: usize) -> Self {
+        let size = width * height;
+        Self {
+            width,
+            height,
+            velocity_x: vec![0.0; size],
+            velocity_y: vec![0.0; size],
+            phi: vec![FAR; size],
+            fraction: vec![0.0; size],
+            pressure: vec![0.0; size],
+            dt: 0.1,
+            gravity: 20.0,
+            pressure_iterations: 40,
+            target_volume: Some(0.0),
+            scratch: vec![0.0; size],
+        }
+    }
+
+    /// Liquid up to `level` of the tank's inner height, 0 empty to 1 full.
+    pub fn fill_below(&mut self, level: f32) {
+        let surface = (self.height - 1) as f32 - level.clamp(0.0, 1.0) * (self.height - 2) as f32;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let idx = y * self.width + x;
+                self.phi[idx] = self.phi[idx].min(surface - y as f32 - 0.5);
+            }
+        }
+        self.liquid_changed();
+    }
+
+    /// Add a rectangle of liquid spanning cells `min..max`.
+    pub fn add_liquid_box(&mut self, min: (usize, usize), max: (usize, usize)) {
+        let (x0, y0) = (min.0 as f32 - 0.5, min.1 as f32 - 0.5);
+        let (x1, y1) = (max.0 as f32 - 0.5, max.1 as f32 - 0.5);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (px, py) = (x as f32, y as f32);
+                // Signed distance to an axis-aligned box
+                let dx = (x0 - px).max(px - x1);
+                let dy = (y0 - py).max(py - y1);
+                let outside = Vec2::new(dx.max(0.0), dy.max(0.0)).length();
+                let distance = outside + dx.max(dy).min(0.0);
+                let idx = y * self.width + x;
+                self.phi[idx] = self.phi[idx].min(distance);
+            }
+        }
+        self.liquid_changed();
+    }
+
+    /// Add a disc of liquid, e.g. a drop to splash into the tank.
+    pub fn add_liquid_disc(&mut self, x: f32, y: f32, radius: f32) {
+        for cy in 0..self.height {
+            for cx in 0..self.width {
+                let distance = Vec2::new(cx as f32 - x, cy as f32 - y).length() - radius;
+                let idx = cy * self.width + cx;
+                self.phi[idx] = self.phi[idx].min(distance);
+            }
+        }
+        self.liquid_changed();
+    }
+
+    pub fn is_liquid(&self, x: usize, y: usize) -> bool {
+        self.phi[y * self.width + x] < 0.0
+    }
+
+    /// Liquid in cells, counting partly filled cells at the surface.
+    pub fn volume(&self) -> f32 {
+        self.interior().map(|idx| liquid_fraction(self.phi[idx])).sum()
+    }
+
+    /// Highest liquid cell in each column (smallest y), or `height` where a
+    /// column is dry. The surface is where the tank has sloshed to.
+    pub fn surface_heights(&self) -> Vec<usize> {
+        (0..self.width)
+            .map(|x| (1..self.height - 1).find(|&y| self.is_liquid(x, y)).unwrap_or(self.height))
+            .collect()
+    }
+
+    pub fn step(&mut self) {
+        self.apply_gravity();
+        self.extrapolate_velocity();
+        self.advect_velocity();
+        self.set_velocity_boundaries();
+        self.project();
+        self.extrapolate_velocity();
+        self.set_velocity_boundaries();
+        self.advect_phi();
+        self.redistance();
+        self.conserve_volume();
+        self.update_fraction();
+    }
+
+    fn interior(&self) -> impl Iterator<Item = usize> + '_ {
+        let w = self.width;
+        (1..self.height - 1).flat_map(move |y| (1..w - 1).map(move |x| y * w + x))
+    }
+
+    // Redistance after an edit so the new surface is smooth, and keep the
+    // new volume from here on
+    fn liquid_changed(&mut self) {
+        self.redistance();
+        if self.target_volume.is_some() {
+            self.target_volume = Some(self.volume());
+        }
+        self.update_fraction();
+    }
+
+    fn apply_gravity(&mut self) {
+        // The band just outside the surface too, so a falling surface keeps up
+        let pull = self.dt * self.gravity;
+        for (vy, &phi) in self.velocity_y.iter_mut().zip(&self.phi) {
+            if phi < 1.0 {
+                *vy += pull;
+            }
+        }
+    }
+
+    // Carry liquid velocity out into the air, one layer of cells at a time
+    fn extrapolate_velocity(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let mut known: Vec<bool> = self.phi.iter().map(|&p| p < 0.0).collect();
+        for _ in 0..EXTRAPOLATION_LAYERS {
+            let mut next = known.clone();
+            for y in 1..h - 1 {
+                for x in 1..w - 1 {
+                    let idx = y * w + x;
+                    if known[idx] {
+                        continue;
+                    }
+                    let (mut sum, mut count) = (Vec2::ZERO, 0.0);
+                    for n in [idx - 1, idx + 1, idx - w, idx + w] {
+                        if known[n] {
+                            sum += Vec2::new(self.velocity_x[n], self.velocity_y[n]);
+                            count += 1.0;
+                        }
+                    }
+                    if count > 0.0 {
+                        self.velocity_x[idx] = sum.x / count;
+                        self.velocity_y[idx] = sum.y / count;
+                        next[idx] = true;
+                    }
+                }
+            }
+            known = next;
+        }
+        // Air beyond the band stays still
+        for (idx, &k) in known.iter().enumerate() {
+            if !k {
+                self.velocity_x[idx] = 0.0;
+                self.velocity_y[idx] = 0.0;
+            }
+        }
+    }
+
+    fn advect_velocity(&mut self) {
+        let (w, h, dt) = (self.width, self.height, self.dt);
+        let (vx_prev, vy_prev) = (self.velocity_x.clone(), self.velocity_y.clone());
+        let rows_x = self.velocity_x[w..(h - 1) * w].chunks_mut(w);
+        let rows_y = self.velocity_y[w..(h - 1) * w].chunks_mut(w);
+        for (i, (row_x, row_y)) in rows_x.zip(rows_y).enumerate() {
+            kernels::advect_row([row_x, row_y], [&vx_prev, &vy_prev], &vx_prev, &vy_prev, i + 1, w, h, [dt, dt]);
+        }
+    }
+
+    fn advect_phi(&mut self) {
+        let (w, h, dt) = (self.width, self.height, self.dt);
+        self.scratch.copy_from_slice(&self.phi);
+        let (prev, vx, vy) = (&self.scratch, &self.velocity_x, &self.velocity_y);
+        for (i, row) in self.phi[w..(h - 1) * w].chunks_mut(w).enumerate() {
+            kernels::advect_row([row], [prev], vx, vy, i + 1, w, h, [dt, dt]);
+        }
+        copy_ring(&mut self.phi, w, h);
+    }
+
+    // Free-slip walls: nothing flows through the tank's sides
+    fn set_velocity_boundaries(&mut self) {
+        let (w, h) = (self.width, self.height);
+        for x in 0..w {
+            for (edge, inner) in [(x, w + x), ((h - 1) * w + x, (h - 2) * w + x)] {
+                self.velocity_x[edge] = self.velocity_x[inner];
+                self.velocity_y[edge] = 0.0;
+            }
+        }
+        for y in 0..h {
+            for (edge, inner) in [(y * w, y * w + 1), (y * w + w - 1, y * w + w - 2)] {
+                self.velocity_x[edge] = 0.0;
+                self.velocity_y[edge] = self.velocity_y[inner];
+            }
+        }
+        // The row and column next to each wall can't move into it either
+        for y in 1..h - 1 {
+            self.velocity_x[y * w + 1] = self.velocity_x[y * w + 1].max(0.0);
+            self.velocity_x[y * w + w - 2] = self.velocity_x[y * w + w - 2].min(0.0);
+        }
+        for x in 1..w - 1 {
+            self.velocity_y[w + x] = self.velocity_y[w + x].max(0.0);
+            self.velocity_y[(h - 2) * w + x] = self.velocity_y[(h - 2) * w + x].min(0.0);
+        }
+    }
+
+    // Pressure only inside the liquid: zero in air (the free surface) and
+    // mirrored at walls
+    fn project(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let liquid: Vec<bool> = self.phi.iter().map(|&p| p < 0.0).collect();
+        let mut divergence = vec![0.0f32; w * h];
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                let idx = y * w + x;
+                if liquid[idx] {
+                    divergence[idx] = -0.5
+                        * (self.velocity_x[idx + 1] - self.velocity_x[idx - 1] + self.velocity_y[idx + w]
+                            - self.velocity_y[idx - w]);
+                }
+            }
+        }
+
+        let is_wall = |idx: usize| {
+            let (x, y) = (idx % w, idx / w);
+            x == 0 || y == 0 || x == w - 1 || y == h - 1
+        };
+        // Start from last step's pressure, which for liquid near rest is
+        // already close to hydrostatic
+        for (p, &l) in self.pressure.iter_mut().zip(&liquid) {
+            if !l {
+                *p = 0.0;
+            }
+        }
+        for _ in 0..self.pressure_iterations {
+            for y in 1..h - 1 {
+                for x in 1..w - 1 {
+                    let idx = y * w + x;
+                    if !liquid[idx] {
+                        continue;
+                    }
+                    let (mut sum, mut count) = (divergence[idx], 0.0);
+                    for n in [idx - 1, idx + 1, idx - w, idx + w] {
+                        if !is_wall(n) {
+                            sum += if liquid[n] { self.pressure[n] } else { 0.0 };
+                            count += 1.0;
+                        }
+                    }
+                    self.pressure[idx] = if count > 0.0 { sum / count } else { 0.0 };
+                }
+            }
+        }
+
+        // Walls mirror the pressure beside them
+        let p = |n: usize, own: f32, pressure: &[f32]| if is_wall(n) { own } else { pressure[n] };
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                let idx = y * w + x;
+                if !liquid[idx] {
+                    continue;
+                }
+                let own = self.pressure[idx];
+                let pressure = &self.pressure;
+                self.velocity_x[idx] -= 0.5 * (p(idx + 1, own, pressure) - p(idx - 1, own, pressure));
+                self.velocity_y[idx] -= 0.5 * (p(idx + w, own, pressure) - p(idx - w, own, pressure));
+            }
+        }
+    }
+
+    // Rebuild `phi` as a signed distance around its zero crossing by fast
+    // sweeping, so advection doesn't steepen or flatten it
+    fn redistance(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let phi = &self.phi;
+        let sign = |idx: usize| if phi[idx] < 0.0 { -1.0 } else { 1.0 };
+
+        // Cells beside a crossing keep their distance estimate
+        let mut distance = vec![FAR; w * h];
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                let idx = y * w + x;
+                let crosses = [idx - 1, idx + 1, idx - w, idx + w]
+                    .iter()
+                    .any(|&n| (phi[n] < 0.0) != (phi[idx] < 0.0));
+                if crosses {
+                    let gx = 0.5 * (phi[idx + 1] - phi[idx - 1]);
+                    let gy = 0.5 * (phi[idx + w] - phi[idx - w]);
+                    let gradient = (gx * gx + gy * gy).sqrt().max(0.5);
+                    distance[idx] = (phi[idx].abs() / gradient).min(1.0);
+                }
+            }
+        }
+
+        let xs: Vec<usize> = (1..w - 1).collect();
+        let ys: Vec<usize> = (1..h - 1).collect();
+        for (flip_x, flip_y) in [(false, false), (true, false), (false, true), (true, true)] {
+            for &y in ordered(&ys, flip_y) {
+                for &x in ordered(&xs, flip_x) {
+                    let idx = y * w + x;
+                    let a = distance[idx - 1].min(distance[idx + 1]);
+                    let b = distance[idx - w].min(distance[idx + w]);
+                    let candidate = if (a - b).abs() >= 1.0 {
+                        a.min(b) + 1.0
+                    } else {
+                        0.5 * (a + b + (2.0 - (a - b) * (a - b)).sqrt())
+                    };
+                    distance[idx] = distance[idx].min(candidate);
+                }
+            }
+        }
+
+        let signed: Vec<f32> = (0..w * h).map(|idx| sign(idx) * distance[idx]).collect();
+        self.phi = signed;
+        copy_ring(&mut self.phi, w, h);
+    }
+
+    // Shift the surface so the liquid keeps its volume, by bisection on the
+    // offset added to `phi`
+    fn conserve_volume(&mut self) {
+        let Some(target) = self.target_volume else { return };
+        if target <= 0.0 {
+            return;
+        }
+        let volume_with = |offset: f32| -> f32 { self.interior().map(|idx| liquid_fraction(self.phi[idx] + offset)).sum() };
+        let (mut low, mut high) = (-2.0f32, 2.0f32);
+        for _ in 0..24 {
+            let mid = 0.5 * (low + high);
+            // More offset means less liquid
+            if volume_with(mid) > target {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        let offset = 0.5 * (low + high);
+        self.phi.iter_mut().for_each(|p| *p += offset);
+    }
+
+    fn update_fraction(&mut self) {
+        for (fraction, &phi) in self.fraction.iter_mut().zip(&self.phi) {
+            *fraction = liquid_fraction(phi);
+        }
+    }
+}
+
+/// Share of a cell that is liquid, blending over one cell around the surface.
+pub fn liquid_fraction(phi: f32) -> f32 {
+    (0.5 - phi).clamp(0.0, 1.0)
+}
+
+fn ordered(values: &[usize], reverse: bool) -> Box<dyn Iterator<Item = &usize> + '_> {
+    if reverse { Box::new(values.iter().rev()) } else { Box::new(values.iter()) }
+}
+
+fn copy_ring(field: &mut [f32], w: usize, h: usize) {
+    for x in 0..w {
+        field[x] = field[w + x];
+        field[(h - 1) * w + x] = field[(h - 2) * w + x];
+    }
+    for y in 0..h {
+        field[y * w] = field[y * w + 1];
+        field[y * w + w - 1] = field[y * w + w - 2];
+    }
+}
+
+impl FluidSimulation for LevelSetFluid {
+    fn step(&mut self) {
+        LevelSetFluid::step(self);
+    }
+
+    /// Push the liquid within three cells of (x, y).
+    fn add_force(&mut self, x: usize, y: usize, force: Vec2) {
+        let radius = 3i32;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let (px, py) = ((x as i32 + dx) as usize, (y as i32 + dy) as usize);
+                if px < self.width && py < self.height && dx * dx + dy * dy <= radius * radius {
+                    let idx = py * self.width + px;
+                    self.velocity_x[idx] += force.x;
+                    self.velocity_y[idx] += force.y;
+                }
+            }
+        }
+    }
+
+    /// Pours a little liquid at (x, y); a free surface has no dye, so the
+    /// color is ignored.
+    fn add_dye(&mut self, x: usize, y: usize, _color: (f32, f32, f32)) {
+        self.add_liquid_disc(x as f32, y as f32, 1.5);
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+}
+
+impl ConfigurableSimulation for LevelSetFluid {
+    fn params(&self) -> Vec<(Param, f32)> {
+        vec![(Param::Dt, self.dt)]
+    }
+
+    fn set_param(&mut self, param: Param, value: f32) -> Result<(), Box<dyn std::error::Error>> {
+        match param {
+            Param::Dt => self.dt = param.validate(value)?,
+            _ => return Err(format!("the level set solver has no {} parameter", param.name()).into()),
+        }
+        Ok(())
+    }
+}
+
+/// Density is the liquid fraction, so renderers and metrics show the water.
+impl FluidData for LevelSetFluid {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn density(&self) -> &[f32] {
+        &self.fraction
+    }
+
+    fn velocity_x(&self) -> &[f32] {
+        &self.velocity_x
+    }
+
+    fn velocity_y(&self) -> &[f32] {
+        &self.velocity_y
+    }
+}
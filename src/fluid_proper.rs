@@ -1,5 +1,8 @@
+#![allow(deprecated)]
+
 use glam::Vec2;
 
+#[deprecated(note = "use solver::Solver with Scheme::Buoyant")]
 #[derive(Debug, Clone)]
 pub struct FluidSolver {
     pub width: usize,
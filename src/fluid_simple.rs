@@ -1,5 +1,8 @@
+#![allow(deprecated)]
+
 use glam::Vec2;
 
+#[deprecated(note = "use solver::Solver with Scheme::Explicit")]
 #[derive(Debug, Clone)]
 pub struct FluidSimulation {
     pub width: usize,
@@ -1,5 +1,8 @@
+#![allow(deprecated)]
+
 use glam::Vec2;
 
+#[deprecated(note = "use solver::Solver with Scheme::Stable")]
 #[derive(Debug, Clone)]
 pub struct WorkingFluid {
     pub width: usize,
@@ -13,6 +16,7 @@ pub struct WorkingFluid {
     pub dt: f32,
     pub viscosity: f32,
     pub diffusion: f32,
+    pub pressure_iterations: usize,
 }
 
 impl WorkingFluid {
@@ -30,6 +34,7 @@ impl WorkingFluid {
             dt: 0.1,
             viscosity: 0.001,
             diffusion: 0.001,
+            pressure_iterations: 20,
         }
     }
 
@@ -218,7 +223,7 @@ impl WorkingFluid {
         }
 
         // Solve for pressure
-        for _ in 0..20 {
+        for _ in 0..self.pressure_iterations {
             for y in 1..self.height - 1 {
                 for x in 1..self.width - 1 {
                     let idx = y * self.width + x;
@@ -0,0 +1,127 @@
+//! Tone-mapped RGBA frames for external encoders and streamers, reusing one
+//! buffer across frames.
+
+use crate::InteractiveFluid;
+
+/// Owns an RGBA8 buffer that is refilled in place every frame, so consumers
+/// such as an ffmpeg pipe or a v4l2 loopback can borrow the bytes without a
+/// per-frame allocation.
+#[derive(Debug, Clone)]
+pub struct FrameBuffer {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            rgba: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Change the output size. Only reallocates when the buffer must grow.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.rgba.resize(width as usize * height as usize * 4, 0);
+    }
+
+    /// Tone-map the current dye into the buffer and borrow the result.
+    pub fn update(&mut self, simulation: &InteractiveFluid) -> &[u8] {
+        write_rgba(simulation, self.width, self.height, &mut self.rgba)
+            .expect("buffer is sized by new/resize");
+        &self.rgba
+    }
+
+    /// The last frame written by `update`, tightly packed RGBA8 rows.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.rgba
+    }
+}
+
+/// Tone-map dye into a caller-provided RGBA8 buffer of exactly
+/// `width * height * 4` bytes, scaling the grid with nearest-neighbour
+/// sampling. Uses the same Reinhard curve as the interactive canvas.
+pub fn write_rgba(
+    simulation: &InteractiveFluid,
+    width: u32,
+    height: u32,
+    out: &mut [u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let expected = width as usize * height as usize * 4;
+    if out.len() != expected {
+        return Err(format!(
+            "RGBA buffer holds {} bytes, {}x{} needs {}",
+            out.len(),
+            width,
+            height,
+            expected
+        )
+        .into());
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    if w == 0 || h == 0 {
+        return Ok(());
+    }
+    let (grid_w, grid_h) = (simulation.width, simulation.height);
+
+    for (y, row) in out.chunks_exact_mut(w * 4).enumerate() {
+        let sim_y = y * grid_h / h;
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let idx = sim_y * grid_w + x * grid_w / w;
+            pixel[0] = reinhard(simulation.dye_r[idx]);
+            pixel[1] = reinhard(simulation.dye_g[idx]);
+            pixel[2] = reinhard(simulation.dye_b[idx]);
+            pixel[3] = 255;
+        }
+    }
+    Ok(())
+}
+
+// Reinhard tone mapping x / (1 + x), quantized to 8 bits
+fn reinhard(value: f32) -> u8 {
+    let v = value.max(0.0);
+    (v / (1.0 + v) * 255.0) as u8
+}
+
+/// Application icon: a short swirl of dye rendered into a round
+/// `size` x `size` RGBA8 image. The simulation is deterministic, so the icon
+/// is the same on every launch.
+pub fn icon_rgba(size: u32) -> Vec<u8> {
+    let mut simulation = InteractiveFluid::new(48, 48);
+    for step in 0..40 {
+        // Two opposed jets around the centre spin up a vortex
+        if step < 20 {
+            simulation.add_force(16, 24, glam::Vec2::new(0.0, -6.0), 4.0);
+            simulation.add_force(32, 24, glam::Vec2::new(0.0, 6.0), 4.0);
+            for dy in 0..3 {
+                for dx in 0..3 {
+                    simulation.add_dye(15 + dx, 23 + dy, (0.2, 1.2, 2.0));
+                    simulation.add_dye(31 + dx, 23 + dy, (2.0, 0.6, 1.0));
+                }
+            }
+        }
+        simulation.step();
+    }
+
+    let mut rgba = vec![0; size as usize * size as usize * 4];
+    write_rgba(&simulation, size, size, &mut rgba).expect("buffer sized above");
+
+    // Round mask with a one-pixel soft edge
+    let radius = size as f32 * 0.5;
+    for (i, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+        let x = (i % size as usize) as f32 + 0.5 - radius;
+        let y = (i / size as usize) as f32 + 0.5 - radius;
+        let coverage = (radius - (x * x + y * y).sqrt()).clamp(0.0, 1.0);
+        pixel[3] = (coverage * 255.0) as u8;
+    }
+    rgba
+}
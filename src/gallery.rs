@@ -0,0 +1,181 @@
+//! Take-home gallery cards: the final canvas framed with a caption, the
+//! session's colors and an optional QR code for the share link.
+
+use crate::frame::write_rgba;
+use crate::{InteractiveFluid, SessionStats};
+use ab_glyph::{point, Font, FontArc, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+
+const BACKGROUND: Rgba<u8> = Rgba([12, 12, 16, 255]);
+const CAPTION: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const DETAIL: Rgba<u8> = Rgba([170, 170, 180, 255]);
+
+/// Layout options for a gallery card.
+#[derive(Debug, Clone)]
+pub struct GalleryCard {
+    /// Output pixels per simulation cell
+    pub scale: u32,
+    pub caption: Option<String>,
+    /// Encoded as a QR code in the footer, typically the share link
+    pub link: Option<String>,
+}
+
+impl Default for GalleryCard {
+    fn default() -> Self {
+        Self {
+            scale: 4,
+            caption: None,
+            link: None,
+        }
+    }
+}
+
+impl GalleryCard {
+    /// Render the canvas with a footer holding the caption, the session
+    /// summary, one swatch per color used and the QR code.
+    pub fn render(
+        &self,
+        simulation: &InteractiveFluid,
+        stats: &SessionStats,
+    ) -> Result<RgbaImage, Box<dyn std::error::Error>> {
+        let scale = self.scale.max(1);
+        let (canvas_w, canvas_h) = (simulation.width as u32 * scale, simulation.height as u32 * scale);
+        let margin = (canvas_w / 24).max(8);
+
+        let qr = match &self.link {
+            Some(link) => Some(qr_modules(link)?),
+            None => None,
+        };
+        // Two modules of quiet zone on each side
+        let qr_span = qr.as_ref().map_or(0, |(modules, _)| *modules as u32 + 4);
+        let qr_module_px = (canvas_w / 5).checked_div(qr_span).map_or(0, |px| px.max(2));
+        let qr_px = qr_span * qr_module_px;
+
+        let caption_px = (canvas_w as f32 / 16.0).max(16.0);
+        let detail_px = caption_px * 0.6;
+        let swatch = detail_px as u32;
+        let mut text_h = detail_px as u32 + margin / 2 + swatch;
+        if self.caption.is_some() {
+            text_h += caption_px as u32 + margin / 2;
+        }
+        let footer_h = text_h.max(qr_px);
+
+        let width = canvas_w + 2 * margin;
+        let height = canvas_h + footer_h + 3 * margin;
+        let mut card = RgbaImage::from_pixel(width, height, BACKGROUND);
+
+        let mut canvas = vec![0u8; canvas_w as usize * canvas_h as usize * 4];
+        write_rgba(simulation, canvas_w, canvas_h, &mut canvas)?;
+        let canvas = RgbaImage::from_raw(canvas_w, canvas_h, canvas).ok_or("canvas buffer size mismatch")?;
+        image::imageops::replace(&mut card, &canvas, margin as i64, margin as i64);
+
+        // Footer: text on the left, QR code on the right
+        let footer_top = canvas_h + 2 * margin;
+        let text_right = if qr_px > 0 { width - margin - qr_px - margin } else { width - margin };
+        let font = card_font()?;
+        let mut y = footer_top;
+        if let Some(caption) = &self.caption {
+            draw_text(&mut card, &font, caption, margin, y, caption_px, CAPTION, text_right);
+            y += caption_px as u32 + margin / 2;
+        }
+        draw_text(&mut card, &font, &stats.summary(), margin, y, detail_px, DETAIL, text_right);
+        y += detail_px as u32 + margin / 2;
+
+        for (i, &(r, g, b)) in stats.colors.iter().enumerate() {
+            let x = margin + i as u32 * (swatch + swatch / 2);
+            if x + swatch > text_right {
+                break;
+            }
+            let color = Rgba([channel(r), channel(g), channel(b), 255]);
+            fill(&mut card, x, y, swatch, swatch, color);
+        }
+
+        if let Some((modules, dark)) = qr {
+            let left = width - margin - qr_px;
+            fill(&mut card, left, footer_top, qr_px, qr_px, Rgba([255, 255, 255, 255]));
+            for (i, dark) in dark.into_iter().enumerate() {
+                if dark {
+                    let (mx, my) = ((i % modules) as u32 + 2, (i / modules) as u32 + 2);
+                    let (x, y) = (left + mx * qr_module_px, footer_top + my * qr_module_px);
+                    fill(&mut card, x, y, qr_module_px, qr_module_px, Rgba([0, 0, 0, 255]));
+                }
+            }
+        }
+
+        Ok(card)
+    }
+
+    pub fn save(
+        &self,
+        simulation: &InteractiveFluid,
+        stats: &SessionStats,
+        path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.render(simulation, stats)?.save(path)?;
+        Ok(())
+    }
+}
+
+/// QR code for `data` as its width in modules and one dark flag per module,
+/// row by row. The caller adds the quiet zone.
+pub fn qr_modules(data: &str) -> Result<(usize, Vec<bool>), Box<dyn std::error::Error>> {
+    let code = qrcode::QrCode::new(data.as_bytes())?;
+    let dark = code.to_colors().into_iter().map(|color| color == qrcode::Color::Dark).collect();
+    Ok((code.width(), dark))
+}
+
+// The UI's proportional font, so cards match the app
+fn card_font() -> Result<FontArc, Box<dyn std::error::Error>> {
+    let definitions = egui::FontDefinitions::default();
+    let data = definitions.font_data.get("Ubuntu-Light").ok_or("egui default font missing")?;
+    Ok(FontArc::try_from_vec(data.font.to_vec())?)
+}
+
+// Draw one line with its top at `top`, clipped at `right`
+#[allow(clippy::too_many_arguments)]
+fn draw_text(image: &mut RgbaImage, font: &FontArc, text: &str, left: u32, top: u32, size: f32, color: Rgba<u8>, right: u32) {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let baseline = top as f32 + scaled.ascent();
+    let mut caret = left as f32;
+    let mut previous = None;
+
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, id);
+        }
+        previous = Some(id);
+        let glyph = id.with_scale_and_position(size, point(caret, baseline));
+        caret += scaled.h_advance(id);
+
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let x = bounds.min.x as i64 + gx as i64;
+            let y = bounds.min.y as i64 + gy as i64;
+            if x < left as i64 || x >= right as i64 || y < 0 || y >= image.height() as i64 {
+                return;
+            }
+            let pixel = image.get_pixel_mut(x as u32, y as u32);
+            for c in 0..3 {
+                let blended = pixel[c] as f32 + (color[c] as f32 - pixel[c] as f32) * coverage.clamp(0.0, 1.0);
+                pixel[c] = blended.round() as u8;
+            }
+        });
+    }
+}
+
+fn fill(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for py in y..(y + height).min(image.height()) {
+        for px in x..(x + width).min(image.width()) {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn channel(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0) as u8
+}
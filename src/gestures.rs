@@ -0,0 +1,138 @@
+//! Multi-touch on the canvas: two fingers pan and pinch-zoom the view while
+//! one finger paints, or, with multi-finger painting on, every finger draws
+//! its own stroke. egui turns the first touch into the pointer; the others
+//! only arrive as touch events, so all of them are followed here.
+
+use crate::brush::{Brush, BrushStroke};
+use egui::{Event, Pos2, Rect, TouchPhase, Vec2};
+use std::collections::BTreeMap;
+
+pub const MAX_ZOOM: f32 = 8.0;
+
+/// Zoom and pan of the grid within the canvas. At zoom 1 the whole grid
+/// shows; zoomed in, the grid always covers the canvas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CanvasView {
+    pub zoom: f32,
+    /// Offset of the grid's center from the canvas center, in points
+    pub pan: Vec2,
+}
+
+impl Default for CanvasView {
+    fn default() -> Self {
+        Self { zoom: 1.0, pan: Vec2::ZERO }
+    }
+}
+
+impl CanvasView {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Where the grid is drawn for a canvas occupying `canvas`.
+    pub fn apply(&self, canvas: Rect) -> Rect {
+        Rect::from_center_size(canvas.center() + self.pan, canvas.size() * self.zoom)
+    }
+
+    /// Zoom by `factor` keeping the point under `anchor` in place, then
+    /// move by `translation`.
+    pub fn gesture(&mut self, canvas: Rect, anchor: Pos2, factor: f32, translation: Vec2) {
+        let zoom = (self.zoom * factor).clamp(1.0, MAX_ZOOM);
+        let center = canvas.center() + self.pan;
+        let center = anchor + (center - anchor) * (zoom / self.zoom) + translation;
+        self.zoom = zoom;
+        self.pan = center - canvas.center();
+        self.fit(canvas);
+    }
+
+    /// Keep the pan within what the zoom allows, e.g. after the canvas
+    /// changed size.
+    pub fn fit(&mut self, canvas: Rect) {
+        let slack = canvas.size() * (self.zoom - 1.0) * 0.5;
+        self.pan = self.pan.clamp(-slack, slack);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Touch {
+    pos: Pos2,
+    // Position at the end of the previous frame
+    last: Pos2,
+    stroke: BrushStroke,
+}
+
+/// Fingers currently on the screen, by touch id.
+#[derive(Debug, Clone, Default)]
+pub struct Touches {
+    touches: BTreeMap<u64, Touch>,
+    // Set once a second finger lands and held until every finger lifts, so
+    // the finger left over from a pinch doesn't start painting
+    multi: bool,
+}
+
+impl Touches {
+    /// Follow this frame's touch events.
+    pub fn update(&mut self, events: &[Event]) {
+        for touch in self.touches.values_mut() {
+            touch.last = touch.pos;
+        }
+        for event in events {
+            let Event::Touch { id, phase, pos, .. } = event else {
+                continue;
+            };
+            match phase {
+                TouchPhase::Start => {
+                    self.touches.insert(id.0, Touch { pos: *pos, last: *pos, stroke: BrushStroke::default() });
+                }
+                TouchPhase::Move => {
+                    if let Some(touch) = self.touches.get_mut(&id.0) {
+                        touch.pos = *pos;
+                    }
+                }
+                TouchPhase::End | TouchPhase::Cancel => {
+                    self.touches.remove(&id.0);
+                }
+            }
+        }
+        if self.touches.len() >= 2 {
+            self.multi = true;
+        } else if self.touches.is_empty() {
+            self.multi = false;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.touches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.touches.is_empty()
+    }
+
+    /// Whether several fingers have been down since the screen was last
+    /// clear. Single-pointer tools stay out of the way meanwhile.
+    pub fn multi(&self) -> bool {
+        self.multi
+    }
+
+    /// The first two fingers' pinch since the last frame: the midpoint they
+    /// started from, the change in their spread and how far the midpoint
+    /// moved.
+    pub fn pinch(&self) -> Option<(Pos2, f32, Vec2)> {
+        let mut fingers = self.touches.values();
+        let (a, b) = (fingers.next()?, fingers.next()?);
+        let (last_spread, spread) = (a.last.distance(b.last), a.pos.distance(b.pos));
+        if last_spread < 1.0 {
+            return None;
+        }
+        let last_mid = a.last.lerp(b.last, 0.5);
+        Some((last_mid, spread / last_spread, a.pos.lerp(b.pos, 0.5) - last_mid))
+    }
+
+    /// Brush dabs along every finger's path since the last frame, each
+    /// finger spaced as its own stroke. `to_grid` maps screen points to
+    /// grid cells.
+    pub fn dabs(&mut self, brush: &Brush, to_grid: impl Fn(Pos2) -> glam::Vec2) -> Vec<glam::Vec2> {
+        self.touches.values_mut().flat_map(|touch| touch.stroke.dabs(brush, to_grid(touch.pos))).collect()
+    }
+}
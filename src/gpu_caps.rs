@@ -0,0 +1,241 @@
+//! GPU capability probing: which storage formats and workgroup sizes an
+//! adapter can actually run, and which pipeline configuration to use.
+
+use std::fmt::Write as _;
+use std::future::Future;
+use std::task::{Context, Poll, Waker};
+
+/// Texel format of the simulation's velocity and dye fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldFormat {
+    Rgba32Float,
+    /// Half precision, for adapters without read-write rgba32float storage
+    Rgba16Float,
+}
+
+impl FieldFormat {
+    /// Preferred first.
+    pub const ALL: [FieldFormat; 2] = [FieldFormat::Rgba32Float, FieldFormat::Rgba16Float];
+
+    pub fn texture_format(self) -> wgpu::TextureFormat {
+        match self {
+            FieldFormat::Rgba32Float => wgpu::TextureFormat::Rgba32Float,
+            FieldFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+
+    pub fn wgsl_name(self) -> &'static str {
+        match self {
+            FieldFormat::Rgba32Float => "rgba32float",
+            FieldFormat::Rgba16Float => "rgba16float",
+        }
+    }
+
+    pub fn bytes_per_texel(self) -> usize {
+        match self {
+            FieldFormat::Rgba32Float => 16,
+            FieldFormat::Rgba16Float => 8,
+        }
+    }
+
+    /// Pack f32 channel values into texel bytes.
+    pub fn encode(self, values: &[f32]) -> Vec<u8> {
+        match self {
+            FieldFormat::Rgba32Float => bytemuck::cast_slice(values).to_vec(),
+            FieldFormat::Rgba16Float => values
+                .iter()
+                .flat_map(|&v| half::f16::from_f32(v).to_bits().to_le_bytes())
+                .collect(),
+        }
+    }
+
+    /// Unpack texel bytes into f32 channel values.
+    pub fn decode(self, bytes: &[u8]) -> Vec<f32> {
+        match self {
+            FieldFormat::Rgba32Float => bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            FieldFormat::Rgba16Float => bytes
+                .chunks_exact(2)
+                .map(|b| half::f16::from_bits(u16::from_le_bytes([b[0], b[1]])).to_f32())
+                .collect(),
+        }
+    }
+}
+
+/// Format and workgroup size the simulation shaders are built with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineConfig {
+    pub format: FieldFormat,
+    /// Square workgroups of `workgroup_size` x `workgroup_size` invocations
+    pub workgroup_size: u32,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            format: FieldFormat::Rgba32Float,
+            workgroup_size: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatSupport {
+    pub format: FieldFormat,
+    pub storage: bool,
+    pub storage_read_write: bool,
+}
+
+/// What an adapter/device pair supports, as far as the solver cares.
+#[derive(Debug, Clone)]
+pub struct GpuCapabilities {
+    pub adapter_name: String,
+    pub backend: String,
+    pub device_type: String,
+    /// Whether the device was created with adapter-specific format features,
+    /// which read-write storage textures need
+    pub adapter_specific_formats: bool,
+    pub formats: Vec<FormatSupport>,
+    pub max_workgroup_size_x: u32,
+    pub max_workgroup_size_y: u32,
+    pub max_invocations_per_workgroup: u32,
+    pub max_texture_dimension_2d: u32,
+}
+
+impl GpuCapabilities {
+    pub fn probe(adapter: &wgpu::Adapter, device: &wgpu::Device) -> Self {
+        let info = adapter.get_info();
+        let limits = device.limits();
+        let formats = FieldFormat::ALL
+            .iter()
+            .map(|&format| {
+                let features = adapter.get_texture_format_features(format.texture_format());
+                FormatSupport {
+                    format,
+                    storage: features.allowed_usages.contains(wgpu::TextureUsages::STORAGE_BINDING),
+                    storage_read_write: features
+                        .flags
+                        .contains(wgpu::TextureFormatFeatureFlags::STORAGE_READ_WRITE),
+                }
+            })
+            .collect();
+
+        Self {
+            adapter_name: info.name,
+            backend: format!("{:?}", info.backend),
+            device_type: format!("{:?}", info.device_type),
+            adapter_specific_formats: device
+                .features()
+                .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES),
+            formats,
+            max_workgroup_size_x: limits.max_compute_workgroup_size_x,
+            max_workgroup_size_y: limits.max_compute_workgroup_size_y,
+            max_invocations_per_workgroup: limits.max_compute_invocations_per_workgroup,
+            max_texture_dimension_2d: limits.max_texture_dimension_2d,
+        }
+    }
+
+    /// Whether the solver's read-write storage textures can use `format`.
+    pub fn supports_format(&self, format: FieldFormat) -> bool {
+        self.adapter_specific_formats
+            && self
+                .formats
+                .iter()
+                .any(|s| s.format == format && s.storage && s.storage_read_write)
+    }
+
+    pub fn supports_workgroup(&self, size: u32) -> bool {
+        size <= self.max_workgroup_size_x
+            && size <= self.max_workgroup_size_y
+            && size * size <= self.max_invocations_per_workgroup
+    }
+
+    /// Usable configurations, best first: full precision before half, then
+    /// 8x8 workgroups before 4x4.
+    pub fn candidates(&self) -> Vec<PipelineConfig> {
+        let mut candidates = Vec::new();
+        for format in FieldFormat::ALL {
+            if !self.supports_format(format) {
+                continue;
+            }
+            for workgroup_size in [8, 4] {
+                if self.supports_workgroup(workgroup_size) {
+                    candidates.push(PipelineConfig { format, workgroup_size });
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Human-readable capability matrix.
+    pub fn report(&self) -> String {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        let mut report = format!(
+            "GPU: {} ({}, {})\n  adapter-specific formats: {}\n  format        storage  read-write\n",
+            self.adapter_name,
+            self.backend,
+            self.device_type,
+            yes_no(self.adapter_specific_formats)
+        );
+        for support in &self.formats {
+            let _ = writeln!(
+                report,
+                "  {:<13} {:<8} {}",
+                support.format.wgsl_name(),
+                yes_no(support.storage),
+                yes_no(support.storage_read_write)
+            );
+        }
+        let _ = writeln!(
+            report,
+            "  workgroups up to {}x{}, {} invocations; textures up to {}",
+            self.max_workgroup_size_x,
+            self.max_workgroup_size_y,
+            self.max_invocations_per_workgroup,
+            self.max_texture_dimension_2d
+        );
+        report
+    }
+}
+
+/// Outcome of compiling one shader entry point for a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPointCheck {
+    pub entry_point: String,
+    pub error: Option<String>,
+}
+
+/// Checks for every configuration tried, in order, with the selected one last.
+pub fn format_checks(checks: &[(PipelineConfig, Vec<EntryPointCheck>)]) -> String {
+    let mut report = String::new();
+    for (config, entries) in checks {
+        let failed: Vec<_> = entries.iter().filter(|c| c.error.is_some()).collect();
+        let _ = writeln!(
+            report,
+            "  {} with {}x{} workgroups: {}",
+            config.format.wgsl_name(),
+            config.workgroup_size,
+            config.workgroup_size,
+            if failed.is_empty() { "ok".to_string() } else { format!("{} entry point(s) failed", failed.len()) }
+        );
+        for check in failed {
+            let _ = writeln!(report, "    {}: {}", check.entry_point, check.error.as_deref().unwrap_or(""));
+        }
+    }
+    report
+}
+
+/// Pop a validation error scope. Native wgpu resolves the future once the
+/// device is polled, so no async runtime is needed. In the browser it only
+/// resolves after yielding to the event loop, so a pending scope counts as
+/// no error and the capability checks are the only filter there.
+pub fn pop_validation_error(device: &wgpu::Device) -> Option<String> {
+    let mut future = std::pin::pin!(device.pop_error_scope());
+    device.poll(wgpu::Maintain::Wait);
+    match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(error) => error.map(|e| e.to_string()),
+        Poll::Pending => None,
+    }
+}
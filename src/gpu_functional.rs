@@ -1,9 +1,17 @@
 //! Functional GPU fluid simulation with actual computation
 
+use crate::{BoundaryMode, Param};
+use crate::timing::{now_seconds, Stage, StageTimes};
+use crate::gpu_caps::{
+    format_checks, pop_validation_error, EntryPointCheck, GpuCapabilities, PipelineConfig,
+};
 use bytemuck::{Pod, Zeroable};
 use glam::Vec2;
+use std::future::Future;
 use std::num::NonZeroU64;
-use tokio::sync::oneshot;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
 use wgpu::util::DeviceExt;
 use wgpu::{
     BindGroup, BindGroupLayout, Buffer, ComputePipeline, Device, Queue, Texture, TextureView,
@@ -17,14 +25,82 @@ struct SimulationParams {
     dt: f32,
     viscosity: f32,
     diffusion: f32,
-    _padding: [u32; 2],
+    /// `boundary_code` of the edge mode
+    boundary: u32,
+    /// Dye fade rate per RGB channel
+    decay: [f32; 3],
+    /// Splats in the buffer for the next inject pass
+    splat_count: u32,
+    /// Corner of the cells the inject pass covers
+    inject_origin: [u32; 2],
+    /// Cell height over width
+    cell_aspect: f32,
+    _padding: [u32; 3],
+}
+
+/// One cell's worth of added dye or force, as the inject pass reads it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct Splat {
+    x: u32,
+    y: u32,
+    kind: u32,
+    _padding: u32,
+    value: [f32; 4],
+}
+
+const DYE_SPLAT: u32 = 0;
+const FORCE_SPLAT: u32 = 1;
+
+// Splats uploaded per inject pass; larger batches take several passes
+const SPLAT_CAPACITY: usize = 4096;
+
+// Shader constants for each supported edge mode
+fn boundary_code(mode: BoundaryMode) -> Option<u32> {
+    match mode {
+        BoundaryMode::NoSlip => Some(0),
+        BoundaryMode::FreeSlip => Some(1),
+        BoundaryMode::Periodic => Some(2),
+        BoundaryMode::Open => Some(3),
+        BoundaryMode::Absorbing => None,
+    }
 }
 
+/// Device features the simulation shaders need (read-write float storage
+/// textures). A shared device, e.g. egui's, must be created with them.
+pub const REQUIRED_FEATURES: wgpu::Features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
+// Compute entry points in SHADER_SOURCE, in the order build_pipelines returns them
+const ENTRY_POINTS: [&str; 10] = [
+    "copy_velocity_to_prev",
+    "diffuse_velocity",
+    "project_velocity",
+    "advect_velocity",
+    "set_velocity_boundaries",
+    "copy_dye_to_prev",
+    "diffuse_dye",
+    "advect_dye",
+    "set_dye_boundaries",
+    "inject",
+];
+
 pub struct FunctionalGPUFluid {
-    device: Device,
-    queue: Queue,
+    // Shared so the simulation can live on the same device as the renderer
+    device: Arc<Device>,
+    queue: Arc<Queue>,
     width: u32,
     height: u32,
+    dt: f32,
+    viscosity: f32,
+    diffusion: f32,
+    boundary: BoundaryMode,
+    dye_decay: [f32; 3],
+    cell_aspect: f32,
+
+    // Field format and workgroup size chosen for this device
+    capabilities: GpuCapabilities,
+    pipeline_config: PipelineConfig,
+    shader_checks: Vec<(PipelineConfig, Vec<EntryPointCheck>)>,
 
     // Simulation parameters buffer
     params_buffer: Buffer,
@@ -49,13 +125,35 @@ pub struct FunctionalGPUFluid {
     project_velocity_pipeline: ComputePipeline,
     copy_velocity_to_prev_pipeline: ComputePipeline,
     copy_dye_to_prev_pipeline: ComputePipeline,
+    inject_pipeline: ComputePipeline,
+
+    // Staging for batched dye and force
+    splat_buffer: Buffer,
+    // Painted viscosity factor per cell
+    viscosity_buffer: Buffer,
 
     // Bind groups
     bind_group: BindGroup,
+
+    // Shader hot reload
+    shader_error: Option<String>,
+    #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+    shader_watch: Option<ShaderWatch>,
+
+    // Stage timing; see `set_profiling`
+    profiling: bool,
+    stage_times: StageTimes,
 }
 
 impl FunctionalGPUFluid {
     pub async fn new(width: u32, height: u32) -> Result<Self, Box<dyn std::error::Error>> {
+        // The web build only wants WebGPU; WebGL has no compute shaders
+        #[cfg(target_arch = "wasm32")]
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::BROWSER_WEBGPU,
+            ..Default::default()
+        });
+        #[cfg(not(target_arch = "wasm32"))]
         let instance = wgpu::Instance::default();
 
         let adapter = instance
@@ -71,13 +169,29 @@ impl FunctionalGPUFluid {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: Some("Functional Fluid GPU"),
-                    required_features: wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
-                    required_limits: wgpu::Limits::downlevel_defaults(),
+                    required_features: REQUIRED_FEATURES & adapter.features(),
+                    // Downlevel limits fit every WebGPU implementation; the
+                    // texture size follows the adapter for large grids
+                    required_limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
                 },
                 None,
             )
             .await?;
 
+        Self::with_device(&adapter, Arc::new(device), Arc::new(queue), width, height)
+    }
+
+    /// Build the simulation on an existing device, such as the one eframe
+    /// renders with, so its textures can be drawn without a readback. The
+    /// device needs `REQUIRED_FEATURES`; the field format and workgroup size
+    /// are picked from what the adapter supports and what compiles.
+    pub fn with_device(
+        adapter: &wgpu::Adapter,
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         // Create simulation parameters buffer
         let params = SimulationParams {
             width,
@@ -85,7 +199,12 @@ impl FunctionalGPUFluid {
             dt: 0.1,
             viscosity: 0.00001,
             diffusion: 0.00001,
-            _padding: [0, 0],
+            boundary: 0,
+            decay: [0.0; 3],
+            splat_count: 0,
+            inject_origin: [0; 2],
+            cell_aspect: 1.0,
+            _padding: [0; 3],
         };
 
         let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -94,6 +213,61 @@ impl FunctionalGPUFluid {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        let splat_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Splats"),
+            size: (SPLAT_CAPACITY * std::mem::size_of::<Splat>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let viscosity_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Viscosity Factors"),
+            contents: bytemuck::cast_slice(&vec![1.0f32; (width * height) as usize]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Pick the best configuration whose shaders actually compile here
+        let capabilities = GpuCapabilities::probe(adapter, &device);
+        let mut shader_checks = Vec::new();
+        let mut selected = None;
+        for config in capabilities.candidates() {
+            let (layout, pipelines, checks) = build_pipelines(&device, config, SHADER_SOURCE);
+            let ok = checks.iter().all(|c| c.error.is_none());
+            shader_checks.push((config, checks));
+            if ok {
+                selected = Some((config, layout, pipelines));
+                break;
+            }
+        }
+        let Some((pipeline_config, bind_group_layout, pipelines)) = selected else {
+            return Err(format!(
+                "No usable GPU pipeline configuration\n{}{}",
+                capabilities.report(),
+                format_checks(&shader_checks)
+            )
+            .into());
+        };
+        if pipeline_config != PipelineConfig::default() {
+            eprintln!(
+                "GPU: falling back to {} fields with {}x{} workgroups",
+                pipeline_config.format.wgsl_name(),
+                pipeline_config.workgroup_size,
+                pipeline_config.workgroup_size
+            );
+        }
+        let [
+            copy_velocity_to_prev_pipeline,
+            diffuse_velocity_pipeline,
+            project_velocity_pipeline,
+            advect_velocity_pipeline,
+            set_velocity_boundaries_pipeline,
+            copy_dye_to_prev_pipeline,
+            diffuse_dye_pipeline,
+            advect_dye_pipeline,
+            set_dye_boundaries_pipeline,
+            inject_pipeline,
+        ]: [ComputePipeline; 10] = pipelines.try_into().map_err(|_| "pipeline count mismatch")?;
+
         // Create textures
         let texture_size = wgpu::Extent3d {
             width,
@@ -107,7 +281,7 @@ impl FunctionalGPUFluid {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba32Float,
+            format: pipeline_config.format.texture_format(),
             usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
@@ -120,7 +294,7 @@ impl FunctionalGPUFluid {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba32Float,
+            format: pipeline_config.format.texture_format(),
             usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
@@ -133,8 +307,9 @@ impl FunctionalGPUFluid {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba32Float,
+            format: pipeline_config.format.texture_format(),
             usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
                 | wgpu::TextureUsages::COPY_DST
                 | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[],
@@ -148,7 +323,7 @@ impl FunctionalGPUFluid {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba32Float,
+            format: pipeline_config.format.texture_format(),
             usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
@@ -156,7 +331,8 @@ impl FunctionalGPUFluid {
         let dye_prev_view = dye_prev_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         // Initialize all textures to zero
-        let zero_data = vec![0.0f32; (width * height * 4) as usize];
+        let texel_bytes = pipeline_config.format.bytes_per_texel() as u32;
+        let zero_data = vec![0u8; (width * height * texel_bytes) as usize];
 
         queue.write_texture(
             wgpu::ImageCopyTexture {
@@ -165,10 +341,10 @@ impl FunctionalGPUFluid {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            bytemuck::cast_slice(&zero_data),
+            &zero_data,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(width * 4 * std::mem::size_of::<f32>() as u32),
+                bytes_per_row: Some(width * texel_bytes),
                 rows_per_image: Some(height),
             },
             texture_size,
@@ -181,10 +357,10 @@ impl FunctionalGPUFluid {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            bytemuck::cast_slice(&zero_data),
+            &zero_data,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(width * 4 * std::mem::size_of::<f32>() as u32),
+                bytes_per_row: Some(width * texel_bytes),
                 rows_per_image: Some(height),
             },
             texture_size,
@@ -197,10 +373,10 @@ impl FunctionalGPUFluid {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            bytemuck::cast_slice(&zero_data),
+            &zero_data,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(width * 4 * std::mem::size_of::<f32>() as u32),
+                bytes_per_row: Some(width * texel_bytes),
                 rows_per_image: Some(height),
             },
             texture_size,
@@ -213,524 +389,36 @@ impl FunctionalGPUFluid {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            bytemuck::cast_slice(&zero_data),
+            &zero_data,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(width * 4 * std::mem::size_of::<f32>() as u32),
+                bytes_per_row: Some(width * texel_bytes),
                 rows_per_image: Some(height),
             },
             texture_size,
         );
 
-        // Create complete fluid simulation shader matching CPU algorithm
-        let shader_source = r"
-            // Helper functions
-            fn floor(x: f32) -> f32 {
-                return f32(i32(x));
-            }
-            
-            fn max(a: f32, b: f32) -> f32 {
-                return select(b, a, a >= b);
-            }
-            
-            fn min(a: f32, b: f32) -> f32 {
-                return select(a, b, a <= b);
-            }
-            
-            fn select(a: f32, b: f32, condition: bool) -> f32 {
-                if (condition) {
-                    return a;
-                } else {
-                    return b;
-                }
-            }
-            struct SimulationParams {
-                width: u32,
-                height: u32,
-                dt: f32,
-                viscosity: f32,
-                diffusion: f32,
-            }
-            
-            @group(0) @binding(0)
-            var<uniform> params: SimulationParams;
-
-            @group(0) @binding(1)
-            var velocity_texture: texture_storage_2d<rgba32float, read_write>;
-
-            @group(0) @binding(2)
-            var velocity_prev_texture: texture_storage_2d<rgba32float, read_write>;
-
-            @group(0) @binding(3)
-            var dye_texture: texture_storage_2d<rgba32float, read_write>;
-
-            @group(0) @binding(4)
-            var dye_prev_texture: texture_storage_2d<rgba32float, read_write>;
-            
-            fn sample_velocity(coord: vec2<u32>) -> vec2<f32> {
-                let texel = textureLoad(velocity_texture, coord);
-                return vec2<f32>(texel.x, texel.y);
-            }
-            
-            fn sample_velocity_prev(coord: vec2<u32>) -> vec2<f32> {
-                let texel = textureLoad(velocity_prev_texture, coord);
-                return vec2<f32>(texel.x, texel.y);
-            }
-            
-            fn sample_dye(coord: vec2<u32>) -> vec3<f32> {
-                let texel = textureLoad(dye_texture, coord);
-                return vec3<f32>(texel.x, texel.y, texel.z);
-            }
-
-            fn sample_dye_prev(coord: vec2<u32>) -> vec3<f32> {
-                let texel = textureLoad(dye_prev_texture, coord);
-                return vec3<f32>(texel.x, texel.y, texel.z);
-            }
-
-            fn set_velocity(coord: vec2<u32>, velocity: vec2<f32>) {
-                textureStore(velocity_texture, coord, vec4<f32>(velocity.x, velocity.y, 0.0, 1.0));
-            }
-
-            fn set_dye(coord: vec2<u32>, dye: vec3<f32>) {
-                textureStore(dye_texture, coord, vec4<f32>(dye.x, dye.y, dye.z, 1.0));
-            }
-            
-            // Velocity diffusion matching CPU implementation
-            @compute @workgroup_size(8, 8)
-            fn diffuse_velocity(@builtin(global_invocation_id) global_id: vec3<u32>) {
-                if (global_id.x >= params.width || global_id.y >= params.height) {
-                    return;
-                }
-                
-                let coord = vec2<u32>(global_id.x, global_id.y);
-                let x = i32(coord.x);
-                let y = i32(coord.y);
-                
-                // Skip boundaries (handled separately)
-                if (x <= 0 || x >= i32(params.width - 1) || y <= 0 || y >= i32(params.height - 1)) {
-                    return;
-                }
-                
-                // Sample neighbors
-                let left = sample_velocity_prev(vec2<u32>(u32(x - 1), u32(y)));
-                let right = sample_velocity_prev(vec2<u32>(u32(x + 1), u32(y)));
-                let up = sample_velocity_prev(vec2<u32>(u32(x), u32(y - 1)));
-                let down = sample_velocity_prev(vec2<u32>(u32(x), u32(y + 1)));
-                
-                // Velocity diffusion with CPU scaling (no width*height factor)
-                let a = params.dt * params.viscosity;
-                let current = sample_velocity_prev(coord);
-                let diffused = (current + a * (left + right + up + down)) / (1.0 + 4.0 * a);
-                
-                set_velocity(coord, diffused);
-            }
-            
-            // Dye diffusion matching CPU implementation
-            @compute @workgroup_size(8, 8)
-            fn diffuse_dye(@builtin(global_invocation_id) global_id: vec3<u32>) {
-                if (global_id.x >= params.width || global_id.y >= params.height) {
-                    return;
-                }
-
-                let coord = vec2<u32>(global_id.x, global_id.y);
-                let x = i32(coord.x);
-                let y = i32(coord.y);
-
-                // Skip boundaries (handled separately)
-                if (x <= 0 || x >= i32(params.width - 1) || y <= 0 || y >= i32(params.height - 1)) {
-                    return;
-                }
-
-                // Sample neighbors from PREVIOUS dye buffer
-                let dye_left = sample_dye_prev(vec2<u32>(u32(x - 1), u32(y)));
-                let dye_right = sample_dye_prev(vec2<u32>(u32(x + 1), u32(y)));
-                let dye_up = sample_dye_prev(vec2<u32>(u32(x), u32(y - 1)));
-                let dye_down = sample_dye_prev(vec2<u32>(u32(x), u32(y + 1)));
-
-                // Dye diffusion with CPU scaling (no width*height factor)
-                let b = params.dt * params.diffusion;
-                let current = sample_dye_prev(coord);
-                let diffused = (current + b * (dye_left + dye_right + dye_up + dye_down)) / (1.0 + 4.0 * b);
-
-                set_dye(coord, diffused);
-            }
-            
-            // Velocity advection using previous velocity field (like CPU)
-            @compute @workgroup_size(8, 8)
-            fn advect_velocity(@builtin(global_invocation_id) global_id: vec3<u32>) {
-                if (global_id.x >= params.width || global_id.y >= params.height) {
-                    return;
-                }
-                
-                let coord = vec2<u32>(global_id.x, global_id.y);
-                let x = i32(coord.x);
-                let y = i32(coord.y);
-                
-                // Skip boundaries
-                if (x <= 0 || x >= i32(params.width - 1) || y <= 0 || y >= i32(params.height - 1)) {
-                    return;
-                }
-                
-                // Sample previous velocity (like CPU version)
-                let velocity_prev = sample_velocity_prev(coord);
-                
-                // Backtrace position matching CPU scaling (no width*height factor)
-                let src_x = f32(x) - params.dt * velocity_prev.x;
-                let src_y = f32(y) - params.dt * velocity_prev.y;
-                
-                // Clamp to valid range with border (same as CPU)
-                let clamped_x = max(0.5, min(src_x, f32(params.width - 1) - 0.5));
-                let clamped_y = max(0.5, min(src_y, f32(params.height - 1) - 0.5));
-                
-                // Bilinear interpolation matching CPU
-                let x0 = u32(floor(clamped_x));
-                let x1 = u32(min(f32(params.width - 1), f32(x0) + 1.0));
-                let y0 = u32(floor(clamped_y));
-                let y1 = u32(min(f32(params.height - 1), f32(y0) + 1.0));
-                
-                let tx = clamped_x - f32(x0);
-                let ty = clamped_y - f32(y0);
-                
-                // Advect velocity using previous velocity field (like CPU)
-                let v00 = sample_velocity_prev(vec2<u32>(x0, y0));
-                let v01 = sample_velocity_prev(vec2<u32>(x1, y0));
-                let v10 = sample_velocity_prev(vec2<u32>(x0, y1));
-                let v11 = sample_velocity_prev(vec2<u32>(x1, y1));
-                
-                let advected_velocity = (1.0 - tx) * (1.0 - ty) * v00
-                    + tx * (1.0 - ty) * v01
-                    + (1.0 - tx) * ty * v10
-                    + tx * ty * v11;
-                
-                set_velocity(coord, advected_velocity);
-            }
-            
-            // Dye advection using current velocity field
-            @compute @workgroup_size(8, 8)
-            fn advect_dye(@builtin(global_invocation_id) global_id: vec3<u32>) {
-                if (global_id.x >= params.width || global_id.y >= params.height) {
-                    return;
-                }
-
-                let coord = vec2<u32>(global_id.x, global_id.y);
-                let x = f32(global_id.x);
-                let y = f32(global_id.y);
-
-                // Get velocity
-                let vel = sample_velocity(coord);
-
-                // Backtrace
-                var src_x = x - params.dt * vel.x;
-                var src_y = y - params.dt * vel.y;
-
-                // Clamp
-                if (src_x < 0.5) { src_x = 0.5; }
-                if (src_x > f32(params.width) - 1.5) { src_x = f32(params.width) - 1.5; }
-                if (src_y < 0.5) { src_y = 0.5; }
-                if (src_y > f32(params.height) - 1.5) { src_y = f32(params.height) - 1.5; }
-
-                // Integer coordinates
-                let i0 = u32(src_x);
-                let j0 = u32(src_y);
-                let i1 = i0 + 1u;
-                let j1 = j0 + 1u;
-
-                // Fractions
-                let s1 = src_x - f32(i0);
-                let s0 = 1.0 - s1;
-                let t1 = src_y - f32(j0);
-                let t0 = 1.0 - t1;
-
-                // Sample and interpolate
-                let dye = s0 * (t0 * sample_dye_prev(vec2<u32>(i0, j0)) + t1 * sample_dye_prev(vec2<u32>(i0, j1)))
-                        + s1 * (t0 * sample_dye_prev(vec2<u32>(i1, j0)) + t1 * sample_dye_prev(vec2<u32>(i1, j1)));
-
-                set_dye(coord, dye);
-            }
-            
-            // Boundary conditions for velocity
-            @compute @workgroup_size(8, 8)
-            fn set_velocity_boundaries(@builtin(global_invocation_id) global_id: vec3<u32>) {
-                if (global_id.x >= params.width || global_id.y >= params.height) {
-                    return;
-                }
-                
-                let coord = vec2<u32>(global_id.x, global_id.y);
-                let x = i32(coord.x);
-                let y = i32(coord.y);
-                
-                // Set boundary velocity to zero (like CPU)
-                if (x == 0 || x == i32(params.width - 1) || y == 0 || y == i32(params.height - 1)) {
-                    set_velocity(coord, vec2<f32>(0.0));
-                }
-            }
-            
-            // Boundary conditions for dye - read from previous buffer to avoid race conditions
-            @compute @workgroup_size(8, 8)
-            fn set_dye_boundaries(@builtin(global_invocation_id) global_id: vec3<u32>) {
-                if (global_id.x >= params.width || global_id.y >= params.height) {
-                    return;
-                }
-
-                let coord = vec2<u32>(global_id.x, global_id.y);
-                let x = i32(coord.x);
-                let y = i32(coord.y);
-
-                // Set dye boundaries - read from dye (current after diffusion/advection)
-                if (x == 0) {
-                    let right = sample_dye(vec2<u32>(1, u32(y)));
-                    set_dye(coord, right);
-                } else if (x == i32(params.width - 1)) {
-                    let left = sample_dye(vec2<u32>(u32(params.width - 2), u32(y)));
-                    set_dye(coord, left);
-                } else if (y == 0) {
-                    let down = sample_dye(vec2<u32>(u32(x), 1));
-                    set_dye(coord, down);
-                } else if (y == i32(params.height - 1)) {
-                    let up = sample_dye(vec2<u32>(u32(x), u32(params.height - 2)));
-                    set_dye(coord, up);
-                }
-            }
-            
-            // Simple velocity projection (basic divergence-free enforcement)
-            @compute @workgroup_size(8, 8)
-            fn project_velocity(@builtin(global_invocation_id) global_id: vec3<u32>) {
-                if (global_id.x >= params.width || global_id.y >= params.height) {
-                    return;
-                }
-                
-                let coord = vec2<u32>(global_id.x, global_id.y);
-                let x = i32(coord.x);
-                let y = i32(coord.y);
-                
-                // Skip boundaries
-                if (x <= 0 || x >= i32(params.width - 1) || y <= 0 || y >= i32(params.height - 1)) {
-                    return;
-                }
-                
-                let h = 1.0 / f32(params.width);
-                
-                // Calculate divergence (like CPU)
-                let vel_left = sample_velocity(vec2<u32>(u32(x - 1), u32(y)));
-                let vel_right = sample_velocity(vec2<u32>(u32(x + 1), u32(y)));
-                let vel_up = sample_velocity(vec2<u32>(u32(x), u32(y - 1)));
-                let vel_down = sample_velocity(vec2<u32>(u32(x), u32(y + 1)));
-                
-                let divergence = -0.5 * h * (vel_right.x - vel_left.x + vel_down.y - vel_up.y);
-                
-                // Simple pressure correction (single iteration for now)
-                let pressure_correction = divergence * 0.25;
-                
-                // Apply pressure gradient correction
-                let current_vel = sample_velocity(coord);
-                let new_vel_x = current_vel.x - 0.5 * pressure_correction / h;
-                let new_vel_y = current_vel.y - 0.5 * pressure_correction / h;
-                
-                set_velocity(coord, vec2<f32>(new_vel_x, new_vel_y));
-            }
-            
-            // Copy velocity to velocity_prev (like CPU's copy_from_slice)
-            @compute @workgroup_size(8, 8)
-            fn copy_velocity_to_prev(@builtin(global_invocation_id) global_id: vec3<u32>) {
-                if (global_id.x >= params.width || global_id.y >= params.height) {
-                    return;
-                }
-
-                let coord = vec2<u32>(global_id.x, global_id.y);
-                let velocity = sample_velocity(coord);
-                textureStore(velocity_prev_texture, coord, vec4<f32>(velocity.x, velocity.y, 0.0, 1.0));
-            }
-
-            // Copy dye to dye_prev (for double buffering)
-            @compute @workgroup_size(8, 8)
-            fn copy_dye_to_prev(@builtin(global_invocation_id) global_id: vec3<u32>) {
-                if (global_id.x >= params.width || global_id.y >= params.height) {
-                    return;
-                }
-
-                let coord = vec2<u32>(global_id.x, global_id.y);
-                let dye = sample_dye(coord);
-                textureStore(dye_prev_texture, coord, vec4<f32>(dye.x, dye.y, dye.z, 1.0));
-            }
-        ";
-
-        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Functional Fluid Shader"),
-            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
-        });
-
-        // Create bind group layout
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Fluid Bind Group Layout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: Some(
-                            NonZeroU64::new(std::mem::size_of::<SimulationParams>() as u64)
-                                .unwrap(),
-                        ),
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::StorageTexture {
-                        access: wgpu::StorageTextureAccess::ReadWrite,
-                        format: wgpu::TextureFormat::Rgba32Float,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::StorageTexture {
-                        access: wgpu::StorageTextureAccess::ReadWrite,
-                        format: wgpu::TextureFormat::Rgba32Float,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 3,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::StorageTexture {
-                        access: wgpu::StorageTextureAccess::ReadWrite,
-                        format: wgpu::TextureFormat::Rgba32Float,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 4,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::StorageTexture {
-                        access: wgpu::StorageTextureAccess::ReadWrite,
-                        format: wgpu::TextureFormat::Rgba32Float,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                    },
-                    count: None,
-                },
-            ],
-        });
-
-        // Create bind group
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Fluid Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: params_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&velocity_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&velocity_prev_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::TextureView(&dye_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::TextureView(&dye_prev_view),
-                },
-            ],
-        });
-
-        // Create compute pipelines
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Fluid Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let diffuse_velocity_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Diffuse Velocity Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "diffuse_velocity",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
-
-        let diffuse_dye_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Diffuse Dye Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "diffuse_dye",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
-
-        let advect_velocity_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Advect Velocity Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "advect_velocity",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
-
-        let advect_dye_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Advect Dye Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "advect_dye",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
-
-        let set_velocity_boundaries_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Set Velocity Boundaries Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "set_velocity_boundaries",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
-
-        let set_dye_boundaries_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Set Dye Boundaries Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "set_dye_boundaries",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
-
-        let project_velocity_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Project Velocity Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "project_velocity",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
-
-        let copy_velocity_to_prev_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Copy Velocity to Prev Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "copy_velocity_to_prev",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
-
-        let copy_dye_to_prev_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Copy Dye to Prev Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "copy_dye_to_prev",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
+        let bind_group = create_bind_group(
+            &device,
+            &bind_group_layout,
+            [&params_buffer, &splat_buffer, &viscosity_buffer],
+            [&velocity_view, &velocity_prev_view, &dye_view, &dye_prev_view],
+        );
 
         Ok(Self {
             device,
             queue,
             width,
             height,
+            dt: params.dt,
+            viscosity: params.viscosity,
+            diffusion: params.diffusion,
+            boundary: BoundaryMode::NoSlip,
+            dye_decay: params.decay,
+            cell_aspect: params.cell_aspect,
+            capabilities,
+            pipeline_config,
+            shader_checks,
             params_buffer,
             velocity_texture,
             velocity_view,
@@ -749,12 +437,112 @@ impl FunctionalGPUFluid {
             project_velocity_pipeline,
             copy_velocity_to_prev_pipeline,
             copy_dye_to_prev_pipeline,
+            inject_pipeline,
+            splat_buffer,
+            viscosity_buffer,
             bind_group,
+            shader_error: None,
+            profiling: false,
+            stage_times: StageTimes::default(),
+            #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+            shader_watch: None,
         })
     }
 
+    /// Rebuild every pipeline from WGSL `source` for the configuration
+    /// already selected. If anything fails to compile, the running
+    /// pipelines are kept and the failing entry points are reported.
+    pub fn reload_shader(&mut self, source: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (layout, pipelines, checks) = build_pipelines(&self.device, self.pipeline_config, source);
+        if checks.iter().any(|c| c.error.is_some()) {
+            return Err(format_checks(&[(self.pipeline_config, checks)]).into());
+        }
+        [
+            self.copy_velocity_to_prev_pipeline,
+            self.diffuse_velocity_pipeline,
+            self.project_velocity_pipeline,
+            self.advect_velocity_pipeline,
+            self.set_velocity_boundaries_pipeline,
+            self.copy_dye_to_prev_pipeline,
+            self.diffuse_dye_pipeline,
+            self.advect_dye_pipeline,
+            self.set_dye_boundaries_pipeline,
+            self.inject_pipeline,
+        ] = pipelines.try_into().map_err(|_| "pipeline count mismatch")?;
+        // The pipelines were made against the new layout, so bind to it too
+        self.bind_group = create_bind_group(
+            &self.device,
+            &layout,
+            [&self.params_buffer, &self.splat_buffer, &self.viscosity_buffer],
+            [&self.velocity_view, &self.velocity_prev_view, &self.dye_view, &self.dye_prev_view],
+        );
+        Ok(())
+    }
+
+    /// Error from the last hot reload that failed to compile, cleared by the
+    /// next one that succeeds.
+    pub fn shader_error(&self) -> Option<&str> {
+        self.shader_error.as_deref()
+    }
+
+    /// Watch a WGSL file, normally `SHADER_PATH`, and rebuild the pipelines
+    /// from it whenever it changes, so shader edits show up without
+    /// recompiling the crate. Checked from `step`; debug builds only.
+    #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+    pub fn watch_shader(&mut self, path: impl Into<std::path::PathBuf>) {
+        self.shader_watch = Some(ShaderWatch {
+            path: path.into(),
+            // Unknown, so the first check compares the file to the build
+            modified: None,
+            checked: None,
+            source: SHADER_SOURCE.to_string(),
+        });
+    }
+
+    #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+    fn poll_shader_watch(&mut self) {
+        let Some(watch) = &mut self.shader_watch else {
+            return;
+        };
+        if watch.checked.is_some_and(|t| t.elapsed() < SHADER_POLL_INTERVAL) {
+            return;
+        }
+        watch.checked = Some(std::time::Instant::now());
+        let modified = std::fs::metadata(&watch.path).and_then(|m| m.modified()).ok();
+        if modified.is_none() || modified == watch.modified {
+            return;
+        }
+        watch.modified = modified;
+        let Ok(source) = std::fs::read_to_string(&watch.path) else {
+            return;
+        };
+        if source == watch.source {
+            return;
+        }
+        let path = watch.path.display().to_string();
+        watch.source = source.clone();
+        match self.reload_shader(&source) {
+            Ok(()) => {
+                eprintln!("GPU: reloaded {}", path);
+                self.shader_error = None;
+            }
+            Err(e) => {
+                eprintln!("GPU: keeping the previous shader, {} failed:\n{}", path, e);
+                self.shader_error = Some(e.to_string());
+            }
+        }
+    }
+
     pub fn step(&mut self) {
+        #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+        self.poll_shader_watch();
+
         // Full GPU fluid simulation
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("gpu_step", width = self.width, height = self.height).entered();
+        self.stage_times = StageTimes::default();
+        let start = now_seconds();
+        let mut mark = start;
 
         // Velocity: copy to prev
         self.run_compute_pass(&self.copy_velocity_to_prev_pipeline);
@@ -764,18 +552,23 @@ impl FunctionalGPUFluid {
             self.run_compute_pass(&self.diffuse_velocity_pipeline);
             self.run_compute_pass(&self.set_velocity_boundaries_pipeline);
         }
+        self.end_stage(Stage::Diffuse, &mut mark);
 
         // Velocity: project
         self.run_compute_pass(&self.project_velocity_pipeline);
         self.run_compute_pass(&self.set_velocity_boundaries_pipeline);
+        self.end_stage(Stage::Project, &mut mark);
 
-        // Velocity: advect
+        // Velocity: advect the diffused, projected field, as the CPU does
+        self.run_compute_pass(&self.copy_velocity_to_prev_pipeline);
         self.run_compute_pass(&self.advect_velocity_pipeline);
         self.run_compute_pass(&self.set_velocity_boundaries_pipeline);
+        self.end_stage(Stage::Advect, &mut mark);
 
         // Velocity: project again
         self.run_compute_pass(&self.project_velocity_pipeline);
         self.run_compute_pass(&self.set_velocity_boundaries_pipeline);
+        self.end_stage(Stage::Project, &mut mark);
 
         // Dye: copy to prev
         self.run_compute_pass(&self.copy_dye_to_prev_pipeline);
@@ -784,16 +577,60 @@ impl FunctionalGPUFluid {
         self.run_compute_pass(&self.diffuse_dye_pipeline);
         self.run_compute_pass(&self.set_dye_boundaries_pipeline);
         self.run_compute_pass(&self.copy_dye_to_prev_pipeline);
+        self.end_stage(Stage::Diffuse, &mut mark);
 
         // Dye: advect
         self.run_compute_pass(&self.advect_dye_pipeline);
         self.run_compute_pass(&self.set_dye_boundaries_pipeline);
+        self.end_stage(Stage::Advect, &mut mark);
 
         // Final sync
-        self.device.poll(wgpu::Maintain::Wait);
+        {
+            #[cfg(feature = "profiling")]
+            let _span = tracing::info_span!("gpu_sync").entered();
+            self.device.poll(wgpu::Maintain::Wait);
+        }
+        self.stage_times.total = now_seconds() - start;
+    }
+
+    // While profiling, wait for the queue and charge the time since `mark`
+    // to `stage`
+    fn end_stage(&mut self, stage: Stage, mark: &mut f64) {
+        if self.profiling {
+            #[cfg(feature = "profiling")]
+            let _span = stage.span().entered();
+            self.device.poll(wgpu::Maintain::Wait);
+            let now = now_seconds();
+            self.stage_times.add(stage, now - *mark);
+            *mark = now;
+        }
+    }
+
+    /// Time each stage of `step` separately. The queue is then drained after
+    /// every stage, which costs some overlap between them; in the browser it
+    /// can't be waited on, so the stages only measure their submission.
+    pub fn set_profiling(&mut self, on: bool) {
+        self.profiling = on;
+    }
+
+    /// Wall-clock time of the last `step`, split by stage while profiling.
+    pub fn stage_times(&self) -> StageTimes {
+        self.stage_times
+    }
+
+    /// Bytes of the field textures: velocity and dye, each with its copy.
+    pub fn memory_bytes(&self) -> usize {
+        4 * (self.width * self.height) as usize * self.pipeline_config.format.bytes_per_texel()
     }
 
     fn run_compute_pass(&self, pipeline: &ComputePipeline) {
+        self.dispatch(pipeline, self.width, self.height);
+    }
+
+    // Run `pipeline` over a `width` × `height` block of cells
+    fn dispatch(&self, pipeline: &ComputePipeline, width: u32, height: u32) {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("gpu_submit").entered();
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -808,9 +645,9 @@ impl FunctionalGPUFluid {
         compute_pass.set_pipeline(pipeline);
         compute_pass.set_bind_group(0, &self.bind_group, &[]);
 
-        let workgroup_size = 8;
-        let workgroup_count_x = (self.width + workgroup_size - 1) / workgroup_size;
-        let workgroup_count_y = (self.height + workgroup_size - 1) / workgroup_size;
+        let workgroup_size = self.pipeline_config.workgroup_size;
+        let workgroup_count_x = width.div_ceil(workgroup_size);
+        let workgroup_count_y = height.div_ceil(workgroup_size);
 
         compute_pass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
 
@@ -819,60 +656,78 @@ impl FunctionalGPUFluid {
         self.queue.submit(std::iter::once(encoder.finish()));
     }
 
-    pub fn gpu_add_dye(&mut self, x: u32, y: u32, color: (f32, f32, f32)) {
-        // Write directly to the texture using queue.write_texture instead of buffer copy
-        let dye_data = vec![color.0, color.1, color.2, 1.0];
+    /// Add dye to many cells at once, on top of what is already there. The
+    /// whole batch is one upload and one compute pass, where calling
+    /// `gpu_add_dye` per cell makes a pass for every cell.
+    pub fn add_dye_batch(&mut self, dye: &[(u32, u32, (f32, f32, f32))]) {
+        let splats: Vec<Splat> = dye
+            .iter()
+            .map(|&(x, y, (r, g, b))| Splat { x, y, kind: DYE_SPLAT, _padding: 0, value: [r, g, b, 0.0] })
+            .collect();
+        self.inject(&splats);
+    }
 
-        self.queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &self.dye_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d { x, y, z: 0 },
-                aspect: wgpu::TextureAspect::All,
-            },
-            bytemuck::cast_slice(&dye_data),
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * std::mem::size_of::<f32>() as u32),
-                rows_per_image: Some(1),
-            },
-            wgpu::Extent3d {
-                width: 1,
-                height: 1,
-                depth_or_array_layers: 1,
-            },
-        );
+    /// Add to the velocity of many cells at once, like `add_dye_batch`.
+    pub fn add_force_batch(&mut self, forces: &[(u32, u32, Vec2)]) {
+        let splats: Vec<Splat> = forces
+            .iter()
+            .map(|&(x, y, force)| Splat { x, y, kind: FORCE_SPLAT, _padding: 0, value: [force.x, force.y, 0.0, 0.0] })
+            .collect();
+        self.inject(&splats);
+    }
 
-        // Ensure GPU operations complete
-        self.device.poll(wgpu::Maintain::Wait);
+    // Upload splats and add them in with the inject pass, run over just the
+    // cells they cover. Queued only; the next step or readback waits for it.
+    fn inject(&mut self, splats: &[Splat]) {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!("gpu_upload", splats = splats.len()).entered();
+        let (width, height) = (self.width, self.height);
+        let inside: Vec<Splat> = splats.iter().copied().filter(|s| s.x < width && s.y < height).collect();
+        for chunk in inside.chunks(SPLAT_CAPACITY) {
+            let (min_x, min_y) = chunk.iter().fold((u32::MAX, u32::MAX), |(x, y), s| (x.min(s.x), y.min(s.y)));
+            let (max_x, max_y) = chunk.iter().fold((0, 0), |(x, y), s| (x.max(s.x), y.max(s.y)));
+            self.queue.write_buffer(&self.splat_buffer, 0, bytemuck::cast_slice(chunk));
+            let header = [chunk.len() as u32, min_x, min_y];
+            let offset = std::mem::offset_of!(SimulationParams, splat_count) as u64;
+            self.queue.write_buffer(&self.params_buffer, offset, bytemuck::cast_slice(&header));
+            self.dispatch(&self.inject_pipeline, max_x - min_x + 1, max_y - min_y + 1);
+        }
     }
 
-    pub fn gpu_add_force(&mut self, x: u32, y: u32, force: Vec2) {
-        // Write directly to the texture using queue.write_texture
-        let force_data = vec![force.x, force.y, 0.0, 1.0];
+    /// Add dye to one cell, on top of what is already there, like
+    /// `InteractiveFluid::add_dye`.
+    pub fn gpu_add_dye(&mut self, x: u32, y: u32, color: (f32, f32, f32)) {
+        self.add_dye_batch(&[(x, y, color)]);
+    }
 
-        self.queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &self.velocity_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d { x, y, z: 0 },
-                aspect: wgpu::TextureAspect::All,
-            },
-            bytemuck::cast_slice(&force_data),
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * std::mem::size_of::<f32>() as u32),
-                rows_per_image: Some(1),
-            },
-            wgpu::Extent3d {
-                width: 1,
-                height: 1,
-                depth_or_array_layers: 1,
-            },
-        );
+    /// Add to the velocity of one cell.
+    pub fn gpu_add_force(&mut self, x: u32, y: u32, force: Vec2) {
+        self.add_force_batch(&[(x, y, force)]);
+    }
 
-        // Ensure GPU operations complete
-        self.device.poll(wgpu::Maintain::Wait);
+    /// Add force in a circular area, fading as `1 - d²/r²` out to `radius`
+    /// the same as `InteractiveFluid::add_force`, so a brush pushes the
+    /// fluid alike on either backend.
+    pub fn add_force_radius(&mut self, x: u32, y: u32, force: Vec2, radius: f32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let r_sq = radius * radius;
+        let reach = radius as i32;
+        let mut forces = Vec::new();
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let dist_sq = (dx * dx + dy * dy) as f32;
+                if dist_sq <= r_sq {
+                    let falloff = 1.0 - dist_sq / r_sq.max(1.0);
+                    // Cells off the low edges wrap to huge coordinates, which the batch skips
+                    let px = x.wrapping_add_signed(dx);
+                    let py = y.wrapping_add_signed(dy);
+                    forces.push((px, py, force * falloff));
+                }
+            }
+        }
+        self.add_force_batch(&forces);
     }
 
     pub fn gpu_width(&self) -> u32 {
@@ -882,12 +737,120 @@ impl FunctionalGPUFluid {
         self.height
     }
 
+    pub fn gpu_dt(&self) -> f32 {
+        self.dt
+    }
+
+    /// Change timestep, viscosity and dye diffusion between frames; the next
+    /// step reads them from the uniform buffer. Each is checked against
+    /// `Param::range`, and nothing changes if one is out of range.
+    pub fn set_params(&mut self, dt: f32, viscosity: f32, diffusion: f32) -> Result<(), Box<dyn std::error::Error>> {
+        let values = [
+            Param::Dt.validate(dt)?,
+            Param::Viscosity.validate(viscosity)?,
+            Param::Diffusion.validate(diffusion)?,
+        ];
+        // dt, viscosity and diffusion sit next to each other in the uniform
+        let offset = std::mem::offset_of!(SimulationParams, dt) as u64;
+        self.queue.write_buffer(&self.params_buffer, offset, bytemuck::cast_slice(&values));
+        [self.dt, self.viscosity, self.diffusion] = values;
+        Ok(())
+    }
+
+    /// Scale the viscosity per cell, as `InteractiveFluid::viscosity_field`:
+    /// row-major factors for the whole grid, or none for 1 everywhere.
+    pub fn set_viscosity_field(&mut self, factors: &[f32]) -> Result<(), Box<dyn std::error::Error>> {
+        let cells = (self.width * self.height) as usize;
+        let factors = match factors.len() {
+            0 => vec![1.0; cells],
+            len if len == cells => factors.iter().map(|f| f.max(0.0)).collect(),
+            len => return Err(format!("viscosity field has {} cells, the grid {}", len, cells).into()),
+        };
+        self.queue.write_buffer(&self.viscosity_buffer, 0, bytemuck::cast_slice(&factors));
+        Ok(())
+    }
+
+    pub fn boundary(&self) -> BoundaryMode {
+        self.boundary
+    }
+
+    /// Change what happens at the edges. The shaders have no `Absorbing`
+    /// walls.
+    pub fn set_boundary(&mut self, mode: BoundaryMode) -> Result<(), Box<dyn std::error::Error>> {
+        let code = boundary_code(mode).ok_or_else(|| format!("the GPU solver has no {:?} walls", mode))?;
+        let offset = std::mem::offset_of!(SimulationParams, boundary) as u64;
+        self.queue.write_buffer(&self.params_buffer, offset, bytemuck::bytes_of(&code));
+        self.boundary = mode;
+        Ok(())
+    }
+
+    pub fn dye_decay(&self) -> [f32; 3] {
+        self.dye_decay
+    }
+
+    /// Fade each dye channel by `exp(-rate * dt)` per step, as
+    /// `InteractiveFluid::dye_fade_rates` gives them.
+    pub fn set_dye_decay(&mut self, rates: [f32; 3]) {
+        let rates = rates.map(|rate| rate.max(0.0));
+        let offset = std::mem::offset_of!(SimulationParams, decay) as u64;
+        self.queue.write_buffer(&self.params_buffer, offset, bytemuck::cast_slice(&rates));
+        self.dye_decay = rates;
+    }
+
+    pub fn cell_aspect(&self) -> f32 {
+        self.cell_aspect
+    }
+
+    /// Height of a cell over its width, as `InteractiveFluid::cell_aspect`.
+    pub fn set_cell_aspect(&mut self, aspect: f32) -> Result<(), Box<dyn std::error::Error>> {
+        if !(aspect.is_finite() && aspect > 0.0) {
+            return Err(format!("cell aspect must be positive, got {}", aspect).into());
+        }
+        let offset = std::mem::offset_of!(SimulationParams, cell_aspect) as u64;
+        self.queue.write_buffer(&self.params_buffer, offset, bytemuck::bytes_of(&aspect));
+        self.cell_aspect = aspect;
+        Ok(())
+    }
+
     pub fn get_dye_texture_view(&self) -> &TextureView {
         &self.dye_view
     }
 
+    pub fn pipeline_config(&self) -> PipelineConfig {
+        self.pipeline_config
+    }
+
+    pub fn capabilities(&self) -> &GpuCapabilities {
+        &self.capabilities
+    }
+
+    /// Capability matrix plus the shader checks for each configuration tried.
+    pub fn capability_report(&self) -> String {
+        format!(
+            "{}  selected: {} with {}x{} workgroups\n{}",
+            self.capabilities.report(),
+            self.pipeline_config.format.wgsl_name(),
+            self.pipeline_config.workgroup_size,
+            self.pipeline_config.workgroup_size,
+            format_checks(&self.shader_checks)
+        )
+    }
+
     pub async fn read_dye_data(&self) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
-        let bytes_per_pixel = 4 * std::mem::size_of::<f32>();
+        self.read_texture_data(&self.dye_texture).await
+    }
+
+    /// Read back velocity as interleaved RGBA texels (R = x, G = y).
+    pub async fn read_velocity_data(&self) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        self.read_texture_data(&self.velocity_texture).await
+    }
+
+    async fn read_texture_data(
+        &self,
+        texture: &Texture,
+    ) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let format = self.pipeline_config.format;
+        let bytes_per_pixel = format.bytes_per_texel();
         let bytes_per_row_unpadded = self.width as u64 * bytes_per_pixel as u64;
         
         // Align bytes per row to 256 bytes (WGSL requirement)
@@ -897,7 +860,7 @@ impl FunctionalGPUFluid {
         let buffer_size = bytes_per_row * self.height as u64;
 
         let read_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Dye Read Buffer"),
+            label: Some("Texture Read Buffer"),
             size: buffer_size,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
             mapped_at_creation: false,
@@ -906,12 +869,12 @@ impl FunctionalGPUFluid {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Read Dye Encoder"),
+                label: Some("Read Texture Encoder"),
             });
 
         encoder.copy_texture_to_buffer(
             wgpu::ImageCopyTexture {
-                texture: &self.dye_texture,
+                texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
@@ -934,35 +897,62 @@ impl FunctionalGPUFluid {
         self.queue.submit(std::iter::once(encoder.finish()));
 
         let buffer_slice = read_buffer.slice(..);
-        let (sender, receiver) = oneshot::channel();
+        let mapped = MapFuture::default();
+        let state = mapped.state.clone();
         buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-            let _ = sender.send(result);
+            let mut state = state.lock().unwrap();
+            state.0 = Some(result);
+            if let Some(waker) = state.1.take() {
+                waker.wake();
+            }
         });
 
+        // Native runs the callback here; the browser runs it on its own
         self.device.poll(wgpu::Maintain::Wait);
 
-        receiver.await??;
+        mapped.await?;
 
         let data = buffer_slice.get_mapped_range();
-        let all_data: &[f32] = bytemuck::cast_slice(&data);
-        
-        // Extract actual data skipping padding
-        let mut dye_data = Vec::with_capacity((self.width * self.height * 4) as usize);
-        let pixels_per_row = self.width as usize;
-        let floats_per_pixel = 4;
-        let floats_per_row_unpadded = pixels_per_row * floats_per_pixel;
-        let floats_per_row_padded = (bytes_per_row as usize) / std::mem::size_of::<f32>();
-        
+
+        // Extract actual data skipping row padding
+        let mut texel_data = Vec::with_capacity((self.width * self.height * 4) as usize);
+        let bytes_per_row_unpadded = bytes_per_row_unpadded as usize;
+
         for row in 0..self.height as usize {
-            let row_start = row * floats_per_row_padded;
-            let row_end = row_start + floats_per_row_unpadded;
-            
-            if row_end <= all_data.len() {
-                dye_data.extend_from_slice(&all_data[row_start..row_end]);
+            let row_start = row * bytes_per_row as usize;
+            let row_end = row_start + bytes_per_row_unpadded;
+
+            if row_end <= data.len() {
+                texel_data.extend(format.decode(&data[row_start..row_end]));
             }
         }
 
-        Ok(dye_data)
+        Ok(texel_data)
+    }
+}
+
+// The map_async result once the callback has run, and the waker to notify
+type MapState = (Option<Result<(), wgpu::BufferAsyncError>>, Option<Waker>);
+
+// Resolves once map_async's callback has run. Stands in for a runtime's
+// oneshot channel so readback works without tokio, including on the web.
+#[derive(Default)]
+struct MapFuture {
+    state: Arc<Mutex<MapState>>,
+}
+
+impl Future for MapFuture {
+    type Output = Result<(), wgpu::BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.0.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
     }
 }
 
@@ -972,7 +962,7 @@ impl crate::FluidSimulation for FunctionalGPUFluid {
     }
 
     fn add_force(&mut self, x: usize, y: usize, force: glam::Vec2) {
-        self.gpu_add_force(x as u32, y as u32, force)
+        self.add_force_radius(x as u32, y as u32, force, 3.0)
     }
 
     fn add_dye(&mut self, x: usize, y: usize, color: (f32, f32, f32)) {
@@ -986,3 +976,252 @@ impl crate::FluidSimulation for FunctionalGPUFluid {
         self.gpu_height() as usize
     }
 }
+
+impl crate::ConfigurableSimulation for FunctionalGPUFluid {
+    fn params(&self) -> Vec<(Param, f32)> {
+        vec![
+            (Param::Dt, self.dt),
+            (Param::Viscosity, self.viscosity),
+            (Param::Diffusion, self.diffusion),
+        ]
+    }
+
+    fn set_param(&mut self, param: Param, value: f32) -> Result<(), Box<dyn std::error::Error>> {
+        let (mut dt, mut viscosity, mut diffusion) = (self.dt, self.viscosity, self.diffusion);
+        match param {
+            Param::Dt => dt = value,
+            Param::Viscosity => viscosity = value,
+            Param::Diffusion => diffusion = value,
+            // Fading is per channel here, through set_dye_decay
+            Param::Dissipation => return Err("the GPU solver has no Dissipation parameter".into()),
+        }
+        self.set_params(dt, viscosity, diffusion)
+    }
+}
+
+// Readback for the conformance harness. On native, `device.poll(Wait)`
+// inside the read finishes the mapping, so the future is ready when first
+// polled.
+#[cfg(not(target_arch = "wasm32"))]
+impl crate::conformance::Observe for FunctionalGPUFluid {
+    fn fields(&mut self) -> Result<crate::conformance::Fields, Box<dyn std::error::Error>> {
+        let dye = block_on(self.read_dye_data())?;
+        let velocity = block_on(self.read_velocity_data())?;
+        Ok(crate::conformance::Fields {
+            width: self.width as usize,
+            height: self.height as usize,
+            dye: dye.chunks_exact(4).map(|t| t[0] + t[1] + t[2]).collect(),
+            velocity_x: velocity.chunks_exact(4).map(|t| t[0]).collect(),
+            velocity_y: velocity.chunks_exact(4).map(|t| t[1]).collect(),
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        std::thread::yield_now();
+    }
+}
+
+const SHADER_SOURCE: &str = include_str!("shaders/functional.wgsl");
+
+/// The WGSL file embedded in the crate, for `watch_shader` in debug builds.
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+pub const SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders/functional.wgsl");
+
+// How often `step` looks at a watched shader file
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+const SHADER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+struct ShaderWatch {
+    path: std::path::PathBuf,
+    modified: Option<std::time::SystemTime>,
+    checked: Option<std::time::Instant>,
+    // Last source built, so a save without changes compiles nothing
+    source: String,
+}
+
+// Compile `source` and every compute pipeline for `config`, capturing
+// validation errors per entry point instead of panicking at first use
+fn build_pipelines(
+    device: &Device,
+    config: PipelineConfig,
+    source: &str,
+) -> (BindGroupLayout, Vec<ComputePipeline>, Vec<EntryPointCheck>) {
+    let source = source
+        .replace("rgba32float", config.format.wgsl_name())
+        .replace(
+            "@workgroup_size(8, 8)",
+            &format!("@workgroup_size({0}, {0})", config.workgroup_size),
+        );
+
+    let mut checks = Vec::new();
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Functional Fluid Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    checks.push(EntryPointCheck {
+        entry_point: "<module>".to_string(),
+        error: pop_validation_error(device),
+    });
+
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Fluid Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(
+                        NonZeroU64::new(std::mem::size_of::<SimulationParams>() as u64)
+                            .unwrap(),
+                    ),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::ReadWrite,
+                    format: config.format.texture_format(),
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::ReadWrite,
+                    format: config.format.texture_format(),
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::ReadWrite,
+                    format: config.format.texture_format(),
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::ReadWrite,
+                    format: config.format.texture_format(),
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 5,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(NonZeroU64::new(std::mem::size_of::<Splat>() as u64).unwrap()),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 6,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(NonZeroU64::new(std::mem::size_of::<f32>() as u64).unwrap()),
+                },
+                count: None,
+            },
+        ],
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Fluid Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    checks.push(EntryPointCheck {
+        entry_point: "<layout>".to_string(),
+        error: pop_validation_error(device),
+    });
+
+    let pipelines = ENTRY_POINTS
+        .iter()
+        .map(|&entry_point| {
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader_module,
+                entry_point,
+            });
+            checks.push(EntryPointCheck {
+                entry_point: entry_point.to_string(),
+                error: pop_validation_error(device),
+            });
+            pipeline
+        })
+        .collect();
+
+    (bind_group_layout, pipelines, checks)
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    [params_buffer, splat_buffer, viscosity_buffer]: [&Buffer; 3],
+    [velocity, velocity_prev, dye, dye_prev]: [&TextureView; 4],
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Fluid Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(velocity),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(velocity_prev),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(dye),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(dye_prev),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: splat_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: viscosity_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
@@ -0,0 +1,112 @@
+//! Undo/redo for interactive editing: a bounded stack of states saved
+//! before each edit, and snapshots of the fluid fields to put in it.
+
+use crate::replay::Input;
+use crate::InteractiveFluid;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// States saved before each edit. `undo` and `redo` swap the current state
+/// for a saved one, so the caller decides what a state holds.
+#[derive(Debug, Clone)]
+pub struct UndoStack<T> {
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    /// Oldest states are dropped beyond this many
+    pub max_depth: usize,
+}
+
+impl<T> UndoStack<T> {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            max_depth,
+        }
+    }
+
+    /// Save the state from before an edit. A new edit forgets anything undone.
+    pub fn push(&mut self, state: T) {
+        self.redo.clear();
+        self.undo.push_back(state);
+        while self.undo.len() > self.max_depth.max(1) {
+            self.undo.pop_front();
+        }
+    }
+
+    /// The state before the last edit, keeping `current` for `redo`.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    /// The state the last `undo` left, keeping `current` for `undo`.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push_back(current);
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+/// Dye, velocity and temperature of an `InteractiveFluid`. Solver settings
+/// are left alone on restore.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FluidSnapshot {
+    pub width: usize,
+    pub height: usize,
+    pub dye: [Vec<f32>; 3],
+    pub velocity: [Vec<f32>; 2],
+    pub temperature: Vec<f32>,
+}
+
+impl FluidSnapshot {
+    pub fn capture(fluid: &InteractiveFluid) -> Self {
+        Self {
+            width: fluid.width,
+            height: fluid.height,
+            dye: [fluid.dye_r.clone(), fluid.dye_g.clone(), fluid.dye_b.clone()],
+            velocity: [fluid.velocity_x.clone(), fluid.velocity_y.clone()],
+            temperature: fluid.temperature.clone(),
+        }
+    }
+
+    /// Errors, leaving `fluid` untouched, if the grid was resized since capture.
+    pub fn restore(&self, fluid: &mut InteractiveFluid) -> Result<(), Box<dyn std::error::Error>> {
+        if (self.width, self.height) != (fluid.width, fluid.height) {
+            return Err(format!(
+                "snapshot is {}x{} but the grid is {}x{}",
+                self.width, self.height, fluid.width, fluid.height
+            )
+            .into());
+        }
+        if let Some(recording) = &mut fluid.recording {
+            recording.push(Input::Restore(self.clone()));
+        }
+        fluid.dye_r.copy_from_slice(&self.dye[0]);
+        fluid.dye_g.copy_from_slice(&self.dye[1]);
+        fluid.dye_b.copy_from_slice(&self.dye[2]);
+        fluid.velocity_x.copy_from_slice(&self.velocity[0]);
+        fluid.velocity_y.copy_from_slice(&self.velocity[1]);
+        fluid.temperature.copy_from_slice(&self.temperature);
+        Ok(())
+    }
+
+    /// Heap bytes held, for sizing the undo depth.
+    pub fn bytes(&self) -> usize {
+        6 * self.width * self.height * std::mem::size_of::<f32>()
+    }
+}
@@ -0,0 +1,271 @@
+//! Shared front door for remote inputs (chat, network, OSC): parses,
+//! validates and clamps commands, and rate-limits each source so no single
+//! sender, or flood of senders, can destabilize the simulation.
+
+use crate::commands::SimCommand;
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+// Rejects kept for inspection; older ones are dropped
+const REJECT_LOG_LEN: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngestLimits {
+    /// Largest force direction length; longer vectors are scaled down
+    pub max_force: f32,
+    /// Largest absolute heat amount
+    pub max_heat: f32,
+    /// Upper bound for each dye color channel
+    pub max_color: f32,
+    /// Sustained commands per second allowed from one source
+    pub per_source_rate: f32,
+    /// Commands a source may send at once before the rate applies
+    pub per_source_burst: f32,
+    /// Sustained commands per second across all sources
+    pub global_rate: f32,
+    pub global_burst: f32,
+    /// Remote `!clear` wipes everyone's work, so it is off by default
+    pub allow_clear: bool,
+}
+
+impl Default for IngestLimits {
+    fn default() -> Self {
+        Self {
+            max_force: 1.0,
+            max_heat: 5.0,
+            max_color: 1.0,
+            per_source_rate: 1.0,
+            per_source_burst: 3.0,
+            global_rate: 20.0,
+            global_burst: 40.0,
+            allow_clear: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    Parse(String),
+    NonFinite,
+    ClearDisabled,
+    RateLimited,
+    GlobalRateLimited,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Rejection::Parse(e) => write!(f, "{}", e),
+            Rejection::NonFinite => write!(f, "non-finite value"),
+            Rejection::ClearDisabled => write!(f, "clear is disabled for remote inputs"),
+            Rejection::RateLimited => write!(f, "source rate limit exceeded"),
+            Rejection::GlobalRateLimited => write!(f, "global rate limit exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectRecord {
+    pub source: String,
+    pub input: String,
+    pub reason: Rejection,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f32,
+    last: Instant,
+    // Whether the previous attempt was rate limited, to log only the first
+    limited: bool,
+}
+
+impl TokenBucket {
+    fn new(burst: f32, now: Instant) -> Self {
+        Self {
+            tokens: burst,
+            last: now,
+            limited: false,
+        }
+    }
+
+    fn refill(&mut self, rate: f32, burst: f32, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f32();
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        self.last = now;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ingestor {
+    pub limits: IngestLimits,
+    pub accepted: u64,
+    pub rejected: u64,
+    sources: HashMap<String, TokenBucket>,
+    global: Option<TokenBucket>,
+    rejects: VecDeque<RejectRecord>,
+}
+
+impl Ingestor {
+    pub fn new(limits: IngestLimits) -> Self {
+        Self {
+            limits,
+            accepted: 0,
+            rejected: 0,
+            sources: HashMap::new(),
+            global: None,
+            rejects: VecDeque::new(),
+        }
+    }
+
+    /// Parse, sanitize and rate-limit a text command from `source`.
+    pub fn submit_text(&mut self, source: &str, text: &str, now: Instant) -> Result<SimCommand, Rejection> {
+        let result = SimCommand::parse(text)
+            .map_err(Rejection::Parse)
+            .and_then(|command| self.sanitize(command))
+            .and_then(|command| self.admit(source, now).map(|_| command));
+        self.record(source, text, result)
+    }
+
+    /// Sanitize and rate-limit an already parsed command from `source`.
+    pub fn submit(&mut self, source: &str, command: SimCommand, now: Instant) -> Result<SimCommand, Rejection> {
+        let result = self
+            .sanitize(command)
+            .and_then(|command| self.admit(source, now).map(|_| command));
+        self.record(source, &format!("{:?}", command), result)
+    }
+
+    /// Reject non-finite values and clamp everything else into safe ranges.
+    pub fn sanitize(&self, command: SimCommand) -> Result<SimCommand, Rejection> {
+        let limits = &self.limits;
+        let finite = |values: &[f32]| values.iter().all(|v| v.is_finite());
+        let position = |x: f32, y: f32| (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+
+        match command {
+            SimCommand::Dye { x, y, color } => {
+                if !finite(&[x, y, color.0, color.1, color.2]) {
+                    return Err(Rejection::NonFinite);
+                }
+                let (x, y) = position(x, y);
+                let channel = |c: f32| c.clamp(0.0, limits.max_color);
+                Ok(SimCommand::Dye {
+                    x,
+                    y,
+                    color: (channel(color.0), channel(color.1), channel(color.2)),
+                })
+            }
+            SimCommand::Force { x, y, dx, dy } => {
+                if !finite(&[x, y, dx, dy]) {
+                    return Err(Rejection::NonFinite);
+                }
+                let (x, y) = position(x, y);
+                let length = (dx * dx + dy * dy).sqrt();
+                let scale = if length > limits.max_force { limits.max_force / length } else { 1.0 };
+                Ok(SimCommand::Force {
+                    x,
+                    y,
+                    dx: dx * scale,
+                    dy: dy * scale,
+                })
+            }
+            SimCommand::Heat { x, y, amount } => {
+                if !finite(&[x, y, amount]) {
+                    return Err(Rejection::NonFinite);
+                }
+                let (x, y) = position(x, y);
+                Ok(SimCommand::Heat {
+                    x,
+                    y,
+                    amount: amount.clamp(-limits.max_heat, limits.max_heat),
+                })
+            }
+            SimCommand::Clear if limits.allow_clear => Ok(SimCommand::Clear),
+            SimCommand::Clear => Err(Rejection::ClearDisabled),
+        }
+    }
+
+    /// Most recent rejects, oldest first.
+    pub fn recent_rejects(&self) -> impl Iterator<Item = &RejectRecord> {
+        self.rejects.iter()
+    }
+
+    /// Forget sources that have been idle long enough to be back at full
+    /// burst, so one-off senders don't accumulate forever.
+    pub fn prune(&mut self, now: Instant) {
+        let limits = &self.limits;
+        let full_after = limits.per_source_burst / limits.per_source_rate.max(1e-6);
+        self.sources
+            .retain(|_, bucket| now.saturating_duration_since(bucket.last).as_secs_f32() < full_after);
+    }
+
+    fn admit(&mut self, source: &str, now: Instant) -> Result<(), Rejection> {
+        let limits = &self.limits;
+
+        let global = self
+            .global
+            .get_or_insert_with(|| TokenBucket::new(limits.global_burst, now));
+        global.refill(limits.global_rate, limits.global_burst, now);
+        if global.tokens < 1.0 {
+            return Err(Rejection::GlobalRateLimited);
+        }
+
+        let bucket = self
+            .sources
+            .entry(source.to_string())
+            .or_insert_with(|| TokenBucket::new(limits.per_source_burst, now));
+        bucket.refill(limits.per_source_rate, limits.per_source_burst, now);
+        if bucket.tokens < 1.0 {
+            return Err(Rejection::RateLimited);
+        }
+
+        bucket.tokens -= 1.0;
+        global.tokens -= 1.0;
+        Ok(())
+    }
+
+    fn record(
+        &mut self,
+        source: &str,
+        input: &str,
+        result: Result<SimCommand, Rejection>,
+    ) -> Result<SimCommand, Rejection> {
+        // Only the first reject of a rate-limited streak is logged
+        let mut repeat = false;
+        let limited = matches!(result, Err(Rejection::RateLimited));
+        if let Some(bucket) = self.sources.get_mut(source) {
+            repeat |= limited && bucket.limited;
+            bucket.limited = limited;
+        }
+        let limited = matches!(result, Err(Rejection::GlobalRateLimited));
+        if let Some(bucket) = self.global.as_mut() {
+            repeat |= limited && bucket.limited;
+            bucket.limited = limited;
+        }
+
+        match &result {
+            Ok(_) => self.accepted += 1,
+            Err(reason) => {
+                self.rejected += 1;
+                if !repeat {
+                    eprintln!("Rejected input from {}: {} ({})", source, reason, input);
+                    if self.rejects.len() == REJECT_LOG_LEN {
+                        self.rejects.pop_front();
+                    }
+                    self.rejects.push_back(RejectRecord {
+                        source: source.to_string(),
+                        input: input.to_string(),
+                        reason: reason.clone(),
+                    });
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Default for Ingestor {
+    fn default() -> Self {
+        Self::new(IngestLimits::default())
+    }
+}
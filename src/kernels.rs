@@ -0,0 +1,311 @@
+//! Per-row kernels of the CPU solver. The solver runs them row-parallel;
+//! with the `simd` feature they also process eight cells per instruction.
+//! Both versions perform the same floating-point operations in the same
+//! order, so they give bitwise identical results.
+
+#[cfg(feature = "simd")]
+use wide::{CmpEq, f32x8};
+
+/// One red-black Gauss-Seidel update of interior row `y`:
+/// `(source + a[0] * (left + right) + a[1] * (up + down)) / denominator`
+/// for every cell whose `x + y` has the given parity; the two weights
+/// differ on grids with non-square cells. `row` is row `y` of the field
+/// being relaxed and `snapshot` the whole field before this half sweep.
+/// Returns the largest change.
+#[allow(clippy::too_many_arguments)]
+pub fn relax_row(
+    row: &mut [f32],
+    snapshot: &[f32],
+    source: &[f32],
+    y: usize,
+    width: usize,
+    a: [f32; 2],
+    denominator: f32,
+    parity: usize,
+) -> f32 {
+    #[cfg(feature = "simd")]
+    return relax_row_simd(row, snapshot, source, y, width, a, denominator, parity);
+    #[cfg(not(feature = "simd"))]
+    return relax_row_scalar(row, snapshot, source, y, width, a, denominator, parity);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn relax_row_scalar(
+    row: &mut [f32],
+    snapshot: &[f32],
+    source: &[f32],
+    y: usize,
+    width: usize,
+    a: [f32; 2],
+    denominator: f32,
+    parity: usize,
+) -> f32 {
+    let first = if (1 + y) % 2 == parity { 1 } else { 2 };
+    let mut max_change = 0.0f32;
+    for x in (first..width - 1).step_by(2) {
+        let idx = y * width + x;
+        let value = (source[idx]
+            + a[0] * (snapshot[idx - 1] + snapshot[idx + 1])
+            + a[1] * (snapshot[idx - width] + snapshot[idx + width]))
+            / denominator;
+        max_change = max_change.max((value - row[x]).abs());
+        row[x] = value;
+    }
+    max_change
+}
+
+/// `relax_row` on eight cells at a time. Every lane is computed and the
+/// cells of the other colour are blended back unchanged.
+#[cfg(feature = "simd")]
+#[allow(clippy::too_many_arguments)]
+pub fn relax_row_simd(
+    row: &mut [f32],
+    snapshot: &[f32],
+    source: &[f32],
+    y: usize,
+    width: usize,
+    a: [f32; 2],
+    denominator: f32,
+    parity: usize,
+) -> f32 {
+    let lane_parity = f32x8::from([0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0]);
+    let (a_x, a_y, denominator_v) = (f32x8::splat(a[0]), f32x8::splat(a[1]), f32x8::splat(denominator));
+    let mut max_change = f32x8::splat(0.0);
+
+    let mut x = 1;
+    while x + 8 < width {
+        let idx = y * width + x;
+        // Lane i holds cell x + i, which is active when (x + i + y) % 2 == parity
+        let first_active = ((parity + 2 - (x + y) % 2) % 2) as f32;
+        let active = lane_parity.cmp_eq(f32x8::splat(first_active));
+
+        let old = load(row, x);
+        let across = load(snapshot, idx - 1) + load(snapshot, idx + 1);
+        let along = load(snapshot, idx - width) + load(snapshot, idx + width);
+        let value = (load(source, idx) + a_x * across + a_y * along) / denominator_v;
+
+        max_change = max_change.max(active.blend((value - old).abs(), f32x8::splat(0.0)));
+        row[x..x + 8].copy_from_slice(&active.blend(value, old).to_array());
+        x += 8;
+    }
+
+    // Remaining cells one at a time
+    let mut rest = max_change.to_array().into_iter().fold(0.0f32, f32::max);
+    let first = x + (parity + 2 - (x + y) % 2) % 2;
+    for x in (first..width - 1).step_by(2) {
+        let idx = y * width + x;
+        let value = (source[idx]
+            + a[0] * (snapshot[idx - 1] + snapshot[idx + 1])
+            + a[1] * (snapshot[idx - width] + snapshot[idx + width]))
+            / denominator;
+        rest = rest.max((value - row[x]).abs());
+        row[x] = value;
+    }
+    rest
+}
+
+/// `relax_row` with both weights scaled per cell by `factor`, for a
+/// diffusion rate that varies over the grid:
+/// `(source + f * (a[0] * (left + right) + a[1] * (up + down))) / (1 + 2 f (a[0] + a[1]))`.
+/// Only used while the viscosity varies, so it has no SIMD version.
+#[allow(clippy::too_many_arguments)]
+pub fn relax_row_varying(
+    row: &mut [f32],
+    snapshot: &[f32],
+    source: &[f32],
+    factor: &[f32],
+    y: usize,
+    width: usize,
+    a: [f32; 2],
+    parity: usize,
+) -> f32 {
+    let first = if (1 + y) % 2 == parity { 1 } else { 2 };
+    let mut max_change = 0.0f32;
+    for x in (first..width - 1).step_by(2) {
+        let idx = y * width + x;
+        let (ax, ay) = (a[0] * factor[idx], a[1] * factor[idx]);
+        let value = (source[idx]
+            + ax * (snapshot[idx - 1] + snapshot[idx + 1])
+            + ay * (snapshot[idx - width] + snapshot[idx + width]))
+            / (1.0 + 2.0 * ax + 2.0 * ay);
+        max_change = max_change.max((value - row[x]).abs());
+        row[x] = value;
+    }
+    max_change
+}
+
+/// Semi-Lagrangian advection of interior row `y` for `N` fields carried by
+/// the same velocity: `rows[k]` receives `sources[k]` bilinearly sampled at
+/// the point `dt` back along `(vx, vy)`. `dt` is given per axis, in cells
+/// per unit velocity, so non-square cells backtrace `[dt, dt / aspect]`.
+#[allow(clippy::too_many_arguments)]
+pub fn advect_row<const N: usize>(
+    rows: [&mut [f32]; N],
+    sources: [&[f32]; N],
+    vx: &[f32],
+    vy: &[f32],
+    y: usize,
+    width: usize,
+    height: usize,
+    dt: [f32; 2],
+) {
+    #[cfg(feature = "simd")]
+    advect_row_simd(rows, sources, vx, vy, y, width, height, dt);
+    #[cfg(not(feature = "simd"))]
+    advect_row_scalar(rows, sources, vx, vy, y, width, height, dt);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn advect_row_scalar<const N: usize>(
+    rows: [&mut [f32]; N],
+    sources: [&[f32]; N],
+    vx: &[f32],
+    vy: &[f32],
+    y: usize,
+    width: usize,
+    height: usize,
+    dt: [f32; 2],
+) {
+    advect_cells(rows, sources, vx, vy, y, 1, width, height, dt);
+}
+
+/// `advect_row` on a torus: backtraces leaving one side of the interior
+/// re-enter on the other. The boundary ring must hold the wrapped interior,
+/// since interpolation next to an edge reads it.
+#[allow(clippy::too_many_arguments)]
+pub fn advect_row_periodic<const N: usize>(
+    mut rows: [&mut [f32]; N],
+    sources: [&[f32]; N],
+    vx: &[f32],
+    vy: &[f32],
+    y: usize,
+    width: usize,
+    height: usize,
+    dt: [f32; 2],
+) {
+    for x in 1..width - 1 {
+        let idx = y * width + x;
+        let src_x = wrap_interior(x as f32 - dt[0] * vx[idx], width);
+        let src_y = wrap_interior(y as f32 - dt[1] * vy[idx], height);
+
+        let x0 = src_x.floor() as usize;
+        let y0 = src_y.floor() as usize;
+        let sx = src_x - x0 as f32;
+        let sy = src_y - y0 as f32;
+
+        let (idx00, idx01) = (y0 * width + x0, y0 * width + x0 + 1);
+        let (idx10, idx11) = ((y0 + 1) * width + x0, (y0 + 1) * width + x0 + 1);
+        for (row, source) in rows.iter_mut().zip(sources) {
+            row[x] = (1.0 - sx) * (1.0 - sy) * source[idx00]
+                + sx * (1.0 - sy) * source[idx01]
+                + (1.0 - sx) * sy * source[idx10]
+                + sx * sy * source[idx11];
+        }
+    }
+}
+
+/// A coordinate folded into [1, size - 1), the interior of a wrapped axis.
+pub fn wrap_interior(p: f32, size: usize) -> f32 {
+    let n = (size - 2) as f32;
+    let folded = (p - 1.0).rem_euclid(n);
+    // rem_euclid can round up to n for tiny negative inputs, and when n is a
+    // power of two 1 + folded can round up to size - 1 just below the seam
+    if folded >= n { 1.0 } else { (1.0 + folded).min(((size - 1) as f32).next_down()) }
+}
+
+/// `advect_row` with the backtrace and interpolation weights computed eight
+/// cells at a time. The four corner samples are still gathered per lane.
+#[cfg(feature = "simd")]
+#[allow(clippy::too_many_arguments)]
+pub fn advect_row_simd<const N: usize>(
+    mut rows: [&mut [f32]; N],
+    sources: [&[f32]; N],
+    vx: &[f32],
+    vy: &[f32],
+    y: usize,
+    width: usize,
+    height: usize,
+    dt: [f32; 2],
+) {
+    let lanes = f32x8::from([0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+    let (dt_x, dt_y) = (f32x8::splat(dt[0]), f32x8::splat(dt[1]));
+    let (low, high_x, high_y) = (
+        f32x8::splat(0.5),
+        f32x8::splat((width - 1) as f32 - 0.5),
+        f32x8::splat((height - 1) as f32 - 0.5),
+    );
+    let one = f32x8::splat(1.0);
+
+    let mut x = 1;
+    while x + 8 < width {
+        let idx = y * width + x;
+        let src_x = (f32x8::splat(x as f32) + lanes - dt_x * load(vx, idx)).max(low).min(high_x);
+        let src_y = (f32x8::splat(y as f32) - dt_y * load(vy, idx)).max(low).min(high_y);
+
+        let (src_x_lanes, src_y_lanes) = (src_x.to_array(), src_y.to_array());
+        let x0 = src_x_lanes.map(|v| v.floor() as usize);
+        let y0 = src_y_lanes.map(|v| v.floor() as usize);
+        let sx = src_x - f32x8::from(x0.map(|v| v as f32));
+        let sy = src_y - f32x8::from(y0.map(|v| v as f32));
+        let weights = [
+            (one - sx) * (one - sy),
+            sx * (one - sy),
+            (one - sx) * sy,
+            sx * sy,
+        ];
+
+        for (row, source) in rows.iter_mut().zip(sources) {
+            let corner = |dx: usize, dy: usize| {
+                f32x8::from(std::array::from_fn::<f32, 8, _>(|i| source[(y0[i] + dy) * width + x0[i] + dx]))
+            };
+            let value = weights[0] * corner(0, 0) + weights[1] * corner(1, 0) + weights[2] * corner(0, 1)
+                + weights[3] * corner(1, 1);
+            row[x..x + 8].copy_from_slice(&value.to_array());
+        }
+        x += 8;
+    }
+
+    advect_cells(rows, sources, vx, vy, y, x, width, height, dt);
+}
+
+// Scalar advection of cells x_start..width - 1 in row y
+#[allow(clippy::too_many_arguments)]
+fn advect_cells<const N: usize>(
+    mut rows: [&mut [f32]; N],
+    sources: [&[f32]; N],
+    vx: &[f32],
+    vy: &[f32],
+    y: usize,
+    x_start: usize,
+    width: usize,
+    height: usize,
+    dt: [f32; 2],
+) {
+    for x in x_start..width - 1 {
+        let idx = y * width + x;
+
+        // Clamp to valid range
+        let src_x = (x as f32 - dt[0] * vx[idx]).max(0.5).min((width - 1) as f32 - 0.5);
+        let src_y = (y as f32 - dt[1] * vy[idx]).max(0.5).min((height - 1) as f32 - 0.5);
+
+        let x0 = src_x.floor() as usize;
+        let y0 = src_y.floor() as usize;
+        let sx = src_x - x0 as f32;
+        let sy = src_y - y0 as f32;
+
+        let (idx00, idx01) = (y0 * width + x0, y0 * width + x0 + 1);
+        let (idx10, idx11) = ((y0 + 1) * width + x0, (y0 + 1) * width + x0 + 1);
+        for (row, source) in rows.iter_mut().zip(sources) {
+            row[x] = (1.0 - sx) * (1.0 - sy) * source[idx00]
+                + sx * (1.0 - sy) * source[idx01]
+                + (1.0 - sx) * sy * source[idx10]
+                + sx * sy * source[idx11];
+        }
+    }
+}
+
+#[cfg(feature = "simd")]
+fn load(field: &[f32], start: usize) -> f32x8 {
+    let lanes: [f32; 8] = field[start..start + 8].try_into().expect("eight lanes");
+    f32x8::from(lanes)
+}
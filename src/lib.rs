@@ -1,15 +1,80 @@
 //! Core fluid simulation library for itsliquid
 
 pub mod analysis;
+pub mod audio;
+pub mod brush;
+pub mod calibration;
+pub mod chemistry;
+pub mod checkpoint;
+pub mod choreography;
+pub mod commands;
+pub mod conformance;
+pub mod container;
 pub mod desktop;
 pub mod desktop_interactive;
+pub mod drifters;
 pub mod export;
+pub mod fluid3d;
+pub mod fluid_better;
+pub mod fluid_dynamic;
 pub mod fluid_final;
+pub mod fluid_flip;
 pub mod fluid_interactive;
+pub mod fluid_levelset;
 pub mod fluid_proper;
 pub mod fluid_simple;
 pub mod fluid_working;
+pub mod frame;
+pub mod gallery;
+pub mod gates;
+pub mod gestures;
+pub mod history;
+pub mod ingest;
+pub mod kernels;
+pub mod mask;
+pub mod obstacle;
+pub mod output;
+pub mod palette;
+pub mod parity;
+pub mod particles;
 pub mod render;
+pub mod replay;
+pub mod rheology;
+pub mod scalar;
+pub mod scenarios;
+pub mod scene;
+pub mod session;
+pub mod solver;
+pub mod still;
+pub mod stress;
+pub mod timeline;
+pub mod timing;
+pub mod turbulence;
+pub mod validation;
+
+#[cfg(feature = "f16-dye")]
+pub mod dye_half;
+
+#[cfg(feature = "spectral")]
+pub mod spectral;
+
+#[cfg(feature = "scripting")]
+pub mod script;
+
+#[cfg(all(feature = "chat", not(target_arch = "wasm32")))]
+pub mod chat;
+
+#[cfg(all(feature = "control", not(target_arch = "wasm32")))]
+pub mod control;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod animation;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watchdog;
+
+#[cfg(feature = "gpu")]
+pub mod gpu_caps;
 
 #[cfg(feature = "gpu")]
 pub mod gpu_minimal;
@@ -29,6 +94,70 @@ pub trait FluidSimulation {
     fn height(&self) -> usize;
 }
 
+/// Solver parameters that can change while the simulation runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Param {
+    Dt,
+    Viscosity,
+    /// Diffusion of dye, or of density in one-channel solvers
+    Diffusion,
+    /// Fading of dye over time
+    Dissipation,
+}
+
+impl Param {
+    pub const ALL: [Param; 4] = [Param::Dt, Param::Viscosity, Param::Diffusion, Param::Dissipation];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Param::Dt => "Timestep",
+            Param::Viscosity => "Viscosity",
+            Param::Diffusion => "Diffusion",
+            Param::Dissipation => "Dissipation",
+        }
+    }
+
+    /// Accepted values, also used as slider bounds.
+    pub fn range(self) -> std::ops::RangeInclusive<f32> {
+        match self {
+            Param::Dt => 0.001..=1.0,
+            Param::Viscosity => 0.0..=0.1,
+            Param::Diffusion => 0.0..=0.01,
+            Param::Dissipation => 0.0..=2.0,
+        }
+    }
+
+    pub fn validate(self, value: f32) -> Result<f32, Box<dyn std::error::Error>> {
+        if self.range().contains(&value) {
+            Ok(value)
+        } else {
+            Err(format!("{} must be in {:?}, got {}", self.name(), self.range(), value).into())
+        }
+    }
+}
+
+/// A simulation whose parameters can be read and changed after construction.
+pub trait ConfigurableSimulation: FluidSimulation {
+    /// Every parameter the solver supports, with its current value.
+    fn params(&self) -> Vec<(Param, f32)>;
+
+    /// Errors if the solver lacks `param` or `value` is outside `Param::range`.
+    fn set_param(&mut self, param: Param, value: f32) -> Result<(), Box<dyn std::error::Error>>;
+
+    fn param(&self, param: Param) -> Option<f32> {
+        self.params().into_iter().find(|&(p, _)| p == param).map(|(_, value)| value)
+    }
+
+    /// Builder form of `set_param`.
+    fn with_param(mut self, param: Param, value: f32) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        Self: Sized,
+    {
+        self.set_param(param, value)?;
+        Ok(self)
+    }
+}
+
 // Feature-based implementation selection
 #[cfg(feature = "cpu")]
 pub type DefaultFluid = fluid_interactive::InteractiveFluid;
@@ -36,19 +165,65 @@ pub type DefaultFluid = fluid_interactive::InteractiveFluid;
 #[cfg(all(feature = "gpu", not(feature = "cpu")))]
 pub type DefaultFluid = gpu_functional::FunctionalGPUFluid;
 
-pub use analysis::{AnalysisRecorder, FluidMetrics};
+pub use analysis::{AnalysisRecorder, DyeMetrics, FluidMetrics};
+pub use audio::{AudioBand, AudioLevels, AudioLink};
+pub use brush::{BlendMode, Brush, BrushStroke, Falloff};
+pub use calibration::{Calibration, DeviceClass, Quality};
+pub use checkpoint::Checkpoint;
+pub use choreography::{Choreography, Routine};
+pub use commands::SimCommand;
+pub use container::Container;
 pub use desktop::DesktopApp;
 pub use desktop_interactive::InteractiveApp;
-pub use export::ImageExporter;
+pub use drifters::{Drifter, DrifterSet};
+pub use gates::{Gate, GateFlux, GateSet};
+pub use history::{FluidSnapshot, UndoStack};
+pub use ingest::{IngestLimits, Ingestor};
+pub use mask::Mask;
+pub use obstacle::{MovingObstacle, ObstacleShape};
+pub use particles::{Integrator, Particle, ParticleSystem, VelocityField, VelocityGrid};
+pub use export::{ImageExporter, PixelFormat, VideoCodec, VideoOptions};
+pub use fluid3d::{Fluid3D, FluidSimulation3D};
+#[allow(deprecated)]
 pub use fluid_final::FluidFinal;
-pub use fluid_interactive::InteractiveFluid;
+pub use fluid_flip::FlipFluid;
+pub use chemistry::Chemistry;
+pub use fluid_interactive::{BoundaryLeak, DivergenceStats, DyeStorage, InteractiveFluid, OwnDye, SanitizeEvents};
+pub use fluid_levelset::LevelSetFluid;
+#[allow(deprecated)]
 pub use fluid_proper::FluidSolver;
+#[allow(deprecated)]
 pub use fluid_working::WorkingFluid;
-pub use render::Renderer;
+pub use frame::FrameBuffer;
+pub use gallery::GalleryCard;
+pub use output::FrameSink;
+pub use palette::Palette;
+pub use parity::{FieldDiff, ParityReport, Tolerance, Tolerances};
+pub use render::{Colormap, RenderMode, Renderer};
+pub use replay::{Recording, ReplayTarget};
+pub use rheology::PowerLaw;
+pub use scalar::ScalarField;
+pub use scenarios::Scenario;
+pub use scene::{Emission, FORCE_REFERENCE_DT, PersistentElement, PersistentElementType, Scene, SceneParams};
+pub use session::SessionStats;
+pub use timeline::{Interpolation, Keyframe, Timeline, Track};
+pub use timing::{PerfStats, Stage, StageTimes, StepClock};
+pub use still::{Bloom, StillExport};
+pub use solver::{BoundaryMode, PoissonSolver, Scheme, Solver, SolverConfig};
+pub use turbulence::Turbulence;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use watchdog::{Watchdog, WatchdogConfig};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use animation::{AnimationExport, AnimationFormat};
 
 #[cfg(feature = "gpu")]
 pub use desktop_gpu::GPUInteractiveApp;
 
+#[cfg(feature = "f16-dye")]
+pub use dye_half::HalfDyeFluid;
+
 // WASM entry point
 #[cfg(target_arch = "wasm32")]
 use eframe::wasm_bindgen::{self, prelude::*};
@@ -66,15 +241,52 @@ pub fn start(canvas_id: String) -> Result<(), JsValue> {
 
     wasm_bindgen_futures::spawn_local(async move {
         log::info!("Creating WebRunner...");
-        let web_options = eframe::WebOptions::default();
+        #[cfg_attr(not(feature = "gpu"), allow(unused_mut))]
+        let mut web_options = eframe::WebOptions::default();
+        // As on desktop, give eframe's device what the GPU solver needs
+        #[cfg(feature = "gpu")]
+        {
+            web_options.wgpu_options.device_descriptor = std::sync::Arc::new(|adapter| {
+                let base_limits = if adapter.get_info().backend == wgpu::Backend::Gl {
+                    wgpu::Limits::downlevel_webgl2_defaults()
+                } else {
+                    wgpu::Limits::downlevel_defaults()
+                };
+                wgpu::DeviceDescriptor {
+                    label: Some("itsliquid wgpu device"),
+                    required_features: gpu_functional::REQUIRED_FEATURES & adapter.features(),
+                    required_limits: base_limits.using_resolution(adapter.limits()),
+                }
+            });
+        }
 
         match eframe::WebRunner::new()
             .start(
                 &canvas_id,
                 web_options,
-                Box::new(|_cc| {
+                Box::new(|cc| {
+                    // Prefer the GPU solver when the browser gave eframe a
+                    // WebGPU device that can run it
+                    #[cfg(feature = "gpu")]
+                    if let Some(render_state) = cc.wgpu_render_state.clone() {
+                        if render_state.adapter.get_info().backend == wgpu::Backend::BrowserWebGpu {
+                            match GPUInteractiveApp::try_with_render_state(render_state, 100, 100) {
+                                Ok(app) => {
+                                    log::info!("Creating GPUInteractiveApp on WebGPU...");
+                                    let app: Box<dyn eframe::App> = Box::new(app);
+                                    return app;
+                                }
+                                Err(e) => log::warn!("WebGPU can't run the GPU solver, using the CPU one: {}", e),
+                            }
+                        }
+                    }
                     log::info!("Creating InteractiveApp...");
-                    Box::new(InteractiveApp::new(100, 100))
+                    let mut app = InteractiveApp::new(100, 100);
+                    match cc.storage {
+                        Some(storage) => app.restore(storage),
+                        None => app.calibrate(),
+                    }
+                    Box::new(app)
                 }),
             )
             .await
@@ -1,35 +1,627 @@
-use itsliquid::{AnalysisRecorder, FluidFinal, FluidMetrics, ImageExporter};
-use std::path::Path;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use itsliquid::{
+    AnalysisRecorder, BlendMode, BoundaryMode, Checkpoint, ConfigurableSimulation, Emission, DrifterSet, FluidMetrics, GateSet, ImageExporter,
+    InteractiveFluid, Param, PersistentElement, PersistentElementType, Recording, RenderMode, Renderer, ReplayTarget,
+    Scenario, Scene, Scheme, Solver, Timeline, VideoCodec, VideoOptions,
+};
+use itsliquid::export::{FluidData, VideoEncoder};
+#[cfg(feature = "scripting")]
+use itsliquid::script::Script;
+use itsliquid::stress::{StressConfig, StressRun};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Interactive fluid simulation. Without a subcommand the app opens.
+#[derive(Parser)]
+#[command(name = "itsliquid", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Write solver and GPU spans to this Chrome trace file
+    #[cfg(feature = "profiling")]
+    #[arg(long, global = true)]
+    trace: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Step a simulation headless and print its metrics
+    Run {
+        #[command(flatten)]
+        sim: SimArgs,
+        /// Print metrics every N steps
+        #[arg(long, default_value_t = 50)]
+        every: usize,
+        #[command(flatten)]
+        checkpoint: CheckpointArgs,
+    },
+    /// Step a simulation and write image frames or a video
+    Render {
+        #[command(flatten)]
+        sim: SimArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        checkpoint: CheckpointArgs,
+    },
+    /// Time the solver and report its throughput
+    Bench {
+        #[command(flatten)]
+        sim: SimArgs,
+    },
+    /// Record metrics every few steps to a CSV file
+    Analyze {
+        #[command(flatten)]
+        sim: SimArgs,
+        /// Record every N steps
+        #[arg(long, default_value_t = 10)]
+        every: usize,
+        #[arg(long, short, default_value = "analysis.csv")]
+        out: PathBuf,
+    },
+    /// Rerun inputs recorded in the app and print metrics
+    Replay {
+        /// Recording saved with the app's Record button
+        path: PathBuf,
+        /// Print metrics every N steps
+        #[arg(long, default_value_t = 50)]
+        every: usize,
+        /// Write the final dye as a PNG
+        #[arg(long, short)]
+        out: Option<PathBuf>,
+    },
+    /// Forward-move solver test writing PNG frames and debug output
+    Test,
+    /// GPU solver test writing PNG frames (needs the gpu feature)
+    GpuTest,
+    /// Buoyant smoke encoded straight to a video clip
+    Video {
+        #[arg(default_value = "simulation.mp4")]
+        path: PathBuf,
+    },
+    /// Transport experiment with drifter trajectory export
+    Drifters,
+    /// Flux measurement across two gates
+    Gates,
+    /// Swirl rendered as dye, streamlines, LIC and pressure
+    Flow,
+    /// Error norms on the Taylor-Green vortex and the lid-driven cavity
+    Validate,
+    /// Randomized interactions with invariant checks
+    Stress {
+        #[arg(default_value_t = 1.0)]
+        minutes: f64,
+        /// Check invariants every K frames
+        #[arg(default_value_t = 10)]
+        check_every: usize,
+        /// Seed to replay; random if left out
+        seed: Option<u64>,
+    },
+}
+
+/// The simulation a headless command steps.
+#[derive(Args)]
+struct SimArgs {
+    /// Grid width in cells; a scene brings its own size
+    #[arg(long, default_value_t = 200)]
+    width: usize,
+    #[arg(long, default_value_t = 200)]
+    height: usize,
+    #[arg(long, default_value_t = 300)]
+    steps: usize,
+    /// Timestep, overriding the default or the scene's
+    #[arg(long)]
+    dt: Option<f32>,
+    /// Viscosity, overriding the default or the scene's
+    #[arg(long)]
+    viscosity: Option<f32>,
+    /// Share of the velocity lost per unit time, overriding the scene's
+    #[arg(long)]
+    damping: Option<f32>,
+    /// Edge behaviour, overriding the default walls or the scene's
+    #[arg(long, value_enum)]
+    boundary: Option<Boundary>,
+    /// Scene saved from the app; without one or a scenario a dye jet runs
+    /// from the left
+    #[arg(long)]
+    scene: Option<PathBuf>,
+    /// Built-in starting flow on the --width x --height grid
+    #[arg(long, value_enum, conflicts_with = "scene")]
+    scenario: Option<Preset>,
+    /// Exact FFT pressure and diffusion solves; needs periodic edges
+    #[cfg(feature = "spectral")]
+    #[arg(long)]
+    spectral: bool,
+    /// Rhai script run once before the first step, then its `frame(n)`
+    /// before every step
+    #[cfg(feature = "scripting")]
+    #[arg(long)]
+    script: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Directory for image frames, or the file for video formats
+    #[arg(long, short, default_value = ".")]
+    out: PathBuf,
+    #[arg(long, value_enum, default_value_t = Format::Png)]
+    format: Format,
+    #[arg(long, value_enum, default_value_t = View::Dye)]
+    view: View,
+    /// Write an image every N steps; videos get every step
+    #[arg(long, default_value_t = 10)]
+    every: usize,
+    /// Image width in pixels; the height follows the grid
+    #[arg(long, default_value_t = 800)]
+    size: u32,
+}
+
+/// Periodic checkpoints of a long run, and a checkpoint to carry on from.
+#[derive(Args)]
+struct CheckpointArgs {
+    /// Write a checkpoint every N steps; 0 never does
+    #[arg(long, default_value_t = 0)]
+    checkpoint_every: usize,
+    /// Checkpoint file, replaced each time one is written
+    #[arg(long, default_value = "checkpoint.json")]
+    checkpoint: PathBuf,
+    /// Carry on from a checkpoint; give the same scene, script and options
+    /// as the run that wrote it
+    #[arg(long)]
+    resume: Option<PathBuf>,
+}
+
+impl CheckpointArgs {
+    /// Steps already run: 0, or the checkpoint's frame once its state is
+    /// loaded into the run with `--resume`.
+    fn resume(&self, simulation: &mut Solver, driver: &mut Driver) -> Result<usize, Box<dyn std::error::Error>> {
+        let Some(path) = &self.resume else {
+            return Ok(0);
+        };
+        let checkpoint = Checkpoint::load(path)?;
+        let fluid = simulation.as_interactive_mut().expect("headless runs are interactive");
+        driver.elements = checkpoint.restore(fluid)?;
+        driver.fast_forward(checkpoint.frame);
+        println!("Resuming from {} at step {}", path.display(), checkpoint.frame);
+        Ok(checkpoint.frame as usize)
+    }
+
+    fn save_if_due(&self, step: usize, simulation: &Solver, driver: &Driver) -> Result<(), Box<dyn std::error::Error>> {
+        if self.checkpoint_every == 0 || !step.is_multiple_of(self.checkpoint_every) {
+            return Ok(());
+        }
+        let fluid = simulation.as_interactive().expect("headless runs are interactive");
+        Checkpoint::capture(step as u64, fluid, &driver.elements).save(&self.checkpoint)
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Png,
+    /// Raw float dye and velocity fields
+    Exr,
+    /// Raw float dye and velocity fields
+    Tif,
+    Mp4,
+    Webm,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Boundary {
+    NoSlip,
+    FreeSlip,
+    /// Opposite edges joined, for seamless loops
+    Periodic,
+    /// Flow leaves through the edges
+    Open,
+}
+
+impl Boundary {
+    fn mode(self) -> BoundaryMode {
+        match self {
+            Boundary::NoSlip => BoundaryMode::NoSlip,
+            Boundary::FreeSlip => BoundaryMode::FreeSlip,
+            Boundary::Periodic => BoundaryMode::Periodic,
+            Boundary::Open => BoundaryMode::Open,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Preset {
+    /// Kelvin-Helmholtz billows on periodic edges
+    ShearLayer,
+    /// Heavy dye sinking into light dye
+    RayleighTaylor,
+    VortexPair,
+    /// A column of heavy dye collapsing
+    DamBreak,
+}
+
+impl Preset {
+    fn scenario(self) -> Scenario {
+        match self {
+            Preset::ShearLayer => Scenario::ShearLayer,
+            Preset::RayleighTaylor => Scenario::RayleighTaylor,
+            Preset::VortexPair => Scenario::VortexPair,
+            Preset::DamBreak => Scenario::DamBreak,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum View {
+    Dye,
+    Intensity,
+    Streamlines,
+    Lic,
+    Pressure,
+}
+
+impl View {
+    fn mode(self) -> RenderMode {
+        match self {
+            View::Dye => RenderMode::Dye,
+            View::Intensity => RenderMode::DyeIntensity,
+            View::Streamlines => RenderMode::Streamlines,
+            View::Lic => RenderMode::Lic,
+            View::Pressure => RenderMode::Pressure,
+        }
+    }
+}
+
+impl SimArgs {
+    /// The simulation and what drives it every step.
+    fn build(&self) -> Result<(Solver, Driver), Box<dyn std::error::Error>> {
+        let (mut fluid, elements, timeline) = match &self.scene {
+            Some(path) => {
+                let scene = Scene::load(path)?;
+                (scene.simulation(), scene.elements.clone(), scene.timeline)
+            }
+            None => {
+                if self.width < 3 || self.height < 3 {
+                    return Err(format!("grid must be at least 3x3, got {}x{}", self.width, self.height).into());
+                }
+                let mut fluid = InteractiveFluid::new(self.width, self.height);
+                match self.scenario {
+                    Some(preset) => {
+                        preset.scenario().setup(&mut fluid);
+                        (fluid, Vec::new(), None)
+                    }
+                    None => (fluid, jet(self.width, self.height), None),
+                }
+            }
+        };
+        if let Some(dt) = self.dt {
+            fluid.set_param(Param::Dt, dt)?;
+        }
+        if let Some(viscosity) = self.viscosity {
+            fluid.set_param(Param::Viscosity, viscosity)?;
+        }
+        if let Some(damping) = self.damping {
+            if damping < 0.0 {
+                return Err(format!("--damping must not be negative, got {}", damping).into());
+            }
+            fluid.velocity_damping = damping;
+        }
+        if let Some(boundary) = self.boundary {
+            fluid.boundary = boundary.mode();
+        }
+        #[cfg(feature = "spectral")]
+        if self.spectral {
+            if fluid.boundary != BoundaryMode::Periodic {
+                return Err("--spectral needs periodic edges (--boundary periodic)".into());
+            }
+            fluid.enable_spectral();
+        }
+        #[cfg(feature = "scripting")]
+        let mut elements = elements;
+        #[cfg(feature = "scripting")]
+        let script = match &self.script {
+            Some(path) => {
+                let mut script = Script::load(path)?;
+                script.setup(&mut fluid, &mut elements)?;
+                print_script_output(&mut script);
+                Some(script)
+            }
+            None => None,
+        };
+        let driver = Driver {
+            elements,
+            timeline: timeline.map(|timeline| Timeline { playing: true, ..timeline }),
+            #[cfg(feature = "scripting")]
+            script,
+        };
+        Ok((Solver::from_interactive(fluid), driver))
+    }
+}
+
+/// What acts on a headless simulation before each step: the scene's
+/// elements, posed by its timeline if it has one, and the script's
+/// `frame(n)` with `--script`.
+struct Driver {
+    elements: Vec<PersistentElement>,
+    timeline: Option<Timeline>,
+    #[cfg(feature = "scripting")]
+    script: Option<Script>,
+}
+
+impl Driver {
+    fn advance(&mut self, simulation: &mut Solver) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(fluid) = simulation.as_interactive_mut() {
+            #[cfg(feature = "scripting")]
+            if let Some(script) = &mut self.script {
+                script.run_frame(fluid, &mut self.elements)?;
+                print_script_output(script);
+            }
+            if let Some(timeline) = &self.timeline {
+                timeline.pose(&mut self.elements);
+            }
+            for (i, elem) in self.elements.iter().enumerate() {
+                if self.timeline.as_ref().is_none_or(|timeline| timeline.enabled(i)) {
+                    elem.apply(fluid, None);
+                }
+            }
+            for elem in &mut self.elements {
+                elem.advance(fluid.dt);
+            }
+        }
+        if let Some(timeline) = &mut self.timeline {
+            timeline.advance();
+        }
+        simulation.step();
+        Ok(())
+    }
+
+    // Move the timeline and script on to where they were after `frames`
+    // steps, for a resumed run
+    fn fast_forward(&mut self, frames: u64) {
+        if let Some(timeline) = &mut self.timeline {
+            for _ in 0..frames {
+                timeline.advance();
+            }
+        }
+        #[cfg(feature = "scripting")]
+        if let Some(script) = &mut self.script {
+            script.frame = frames as i64;
+        }
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn print_script_output(script: &mut Script) {
+    for line in script.take_output() {
+        println!("{}", line);
+    }
+}
+
+// Dye and a rightward push from the middle of the left edge
+fn jet(width: usize, height: usize) -> Vec<PersistentElement> {
+    let (x, y) = (width as f32 / 8.0, height as f32 / 2.0);
+    vec![
+        PersistentElement {
+            element_type: PersistentElementType::DyeSource {
+                color: (1.0, 0.4, 0.1),
+                intensity: 1.0,
+                blend: BlendMode::Add,
+                emission: Emission::default(),
+            },
+            x,
+            y,
+            radius: 3.0,
+            audio: None,
+        },
+        PersistentElement {
+            element_type: PersistentElementType::ForceSource { direction: (24.0, 0.0), intensity: 1.0 },
+            x,
+            y,
+            radius: 3.0,
+            audio: None,
+        },
+    ]
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = std::env::args().collect();
-
-    if args.len() > 1 && args[1] == "test" {
-        // Run headless test and export PNGs
-        run_headless_test()?;
-    } else if args.len() > 1 && args[1] == "gpu-test" {
-        // Run GPU test
-        #[cfg(feature = "gpu")]
-        run_gpu_test()?;
-
-        #[cfg(not(feature = "gpu"))]
-        {
-            eprintln!("GPU feature not enabled. Build with --features gpu");
-            std::process::exit(1);
-        }
-    } else {
-        // Run GUI application
+    let cli = Cli::parse();
+    // Kept until main returns; the trace is flushed when it drops
+    #[cfg(feature = "profiling")]
+    let _trace = cli.trace.as_deref().map(itsliquid::timing::start_trace).transpose()?;
+    let Some(command) = cli.command else {
         run_gui_app();
+        return Ok(());
+    };
+
+    match command {
+        Command::Run { sim, every, checkpoint } => run_headless(&sim, every, &checkpoint)?,
+        Command::Render { sim, output, checkpoint } => run_render(&sim, &output, &checkpoint)?,
+        Command::Bench { sim } => run_bench(&sim)?,
+        Command::Analyze { sim, every, out } => run_analyze(&sim, every, &out)?,
+        Command::Replay { path, every, out } => run_replay(&path, every, out.as_deref())?,
+        Command::Test => run_headless_test()?,
+        Command::GpuTest => {
+            #[cfg(feature = "gpu")]
+            run_gpu_test()?;
+
+            #[cfg(not(feature = "gpu"))]
+            {
+                eprintln!("GPU feature not enabled. Build with --features gpu");
+                std::process::exit(1);
+            }
+        }
+        Command::Video { path } => run_video_export(&path)?,
+        Command::Drifters => run_drifter_experiment()?,
+        Command::Gates => run_gate_experiment()?,
+        Command::Flow => run_flow_visualization()?,
+        Command::Validate => run_validation(),
+        Command::Stress { minutes, check_every, seed } => {
+            run_stress(minutes, check_every, seed.unwrap_or_else(rand::random))?
+        }
     }
 
     Ok(())
 }
 
+fn run_headless(sim: &SimArgs, every: usize, checkpoint: &CheckpointArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut simulation, mut driver) = sim.build()?;
+    let start = checkpoint.resume(&mut simulation, &mut driver)?;
+    println!("Running {}x{} for {} steps...", simulation.width(), simulation.height(), sim.steps);
+
+    let mut recorder = AnalysisRecorder::new();
+    for step in start + 1..=sim.steps {
+        driver.advance(&mut simulation)?;
+        if step % every.max(1) == 0 || step == sim.steps {
+            let metrics = FluidMetrics::analyze(&simulation, step);
+            metrics.print_summary();
+            recorder.record_metrics(metrics);
+        }
+        checkpoint.save_if_due(step, &simulation, &driver)?;
+    }
+    recorder.print_trends();
+    Ok(())
+}
+
+fn run_replay(path: &Path, every: usize, out: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let recording = Recording::load(path)?;
+    let mut simulation = Solver::from_interactive(recording.simulation());
+    println!(
+        "Replaying {} inputs over {} steps on {}x{}...",
+        recording.events.len(),
+        recording.frames,
+        recording.width(),
+        recording.height()
+    );
+
+    let mut recorder = AnalysisRecorder::new();
+    for frame in 0..recording.frames {
+        for input in recording.events_at(frame) {
+            simulation.apply_input(input);
+        }
+        simulation.step();
+        let step = frame as usize + 1;
+        if step.is_multiple_of(every.max(1)) || frame + 1 == recording.frames {
+            let metrics = FluidMetrics::analyze(&simulation, step);
+            metrics.print_summary();
+            recorder.record_metrics(metrics);
+        }
+    }
+    for input in recording.events_at(recording.frames) {
+        simulation.apply_input(input);
+    }
+    recorder.print_trends();
+
+    if let Some(out) = out {
+        let renderer = Renderer::new(800, (800 * recording.height() / recording.width()).max(1) as u32);
+        let fluid = simulation.as_interactive().expect("replays are interactive");
+        renderer.render_interactive(fluid, RenderMode::Dye).save(out)?;
+        println!("Final frame written to {}", out.display());
+    }
+    Ok(())
+}
+
+fn run_render(sim: &SimArgs, output: &OutputArgs, checkpoint: &CheckpointArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut simulation, mut driver) = sim.build()?;
+    let (width, height) = (simulation.width(), simulation.height());
+    let renderer = Renderer::new(output.size, (output.size as usize * height / width).max(1) as u32);
+    let render = |simulation: &Solver| {
+        renderer.render_interactive(simulation.as_interactive().expect("headless runs are interactive"), output.view.mode())
+    };
+
+    let codec = match output.format {
+        Format::Mp4 => Some(VideoCodec::H264),
+        Format::Webm => Some(VideoCodec::Vp9),
+        _ => None,
+    };
+    if let Some(codec) = codec {
+        if checkpoint.resume.is_some() || checkpoint.checkpoint_every > 0 {
+            return Err("videos can't be resumed; checkpoint a render of image frames instead".into());
+        }
+        let options = VideoOptions {
+            codec,
+            ..Default::default()
+        };
+        let (w, h) = renderer.dimensions();
+        let mut encoder = VideoEncoder::start(&output.out, w, h, &options)?;
+        for _ in 0..sim.steps {
+            driver.advance(&mut simulation)?;
+            encoder.write_frame(&render(&simulation))?;
+        }
+        encoder.finish()?;
+        println!("Video written to {}", output.out.display());
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(&output.out)?;
+    let exporter = ImageExporter::new(output.size, output.size);
+    let start = checkpoint.resume(&mut simulation, &mut driver)?;
+    let mut written = 0;
+    for step in start + 1..=sim.steps {
+        driver.advance(&mut simulation)?;
+        if step % output.every.max(1) == 0 || step == sim.steps {
+            let prefix = format!("frame_{:04}", step);
+            let fluid = simulation.as_interactive().expect("headless runs are interactive");
+            match output.format {
+                Format::Exr => exporter.export_fields_float(fluid, &output.out, &prefix, "exr")?,
+                Format::Tif => exporter.export_fields_float(fluid, &output.out, &prefix, "tif")?,
+                _ => render(&simulation).save(output.out.join(format!("{}.png", prefix)))?,
+            }
+            written += 1;
+        }
+        // After the frame's image, so a resumed render never skips one
+        checkpoint.save_if_due(step, &simulation, &driver)?;
+    }
+    println!("Wrote {} frames to {}", written, output.out.display());
+    Ok(())
+}
+
+fn run_bench(sim: &SimArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut simulation, mut driver) = sim.build()?;
+    let cells = simulation.width() * simulation.height();
+    // One untimed step so first-touch allocation isn't measured
+    driver.advance(&mut simulation)?;
+
+    let start = Instant::now();
+    for _ in 0..sim.steps {
+        driver.advance(&mut simulation)?;
+    }
+    let seconds = start.elapsed().as_secs_f64();
+    println!(
+        "{}x{}: {} steps in {:.2}s, {:.3} ms/step, {:.1} Mcells/s",
+        simulation.width(),
+        simulation.height(),
+        sim.steps,
+        seconds,
+        seconds * 1000.0 / sim.steps.max(1) as f64,
+        (cells * sim.steps) as f64 / seconds.max(1e-9) / 1e6
+    );
+    Ok(())
+}
+
+fn run_analyze(sim: &SimArgs, every: usize, out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut simulation, mut driver) = sim.build()?;
+    let mut recorder = AnalysisRecorder::new();
+    recorder.record_frame(&simulation, 0);
+    for step in 1..=sim.steps {
+        driver.advance(&mut simulation)?;
+        if step % every.max(1) == 0 {
+            let leak = simulation.boundary_leak();
+            let metrics = FluidMetrics::analyze(&simulation, step);
+            recorder.record_metrics(match leak {
+                Some(leak) => metrics.with_boundary_leak(leak),
+                None => metrics,
+            });
+        }
+    }
+    recorder.export_csv(out)?;
+    recorder.print_trends();
+    println!("Wrote {} rows to {}", recorder.metrics_history.len(), out.display());
+    Ok(())
+}
+
 fn run_headless_test() -> Result<(), Box<dyn std::error::Error>> {
     println!("Running headless fluid simulation test with quantitative analysis...");
 
     // Use larger simulation for better visualization
-    let mut simulation = FluidFinal::new(200, 200);
+    let mut simulation = Solver::with_scheme(Scheme::ForwardMove, 200, 200);
     let exporter = ImageExporter::new(800, 800);
     let mut recorder = AnalysisRecorder::new();
 
@@ -82,9 +674,187 @@ fn run_headless_test() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn debug_visualize_density(simulation: &FluidFinal) {
-    let width = simulation.width;
-    let height = simulation.height;
+fn run_video_export(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Rendering simulation to {}...", path.display());
+
+    let mut simulation = Solver::with_scheme(Scheme::Buoyant, 200, 200);
+    for i in 0..40 {
+        simulation.add_density(80 + i, 100, 1.0);
+        simulation.add_velocity(80 + i, 100, glam::Vec2::new(0.0, -3.0));
+    }
+
+    let codec = match path.extension().and_then(|e| e.to_str()) {
+        Some("webm") => VideoCodec::Vp9,
+        _ => VideoCodec::H264,
+    };
+    let options = VideoOptions {
+        codec,
+        ..Default::default()
+    };
+
+    let exporter = ImageExporter::new(800, 800);
+    exporter.export_video(&mut simulation, 300, path, &options)?;
+
+    println!("Video written to {}", path.display());
+    Ok(())
+}
+
+fn run_drifter_experiment() -> Result<(), Box<dyn std::error::Error>> {
+    println!("Running drifter transport experiment...");
+
+    let mut simulation = InteractiveFluid::new(100, 100);
+    let mut drifters = DrifterSet::new();
+
+    // A lattice of drifters around a rightward jet
+    drifters.add_grid((30.0, 40.0), (50.0, 60.0), 4, 4, 0);
+
+    for frame in 1..=200 {
+        // Keep the jet running for the first half of the run
+        if frame <= 100 {
+            simulation.add_force(30, 50, glam::Vec2::new(20.0, 0.0), 4.0);
+            simulation.add_dye(30, 50, (1.0, 0.5, 0.2));
+        }
+        simulation.step();
+        drifters.step(&simulation, frame);
+    }
+
+    drifters.export_csv(Path::new("drifters.csv"))?;
+    drifters.export_geojson(Path::new("drifters.geojson"))?;
+
+    let renderer = Renderer::new(800, 800);
+    let overlay = renderer.render_trajectories(&drifters, simulation.width, simulation.height);
+    overlay.save("drifters.png")?;
+
+    for drifter in &drifters.drifters {
+        let displacement = drifter.displacement();
+        println!(
+            "  {}: displacement ({:+.2}, {:+.2}), path length {:.2}",
+            drifter.name,
+            displacement.x,
+            displacement.y,
+            drifter.path_length()
+        );
+    }
+
+    println!("Wrote drifters.csv, drifters.geojson and drifters.png");
+    Ok(())
+}
+
+fn run_validation() {
+    use itsliquid::validation::{LidDrivenCavity, TaylorGreen};
+
+    let vortex = TaylorGreen::default();
+    let report = vortex.run(40);
+    println!("Taylor-Green vortex, {} cells per period, t = {}:", vortex.period(), report.time);
+    println!(
+        "  Velocity error: L2 {:.6}, Linf {:.6}, relative L2 {:.4}",
+        report.velocity.l2, report.velocity.linf, report.velocity.relative_l2
+    );
+    println!("  Decay rate: {:.6} measured, {:.6} exact", report.measured_decay, report.exact_decay);
+
+    let cavity = LidDrivenCavity::default();
+    let report = cavity.run(600);
+    println!("Lid-driven cavity, Re {}, {} cells across, against Ghia et al.:", LidDrivenCavity::REYNOLDS, cavity.side());
+    println!("  u on the vertical centerline: L2 {:.4}, Linf {:.4}", report.u.l2, report.u.linf);
+    println!("  v on the horizontal centerline: L2 {:.4}, Linf {:.4}", report.v.l2, report.v.linf);
+}
+
+fn run_gate_experiment() -> Result<(), Box<dyn std::error::Error>> {
+    println!("Running gate flux experiment...");
+
+    let mut simulation = InteractiveFluid::new(100, 100);
+    let mut gates = GateSet::new();
+
+    // Two vertical gates across a rightward jet, drawn bottom-to-top so that
+    // rightward flow counts as positive
+    gates.add(
+        "near",
+        glam::Vec2::new(45.0, 65.0),
+        glam::Vec2::new(45.0, 35.0),
+    );
+    gates.add(
+        "far",
+        glam::Vec2::new(70.0, 65.0),
+        glam::Vec2::new(70.0, 35.0),
+    );
+
+    let mut recorder = AnalysisRecorder::new();
+    for frame in 1..=200 {
+        if frame <= 100 {
+            simulation.add_force(30, 50, glam::Vec2::new(20.0, 0.0), 4.0);
+            simulation.add_dye(30, 50, (1.0, 0.5, 0.2));
+        }
+        simulation.step();
+        gates.measure(&simulation, frame);
+        recorder.record_metrics(FluidMetrics::analyze_interactive(&simulation, frame).with_gates(&gates));
+
+        if frame % 50 == 0 {
+            println!("Frame {}:", frame);
+            gates.print_summary();
+        }
+    }
+
+    recorder.print_trends();
+    gates.export_csv(Path::new("gates.csv"))?;
+
+    let renderer = Renderer::new(800, 300);
+    renderer.render_gate_plot(&gates).save("gates_plot.png")?;
+
+    println!("Wrote gates.csv and gates_plot.png");
+    Ok(())
+}
+
+fn run_flow_visualization() -> Result<(), Box<dyn std::error::Error>> {
+    println!("Rendering flow visualization...");
+
+    let mut simulation = InteractiveFluid::new(120, 120);
+
+    // Two opposing jets that roll up into a vortex pair
+    for _ in 0..60 {
+        simulation.add_force(30, 50, glam::Vec2::new(25.0, 0.0), 5.0);
+        simulation.add_force(90, 70, glam::Vec2::new(-25.0, 0.0), 5.0);
+        simulation.add_dye(30, 50, (1.0, 0.3, 0.1));
+        simulation.add_dye(90, 70, (0.1, 0.4, 1.0));
+        simulation.step();
+    }
+
+    let renderer = Renderer::new(600, 600);
+    for (mode, path) in [
+        (RenderMode::Dye, "flow_dye.png"),
+        (RenderMode::Streamlines, "flow_streamlines.png"),
+        (RenderMode::Lic, "flow_lic.png"),
+        (RenderMode::Pressure, "flow_pressure.png"),
+    ] {
+        renderer.render_interactive(&simulation, mode).save(path)?;
+        println!("Wrote {}", path);
+    }
+    Ok(())
+}
+
+fn run_stress(minutes: f64, check_every: usize, seed: u64) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Stress testing for {} minute(s), checking every {} frames, seed {}...", minutes, check_every, seed);
+
+    let config = StressConfig {
+        seed,
+        duration: std::time::Duration::from_secs_f64(minutes * 60.0),
+        check_every,
+        ..StressConfig::default()
+    };
+    let report = StressRun::new(config).run()?;
+
+    println!(
+        "Passed: {} frames, {} actions, {} invariant checks in {:.1}s",
+        report.frames,
+        report.actions,
+        report.checks,
+        report.elapsed.as_secs_f32()
+    );
+    Ok(())
+}
+
+fn debug_visualize_density(simulation: &Solver) {
+    let width = simulation.width();
+    let height = simulation.height();
 
     // Show a wider section to see horizontal movement
     for y in 95..105 {
@@ -92,7 +862,7 @@ fn debug_visualize_density(simulation: &FluidFinal) {
             for x in 80..120 {
                 if x < width {
                     let idx = y * width + x;
-                    let density = simulation.density[idx];
+                    let density = simulation.density()[idx];
                     if density > 0.5 {
                         print!("██");
                     } else if density > 0.1 {
@@ -112,9 +882,9 @@ fn debug_visualize_density(simulation: &FluidFinal) {
     println!();
 }
 
-fn debug_visualize_velocity(simulation: &FluidFinal) {
-    let width = simulation.width;
-    let height = simulation.height;
+fn debug_visualize_velocity(simulation: &Solver) {
+    let width = simulation.width();
+    let height = simulation.height();
 
     // Show velocity magnitude
     for y in 95..105 {
@@ -122,8 +892,8 @@ fn debug_visualize_velocity(simulation: &FluidFinal) {
             for x in 80..120 {
                 if x < width {
                     let idx = y * width + x;
-                    let vel_x = simulation.velocity_x[idx];
-                    let vel_y = simulation.velocity_y[idx];
+                    let vel_x = simulation.velocity_x()[idx];
+                    let vel_y = simulation.velocity_y()[idx];
                     let vel_mag = (vel_x * vel_x + vel_y * vel_y).sqrt();
 
                     if vel_mag > 0.5 {
@@ -144,20 +914,58 @@ fn debug_visualize_velocity(simulation: &FluidFinal) {
 }
 
 fn run_gui_app() {
+    let icon = egui::IconData {
+        rgba: itsliquid::frame::icon_rgba(64),
+        width: 64,
+        height: 64,
+    };
+    // The inner size is only a first-run default: with persist_window, eframe
+    // restores the last size and position from its storage
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([800.0, 800.0])
-            .with_title("itsliquid - Interactive Fluid Simulation"),
+            .with_title("itsliquid - Interactive Fluid Simulation")
+            .with_app_id("itsliquid")
+            .with_icon(icon),
+        persist_window: true,
         ..Default::default()
     };
 
     // Use GPU version if feature is enabled, otherwise use CPU version
     #[cfg(feature = "gpu")]
     {
+        use itsliquid::gpu_functional::REQUIRED_FEATURES;
+        use std::sync::Arc;
+
+        // Render with wgpu and give its device the solver's features, so the
+        // simulation can share it and draw its dye texture without readback
+        let mut options = options;
+        options.renderer = eframe::Renderer::Wgpu;
+        options.wgpu_options.device_descriptor = Arc::new(|adapter| {
+            let base_limits = if adapter.get_info().backend == wgpu::Backend::Gl {
+                wgpu::Limits::downlevel_webgl2_defaults()
+            } else {
+                wgpu::Limits::default()
+            };
+            wgpu::DeviceDescriptor {
+                label: Some("itsliquid wgpu device"),
+                required_features: REQUIRED_FEATURES & adapter.features(),
+                required_limits: wgpu::Limits {
+                    max_texture_dimension_2d: 8192,
+                    ..base_limits
+                },
+            }
+        });
+
         eframe::run_native(
             "itsliquid",
             options,
-            Box::new(|_cc| Box::new(itsliquid::GPUInteractiveApp::new(100, 100))),
+            Box::new(|cc| {
+                Box::new(match cc.wgpu_render_state.clone() {
+                    Some(render_state) => itsliquid::GPUInteractiveApp::with_render_state(render_state, 100, 100),
+                    None => itsliquid::GPUInteractiveApp::new(100, 100),
+                })
+            }),
         )
         .unwrap();
     }
@@ -167,7 +975,14 @@ fn run_gui_app() {
         eframe::run_native(
             "itsliquid",
             options,
-            Box::new(|_cc| Box::new(itsliquid::InteractiveApp::new(100, 100))),
+            Box::new(|cc| {
+                let mut app = itsliquid::InteractiveApp::new(100, 100);
+                match cc.storage {
+                    Some(storage) => app.restore(storage),
+                    None => app.calibrate(),
+                }
+                Box::new(app)
+            }),
         )
         .unwrap();
     }
@@ -189,6 +1004,7 @@ fn run_gpu_test() -> Result<(), Box<dyn std::error::Error>> {
         let mut simulation = FunctionalGPUFluid::new(width, height).await?;
 
         println!("GPU simulation initialized: {}x{}", width, height);
+        print!("{}", simulation.capability_report());
 
         // Add initial fluid WITH velocity to test movement
         println!("Adding initial dye with velocity...");
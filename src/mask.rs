@@ -0,0 +1,101 @@
+//! Black-and-white stencils resampled to the simulation grid: obstacles the
+//! flow has to go around, or emitters that pour dye from their white cells,
+//! as in logo-reveal scenes.
+
+use crate::InteractiveFluid;
+use image::GrayImage;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mask {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major, `width * height` cells; true where the image was white
+    pub cells: Vec<bool>,
+}
+
+impl Mask {
+    /// A mask with no cells set.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![false; width * height],
+        }
+    }
+
+    /// Load an image as a grayscale stencil, with transparent pixels
+    /// counted as black. Resample it with `from_gray`.
+    pub fn load_image(path: &std::path::Path) -> Result<GrayImage, Box<dyn std::error::Error>> {
+        let img = image::open(path)?.to_luma_alpha8();
+        let (w, h) = img.dimensions();
+        if w == 0 || h == 0 {
+            return Err(format!("{} is empty", path.display()).into());
+        }
+        Ok(GrayImage::from_fn(w, h, |x, y| {
+            let [luma, alpha] = img.get_pixel(x, y).0;
+            image::Luma([(luma as u16 * alpha as u16 / 255) as u8])
+        }))
+    }
+
+    /// Resample `img` to a `width` × `height` grid: each cell averages the
+    /// pixels it covers and is set when that is brighter than `threshold`,
+    /// in `0..=1`.
+    pub fn from_gray(img: &GrayImage, width: usize, height: usize, threshold: f32) -> Self {
+        let (img_w, img_h) = (img.width() as usize, img.height() as usize);
+        let mut mask = Self::new(width, height);
+        if img_w == 0 || img_h == 0 {
+            return mask;
+        }
+        // Pixels [start, end) along one axis for cell i; at least one pixel
+        // so grids finer than the image still sample it
+        let span = |i: usize, cells: usize, pixels: usize| {
+            let start = (i * pixels / cells).min(pixels - 1);
+            (start, ((i + 1) * pixels / cells).max(start + 1))
+        };
+        for y in 0..height {
+            let (y0, y1) = span(y, height, img_h);
+            for x in 0..width {
+                let (x0, x1) = span(x, width, img_w);
+                let mut sum = 0u32;
+                for py in y0..y1 {
+                    for px in x0..x1 {
+                        sum += img.get_pixel(px as u32, py as u32).0[0] as u32;
+                    }
+                }
+                let mean = sum as f32 / ((x1 - x0) * (y1 - y0)) as f32 / 255.0;
+                mask.cells[y * width + x] = mean > threshold;
+            }
+        }
+        mask
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.cells[y * self.width + x]
+    }
+
+    /// Number of cells set.
+    pub fn count(&self) -> usize {
+        self.cells.iter().filter(|&&set| set).count()
+    }
+
+    /// Set every cell set in `other`, a mask of the same grid.
+    pub fn union(&mut self, other: &Mask) {
+        for (cell, &set) in self.cells.iter_mut().zip(&other.cells) {
+            *cell |= set;
+        }
+    }
+
+    /// Swap set and clear cells, e.g. for a black logo on white.
+    pub fn invert(&mut self) {
+        for cell in &mut self.cells {
+            *cell = !*cell;
+        }
+    }
+
+    /// Add `color` to every set cell, as a dye emitter does once per step.
+    pub fn emit_dye(&self, fluid: &mut InteractiveFluid, color: (f32, f32, f32)) {
+        for (i, _) in self.cells.iter().enumerate().filter(|&(_, &set)| set) {
+            fluid.add_dye(i % self.width, i / self.width, color);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Moving obstacles: rigid discs and paddles that travel and spin through
+//! the fluid. Unlike the static `Mask` obstacles, which hold the flow at
+//! rest, the cells one covers take its surface velocity on every step, so a
+//! dragged disc pushes the fluid aside and a spinning paddle stirs it.
+//! A floating obstacle is also pushed back: each step it takes up the
+//! momentum of the fluid it covers, so currents carry it like a leaf.
+
+use glam::Vec2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObstacleShape {
+    Disc { radius: f32 },
+    /// A bar through the center along the obstacle's angle, `length` end to
+    /// end and `width` across
+    Paddle { length: f32, width: f32 },
+}
+
+impl ObstacleShape {
+    // Farthest a covered point can be from the center
+    fn reach(self) -> f32 {
+        match self {
+            ObstacleShape::Disc { radius } => radius,
+            ObstacleShape::Paddle { length, width } => 0.5 * length.hypot(width),
+        }
+    }
+}
+
+/// A rigid obstacle in grid cells. Positive `spin` turns clockwise on
+/// screen (y down), in radians per unit time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovingObstacle {
+    pub shape: ObstacleShape,
+    pub center: Vec2,
+    /// Cells per unit time
+    pub velocity: Vec2,
+    /// Radians, turning a paddle; a disc looks the same at any angle
+    pub angle: f32,
+    pub spin: f32,
+    /// Density relative to the fluid for an obstacle the flow moves, see
+    /// `float`; `None` keeps whatever velocity and spin it is given
+    pub density: Option<f32>,
+}
+
+impl MovingObstacle {
+    pub fn new(shape: ObstacleShape, center: Vec2) -> Self {
+        Self {
+            shape,
+            center,
+            velocity: Vec2::ZERO,
+            angle: 0.0,
+            spin: 0.0,
+            density: None,
+        }
+    }
+
+    /// A floating obstacle at rest, `density` times as heavy as the fluid
+    /// it displaces.
+    pub fn floating(shape: ObstacleShape, center: Vec2, density: f32) -> Self {
+        Self { density: Some(density), ..Self::new(shape, center) }
+    }
+
+    /// This obstacle on a grid `sx` and `sy` times the size; its shape
+    /// follows the grid's width.
+    pub fn rescaled(&self, sx: f32, sy: f32) -> Self {
+        let shape = match self.shape {
+            ObstacleShape::Disc { radius } => ObstacleShape::Disc { radius: radius * sx },
+            ObstacleShape::Paddle { length, width } => ObstacleShape::Paddle { length: length * sx, width: width * sx },
+        };
+        Self {
+            shape,
+            center: self.center * Vec2::new(sx, sy),
+            velocity: self.velocity * Vec2::new(sx, sy),
+            ..*self
+        }
+    }
+
+    /// Move and turn for a step of `dt`.
+    pub fn advance(&mut self, dt: f32) {
+        self.center += self.velocity * dt;
+        self.angle += self.spin * dt;
+    }
+
+    /// Stop at the edges of a `width` x `height` grid, so a floating
+    /// obstacle can't drift off it.
+    pub fn keep_inside(&mut self, width: usize, height: usize) {
+        let reach = self.shape.reach();
+        let max = Vec2::new(width as f32 - 1.0, height as f32 - 1.0);
+        for axis in 0..2 {
+            let (low, high) = (reach.min(max[axis] / 2.0), (max[axis] - reach).max(max[axis] / 2.0));
+            if self.center[axis] < low {
+                self.center[axis] = low;
+                self.velocity[axis] = self.velocity[axis].max(0.0);
+            } else if self.center[axis] > high {
+                self.center[axis] = high;
+                self.velocity[axis] = self.velocity[axis].min(0.0);
+            }
+        }
+    }
+
+    /// Take up the momentum of the fluid under a floating obstacle: its
+    /// velocity and spin become the average of its own and the covered
+    /// cells', weighted by `density`. Pressure and drag both act through the
+    /// projected velocities, so a current carries it along and a vortex
+    /// turns it. Does nothing without a density or any covered cell.
+    pub fn float(&mut self, velocity_x: &[f32], velocity_y: &[f32], width: usize, height: usize) {
+        let Some(density) = self.density else {
+            return;
+        };
+        let (mut momentum, mut angular, mut inertia, mut count) = (Vec2::ZERO, 0.0, 0.0, 0.0);
+        for idx in self.cells(width, height) {
+            let offset = Vec2::new((idx % width) as f32, (idx / width) as f32) - self.center;
+            let flow = Vec2::new(velocity_x[idx], velocity_y[idx]);
+            momentum += flow;
+            angular += offset.perp().dot(flow);
+            inertia += offset.length_squared();
+            count += 1.0;
+        }
+        if count == 0.0 {
+            return;
+        }
+        let density = density.max(0.0);
+        self.velocity = (self.velocity * density * count + momentum) / ((density + 1.0) * count);
+        if inertia > 0.0 {
+            self.spin = (self.spin * density * inertia + angular) / ((density + 1.0) * inertia);
+        }
+    }
+
+    /// Set the velocity that carries the obstacle to `target` over the next
+    /// `dt` of simulated time, e.g. to follow the pointer.
+    pub fn steer_to(&mut self, target: Vec2, dt: f32) {
+        self.velocity = if dt > 0.0 { (target - self.center) / dt } else { Vec2::ZERO };
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        let offset = point - self.center;
+        match self.shape {
+            ObstacleShape::Disc { radius } => offset.length_squared() <= radius * radius,
+            ObstacleShape::Paddle { length, width } => {
+                let along = Vec2::from_angle(-self.angle).rotate(offset);
+                along.x.abs() <= 0.5 * length && along.y.abs() <= 0.5 * width
+            }
+        }
+    }
+
+    /// Velocity of the obstacle's material at `point`: its own velocity
+    /// plus the turning of its spin.
+    pub fn surface_velocity(&self, point: Vec2) -> Vec2 {
+        self.velocity + self.spin * (point - self.center).perp()
+    }
+
+    /// Indices of the cells of a `width` x `height` grid whose centers the
+    /// obstacle covers.
+    pub fn cells(&self, width: usize, height: usize) -> Vec<usize> {
+        let reach = self.shape.reach();
+        let span = |center: f32, len: usize| {
+            ((center - reach).floor().max(0.0) as usize, ((center + reach).ceil().max(0.0) as usize + 1).min(len))
+        };
+        let ((x0, x1), (y0, y1)) = (span(self.center.x, width), span(self.center.y, height));
+        (y0..y1)
+            .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.contains(Vec2::new(x as f32, y as f32)))
+            .map(|(x, y)| y * width + x)
+            .collect()
+    }
+}
@@ -0,0 +1,315 @@
+//! Live frame outputs for VJ and streaming tools (NDI, a v4l2loopback virtual
+//! camera), fed from a `FrameBuffer` each frame.
+//!
+//! Spout (Windows) and Syphon (macOS) share GPU textures through DirectX and
+//! Metal/OpenGL handles, which the CPU renderer doesn't have, so they are not
+//! provided here. NDI Tools can bridge an NDI source into both.
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::export::{PixelFormat, VideoEncoder};
+
+/// Anything that consumes tightly packed RGBA8 frames.
+pub trait FrameSink {
+    fn send_rgba(
+        &mut self,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+    ) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FrameSink for VideoEncoder {
+    fn send_rgba(
+        &mut self,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.pixel_format() != PixelFormat::Rgba || self.dimensions() != (width, height) {
+            return Err("encoder was not started for this RGBA frame size".into());
+        }
+        self.write_raw(rgba)
+    }
+}
+
+#[cfg(all(feature = "ndi", not(target_arch = "wasm32")))]
+pub use ndi::NdiSender;
+
+#[cfg(all(feature = "webcam", target_os = "linux"))]
+pub use webcam::VirtualCamera;
+
+#[cfg(all(feature = "webcam", target_os = "linux"))]
+mod webcam {
+    use super::FrameSink;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::process::{Child, ChildStdin, Command, Stdio};
+
+    /// Feeds frames to a v4l2loopback device through ffmpeg, so video-call
+    /// apps see the simulation as a camera. Create the device first, e.g.
+    /// `sudo modprobe v4l2loopback exclusive_caps=1 card_label=itsliquid`.
+    pub struct VirtualCamera {
+        child: Child,
+        stdin: Option<ChildStdin>,
+        width: u32,
+        height: u32,
+        pub device: PathBuf,
+    }
+
+    impl VirtualCamera {
+        /// Open the first v4l2loopback device at a fixed output size; frames
+        /// must always be sent at this size.
+        pub fn start(width: u32, height: u32, fps: u32) -> Result<Self, Box<dyn std::error::Error>> {
+            let device = find_loopback_device()
+                .ok_or("no v4l2loopback device found; load the v4l2loopback module")?;
+            Self::start_on(&device, width, height, fps, "ffmpeg")
+        }
+
+        pub fn start_on(
+            device: &Path,
+            width: u32,
+            height: u32,
+            fps: u32,
+            ffmpeg_path: &str,
+        ) -> Result<Self, Box<dyn std::error::Error>> {
+            let mut child = Command::new(ffmpeg_path)
+                .args(["-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+                .args(["-s", &format!("{}x{}", width, height)])
+                .args(["-r", &fps.to_string()])
+                .args(["-i", "-"])
+                // Browsers and call apps expect a planar YUV camera
+                .args(["-f", "v4l2", "-pix_fmt", "yuv420p"])
+                .arg(device)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .map_err(|e| format!("failed to launch {}: {}", ffmpeg_path, e))?;
+
+            let stdin = child.stdin.take();
+            Ok(Self {
+                child,
+                stdin,
+                width,
+                height,
+                device: device.to_path_buf(),
+            })
+        }
+
+        pub fn dimensions(&self) -> (u32, u32) {
+            (self.width, self.height)
+        }
+    }
+
+    impl FrameSink for VirtualCamera {
+        fn send_rgba(
+            &mut self,
+            width: u32,
+            height: u32,
+            rgba: &[u8],
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            if (width, height) != (self.width, self.height) || rgba.len() != width as usize * height as usize * 4 {
+                return Err(format!("virtual camera expects {}x{} RGBA frames", self.width, self.height).into());
+            }
+            let stdin = self.stdin.as_mut().ok_or("virtual camera closed")?;
+            stdin.write_all(rgba)?;
+            Ok(())
+        }
+    }
+
+    impl Drop for VirtualCamera {
+        fn drop(&mut self) {
+            // Closing stdin lets ffmpeg release the device
+            drop(self.stdin.take());
+            let _ = self.child.wait();
+        }
+    }
+
+    // The first /dev/video* whose driver name marks it as a loopback device
+    fn find_loopback_device() -> Option<PathBuf> {
+        let mut entries: Vec<_> = std::fs::read_dir("/sys/class/video4linux")
+            .ok()?
+            .filter_map(Result::ok)
+            .collect();
+        entries.sort_by_key(|e| e.file_name());
+
+        entries.into_iter().find_map(|entry| {
+            let name = std::fs::read_to_string(entry.path().join("name")).ok()?;
+            let is_loopback = name.contains("Dummy video device")
+                || name.contains("loopback")
+                || name.contains("itsliquid");
+            is_loopback.then(|| Path::new("/dev").join(entry.file_name()))
+        })
+    }
+}
+
+#[cfg(all(feature = "ndi", not(target_arch = "wasm32")))]
+mod ndi {
+    use super::FrameSink;
+    use libloading::Library;
+    use std::ffi::{CString, c_char, c_int, c_void};
+
+    // Layouts follow Processing.NDI.Lib.h from the NDI SDK
+    #[repr(C)]
+    struct SendCreate {
+        p_ndi_name: *const c_char,
+        p_groups: *const c_char,
+        clock_video: bool,
+        clock_audio: bool,
+    }
+
+    #[repr(C)]
+    struct VideoFrameV2 {
+        xres: c_int,
+        yres: c_int,
+        four_cc: u32,
+        frame_rate_n: c_int,
+        frame_rate_d: c_int,
+        picture_aspect_ratio: f32,
+        frame_format_type: c_int,
+        timecode: i64,
+        p_data: *const u8,
+        line_stride_in_bytes: c_int,
+        p_metadata: *const c_char,
+        timestamp: i64,
+    }
+
+    const FOURCC_RGBA: u32 = u32::from_le_bytes(*b"RGBA");
+    const FRAME_FORMAT_PROGRESSIVE: c_int = 1;
+    // Ask the runtime to synthesize timecodes
+    const TIMECODE_SYNTHESIZE: i64 = i64::MAX;
+
+    type InitializeFn = unsafe extern "C" fn() -> bool;
+    type DestroyFn = unsafe extern "C" fn();
+    type SendCreateFn = unsafe extern "C" fn(*const SendCreate) -> *mut c_void;
+    type SendDestroyFn = unsafe extern "C" fn(*mut c_void);
+    type SendVideoFn = unsafe extern "C" fn(*mut c_void, *const VideoFrameV2);
+
+    /// Publishes frames as an NDI source. The NDI runtime is loaded at run
+    /// time, so builds don't need the SDK; `NDI_RUNTIME_DIR_V6`/`_V5` point
+    /// at a non-default install.
+    pub struct NdiSender {
+        instance: *mut c_void,
+        send_destroy: SendDestroyFn,
+        send_video: SendVideoFn,
+        destroy: DestroyFn,
+        pub fps: u32,
+        // Keeps the function pointers above valid
+        _library: Library,
+        _name: CString,
+    }
+
+    impl NdiSender {
+        pub fn new(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+            let library = load_runtime()?;
+            let name = CString::new(name)?;
+
+            // SAFETY: symbol types match the NDI SDK declarations, and the
+            // library outlives the copied pointers by living in the struct
+            unsafe {
+                let initialize = *library.get::<InitializeFn>(b"NDIlib_initialize\0")?;
+                let destroy = *library.get::<DestroyFn>(b"NDIlib_destroy\0")?;
+                let send_create = *library.get::<SendCreateFn>(b"NDIlib_send_create\0")?;
+                let send_destroy = *library.get::<SendDestroyFn>(b"NDIlib_send_destroy\0")?;
+                let send_video = *library.get::<SendVideoFn>(b"NDIlib_send_send_video_v2\0")?;
+
+                if !initialize() {
+                    return Err("NDI runtime refused to initialize (unsupported CPU?)".into());
+                }
+
+                let settings = SendCreate {
+                    p_ndi_name: name.as_ptr(),
+                    p_groups: std::ptr::null(),
+                    // The UI loop already paces frames
+                    clock_video: false,
+                    clock_audio: false,
+                };
+                let instance = send_create(&settings);
+                if instance.is_null() {
+                    destroy();
+                    return Err("failed to create NDI sender".into());
+                }
+
+                Ok(Self {
+                    instance,
+                    send_destroy,
+                    send_video,
+                    destroy,
+                    fps: 60,
+                    _library: library,
+                    _name: name,
+                })
+            }
+        }
+    }
+
+    impl FrameSink for NdiSender {
+        fn send_rgba(
+            &mut self,
+            width: u32,
+            height: u32,
+            rgba: &[u8],
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            if rgba.len() != width as usize * height as usize * 4 {
+                return Err("RGBA frame size does not match its dimensions".into());
+            }
+
+            let frame = VideoFrameV2 {
+                xres: width as c_int,
+                yres: height as c_int,
+                four_cc: FOURCC_RGBA,
+                frame_rate_n: self.fps as c_int,
+                frame_rate_d: 1,
+                picture_aspect_ratio: 0.0, // square pixels
+                frame_format_type: FRAME_FORMAT_PROGRESSIVE,
+                timecode: TIMECODE_SYNTHESIZE,
+                p_data: rgba.as_ptr(),
+                line_stride_in_bytes: (width * 4) as c_int,
+                p_metadata: std::ptr::null(),
+                timestamp: 0,
+            };
+            // SAFETY: the synchronous send copies the frame before returning
+            unsafe { (self.send_video)(self.instance, &frame) };
+            Ok(())
+        }
+    }
+
+    impl Drop for NdiSender {
+        fn drop(&mut self) {
+            // SAFETY: instance came from send_create and is destroyed once
+            unsafe {
+                (self.send_destroy)(self.instance);
+                (self.destroy)();
+            }
+        }
+    }
+
+    fn load_runtime() -> Result<Library, Box<dyn std::error::Error>> {
+        let file = if cfg!(target_os = "windows") {
+            "Processing.NDI.Lib.x64.dll"
+        } else if cfg!(target_os = "macos") {
+            "libndi.dylib"
+        } else {
+            "libndi.so"
+        };
+
+        let mut candidates: Vec<std::path::PathBuf> = ["NDI_RUNTIME_DIR_V6", "NDI_RUNTIME_DIR_V5"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .map(|dir| std::path::Path::new(&dir).join(file))
+            .collect();
+        candidates.push(file.into());
+        if cfg!(target_os = "linux") {
+            candidates.push("libndi.so.6".into());
+            candidates.push("libndi.so.5".into());
+        }
+
+        for path in &candidates {
+            // SAFETY: the NDI runtime's load-time initializers have no preconditions
+            if let Ok(library) = unsafe { Library::new(path) } {
+                return Ok(library);
+            }
+        }
+        Err(format!("NDI runtime not found (tried {:?}); install NDI Tools", candidates).into())
+    }
+}
@@ -0,0 +1,125 @@
+//! The dye palette: a list of color slots the user can add to, edit with the
+//! color picker, fill from the eyedropper and remove, and which slot is
+//! painting.
+
+use serde::{Deserialize, Serialize};
+
+pub type Rgb = (f32, f32, f32);
+
+pub const DEFAULT_COLORS: [Rgb; 8] = [
+    (1.0, 0.0, 0.0), // Red
+    (0.0, 1.0, 0.0), // Green
+    (0.0, 0.0, 1.0), // Blue
+    (1.0, 1.0, 0.0), // Yellow
+    (1.0, 0.0, 1.0), // Magenta
+    (0.0, 1.0, 1.0), // Cyan
+    (1.0, 1.0, 1.0), // White
+    (0.0, 0.0, 0.0), // Black (negative dye - removes color)
+];
+
+/// Most slots a palette holds, so the swatch row stays usable.
+pub const MAX_COLORS: usize = 24;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Palette {
+    pub colors: Vec<Rgb>,
+    /// Index of the color being painted with
+    pub current: usize,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self { colors: DEFAULT_COLORS.to_vec(), current: 0 }
+    }
+}
+
+impl Palette {
+    pub fn current(&self) -> Rgb {
+        self.colors[self.current]
+    }
+
+    /// Replace the color in the current slot.
+    pub fn set_current(&mut self, color: Rgb) {
+        self.colors[self.current] = color;
+    }
+
+    /// Add a slot holding `color` and select it. When the palette is full
+    /// the current slot takes the color instead.
+    pub fn insert(&mut self, color: Rgb) {
+        if self.colors.len() < MAX_COLORS {
+            self.colors.push(color);
+            self.current = self.colors.len() - 1;
+        } else {
+            self.set_current(color);
+        }
+    }
+
+    /// Select `color`, adding a slot for it unless one already holds it.
+    pub fn add(&mut self, color: Rgb) {
+        match self.colors.iter().position(|&c| c == color) {
+            Some(i) => self.current = i,
+            None => self.insert(color),
+        }
+    }
+
+    /// Remove slot `index`, keeping at least one. The selection stays on
+    /// the same color where it can.
+    pub fn remove(&mut self, index: usize) {
+        if self.colors.len() <= 1 || index >= self.colors.len() {
+            return;
+        }
+        self.colors.remove(index);
+        if self.current > index || self.current == self.colors.len() {
+            self.current -= 1;
+        }
+    }
+
+    /// Repair a palette read back from storage: never empty, and the
+    /// selection inside it.
+    pub fn sanitized(mut self) -> Self {
+        self.colors.truncate(MAX_COLORS);
+        if self.colors.is_empty() {
+            return Self::default();
+        }
+        self.current = self.current.min(self.colors.len() - 1);
+        self
+    }
+}
+
+/// A brush color from a sampled dye value. Dye piles up past 1 where it is
+/// dense, so the sample is scaled until its brightest channel is 1, keeping
+/// the hue; the intensity slider sets how much is painted. Empty cells give
+/// black.
+pub fn color_from_sample((r, g, b): Rgb) -> Rgb {
+    let (r, g, b) = (r.max(0.0), g.max(0.0), b.max(0.0));
+    let peak = r.max(g).max(b);
+    if peak <= 1e-6 || !peak.is_finite() {
+        return (0.0, 0.0, 0.0);
+    }
+    (r / peak, g / peak, b / peak)
+}
+
+/// `#RRGGBB` for a color in 0..1.
+pub fn to_hex((r, g, b): Rgb) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02X}{:02X}{:02X}", channel(r), channel(g), channel(b))
+}
+
+/// A color from `#RRGGBB` or the short `#RGB`, with or without the `#`.
+pub fn parse_hex(text: &str) -> Option<Rgb> {
+    let digits = text.trim();
+    let digits = digits.strip_prefix('#').unwrap_or(digits);
+    // from_str_radix would also take a sign
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |hex: &str| u8::from_str_radix(hex, 16).ok().map(|v| v as f32 / 255.0);
+    match digits.len() {
+        6 => Some((channel(&digits[0..2])?, channel(&digits[2..4])?, channel(&digits[4..6])?)),
+        3 => {
+            let short = |i: usize| channel(&digits[i..i + 1].repeat(2));
+            Some((short(0)?, short(1)?, short(2)?))
+        }
+        _ => None,
+    }
+}
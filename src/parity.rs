@@ -0,0 +1,291 @@
+//! Cell-by-cell comparison of two backends running the same scripted
+//! scenario, to measure how far a port (chiefly `FunctionalGPUFluid`) drifts
+//! from the CPU reference rather than only whether it behaves plausibly.
+//!
+//! Scenarios come from `conformance`, so both backends get identical inputs.
+//! Differences are reported per sampled step as RMS (L2 per cell) and
+//! largest (L∞) error for summed dye and for velocity.
+
+use crate::conformance::{Fields, Observe, Scenario};
+use crate::FluidSimulation;
+
+/// Largest acceptable error in one field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Root mean square difference per cell
+    pub l2: f32,
+    /// Largest difference in any cell
+    pub linf: f32,
+}
+
+impl Tolerance {
+    pub fn new(l2: f32, linf: f32) -> Self {
+        Self { l2, linf }
+    }
+
+    /// Accepts only identical fields.
+    pub fn exact() -> Self {
+        Self::new(0.0, 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerances {
+    pub dye: Tolerance,
+    pub velocity: Tolerance,
+}
+
+impl Tolerances {
+    pub fn exact() -> Self {
+        Self {
+            dye: Tolerance::exact(),
+            velocity: Tolerance::exact(),
+        }
+    }
+}
+
+impl Default for Tolerances {
+    // Loose enough for f32 reordering and half-precision storage, tight
+    // enough that a different integrator or lost projection fails
+    fn default() -> Self {
+        Self {
+            dye: Tolerance::new(0.01, 0.1),
+            velocity: Tolerance::new(0.05, 0.5),
+        }
+    }
+}
+
+/// Error between a reference field and a candidate's.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FieldDiff {
+    /// Root mean square difference per cell
+    pub l2: f32,
+    /// Largest difference in any cell
+    pub linf: f32,
+    /// Cell holding the largest difference
+    pub worst_cell: (usize, usize),
+    /// `l2` over the reference's RMS value, so backends with different
+    /// magnitudes can be compared; 0 when the reference is empty
+    pub relative_l2: f32,
+}
+
+impl FieldDiff {
+    /// Differences of scalar fields, row-major with `width` cells per row.
+    pub fn scalar(reference: &[f32], candidate: &[f32], width: usize) -> Self {
+        Self::from_errors(
+            reference.iter().zip(candidate).map(|(a, b)| (b - a).abs()),
+            reference.iter().map(|a| a.abs()),
+            width,
+        )
+    }
+
+    /// Differences of vector fields, by the length of the difference vector.
+    pub fn vector(reference: (&[f32], &[f32]), candidate: (&[f32], &[f32]), width: usize) -> Self {
+        let errors = reference
+            .0
+            .iter()
+            .zip(reference.1)
+            .zip(candidate.0.iter().zip(candidate.1))
+            .map(|((ax, ay), (bx, by))| ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt());
+        let magnitudes = reference.0.iter().zip(reference.1).map(|(x, y)| (x * x + y * y).sqrt());
+        Self::from_errors(errors, magnitudes, width)
+    }
+
+    fn from_errors(errors: impl Iterator<Item = f32>, magnitudes: impl Iterator<Item = f32>, width: usize) -> Self {
+        let mut diff = FieldDiff::default();
+        let (mut error_sq, mut magnitude_sq, mut cells) = (0.0f64, 0.0f64, 0usize);
+        for (i, (error, magnitude)) in errors.zip(magnitudes).enumerate() {
+            // NaN counts as infinitely wrong
+            let error = if error.is_nan() { f32::INFINITY } else { error };
+            error_sq += (error as f64).powi(2);
+            magnitude_sq += (magnitude as f64).powi(2);
+            cells += 1;
+            if error > diff.linf {
+                diff.linf = error;
+                diff.worst_cell = (i % width.max(1), i / width.max(1));
+            }
+        }
+        if cells > 0 {
+            diff.l2 = (error_sq / cells as f64).sqrt() as f32;
+            let rms = (magnitude_sq / cells as f64).sqrt();
+            diff.relative_l2 = if rms <= 1e-12 { 0.0 } else { (diff.l2 as f64 / rms) as f32 };
+        }
+        diff
+    }
+
+    pub fn within(&self, tolerance: Tolerance) -> bool {
+        self.l2 <= tolerance.l2 && self.linf <= tolerance.linf
+    }
+}
+
+/// Both fields compared after `step` steps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub step: usize,
+    /// Dye summed over channels
+    pub dye: FieldDiff,
+    pub velocity: FieldDiff,
+}
+
+impl Sample {
+    pub fn between(step: usize, reference: &Fields, candidate: &Fields) -> Self {
+        Self {
+            step,
+            dye: FieldDiff::scalar(&reference.dye, &candidate.dye, reference.width),
+            velocity: FieldDiff::vector(
+                (&reference.velocity_x, &reference.velocity_y),
+                (&candidate.velocity_x, &candidate.velocity_y),
+                reference.width,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParityReport {
+    pub scenario: &'static str,
+    pub tolerances: Tolerances,
+    /// In step order; the last is the end of the scenario
+    pub samples: Vec<Sample>,
+}
+
+impl ParityReport {
+    pub fn passed(&self) -> bool {
+        self.samples
+            .iter()
+            .all(|s| s.dye.within(self.tolerances.dye) && s.velocity.within(self.tolerances.velocity))
+    }
+
+    /// One line per sample and field outside its tolerance.
+    pub fn failures(&self) -> Vec<String> {
+        let mut failures = Vec::new();
+        for sample in &self.samples {
+            for (name, diff, tolerance) in [
+                ("dye", sample.dye, self.tolerances.dye),
+                ("velocity", sample.velocity, self.tolerances.velocity),
+            ] {
+                if !diff.within(tolerance) {
+                    failures.push(format!(
+                        "{} step {}: {} L2 {} L∞ {} at {:?}, allowed L2 {} L∞ {}",
+                        self.scenario, sample.step, name, diff.l2, diff.linf, diff.worst_cell, tolerance.l2, tolerance.linf
+                    ));
+                }
+            }
+        }
+        failures
+    }
+
+    /// The largest errors reached at any sample, as (dye, velocity).
+    pub fn worst(&self) -> (FieldDiff, FieldDiff) {
+        let mut worst = (FieldDiff::default(), FieldDiff::default());
+        for sample in &self.samples {
+            if sample.dye.linf >= worst.0.linf {
+                worst.0 = sample.dye;
+            }
+            if sample.velocity.linf >= worst.1.linf {
+                worst.1 = sample.velocity;
+            }
+        }
+        worst
+    }
+
+    pub fn summary(&self) -> String {
+        let (dye, velocity) = self.worst();
+        format!(
+            "{}: {} | dye L2 {:.3e} L∞ {:.3e} (rel {:.2}) | velocity L2 {:.3e} L∞ {:.3e} (rel {:.2})",
+            self.scenario,
+            if self.passed() { "ok" } else { "DIVERGED" },
+            dye.l2,
+            dye.linf,
+            dye.relative_l2,
+            velocity.l2,
+            velocity.linf,
+            velocity.relative_l2
+        )
+    }
+}
+
+/// Step `reference` and `candidate` through `scenario` in lockstep,
+/// comparing their fields every `every` steps and at the end.
+pub fn compare<R, C>(
+    reference: &mut R,
+    candidate: &mut C,
+    scenario: &Scenario,
+    every: usize,
+    tolerances: Tolerances,
+) -> Result<ParityReport, Box<dyn std::error::Error>>
+where
+    R: FluidSimulation + Observe,
+    C: FluidSimulation + Observe,
+{
+    for (name, width, height) in [
+        ("reference", reference.width(), reference.height()),
+        ("candidate", candidate.width(), candidate.height()),
+    ] {
+        if (width, height) != (scenario.width, scenario.height) {
+            return Err(format!(
+                "{} needs a {}x{} grid, the {} is {}x{}",
+                scenario.name, scenario.width, scenario.height, name, width, height
+            )
+            .into());
+        }
+    }
+
+    let mut samples = Vec::new();
+    for step in 0..scenario.steps {
+        scenario.apply_inputs(reference, step);
+        scenario.apply_inputs(candidate, step);
+        reference.step();
+        candidate.step();
+
+        let done = step + 1;
+        if done % every.max(1) == 0 || done == scenario.steps {
+            samples.push(Sample::between(done, &reference.fields()?, &candidate.fields()?));
+        }
+    }
+
+    Ok(ParityReport {
+        scenario: scenario.name,
+        tolerances,
+        samples,
+    })
+}
+
+/// Run every conformance scenario on fresh backends built by the two makers.
+pub fn compare_all<R, C>(
+    mut make_reference: impl FnMut(usize, usize) -> Result<R, Box<dyn std::error::Error>>,
+    mut make_candidate: impl FnMut(usize, usize) -> Result<C, Box<dyn std::error::Error>>,
+    every: usize,
+    tolerances: Tolerances,
+) -> Result<Vec<ParityReport>, Box<dyn std::error::Error>>
+where
+    R: FluidSimulation + Observe,
+    C: FluidSimulation + Observe,
+{
+    crate::conformance::scenarios()
+        .iter()
+        .map(|scenario| {
+            let mut reference = make_reference(scenario.width, scenario.height)?;
+            let mut candidate = make_candidate(scenario.width, scenario.height)?;
+            compare(&mut reference, &mut candidate, scenario, every, tolerances)
+        })
+        .collect()
+}
+
+/// `FunctionalGPUFluid` against `InteractiveFluid` on every scenario, with
+/// the CPU timestep matched to the GPU's. Errors if no adapter can run the
+/// GPU solver.
+#[cfg(all(feature = "gpu", not(target_arch = "wasm32")))]
+pub fn compare_gpu(every: usize, tolerances: Tolerances) -> Result<Vec<ParityReport>, Box<dyn std::error::Error>> {
+    use crate::gpu_functional::{FunctionalGPUFluid, block_on};
+
+    crate::conformance::scenarios()
+        .iter()
+        .map(|scenario| {
+            let mut gpu = block_on(FunctionalGPUFluid::new(scenario.width as u32, scenario.height as u32))?;
+            let mut cpu = crate::InteractiveFluid::new(scenario.width, scenario.height);
+            cpu.dt = gpu.gpu_dt();
+            compare(&mut cpu, &mut gpu, scenario, every, tolerances)
+        })
+        .collect()
+}
@@ -1,14 +1,197 @@
+use crate::drifters::DrifterSet;
 use crate::export::FluidData;
+use crate::gates::GateSet;
+use crate::particles::{ParticleSystem, VelocityField};
+use crate::scalar::ScalarField;
+use crate::InteractiveFluid;
+use glam::Vec2;
 use image::{ImageBuffer, Rgb, RgbImage};
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
+
+// Distinct colors cycled across drifter trajectories and gate plots
+const TRAJECTORY_COLORS: [[u8; 3]; 6] = [
+    [255, 80, 80],
+    [80, 220, 80],
+    [90, 140, 255],
+    [255, 210, 60],
+    [230, 90, 230],
+    [60, 220, 220],
+];
+
+// Streamline seeds are placed every this many grid cells
+const STREAMLINE_SPACING: f32 = 4.0;
+// Maximum integration steps per streamline direction, each half a cell long
+const STREAMLINE_STEPS: usize = 40;
+// LIC kernel half-length in output pixels
+const LIC_LENGTH: usize = 12;
+
+// Samples at t = 0, 1/8, ..., 1 of matplotlib's viridis and magma
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+const MAGMA: [[u8; 3]; 9] = [
+    [0, 0, 4],
+    [28, 16, 68],
+    [79, 18, 123],
+    [129, 37, 129],
+    [181, 54, 122],
+    [229, 80, 100],
+    [251, 135, 97],
+    [254, 194, 135],
+    [252, 253, 191],
+];
+
+/// Maps a scalar in [0, 1] to a color.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Colormap {
+    /// Blue when empty to white when full, the original density look
+    #[default]
+    BlueWhite,
+    Viridis,
+    Magma,
+    Grayscale,
+    /// Lookup table sampled evenly over [0, 1], e.g. from `from_image`
+    Custom(Vec<[u8; 3]>),
+}
+
+impl Colormap {
+    pub fn builtin() -> [Colormap; 4] {
+        [
+            Colormap::BlueWhite,
+            Colormap::Viridis,
+            Colormap::Magma,
+            Colormap::Grayscale,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Colormap::BlueWhite => "Blue-white",
+            Colormap::Viridis => "Viridis",
+            Colormap::Magma => "Magma",
+            Colormap::Grayscale => "Grayscale",
+            Colormap::Custom(_) => "Custom",
+        }
+    }
+
+    /// Color for `t`, clamped to [0, 1]. NaN maps to the low end.
+    pub fn map(&self, t: f32) -> [u8; 3] {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        match self {
+            Colormap::BlueWhite => {
+                let v = (t * 255.0) as u8;
+                [v, v, 255]
+            }
+            Colormap::Viridis => interpolate(&VIRIDIS, t),
+            Colormap::Magma => interpolate(&MAGMA, t),
+            Colormap::Grayscale => {
+                let v = (t * 255.0) as u8;
+                [v, v, v]
+            }
+            Colormap::Custom(lut) if lut.is_empty() => [0, 0, 0],
+            Colormap::Custom(lut) => interpolate(lut, t),
+        }
+    }
+
+    /// Build a lookup table from an image: the middle row of a wide image
+    /// or the middle column of a tall one, read left-to-right/top-to-bottom.
+    pub fn from_image(path: &std::path::Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let img = image::open(path)?.to_rgb8();
+        let (w, h) = img.dimensions();
+        if w == 0 || h == 0 {
+            return Err(format!("{} is empty", path.display()).into());
+        }
+
+        let lut = if w >= h {
+            (0..w).map(|x| img.get_pixel(x, h / 2).0).collect()
+        } else {
+            (0..h).map(|y| img.get_pixel(w / 2, y).0).collect()
+        };
+        Ok(Colormap::Custom(lut))
+    }
+}
+
+fn interpolate(stops: &[[u8; 3]], t: f32) -> [u8; 3] {
+    if stops.len() == 1 {
+        return stops[0];
+    }
+    let pos = t * (stops.len() - 1) as f32;
+    let i = (pos.floor() as usize).min(stops.len() - 2);
+    let f = pos - i as f32;
+    let mut out = [0; 3];
+    for (c, value) in out.iter_mut().enumerate() {
+        let a = stops[i][c] as f32;
+        let b = stops[i + 1][c] as f32;
+        *value = (a + (b - a) * f).round() as u8;
+    }
+    out
+}
+
+/// Reinhard-mapped total dye concentration in [0, 1), for colormapped dye.
+pub fn dye_intensity(r: f32, g: f32, b: f32) -> f32 {
+    let total = (r + g + b).max(0.0);
+    total / (1.0 + total)
+}
+
+/// What `Renderer::render_interactive` draws for a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// Tone-mapped dye, as on the interactive canvas
+    #[default]
+    Dye,
+    /// Total dye concentration through the renderer's colormap
+    DyeIntensity,
+    /// Evenly seeded streamlines shaded by speed
+    Streamlines,
+    /// Line integral convolution of white noise along the flow
+    Lic,
+    /// Pressure from the last projection, blue below zero and red above
+    Pressure,
+    /// One of `InteractiveFluid::scalars`, by index, in its own colormap
+    Scalar(usize),
+    /// Every scalar in its own colormap, blended additively
+    Scalars,
+}
 
 pub struct Renderer {
     width: u32,
     height: u32,
+    colormap: Colormap,
 }
 
 impl Renderer {
     pub fn new(width: u32, height: u32) -> Self {
-        Self { width, height }
+        Self {
+            width,
+            height,
+            colormap: Colormap::default(),
+        }
+    }
+
+    pub fn with_colormap(mut self, colormap: Colormap) -> Self {
+        self.colormap = colormap;
+        self
+    }
+
+    pub fn set_colormap(&mut self, colormap: Colormap) {
+        self.colormap = colormap;
+    }
+
+    pub fn colormap(&self) -> &Colormap {
+        &self.colormap
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
     }
 
     pub fn render_to_image(&self, simulation: &impl FluidData) -> RgbImage {
@@ -25,11 +208,7 @@ impl Renderer {
             if sim_x < simulation.width() && sim_y < simulation.height() {
                 let idx = sim_y * simulation.width() + sim_x;
                 let density = simulation.density()[idx].min(1.0).max(0.0);
-
-                // Create a proper fluid visualization
-                // Blue for low density, white for high density
-                let intensity = (density * 255.0) as u8;
-                *pixel = Rgb([intensity, intensity, 255]);
+                *pixel = Rgb(self.colormap.map(density));
             } else {
                 *pixel = Rgb([0, 0, 0]);
             }
@@ -63,4 +242,425 @@ impl Renderer {
 
         img
     }
+
+    /// Render an `InteractiveFluid` frame in the given mode, so callers can
+    /// switch visualization from one frame to the next.
+    pub fn render_interactive(&self, simulation: &InteractiveFluid, mode: RenderMode) -> RgbImage {
+        match mode {
+            RenderMode::Dye => self.render_dye(simulation),
+            RenderMode::DyeIntensity => self.render_dye_intensity(simulation),
+            RenderMode::Streamlines => self.render_streamlines(simulation),
+            RenderMode::Lic => self.render_lic(simulation),
+            RenderMode::Pressure => {
+                self.render_pressure(&simulation.pressure, simulation.width, simulation.height)
+            }
+            RenderMode::Scalar(index) => match simulation.scalars.get(index) {
+                Some(field) => self.render_scalar(field),
+                None => ImageBuffer::new(self.width, self.height),
+            },
+            RenderMode::Scalars => self.render_scalars(&simulation.scalars),
+        }
+    }
+
+    /// A scalar field scaled by its range into its colormap.
+    pub fn render_scalar(&self, field: &ScalarField) -> RgbImage {
+        let mut img = ImageBuffer::new(self.width, self.height);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let sim_x = x as usize * field.width / self.width as usize;
+            let sim_y = y as usize * field.height / self.height as usize;
+            *pixel = Rgb(field.color(sim_y * field.width + sim_x));
+        }
+        img
+    }
+
+    /// Scalar fields composited over black: each adds its colormap color
+    /// weighted by its normalized value, so empty cells stay dark.
+    pub fn render_scalars(&self, fields: &[ScalarField]) -> RgbImage {
+        let mut img = ImageBuffer::new(self.width, self.height);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let mut sum = [0.0f32; 3];
+            for field in fields {
+                let sim_x = x as usize * field.width / self.width as usize;
+                let sim_y = y as usize * field.height / self.height as usize;
+                let idx = sim_y * field.width + sim_x;
+                let (t, color) = (field.normalized(idx), field.color(idx));
+                for (total, channel) in sum.iter_mut().zip(color) {
+                    *total += t * channel as f32;
+                }
+            }
+            *pixel = Rgb(sum.map(|v| v.min(255.0) as u8));
+        }
+        img
+    }
+
+    /// Dye with the same Reinhard tone mapping as the interactive canvas.
+    pub fn render_dye(&self, simulation: &InteractiveFluid) -> RgbImage {
+        let mut img = ImageBuffer::new(self.width, self.height);
+        let tone = |v: f32| {
+            let v = v.max(0.0);
+            (v / (1.0 + v) * 255.0) as u8
+        };
+
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let sim_x = x as usize * simulation.width / self.width as usize;
+            let sim_y = y as usize * simulation.height / self.height as usize;
+            let idx = sim_y * simulation.width + sim_x;
+            *pixel = Rgb([
+                tone(simulation.dye_r[idx]),
+                tone(simulation.dye_g[idx]),
+                tone(simulation.dye_b[idx]),
+            ]);
+        }
+        img
+    }
+
+    /// Total dye concentration, Reinhard-mapped and colored by the colormap.
+    pub fn render_dye_intensity(&self, simulation: &InteractiveFluid) -> RgbImage {
+        let mut img = ImageBuffer::new(self.width, self.height);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let sim_x = x as usize * simulation.width / self.width as usize;
+            let sim_y = y as usize * simulation.height / self.height as usize;
+            let idx = sim_y * simulation.width + sim_x;
+            let t = dye_intensity(simulation.dye_r[idx], simulation.dye_g[idx], simulation.dye_b[idx]);
+            *pixel = Rgb(self.colormap.map(t));
+        }
+        img
+    }
+
+    /// Pressure with a diverging colormap scaled so the largest magnitude in
+    /// the frame is fully saturated. Zero pressure is white.
+    pub fn render_pressure(&self, pressure: &[f32], grid_width: usize, grid_height: usize) -> RgbImage {
+        let max_abs = pressure.iter().fold(0.0f32, |m, p| m.max(p.abs()));
+        let mut img = ImageBuffer::new(self.width, self.height);
+
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let sim_x = x as usize * grid_width / self.width as usize;
+            let sim_y = y as usize * grid_height / self.height as usize;
+            let p = pressure[sim_y * grid_width + sim_x];
+            let t = if max_abs > 0.0 { p / max_abs } else { 0.0 };
+            *pixel = Rgb(diverging_color(t));
+        }
+        img
+    }
+
+    /// Streamlines seeded on a regular lattice and traced both ways, brighter
+    /// where the flow is faster.
+    pub fn render_streamlines(&self, field: &impl VelocityField) -> RgbImage {
+        let mut img = ImageBuffer::from_pixel(self.width, self.height, Rgb([0, 0, 0]));
+        let (width, height) = field.grid_size();
+        let scale_x = self.width as f32 / width as f32;
+        let scale_y = self.height as f32 / height as f32;
+        let max_speed = max_speed(field);
+        if max_speed <= 0.0 {
+            return img;
+        }
+
+        let mut y = 1.0 + STREAMLINE_SPACING * 0.5;
+        while y < (height - 1) as f32 {
+            let mut x = 1.0 + STREAMLINE_SPACING * 0.5;
+            while x < (width - 1) as f32 {
+                let line = trace_streamline(field, Vec2::new(x, y), STREAMLINE_STEPS);
+                for pair in line.windows(2) {
+                    let mid = (pair[0] + pair[1]) * 0.5;
+                    let color = speed_color(field.velocity_at(mid.x, mid.y).length() / max_speed);
+                    draw_line(
+                        &mut img,
+                        (pair[0].x * scale_x, pair[0].y * scale_y),
+                        (pair[1].x * scale_x, pair[1].y * scale_y),
+                        color,
+                    );
+                }
+                x += STREAMLINE_SPACING;
+            }
+            y += STREAMLINE_SPACING;
+        }
+        img
+    }
+
+    /// Line integral convolution: a fixed white-noise texture is averaged
+    /// along the local flow direction, so streaks follow streamlines. Speed
+    /// modulates brightness.
+    pub fn render_lic(&self, field: &(impl VelocityField + Sync)) -> RgbImage {
+        let (width, height) = field.grid_size();
+        let (out_w, out_h) = (self.width as usize, self.height as usize);
+        let scale_x = out_w as f32 / width as f32;
+        let scale_y = out_h as f32 / height as f32;
+        let max_speed = max_speed(field).max(1e-6);
+
+        let mut img: RgbImage = ImageBuffer::new(self.width, self.height);
+        let buffer: &mut [u8] = &mut img;
+        // Rows in parallel; serial on wasm, which has no threads
+        #[cfg(not(target_arch = "wasm32"))]
+        let rows = buffer.par_chunks_mut(out_w * 3);
+        #[cfg(target_arch = "wasm32")]
+        let rows = buffer.chunks_mut(out_w * 3);
+        rows.enumerate().for_each(|(py, row)| {
+            for (px, pixel) in row.chunks_exact_mut(3).enumerate() {
+                // Walk in output pixels, sampling velocity in grid cells
+                let start = Vec2::new(px as f32 + 0.5, py as f32 + 0.5);
+                let mut total = white_noise(px as i32, py as i32);
+                let mut count = 1.0;
+
+                for sign in [1.0f32, -1.0] {
+                    let mut p = start;
+                    for _ in 0..LIC_LENGTH {
+                        let v = field.velocity_at(p.x / scale_x, p.y / scale_y);
+                        let dir = Vec2::new(v.x * scale_x, v.y * scale_y).normalize_or_zero();
+                        if dir == Vec2::ZERO {
+                            break;
+                        }
+                        p += sign * dir;
+                        if p.x < 0.0 || p.y < 0.0 || p.x >= out_w as f32 || p.y >= out_h as f32 {
+                            break;
+                        }
+                        total += white_noise(p.x as i32, p.y as i32);
+                        count += 1.0;
+                    }
+                }
+
+                let speed = field
+                    .velocity_at(start.x / scale_x, start.y / scale_y)
+                    .length()
+                    / max_speed;
+                let lic = total / count;
+                let Rgb(tint) = speed_color(speed);
+                for (out, &t) in pixel.iter_mut().zip(&tint) {
+                    *out = (lic * t as f32).min(255.0) as u8;
+                }
+            }
+        });
+        img
+    }
+
+    /// Render drifter trajectories on a black background.
+    pub fn render_trajectories(
+        &self,
+        drifters: &DrifterSet,
+        sim_width: usize,
+        sim_height: usize,
+    ) -> RgbImage {
+        let mut img = ImageBuffer::from_pixel(self.width, self.height, Rgb([0, 0, 0]));
+        self.draw_trajectories(&mut img, drifters, sim_width, sim_height);
+        img
+    }
+
+    /// Draw drifter trajectories as polylines over an existing image, e.g. a
+    /// density render of the same simulation.
+    pub fn draw_trajectories(
+        &self,
+        img: &mut RgbImage,
+        drifters: &DrifterSet,
+        sim_width: usize,
+        sim_height: usize,
+    ) {
+        let scale_x = img.width() as f32 / sim_width as f32;
+        let scale_y = img.height() as f32 / sim_height as f32;
+
+        for (i, drifter) in drifters.drifters.iter().enumerate() {
+            let color = Rgb(TRAJECTORY_COLORS[i % TRAJECTORY_COLORS.len()]);
+            for segment in drifter.trajectory.windows(2) {
+                draw_line(
+                    img,
+                    (segment[0].x * scale_x, segment[0].y * scale_y),
+                    (segment[1].x * scale_x, segment[1].y * scale_y),
+                    color,
+                );
+            }
+
+            // Mark the current position
+            let x = (drifter.x * scale_x) as i32;
+            let y = (drifter.y * scale_y) as i32;
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    put_pixel_checked(img, x + dx, y + dy, Rgb([255, 255, 255]));
+                }
+            }
+        }
+    }
+
+    /// Draw tracer particles as single pixels in their own color over an
+    /// existing image.
+    pub fn draw_particles(
+        &self,
+        img: &mut RgbImage,
+        particles: &ParticleSystem,
+        sim_width: usize,
+        sim_height: usize,
+    ) {
+        let scale_x = img.width() as f32 / sim_width as f32;
+        let scale_y = img.height() as f32 / sim_height as f32;
+
+        for p in &particles.particles {
+            let color = Rgb([
+                (p.color.0.clamp(0.0, 1.0) * 255.0) as u8,
+                (p.color.1.clamp(0.0, 1.0) * 255.0) as u8,
+                (p.color.2.clamp(0.0, 1.0) * 255.0) as u8,
+            ]);
+            put_pixel_checked(img, (p.x * scale_x) as i32, (p.y * scale_y) as i32, color);
+        }
+    }
+
+    /// Draw gate segments over an existing image, with a short tick on the
+    /// positive-flux side.
+    pub fn draw_gates(
+        &self,
+        img: &mut RgbImage,
+        gates: &GateSet,
+        sim_width: usize,
+        sim_height: usize,
+    ) {
+        let scale_x = img.width() as f32 / sim_width as f32;
+        let scale_y = img.height() as f32 / sim_height as f32;
+
+        for (i, gate) in gates.gates.iter().enumerate() {
+            let color = Rgb(TRAJECTORY_COLORS[i % TRAJECTORY_COLORS.len()]);
+            let a = (gate.start.x * scale_x, gate.start.y * scale_y);
+            let b = (gate.end.x * scale_x, gate.end.y * scale_y);
+            draw_line(img, a, b, color);
+
+            let mid = ((a.0 + b.0) * 0.5, (a.1 + b.1) * 0.5);
+            let n = gate.normal();
+            draw_line(img, mid, (mid.0 + n.x * 6.0, mid.1 + n.y * 6.0), color);
+        }
+    }
+
+    /// Plot per-frame volume flux of every gate as a time series. The zero
+    /// line is drawn in grey and the vertical range is symmetric around it.
+    pub fn render_gate_plot(&self, gates: &GateSet) -> RgbImage {
+        let mut img = ImageBuffer::from_pixel(self.width, self.height, Rgb([0, 0, 0]));
+        let mid_y = self.height as f32 * 0.5;
+        draw_line(
+            &mut img,
+            (0.0, mid_y),
+            (self.width as f32, mid_y),
+            Rgb([80, 80, 80]),
+        );
+
+        let max_len = gates
+            .gates
+            .iter()
+            .map(|g| g.history.len())
+            .max()
+            .unwrap_or(0);
+        let max_abs = gates
+            .gates
+            .iter()
+            .flat_map(|g| g.history.iter())
+            .map(|f| f.volume.abs())
+            .fold(0.0f32, f32::max);
+        if max_len < 2 || max_abs <= 0.0 {
+            return img;
+        }
+
+        let x_scale = (self.width - 1) as f32 / (max_len - 1) as f32;
+        let y_scale = (mid_y - 2.0) / max_abs;
+
+        for (i, gate) in gates.gates.iter().enumerate() {
+            let color = Rgb(TRAJECTORY_COLORS[i % TRAJECTORY_COLORS.len()]);
+            for (j, pair) in gate.history.windows(2).enumerate() {
+                draw_line(
+                    &mut img,
+                    (j as f32 * x_scale, mid_y - pair[0].volume * y_scale),
+                    ((j + 1) as f32 * x_scale, mid_y - pair[1].volume * y_scale),
+                    color,
+                );
+            }
+        }
+        img
+    }
+}
+
+/// Trace a streamline through `seed` with RK2 in both directions, using unit
+/// steps of half a cell so line length doesn't depend on speed. Returns grid
+/// coordinates ordered from upstream to downstream.
+pub fn trace_streamline(field: &impl VelocityField, seed: Vec2, max_steps: usize) -> Vec<Vec2> {
+    let (width, height) = field.grid_size();
+    let (max_x, max_y) = ((width - 1) as f32 - 0.5, (height - 1) as f32 - 0.5);
+    let inside = |p: Vec2| p.x >= 0.5 && p.y >= 0.5 && p.x <= max_x && p.y <= max_y;
+    let direction = |p: Vec2| field.velocity_at(p.x, p.y).normalize_or_zero();
+    let h = 0.5;
+
+    let mut halves = [Vec::new(), Vec::new()];
+    for (half, sign) in halves.iter_mut().zip([-1.0f32, 1.0]) {
+        let mut p = seed;
+        for _ in 0..max_steps {
+            let k1 = direction(p);
+            let k2 = direction(p + 0.5 * h * sign * k1);
+            if k2 == Vec2::ZERO {
+                break;
+            }
+            p += h * sign * k2;
+            if !inside(p) {
+                break;
+            }
+            half.push(p);
+        }
+    }
+
+    let [upstream, downstream] = halves;
+    let mut line: Vec<Vec2> = upstream.into_iter().rev().collect();
+    line.push(seed);
+    line.extend(downstream);
+    line
+}
+
+/// Blue-white-red diverging colormap for `t` in [-1, 1].
+pub fn diverging_color(t: f32) -> [u8; 3] {
+    let t = t.clamp(-1.0, 1.0);
+    // Fade the other two channels out towards the saturated end
+    let fade = ((1.0 - t.abs()) * 255.0) as u8;
+    if t < 0.0 {
+        [fade, fade, 255]
+    } else {
+        [255, fade, fade]
+    }
+}
+
+fn max_speed(field: &impl VelocityField) -> f32 {
+    let (width, height) = field.grid_size();
+    let mut max = 0.0f32;
+    for y in 0..height {
+        for x in 0..width {
+            max = max.max(field.velocity_at(x as f32, y as f32).length());
+        }
+    }
+    max
+}
+
+// Dark blue for slow flow through cyan to white for the fastest
+fn speed_color(t: f32) -> Rgb<u8> {
+    let t = t.clamp(0.0, 1.0).sqrt();
+    Rgb([
+        (t * t * 255.0) as u8,
+        (40.0 + t * 215.0) as u8,
+        (120.0 + t * 135.0) as u8,
+    ])
+}
+
+// Deterministic per-pixel noise in [0, 1]
+fn white_noise(x: i32, y: i32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343) ^ (y as u32).wrapping_mul(0xd816_3841);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h & 0xffff) as f32 / 65535.0
+}
+
+fn put_pixel_checked(img: &mut RgbImage, x: i32, y: i32, color: Rgb<u8>) {
+    if x >= 0 && y >= 0 && (x as u32) < img.width() && (y as u32) < img.height() {
+        img.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+fn draw_line(img: &mut RgbImage, from: (f32, f32), to: (f32, f32), color: Rgb<u8>) {
+    let dx = to.0 - from.0;
+    let dy = to.1 - from.1;
+    let steps = dx.abs().max(dy.abs()).ceil().max(1.0) as usize;
+
+    for i in 0..=steps {
+        let t = i as f32 / steps as f32;
+        let x = (from.0 + dx * t) as i32;
+        let y = (from.1 + dy * t) as i32;
+        put_pixel_checked(img, x, y, color);
+    }
 }
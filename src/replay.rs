@@ -0,0 +1,231 @@
+//! Input recording and deterministic replay. An `InteractiveFluid` with a
+//! `Recording` attached logs every dye, force, heat and attractor input with
+//! the step it arrived before; replaying the log against a fresh solver
+//! reproduces the session, so interactive bugs can be rerun in tests.
+
+use crate::brush::{BlendMode, Falloff};
+use crate::{FluidSimulation, FluidSnapshot, InteractiveFluid, SceneParams, Solver};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Format version written by `Recording::save`.
+pub const RECORDING_VERSION: u32 = 1;
+
+/// One call into the solver, in grid cells.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum Input {
+    Dye { x: usize, y: usize, color: (f32, f32, f32) },
+    RemoveDye { x: usize, y: usize, amount: f32 },
+    BlendDye { x: usize, y: usize, color: (f32, f32, f32), weight: f32, mode: BlendMode },
+    FadeDye { x: usize, y: usize, amount: f32, velocity: bool },
+    Force { x: usize, y: usize, force: (f32, f32), radius: f32 },
+    Heat { x: usize, y: usize, amount: f32, radius: f32 },
+    Attractor { x: f32, y: f32, radius: f32, strength: f32 },
+    Absorb { x: f32, y: f32, radius: f32, rate: f32 },
+    Vortex { x: f32, y: f32, radius: f32, strength: f32, falloff: Falloff },
+    Fan { from: (f32, f32), to: (f32, f32), half_width: f32, velocity: (f32, f32) },
+    Clear,
+    ClearDye,
+    ClearVelocity,
+    /// Solver settings from this step on
+    Params(SceneParams),
+    /// Fields replaced wholesale, as by undo
+    Restore(FluidSnapshot),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Steps taken before the input arrived
+    pub frame: u64,
+    pub input: Input,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+    /// Fields when recording began
+    pub start: FluidSnapshot,
+    /// Settings when recording began
+    pub params: SceneParams,
+    /// Steps taken while recording
+    pub frames: u64,
+    pub events: Vec<Event>,
+    // Settings in effect, to log only changes
+    #[serde(skip)]
+    current: Option<SceneParams>,
+}
+
+impl Recording {
+    /// Begin recording from the current state of `fluid`. Attach the result
+    /// to `fluid.recording` to log its inputs.
+    pub fn start(fluid: &InteractiveFluid) -> Self {
+        Self {
+            version: RECORDING_VERSION,
+            start: FluidSnapshot::capture(fluid),
+            params: SceneParams::capture(fluid),
+            frames: 0,
+            events: Vec::new(),
+            current: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.start.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.start.height
+    }
+
+    /// Log an input arriving before the next step.
+    pub fn push(&mut self, input: Input) {
+        self.events.push(Event { frame: self.frames, input });
+    }
+
+    /// Log a step taken with `params`, noting them if they changed.
+    pub fn step(&mut self, params: SceneParams) {
+        if params != self.current.unwrap_or(self.params) {
+            self.push(Input::Params(params));
+            self.current = Some(params);
+        }
+        self.frames += 1;
+    }
+
+    /// Inputs that arrived before step `frame`.
+    pub fn events_at(&self, frame: u64) -> impl Iterator<Item = &Input> {
+        let first = self.events.partition_point(|event| event.frame < frame);
+        self.events[first..]
+            .iter()
+            .take_while(move |event| event.frame == frame)
+            .map(|event| &event.input)
+    }
+
+    /// Rerun the session on `target`: start from the recorded state, then
+    /// apply each step's inputs and step, ending with inputs logged after the
+    /// last step.
+    pub fn replay(&self, target: &mut impl ReplayTarget) {
+        target.restore_start(&self.start, &self.params);
+        for frame in 0..self.frames {
+            for input in self.events_at(frame) {
+                target.apply_input(input);
+            }
+            target.step();
+        }
+        for input in self.events_at(self.frames) {
+            target.apply_input(input);
+        }
+    }
+
+    /// A fresh solver at the recorded size with the recorded start state.
+    pub fn simulation(&self) -> InteractiveFluid {
+        let mut fluid = InteractiveFluid::new(self.width(), self.height());
+        fluid.restore_start(&self.start, &self.params);
+        fluid
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Errors on malformed JSON or a recording from a newer version.
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let recording: Recording = serde_json::from_str(json)?;
+        if recording.version > RECORDING_VERSION {
+            return Err(format!(
+                "recording version {} is newer than supported version {}",
+                recording.version, RECORDING_VERSION
+            )
+            .into());
+        }
+        let cells = recording.width() * recording.height();
+        if recording.start.temperature.len() != cells {
+            return Err(format!("recording start state doesn't match its {}x{} grid", recording.width(), recording.height()).into());
+        }
+        Ok(recording)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// A solver a recording can be replayed against. Solvers without a matching
+/// input skip it: dye and force go through `FluidSimulation`, everything else
+/// needs the interactive solver.
+pub trait ReplayTarget: FluidSimulation {
+    /// Take on the recorded start state where the solver can hold it.
+    fn restore_start(&mut self, _start: &FluidSnapshot, _params: &SceneParams) {}
+
+    fn apply_input(&mut self, input: &Input) {
+        apply_basic(self, input);
+    }
+}
+
+/// Apply the inputs every `FluidSimulation` supports.
+pub fn apply_basic(sim: &mut (impl FluidSimulation + ?Sized), input: &Input) {
+    match *input {
+        Input::Dye { x, y, color } => sim.add_dye(x, y, color),
+        Input::Force { x, y, force, .. } => sim.add_force(x, y, Vec2::new(force.0, force.1)),
+        _ => {}
+    }
+}
+
+impl ReplayTarget for InteractiveFluid {
+    fn restore_start(&mut self, start: &FluidSnapshot, params: &SceneParams) {
+        // A grid of another size starts empty
+        let _ = start.restore(self);
+        params.apply(self);
+    }
+
+    fn apply_input(&mut self, input: &Input) {
+        match *input {
+            Input::Dye { x, y, color } => self.add_dye(x, y, color),
+            Input::RemoveDye { x, y, amount } => self.remove_dye(x, y, amount),
+            Input::BlendDye { x, y, color, weight, mode } => self.blend_dye(x, y, color, weight, mode),
+            Input::FadeDye { x, y, amount, velocity } => self.fade_dye(x, y, amount, velocity),
+            Input::Force { x, y, force, radius } => self.add_force(x, y, Vec2::new(force.0, force.1), radius),
+            Input::Heat { x, y, amount, radius } => self.add_heat(x, y, amount, radius),
+            Input::Attractor { x, y, radius, strength } => self.add_attractor(x, y, radius, strength),
+            Input::Absorb { x, y, radius, rate } => self.absorb_dye(Vec2::new(x, y), radius, rate),
+            Input::Vortex { x, y, radius, strength, falloff } => self.add_vortex(x, y, radius, strength, falloff),
+            Input::Fan { from, to, half_width, velocity } => {
+                self.add_fan(Vec2::new(from.0, from.1), Vec2::new(to.0, to.1), half_width, Vec2::new(velocity.0, velocity.1))
+            }
+            Input::Clear => self.clear(),
+            Input::ClearDye => self.clear_dye(),
+            Input::ClearVelocity => self.clear_velocity(),
+            Input::Params(params) => params.apply(self),
+            Input::Restore(ref snapshot) => {
+                let _ = snapshot.restore(self);
+            }
+        }
+    }
+}
+
+impl ReplayTarget for Solver {
+    fn restore_start(&mut self, start: &FluidSnapshot, params: &SceneParams) {
+        if let Some(fluid) = self.as_interactive_mut() {
+            fluid.restore_start(start, params);
+        }
+    }
+
+    fn apply_input(&mut self, input: &Input) {
+        match self.as_interactive_mut() {
+            Some(fluid) => fluid.apply_input(input),
+            None => apply_basic(self, input),
+        }
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl ReplayTarget for crate::gpu_functional::FunctionalGPUFluid {}
+
+#[cfg(feature = "f16-dye")]
+impl ReplayTarget for crate::HalfDyeFluid {}
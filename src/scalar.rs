@@ -0,0 +1,89 @@
+//! Extra quantities carried by the flow alongside the RGB dye, such as fuel
+//! or salinity. Each `ScalarField` diffuses and fades at its own rate and has
+//! its own colormap, so the renderer can show one or blend several.
+
+use crate::render::Colormap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalarField {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    /// Row-major, `width * height` cells
+    pub values: Vec<f32>,
+    pub prev: Vec<f32>,
+    pub diffusion: f32,
+    /// Fade rate, as `InteractiveFluid::dye_dissipation`
+    pub dissipation: f32,
+    pub colormap: Colormap,
+    /// Value drawn at the top of the colormap
+    pub range: f32,
+}
+
+impl ScalarField {
+    pub fn new(name: impl Into<String>, width: usize, height: usize) -> Self {
+        Self {
+            name: name.into(),
+            width,
+            height,
+            values: vec![0.0; width * height],
+            prev: vec![0.0; width * height],
+            diffusion: 0.0001,
+            dissipation: 0.0,
+            colormap: Colormap::default(),
+            range: 1.0,
+        }
+    }
+
+    pub fn with_diffusion(mut self, diffusion: f32) -> Self {
+        self.diffusion = diffusion;
+        self
+    }
+
+    pub fn with_dissipation(mut self, dissipation: f32) -> Self {
+        self.dissipation = dissipation;
+        self
+    }
+
+    pub fn with_colormap(mut self, colormap: Colormap) -> Self {
+        self.colormap = colormap;
+        self
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Add `amount` at (x, y), falling off to zero at `radius` like
+    /// `InteractiveFluid::add_heat`.
+    pub fn add(&mut self, x: usize, y: usize, amount: f32, radius: f32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let r_sq = radius * radius;
+        for dy in (-radius as i32)..=(radius as i32) {
+            for dx in (-radius as i32)..=(radius as i32) {
+                let px = (x as i32 + dx) as usize;
+                let py = (y as i32 + dy) as usize;
+                let dist_sq = (dx * dx + dy * dy) as f32;
+                if px < self.width && py < self.height && dist_sq <= r_sq {
+                    self.values[py * self.width + px] += amount * (1.0 - dist_sq / r_sq.max(1.0));
+                }
+            }
+        }
+    }
+
+    pub fn total(&self) -> f32 {
+        self.values.iter().sum()
+    }
+
+    /// Cell value scaled by `range` into [0, 1].
+    pub fn normalized(&self, idx: usize) -> f32 {
+        if self.range > 0.0 { (self.values[idx] / self.range).clamp(0.0, 1.0) } else { 0.0 }
+    }
+
+    pub fn color(&self, idx: usize) -> [u8; 3] {
+        self.colormap.map(self.normalized(idx))
+    }
+}
@@ -0,0 +1,161 @@
+//! Built-in starting states that show off the solver from the first frame:
+//! each one clears the fluid and lays down its own flow, dye and edges.
+//! Lengths and speeds are in shares of the grid, so they look the same at
+//! any resolution.
+
+use crate::fluid_interactive::InteractiveFluid;
+use crate::fluid_levelset::LevelSetFluid;
+use crate::BoundaryMode;
+use glam::Vec2;
+use std::f32::consts::TAU;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    /// Kelvin-Helmholtz: a band of red flowing against blue on either side
+    /// rolls up into a row of billows
+    ShearLayer,
+    /// Heavy red resting on light blue, with a ripple in the interface that
+    /// grows into falling spikes and rising mushrooms
+    RayleighTaylor,
+    /// Two counter-rotating vortices that carry each other upward
+    VortexPair,
+    /// A column of heavy dye along the left wall that collapses and runs
+    /// along the floor
+    DamBreak,
+}
+
+impl Scenario {
+    pub const ALL: [Scenario; 4] =
+        [Scenario::ShearLayer, Scenario::RayleighTaylor, Scenario::VortexPair, Scenario::DamBreak];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scenario::ShearLayer => "Shear layer",
+            Scenario::RayleighTaylor => "Rayleigh-Taylor",
+            Scenario::VortexPair => "Vortex pair",
+            Scenario::DamBreak => "Dam break",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Scenario::ShearLayer => "Opposing streams roll up into Kelvin-Helmholtz billows",
+            Scenario::RayleighTaylor => "Heavy dye on top of light dye sinks in fingers",
+            Scenario::VortexPair => "Two vortices that swim together",
+            Scenario::DamBreak => "A wall of heavy dye collapses across the floor",
+        }
+    }
+
+    /// Clear `fluid` and set up the scenario, including its edges and dye
+    /// weights; other parameters are left as they are.
+    pub fn setup(self, fluid: &mut InteractiveFluid) {
+        fluid.clear();
+        fluid.dye_density = [0.0; 3];
+        let size = fluid.width.min(fluid.height) as f32;
+        match self {
+            Scenario::ShearLayer => shear_layer(fluid, size),
+            Scenario::RayleighTaylor => rayleigh_taylor(fluid, size),
+            Scenario::VortexPair => vortex_pair(fluid, size),
+            Scenario::DamBreak => dam_break(fluid, size),
+        }
+    }
+}
+
+/// The dam break on the free-surface solver: a `width` x `height` tank with
+/// a block of water held against its left wall, ready to fall.
+pub fn dam_break_tank(width: usize, height: usize) -> LevelSetFluid {
+    let mut tank = LevelSetFluid::new(width, height);
+    tank.add_liquid_box((1, height / 3), (width / 3, height - 1));
+    tank
+}
+
+// Weight of a unit of dye in the heavy scenarios, per grid size, so the
+// dye falls across the grid in about the same time at any resolution
+const DYE_WEIGHT: f32 = 0.05;
+
+// Two shear layers, so the flow wraps around periodic edges; the middle
+// band is red and moves right, the rest is blue and moves left
+fn shear_layer(fluid: &mut InteractiveFluid, size: f32) {
+    fluid.boundary = BoundaryMode::Periodic;
+    let (w, h) = (fluid.width as f32, fluid.height as f32);
+    let speed = 0.1 * size;
+    let thickness = (0.02 * h).max(1.0);
+    let layers = [0.25 * h, 0.75 * h];
+    for y in 0..fluid.height {
+        let fy = y as f32;
+        let band = 0.5 * (((fy - layers[0]) / thickness).tanh() - ((fy - layers[1]) / thickness).tanh());
+        let near_layer: f32 = layers.iter().map(|layer| (-((fy - layer) / (4.0 * thickness)).powi(2)).exp()).sum();
+        for x in 0..fluid.width {
+            let idx = y * fluid.width + x;
+            // A small ripple of two wavelengths seeds the billows
+            let ripple = 0.1 * speed * (2.0 * TAU * x as f32 / w).sin();
+            fluid.velocity_x[idx] = speed * (2.0 * band - 1.0);
+            fluid.velocity_y[idx] = ripple * near_layer;
+            fluid.dye_r[idx] = band;
+            fluid.dye_b[idx] = 1.0 - band;
+        }
+    }
+}
+
+fn rayleigh_taylor(fluid: &mut InteractiveFluid, size: f32) {
+    fluid.boundary = BoundaryMode::NoSlip;
+    let weight = DYE_WEIGHT * size;
+    fluid.dye_density = [weight, 0.0, -weight];
+    let (w, h) = (fluid.width as f32, fluid.height as f32);
+    for x in 0..fluid.width {
+        let interface = 0.5 * h + 0.02 * h * (TAU * x as f32 / w).cos();
+        for y in 0..fluid.height {
+            let idx = y * fluid.width + x;
+            let above = 0.5 * (1.0 - (y as f32 - interface).tanh());
+            fluid.dye_r[idx] = above;
+            fluid.dye_b[idx] = 1.0 - above;
+        }
+    }
+}
+
+// Lamb-Oseen vortices side by side, turning so the fluid between them
+// flows up, which carries the pair up with it
+fn vortex_pair(fluid: &mut InteractiveFluid, size: f32) {
+    fluid.boundary = BoundaryMode::NoSlip;
+    let (w, h) = (fluid.width as f32, fluid.height as f32);
+    let core = (0.05 * size).max(1.0);
+    let strength = 0.15 * size;
+    let vortices = [
+        (Vec2::new(0.5 * w - 0.1 * size, 0.7 * h), -strength),
+        (Vec2::new(0.5 * w + 0.1 * size, 0.7 * h), strength),
+    ];
+    for y in 0..fluid.height {
+        for x in 0..fluid.width {
+            let idx = y * fluid.width + x;
+            let point = Vec2::new(x as f32, y as f32);
+            let mut velocity = Vec2::ZERO;
+            for (center, strength) in vortices {
+                let offset = point - center;
+                let r2 = offset.length_squared();
+                if r2 > 0.0 {
+                    velocity += strength * core * (1.0 - (-r2 / (core * core)).exp()) / r2 * offset.perp();
+                }
+            }
+            fluid.velocity_x[idx] = velocity.x;
+            fluid.velocity_y[idx] = velocity.y;
+            let inside = |center: Vec2| if point.distance(center) <= 1.5 * core { 1.0 } else { 0.0 };
+            fluid.dye_r[idx] = inside(vortices[0].0);
+            fluid.dye_b[idx] = inside(vortices[1].0);
+        }
+    }
+}
+
+// The dye-weight version of a dam break: a heavy column standing in still
+// fluid rather than water in air, on the floor like `dam_break_tank`'s
+// block so it has room to slump
+fn dam_break(fluid: &mut InteractiveFluid, size: f32) {
+    fluid.boundary = BoundaryMode::NoSlip;
+    fluid.dye_density = [DYE_WEIGHT * size, 0.0, 0.0];
+    let (wall, top) = (fluid.width as f32 / 3.0, fluid.height as f32 / 3.0);
+    for y in 0..fluid.height {
+        for x in 0..fluid.width {
+            let column = 0.25 * (1.0 - (x as f32 - wall).tanh()) * (1.0 + (y as f32 - top).tanh());
+            fluid.dye_r[y * fluid.width + x] = column;
+        }
+    }
+}
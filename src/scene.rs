@@ -0,0 +1,746 @@
+//! Scenes: placed elements, solver parameters, grid size and optionally the
+//! dye field and element timeline, saved as versioned JSON files. Share
+//! links carry the same scene, minus the dye and timeline, in a compact
+//! form.
+
+use crate::audio::{AudioLevels, AudioLink};
+use crate::brush::{BlendMode, Falloff};
+use crate::chemistry::Chemistry;
+use crate::container::Container;
+use crate::fluid_interactive::{ABSORB_RATE, DEFAULT_MAX_VELOCITY};
+use crate::rheology::PowerLaw;
+use crate::timeline::Timeline;
+use crate::{BoundaryMode, InteractiveFluid};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Format version written by `Scene::to_json`.
+pub const SCENE_VERSION: u32 = 1;
+
+/// Step length element strengths are given for: a force or attractor
+/// pushes with its full strength over a step of this length, and with a
+/// share of it over a shorter one.
+pub const FORCE_REFERENCE_DT: f32 = 0.1;
+
+// Share links store force directions in cells of the app's 8 px layout
+const LINK_CELL_SIZE: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PersistentElementType {
+    /// Black removes dye instead of adding it, unless `blend` is another
+    /// mode than adding
+    DyeSource {
+        color: (f32, f32, f32),
+        intensity: f32,
+        #[serde(default, skip_serializing_if = "is_add")]
+        blend: BlendMode,
+        #[serde(default, skip_serializing_if = "Emission::is_steady")]
+        emission: Emission,
+    },
+    /// `direction` is the drag that placed it, in screen pixels
+    ForceSource { direction: (f32, f32), intensity: f32 },
+    /// With `absorb`, dye drawn into the center disappears
+    AttractorSource {
+        strength: f32,
+        #[serde(default, skip_serializing_if = "is_false")]
+        absorb: bool,
+    },
+    /// Pushes fluid outward, for explosions and fountains
+    RepulsorSource { strength: f32 },
+    /// Spins fluid around it, clockwise for positive strength
+    VortexSource { strength: f32, falloff: Falloff },
+    /// Holds a steady current across a strip centered on the element, like
+    /// a fan or conveyor. `span` runs from one end of the strip to the
+    /// other and the radius is its half-width, both in grid cells;
+    /// `velocity` is in grid units.
+    FanStrip { span: (f32, f32), velocity: (f32, f32) },
+    HeatSource { amount: f32 },
+}
+
+/// When and how fast a dye source emits: a rate in simulated time, on/off
+/// pulses and a lifetime. Times are in the fluid's time units, so a source
+/// emits the same whatever the timestep or frame rate.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Emission {
+    /// Dye per unit of time at intensity 1. `None` adds the full intensity
+    /// every step, as sources always have
+    pub rate: Option<f32>,
+    /// Length of one on/off cycle; 0 emits without pauses
+    pub period: f32,
+    /// Share of each cycle spent emitting, from its start
+    pub duty: f32,
+    /// Time the source emits for before it expires; `None` never expires
+    pub lifetime: Option<f32>,
+    /// Time the source has run, moved on by `PersistentElement::advance`
+    pub age: f32,
+}
+
+impl Default for Emission {
+    fn default() -> Self {
+        Self {
+            rate: None,
+            period: 0.0,
+            duty: 0.5,
+            lifetime: None,
+            age: 0.0,
+        }
+    }
+}
+
+impl Emission {
+    /// Emits all the time, at the full intensity per step.
+    pub fn is_steady(&self) -> bool {
+        self.rate.is_none() && self.period <= 0.0 && self.lifetime.is_none()
+    }
+
+    pub fn expired(&self) -> bool {
+        self.lifetime.is_some_and(|lifetime| self.age >= lifetime)
+    }
+
+    /// Time spent emitting over the next `dt`, counting only the on part of
+    /// each pulse and nothing past the lifetime.
+    pub fn emitting_time(&self, dt: f32) -> f32 {
+        let end = self.lifetime.map_or(self.age + dt, |lifetime| lifetime.min(self.age + dt));
+        let start = self.age.min(end);
+        self.on_time(end) - self.on_time(start)
+    }
+
+    /// Multiplier on a source's per-step output for the next `dt`: the
+    /// share of the step spent emitting, or the amount `rate` gives.
+    pub fn scale(&self, dt: f32) -> f32 {
+        if self.is_steady() {
+            return 1.0;
+        }
+        if dt <= 0.0 {
+            return 0.0;
+        }
+        let emitting = self.emitting_time(dt);
+        match self.rate {
+            Some(rate) => rate * emitting,
+            None => emitting / dt,
+        }
+    }
+
+    // Time spent emitting from age 0 to `t`
+    fn on_time(&self, t: f32) -> f32 {
+        if self.period <= 0.0 {
+            return t;
+        }
+        let on = self.duty.clamp(0.0, 1.0) * self.period;
+        let cycles = (t / self.period).floor();
+        cycles * on + (t - cycles * self.period).min(on)
+    }
+}
+
+/// A source placed on the canvas that acts on the fluid every step.
+/// Position and radius are in grid cells.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PersistentElement {
+    pub element_type: PersistentElementType,
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    /// Pulse the strength with live audio; see `modulated`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<AudioLink>,
+}
+
+impl PersistentElement {
+    /// The element's main magnitude: dye or force intensity, attractor,
+    /// repulsor or vortex strength, fan speed or heat amount.
+    pub fn strength(&self) -> f32 {
+        match self.element_type {
+            PersistentElementType::DyeSource { intensity, .. } | PersistentElementType::ForceSource { intensity, .. } => intensity,
+            PersistentElementType::AttractorSource { strength, .. }
+            | PersistentElementType::RepulsorSource { strength }
+            | PersistentElementType::VortexSource { strength, .. } => strength,
+            PersistentElementType::FanStrip { velocity, .. } => (velocity.0 * velocity.0 + velocity.1 * velocity.1).sqrt(),
+            PersistentElementType::HeatSource { amount } => amount,
+        }
+    }
+
+    /// This element with `strength` in place of its own. A fan keeps its
+    /// direction, so a stopped one stays stopped.
+    pub fn with_strength(&self, strength: f32) -> Self {
+        let element_type = match self.element_type {
+            PersistentElementType::DyeSource { color, blend, emission, .. } => {
+                PersistentElementType::DyeSource { color, intensity: strength, blend, emission }
+            }
+            PersistentElementType::ForceSource { direction, .. } => PersistentElementType::ForceSource { direction, intensity: strength },
+            PersistentElementType::AttractorSource { absorb, .. } => PersistentElementType::AttractorSource { strength, absorb },
+            PersistentElementType::RepulsorSource { .. } => PersistentElementType::RepulsorSource { strength },
+            PersistentElementType::VortexSource { falloff, .. } => PersistentElementType::VortexSource { strength, falloff },
+            PersistentElementType::FanStrip { span, velocity } => {
+                let speed = self.strength();
+                let scale = if speed > 0.0 { strength / speed } else { 0.0 };
+                PersistentElementType::FanStrip { span, velocity: (velocity.0 * scale, velocity.1 * scale) }
+            }
+            PersistentElementType::HeatSource { .. } => PersistentElementType::HeatSource { amount: strength },
+        };
+        Self { element_type, ..*self }
+    }
+
+    /// This element with its strength scaled by its audio link's band in
+    /// `levels`, or unchanged without a link.
+    pub fn modulated(&self, levels: &AudioLevels) -> Self {
+        let Some(link) = self.audio else { return *self };
+        self.with_strength(self.strength() * (1.0 + link.gain * levels.get(link.band)))
+    }
+
+    /// This element on a grid `sx` and `sy` times the size: position,
+    /// radius and a fan's span and speed follow the grid.
+    pub fn rescaled(&self, sx: f32, sy: f32) -> Self {
+        let element_type = match self.element_type {
+            PersistentElementType::FanStrip { span, velocity } => PersistentElementType::FanStrip {
+                span: (span.0 * sx, span.1 * sy),
+                velocity: (velocity.0 * sx, velocity.1 * sy),
+            },
+            element_type => element_type,
+        };
+        Self {
+            element_type,
+            x: self.x * sx,
+            y: self.y * sy,
+            radius: self.radius * sx,
+            ..*self
+        }
+    }
+
+    /// Move a dye source's emission schedule on by `dt`; other elements
+    /// have none.
+    pub fn advance(&mut self, dt: f32) {
+        if let PersistentElementType::DyeSource { emission, .. } = &mut self.element_type {
+            emission.age += dt;
+        }
+    }
+
+    /// A dye source past its lifetime, which no longer emits.
+    pub fn expired(&self) -> bool {
+        matches!(self.element_type, PersistentElementType::DyeSource { emission, .. } if emission.expired())
+    }
+
+    /// Inject this element's dye, force or heat for one step. With
+    /// `smoke_heat`, dye sources also add that much heat.
+    pub fn apply(&self, fluid: &mut InteractiveFluid, smoke_heat: Option<f32>) {
+        self.apply_source(fluid, smoke_heat);
+        self.apply_force(fluid);
+    }
+
+    /// Forces, attractors, repulsors and vortices, which push the fluid
+    /// and belong inside the substep loop.
+    pub fn is_force(&self) -> bool {
+        matches!(
+            self.element_type,
+            PersistentElementType::ForceSource { .. }
+                | PersistentElementType::AttractorSource { .. }
+                | PersistentElementType::RepulsorSource { .. }
+                | PersistentElementType::VortexSource { .. }
+        )
+    }
+
+    /// Push the fluid for a step of `fluid.dt`, scaled from
+    /// `FORCE_REFERENCE_DT`, so a step split into substeps gets the same
+    /// push in smaller and stabler pieces. Other elements do nothing.
+    pub fn apply_force(&self, fluid: &mut InteractiveFluid) {
+        if !self.is_force() {
+            return;
+        }
+        let elem = self.with_strength(self.strength() * fluid.dt / FORCE_REFERENCE_DT);
+        match elem.element_type {
+            PersistentElementType::ForceSource { direction, intensity } => {
+                let x = elem.x.round() as usize;
+                let y = elem.y.round() as usize;
+                if x < fluid.width && y < fluid.height {
+                    let force = glam::Vec2::new(direction.0 * intensity, direction.1 * intensity);
+                    fluid.add_force(x, y, force, elem.radius);
+                }
+            }
+            PersistentElementType::AttractorSource { strength, absorb } => {
+                fluid.add_attractor(elem.x, elem.y, elem.radius, strength);
+                if absorb {
+                    fluid.absorb_dye(glam::Vec2::new(elem.x, elem.y), elem.radius, ABSORB_RATE);
+                }
+            }
+            PersistentElementType::RepulsorSource { strength } => {
+                fluid.add_repulsor(elem.x, elem.y, elem.radius, strength);
+            }
+            PersistentElementType::VortexSource { strength, falloff } => {
+                fluid.add_vortex(elem.x, elem.y, elem.radius, strength, falloff);
+            }
+            _ => {}
+        }
+    }
+
+    /// Inject a dye source's dye, a heat source's heat or hold a fan, once
+    /// per step. Forces do nothing here; see `apply_force`.
+    pub fn apply_source(&self, fluid: &mut InteractiveFluid, smoke_heat: Option<f32>) {
+        match self.element_type {
+            PersistentElementType::DyeSource { color, intensity, blend, emission } => {
+                let x = self.x.round() as usize;
+                let y = self.y.round() as usize;
+                let scale = emission.scale(fluid.dt);
+                let intensity = intensity * scale;
+                if x < fluid.width && y < fluid.height && scale > 0.0 {
+                    // Check if black (negative dye) is selected
+                    let is_negative = color.0 == 0.0 && color.1 == 0.0 && color.2 == 0.0;
+
+                    if blend != BlendMode::Add {
+                        // Other blends paint the same small area black clears
+                        let color = (color.0 * intensity, color.1 * intensity, color.2 * intensity);
+                        for (px, py, falloff) in source_disk(x, y, fluid.width, fluid.height) {
+                            fluid.blend_dye(px, py, color, falloff * 0.3, blend);
+                        }
+                    } else if is_negative {
+                        // Black removes dye - apply in a small area
+                        for (px, py, falloff) in source_disk(x, y, fluid.width, fluid.height) {
+                            let remove_intensity = falloff * intensity * 0.3; // Scale down for persistent
+                            fluid.remove_dye(px, py, remove_intensity);
+                        }
+                    } else {
+                        // Normal colors add dye
+                        fluid.add_dye(x, y, (color.0 * intensity, color.1 * intensity, color.2 * intensity));
+                    }
+
+                    let removes = blend == BlendMode::Subtract || (blend == BlendMode::Add && is_negative);
+                    if let (Some(heat), false) = (smoke_heat, removes) {
+                        fluid.add_heat(x, y, heat * 0.3, 2.0);
+                    }
+                }
+            }
+            PersistentElementType::HeatSource { amount } => {
+                let x = self.x.round() as usize;
+                let y = self.y.round() as usize;
+                // Scaled down like persistent dye so sources don't run away
+                fluid.add_heat(x, y, amount * 0.3, self.radius);
+            }
+            PersistentElementType::FanStrip { span, velocity } => {
+                let half = glam::Vec2::new(span.0, span.1) * 0.5;
+                let center = glam::Vec2::new(self.x, self.y);
+                fluid.add_fan(center - half, center + half, self.radius, glam::Vec2::new(velocity.0, velocity.1));
+            }
+            PersistentElementType::ForceSource { .. }
+            | PersistentElementType::AttractorSource { .. }
+            | PersistentElementType::RepulsorSource { .. }
+            | PersistentElementType::VortexSource { .. } => {}
+        }
+    }
+}
+
+// Cells within two of a dye source, with a linear falloff to the edge
+fn source_disk(x: usize, y: usize, width: usize, height: usize) -> impl Iterator<Item = (usize, usize, f32)> {
+    (-2i32..=2).flat_map(move |dy| {
+        (-2i32..=2).filter_map(move |dx| {
+            let (px, py) = ((x as i32 + dx) as usize, (y as i32 + dy) as usize);
+            let dist_sq = (dx * dx + dy * dy) as f32;
+            (px < width && py < height && dist_sq <= 4.0).then(|| (px, py, 1.0 - dist_sq / 4.0))
+        })
+    })
+}
+
+fn is_add(blend: &BlendMode) -> bool {
+    *blend == BlendMode::Add
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Solver settings a scene restores.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SceneParams {
+    pub dt: f32,
+    pub viscosity: f32,
+    #[serde(default)]
+    pub velocity_damping: f32,
+    #[serde(default)]
+    pub surface_tension: f32,
+    #[serde(default)]
+    pub shear_model: Option<PowerLaw>,
+    pub dye_diffusion: f32,
+    pub dye_dissipation: f32,
+    #[serde(default)]
+    pub dye_decay: [f32; 3],
+    #[serde(default)]
+    pub chemistry: Option<Chemistry>,
+    pub pressure_iterations: usize,
+    #[serde(default)]
+    pub divergence_tolerance: Option<f32>,
+    pub buoyancy: f32,
+    #[serde(default)]
+    pub dye_density: [f32; 3],
+    pub cooling: f32,
+    // Scenes saved before boundary modes existed had walls
+    #[serde(default)]
+    pub boundary: BoundaryMode,
+    #[serde(default = "default_max_velocity")]
+    pub max_velocity: f32,
+    #[serde(default = "default_cell_aspect")]
+    pub cell_aspect: f32,
+}
+
+fn default_max_velocity() -> f32 {
+    DEFAULT_MAX_VELOCITY
+}
+
+fn default_cell_aspect() -> f32 {
+    1.0
+}
+
+impl SceneParams {
+    pub fn capture(fluid: &InteractiveFluid) -> Self {
+        Self {
+            dt: fluid.dt,
+            viscosity: fluid.viscosity,
+            velocity_damping: fluid.velocity_damping,
+            surface_tension: fluid.surface_tension,
+            shear_model: fluid.shear_model,
+            dye_diffusion: fluid.dye_diffusion,
+            dye_dissipation: fluid.dye_dissipation,
+            dye_decay: fluid.dye_decay,
+            chemistry: fluid.chemistry,
+            pressure_iterations: fluid.pressure_iterations,
+            divergence_tolerance: fluid.divergence_tolerance,
+            buoyancy: fluid.buoyancy,
+            dye_density: fluid.dye_density,
+            cooling: fluid.cooling,
+            boundary: fluid.boundary,
+            max_velocity: fluid.max_velocity,
+            cell_aspect: fluid.cell_aspect,
+        }
+    }
+
+    pub fn apply(&self, fluid: &mut InteractiveFluid) {
+        fluid.dt = self.dt;
+        fluid.viscosity = self.viscosity;
+        fluid.velocity_damping = self.velocity_damping;
+        fluid.surface_tension = self.surface_tension;
+        fluid.shear_model = self.shear_model;
+        fluid.dye_diffusion = self.dye_diffusion;
+        fluid.dye_dissipation = self.dye_dissipation;
+        fluid.dye_decay = self.dye_decay;
+        fluid.chemistry = self.chemistry;
+        fluid.pressure_iterations = self.pressure_iterations;
+        fluid.divergence_tolerance = self.divergence_tolerance;
+        fluid.buoyancy = self.buoyancy;
+        fluid.dye_density = self.dye_density;
+        fluid.cooling = self.cooling;
+        fluid.boundary = self.boundary;
+        fluid.max_velocity = self.max_velocity;
+        fluid.cell_aspect = self.cell_aspect;
+    }
+}
+
+impl Default for SceneParams {
+    fn default() -> Self {
+        Self::capture(&InteractiveFluid::new(1, 1))
+    }
+}
+
+/// Dye channels of a scene, row-major at the scene's grid size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DyeField {
+    pub r: Vec<f32>,
+    pub g: Vec<f32>,
+    pub b: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+    /// Grid the element positions and dye refer to
+    pub width: usize,
+    pub height: usize,
+    pub params: SceneParams,
+    pub elements: Vec<PersistentElement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dye: Option<DyeField>,
+    /// Keyframes for the elements; `capture` leaves it out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<Timeline>,
+    /// Shape of the fluid domain, outside which cells are solid; `capture`
+    /// leaves it out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
+}
+
+impl Scene {
+    pub fn capture(fluid: &InteractiveFluid, elements: &[PersistentElement], include_dye: bool) -> Self {
+        Self {
+            version: SCENE_VERSION,
+            width: fluid.width,
+            height: fluid.height,
+            params: SceneParams::capture(fluid),
+            elements: elements.to_vec(),
+            dye: include_dye.then(|| DyeField {
+                r: fluid.dye_r.clone(),
+                g: fluid.dye_g.clone(),
+                b: fluid.dye_b.clone(),
+            }),
+            timeline: None,
+            container: None,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse and check a scene, rejecting versions newer than this build.
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let scene: Scene = serde_json::from_str(json)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.version > SCENE_VERSION {
+            return Err(format!("scene version {} is newer than supported version {}", self.version, SCENE_VERSION).into());
+        }
+        if self.width < 3 || self.height < 3 {
+            return Err(format!("scene grid {}x{} is too small", self.width, self.height).into());
+        }
+        if let Some(dye) = &self.dye {
+            let size = self.width * self.height;
+            if dye.r.len() != size || dye.g.len() != size || dye.b.len() != size {
+                return Err(format!("scene dye doesn't match its {}x{} grid", self.width, self.height).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// A fresh simulation at the scene's grid size, with its parameters, dye
+    /// and container.
+    pub fn simulation(&self) -> InteractiveFluid {
+        let mut fluid = InteractiveFluid::new(self.width, self.height);
+        self.params.apply(&mut fluid);
+        if let Some(dye) = &self.dye {
+            fluid.dye_r.copy_from_slice(&dye.r);
+            fluid.dye_g.copy_from_slice(&dye.g);
+            fluid.dye_b.copy_from_slice(&dye.b);
+        }
+        fluid.obstacles = self.container.as_ref().map(|container| container.mask(self.width, self.height));
+        fluid
+    }
+
+    /// The elements moved onto a `width` x `height` grid.
+    pub fn elements_for(&self, width: usize, height: usize) -> Vec<PersistentElement> {
+        let sx = width as f32 / self.width as f32;
+        let sy = height as f32 / self.height as f32;
+        self.elements
+            .iter()
+            .map(|elem| {
+                let elem = elem.rescaled(sx, sy);
+                PersistentElement {
+                    x: elem.x.clamp(0.0, width as f32 - 1.0),
+                    y: elem.y.clamp(0.0, height as f32 - 1.0),
+                    ..elem
+                }
+            })
+            .collect()
+    }
+
+    /// The timeline with its keys moved like `elements_for` moves the elements.
+    pub fn timeline_for(&self, width: usize, height: usize) -> Option<Timeline> {
+        let sx = width as f32 / self.width as f32;
+        let sy = height as f32 / self.height as f32;
+        self.timeline.as_ref().map(|timeline| timeline.rescaled(sx, sy))
+    }
+
+    /// The `s=...` fragment of a share link: elements at normalized
+    /// positions, the container, and parameters when they differ from the
+    /// defaults. Dye is left out to keep links short.
+    pub fn to_link(&self) -> String {
+        if self.elements.is_empty() && self.params == SceneParams::default() && self.container.is_none() {
+            return String::from("s=");
+        }
+
+        let width = self.width as f32;
+        let height = self.height as f32;
+        let extent = width.max(height);
+        let e = self
+            .elements
+            .iter()
+            .map(|elem| {
+                let (x, y, r) = ((elem.x / width).clamp(0.0, 1.0), (elem.y / height).clamp(0.0, 1.0), elem.radius / extent);
+                match elem.element_type {
+                    PersistentElementType::DyeSource { color, intensity, blend, emission } => ShareElem::Dye {
+                        x,
+                        y,
+                        r,
+                        c: [color.0, color.1, color.2],
+                        i: intensity,
+                        b: blend,
+                        m: emission,
+                    },
+                    PersistentElementType::ForceSource { direction, intensity } => ShareElem::Force {
+                        x,
+                        y,
+                        r,
+                        d: [direction.0 / LINK_CELL_SIZE, direction.1 / LINK_CELL_SIZE],
+                        i: intensity,
+                    },
+                    PersistentElementType::AttractorSource { strength, absorb } => ShareElem::Attr { x, y, r, s: strength, a: absorb },
+                    PersistentElementType::RepulsorSource { strength } => ShareElem::Rep { x, y, r, s: strength },
+                    PersistentElementType::VortexSource { strength, falloff } => ShareElem::Vortex { x, y, r, s: strength, f: falloff },
+                    PersistentElementType::FanStrip { span, velocity } => ShareElem::Fan {
+                        x,
+                        y,
+                        r,
+                        l: [span.0 / width, span.1 / height],
+                        u: [velocity.0, velocity.1],
+                    },
+                    PersistentElementType::HeatSource { amount } => ShareElem::Heat { x, y, r, a: amount },
+                }
+            })
+            .collect();
+
+        let state = ShareState {
+            v: 1,
+            w: self.width as u32,
+            h: self.height as u32,
+            e,
+            p: (self.params != SceneParams::default()).then_some(self.params),
+            k: self.container.clone(),
+        };
+        match serde_json::to_string(&state) {
+            Ok(json) => format!("s={}", URL_SAFE_NO_PAD.encode(json.as_bytes())),
+            Err(_) => String::from("s="),
+        }
+    }
+
+    /// Read a share link's fragment, e.g. `#s=...` or `a=1&s=...`. Links
+    /// without parameters get the defaults.
+    pub fn from_link(fragment: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let trimmed = fragment.strip_prefix('#').unwrap_or(fragment);
+        let b64 = trimmed
+            .split('&')
+            .find_map(|part| part.strip_prefix("s="))
+            .filter(|value| !value.is_empty())
+            .ok_or("link has no scene")?;
+        let bytes = URL_SAFE_NO_PAD.decode(b64).map_err(|e| e.to_string())?;
+        let state: ShareState = serde_json::from_slice(&bytes)?;
+
+        let (w, h) = (state.w.max(3) as usize, state.h.max(3) as usize);
+        let (width, height) = (w as f32, h as f32);
+        let extent = width.max(height);
+        let at = |x: f32, y: f32| ((x * width).clamp(0.0, width - 1.0), (y * height).clamp(0.0, height - 1.0));
+        let elements = state
+            .e
+            .into_iter()
+            .map(|se| {
+                let (element_type, x, y, r) = match se {
+                    ShareElem::Dye { x, y, r, c, i, b, m } => (
+                        PersistentElementType::DyeSource { color: (c[0], c[1], c[2]), intensity: i, blend: b, emission: m },
+                        x,
+                        y,
+                        r,
+                    ),
+                    ShareElem::Force { x, y, r, d, i } => (
+                        PersistentElementType::ForceSource {
+                            direction: (d[0] * LINK_CELL_SIZE, d[1] * LINK_CELL_SIZE),
+                            intensity: i,
+                        },
+                        x,
+                        y,
+                        r,
+                    ),
+                    ShareElem::Attr { x, y, r, s, a } => (PersistentElementType::AttractorSource { strength: s, absorb: a }, x, y, r),
+                    ShareElem::Rep { x, y, r, s } => (PersistentElementType::RepulsorSource { strength: s }, x, y, r),
+                    ShareElem::Vortex { x, y, r, s, f } => (PersistentElementType::VortexSource { strength: s, falloff: f }, x, y, r),
+                    ShareElem::Fan { x, y, r, l, u } => (
+                        PersistentElementType::FanStrip {
+                            span: (l[0] * width, l[1] * height),
+                            velocity: (u[0], u[1]),
+                        },
+                        x,
+                        y,
+                        r,
+                    ),
+                    ShareElem::Heat { x, y, r, a } => (PersistentElementType::HeatSource { amount: a }, x, y, r),
+                };
+                let (x, y) = at(x, y);
+                PersistentElement {
+                    element_type,
+                    x,
+                    y,
+                    radius: (r * extent).max(1e-3),
+                    audio: None,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            version: SCENE_VERSION,
+            width: w,
+            height: h,
+            params: state.p.unwrap_or_default(),
+            elements,
+            dye: None,
+            timeline: None,
+            container: state.k,
+        })
+    }
+}
+
+// Compact share link encoding, with one-letter keys to keep URLs and QR
+// codes small
+#[derive(Serialize, Deserialize, Debug)]
+struct ShareState {
+    v: u8,            // schema version
+    w: u32,           // grid width at encoding time
+    h: u32,           // grid height at encoding time
+    e: Vec<ShareElem>, // elements
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    p: Option<SceneParams>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    k: Option<Container>, // container, already in shares of the grid
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "t")]
+enum ShareElem {
+    #[serde(rename = "d")]
+    Dye {
+        x: f32,
+        y: f32,
+        r: f32,
+        c: [f32; 3],
+        i: f32,
+        #[serde(default, skip_serializing_if = "is_add")]
+        b: BlendMode,
+        #[serde(default, skip_serializing_if = "Emission::is_steady")]
+        m: Emission,
+    },
+    #[serde(rename = "f")]
+    Force { x: f32, y: f32, r: f32, d: [f32; 2], i: f32 },
+    #[serde(rename = "a")]
+    Attr {
+        x: f32,
+        y: f32,
+        r: f32,
+        s: f32,
+        #[serde(default, skip_serializing_if = "is_false")]
+        a: bool,
+    },
+    #[serde(rename = "s")]
+    Rep { x: f32, y: f32, r: f32, s: f32 },
+    #[serde(rename = "v")]
+    Vortex { x: f32, y: f32, r: f32, s: f32, f: Falloff },
+    #[serde(rename = "w")]
+    Fan { x: f32, y: f32, r: f32, l: [f32; 2], u: [f32; 2] },
+    #[serde(rename = "h")]
+    Heat { x: f32, y: f32, r: f32, a: f32 },
+}
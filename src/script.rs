@@ -0,0 +1,236 @@
+//! Rhai scripts that build and animate scenes, headless with `--script` or
+//! from the app's script console. Top-level code runs once; a `frame(n)`
+//! function, if the script defines one, runs before every step after that.
+//!
+//! ```rhai
+//! set_viscosity(0.0001);
+//! place_element(#{
+//!     element_type: #{ type: "VortexSource", strength: 3.0, falloff: "Gaussian" },
+//!     x: width() / 2, y: height() / 2, radius: 20,
+//! });
+//! fn frame(n) {
+//!     add_dye(10, height() / 2, 1.0, 0.3, 0.1);
+//!     add_force(10, height() / 2, 20, 0, 3);
+//! }
+//! ```
+//!
+//! Functions queue their effects, which are applied in order once the
+//! script returns, so scripts can't read the fields back; `step(n)` is
+//! queued like the rest.
+
+use crate::{ConfigurableSimulation, InteractiveFluid, Param, PersistentElement};
+use glam::Vec2;
+use rhai::{AST, Dynamic, Engine, EvalAltResult, Scope};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Enough for generous loops, small enough that a runaway one returns
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+/// One queued effect of a script call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptOp {
+    Dye { x: usize, y: usize, color: (f32, f32, f32) },
+    Force { x: usize, y: usize, force: Vec2, radius: f32 },
+    Place(PersistentElement),
+    ClearElements,
+    SetParam(Param, f32),
+    /// Apply the elements and step, this many times
+    Step(usize),
+    Clear,
+}
+
+impl ScriptOp {
+    pub fn apply(&self, fluid: &mut InteractiveFluid, elements: &mut Vec<PersistentElement>) {
+        match self {
+            ScriptOp::Dye { x, y, color } => fluid.add_dye(*x, *y, *color),
+            ScriptOp::Force { x, y, force, radius } => fluid.add_force(*x, *y, *force, *radius),
+            ScriptOp::Place(elem) => elements.push(*elem),
+            ScriptOp::ClearElements => elements.clear(),
+            // Checked when the script queued it
+            ScriptOp::SetParam(param, value) => {
+                let _ = fluid.set_param(*param, *value);
+            }
+            ScriptOp::Step(count) => {
+                for _ in 0..*count {
+                    for elem in elements.iter_mut() {
+                        elem.apply(fluid, None);
+                        elem.advance(fluid.dt);
+                    }
+                    fluid.step();
+                }
+            }
+            ScriptOp::Clear => fluid.clear(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    ops: Vec<ScriptOp>,
+    width: usize,
+    height: usize,
+    output: Vec<String>,
+}
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    shared: Rc<RefCell<Shared>>,
+    /// Frames run so far, passed to `frame(n)`
+    pub frame: i64,
+}
+
+impl Script {
+    pub fn compile(source: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let shared = Rc::new(RefCell::new(Shared::default()));
+        let engine = engine(&shared);
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+        Ok(Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            shared,
+            frame: 0,
+        })
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::compile(&std::fs::read_to_string(path)?)
+    }
+
+    /// True when the script defines `frame(n)`.
+    pub fn animates(&self) -> bool {
+        self.ast.iter_functions().any(|f| f.name == "frame" && f.params.len() == 1)
+    }
+
+    /// Run the top-level code against `fluid` and `elements`.
+    pub fn setup(&mut self, fluid: &mut InteractiveFluid, elements: &mut Vec<PersistentElement>) -> Result<(), Box<dyn std::error::Error>> {
+        self.begin(fluid);
+        let result = self.engine.run_ast_with_scope(&mut self.scope, &self.ast);
+        self.finish(fluid, elements, result)
+    }
+
+    /// Call `frame(n)` and advance the frame count; nothing without one.
+    pub fn run_frame(&mut self, fluid: &mut InteractiveFluid, elements: &mut Vec<PersistentElement>) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.animates() {
+            return Ok(());
+        }
+        self.begin(fluid);
+        let result = self.engine.call_fn::<Dynamic>(&mut self.scope, &self.ast, "frame", (self.frame,)).map(|_| ());
+        self.frame += 1;
+        self.finish(fluid, elements, result)
+    }
+
+    /// Lines printed by the script since the last call.
+    pub fn take_output(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.shared.borrow_mut().output)
+    }
+
+    fn begin(&self, fluid: &InteractiveFluid) {
+        let mut shared = self.shared.borrow_mut();
+        shared.ops.clear();
+        (shared.width, shared.height) = (fluid.width, fluid.height);
+    }
+
+    // Queued ops up to an error still apply, as they would have live
+    fn finish(
+        &self,
+        fluid: &mut InteractiveFluid,
+        elements: &mut Vec<PersistentElement>,
+        result: Result<(), Box<EvalAltResult>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ops = std::mem::take(&mut self.shared.borrow_mut().ops);
+        for op in &ops {
+            op.apply(fluid, elements);
+        }
+        result.map_err(|e| e.to_string().into())
+    }
+}
+
+// A number from either a Rhai float or an integer
+fn number(value: &Dynamic, name: &str) -> Result<f32, Box<EvalAltResult>> {
+    value
+        .as_float()
+        .map(|v| v as f32)
+        .or_else(|_| value.as_int().map(|v| v as f32))
+        .map_err(|_| format!("{}: expected a number, got {}", name, value.type_name()).into())
+}
+
+// A cell index, clamped to the grid
+fn cell(value: &Dynamic, name: &str, size: usize) -> Result<usize, Box<EvalAltResult>> {
+    Ok((number(value, name)?.max(0.0) as usize).min(size.saturating_sub(1)))
+}
+
+fn engine(shared: &Rc<RefCell<Shared>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let s = Rc::clone(shared);
+    engine.on_print(move |text| s.borrow_mut().output.push(text.to_string()));
+    let s = Rc::clone(shared);
+    engine.register_fn("width", move || s.borrow().width as i64);
+    let s = Rc::clone(shared);
+    engine.register_fn("height", move || s.borrow().height as i64);
+
+    let s = Rc::clone(shared);
+    engine.register_fn("add_dye", move |x: Dynamic, y: Dynamic, r: Dynamic, g: Dynamic, b: Dynamic| {
+        let mut shared = s.borrow_mut();
+        let op = ScriptOp::Dye {
+            x: cell(&x, "add_dye", shared.width)?,
+            y: cell(&y, "add_dye", shared.height)?,
+            color: (number(&r, "add_dye")?, number(&g, "add_dye")?, number(&b, "add_dye")?),
+        };
+        shared.ops.push(op);
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+
+    let s = Rc::clone(shared);
+    engine.register_fn("add_force", move |x: Dynamic, y: Dynamic, fx: Dynamic, fy: Dynamic, radius: Dynamic| {
+        let mut shared = s.borrow_mut();
+        let op = ScriptOp::Force {
+            x: cell(&x, "add_force", shared.width)?,
+            y: cell(&y, "add_force", shared.height)?,
+            force: Vec2::new(number(&fx, "add_force")?, number(&fy, "add_force")?),
+            radius: number(&radius, "add_force")?,
+        };
+        shared.ops.push(op);
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+
+    // Same fields as elements in saved scene files. Going through JSON lets
+    // whole numbers stand in for floats, as they do in scene files
+    let s = Rc::clone(shared);
+    engine.register_fn("place_element", move |element: rhai::Map| {
+        let json: serde_json::Value = rhai::serde::from_dynamic(&element.into())?;
+        let elem: PersistentElement = serde_json::from_value(json).map_err(|e| format!("place_element: {}", e))?;
+        s.borrow_mut().ops.push(ScriptOp::Place(elem));
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let s = Rc::clone(shared);
+    engine.register_fn("clear_elements", move || s.borrow_mut().ops.push(ScriptOp::ClearElements));
+    let s = Rc::clone(shared);
+    engine.register_fn("clear", move || s.borrow_mut().ops.push(ScriptOp::Clear));
+
+    let s = Rc::clone(shared);
+    engine.register_fn("step", move || s.borrow_mut().ops.push(ScriptOp::Step(1)));
+    let s = Rc::clone(shared);
+    engine.register_fn("step", move |count: i64| s.borrow_mut().ops.push(ScriptOp::Step(count.max(0) as usize)));
+
+    for (name, param) in [
+        ("set_dt", Param::Dt),
+        ("set_viscosity", Param::Viscosity),
+        ("set_diffusion", Param::Diffusion),
+        ("set_dissipation", Param::Dissipation),
+    ] {
+        let s = Rc::clone(shared);
+        engine.register_fn(name, move |value: Dynamic| {
+            let value = param.validate(number(&value, name)?).map_err(|e| e.to_string())?;
+            s.borrow_mut().ops.push(ScriptOp::SetParam(param, value));
+            Ok::<_, Box<EvalAltResult>>(())
+        });
+    }
+
+    engine
+}
@@ -0,0 +1,54 @@
+//! Statistics for one participant's session at an installation: strokes
+//! drawn, colors used and time simulated.
+
+/// Counters an app updates as someone plays. `reset` when the next person
+/// steps up.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    /// Strokes started on the canvas, with any tool
+    pub strokes: u32,
+    /// Distinct dye colors used, in order of first use
+    pub colors: Vec<(f32, f32, f32)>,
+    /// Simulated seconds
+    pub sim_time: f32,
+    pub steps: u64,
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a stroke, remembering its dye color if the tool has one.
+    pub fn record_stroke(&mut self, color: Option<(f32, f32, f32)>) {
+        self.strokes += 1;
+        if let Some(color) = color
+            && !self.colors.contains(&color)
+        {
+            self.colors.push(color);
+        }
+    }
+
+    pub fn record_step(&mut self, dt: f32) {
+        self.steps += 1;
+        self.sim_time += dt;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// One line for a caption, e.g. "12 strokes · 3 colors · 1:05".
+    pub fn summary(&self) -> String {
+        let seconds = self.sim_time.max(0.0) as u32;
+        format!(
+            "{} stroke{} · {} color{} · {}:{:02}",
+            self.strokes,
+            if self.strokes == 1 { "" } else { "s" },
+            self.colors.len(),
+            if self.colors.len() == 1 { "" } else { "s" },
+            seconds / 60,
+            seconds % 60
+        )
+    }
+}
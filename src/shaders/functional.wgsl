@@ -0,0 +1,452 @@
+// Helper functions
+fn floor(x: f32) -> f32 {
+    return f32(i32(x));
+}
+
+fn max(a: f32, b: f32) -> f32 {
+    return select(b, a, a >= b);
+}
+
+fn min(a: f32, b: f32) -> f32 {
+    return select(a, b, a <= b);
+}
+
+fn select(a: f32, b: f32, condition: bool) -> f32 {
+    if (condition) {
+        return a;
+    } else {
+        return b;
+    }
+}
+struct SimulationParams {
+    width: u32,
+    height: u32,
+    dt: f32,
+    viscosity: f32,
+    diffusion: f32,
+    boundary: u32,
+    decay_r: f32,
+    decay_g: f32,
+    decay_b: f32,
+    splat_count: u32,
+    inject_x: u32,
+    inject_y: u32,
+    // Cell height over width
+    cell_aspect: f32,
+    _padding0: u32,
+    _padding1: u32,
+    _padding2: u32,
+}
+
+// Dye or force added to one cell by the inject pass
+struct Splat {
+    x: u32,
+    y: u32,
+    kind: u32,
+    _padding: u32,
+    value: vec4<f32>,
+}
+
+const DYE_SPLAT: u32 = 0u;
+const FORCE_SPLAT: u32 = 1u;
+
+const NO_SLIP: u32 = 0u;
+const FREE_SLIP: u32 = 1u;
+const PERIODIC: u32 = 2u;
+const OPEN: u32 = 3u;
+
+// A backtraced coordinate folded into the interior [1, size - 1)
+fn wrap_coord(p: f32, size: u32) -> f32 {
+    let n = f32(size - 2u);
+    var q = p - 1.0;
+    q = q - n * f32(i32(q / n));
+    if (q < 0.0) { q = q + n; }
+    if (q >= n) { q = 0.0; }
+    return q + 1.0;
+}
+
+// Interior cell an edge cell takes its value from: the adjacent
+// one, or the one on the far side when wrapping
+// (the module's own select only takes f32, hence the branches)
+fn ring_source(coord: vec2<u32>, wrap: bool) -> vec2<u32> {
+    var source = coord;
+    let last_x = params.width - 1u;
+    let last_y = params.height - 1u;
+    var near_x = 1u;
+    var far_x = last_x - 1u;
+    var near_y = 1u;
+    var far_y = last_y - 1u;
+    if (wrap) {
+        near_x = last_x - 1u;
+        far_x = 1u;
+        near_y = last_y - 1u;
+        far_y = 1u;
+    }
+    if (coord.x == 0u) { source.x = near_x; }
+    if (coord.x == last_x) { source.x = far_x; }
+    if (coord.y == 0u) { source.y = near_y; }
+    if (coord.y == last_y) { source.y = far_y; }
+    return source;
+}
+
+@group(0) @binding(0)
+var<uniform> params: SimulationParams;
+
+@group(0) @binding(1)
+var velocity_texture: texture_storage_2d<rgba32float, read_write>;
+
+@group(0) @binding(2)
+var velocity_prev_texture: texture_storage_2d<rgba32float, read_write>;
+
+@group(0) @binding(3)
+var dye_texture: texture_storage_2d<rgba32float, read_write>;
+
+@group(0) @binding(4)
+var dye_prev_texture: texture_storage_2d<rgba32float, read_write>;
+
+@group(0) @binding(5)
+var<storage, read> splats: array<Splat>;
+
+// Factor on the viscosity per cell, row-major; 1 where nothing is painted
+@group(0) @binding(6)
+var<storage, read> viscosity_factor: array<f32>;
+
+fn sample_velocity(coord: vec2<u32>) -> vec2<f32> {
+    let texel = textureLoad(velocity_texture, coord);
+    return vec2<f32>(texel.x, texel.y);
+}
+
+fn sample_velocity_prev(coord: vec2<u32>) -> vec2<f32> {
+    let texel = textureLoad(velocity_prev_texture, coord);
+    return vec2<f32>(texel.x, texel.y);
+}
+
+fn sample_dye(coord: vec2<u32>) -> vec3<f32> {
+    let texel = textureLoad(dye_texture, coord);
+    return vec3<f32>(texel.x, texel.y, texel.z);
+}
+
+fn sample_dye_prev(coord: vec2<u32>) -> vec3<f32> {
+    let texel = textureLoad(dye_prev_texture, coord);
+    return vec3<f32>(texel.x, texel.y, texel.z);
+}
+
+fn set_velocity(coord: vec2<u32>, velocity: vec2<f32>) {
+    textureStore(velocity_texture, coord, vec4<f32>(velocity.x, velocity.y, 0.0, 1.0));
+}
+
+fn set_dye(coord: vec2<u32>, dye: vec3<f32>) {
+    textureStore(dye_texture, coord, vec4<f32>(dye.x, dye.y, dye.z, 1.0));
+}
+
+// Velocity diffusion matching CPU implementation
+@compute @workgroup_size(8, 8)
+fn diffuse_velocity(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord = vec2<u32>(global_id.x, global_id.y);
+    let x = i32(coord.x);
+    let y = i32(coord.y);
+
+    // Skip boundaries (handled separately)
+    if (x <= 0 || x >= i32(params.width - 1) || y <= 0 || y >= i32(params.height - 1)) {
+        return;
+    }
+
+    // Sample neighbors
+    let left = sample_velocity_prev(vec2<u32>(u32(x - 1), u32(y)));
+    let right = sample_velocity_prev(vec2<u32>(u32(x + 1), u32(y)));
+    let up = sample_velocity_prev(vec2<u32>(u32(x), u32(y - 1)));
+    let down = sample_velocity_prev(vec2<u32>(u32(x), u32(y + 1)));
+
+    // Velocity diffusion with CPU scaling (no width*height factor);
+    // taller cells couple more weakly along y
+    let a = params.dt * params.viscosity * viscosity_factor[coord.y * params.width + coord.x];
+    let ay = a / (params.cell_aspect * params.cell_aspect);
+    let current = sample_velocity_prev(coord);
+    let diffused = (current + a * (left + right) + ay * (up + down)) / (1.0 + 2.0 * a + 2.0 * ay);
+
+    set_velocity(coord, diffused);
+}
+
+// Dye diffusion matching CPU implementation
+@compute @workgroup_size(8, 8)
+fn diffuse_dye(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord = vec2<u32>(global_id.x, global_id.y);
+    let x = i32(coord.x);
+    let y = i32(coord.y);
+
+    // Skip boundaries (handled separately)
+    if (x <= 0 || x >= i32(params.width - 1) || y <= 0 || y >= i32(params.height - 1)) {
+        return;
+    }
+
+    // Sample neighbors from PREVIOUS dye buffer
+    let dye_left = sample_dye_prev(vec2<u32>(u32(x - 1), u32(y)));
+    let dye_right = sample_dye_prev(vec2<u32>(u32(x + 1), u32(y)));
+    let dye_up = sample_dye_prev(vec2<u32>(u32(x), u32(y - 1)));
+    let dye_down = sample_dye_prev(vec2<u32>(u32(x), u32(y + 1)));
+
+    // Dye diffusion with CPU scaling (no width*height factor)
+    let b = params.dt * params.diffusion;
+    let by = b / (params.cell_aspect * params.cell_aspect);
+    let current = sample_dye_prev(coord);
+    let diffused = (current + b * (dye_left + dye_right) + by * (dye_up + dye_down)) / (1.0 + 2.0 * b + 2.0 * by);
+
+    set_dye(coord, diffused);
+}
+
+// Velocity advection using previous velocity field (like CPU)
+@compute @workgroup_size(8, 8)
+fn advect_velocity(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord = vec2<u32>(global_id.x, global_id.y);
+    let x = i32(coord.x);
+    let y = i32(coord.y);
+
+    // Skip boundaries
+    if (x <= 0 || x >= i32(params.width - 1) || y <= 0 || y >= i32(params.height - 1)) {
+        return;
+    }
+
+    // Sample previous velocity (like CPU version)
+    let velocity_prev = sample_velocity_prev(coord);
+
+    // Backtrace position matching CPU scaling (no width*height factor);
+    // velocity is in cell widths, so y moves 1 / aspect cells per unit
+    let src_x = f32(x) - params.dt * velocity_prev.x;
+    let src_y = f32(y) - params.dt * velocity_prev.y / params.cell_aspect;
+
+    // Clamp to valid range with border (same as CPU), or wrap
+    var clamped_x = max(0.5, min(src_x, f32(params.width - 1) - 0.5));
+    var clamped_y = max(0.5, min(src_y, f32(params.height - 1) - 0.5));
+    if (params.boundary == PERIODIC) {
+        clamped_x = wrap_coord(src_x, params.width);
+        clamped_y = wrap_coord(src_y, params.height);
+    }
+
+    // Bilinear interpolation matching CPU
+    let x0 = u32(floor(clamped_x));
+    let x1 = u32(min(f32(params.width - 1), f32(x0) + 1.0));
+    let y0 = u32(floor(clamped_y));
+    let y1 = u32(min(f32(params.height - 1), f32(y0) + 1.0));
+
+    let tx = clamped_x - f32(x0);
+    let ty = clamped_y - f32(y0);
+
+    // Advect velocity using previous velocity field (like CPU)
+    let v00 = sample_velocity_prev(vec2<u32>(x0, y0));
+    let v01 = sample_velocity_prev(vec2<u32>(x1, y0));
+    let v10 = sample_velocity_prev(vec2<u32>(x0, y1));
+    let v11 = sample_velocity_prev(vec2<u32>(x1, y1));
+
+    let advected_velocity = (1.0 - tx) * (1.0 - ty) * v00
+        + tx * (1.0 - ty) * v01
+        + (1.0 - tx) * ty * v10
+        + tx * ty * v11;
+
+    set_velocity(coord, advected_velocity);
+}
+
+// Dye advection using current velocity field
+@compute @workgroup_size(8, 8)
+fn advect_dye(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord = vec2<u32>(global_id.x, global_id.y);
+    let x = f32(global_id.x);
+    let y = f32(global_id.y);
+
+    // Get velocity
+    let vel = sample_velocity(coord);
+
+    // Backtrace
+    var src_x = x - params.dt * vel.x;
+    var src_y = y - params.dt * vel.y / params.cell_aspect;
+
+    // Clamp
+    if (src_x < 0.5) { src_x = 0.5; }
+    if (src_x > f32(params.width) - 1.5) { src_x = f32(params.width) - 1.5; }
+    if (src_y < 0.5) { src_y = 0.5; }
+    if (src_y > f32(params.height) - 1.5) { src_y = f32(params.height) - 1.5; }
+    if (params.boundary == PERIODIC) {
+        src_x = wrap_coord(x - params.dt * vel.x, params.width);
+        src_y = wrap_coord(y - params.dt * vel.y / params.cell_aspect, params.height);
+    }
+
+    // Integer coordinates
+    let i0 = u32(src_x);
+    let j0 = u32(src_y);
+    let i1 = i0 + 1u;
+    let j1 = j0 + 1u;
+
+    // Fractions
+    let s1 = src_x - f32(i0);
+    let s0 = 1.0 - s1;
+    let t1 = src_y - f32(j0);
+    let t0 = 1.0 - t1;
+
+    // Sample and interpolate
+    let dye = s0 * (t0 * sample_dye_prev(vec2<u32>(i0, j0)) + t1 * sample_dye_prev(vec2<u32>(i0, j1)))
+            + s1 * (t0 * sample_dye_prev(vec2<u32>(i1, j0)) + t1 * sample_dye_prev(vec2<u32>(i1, j1)));
+
+    // Per-channel fade, as the CPU's dissipate_dye
+    let decay = vec3<f32>(params.decay_r, params.decay_g, params.decay_b);
+    set_dye(coord, dye * exp(-decay * params.dt));
+}
+
+// Boundary conditions for velocity
+@compute @workgroup_size(8, 8)
+fn set_velocity_boundaries(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord = vec2<u32>(global_id.x, global_id.y);
+    let x = i32(coord.x);
+    let y = i32(coord.y);
+
+    // Edge velocity per boundary mode, as on the CPU
+    if (x == 0 || x == i32(params.width - 1) || y == 0 || y == i32(params.height - 1)) {
+        if (params.boundary == NO_SLIP) {
+            set_velocity(coord, vec2<f32>(0.0));
+            return;
+        }
+        var velocity = sample_velocity(ring_source(coord, params.boundary == PERIODIC));
+        if (params.boundary == FREE_SLIP) {
+            if (x == 0 || x == i32(params.width - 1)) { velocity.x = 0.0; }
+            if (y == 0 || y == i32(params.height - 1)) { velocity.y = 0.0; }
+        }
+        set_velocity(coord, velocity);
+    }
+}
+
+// Boundary conditions for dye - read from previous buffer to avoid race conditions
+@compute @workgroup_size(8, 8)
+fn set_dye_boundaries(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord = vec2<u32>(global_id.x, global_id.y);
+    let x = i32(coord.x);
+    let y = i32(coord.y);
+
+    // Set dye boundaries - read from dye (current after diffusion/advection);
+    // sources are always interior, so no edge cell reads another.
+    // Open edges are cleared so dye reaching them leaves
+    if (x == 0 || x == i32(params.width - 1) || y == 0 || y == i32(params.height - 1)) {
+        if (params.boundary == OPEN) {
+            set_dye(coord, vec3<f32>(0.0));
+            return;
+        }
+        set_dye(coord, sample_dye(ring_source(coord, params.boundary == PERIODIC)));
+    }
+}
+
+// Simple velocity projection (basic divergence-free enforcement)
+@compute @workgroup_size(8, 8)
+fn project_velocity(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord = vec2<u32>(global_id.x, global_id.y);
+    let x = i32(coord.x);
+    let y = i32(coord.y);
+
+    // Skip boundaries
+    if (x <= 0 || x >= i32(params.width - 1) || y <= 0 || y >= i32(params.height - 1)) {
+        return;
+    }
+
+    // Cell width and height
+    let h = 1.0 / f32(params.width);
+    let hy = h * params.cell_aspect;
+
+    // Calculate divergence (like CPU)
+    let vel_left = sample_velocity(vec2<u32>(u32(x - 1), u32(y)));
+    let vel_right = sample_velocity(vec2<u32>(u32(x + 1), u32(y)));
+    let vel_up = sample_velocity(vec2<u32>(u32(x), u32(y - 1)));
+    let vel_down = sample_velocity(vec2<u32>(u32(x), u32(y + 1)));
+
+    let divergence = -0.5 * h * (vel_right.x - vel_left.x + (vel_down.y - vel_up.y) / params.cell_aspect);
+
+    // Simple pressure correction (single iteration for now), weighted as
+    // the CPU's anisotropic Poisson solve
+    let ay = 1.0 / (params.cell_aspect * params.cell_aspect);
+    let pressure_correction = divergence / (2.0 + 2.0 * ay);
+
+    // Apply pressure gradient correction
+    let current_vel = sample_velocity(coord);
+    let new_vel_x = current_vel.x - 0.5 * pressure_correction / h;
+    let new_vel_y = current_vel.y - 0.5 * pressure_correction / hy;
+
+    set_velocity(coord, vec2<f32>(new_vel_x, new_vel_y));
+}
+
+// Copy velocity to velocity_prev (like CPU's copy_from_slice)
+@compute @workgroup_size(8, 8)
+fn copy_velocity_to_prev(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord = vec2<u32>(global_id.x, global_id.y);
+    let velocity = sample_velocity(coord);
+    textureStore(velocity_prev_texture, coord, vec4<f32>(velocity.x, velocity.y, 0.0, 1.0));
+}
+
+// Copy dye to dye_prev (for double buffering)
+@compute @workgroup_size(8, 8)
+fn copy_dye_to_prev(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord = vec2<u32>(global_id.x, global_id.y);
+    let dye = sample_dye(coord);
+    textureStore(dye_prev_texture, coord, vec4<f32>(dye.x, dye.y, dye.z, 1.0));
+}
+
+// Add the uploaded splats to dye and velocity. One invocation per cell of
+// the batch's bounding box sums every splat on its cell, so splats that
+// share a cell can't race.
+@compute @workgroup_size(8, 8)
+fn inject(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let coord = vec2<u32>(params.inject_x + global_id.x, params.inject_y + global_id.y);
+    if (coord.x >= params.width || coord.y >= params.height) {
+        return;
+    }
+
+    var dye = vec3<f32>(0.0);
+    var force = vec2<f32>(0.0);
+    for (var i = 0u; i < params.splat_count; i = i + 1u) {
+        let splat = splats[i];
+        if (splat.x == coord.x && splat.y == coord.y) {
+            if (splat.kind == DYE_SPLAT) {
+                dye = dye + splat.value.xyz;
+            } else if (splat.kind == FORCE_SPLAT) {
+                force = force + splat.value.xy;
+            }
+        }
+    }
+
+    if (any(dye != vec3<f32>(0.0))) {
+        set_dye(coord, sample_dye(coord) + dye);
+    }
+    if (any(force != vec2<f32>(0.0))) {
+        set_velocity(coord, sample_velocity(coord) + force);
+    }
+}
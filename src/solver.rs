@@ -0,0 +1,603 @@
+//! One entry point for the CPU solvers. A `SolverConfig` picks the scheme
+//! and its settings, and each scheme reproduces one of the older `fluid_*`
+//! solvers step for step, so apps and tests no longer name those types.
+#![allow(deprecated)]
+
+use crate::conformance::{Fields, Observe};
+use crate::export::{FluidData, Step};
+use crate::fluid_interactive::{BoundaryLeak, SanitizeEvents};
+use crate::fluid_simple::FluidSimulation as SimpleFluid;
+use crate::{ConfigurableSimulation, FluidFinal, FluidSimulation, FluidSolver, InteractiveFluid, Param, WorkingFluid};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// Explicit diffusion and semi-Lagrangian advection without a pressure
+    /// solve (`fluid_simple`)
+    Explicit,
+    /// Density moved whole cells at a time along the rounded velocity, with
+    /// velocity damping and no pressure solve (`FluidFinal`)
+    ForwardMove,
+    /// Stable fluids: implicit diffusion, projection and semi-Lagrangian
+    /// advection of a single density (`WorkingFluid`)
+    Stable,
+    /// Stable fluids with density buoyancy (`FluidSolver`)
+    Buoyant,
+    /// Red-black stable fluids with RGB dye, heat and per-channel mass
+    /// correction (`InteractiveFluid`)
+    Interactive,
+}
+
+impl Scheme {
+    pub const ALL: [Scheme; 5] = [
+        Scheme::Explicit,
+        Scheme::ForwardMove,
+        Scheme::Stable,
+        Scheme::Buoyant,
+        Scheme::Interactive,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scheme::Explicit => "explicit",
+            Scheme::ForwardMove => "forward-move",
+            Scheme::Stable => "stable",
+            Scheme::Buoyant => "buoyant",
+            Scheme::Interactive => "interactive",
+        }
+    }
+
+    /// Walls the scheme uses unless configured otherwise.
+    pub fn boundary(self) -> BoundaryMode {
+        self.boundaries()[0]
+    }
+
+    /// Walls the scheme can run with; the first is its default.
+    pub fn boundaries(self) -> &'static [BoundaryMode] {
+        match self {
+            Scheme::Interactive => &[
+                BoundaryMode::NoSlip,
+                BoundaryMode::FreeSlip,
+                BoundaryMode::Periodic,
+                BoundaryMode::Open,
+            ],
+            Scheme::Explicit => &[BoundaryMode::Absorbing],
+            Scheme::ForwardMove | Scheme::Buoyant => &[BoundaryMode::FreeSlip],
+            Scheme::Stable => &[BoundaryMode::NoSlip],
+        }
+    }
+
+    /// Scalar channels carried by the flow.
+    pub fn channels(self) -> usize {
+        match self {
+            Scheme::Interactive => 3,
+            _ => 1,
+        }
+    }
+
+    /// Whether `SolverConfig::iterations` has any effect.
+    pub fn iterative(self) -> bool {
+        matches!(self, Scheme::Stable | Scheme::Buoyant | Scheme::Interactive)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BoundaryMode {
+    /// Velocity is zero on the walls
+    #[default]
+    NoSlip,
+    /// Only velocity into the walls is removed; flow may slide along them
+    FreeSlip,
+    /// Edge cells lose a little density and velocity every step
+    Absorbing,
+    /// Opposite edges are joined, so flow and dye leaving one side re-enter
+    /// on the other
+    Periodic,
+    /// Velocity continues past the edges and pressure is ambient there, so
+    /// flow can leave the domain
+    Open,
+}
+
+impl BoundaryMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            BoundaryMode::NoSlip => "No-slip",
+            BoundaryMode::FreeSlip => "Free-slip",
+            BoundaryMode::Absorbing => "Absorbing",
+            BoundaryMode::Periodic => "Periodic",
+            BoundaryMode::Open => "Open",
+        }
+    }
+}
+
+/// How the pressure and diffusion systems are solved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PoissonSolver {
+    /// Gauss-Seidel sweeps, up to `SolverConfig::iterations` per projection
+    #[default]
+    GaussSeidel,
+    /// Exact FFT solves; `Interactive` with `Periodic` edges only, and needs
+    /// the `spectral` feature
+    Spectral,
+}
+
+impl PoissonSolver {
+    pub fn name(self) -> &'static str {
+        match self {
+            PoissonSolver::GaussSeidel => "Gauss-Seidel",
+            PoissonSolver::Spectral => "Spectral",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverConfig {
+    pub scheme: Scheme,
+    /// Pressure relaxation sweeps per projection; `Buoyant` also uses it for
+    /// diffusion. Ignored by schemes without a pressure solve.
+    pub iterations: usize,
+    pub dt: f32,
+    pub boundary: BoundaryMode,
+    /// 1 for a density field, 3 for RGB dye
+    pub channels: usize,
+    pub poisson: PoissonSolver,
+}
+
+impl SolverConfig {
+    /// The settings the scheme's original solver was built with.
+    pub fn new(scheme: Scheme) -> Self {
+        let (iterations, dt) = match scheme {
+            Scheme::Explicit => (0, 0.02),
+            Scheme::ForwardMove => (0, 1.0),
+            Scheme::Stable => (20, 0.1),
+            Scheme::Buoyant => (10, 0.05),
+            Scheme::Interactive => (20, 0.1),
+        };
+        Self {
+            scheme,
+            iterations,
+            dt,
+            boundary: scheme.boundary(),
+            channels: scheme.channels(),
+            poisson: PoissonSolver::GaussSeidel,
+        }
+    }
+
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    pub fn with_dt(mut self, dt: f32) -> Self {
+        self.dt = dt;
+        self
+    }
+
+    pub fn with_boundary(mut self, boundary: BoundaryMode) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    pub fn with_poisson(mut self, poisson: PoissonSolver) -> Self {
+        self.poisson = poisson;
+        self
+    }
+
+    /// Check that the scheme supports the requested walls and channels.
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let scheme = self.scheme;
+        if !scheme.boundaries().contains(&self.boundary) {
+            return Err(format!(
+                "the {} scheme supports {:?} walls, not {:?}",
+                scheme.name(),
+                scheme.boundaries(),
+                self.boundary
+            )
+            .into());
+        }
+        if self.channels != scheme.channels() {
+            return Err(format!(
+                "the {} scheme carries {} channel(s), not {}",
+                scheme.name(),
+                scheme.channels(),
+                self.channels
+            )
+            .into());
+        }
+        if scheme.iterative() && self.iterations == 0 {
+            return Err(format!("the {} scheme needs at least one iteration", scheme.name()).into());
+        }
+        if !(self.dt.is_finite() && self.dt > 0.0) {
+            return Err(format!("dt must be positive, got {}", self.dt).into());
+        }
+        if self.poisson == PoissonSolver::Spectral {
+            if !cfg!(feature = "spectral") {
+                return Err("the spectral solver needs the spectral feature".into());
+            }
+            if scheme != Scheme::Interactive || self.boundary != BoundaryMode::Periodic {
+                return Err(format!(
+                    "the spectral solver needs the interactive scheme with periodic edges, not {} with {:?}",
+                    scheme.name(),
+                    self.boundary
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self::new(Scheme::Interactive)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    Explicit(SimpleFluid),
+    ForwardMove(FluidFinal),
+    Stable(WorkingFluid),
+    Buoyant(FluidSolver),
+    Interactive(Box<InteractiveFluid>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Solver {
+    config: SolverConfig,
+    backend: Backend,
+    // Summed RGB dye for `FluidData::density`; empty for one-channel schemes
+    dye_total: Vec<f32>,
+}
+
+impl Solver {
+    pub fn new(config: SolverConfig, width: usize, height: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        config.validate()?;
+        if width < 3 || height < 3 {
+            return Err(format!("grid must be at least 3x3, got {}x{}", width, height).into());
+        }
+
+        let backend = match config.scheme {
+            Scheme::Explicit => {
+                let mut fluid = SimpleFluid::new(width, height);
+                fluid.dt = config.dt;
+                Backend::Explicit(fluid)
+            }
+            Scheme::ForwardMove => {
+                let mut fluid = FluidFinal::new(width, height);
+                fluid.dt = config.dt;
+                Backend::ForwardMove(fluid)
+            }
+            Scheme::Stable => {
+                let mut fluid = WorkingFluid::new(width, height);
+                fluid.dt = config.dt;
+                fluid.pressure_iterations = config.iterations;
+                Backend::Stable(fluid)
+            }
+            Scheme::Buoyant => {
+                let mut fluid = FluidSolver::new(width, height);
+                fluid.dt = config.dt;
+                fluid.iterations = config.iterations;
+                Backend::Buoyant(fluid)
+            }
+            Scheme::Interactive => {
+                let mut fluid = InteractiveFluid::new(width, height);
+                fluid.dt = config.dt;
+                fluid.pressure_iterations = config.iterations;
+                fluid.boundary = config.boundary;
+                #[cfg(feature = "spectral")]
+                if config.poisson == PoissonSolver::Spectral {
+                    fluid.enable_spectral();
+                }
+                Backend::Interactive(Box::new(fluid))
+            }
+        };
+        let dye_total = match backend {
+            Backend::Interactive(_) => vec![0.0; width * height],
+            _ => Vec::new(),
+        };
+
+        Ok(Self {
+            config,
+            backend,
+            dye_total,
+        })
+    }
+
+    /// Wrap an existing interactive simulation, e.g. one rebuilt from a scene.
+    pub fn from_interactive(fluid: InteractiveFluid) -> Self {
+        let mut config = SolverConfig::new(Scheme::Interactive);
+        config.dt = fluid.dt;
+        config.iterations = fluid.pressure_iterations;
+        config.boundary = fluid.boundary;
+        #[cfg(feature = "spectral")]
+        if fluid.spectral.is_some() && fluid.boundary == BoundaryMode::Periodic {
+            config.poisson = PoissonSolver::Spectral;
+        }
+        let dye_total = (0..fluid.width * fluid.height)
+            .map(|i| fluid.dye_r[i] + fluid.dye_g[i] + fluid.dye_b[i])
+            .collect();
+        Self {
+            config,
+            backend: Backend::Interactive(Box::new(fluid)),
+            dye_total,
+        }
+    }
+
+    /// A solver with the scheme's original settings.
+    pub fn with_scheme(scheme: Scheme, width: usize, height: usize) -> Self {
+        Self::new(SolverConfig::new(scheme), width, height).expect("scheme defaults are valid")
+    }
+
+    pub fn config(&self) -> &SolverConfig {
+        &self.config
+    }
+
+    pub fn width(&self) -> usize {
+        match &self.backend {
+            Backend::Explicit(fluid) => fluid.width,
+            Backend::ForwardMove(fluid) => fluid.width,
+            Backend::Stable(fluid) => fluid.width,
+            Backend::Buoyant(fluid) => fluid.width,
+            Backend::Interactive(fluid) => fluid.width,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match &self.backend {
+            Backend::Explicit(fluid) => fluid.height,
+            Backend::ForwardMove(fluid) => fluid.height,
+            Backend::Stable(fluid) => fluid.height,
+            Backend::Buoyant(fluid) => fluid.height,
+            Backend::Interactive(fluid) => fluid.height,
+        }
+    }
+
+    pub fn step(&mut self) {
+        match &mut self.backend {
+            Backend::Explicit(fluid) => fluid.step(),
+            Backend::ForwardMove(fluid) => fluid.step(),
+            Backend::Stable(fluid) => fluid.step(),
+            Backend::Buoyant(fluid) => fluid.step(),
+            Backend::Interactive(fluid) => {
+                fluid.step();
+                for (i, total) in self.dye_total.iter_mut().enumerate() {
+                    *total = fluid.dye_r[i] + fluid.dye_g[i] + fluid.dye_b[i];
+                }
+            }
+        }
+    }
+
+    /// Add to the density of one cell. RGB schemes add it as gray dye, split
+    /// evenly over the channels.
+    pub fn add_density(&mut self, x: usize, y: usize, amount: f32) {
+        match &mut self.backend {
+            Backend::Explicit(fluid) => fluid.add_density(x, y, amount),
+            Backend::ForwardMove(fluid) => fluid.add_density(x, y, amount),
+            Backend::Stable(fluid) => fluid.add_density(x, y, amount),
+            Backend::Buoyant(fluid) => fluid.add_density(x, y, amount),
+            Backend::Interactive(_) => {
+                let third = amount / 3.0;
+                self.add_dye(x, y, (third, third, third));
+            }
+        }
+    }
+
+    /// Add dye to one cell. One-channel schemes add the sum of the channels.
+    pub fn add_dye(&mut self, x: usize, y: usize, color: (f32, f32, f32)) {
+        let amount = color.0 + color.1 + color.2;
+        match &mut self.backend {
+            Backend::Explicit(fluid) => fluid.add_density(x, y, amount),
+            Backend::ForwardMove(fluid) => fluid.add_density(x, y, amount),
+            Backend::Stable(fluid) => fluid.add_density(x, y, amount),
+            Backend::Buoyant(fluid) => fluid.add_density(x, y, amount),
+            Backend::Interactive(fluid) => {
+                fluid.add_dye(x, y, color);
+                if x < fluid.width && y < fluid.height {
+                    let idx = y * fluid.width + x;
+                    self.dye_total[idx] = fluid.dye_r[idx] + fluid.dye_g[idx] + fluid.dye_b[idx];
+                }
+            }
+        }
+    }
+
+    /// Add velocity to one cell.
+    pub fn add_velocity(&mut self, x: usize, y: usize, velocity: Vec2) {
+        match &mut self.backend {
+            Backend::Explicit(fluid) => fluid.add_velocity(x, y, velocity),
+            Backend::ForwardMove(fluid) => fluid.add_velocity(x, y, velocity),
+            Backend::Stable(fluid) => fluid.add_velocity(x, y, velocity),
+            Backend::Buoyant(fluid) => fluid.add_velocity(x, y, velocity),
+            Backend::Interactive(fluid) => {
+                if x < fluid.width && y < fluid.height {
+                    let idx = y * fluid.width + x;
+                    fluid.velocity_x[idx] += velocity.x;
+                    fluid.velocity_y[idx] += velocity.y;
+                }
+            }
+        }
+    }
+
+    /// One scalar channel: the density for one-channel schemes, or red,
+    /// green and blue dye.
+    pub fn channel(&self, index: usize) -> Option<&[f32]> {
+        match (&self.backend, index) {
+            (Backend::Interactive(fluid), 0) => Some(&fluid.dye_r),
+            (Backend::Interactive(fluid), 1) => Some(&fluid.dye_g),
+            (Backend::Interactive(fluid), 2) => Some(&fluid.dye_b),
+            (Backend::Interactive(_), _) => None,
+            (_, 0) => Some(self.density()),
+            _ => None,
+        }
+    }
+
+    /// The `InteractiveFluid` behind the `Interactive` scheme, for tools
+    /// such as heat that the other schemes lack.
+    pub fn as_interactive(&self) -> Option<&InteractiveFluid> {
+        match &self.backend {
+            Backend::Interactive(fluid) => Some(fluid),
+            _ => None,
+        }
+    }
+
+    /// Mutable `as_interactive`. `density` catches up with dye added through
+    /// it on the next step.
+    pub fn as_interactive_mut(&mut self) -> Option<&mut InteractiveFluid> {
+        match &mut self.backend {
+            Backend::Interactive(fluid) => Some(fluid),
+            _ => None,
+        }
+    }
+}
+
+impl FluidSimulation for Solver {
+    fn step(&mut self) {
+        self.step()
+    }
+
+    // Matches each solver's own force: a brush for `Interactive`, one cell
+    // for the rest
+    fn add_force(&mut self, x: usize, y: usize, force: Vec2) {
+        if let Backend::Interactive(fluid) = &mut self.backend {
+            fluid.add_force(x, y, force, 3.0);
+        } else {
+            self.add_velocity(x, y, force);
+        }
+    }
+
+    fn add_dye(&mut self, x: usize, y: usize, color: (f32, f32, f32)) {
+        self.add_dye(x, y, color)
+    }
+
+    fn width(&self) -> usize {
+        self.width()
+    }
+
+    fn height(&self) -> usize {
+        self.height()
+    }
+}
+
+impl ConfigurableSimulation for Solver {
+    fn params(&self) -> Vec<(Param, f32)> {
+        match &self.backend {
+            Backend::Explicit(fluid) => vec![
+                (Param::Dt, fluid.dt),
+                (Param::Viscosity, fluid.viscosity),
+                (Param::Diffusion, fluid.diffusion),
+            ],
+            Backend::ForwardMove(fluid) => vec![(Param::Dt, fluid.dt)],
+            Backend::Stable(fluid) => vec![
+                (Param::Dt, fluid.dt),
+                (Param::Viscosity, fluid.viscosity),
+                (Param::Diffusion, fluid.diffusion),
+            ],
+            Backend::Buoyant(fluid) => vec![
+                (Param::Dt, fluid.dt),
+                (Param::Viscosity, fluid.viscosity),
+                (Param::Diffusion, fluid.diffusion),
+            ],
+            Backend::Interactive(fluid) => fluid.params(),
+        }
+    }
+
+    fn set_param(&mut self, param: Param, value: f32) -> Result<(), Box<dyn std::error::Error>> {
+        let value = param.validate(value)?;
+        let target = match (&mut self.backend, param) {
+            (Backend::Interactive(fluid), _) => {
+                fluid.set_param(param, value)?;
+                self.config.dt = fluid.dt;
+                return Ok(());
+            }
+            (Backend::Explicit(fluid), Param::Dt) => &mut fluid.dt,
+            (Backend::Explicit(fluid), Param::Viscosity) => &mut fluid.viscosity,
+            (Backend::Explicit(fluid), Param::Diffusion) => &mut fluid.diffusion,
+            (Backend::ForwardMove(fluid), Param::Dt) => &mut fluid.dt,
+            (Backend::Stable(fluid), Param::Dt) => &mut fluid.dt,
+            (Backend::Stable(fluid), Param::Viscosity) => &mut fluid.viscosity,
+            (Backend::Stable(fluid), Param::Diffusion) => &mut fluid.diffusion,
+            (Backend::Buoyant(fluid), Param::Dt) => &mut fluid.dt,
+            (Backend::Buoyant(fluid), Param::Viscosity) => &mut fluid.viscosity,
+            (Backend::Buoyant(fluid), Param::Diffusion) => &mut fluid.diffusion,
+            _ => {
+                return Err(format!("the {} scheme has no {} parameter", self.config.scheme.name(), param.name()).into());
+            }
+        };
+        *target = value;
+        if param == Param::Dt {
+            self.config.dt = value;
+        }
+        Ok(())
+    }
+}
+
+impl FluidData for Solver {
+    fn width(&self) -> usize {
+        self.width()
+    }
+
+    fn height(&self) -> usize {
+        self.height()
+    }
+
+    fn density(&self) -> &[f32] {
+        match &self.backend {
+            Backend::Explicit(fluid) => &fluid.density,
+            Backend::ForwardMove(fluid) => &fluid.density,
+            Backend::Stable(fluid) => &fluid.density,
+            Backend::Buoyant(fluid) => &fluid.density,
+            Backend::Interactive(_) => &self.dye_total,
+        }
+    }
+
+    fn velocity_x(&self) -> &[f32] {
+        match &self.backend {
+            Backend::Explicit(fluid) => &fluid.velocity_x,
+            Backend::ForwardMove(fluid) => &fluid.velocity_x,
+            Backend::Stable(fluid) => &fluid.velocity_x,
+            Backend::Buoyant(fluid) => &fluid.velocity_x,
+            Backend::Interactive(fluid) => &fluid.velocity_x,
+        }
+    }
+
+    fn velocity_y(&self) -> &[f32] {
+        match &self.backend {
+            Backend::Explicit(fluid) => &fluid.velocity_y,
+            Backend::ForwardMove(fluid) => &fluid.velocity_y,
+            Backend::Stable(fluid) => &fluid.velocity_y,
+            Backend::Buoyant(fluid) => &fluid.velocity_y,
+            Backend::Interactive(fluid) => &fluid.velocity_y,
+        }
+    }
+
+    fn boundary_leak(&self) -> Option<BoundaryLeak> {
+        self.as_interactive().map(|fluid| fluid.boundary_leak)
+    }
+
+    fn sanitize_events(&self) -> Option<SanitizeEvents> {
+        self.as_interactive().map(|fluid| fluid.sanitize_events)
+    }
+
+    fn dye(&self) -> Option<[&[f32]; 3]> {
+        self.as_interactive().map(|fluid| [&fluid.dye_r[..], &fluid.dye_g[..], &fluid.dye_b[..]])
+    }
+}
+
+impl Step for Solver {
+    fn step(&mut self) {
+        self.step();
+    }
+}
+
+impl Observe for Solver {
+    fn fields(&mut self) -> Result<Fields, Box<dyn std::error::Error>> {
+        Ok(Fields {
+            width: self.width(),
+            height: self.height(),
+            dye: self.density().to_vec(),
+            velocity_x: self.velocity_x().to_vec(),
+            velocity_y: self.velocity_y().to_vec(),
+        })
+    }
+}
@@ -0,0 +1,304 @@
+//! Randomized stress runs: fires valid interactions at a simulation in
+//! random order and checks invariants every few frames. Runs are seeded so
+//! a failure can be replayed exactly.
+
+use crate::commands::SimCommand;
+use crate::gates::GateSet;
+use crate::particles::ParticleSystem;
+use crate::InteractiveFluid;
+use glam::Vec2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Velocities beyond this mean the solver has blown up
+const MAX_SPEED: f32 = 1.0e4;
+// Advection and diffusion only blend values, so dye should never go
+// meaningfully negative
+const MIN_DYE: f32 = -1.0e-3;
+// Actions kept for the failure report
+const ACTION_HISTORY: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct StressConfig {
+    pub seed: u64,
+    /// Wall-clock budget for the run
+    pub duration: Duration,
+    /// Optional frame budget; the run stops at whichever limit comes first
+    pub max_frames: Option<usize>,
+    /// Frames between invariant checks
+    pub check_every: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for StressConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            duration: Duration::from_secs(60),
+            max_frames: None,
+            check_every: 10,
+            width: 100,
+            height: 100,
+        }
+    }
+}
+
+/// One randomized interaction. Positions are normalized like `SimCommand`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StressAction {
+    Command(SimCommand),
+    Attractor { x: f32, y: f32, radius: f32, strength: f32 },
+    AddGate { start: Vec2, end: Vec2 },
+    EmitParticles { x: f32, y: f32, count: usize },
+    /// Replace the grid, as a window resize does in the app
+    Resize { width: usize, height: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct StressReport {
+    pub seed: u64,
+    pub frames: usize,
+    pub actions: usize,
+    pub checks: usize,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct StressFailure {
+    pub seed: u64,
+    pub frame: usize,
+    pub violation: String,
+    /// Actions leading up to the failure, oldest first
+    pub recent_actions: Vec<(usize, StressAction)>,
+}
+
+impl std::fmt::Display for StressFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "invariant violated at frame {} (seed {}): {}", self.frame, self.seed, self.violation)?;
+        writeln!(f, "recent actions:")?;
+        for (frame, action) in &self.recent_actions {
+            writeln!(f, "  frame {}: {:?}", frame, action)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StressFailure {}
+
+pub struct StressRun {
+    pub config: StressConfig,
+    pub simulation: InteractiveFluid,
+    pub gates: GateSet,
+    pub particles: ParticleSystem,
+    rng: StdRng,
+    frame: usize,
+    actions: usize,
+    checks: usize,
+    history: VecDeque<(usize, StressAction)>,
+}
+
+impl StressRun {
+    pub fn new(config: StressConfig) -> Self {
+        Self {
+            simulation: InteractiveFluid::new(config.width, config.height),
+            gates: GateSet::new(),
+            particles: ParticleSystem::with_seed(5000, config.seed),
+            rng: StdRng::seed_from_u64(config.seed),
+            frame: 0,
+            actions: 0,
+            checks: 0,
+            history: VecDeque::new(),
+            config,
+        }
+    }
+
+    /// Run until the time or frame budget is spent or an invariant fails.
+    pub fn run(&mut self) -> Result<StressReport, StressFailure> {
+        let start = Instant::now();
+        while start.elapsed() < self.config.duration
+            && self.config.max_frames.is_none_or(|max| self.frame < max)
+        {
+            self.step_frame()?;
+        }
+        Ok(StressReport {
+            seed: self.config.seed,
+            frames: self.frame,
+            actions: self.actions,
+            checks: self.checks,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    /// Apply a few random actions, advance one frame the way the app does,
+    /// and check invariants when due.
+    pub fn step_frame(&mut self) -> Result<(), StressFailure> {
+        for _ in 0..self.rng.gen_range(0..=3) {
+            let action = self.random_action();
+            self.apply(&action);
+            if self.history.len() == ACTION_HISTORY {
+                self.history.pop_front();
+            }
+            self.history.push_back((self.frame, action));
+            self.actions += 1;
+        }
+
+        self.simulation.step();
+        self.frame += 1;
+        self.gates.measure(&self.simulation, self.frame);
+        self.particles.step(&self.simulation);
+
+        if self.config.check_every > 0 && self.frame.is_multiple_of(self.config.check_every) {
+            self.checks += 1;
+            if let Err(violation) = self.check() {
+                return Err(StressFailure {
+                    seed: self.config.seed,
+                    frame: self.frame,
+                    violation,
+                    recent_actions: self.history.iter().cloned().collect(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&mut self, action: &StressAction) {
+        match *action {
+            StressAction::Command(command) => command.apply(&mut self.simulation),
+            StressAction::Attractor { x, y, radius, strength } => {
+                apply_attractor(&mut self.simulation, x, y, radius, strength)
+            }
+            StressAction::AddGate { start, end } => {
+                let name = format!("gate{}", self.gates.len());
+                self.gates.add(name, start, end);
+            }
+            StressAction::EmitParticles { x, y, count } => {
+                let (px, py) = (x * self.simulation.width as f32, y * self.simulation.height as f32);
+                self.particles.emit(px, py, 3.0, count, (1.0, 1.0, 1.0));
+            }
+            StressAction::Resize { width, height } => {
+                // Like a window resize: gates survive, particles don't
+                self.simulation = InteractiveFluid::new(width, height);
+                self.particles.clear();
+            }
+        }
+    }
+
+    /// Check the simulation state, returning a description of the first
+    /// broken invariant.
+    pub fn check(&self) -> Result<(), String> {
+        let sim = &self.simulation;
+        let size = sim.width * sim.height;
+        let fields = [
+            ("velocity_x", &sim.velocity_x),
+            ("velocity_y", &sim.velocity_y),
+            ("dye_r", &sim.dye_r),
+            ("dye_g", &sim.dye_g),
+            ("dye_b", &sim.dye_b),
+            ("pressure", &sim.pressure),
+            ("divergence", &sim.divergence),
+            ("temperature", &sim.temperature),
+        ];
+
+        for (name, field) in fields {
+            if field.len() != size {
+                return Err(format!("{} has {} cells, expected {}", name, field.len(), size));
+            }
+            if let Some(i) = field.iter().position(|v| !v.is_finite()) {
+                return Err(format!("{} is {} at ({}, {})", name, field[i], i % sim.width, i / sim.width));
+            }
+        }
+
+        for (name, field) in [("dye_r", &sim.dye_r), ("dye_g", &sim.dye_g), ("dye_b", &sim.dye_b)] {
+            if let Some(i) = field.iter().position(|&v| v < MIN_DYE) {
+                return Err(format!("{} is negative ({}) at ({}, {})", name, field[i], i % sim.width, i / sim.width));
+            }
+        }
+
+        for i in 0..size {
+            let speed = Vec2::new(sim.velocity_x[i], sim.velocity_y[i]).length();
+            if speed > MAX_SPEED {
+                return Err(format!("speed {} at ({}, {}) exceeds {}", speed, i % sim.width, i / sim.width, MAX_SPEED));
+            }
+        }
+
+        // The sanitize pass hides a blow-up from the checks above
+        if sim.sanitize_events.reset > 0 {
+            return Err(format!("sanitize reset {} non-finite values", sim.sanitize_events.reset));
+        }
+
+        let leak = sim.boundary_leak.total_outflow();
+        if leak.iter().any(|v| !v.is_finite()) {
+            return Err(format!("boundary leak is not finite: {:?}", leak));
+        }
+
+        for gate in &self.gates.gates {
+            if let Some(flux) = gate.latest()
+                && !flux.dye_total().is_finite()
+            {
+                return Err(format!("gate {} flux is not finite", gate.name));
+            }
+        }
+
+        if let Some(p) = self.particles.positions().find(|p| !p.is_finite()) {
+            return Err(format!("particle position {:?} is not finite", p));
+        }
+        Ok(())
+    }
+
+    fn random_action(&mut self) -> StressAction {
+        let (x, y) = (self.rng.gen_range(0.0..=1.0), self.rng.gen_range(0.0..=1.0));
+        match self.rng.gen_range(0..100) {
+            0..=29 => StressAction::Command(SimCommand::Dye {
+                x,
+                y,
+                color: (self.rng.gen_range(0.0..=1.0), self.rng.gen_range(0.0..=1.0), self.rng.gen_range(0.0..=1.0)),
+            }),
+            30..=59 => StressAction::Command(SimCommand::Force {
+                x,
+                y,
+                dx: self.rng.gen_range(-5.0..=5.0),
+                dy: self.rng.gen_range(-5.0..=5.0),
+            }),
+            60..=69 => StressAction::Command(SimCommand::Heat {
+                x,
+                y,
+                amount: self.rng.gen_range(-5.0..=5.0),
+            }),
+            70..=79 => StressAction::Attractor {
+                x,
+                y,
+                radius: self.rng.gen_range(2.0..=30.0),
+                strength: self.rng.gen_range(0.1..=100.0),
+            },
+            80..=85 => {
+                // Gate endpoints in cells, anywhere on (or off) the grid
+                let w = self.simulation.width as f32;
+                let h = self.simulation.height as f32;
+                StressAction::AddGate {
+                    start: Vec2::new(self.rng.gen_range(-2.0..=w + 2.0), self.rng.gen_range(-2.0..=h + 2.0)),
+                    end: Vec2::new(self.rng.gen_range(-2.0..=w + 2.0), self.rng.gen_range(-2.0..=h + 2.0)),
+                }
+            }
+            86..=93 => StressAction::EmitParticles {
+                x,
+                y,
+                count: self.rng.gen_range(1..=200),
+            },
+            94..=96 => StressAction::Command(SimCommand::Clear),
+            _ => StressAction::Resize {
+                width: self.rng.gen_range(8..=2 * self.config.width),
+                height: self.rng.gen_range(8..=2 * self.config.height),
+            },
+        }
+    }
+}
+
+/// Same point sink the app's attractor tool applies while held. Position is
+/// normalized like `SimCommand`; radius is in cells.
+pub(crate) fn apply_attractor(sim: &mut InteractiveFluid, x: f32, y: f32, radius: f32, strength: f32) {
+    let center = glam::Vec2::new(x * sim.width as f32, y * sim.height as f32);
+    sim.apply_radial_force(center, radius, strength);
+}
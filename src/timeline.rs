@@ -0,0 +1,259 @@
+//! Keyframe animation of persistent elements over simulation frames:
+//! position, strength, dye color and on/off, interpolated between keys.
+//! Tracks refer to elements by their index in the scene's element list.
+
+use crate::scene::{PersistentElement, PersistentElementType};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_LENGTH: u32 = 240;
+
+/// How values move from a key to the next one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Eases in and out of each key
+    Smooth,
+    /// Keeps this key's values until the next key
+    Hold,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 3] = [Interpolation::Linear, Interpolation::Smooth, Interpolation::Hold];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Interpolation::Linear => "Linear",
+            Interpolation::Smooth => "Smooth",
+            Interpolation::Hold => "Hold",
+        }
+    }
+
+    // Progress between two keys for a fraction `t` of the way
+    fn ease(self, t: f32) -> f32 {
+        match self {
+            Interpolation::Linear => t,
+            Interpolation::Smooth => t * t * (3.0 - 2.0 * t),
+            Interpolation::Hold => 0.0,
+        }
+    }
+}
+
+/// An element's values at one frame. Position is in grid cells.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub frame: u32,
+    pub x: f32,
+    pub y: f32,
+    /// See `PersistentElement::strength`
+    pub strength: f32,
+    /// Dye sources only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<(f32, f32, f32)>,
+    /// Switched, never interpolated
+    pub enabled: bool,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+impl Keyframe {
+    pub fn capture(frame: u32, elem: &PersistentElement, enabled: bool) -> Self {
+        let color = match elem.element_type {
+            PersistentElementType::DyeSource { color, .. } => Some(color),
+            _ => None,
+        };
+        Self {
+            frame,
+            x: elem.x,
+            y: elem.y,
+            strength: elem.strength(),
+            color,
+            enabled,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    // The values a fraction `t` of the way to `next`
+    fn lerp(&self, next: &Keyframe, t: f32) -> Keyframe {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        let color = match (self.color, next.color) {
+            (Some(a), Some(b)) => Some((mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))),
+            (color, _) => color,
+        };
+        Keyframe {
+            x: mix(self.x, next.x),
+            y: mix(self.y, next.y),
+            strength: mix(self.strength, next.strength),
+            color,
+            ..*self
+        }
+    }
+
+    /// `elem` posed with these values; its type, radius and audio link stay.
+    pub fn pose(&self, elem: &PersistentElement) -> PersistentElement {
+        let mut posed = elem.with_strength(self.strength);
+        posed.x = self.x;
+        posed.y = self.y;
+        if let (PersistentElementType::DyeSource { intensity, blend, emission, .. }, Some(color)) = (posed.element_type, self.color) {
+            posed.element_type = PersistentElementType::DyeSource { color, intensity, blend, emission };
+        }
+        posed
+    }
+}
+
+/// The keys of one element, kept sorted by frame.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub element: usize,
+    pub keys: Vec<Keyframe>,
+}
+
+impl Track {
+    /// Values at `frame`: the first key before it, the last after it, and
+    /// interpolated in between.
+    pub fn sample(&self, frame: u32) -> Option<Keyframe> {
+        let next = self.keys.partition_point(|key| key.frame <= frame);
+        match (next.checked_sub(1).map(|i| &self.keys[i]), self.keys.get(next)) {
+            (Some(key), Some(next)) => {
+                let t = (frame - key.frame) as f32 / (next.frame - key.frame) as f32;
+                Some(key.lerp(next, key.interpolation.ease(t)))
+            }
+            (Some(key), None) => Some(*key),
+            (None, next) => next.copied(),
+        }
+    }
+
+    /// Add `key`, replacing any key on the same frame.
+    pub fn set_key(&mut self, key: Keyframe) {
+        match self.keys.binary_search_by_key(&key.frame, |k| k.frame) {
+            Ok(i) => self.keys[i] = key,
+            Err(i) => self.keys.insert(i, key),
+        }
+    }
+
+    pub fn remove_key(&mut self, frame: u32) -> bool {
+        let count = self.keys.len();
+        self.keys.retain(|key| key.frame != frame);
+        self.keys.len() != count
+    }
+}
+
+/// Tracks plus a playhead. The playhead isn't saved with scenes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timeline {
+    /// Frames in one pass
+    pub length: u32,
+    pub looping: bool,
+    pub tracks: Vec<Track>,
+    #[serde(skip)]
+    pub frame: u32,
+    #[serde(skip)]
+    pub playing: bool,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            length: DEFAULT_LENGTH,
+            looping: true,
+            tracks: Vec::new(),
+            frame: 0,
+            playing: false,
+        }
+    }
+}
+
+impl Timeline {
+    pub fn is_empty(&self) -> bool {
+        self.tracks.iter().all(|track| track.keys.is_empty())
+    }
+
+    pub fn track(&self, element: usize) -> Option<&Track> {
+        self.tracks.iter().find(|track| track.element == element)
+    }
+
+    /// Key `elem`, the element at index `element`, at the playhead.
+    pub fn set_key(&mut self, element: usize, elem: &PersistentElement, enabled: bool) {
+        let frame = self.frame;
+        let interpolation = self
+            .track(element)
+            .and_then(|track| track.sample(frame))
+            .map_or_else(Interpolation::default, |key| key.interpolation);
+        let key = Keyframe { interpolation, ..Keyframe::capture(frame, elem, enabled) };
+        match self.tracks.iter_mut().find(|track| track.element == element) {
+            Some(track) => track.set_key(key),
+            None => self.tracks.push(Track { element, keys: vec![key] }),
+        }
+    }
+
+    /// Remove the element's key at the playhead, and its track once empty.
+    pub fn remove_key(&mut self, element: usize) -> bool {
+        let frame = self.frame;
+        let removed = self
+            .tracks
+            .iter_mut()
+            .find(|track| track.element == element)
+            .is_some_and(|track| track.remove_key(frame));
+        self.tracks.retain(|track| !track.keys.is_empty());
+        removed
+    }
+
+    /// Drop the track of a removed element and renumber the ones after it.
+    pub fn element_removed(&mut self, element: usize) {
+        self.tracks.retain(|track| track.element != element);
+        for track in &mut self.tracks {
+            if track.element > element {
+                track.element -= 1;
+            }
+        }
+    }
+
+    /// Frames that have a key on any track, in order.
+    pub fn key_frames(&self) -> Vec<u32> {
+        let mut frames: Vec<u32> = self.tracks.iter().flat_map(|track| track.keys.iter().map(|key| key.frame)).collect();
+        frames.sort_unstable();
+        frames.dedup();
+        frames
+    }
+
+    /// Move the playhead on a frame while playing, wrapping when looping
+    /// and stopping at the end otherwise.
+    pub fn advance(&mut self) {
+        if !self.playing {
+            return;
+        }
+        if self.frame + 1 < self.length {
+            self.frame += 1;
+        } else if self.looping {
+            self.frame = 0;
+        } else {
+            self.playing = false;
+        }
+    }
+
+    /// Whether the element at `element` is switched on at the playhead.
+    pub fn enabled(&self, element: usize) -> bool {
+        self.track(element).and_then(|track| track.sample(self.frame)).is_none_or(|key| key.enabled)
+    }
+
+    /// Set every keyed element to its values at the playhead. Tracks for
+    /// elements that no longer exist are skipped.
+    pub fn pose(&self, elements: &mut [PersistentElement]) {
+        for track in &self.tracks {
+            if let (Some(elem), Some(key)) = (elements.get_mut(track.element), track.sample(self.frame)) {
+                *elem = key.pose(elem);
+            }
+        }
+    }
+
+    /// Key positions scaled to a grid `sx` and `sy` times the size, as
+    /// `Scene::elements_for` does for the elements.
+    pub fn rescaled(&self, sx: f32, sy: f32) -> Self {
+        let mut timeline = self.clone();
+        for key in timeline.tracks.iter_mut().flat_map(|track| track.keys.iter_mut()) {
+            key.x *= sx;
+            key.y *= sy;
+        }
+        timeline
+    }
+}
@@ -0,0 +1,236 @@
+//! Wall-clock timing of the solver stages, and the running averages the
+//! performance HUD shows.
+
+/// Stage of a solver step with its own timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Diffuse,
+    Advect,
+    Project,
+}
+
+impl Stage {
+    /// Tracing span covering one run of the stage.
+    #[cfg(feature = "profiling")]
+    pub fn span(self) -> tracing::Span {
+        match self {
+            Stage::Diffuse => tracing::info_span!("diffuse"),
+            Stage::Advect => tracing::info_span!("advect"),
+            Stage::Project => tracing::info_span!("project"),
+        }
+    }
+}
+
+/// Seconds spent in each stage of a step. Whatever is left of `total`, such
+/// as forces, boundaries and sanitizing, is `other`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StageTimes {
+    pub diffuse: f64,
+    pub advect: f64,
+    pub project: f64,
+    pub total: f64,
+}
+
+impl StageTimes {
+    pub fn add(&mut self, stage: Stage, seconds: f64) {
+        match stage {
+            Stage::Diffuse => self.diffuse += seconds,
+            Stage::Advect => self.advect += seconds,
+            Stage::Project => self.project += seconds,
+        }
+    }
+
+    pub fn other(&self) -> f64 {
+        (self.total - self.diffuse - self.advect - self.project).max(0.0)
+    }
+
+    /// Both steps' times added, e.g. for the substeps of one frame.
+    pub fn accumulate(&mut self, other: &StageTimes) {
+        self.diffuse += other.diffuse;
+        self.advect += other.advect;
+        self.project += other.project;
+        self.total += other.total;
+    }
+
+    // Move each time a share `rate` of the way toward `target`
+    fn approach(&mut self, target: &StageTimes, rate: f64) {
+        self.diffuse += (target.diffuse - self.diffuse) * rate;
+        self.advect += (target.advect - self.advect) * rate;
+        self.project += (target.project - self.project) * rate;
+        self.total += (target.total - self.total) * rate;
+    }
+}
+
+// Share of each new frame in the running averages: about the last 20
+// frames, steady enough to read while still following a change of grid
+const SMOOTHING: f64 = 0.05;
+
+/// Frame rate and solver time per frame, averaged over recent frames.
+#[derive(Debug, Clone, Default)]
+pub struct PerfStats {
+    frame_seconds: f64,
+    solver: StageTimes,
+    last_frame: Option<f64>,
+}
+
+impl PerfStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a frame drawn at `now` seconds, in which the solver took
+    /// `solver`. The first frame only starts the clock.
+    pub fn frame(&mut self, now: f64, solver: StageTimes) {
+        let Some(last) = self.last_frame.replace(now) else {
+            self.solver = solver;
+            return;
+        };
+        let elapsed = (now - last).max(0.0);
+        if self.frame_seconds == 0.0 {
+            self.frame_seconds = elapsed;
+        } else {
+            self.frame_seconds += (elapsed - self.frame_seconds) * SMOOTHING;
+        }
+        self.solver.approach(&solver, SMOOTHING);
+    }
+
+    pub fn fps(&self) -> f64 {
+        if self.frame_seconds > 0.0 { 1.0 / self.frame_seconds } else { 0.0 }
+    }
+
+    /// Average milliseconds between frames.
+    pub fn frame_ms(&self) -> f64 {
+        self.frame_seconds * 1000.0
+    }
+
+    /// Average solver time per frame, in seconds.
+    pub fn solver(&self) -> StageTimes {
+        self.solver
+    }
+
+    /// HUD lines: frame rate, then milliseconds per stage.
+    pub fn summary(&self) -> String {
+        let ms = |seconds: f64| seconds * 1000.0;
+        let s = &self.solver;
+        format!(
+            "{:.0} fps  {:.1} ms/frame\nstep     {:>6.2} ms\n diffuse {:>6.2}\n advect  {:>6.2}\n project {:>6.2}\n other   {:>6.2}",
+            self.fps(),
+            self.frame_ms(),
+            ms(s.total),
+            ms(s.diffuse),
+            ms(s.advect),
+            ms(s.project),
+            ms(s.other())
+        )
+    }
+}
+
+/// Steps run per second of wall-clock time by default, one per frame on a
+/// 60 Hz display.
+pub const DEFAULT_STEPS_PER_SECOND: f32 = 60.0;
+
+/// Most steps one frame catches up on. When the solver can't keep up, the
+/// flow slows down rather than every frame taking longer than the last.
+pub const MAX_CATCH_UP_STEPS: usize = 4;
+
+/// Fixed-timestep pacing: wall-clock time collects in an accumulator and is
+/// paid out in whole steps, so the simulation runs `steps_per_second` steps
+/// a second, scaled by `speed`, whatever the display's refresh rate.
+#[derive(Debug, Clone)]
+pub struct StepClock {
+    pub steps_per_second: f32,
+    /// 1 runs in real time; below 1 is slow motion
+    pub speed: f32,
+    /// Run exactly this many steps every frame instead of following the
+    /// clock: fast-forward as fast as the machine goes, or an even pace
+    /// for stepping through a flow while debugging
+    pub per_frame: Option<usize>,
+    accumulator: f64,
+    last: Option<f64>,
+}
+
+impl Default for StepClock {
+    fn default() -> Self {
+        Self::new(DEFAULT_STEPS_PER_SECOND)
+    }
+}
+
+impl StepClock {
+    pub fn new(steps_per_second: f32) -> Self {
+        Self {
+            steps_per_second,
+            speed: 1.0,
+            per_frame: None,
+            accumulator: 0.0,
+            last: None,
+        }
+    }
+
+    /// Steps due for a frame drawn at `now` seconds, at most
+    /// `MAX_CATCH_UP_STEPS`. The first frame, and the first after `pause`,
+    /// only starts the clock. With `per_frame` set, that many.
+    pub fn steps(&mut self, now: f64) -> usize {
+        let last = self.last.replace(now);
+        if let Some(steps) = self.per_frame {
+            self.accumulator = 0.0;
+            return steps;
+        }
+        let Some(last) = last else {
+            return 0;
+        };
+        let rate = (self.steps_per_second.max(0.0) * self.speed.max(0.0)) as f64;
+        self.accumulator += (now - last).max(0.0) * rate;
+        let steps = self.accumulator.floor();
+        self.accumulator -= steps;
+        (steps as usize).min(MAX_CATCH_UP_STEPS)
+    }
+
+    /// Stop counting time, so resuming doesn't catch up on the pause.
+    pub fn pause(&mut self) {
+        self.last = None;
+        self.accumulator = 0.0;
+    }
+
+    /// Simulated time per wall-clock second, with steps of `dt`.
+    pub fn time_scale(&self, dt: f32) -> f32 {
+        self.steps_per_second * self.speed * dt
+    }
+}
+
+/// `bytes` as KiB or MiB.
+pub fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1 << 20 {
+        format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64)
+    } else {
+        format!("{:.0} KiB", bytes as f64 / 1024.0)
+    }
+}
+
+/// Seconds on a monotonic clock, in native builds and in the browser.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_seconds() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_secs_f64()
+}
+
+// `Instant` isn't available in the browser
+#[cfg(target_arch = "wasm32")]
+pub fn now_seconds() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map_or(0.0, |performance| performance.now() / 1000.0)
+}
+
+/// Write every tracing span to a Chrome trace file at `path`, for Perfetto or
+/// `chrome://tracing`. The file is complete once the returned guard drops.
+#[cfg(all(feature = "profiling", not(target_arch = "wasm32")))]
+pub fn start_trace(path: &std::path::Path) -> Result<tracing_chrome::FlushGuard, Box<dyn std::error::Error>> {
+    use tracing_subscriber::prelude::*;
+
+    let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new().file(path).include_args(true).build();
+    tracing_subscriber::registry().with(layer).try_init()?;
+    Ok(guard)
+}
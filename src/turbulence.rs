@@ -0,0 +1,113 @@
+//! Procedural turbulence that keeps the fluid moving without input: the
+//! curl of an animated noise field, added to the velocity each step either
+//! everywhere or only where it has been painted in.
+
+use crate::Brush;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Turbulence {
+    /// Size of the swirls, in grid cells
+    pub scale: f32,
+    /// Added velocity per unit of time at full weight
+    pub strength: f32,
+    /// How fast the pattern evolves; 0 freezes it
+    pub speed: f32,
+    /// Position along the noise's time axis
+    pub time: f32,
+    /// Stir the whole grid rather than just the painted cells
+    pub everywhere: bool,
+    /// Row-major weight in `0..=1` per cell, empty until painted
+    pub region: Vec<f32>,
+}
+
+impl Default for Turbulence {
+    fn default() -> Self {
+        Self {
+            scale: 12.0,
+            strength: 4.0,
+            speed: 0.5,
+            time: 0.0,
+            everywhere: true,
+            region: Vec::new(),
+        }
+    }
+}
+
+impl Turbulence {
+    /// Add `amount` to the painted weight under a dab, clamped to `0..=1`;
+    /// negative amounts erase. Painting on a grid of another size starts
+    /// the region over.
+    pub fn paint(&mut self, brush: &Brush, x: f32, y: f32, width: usize, height: usize, amount: f32) {
+        if self.region.len() != width * height {
+            self.region = vec![0.0; width * height];
+        }
+        for (px, py, weight) in brush.dab(x, y, width, height) {
+            let cell = &mut self.region[py * width + px];
+            *cell = (*cell + amount * weight).clamp(0.0, 1.0);
+        }
+    }
+
+    /// True when some cell has been painted.
+    pub fn has_region(&self) -> bool {
+        self.region.iter().any(|&w| w > 0.0)
+    }
+
+    /// How much of the turbulence cell `i` receives.
+    pub fn weight(&self, i: usize) -> f32 {
+        if self.everywhere { 1.0 } else { self.region.get(i).copied().unwrap_or(0.0) }
+    }
+
+    /// Turbulent velocity at a grid position: the curl of the noise
+    /// potential, so the added flow is divergence-free and the pressure
+    /// solve doesn't cancel it.
+    pub fn velocity_at(&self, x: f32, y: f32) -> (f32, f32) {
+        let scale = self.scale.max(1.0);
+        let (u, v) = (x / scale, y / scale);
+        let h = 0.01;
+        let dpdx = (value_noise(u + h, v, self.time) - value_noise(u - h, v, self.time)) / (2.0 * h);
+        let dpdy = (value_noise(u, v + h, self.time) - value_noise(u, v - h, self.time)) / (2.0 * h);
+        (dpdy * self.strength, -dpdx * self.strength)
+    }
+
+    /// Stir a `width` × `height` velocity field for one step of `dt` and
+    /// advance the pattern.
+    pub fn apply(&mut self, velocity_x: &mut [f32], velocity_y: &mut [f32], width: usize, dt: f32) {
+        // A region painted on a grid of another size no longer lines up
+        let painted = self.region.len() == velocity_x.len() && self.has_region();
+        if self.strength != 0.0 && (self.everywhere || painted) {
+            for (i, (vx, vy)) in velocity_x.iter_mut().zip(velocity_y.iter_mut()).enumerate() {
+                let weight = self.weight(i);
+                if weight > 0.0 {
+                    let (tx, ty) = self.velocity_at((i % width) as f32, (i / width) as f32);
+                    *vx += tx * weight * dt;
+                    *vy += ty * weight * dt;
+                }
+            }
+        }
+        self.time += self.speed * dt;
+    }
+}
+
+// Smooth 3D value noise in [0, 1], with the third axis as time
+fn value_noise(x: f32, y: f32, t: f32) -> f32 {
+    let (x0, y0, t0) = (x.floor(), y.floor(), t.floor());
+    let fade = |f: f32| f * f * (3.0 - 2.0 * f);
+    let (fx, fy, ft) = (fade(x - x0), fade(y - y0), fade(t - t0));
+    let (ix, iy, it) = (x0 as i32, y0 as i32, t0 as i32);
+    let lerp = |a: f32, b: f32, f: f32| a + (b - a) * f;
+    let layer = |it: i32| {
+        let top = lerp(lattice(ix, iy, it), lattice(ix + 1, iy, it), fx);
+        let bottom = lerp(lattice(ix, iy + 1, it), lattice(ix + 1, iy + 1, it), fx);
+        lerp(top, bottom, fy)
+    };
+    lerp(layer(it), layer(it + 1), ft)
+}
+
+// Deterministic noise in [0, 1] at an integer lattice point
+fn lattice(x: i32, y: i32, t: i32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343) ^ (y as u32).wrapping_mul(0xd816_3841) ^ (t as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h & 0xffff) as f32 / 65535.0
+}
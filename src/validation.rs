@@ -0,0 +1,338 @@
+//! Canonical CFD test cases with known answers, so a solver change can be
+//! judged by error norms rather than by eye: the Taylor-Green vortex, whose
+//! exact solution decays at a known rate, and the lid-driven cavity at
+//! Reynolds number 100 against the reference profiles of Ghia, Ghia & Shin
+//! (1982).
+//!
+//! Both run on `InteractiveFluid` in its own units: positions in cells,
+//! velocities in cells per time unit. Its diffusion solves
+//! `dt * viscosity * width * height` per cell, so the viscosity in cells² per
+//! time unit is `viscosity * width * height`.
+
+use crate::{BoundaryMode, InteractiveFluid};
+use std::f32::consts::TAU;
+
+/// Differences between a simulated and a reference field, over the points
+/// compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ErrorNorms {
+    /// Root mean square difference
+    pub l2: f32,
+    /// Largest difference
+    pub linf: f32,
+    /// `l2` over the root mean square of the reference, 0 for a zero
+    /// reference
+    pub relative_l2: f32,
+}
+
+impl ErrorNorms {
+    /// Norms over `(simulated, reference)` pairs.
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (f32, f32)>) -> Self {
+        let (mut squared, mut reference_squared, mut linf, mut count) = (0.0f64, 0.0f64, 0.0f32, 0usize);
+        for (simulated, reference) in pairs {
+            let error = (simulated - reference).abs();
+            squared += (error as f64).powi(2);
+            reference_squared += (reference as f64).powi(2);
+            linf = linf.max(error);
+            count += 1;
+        }
+        if count == 0 {
+            return Self::default();
+        }
+        let l2 = (squared / count as f64).sqrt();
+        let reference_rms = (reference_squared / count as f64).sqrt();
+        Self {
+            l2: l2 as f32,
+            linf,
+            relative_l2: if reference_rms > 0.0 { (l2 / reference_rms) as f32 } else { 0.0 },
+        }
+    }
+}
+
+/// One period of the Taylor-Green vortex on a periodic grid:
+/// `u = A sin(kx) cos(ky)`, `v = -A cos(kx) sin(ky)`, which the viscous
+/// equations keep in shape while its amplitude decays as `exp(-2νk²t)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaylorGreen {
+    /// Grid side in cells, edge ring included; the period is `size - 2`
+    pub size: usize,
+    /// Peak speed at the start, in cells per time unit
+    pub amplitude: f32,
+    /// `InteractiveFluid::viscosity`
+    pub viscosity: f32,
+    pub dt: f32,
+}
+
+impl Default for TaylorGreen {
+    fn default() -> Self {
+        // Slow enough that advection's interpolation barely damps the
+        // vortex, viscous enough that it halves in about 30 steps
+        Self {
+            size: 34,
+            amplitude: 0.1,
+            viscosity: 5e-4,
+            dt: 0.5,
+        }
+    }
+}
+
+/// Result of a Taylor-Green run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaylorGreenReport {
+    pub time: f32,
+    /// Both velocity components against the exact solution
+    pub velocity: ErrorNorms,
+    /// Amplitude decay rate the run showed, per time unit
+    pub measured_decay: f32,
+    /// `TaylorGreen::decay_rate`
+    pub exact_decay: f32,
+}
+
+impl TaylorGreen {
+    /// Cells per period of the vortex.
+    pub fn period(&self) -> usize {
+        self.size - 2
+    }
+
+    fn wavenumber(&self) -> f32 {
+        TAU / self.period() as f32
+    }
+
+    /// Amplitude decay rate of the exact solution, per time unit.
+    pub fn decay_rate(&self) -> f32 {
+        let diffusivity = self.viscosity * (self.size * self.size) as f32;
+        2.0 * diffusivity * self.wavenumber().powi(2)
+    }
+
+    /// Exact velocity of cell `(x, y)` at time `t`.
+    pub fn velocity(&self, x: usize, y: usize, t: f32) -> (f32, f32) {
+        let k = self.wavenumber();
+        let (kx, ky) = (k * x as f32, k * y as f32);
+        let a = self.amplitude * (-self.decay_rate() * t).exp();
+        (a * kx.sin() * ky.cos(), -a * kx.cos() * ky.sin())
+    }
+
+    /// A periodic grid holding the vortex at time 0.
+    pub fn setup(&self) -> InteractiveFluid {
+        let mut fluid = InteractiveFluid::new(self.size, self.size);
+        fluid.boundary = BoundaryMode::Periodic;
+        fluid.viscosity = self.viscosity;
+        fluid.dt = self.dt;
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let (u, v) = self.velocity(x, y, 0.0);
+                fluid.velocity_x[y * self.size + x] = u;
+                fluid.velocity_y[y * self.size + x] = v;
+            }
+        }
+        fluid
+    }
+
+    /// Least-squares amplitude of the vortex mode in `fluid`'s velocity.
+    pub fn amplitude_of(&self, fluid: &InteractiveFluid) -> f32 {
+        let (mut dot, mut norm) = (0.0f64, 0.0f64);
+        for (x, y) in self.interior() {
+            let (u, v) = self.velocity(x, y, 0.0);
+            let idx = y * self.size + x;
+            dot += (fluid.velocity_x[idx] * u + fluid.velocity_y[idx] * v) as f64;
+            norm += (u * u + v * v) as f64;
+        }
+        if norm > 0.0 { (dot / norm) as f32 * self.amplitude } else { 0.0 }
+    }
+
+    /// Velocity errors of `fluid` against the exact solution at time `t`.
+    pub fn errors(&self, fluid: &InteractiveFluid, t: f32) -> ErrorNorms {
+        ErrorNorms::from_pairs(self.interior().flat_map(|(x, y)| {
+            let (u, v) = self.velocity(x, y, t);
+            let idx = y * self.size + x;
+            [(fluid.velocity_x[idx], u), (fluid.velocity_y[idx], v)]
+        }))
+    }
+
+    /// Step the vortex `steps` times and compare with the exact solution.
+    pub fn run(&self, steps: usize) -> TaylorGreenReport {
+        let mut fluid = self.setup();
+        for _ in 0..steps {
+            fluid.step();
+        }
+        let time = steps as f32 * self.dt;
+        let remaining = self.amplitude_of(&fluid) / self.amplitude;
+        TaylorGreenReport {
+            time,
+            velocity: self.errors(&fluid, time),
+            measured_decay: if time > 0.0 && remaining > 0.0 { -remaining.ln() / time } else { 0.0 },
+            exact_decay: self.decay_rate(),
+        }
+    }
+
+    // Cells of one period, without the ring that copies them
+    fn interior(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (1..self.size - 1).flat_map(move |y| (1..self.size - 1).map(move |x| (x, y)))
+    }
+}
+
+/// Ghia et al.'s horizontal velocity along the cavity's vertical centerline
+/// at Re 100, as `(height, u / lid speed)` from the bottom wall up to the lid.
+pub const GHIA_RE100_U: [(f32, f32); 17] = [
+    (0.0000, 0.00000),
+    (0.0547, -0.03717),
+    (0.0625, -0.04192),
+    (0.0703, -0.04775),
+    (0.1016, -0.06434),
+    (0.1719, -0.10150),
+    (0.2813, -0.15662),
+    (0.4531, -0.21090),
+    (0.5000, -0.20581),
+    (0.6172, -0.13641),
+    (0.7344, 0.00332),
+    (0.8516, 0.23151),
+    (0.9531, 0.68717),
+    (0.9609, 0.73722),
+    (0.9688, 0.78871),
+    (0.9766, 0.84123),
+    (1.0000, 1.00000),
+];
+
+/// Ghia et al.'s vertical velocity along the horizontal centerline at Re
+/// 100, as `(distance from the left wall, v / lid speed)`, upward positive.
+pub const GHIA_RE100_V: [(f32, f32); 17] = [
+    (0.0000, 0.00000),
+    (0.0625, 0.09233),
+    (0.0703, 0.10091),
+    (0.0781, 0.10890),
+    (0.0938, 0.12317),
+    (0.1563, 0.16077),
+    (0.2266, 0.17507),
+    (0.2344, 0.17527),
+    (0.5000, 0.05454),
+    (0.8047, -0.24533),
+    (0.8594, -0.22445),
+    (0.9063, -0.16914),
+    (0.9453, -0.10313),
+    (0.9531, -0.08864),
+    (0.9609, -0.07391),
+    (0.9688, -0.05906),
+    (1.0000, 0.00000),
+];
+
+/// Square cavity with no-slip walls, driven by its lid sliding right, run to
+/// a steady state at Re 100.
+///
+/// The lid is the first row below the top edge, held at `lid_speed` before
+/// every step; the other walls are the edge ring. The cavity is `size - 1`
+/// cells across, so the grid is one row taller than wide.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LidDrivenCavity {
+    /// Grid width in cells, odd so a column lies on the centerline
+    pub size: usize,
+    /// In cells per time unit
+    pub lid_speed: f32,
+    pub dt: f32,
+    /// `InteractiveFluid::pressure_iterations`
+    pub pressure_iterations: usize,
+}
+
+impl Default for LidDrivenCavity {
+    fn default() -> Self {
+        Self {
+            size: 33,
+            lid_speed: 2.0,
+            dt: 0.5,
+            pressure_iterations: 100,
+        }
+    }
+}
+
+/// Result of a cavity run against Ghia et al.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CavityReport {
+    /// `u / lid speed` at `GHIA_RE100_U`'s heights
+    pub u_profile: Vec<(f32, f32)>,
+    /// `v / lid speed` at `GHIA_RE100_V`'s positions
+    pub v_profile: Vec<(f32, f32)>,
+    pub u: ErrorNorms,
+    pub v: ErrorNorms,
+}
+
+impl LidDrivenCavity {
+    pub const REYNOLDS: f32 = 100.0;
+
+    /// Cavity side in cells.
+    pub fn side(&self) -> usize {
+        self.size - 1
+    }
+
+    /// The cavity at rest, with the viscosity that makes Re 100.
+    pub fn setup(&self) -> InteractiveFluid {
+        let (w, h) = (self.size, self.size + 1);
+        let mut fluid = InteractiveFluid::new(w, h);
+        fluid.boundary = BoundaryMode::NoSlip;
+        fluid.dt = self.dt;
+        fluid.pressure_iterations = self.pressure_iterations;
+        let diffusivity = self.lid_speed * self.side() as f32 / Self::REYNOLDS;
+        fluid.viscosity = diffusivity / (w * h) as f32;
+        fluid
+    }
+
+    /// Hold the lid row at the lid speed.
+    pub fn drive(&self, fluid: &mut InteractiveFluid) {
+        let w = fluid.width;
+        for x in 1..w - 1 {
+            fluid.velocity_x[w + x] = self.lid_speed;
+            fluid.velocity_y[w + x] = 0.0;
+        }
+    }
+
+    /// Drive and step `steps` times, then compare the centerline profiles
+    /// with Ghia et al.
+    pub fn run(&self, steps: usize) -> CavityReport {
+        let mut fluid = self.setup();
+        for _ in 0..steps {
+            self.drive(&mut fluid);
+            fluid.step();
+        }
+        self.drive(&mut fluid);
+        self.compare(&fluid)
+    }
+
+    /// Centerline profiles of `fluid` against Ghia et al.
+    pub fn compare(&self, fluid: &InteractiveFluid) -> CavityReport {
+        let (w, h) = (fluid.width, fluid.height);
+        let side = self.side() as f32;
+        let center_x = (w - 1) as f32 / 2.0;
+        // The lid row is height 1 and the bottom ring height 0
+        let center_y = (h - 1) as f32 - side / 2.0;
+
+        let u_profile: Vec<(f32, f32)> = GHIA_RE100_U
+            .iter()
+            .map(|&(height, _)| {
+                let y = (h - 1) as f32 - height * side;
+                (height, sample(&fluid.velocity_x, w, h, center_x, y) / self.lid_speed)
+            })
+            .collect();
+        // Rows grow downward, so upward flow is negative velocity_y
+        let v_profile: Vec<(f32, f32)> = GHIA_RE100_V
+            .iter()
+            .map(|&(position, _)| (position, -sample(&fluid.velocity_y, w, h, position * side, center_y) / self.lid_speed))
+            .collect();
+
+        CavityReport {
+            u: ErrorNorms::from_pairs(u_profile.iter().zip(&GHIA_RE100_U).map(|(sim, reference)| (sim.1, reference.1))),
+            v: ErrorNorms::from_pairs(v_profile.iter().zip(&GHIA_RE100_V).map(|(sim, reference)| (sim.1, reference.1))),
+            u_profile,
+            v_profile,
+        }
+    }
+}
+
+// Bilinear sample at cell coordinates, clamped to the grid
+fn sample(field: &[f32], width: usize, height: usize, x: f32, y: f32) -> f32 {
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (sx, sy) = (x - x0 as f32, y - y0 as f32);
+    let top = field[y0 * width + x0] * (1.0 - sx) + field[y0 * width + x1] * sx;
+    let bottom = field[y1 * width + x0] * (1.0 - sx) + field[y1 * width + x1] * sx;
+    top * (1.0 - sy) + bottom * sy
+}
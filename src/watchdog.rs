@@ -0,0 +1,343 @@
+//! Watchdog for unattended runs: steps the simulation on a worker thread,
+//! detects panics and missed deadlines, and restarts from the last
+//! checkpoint with an incident report.
+
+use crate::commands::SimCommand;
+use crate::InteractiveFluid;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Once};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub type StepFn = Arc<dyn Fn(&mut InteractiveFluid) + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// Longest a single step may take before the worker counts as hung
+    pub deadline: Duration,
+    /// Steps between in-memory checkpoints
+    pub checkpoint_interval: u64,
+    /// Recent commands kept for incident reports
+    pub command_history: usize,
+    /// Directory for incident report files; reports always go to stderr
+    pub incident_dir: Option<PathBuf>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            deadline: Duration::from_secs(2),
+            checkpoint_interval: 300,
+            command_history: 50,
+            incident_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    Panic(String),
+    DeadlineMissed(Duration),
+}
+
+/// Cheap summary of a simulation state, taken at every checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub step: u64,
+    pub max_speed: f32,
+    pub total_dye: f32,
+    pub non_finite_cells: usize,
+}
+
+impl Health {
+    pub fn measure(sim: &InteractiveFluid, step: u64) -> Self {
+        let mut max_speed: f32 = 0.0;
+        let mut total_dye = 0.0;
+        let mut non_finite_cells = 0;
+        for i in 0..sim.width * sim.height {
+            let values = [sim.velocity_x[i], sim.velocity_y[i], sim.dye_r[i], sim.dye_g[i], sim.dye_b[i]];
+            if values.iter().any(|v| !v.is_finite()) {
+                non_finite_cells += 1;
+                continue;
+            }
+            max_speed = max_speed.max((values[0] * values[0] + values[1] * values[1]).sqrt());
+            total_dye += values[2] + values[3] + values[4];
+        }
+        Self {
+            step,
+            max_speed,
+            total_dye,
+            non_finite_cells,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub fault: Fault,
+    pub time: SystemTime,
+    /// Step that failed
+    pub step: u64,
+    /// Step the simulation was restored to
+    pub restored_step: u64,
+    /// Commands leading up to the fault, oldest first. The ones queued for
+    /// the failed step are included but not replayed.
+    pub recent_commands: Vec<SimCommand>,
+    pub last_health: Health,
+}
+
+impl Incident {
+    pub fn report(&self) -> String {
+        let since_epoch = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let fault = match &self.fault {
+            Fault::Panic(message) => format!("panic: {}", message),
+            Fault::DeadlineMissed(deadline) => format!("step exceeded {:.2}s deadline", deadline.as_secs_f32()),
+        };
+        let health = &self.last_health;
+        let mut report = format!(
+            "Simulation incident at {}s\n  fault: {}\n  failed step: {}\n  restored to step: {}\n  \
+             health at step {}: max speed {:.3}, total dye {:.3}, non-finite cells {}\n  recent commands:\n",
+            since_epoch.as_secs(),
+            fault,
+            self.step,
+            self.restored_step,
+            health.step,
+            health.max_speed,
+            health.total_dye,
+            health.non_finite_cells,
+        );
+        for command in &self.recent_commands {
+            report.push_str(&format!("    {:?}\n", command));
+        }
+        report
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(dir)?;
+        let since_epoch = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let path = dir.join(format!("incident_{}_{}.txt", since_epoch.as_secs(), self.step));
+        std::fs::write(&path, self.report())?;
+        Ok(path)
+    }
+}
+
+struct Job {
+    sim: InteractiveFluid,
+    commands: Vec<SimCommand>,
+}
+
+type JobResult = Result<InteractiveFluid, String>;
+
+struct Worker {
+    jobs: Sender<Job>,
+    results: Receiver<JobResult>,
+}
+
+impl Worker {
+    fn spawn(step: StepFn) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, results) = mpsc::channel();
+        let spawned = thread::Builder::new().name(WORKER_THREAD.to_string()).spawn(move || {
+            for Job { mut sim, commands } in job_receiver {
+                let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                    for command in &commands {
+                        command.apply(&mut sim);
+                    }
+                    step(&mut sim);
+                }));
+                let result = match outcome {
+                    Ok(()) => Ok(sim),
+                    Err(payload) => Err(panic_message(payload)),
+                };
+                if result_sender.send(result).is_err() {
+                    break; // abandoned after a missed deadline
+                }
+            }
+        });
+        spawned.expect("failed to spawn the watchdog worker");
+        Self { jobs, results }
+    }
+}
+
+thread_local! {
+    // Location of the last panic on this thread, recorded by the hook
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+const WORKER_THREAD: &str = "watchdog-worker";
+
+// Chain a hook that remembers where worker panics happen, since the
+// unwind payload only carries the message. Worker panics become incidents
+// instead of being printed: the default hook can take longer than the
+// deadline to symbolize a backtrace, which would turn the panic into a
+// missed deadline.
+fn install_panic_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().name() != Some(WORKER_THREAD) {
+                previous(info);
+                return;
+            }
+            let location = info.location().map(|l| format!("{}:{}", l.file(), l.line()));
+            PANIC_LOCATION.with(|slot| *slot.borrow_mut() = location);
+        }));
+    });
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    let message = if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    };
+    match PANIC_LOCATION.with(|slot| slot.borrow_mut().take()) {
+        Some(location) => format!("{} at {}", message, location),
+        None => message,
+    }
+}
+
+/// Runs an `InteractiveFluid` under supervision. Every step is executed on
+/// a worker thread; if it panics or misses the deadline, the simulation is
+/// restored from the last checkpoint, a fresh worker is started and an
+/// `Incident` is recorded. A hung worker can't be killed, so it is
+/// abandoned and exits on its own if it ever finishes.
+pub struct Watchdog {
+    pub config: WatchdogConfig,
+    sim: Option<InteractiveFluid>,
+    step_fn: StepFn,
+    worker: Worker,
+    step: u64,
+    checkpoint: InteractiveFluid,
+    checkpoint_step: u64,
+    checkpoint_health: Health,
+    pending: Vec<SimCommand>,
+    history: VecDeque<SimCommand>,
+    incidents: Vec<Incident>,
+}
+
+impl Watchdog {
+    pub fn new(sim: InteractiveFluid, config: WatchdogConfig) -> Self {
+        Self::with_step(sim, config, Arc::new(InteractiveFluid::step))
+    }
+
+    /// Supervise a custom step function instead of `InteractiveFluid::step`.
+    pub fn with_step(sim: InteractiveFluid, config: WatchdogConfig, step_fn: StepFn) -> Self {
+        install_panic_hook();
+        let checkpoint_health = Health::measure(&sim, 0);
+        Self {
+            config,
+            checkpoint: sim.clone(),
+            sim: Some(sim),
+            worker: Worker::spawn(step_fn.clone()),
+            step_fn,
+            step: 0,
+            checkpoint_step: 0,
+            checkpoint_health,
+            pending: Vec::new(),
+            history: VecDeque::new(),
+            incidents: Vec::new(),
+        }
+    }
+
+    /// Queue a command for the next step.
+    pub fn queue(&mut self, command: SimCommand) {
+        self.pending.push(command);
+        if self.history.len() == self.config.command_history {
+            self.history.pop_front();
+        }
+        if self.config.command_history > 0 {
+            self.history.push_back(command);
+        }
+    }
+
+    /// Advance one step. Returns the incident if the step failed and the
+    /// simulation was restored.
+    pub fn step(&mut self) -> Option<&Incident> {
+        let sim = self.sim.take().expect("simulation is only taken during a step");
+        let commands = std::mem::take(&mut self.pending);
+
+        let fault = match self.worker.jobs.send(Job { sim, commands }) {
+            Err(_) => Fault::Panic("worker thread exited".to_string()),
+            Ok(()) => match self.worker.results.recv_timeout(self.config.deadline) {
+                Ok(Ok(sim)) => {
+                    self.sim = Some(sim);
+                    self.step += 1;
+                    if self.config.checkpoint_interval > 0 && self.step.is_multiple_of(self.config.checkpoint_interval) {
+                        self.checkpoint();
+                    }
+                    return None;
+                }
+                Ok(Err(message)) => Fault::Panic(message),
+                Err(RecvTimeoutError::Timeout) => Fault::DeadlineMissed(self.config.deadline),
+                Err(RecvTimeoutError::Disconnected) => Fault::Panic("worker thread exited".to_string()),
+            },
+        };
+
+        Some(self.recover(fault))
+    }
+
+    /// Take a checkpoint of the current state now.
+    pub fn checkpoint(&mut self) {
+        let sim = self.simulation().clone();
+        self.checkpoint_health = Health::measure(&sim, self.step);
+        self.checkpoint = sim;
+        self.checkpoint_step = self.step;
+    }
+
+    pub fn simulation(&self) -> &InteractiveFluid {
+        self.sim.as_ref().expect("simulation is only taken during a step")
+    }
+
+    /// Mutable access between steps, e.g. for direct mouse input.
+    pub fn simulation_mut(&mut self) -> &mut InteractiveFluid {
+        self.sim.as_mut().expect("simulation is only taken during a step")
+    }
+
+    pub fn step_count(&self) -> u64 {
+        self.step
+    }
+
+    pub fn checkpoint_step(&self) -> u64 {
+        self.checkpoint_step
+    }
+
+    pub fn incidents(&self) -> &[Incident] {
+        &self.incidents
+    }
+
+    fn recover(&mut self, fault: Fault) -> &Incident {
+        let incident = Incident {
+            fault,
+            time: SystemTime::now(),
+            step: self.step + 1,
+            restored_step: self.checkpoint_step,
+            recent_commands: self.history.iter().copied().collect(),
+            last_health: self.checkpoint_health,
+        };
+
+        // The old worker is either dead or stuck; never reuse it
+        self.worker = Worker::spawn(self.step_fn.clone());
+        self.sim = Some(self.checkpoint.clone());
+        self.step = self.checkpoint_step;
+
+        eprint!("{}", incident.report());
+        if let Some(dir) = &self.config.incident_dir {
+            match incident.save(dir) {
+                Ok(path) => eprintln!("Incident report written to {}", path.display()),
+                Err(e) => eprintln!("Failed to write incident report: {}", e),
+            }
+        }
+
+        self.incidents.push(incident);
+        self.incidents.last().unwrap()
+    }
+}
@@ -0,0 +1,78 @@
+// Still covers the deprecated solvers until they are removed
+#![allow(deprecated)]
+
+use itsliquid::{AnalysisRecorder, DyeMetrics, FluidMetrics, FluidSolver, GateSet, InteractiveFluid};
+
+#[test]
+fn test_dye_metrics_per_channel() {
+    let mut sim = InteractiveFluid::new(20, 10);
+    sim.dye_r[2 * 20 + 4] = 2.0;
+    sim.dye_b[5 * 20 + 10] = 1.0;
+    sim.dye_b[5 * 20 + 14] = 1.0;
+
+    let metrics = FluidMetrics::analyze_interactive(&sim, 3);
+    assert_eq!(metrics.frame, 3);
+    assert!((metrics.total_mass - 4.0).abs() < 1e-5);
+    let dye = metrics.dye.expect("RGB solver reports dye");
+    assert_eq!(dye.total, [2.0, 0.0, 2.0]);
+    assert_eq!(dye.center_of_mass[0], Some((4.5, 2.5)));
+    assert_eq!(dye.center_of_mass[1], None);
+    assert_eq!(dye.center_of_mass[2], Some((12.5, 5.5)));
+    assert!(metrics.boundary_leak.is_some());
+}
+
+#[test]
+fn test_luminance_entropy_measures_spread() {
+    // All the light in one cell
+    let mut sim = InteractiveFluid::new(8, 8);
+    sim.dye_g[10] = 1.0;
+    let dye = DyeMetrics::analyze(8, [&sim.dye_r, &sim.dye_g, &sim.dye_b]);
+    assert_eq!(dye.luminance_entropy, 0.0);
+
+    // Evenly over all 64 cells
+    sim.dye_g.fill(0.5);
+    let dye = DyeMetrics::analyze(8, [&sim.dye_r, &sim.dye_g, &sim.dye_b]);
+    assert!((dye.luminance_entropy - 6.0).abs() < 1e-4, "{}", dye.luminance_entropy);
+
+    // Green counts for more brightness than blue: two equal amounts in two
+    // cells split the light unevenly
+    let mut sim = InteractiveFluid::new(8, 8);
+    sim.dye_g[0] = 1.0;
+    sim.dye_b[1] = 1.0;
+    let dye = DyeMetrics::analyze(8, [&sim.dye_r, &sim.dye_g, &sim.dye_b]);
+    assert!(dye.luminance_entropy > 0.0 && dye.luminance_entropy < 1.0);
+
+    let empty = InteractiveFluid::new(8, 8);
+    let dye = DyeMetrics::analyze(8, [&empty.dye_r, &empty.dye_g, &empty.dye_b]);
+    assert_eq!(dye.luminance_entropy, 0.0);
+    assert_eq!(dye.center_of_mass, [None; 3]);
+}
+
+#[test]
+fn test_single_channel_solvers_report_no_dye() {
+    let solver = FluidSolver::new(10, 10);
+    assert!(FluidMetrics::analyze(&solver, 0).dye.is_none());
+}
+
+#[test]
+fn test_recorder_sums_gate_flux() {
+    let mut sim = InteractiveFluid::new(32, 32);
+    sim.velocity_x.fill(1.0);
+    sim.dye_b.fill(0.5);
+    let mut gates = GateSet::new();
+    gates.add("channel", glam::Vec2::new(16.0, 20.0), glam::Vec2::new(16.0, 10.0));
+
+    let mut recorder = AnalysisRecorder::new();
+    for frame in 0..4 {
+        gates.measure(&sim, frame);
+        recorder.record_metrics(FluidMetrics::analyze_interactive(&sim, frame).with_gates(&gates));
+    }
+
+    let totals = recorder.cumulative_gate_flux();
+    assert_eq!(totals.len(), 1);
+    let (name, flux) = &totals[0];
+    assert_eq!(name, "channel");
+    assert_eq!(flux.frame, 3);
+    assert!((flux.volume - 4.0).abs() < 1e-3, "volume {}", flux.volume);
+    assert!((flux.dye[2] - 2.0).abs() < 1e-3, "dye {}", flux.dye[2]);
+}
@@ -0,0 +1,111 @@
+use itsliquid::{BoundaryMode, InteractiveFluid, Scene};
+
+// Dye-weighted center of the red channel, in cells
+fn red_centroid(fluid: &InteractiveFluid) -> (f32, f32) {
+    let (mut total, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for (i, &v) in fluid.dye_r.iter().enumerate() {
+        total += v;
+        cx += v * (i % fluid.width) as f32;
+        cy += v * (i / fluid.width) as f32;
+    }
+    (cx / total, cy / total)
+}
+
+// Spread of the red channel around its center along each axis, in cells²
+fn red_variance(fluid: &InteractiveFluid) -> (f32, f32) {
+    let (cx, cy) = red_centroid(fluid);
+    let (mut total, mut vx, mut vy) = (0.0, 0.0, 0.0);
+    for (i, &v) in fluid.dye_r.iter().enumerate() {
+        total += v;
+        vx += v * ((i % fluid.width) as f32 - cx).powi(2);
+        vy += v * ((i / fluid.width) as f32 - cy).powi(2);
+    }
+    (vx / total, vy / total)
+}
+
+#[test]
+fn test_domain_size_sets_aspect() {
+    let mut fluid = InteractiveFluid::new(64, 32);
+    assert_eq!(fluid.cell_aspect, 1.0);
+    fluid.set_domain_size(640.0, 320.0);
+    assert_eq!(fluid.cell_aspect, 1.0);
+    // A square domain on a 2:1 grid has cells twice as tall as wide
+    fluid.set_domain_size(1.0, 1.0);
+    assert_eq!(fluid.cell_aspect, 2.0);
+    fluid.set_domain_size(0.0, 1.0);
+    assert_eq!(fluid.cell_aspect, 2.0);
+
+    let loaded = Scene::capture(&fluid, &[], false).simulation();
+    assert_eq!(loaded.cell_aspect, 2.0);
+}
+
+#[test]
+fn test_tall_cells_slow_vertical_motion() {
+    let mut fluid = InteractiveFluid::new(64, 32);
+    fluid.boundary = BoundaryMode::Periodic;
+    fluid.cell_aspect = 2.0;
+    for y in 15..18 {
+        for x in 19..22 {
+            fluid.add_dye(x, y, (1.0, 0.0, 0.0));
+        }
+    }
+    // The same speed on both axes
+    fluid.velocity_x.fill(1.0);
+    fluid.velocity_y.fill(1.0);
+    let before = red_centroid(&fluid);
+    for _ in 0..40 {
+        fluid.step();
+    }
+    let after = red_centroid(&fluid);
+    // 40 steps of dt 0.1: four cell widths across, the same distance down
+    // is two cell heights
+    assert!((after.0 - before.0 - 4.0).abs() < 0.05, "{:?} {:?}", before, after);
+    assert!((after.1 - before.1 - 2.0).abs() < 0.05, "{:?} {:?}", before, after);
+}
+
+#[test]
+fn test_diffusion_is_round_in_the_domain() {
+    let mut fluid = InteractiveFluid::new(64, 32);
+    fluid.cell_aspect = 2.0;
+    fluid.dye_diffusion = 0.1 / (fluid.dt * (64 * 32) as f32);
+    fluid.add_dye(32, 16, (1.0, 0.0, 0.0));
+    for _ in 0..50 {
+        fluid.step();
+    }
+    // A circle spans twice as many cells across as down
+    let (vx, vy) = red_variance(&fluid);
+    assert!((vx / vy - 4.0).abs() < 0.6, "{} {}", vx, vy);
+}
+
+#[test]
+fn test_projection_keeps_flow_divergence_free_for_the_cell_shape() {
+    let (w, h) = (48, 24);
+    // Velocity from a stream function vanishing at the edges, with its
+    // derivatives taken over the cells' true width and height
+    let psi = |x: usize, y: usize| {
+        let (sx, sy) = (
+            (std::f32::consts::PI * x as f32 / (w - 1) as f32).sin(),
+            (std::f32::consts::PI * y as f32 / (h - 1) as f32).sin(),
+        );
+        10.0 * sx * sx * sy * sy
+    };
+    let project = |aspect: f32| {
+        let mut fluid = InteractiveFluid::new(w, h);
+        for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                let idx = y * w + x;
+                fluid.velocity_x[idx] = (psi(x, y + 1) - psi(x, y - 1)) / 2.0;
+                fluid.velocity_y[idx] = -(psi(x + 1, y) - psi(x - 1, y));
+            }
+        }
+        let before = fluid.velocity_x.iter().chain(&fluid.velocity_y).cloned().collect::<Vec<f32>>();
+        fluid.cell_aspect = aspect;
+        fluid.project_velocity();
+        let after = fluid.velocity_x.iter().chain(&fluid.velocity_y);
+        before.iter().zip(after).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+    };
+    // Already divergence-free on 2:1 cells, but not on square ones
+    let (kept, changed) = (project(2.0), project(1.0));
+    assert!(kept < 1e-3, "{}", kept);
+    assert!(changed > 10.0 * kept.max(1e-4), "{} {}", kept, changed);
+}
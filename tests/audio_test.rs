@@ -0,0 +1,66 @@
+use itsliquid::{AudioBand, AudioLevels, AudioLink, PersistentElement, PersistentElementType};
+
+fn sine(frequency: f32, sample_rate: f32, seconds: f32) -> Vec<f32> {
+    let count = (sample_rate * seconds) as usize;
+    (0..count)
+        .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate).sin())
+        .collect()
+}
+
+#[test]
+fn test_bands_separate_tones() {
+    let rate = 48_000.0;
+    let low = AudioLevels::analyze(&sine(60.0, rate, 0.5), rate);
+    assert!(low.bass > 0.7, "{:?}", low);
+    assert!(low.bass > low.mid * 2.0 && low.bass > low.treble * 4.0, "{:?}", low);
+
+    let high = AudioLevels::analyze(&sine(16_000.0, rate, 0.5), rate);
+    assert!(high.treble > 0.6, "{:?}", high);
+    assert!(high.treble > high.mid * 2.0 && high.treble > high.bass * 4.0, "{:?}", high);
+
+    assert_eq!(AudioLevels::analyze(&[], rate), AudioLevels::default());
+    assert_eq!(AudioLevels::analyze(&vec![0.0; 1000], rate), AudioLevels::default());
+}
+
+#[test]
+fn test_levels_follow_with_release() {
+    let mut levels = AudioLevels::default();
+    let loud = AudioLevels { bass: 0.8, mid: 0.2, treble: 0.0 };
+    levels.follow(loud, 0.5);
+    assert_eq!(levels, loud);
+    // Falls halfway towards silence per call
+    levels.follow(AudioLevels::default(), 0.5);
+    assert!((levels.bass - 0.4).abs() < 1e-6);
+
+    let scaled = AudioLevels { bass: 0.5, mid: 0.1, treble: -0.1 }.scaled(4.0);
+    assert_eq!(scaled, AudioLevels { bass: 1.0, mid: 0.4, treble: 0.0 });
+}
+
+#[test]
+fn test_audio_link_scales_element_strength() {
+    let mut elem = PersistentElement {
+        element_type: PersistentElementType::AttractorSource { strength: 10.0, absorb: false },
+        x: 5.0,
+        y: 5.0,
+        radius: 4.0,
+        audio: None,
+    };
+    let levels = AudioLevels { bass: 0.5, mid: 1.0, treble: 0.0 };
+    // Unlinked elements ignore audio
+    assert_eq!(elem.modulated(&levels), elem);
+
+    elem.audio = Some(AudioLink { band: AudioBand::Bass, gain: 2.0 });
+    let pulsed = elem.modulated(&levels);
+    assert_eq!(pulsed.element_type, PersistentElementType::AttractorSource { strength: 20.0, absorb: false });
+    assert_eq!((pulsed.x, pulsed.radius, pulsed.audio), (elem.x, elem.radius, elem.audio));
+    // Silence leaves it as placed
+    assert_eq!(elem.modulated(&AudioLevels::default()).element_type, elem.element_type);
+}
+
+#[test]
+fn test_elements_without_audio_link_still_load() {
+    let json = r#"{"element_type":{"type":"HeatSource","amount":1.5},"x":1.0,"y":2.0,"radius":3.0}"#;
+    let elem: PersistentElement = serde_json::from_str(json).unwrap();
+    assert_eq!(elem.audio, None);
+    assert!(!serde_json::to_string(&elem).unwrap().contains("audio"));
+}
@@ -1,3 +1,6 @@
+// Still covers the deprecated solvers until they are removed
+#![allow(deprecated)]
+
 use itsliquid::{FluidFinal, InteractiveFluid};
 
 #[test]
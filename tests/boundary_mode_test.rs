@@ -0,0 +1,106 @@
+use glam::Vec2;
+use itsliquid::{BoundaryMode, InteractiveFluid, Scheme, Solver, SolverConfig, kernels};
+
+const N: usize = 40;
+
+// Dye in the left quarter after a rightward jet runs near the right edge
+fn dye_wrapped_left(boundary: BoundaryMode) -> (f32, InteractiveFluid) {
+    let mut sim = InteractiveFluid::new(N, N);
+    sim.boundary = boundary;
+    for _ in 0..60 {
+        sim.add_dye(N - 6, N / 2, (1.0, 0.0, 0.0));
+        sim.add_force(N - 6, N / 2, Vec2::new(60.0, 0.0), 3.0);
+        sim.step();
+    }
+    let left: f32 = (0..N).flat_map(|y| (1..N / 4).map(move |x| y * N + x)).map(|i| sim.dye_r[i]).sum();
+    (left, sim)
+}
+
+#[test]
+fn test_periodic_edges_carry_dye_across() {
+    let (walled, _) = dye_wrapped_left(BoundaryMode::NoSlip);
+    let (wrapped, sim) = dye_wrapped_left(BoundaryMode::Periodic);
+    assert!(wrapped > 1.0, "no dye came back in on the left: {}", wrapped);
+    assert!(wrapped > 10.0 * walled, "periodic {} vs walled {}", wrapped, walled);
+
+    // Nothing leaves a periodic domain
+    assert_eq!(sim.boundary_leak.total_outflow(), [0.0; 3]);
+    // Ghost cells hold the opposite interior
+    for y in 1..N - 1 {
+        assert_eq!(sim.dye_r[y * N], sim.dye_r[y * N + N - 2]);
+        assert_eq!(sim.velocity_x[y * N + N - 1], sim.velocity_x[y * N + 1]);
+    }
+}
+
+#[test]
+fn test_free_slip_keeps_tangential_flow() {
+    for boundary in [BoundaryMode::NoSlip, BoundaryMode::FreeSlip] {
+        let mut sim = InteractiveFluid::new(N, N);
+        sim.boundary = boundary;
+        for _ in 0..10 {
+            // Flow along the top wall
+            sim.add_force(N / 2, 2, Vec2::new(40.0, 0.0), 3.0);
+            sim.step();
+        }
+        let along: f32 = (1..N - 1).map(|x| sim.velocity_x[x]).sum();
+        let into: f32 = (0..N).map(|x| sim.velocity_y[x].abs()).sum();
+        assert_eq!(into, 0.0, "{:?} lets flow through the wall", boundary);
+        match boundary {
+            BoundaryMode::NoSlip => assert_eq!(along, 0.0),
+            _ => assert!(along > 0.0, "free-slip wall stopped the flow"),
+        }
+    }
+}
+
+#[test]
+fn test_open_edges_let_flow_leave() {
+    let mut sim = InteractiveFluid::new(N, N);
+    sim.boundary = BoundaryMode::Open;
+    for _ in 0..20 {
+        sim.add_force(N - 4, N / 2, Vec2::new(40.0, 0.0), 3.0);
+        sim.step();
+    }
+    // Velocity through the right edge matches the cells beside it
+    let row = (N / 2) * N;
+    assert!(sim.velocity_x[row + N - 1] > 0.0);
+    assert_eq!(sim.velocity_x[row + N - 1], sim.velocity_x[row + N - 2]);
+    assert_eq!(sim.pressure[row + N - 1], 0.0);
+}
+
+#[test]
+fn test_periodic_backtrace_just_under_the_seam() {
+    // With a power-of-two interior, 1 + folded can round up onto the far ring
+    for size in [10, 34, 66, 130] {
+        let mut p = 1.0f32;
+        for _ in 0..64 {
+            p = p.next_down();
+            let wrapped = kernels::wrap_interior(p, size);
+            assert!((1.0..(size - 1) as f32).contains(&wrapped), "{} wrapped to {} on {}", p, wrapped, size);
+        }
+    }
+
+    // Tiny rightward velocities backtrace a hair left of the first interior cell
+    let (w, h) = (66, 4);
+    let source = vec![1.0; w * h];
+    let vy = vec![0.0; w * h];
+    for k in 1..64 {
+        let mut vx = vec![0.0; w * h];
+        vx[w + 1] = k as f32 * 1e-7;
+        let mut row = vec![0.0; w];
+        kernels::advect_row_periodic([&mut row], [&source], &vx, &vy, 1, w, h, [1.0, 1.0]);
+        assert!((row[1] - 1.0).abs() < 1e-5, "{}", row[1]);
+    }
+}
+
+#[test]
+fn test_schemes_declare_supported_boundaries() {
+    for mode in [BoundaryMode::Periodic, BoundaryMode::Open, BoundaryMode::FreeSlip] {
+        let solver = Solver::new(SolverConfig::new(Scheme::Interactive).with_boundary(mode), N, N).unwrap();
+        assert_eq!(solver.as_interactive().unwrap().boundary, mode);
+        assert!(Solver::new(SolverConfig::new(Scheme::Stable).with_boundary(mode), N, N).is_err());
+    }
+    // The first listed mode is each scheme's default
+    for scheme in Scheme::ALL {
+        assert_eq!(scheme.boundaries()[0], scheme.boundary());
+    }
+}
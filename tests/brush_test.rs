@@ -0,0 +1,88 @@
+use glam::Vec2;
+use itsliquid::{BlendMode, Brush, BrushStroke, Falloff};
+
+#[test]
+fn test_falloff_fades_to_edge() {
+    for falloff in Falloff::ALL {
+        assert_eq!(falloff.weight(0.0), 1.0);
+        assert!(falloff.weight(0.5) < 1.0 && falloff.weight(0.5) > falloff.weight(0.9));
+        assert!(falloff.weight(1.0) < 0.02);
+            assert_eq!(falloff.weight(1.5), 0.0);
+    }
+}
+
+#[test]
+fn test_dab_covers_radius_and_clips_to_grid() {
+    let brush = Brush { radius: 3.0, ..Brush::default() };
+    let cells = brush.dab(10.5, 10.5, 32, 32);
+    assert!(cells.iter().all(|&(x, y, _)| (x as f32 - 10.0).hypot(y as f32 - 10.0) <= 3.0));
+    let center = cells.iter().find(|&&(x, y, _)| (x, y) == (10, 10)).unwrap();
+    assert_eq!(center.2, 1.0);
+
+    // Near a corner only the cells on the grid are left
+    let corner = brush.dab(0.5, 0.5, 32, 32);
+    assert!(!corner.is_empty() && corner.len() < cells.len());
+
+    // A tiny brush still paints the cell under it
+    let tiny = Brush { radius: 0.1, ..Brush::default() };
+    assert_eq!(tiny.dab(4.2, 7.8, 32, 32).iter().map(|c| (c.0, c.1)).collect::<Vec<_>>(), vec![(4, 7)]);
+}
+
+#[test]
+fn test_pressure_scales_only_when_enabled() {
+    let mut brush = Brush::default();
+    assert_eq!(brush.pressure_scale(None), 1.0);
+    assert_eq!(brush.pressure_scale(Some(0.25)), 0.25);
+    assert_eq!(brush.pressure_scale(Some(3.0)), 1.0);
+    brush.pressure = false;
+    assert_eq!(brush.pressure_scale(Some(0.25)), 1.0);
+}
+
+#[test]
+fn test_stroke_spaces_dabs_by_distance() {
+    let brush = Brush { radius: 4.0, spacing: 0.5, ..Brush::default() };
+    let mut stroke = BrushStroke::default();
+    assert_eq!(stroke.dabs(&brush, Vec2::new(0.0, 0.0)), vec![Vec2::new(0.0, 0.0)]);
+    assert!(stroke.dabs(&brush, Vec2::new(1.0, 0.0)).is_empty());
+    assert_eq!(stroke.dabs(&brush, Vec2::new(2.0, 0.0)), vec![Vec2::new(2.0, 0.0)]);
+    stroke.end();
+    assert_eq!(stroke.dabs(&brush, Vec2::new(2.5, 0.0)), vec![Vec2::new(2.5, 0.0)]);
+}
+
+#[test]
+fn test_fast_stroke_fills_the_gap() {
+    let brush = Brush { radius: 2.0, spacing: 0.5, ..Brush::default() };
+    let mut stroke = BrushStroke::default();
+    stroke.dabs(&brush, Vec2::new(0.0, 10.0));
+
+    // One frame's jump of 9.5 cells is filled with dabs a cell apart
+    let dabs = stroke.dabs(&brush, Vec2::new(9.5, 10.0));
+    assert_eq!(dabs.len(), 9);
+    for (i, dab) in dabs.iter().enumerate() {
+        assert!((dab.x - (i + 1) as f32).abs() < 1e-4 && dab.y == 10.0);
+    }
+
+    // The leftover half cell carries into the next segment
+    let dabs = stroke.dabs(&brush, Vec2::new(9.0, 10.5));
+    assert!(dabs.is_empty());
+    let dabs = stroke.dabs(&brush, Vec2::new(10.0, 10.0));
+    assert_eq!(dabs.len(), 1);
+}
+
+#[test]
+fn test_blend_modes() {
+    let over = BlendMode::Over { opacity: 0.5 };
+    assert_eq!(BlendMode::Add.blend(1.0, 2.0, 0.5), 2.0);
+    assert_eq!(BlendMode::Subtract.blend(1.0, 4.0, 0.5), 0.0);
+    assert_eq!(BlendMode::Replace.blend(3.0, 0.5, 0.1), 0.5);
+    assert_eq!(BlendMode::Replace.blend(3.0, 0.5, 0.0), 3.0);
+    assert_eq!(BlendMode::Multiply.blend(2.0, 0.5, 1.0), 1.0);
+    assert_eq!(BlendMode::Multiply.blend(2.0, 0.5, 0.0), 2.0);
+    assert_eq!(over.blend(1.0, 3.0, 1.0), 2.0);
+    assert_eq!(over.blend(1.0, 3.0, 0.5), 1.5);
+
+    // Weight outside 0..1 is clamped
+    assert_eq!(over.blend(1.0, 3.0, 4.0), 2.0);
+    assert!(over.same_kind(BlendMode::Over { opacity: 1.0 }) && !over.same_kind(BlendMode::Add));
+    assert_eq!(Brush::default().blend, BlendMode::Add);
+}
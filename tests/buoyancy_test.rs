@@ -0,0 +1,115 @@
+use itsliquid::InteractiveFluid;
+
+fn mean_vertical_velocity(sim: &InteractiveFluid, cx: usize, cy: usize, r: usize) -> f32 {
+    let mut sum = 0.0;
+    let mut count = 0;
+    for y in cy - r..=cy + r {
+        for x in cx - r..=cx + r {
+            sum += sim.velocity_y[y * sim.width + x];
+            count += 1;
+        }
+    }
+    sum / count as f32
+}
+
+#[test]
+fn test_hot_fluid_rises() {
+    let mut sim = InteractiveFluid::new(48, 48);
+    sim.add_heat(24, 32, 5.0, 4.0);
+
+    for _ in 0..10 {
+        sim.step();
+    }
+
+    // y grows downward: rising means negative vertical velocity
+    let vy = mean_vertical_velocity(&sim, 24, 30, 3);
+    assert!(vy < 0.0, "hot plume should rise, mean vy = {}", vy);
+}
+
+#[test]
+fn test_cold_fluid_sinks() {
+    let mut sim = InteractiveFluid::new(48, 48);
+    sim.add_heat(24, 16, -5.0, 4.0);
+
+    for _ in 0..10 {
+        sim.step();
+    }
+
+    let vy = mean_vertical_velocity(&sim, 24, 18, 3);
+    assert!(vy > 0.0, "cold blob should sink, mean vy = {}", vy);
+}
+
+#[test]
+fn test_temperature_cools_toward_ambient() {
+    let mut sim = InteractiveFluid::new(32, 32);
+    sim.ambient_temperature = 1.0;
+    sim.temperature.fill(1.0);
+    sim.add_heat(16, 16, 4.0, 3.0);
+
+    let excess = |sim: &InteractiveFluid| -> f32 {
+        sim.temperature.iter().map(|t| (t - sim.ambient_temperature).abs()).sum()
+    };
+
+    let initial = excess(&sim);
+    for _ in 0..50 {
+        sim.step();
+    }
+    let later = excess(&sim);
+
+    assert!(later < initial * 0.9, "excess heat {} -> {}", initial, later);
+    assert!(sim.temperature.iter().all(|t| t.is_finite()));
+}
+
+#[test]
+fn test_zero_buoyancy_leaves_velocity_untouched() {
+    let mut sim = InteractiveFluid::new(32, 32);
+    sim.buoyancy = 0.0;
+    sim.add_heat(16, 16, 10.0, 3.0);
+
+    for _ in 0..5 {
+        sim.step();
+    }
+
+    assert!(sim.velocity_x.iter().chain(&sim.velocity_y).all(|&v| v == 0.0));
+}
+
+// Height of the red dye's center, in cells from the top
+fn red_depth(sim: &InteractiveFluid) -> f32 {
+    let total: f32 = sim.dye_r.iter().sum();
+    let moment: f32 = sim.dye_r.iter().enumerate().map(|(i, d)| (i / sim.width) as f32 * d).sum();
+    moment / total
+}
+
+#[test]
+fn test_heavy_dye_sinks_and_light_dye_rises() {
+    let run = |density: [f32; 3]| {
+        let mut sim = InteractiveFluid::new(48, 48);
+        sim.dye_density = density;
+        for y in 20..28 {
+            for x in 20..28 {
+                sim.dye_r[y * 48 + x] = 1.0;
+                sim.dye_b[y * 48 + x] = 1.0;
+            }
+        }
+        let start = red_depth(&sim);
+        for _ in 0..20 {
+            sim.step();
+        }
+        red_depth(&sim) - start
+    };
+    assert!(run([0.0; 3]).abs() < 0.1);
+    assert!(run([3.0, 0.0, 0.0]) > 0.5);
+    // Light blue carries the red up with it
+    assert!(run([0.0, 0.0, -3.0]) < -0.5);
+    // Equal and opposite weights cancel out
+    assert!(run([2.0, 0.0, -2.0]).abs() < 0.1);
+}
+
+#[test]
+fn test_scenes_keep_the_dye_density() {
+    let mut sim = InteractiveFluid::new(8, 8);
+    sim.dye_density = [1.0, 0.0, -0.5];
+    let mut restored = InteractiveFluid::new(8, 8);
+    itsliquid::SceneParams::capture(&sim).apply(&mut restored);
+    assert_eq!(restored.dye_density, [1.0, 0.0, -0.5]);
+}
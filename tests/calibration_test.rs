@@ -0,0 +1,52 @@
+use itsliquid::calibration::{self, BENCH_SIZE};
+use itsliquid::{Calibration, DeviceClass, Quality};
+
+#[test]
+fn test_presets_scale_with_throughput() {
+    let phone = Calibration::from_throughput(1.5, false);
+    assert_eq!(phone.device, DeviceClass::Phone);
+    assert_eq!((phone.resolution_scale, phone.substeps, phone.quality), (1, 1, Quality::Low));
+
+    let laptop = Calibration::from_throughput(6.0, false);
+    assert_eq!(laptop.device, DeviceClass::Integrated);
+    assert_eq!(laptop.quality, Quality::Medium);
+
+    let desktop = Calibration::from_throughput(40.0, false);
+    assert_eq!(desktop.device, DeviceClass::Desktop);
+    assert_eq!(desktop.quality, Quality::High);
+    assert!(desktop.resolution_scale >= 2);
+
+    // A fast tablet still gets the phone preset
+    assert_eq!(Calibration::from_throughput(40.0, true).quality, Quality::Low);
+
+    for throughput in [0.1, 1.0, 10.0, 100.0, 10000.0] {
+        let c = Calibration::from_throughput(throughput, false);
+        assert!([1, 2, 4].contains(&c.resolution_scale));
+        assert!((1..=3).contains(&c.substeps));
+    }
+}
+
+#[test]
+fn test_measure_counts_cell_steps() {
+    // Each clock read advances 10 ms, so steps until 0.1 s is passed
+    let mut t = 0.0;
+    let throughput = calibration::measure(0.1, || {
+        t += 0.01;
+        t
+    });
+    let steps = 10.0;
+    let expected = (BENCH_SIZE * BENCH_SIZE) as f32 * steps / 0.1 / 1e6;
+    assert!((throughput - expected).abs() < expected * 0.01, "{} vs {}", throughput, expected);
+
+    // A clock that never moves still finishes
+    let throughput = calibration::measure(1.0, || 0.0);
+    assert!(throughput.is_finite() && throughput > 0.0);
+}
+
+#[test]
+fn test_calibration_round_trips_through_storage() {
+    let calibration = Calibration::from_throughput(25.0, false);
+    let json = serde_json::to_string(&calibration).unwrap();
+    let restored: Calibration = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, calibration);
+}
@@ -0,0 +1,16 @@
+#![cfg(feature = "chat")]
+
+use itsliquid::chat::parse_privmsg;
+
+#[test]
+fn test_parse_privmsg_extracts_commands() {
+    let line = ":viewer42!viewer42@viewer42.tmi.twitch.tv PRIVMSG #somechannel :!dye red 0.3 0.7\r";
+    assert_eq!(parse_privmsg(line), Some(("viewer42".to_string(), "!dye red 0.3 0.7")));
+}
+
+#[test]
+fn test_parse_privmsg_ignores_chatter_and_server_lines() {
+    assert_eq!(parse_privmsg(":a!a@a.tmi.twitch.tv PRIVMSG #c :hello everyone"), None);
+    assert_eq!(parse_privmsg(":tmi.twitch.tv 001 justinfan1 :Welcome, GLHF!"), None);
+    assert_eq!(parse_privmsg("PING :tmi.twitch.tv"), None);
+}
@@ -0,0 +1,99 @@
+use itsliquid::checkpoint::CHECKPOINT_VERSION;
+use itsliquid::{BlendMode, BoundaryMode, Checkpoint, Emission, InteractiveFluid, PersistentElement, PersistentElementType};
+
+fn jet() -> Vec<PersistentElement> {
+    vec![
+        PersistentElement {
+            element_type: PersistentElementType::DyeSource {
+                color: (1.0, 0.4, 0.1),
+                intensity: 1.0,
+                blend: BlendMode::Add,
+                emission: Emission::default(),
+            },
+            x: 6.0,
+            y: 16.0,
+            radius: 3.0,
+            audio: None,
+        },
+        PersistentElement {
+            element_type: PersistentElementType::ForceSource { direction: (24.0, 0.0), intensity: 1.0 },
+            x: 6.0,
+            y: 16.0,
+            radius: 3.0,
+            audio: None,
+        },
+    ]
+}
+
+fn advance(fluid: &mut InteractiveFluid, elements: &[PersistentElement], steps: usize) {
+    for _ in 0..steps {
+        for elem in elements {
+            elem.apply(fluid, None);
+        }
+        fluid.step();
+    }
+}
+
+fn path(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join("itsliquid_checkpoint_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn checkpoint_round_trips_through_a_file() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    fluid.boundary = BoundaryMode::FreeSlip;
+    fluid.viscosity = 0.002;
+    advance(&mut fluid, &jet(), 5);
+
+    let checkpoint = Checkpoint::capture(5, &fluid, &jet());
+    let file = path("round_trip.json");
+    checkpoint.save(&file).unwrap();
+    assert_eq!(Checkpoint::load(&file).unwrap(), checkpoint);
+    assert!(!file.with_extension("partial").exists());
+}
+
+#[test]
+fn resumed_run_matches_an_uninterrupted_one() {
+    let elements = jet();
+    let mut straight = InteractiveFluid::new(32, 32);
+    advance(&mut straight, &elements, 20);
+
+    let mut first = InteractiveFluid::new(32, 32);
+    advance(&mut first, &elements, 10);
+    let file = path("resume.json");
+    Checkpoint::capture(10, &first, &elements).save(&file).unwrap();
+    drop(first);
+
+    let checkpoint = Checkpoint::load(&file).unwrap();
+    assert_eq!(checkpoint.frame, 10);
+    let mut resumed = InteractiveFluid::new(32, 32);
+    let elements = checkpoint.restore(&mut resumed).unwrap();
+    advance(&mut resumed, &elements, 10);
+
+    assert_eq!(resumed.velocity_x, straight.velocity_x);
+    assert_eq!(resumed.velocity_y, straight.velocity_y);
+    assert_eq!(resumed.dye_r, straight.dye_r);
+    assert_eq!(resumed.temperature, straight.temperature);
+}
+
+#[test]
+fn restore_rejects_another_grid() {
+    let checkpoint = Checkpoint::capture(0, &InteractiveFluid::new(32, 32), &[]);
+    let mut fluid = InteractiveFluid::new(16, 16);
+    fluid.viscosity = 0.5;
+    assert!(checkpoint.restore(&mut fluid).is_err());
+    assert_eq!(fluid.viscosity, 0.5);
+}
+
+#[test]
+fn validate_rejects_newer_or_truncated_checkpoints() {
+    let mut checkpoint = Checkpoint::capture(0, &InteractiveFluid::new(8, 8), &[]);
+    assert!(checkpoint.validate().is_ok());
+    checkpoint.version = CHECKPOINT_VERSION + 1;
+    assert!(checkpoint.validate().is_err());
+    checkpoint.version = CHECKPOINT_VERSION;
+    checkpoint.fields.velocity[1].pop();
+    assert!(checkpoint.validate().is_err());
+}
@@ -0,0 +1,85 @@
+use itsliquid::{Chemistry, InteractiveFluid, SceneParams};
+
+#[test]
+fn test_combining_conserves_the_dye() {
+    let (w, h) = (8, 8);
+    let mut red = vec![1.0; w * h];
+    let mut green = vec![0.0; w * h];
+    let mut blue = vec![0.0; w * h];
+    for b in &mut blue[3 * w..5 * w] {
+        *b = 0.5;
+    }
+    Chemistry::combine().react([&mut red[..], &mut green[..], &mut blue[..]], w, h, 0.1);
+
+    let idx = 4 * w + 4;
+    assert!((green[idx] - 0.2).abs() < 1e-6);
+    assert!((red[idx] - 0.9).abs() < 1e-6 && (blue[idx] - 0.4).abs() < 1e-6);
+    // Only where both colors meet, and never into the edge ring
+    assert_eq!(green[w + 4], 0.0);
+    assert_eq!(green[4 * w], 0.0);
+    let total = |c: &[f32]| c.iter().sum::<f32>();
+    assert!((total(&red) + total(&green) + total(&blue) - (64.0 + 8.0)).abs() < 1e-4);
+
+    // A reaction can't use up more than there is
+    let fast = Chemistry::Combine { a: 0, b: 2, product: 1, rate: 1000.0 };
+    fast.react([&mut red[..], &mut green[..], &mut blue[..]], w, h, 1.0);
+    assert_eq!(blue[idx], 0.0);
+    assert!(red[idx] >= 0.0);
+}
+
+#[test]
+fn test_gray_scott_seed_grows() {
+    let (w, h) = (40, 40);
+    let mut activator = vec![0.0; w * h];
+    let mut substrate = vec![1.0; w * h];
+    let mut blue = vec![0.0; w * h];
+    for y in 15..25 {
+        for x in 15..25 {
+            substrate[y * w + x] = 0.5;
+            activator[y * w + x] = 0.25;
+        }
+    }
+    let grown = |v: &[f32]| v.iter().filter(|c| **c > 0.1).count();
+    let chemistry = Chemistry::gray_scott();
+    for _ in 0..50 {
+        chemistry.react([&mut activator[..], &mut substrate[..], &mut blue[..]], w, h, 1.0);
+    }
+    assert!(grown(&activator) > 200, "{}", grown(&activator));
+    assert!(activator.iter().chain(&substrate).all(|c| c.is_finite() && *c >= 0.0 && *c <= 1.0));
+    assert!(blue.iter().all(|c| *c == 0.0));
+
+    // Without an activator the substrate is only fed toward 1
+    let mut empty = vec![0.0; w * h];
+    let mut substrate = vec![0.0; w * h];
+    chemistry.react([&mut empty[..], &mut substrate[..], &mut blue[..]], w, h, 0.1);
+    assert!(empty.iter().all(|c| *c == 0.0));
+    assert!(substrate[20 * w + 20] > 0.0 && substrate[20 * w + 20] < 1.0);
+}
+
+#[test]
+fn test_fluid_reacts_its_dye() {
+    let run = |chemistry: Option<Chemistry>| {
+        let mut fluid = InteractiveFluid::new(32, 32);
+        fluid.chemistry = chemistry;
+        for y in 12..20 {
+            for x in 12..20 {
+                fluid.dye_r[y * 32 + x] = 1.0;
+                fluid.dye_b[y * 32 + x] = 1.0;
+            }
+        }
+        fluid.step();
+        fluid.dye_g.iter().sum::<f32>()
+    };
+    assert_eq!(run(None), 0.0);
+    assert!(run(Some(Chemistry::combine())) > 0.0);
+}
+
+#[test]
+fn test_scenes_keep_the_chemistry() {
+    let mut fluid = InteractiveFluid::new(8, 8);
+    fluid.chemistry = Some(Chemistry::GrayScott { substrate: 2, activator: 1, feed: 0.03, kill: 0.062, speed: 5.0 });
+    let json = serde_json::to_string(&SceneParams::capture(&fluid)).unwrap();
+    let mut restored = InteractiveFluid::new(8, 8);
+    serde_json::from_str::<SceneParams>(&json).unwrap().apply(&mut restored);
+    assert_eq!(restored.chemistry, fluid.chemistry);
+}
@@ -0,0 +1,56 @@
+use itsliquid::{Choreography, InteractiveFluid, Routine};
+
+fn total_dye(sim: &InteractiveFluid) -> f32 {
+    sim.dye_r.iter().chain(&sim.dye_g).chain(&sim.dye_b).sum()
+}
+
+#[test]
+fn test_every_routine_adds_dye_and_motion() {
+    for routine in Routine::ALL {
+        let mut choreography = Choreography::new(1);
+        choreography.routines = vec![routine];
+        choreography.reset();
+        assert_eq!(choreography.current(), routine);
+
+        let mut sim = InteractiveFluid::new(64, 64);
+        for _ in 0..60 {
+            choreography.update(&mut sim, 1.0 / 30.0);
+            sim.step();
+        }
+        assert!(total_dye(&sim) > 0.0, "{} added no dye", routine.name());
+        assert!(sim.velocity_x.iter().any(|v| v.abs() > 0.0), "{} added no motion", routine.name());
+        assert!(sim.velocity_x.iter().chain(&sim.dye_r).all(|v| v.is_finite()));
+    }
+}
+
+#[test]
+fn test_routines_chain_after_their_duration() {
+    let mut choreography = Choreography::new(7);
+    let mut sim = InteractiveFluid::new(32, 32);
+    let first = choreography.current();
+
+    let mut t = 0.0;
+    while t < first.duration() - 0.5 {
+        choreography.update(&mut sim, 0.25);
+        t += 0.25;
+    }
+    assert!(choreography.elapsed() > 0.0);
+
+    choreography.update(&mut sim, 1.0);
+    // A new routine started from the beginning
+    assert!(choreography.elapsed() <= 1.0);
+}
+
+#[test]
+fn test_same_seed_plays_the_same_show() {
+    let run = |seed| {
+        let mut choreography = Choreography::new(seed);
+        let mut sim = InteractiveFluid::new(32, 32);
+        for _ in 0..90 {
+            choreography.update(&mut sim, 1.0 / 30.0);
+            sim.step();
+        }
+        sim.dye_r
+    };
+    assert_eq!(run(3), run(3));
+}
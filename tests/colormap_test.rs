@@ -0,0 +1,44 @@
+use itsliquid::render::dye_intensity;
+use itsliquid::{Colormap, InteractiveFluid, RenderMode, Renderer};
+
+#[test]
+fn test_builtin_colormap_endpoints() {
+    assert_eq!(Colormap::BlueWhite.map(0.0), [0, 0, 255]);
+    assert_eq!(Colormap::BlueWhite.map(1.0), [255, 255, 255]);
+    assert_eq!(Colormap::Grayscale.map(0.5), [127, 127, 127]);
+    assert_eq!(Colormap::Viridis.map(0.0), [68, 1, 84]);
+    assert_eq!(Colormap::Viridis.map(1.0), [253, 231, 37]);
+    assert_eq!(Colormap::Magma.map(0.0), [0, 0, 4]);
+    assert_eq!(Colormap::Magma.map(1.0), [252, 253, 191]);
+}
+
+#[test]
+fn test_colormap_clamps_out_of_range_values() {
+    for colormap in Colormap::builtin() {
+        assert_eq!(colormap.map(-3.0), colormap.map(0.0), "{}", colormap.name());
+        assert_eq!(colormap.map(7.0), colormap.map(1.0), "{}", colormap.name());
+        assert_eq!(colormap.map(f32::NAN), colormap.map(0.0), "{}", colormap.name());
+    }
+}
+
+#[test]
+fn test_custom_lut_interpolates() {
+    let lut = Colormap::Custom(vec![[0, 0, 0], [200, 100, 50]]);
+    assert_eq!(lut.map(0.5), [100, 50, 25]);
+    assert_eq!(Colormap::Custom(vec![[9, 8, 7]]).map(0.3), [9, 8, 7]);
+    assert_eq!(Colormap::Custom(Vec::new()).map(0.3), [0, 0, 0]);
+}
+
+#[test]
+fn test_renderer_applies_colormap_to_dye_intensity() {
+    let mut sim = InteractiveFluid::new(8, 8);
+    sim.dye_r[8 + 1] = 0.5;
+    sim.dye_g[8 + 1] = 0.5;
+
+    let renderer = Renderer::new(8, 8).with_colormap(Colormap::Grayscale);
+    let img = renderer.render_interactive(&sim, RenderMode::DyeIntensity);
+    // Total 1.0 maps to 0.5
+    assert!((dye_intensity(0.5, 0.5, 0.0) - 0.5).abs() < 1e-6);
+    assert_eq!(img.get_pixel(1, 1).0, [127, 127, 127]);
+    assert_eq!(img.get_pixel(4, 4).0, [0, 0, 0]);
+}
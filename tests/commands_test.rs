@@ -0,0 +1,53 @@
+use itsliquid::commands::parse_color;
+use itsliquid::{InteractiveFluid, SimCommand};
+
+#[test]
+fn test_parse_commands() {
+    assert_eq!(
+        SimCommand::parse("!dye red 0.3 0.7"),
+        Ok(SimCommand::Dye { x: 0.3, y: 0.7, color: (1.0, 0.0, 0.0) })
+    );
+    assert_eq!(
+        SimCommand::parse("!FORCE 0.5 0.5 1 -0.5"),
+        Ok(SimCommand::Force { x: 0.5, y: 0.5, dx: 1.0, dy: -0.5 })
+    );
+    assert_eq!(
+        SimCommand::parse("!heat 0.5 0.9"),
+        Ok(SimCommand::Heat { x: 0.5, y: 0.9, amount: 2.0 })
+    );
+    assert_eq!(SimCommand::parse("  !clear  "), Ok(SimCommand::Clear));
+}
+
+#[test]
+fn test_parse_rejects_bad_input() {
+    assert!(SimCommand::parse("").is_err());
+    assert!(SimCommand::parse("hello chat").is_err());
+    assert!(SimCommand::parse("!dye chartreuse 0.5 0.5").is_err());
+    assert!(SimCommand::parse("!dye red 0.5").is_err());
+    assert!(SimCommand::parse("!force 0.5 0.5 NaN 0").is_err());
+    assert!(SimCommand::parse("!heat 0.5 inf").is_err());
+}
+
+#[test]
+fn test_parse_hex_colors() {
+    assert_eq!(parse_color("#ff8000"), Some((1.0, 128.0 / 255.0, 0.0)));
+    assert_eq!(parse_color("#FFF"), None);
+    assert_eq!(parse_color("#gg0000"), None);
+    assert_eq!(parse_color("Cyan"), Some((0.0, 1.0, 1.0)));
+}
+
+#[test]
+fn test_commands_apply_at_normalized_positions() {
+    let mut sim = InteractiveFluid::new(41, 41);
+
+    SimCommand::parse("!dye blue 0.25 0.5").unwrap().apply(&mut sim);
+    assert!(sim.dye_b[20 * 41 + 10] > 0.0);
+    assert_eq!(sim.dye_r.iter().sum::<f32>(), 0.0);
+
+    // Out-of-range positions are pulled onto the interior
+    SimCommand::parse("!force 2 -1 1 0").unwrap().apply(&mut sim);
+    assert!(sim.velocity_x[41 + 39] > 0.0);
+
+    SimCommand::Clear.apply(&mut sim);
+    assert!(sim.dye_b.iter().chain(&sim.velocity_x).all(|&v| v == 0.0));
+}
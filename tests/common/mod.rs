@@ -0,0 +1,11 @@
+// Setup shared by the integration tests
+
+use itsliquid::InteractiveFluid;
+
+// A square grid with the same velocity in every cell
+pub fn uniform_flow(size: usize, vx: f32, vy: f32) -> InteractiveFluid {
+    let mut sim = InteractiveFluid::new(size, size);
+    sim.velocity_x.fill(vx);
+    sim.velocity_y.fill(vy);
+    sim
+}
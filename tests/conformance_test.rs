@@ -0,0 +1,64 @@
+use itsliquid::conformance::{self, Observe, Report};
+use itsliquid::{FluidSimulation, InteractiveFluid};
+
+fn assert_conforms(backend: &str, reports: &[Report]) {
+    let failures: Vec<String> = reports.iter().flat_map(Report::failures).collect();
+    assert!(failures.is_empty(), "{} is not conformant:\n{}", backend, failures.join("\n"));
+}
+
+// Same backend, same scenario, bitwise identical end state
+fn assert_reproducible<S: FluidSimulation + Observe>(mut make: impl FnMut(usize, usize) -> S) {
+    for scenario in conformance::scenarios() {
+        let a = conformance::run(&mut make(scenario.width, scenario.height), &scenario).unwrap();
+        let b = conformance::run(&mut make(scenario.width, scenario.height), &scenario).unwrap();
+        assert_eq!(a.fields, b.fields, "{} differs between runs", scenario.name);
+    }
+}
+
+#[test]
+fn test_cpu_solver_conforms() {
+    let reports = conformance::run_all(|w, h| Ok(InteractiveFluid::new(w, h))).unwrap();
+    assert_eq!(reports.len(), conformance::scenarios().len());
+    assert_conforms("InteractiveFluid", &reports);
+}
+
+#[test]
+fn test_cpu_solver_is_reproducible() {
+    assert_reproducible(InteractiveFluid::new);
+}
+
+#[test]
+fn test_scenarios_are_well_formed() {
+    for scenario in conformance::scenarios() {
+        assert!(scenario.inputs_end() < scenario.steps, "{} has no settling steps", scenario.name);
+        assert!(!scenario.expectations.is_empty(), "{} checks nothing", scenario.name);
+        for e in &scenario.expectations {
+            assert!(e.min <= e.max, "{} has an empty range for {:?}", scenario.name, e.metric);
+        }
+    }
+
+    // Wrong grid size is an error rather than a silently different scenario
+    let scenario = &conformance::scenarios()[0];
+    assert!(conformance::run(&mut InteractiveFluid::new(8, 8), scenario).is_err());
+}
+
+#[cfg(feature = "f16-dye")]
+#[test]
+fn test_half_dye_solver_conforms() {
+    let reports = conformance::run_all(|w, h| Ok(itsliquid::HalfDyeFluid::new(w, h))).unwrap();
+    assert_conforms("HalfDyeFluid", &reports);
+}
+
+#[cfg(feature = "gpu")]
+#[test]
+fn test_gpu_solver_conforms() {
+    use itsliquid::gpu_functional::FunctionalGPUFluid;
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    if let Err(e) = runtime.block_on(FunctionalGPUFluid::new(8, 8)) {
+        eprintln!("Skipping GPU conformance, no usable adapter: {}", e);
+        return;
+    }
+    let reports = conformance::run_all(|w, h| runtime.block_on(FunctionalGPUFluid::new(w as u32, h as u32))).unwrap();
+    assert_conforms("FunctionalGPUFluid", &reports);
+}
@@ -0,0 +1,64 @@
+use itsliquid::{Container, InteractiveFluid, Mask, Scene};
+
+#[test]
+fn test_petri_dish_is_solid_outside_the_circle() {
+    let mask = Container::petri_dish().mask(40, 20);
+    assert!(!mask.get(20, 10));
+    assert!(mask.get(0, 0) && mask.get(39, 19));
+    // Round on a wide grid: the circle spans the height, not the width
+    assert!(mask.get(5, 10));
+    assert!(!mask.get(12, 10));
+}
+
+#[test]
+fn test_ring_and_letterbox() {
+    let ring = Container::ring().mask(40, 40);
+    assert!(ring.get(20, 20));
+    assert!(!ring.get(20, 6));
+    assert!(ring.get(0, 0));
+
+    let letterbox = Container::letterbox().mask(40, 40);
+    assert!(letterbox.get(20, 2) && letterbox.get(20, 37));
+    assert!(!letterbox.get(0, 20) && !letterbox.get(39, 20));
+}
+
+#[test]
+fn test_rounded_corners_and_polygons() {
+    let rounded = Container::RoundedRect { min: (0.0, 0.0), max: (1.0, 1.0), corner: 0.25 };
+    // Straight edges reach the grid's edge, the corners are cut
+    assert!(rounded.contains(0.5, 20.0, 40, 40));
+    assert!(rounded.contains(20.0, 0.5, 40, 40));
+    assert!(!rounded.contains(2.0, 2.0, 40, 40));
+    assert!(rounded.contains(5.0, 5.0, 40, 40));
+
+    let triangle = Container::Polygon { points: vec![(0.5, 0.0), (1.0, 1.0), (0.0, 1.0)] };
+    assert!(triangle.contains(20.0, 30.0, 40, 40));
+    assert!(!triangle.contains(2.0, 5.0, 40, 40));
+    let hexagon = Container::regular_polygon(6, 0.45).mask(40, 40);
+    assert!(!hexagon.get(20, 20));
+    assert!(hexagon.get(1, 1));
+}
+
+#[test]
+fn test_scenes_carry_their_container() {
+    let mut scene = Scene::capture(&InteractiveFluid::new(32, 32), &[], false);
+    assert!(scene.simulation().obstacles.is_none());
+    scene.container = Some(Container::ring());
+    let loaded = Scene::from_json(&scene.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.container, scene.container);
+    assert_eq!(loaded.simulation().obstacles, Some(Container::ring().mask(32, 32)));
+
+    // Share links keep it too, even with nothing else to share
+    let linked = Scene::from_link(&scene.to_link()).unwrap();
+    assert_eq!(linked.container, scene.container);
+}
+
+#[test]
+fn test_union_adds_cells() {
+    let mut mask = Mask::new(4, 1);
+    mask.cells[0] = true;
+    let mut other = Mask::new(4, 1);
+    other.cells[2] = true;
+    mask.union(&other);
+    assert_eq!(mask.cells, vec![true, false, true, false]);
+}
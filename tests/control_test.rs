@@ -0,0 +1,75 @@
+#![cfg(feature = "control")]
+
+use itsliquid::control::{Control, ControlTarget, OscMessage, parse_midi_cc, parse_osc};
+
+// An OSC string: null-terminated and padded to four bytes
+fn osc_string(text: &str) -> Vec<u8> {
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.push(0);
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(0);
+    }
+    bytes
+}
+
+fn osc_message(address: &str, floats: &[f32]) -> Vec<u8> {
+    let mut packet = osc_string(address);
+    packet.extend(osc_string(&format!(",{}", "f".repeat(floats.len()))));
+    for value in floats {
+        packet.extend(value.to_be_bytes());
+    }
+    packet
+}
+
+#[test]
+fn test_parse_osc_message_and_bundle() {
+    let packet = osc_message("/itsliquid/force/intensity", &[0.5]);
+    assert_eq!(parse_osc(&packet), vec![OscMessage { address: "/itsliquid/force/intensity".into(), args: vec![0.5] }]);
+
+    // Ints, booleans and strings mixed in
+    let mut packet = osc_string("/itsliquid/paused");
+    packet.extend(osc_string(",isT"));
+    packet.extend(7i32.to_be_bytes());
+    packet.extend(osc_string("ignored"));
+    assert_eq!(parse_osc(&packet)[0].args, vec![7.0, 1.0]);
+
+    let mut bundle = b"#bundle\0".to_vec();
+    bundle.extend([0u8; 8]);
+    for element in [osc_message("/itsliquid/clear", &[]), osc_message("/itsliquid/dye/color", &[1.0, 0.0, 0.5])] {
+        bundle.extend((element.len() as u32).to_be_bytes());
+        bundle.extend(element);
+    }
+    let messages = parse_osc(&bundle);
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[1].args, vec![1.0, 0.0, 0.5]);
+
+    // Garbage and truncated packets don't panic
+    assert!(parse_osc(b"not osc").is_empty());
+    assert!(parse_osc(&packet[..packet.len() - 3]).len() <= 1);
+}
+
+#[test]
+fn test_targets_scale_normalized_values() {
+    let target = ControlTarget::from_osc_address("/itsliquid/attractor/strength").unwrap();
+    assert_eq!(target.control(&[1.0]), Some(Control::AttractorStrength(100.0)));
+    assert_eq!(ControlTarget::ForceIntensity.control(&[0.0]), Some(Control::ForceIntensity(0.01)));
+    assert_eq!(ControlTarget::DyeColor.control(&[0.0]), Some(Control::DyeColor((1.0, 0.0, 0.0))));
+    assert_eq!(ControlTarget::DyeColor.control(&[0.2, 2.0, -1.0]), Some(Control::DyeColor((0.2, 1.0, 0.0))));
+    assert_eq!(ControlTarget::Paused.control(&[1.0]), Some(Control::Paused(true)));
+    // Button releases don't clear again
+    assert_eq!(ControlTarget::Clear.control(&[0.0]), None);
+    assert_eq!(ControlTarget::Clear.control(&[]), Some(Control::Clear));
+    assert_eq!(ControlTarget::ForceIntensity.control(&[]), None);
+    assert_eq!(ControlTarget::from_osc_address("/other"), None);
+}
+
+#[test]
+fn test_parse_midi_cc() {
+    // CC 21 at full on channel 3
+    let (channel, cc, value) = parse_midi_cc(&[0xb2, 21, 127]).unwrap();
+    assert_eq!((channel, cc, value), (2, 21, 1.0));
+    assert_eq!(ControlTarget::from_cc(cc), Some(ControlTarget::ForceIntensity));
+    // Note-on is not a control change
+    assert_eq!(parse_midi_cc(&[0x90, 60, 100]), None);
+    assert_eq!(parse_midi_cc(&[0xb0, 1]), None);
+}
@@ -0,0 +1,69 @@
+use itsliquid::InteractiveFluid;
+
+// Large enough that parallel reductions span several chunks
+const SIZE: usize = 128;
+const STEPS: usize = 60;
+
+fn run_scenario() -> InteractiveFluid {
+    let mut sim = InteractiveFluid::new(SIZE, SIZE);
+    for step in 0..STEPS {
+        if step < 20 {
+            sim.add_dye(40, 64, (2.0, 0.5, 0.1));
+            sim.add_dye(88, 64, (0.1, 0.5, 2.0));
+            sim.add_force(40, 64, glam::Vec2::new(15.0, 3.0), 4.0);
+            sim.add_force(88, 64, glam::Vec2::new(-15.0, -3.0), 4.0);
+        }
+        sim.step();
+    }
+    sim
+}
+
+fn run_with_threads(threads: usize) -> InteractiveFluid {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .expect("failed to build thread pool")
+        .install(run_scenario)
+}
+
+fn assert_bitwise_equal(a: &InteractiveFluid, b: &InteractiveFluid, label: &str) {
+    let fields = [
+        ("velocity_x", &a.velocity_x, &b.velocity_x),
+        ("velocity_y", &a.velocity_y, &b.velocity_y),
+        ("dye_r", &a.dye_r, &b.dye_r),
+        ("dye_g", &a.dye_g, &b.dye_g),
+        ("dye_b", &a.dye_b, &b.dye_b),
+        ("pressure", &a.pressure, &b.pressure),
+    ];
+
+    for (name, x, y) in fields {
+        for (i, (p, q)) in x.iter().zip(y.iter()).enumerate() {
+            assert_eq!(
+                p.to_bits(),
+                q.to_bits(),
+                "{}: {}[{}] differs ({} vs {})",
+                label,
+                name,
+                i,
+                p,
+                q
+            );
+        }
+    }
+}
+
+#[test]
+fn test_single_and_multi_threaded_results_match() {
+    let reference = run_with_threads(1);
+    for threads in [2, 4, 8] {
+        let result = run_with_threads(threads);
+        assert_bitwise_equal(&reference, &result, &format!("{} threads", threads));
+    }
+}
+
+#[test]
+fn test_repeated_runs_match() {
+    let first = run_with_threads(4);
+    let second = run_with_threads(4);
+    assert_bitwise_equal(&first, &second, "repeat");
+}
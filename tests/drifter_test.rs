@@ -0,0 +1,89 @@
+mod common;
+
+use common::uniform_flow;
+use itsliquid::{DrifterSet, InteractiveFluid, Integrator, ParticleSystem};
+
+#[test]
+fn test_drifter_follows_uniform_flow() {
+    let sim = uniform_flow(40, 2.0, 0.0);
+    let mut drifters = DrifterSet::new();
+    drifters.add("probe", 10.0, 20.0, 0);
+
+    for frame in 1..=10 {
+        drifters.step(&sim, frame);
+    }
+
+    let drifter = &drifters.drifters[0];
+    assert_eq!(drifter.trajectory.len(), 11);
+
+    // dt = 0.1, v = 2.0 -> 0.2 cells per frame
+    let displacement = drifter.displacement();
+    assert!((displacement.x - 2.0).abs() < 1e-4, "x displacement was {}", displacement.x);
+    assert!(displacement.y.abs() < 1e-6);
+    assert!((drifter.path_length() - 2.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_drifters_stay_inside_domain() {
+    let mut sim = InteractiveFluid::new(20, 20);
+    sim.add_force(10, 10, glam::Vec2::new(500.0, 500.0), 5.0);
+
+    let mut drifters = DrifterSet::new();
+    drifters.add_grid((5.0, 5.0), (15.0, 15.0), 3, 3, 0);
+    assert_eq!(drifters.len(), 9);
+
+    for frame in 1..=50 {
+        sim.step();
+        drifters.step(&sim, frame);
+    }
+
+    for drifter in &drifters.drifters {
+        for point in &drifter.trajectory {
+            assert!(point.x >= 0.0 && point.x <= 19.0);
+            assert!(point.y >= 0.0 && point.y <= 19.0);
+        }
+    }
+}
+
+#[test]
+fn test_drifter_csv_export() {
+    let sim = InteractiveFluid::new(20, 20);
+    let mut drifters = DrifterSet::new();
+    drifters.add("a", 5.0, 5.0, 0);
+    drifters.step(&sim, 1);
+
+    let dir = std::env::temp_dir().join("itsliquid_drifter_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("drifters.csv");
+    drifters.export_csv(&path).unwrap();
+
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "name,frame,x,y");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[2].starts_with("a,1,"));
+}
+
+#[test]
+fn test_drifters_and_particles_move_alike() {
+    let mut sim = InteractiveFluid::new(32, 32);
+    sim.add_force(16, 16, glam::Vec2::new(200.0, -100.0), 4.0);
+    sim.step();
+
+    let mut drifters = DrifterSet::new();
+    drifters.integrator = Integrator::Rk4;
+    drifters.add("probe", 14.0, 17.0, 0);
+    let mut particles = ParticleSystem::new(1);
+    particles.integrator = Integrator::Rk4;
+    particles.seed(14.0, 17.0, (1.0, 1.0, 1.0));
+
+    for frame in 1..=20 {
+        drifters.step(&sim, frame);
+        particles.step(&sim);
+    }
+
+    let drifter = &drifters.drifters[0];
+    let particle = particles.particles[0];
+    assert!(drifter.displacement().length() > 0.1);
+    assert_eq!((drifter.x, drifter.y), (particle.x, particle.y));
+}
@@ -0,0 +1,33 @@
+use itsliquid::{ImageExporter, InteractiveFluid};
+
+#[test]
+fn test_dye_exr_round_trip_preserves_hdr_values() {
+    let mut sim = InteractiveFluid::new(16, 12);
+    let idx = 5 * sim.width + 7;
+    sim.dye_r[idx] = 12.5;
+    sim.dye_g[idx] = 0.25;
+    sim.dye_b[idx] = 3.0;
+
+    let path = std::env::temp_dir().join("itsliquid_dye_round_trip.exr");
+    let exporter = ImageExporter::new(16, 12);
+    exporter.export_dye_float(&sim, &path).unwrap();
+
+    let img = image::open(&path).unwrap().to_rgb32f();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(img.dimensions(), (16, 12));
+    let pixel = img.get_pixel(7, 5);
+    // Values above 1.0 must survive unclamped
+    assert!((pixel[0] - 12.5).abs() < 1e-4);
+    assert!((pixel[1] - 0.25).abs() < 1e-4);
+    assert!((pixel[2] - 3.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_float_export_rejects_8bit_formats() {
+    let sim = InteractiveFluid::new(8, 8);
+    let exporter = ImageExporter::new(8, 8);
+    let path = std::env::temp_dir().join("itsliquid_dye.png");
+
+    assert!(exporter.export_dye_float(&sim, &path).is_err());
+}
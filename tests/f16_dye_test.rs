@@ -0,0 +1,95 @@
+#![cfg(feature = "f16-dye")]
+
+use itsliquid::{HalfDyeFluid, InteractiveFluid};
+
+const SIZE: usize = 64;
+
+fn run_pair(steps: usize) -> (InteractiveFluid, HalfDyeFluid) {
+    run_pair_with(steps, |_| {})
+}
+
+// `run_pair` with `setup` applied to both solvers first
+fn run_pair_with(steps: usize, setup: impl Fn(&mut InteractiveFluid)) -> (InteractiveFluid, HalfDyeFluid) {
+    let mut reference = InteractiveFluid::new(SIZE, SIZE);
+    let mut half = HalfDyeFluid::new(SIZE, SIZE);
+    setup(&mut reference);
+    setup(&mut half.fluid);
+
+    reference.add_dye(24, 32, (6.0, 3.0, 1.0));
+    half.add_dye(24, 32, (6.0, 3.0, 1.0));
+    reference.add_force(24, 32, glam::Vec2::new(15.0, 4.0), 3.0);
+    half.add_force(24, 32, glam::Vec2::new(15.0, 4.0), 3.0);
+
+    for _ in 0..steps {
+        reference.step();
+        half.step();
+    }
+    (reference, half)
+}
+
+#[test]
+fn test_velocity_matches_f32_solver() {
+    // Dye never feeds back into velocity, so the f32 halves must agree exactly
+    let (reference, half) = run_pair(40);
+    assert_eq!(reference.velocity_x, half.fluid.velocity_x);
+    assert_eq!(reference.velocity_y, half.fluid.velocity_y);
+}
+
+#[test]
+fn test_dye_stays_close_to_f32_solver() {
+    let (reference, half) = run_pair(100);
+    let widened = half.dye_to_f32();
+    let channels = [&reference.dye_r, &reference.dye_g, &reference.dye_b];
+
+    for c in 0..3 {
+        let mass_ref: f32 = channels[c].iter().sum();
+        let mass_half: f32 = widened[c].iter().sum();
+        assert!(
+            (mass_half - mass_ref).abs() / mass_ref < 0.01,
+            "channel {} mass {} vs {}",
+            c,
+            mass_half,
+            mass_ref
+        );
+
+        let peak = channels[c].iter().cloned().fold(0.0f32, f32::max);
+        let max_abs = channels[c]
+            .iter()
+            .zip(&widened[c])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0f32, f32::max);
+        assert!(max_abs < peak * 0.02, "channel {} max error {} (peak {})", c, max_abs, peak);
+    }
+}
+
+#[test]
+fn test_dye_storage_is_halved() {
+    let half = HalfDyeFluid::new(SIZE, SIZE);
+    assert_eq!(half.dye_bytes(), 6 * SIZE * SIZE * 2);
+    assert!(half.fluid.dye_r.is_empty());
+}
+
+// Largest velocity difference from the f32 solver, against its fastest cell
+fn velocity_error(reference: &InteractiveFluid, half: &HalfDyeFluid) -> (f32, f32) {
+    let pairs = reference.velocity_x.iter().zip(&half.fluid.velocity_x);
+    let pairs = pairs.chain(reference.velocity_y.iter().zip(&half.fluid.velocity_y));
+    let error = pairs.map(|(a, b)| (a - b).abs()).fold(0.0f32, f32::max);
+    let peak = reference.velocity_x.iter().chain(&reference.velocity_y).fold(0.0f32, |m, v| m.max(v.abs()));
+    (error, peak)
+}
+
+#[test]
+fn test_dye_weight_reads_f16_dye() {
+    let (reference, half) = run_pair_with(20, |fluid| fluid.dye_density = [2.0, 1.0, 0.0]);
+    let (error, peak) = velocity_error(&reference, &half);
+    assert!(peak > 0.0);
+    assert!(error < peak * 0.02, "velocity error {} (peak {})", error, peak);
+}
+
+#[test]
+fn test_surface_tension_reads_f16_dye() {
+    let (reference, half) = run_pair_with(20, |fluid| fluid.surface_tension = 5.0);
+    let (error, peak) = velocity_error(&reference, &half);
+    assert!(peak > 0.0);
+    assert!(error < peak * 0.02, "velocity error {} (peak {})", error, peak);
+}
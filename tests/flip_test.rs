@@ -0,0 +1,87 @@
+use glam::Vec2;
+use itsliquid::export::FluidData;
+use itsliquid::fluid_flip::Cell;
+use itsliquid::{ConfigurableSimulation, FlipFluid, FluidSimulation, Param};
+
+fn in_tank(fluid: &FlipFluid) -> bool {
+    fluid.particles.iter().all(|p| {
+        p.position.is_finite()
+            && p.position.x > 1.0
+            && p.position.y > 1.0
+            && p.position.x < fluid.width as f32 - 1.0
+            && p.position.y < fluid.height as f32 - 1.0
+    })
+}
+
+#[test]
+fn test_still_water_stays_put() {
+    let mut fluid = FlipFluid::new(32, 32);
+    fluid.fill_below(0.4);
+    let count = fluid.particles.len();
+    let level = fluid.surface_heights()[16];
+    for _ in 0..100 {
+        fluid.step();
+    }
+    assert_eq!(fluid.particles.len(), count);
+    assert!(in_tank(&fluid));
+    let heights = fluid.surface_heights();
+    assert!(heights[2..30].iter().all(|&y| y.abs_diff(level) <= 1), "{:?} from {}", heights, level);
+    let top_speed = fluid.particles.iter().map(|p| p.velocity.length()).fold(0.0, f32::max);
+    assert!(top_speed < 1.5, "still water moving at {}", top_speed);
+}
+
+#[test]
+fn test_dam_break_reaches_the_far_wall() {
+    let mut fluid = FlipFluid::new(48, 32);
+    fluid.add_liquid_box((1, 8), (14, 31));
+    assert_eq!(fluid.cell(40, 29), Cell::Air);
+    for _ in 0..150 {
+        fluid.step();
+    }
+    assert_eq!(fluid.cell(40, 29), Cell::Liquid, "heights {:?}", fluid.surface_heights());
+    // The column collapsed
+    assert!(fluid.surface_heights()[5] > 12);
+    assert!(in_tank(&fluid));
+}
+
+#[test]
+fn test_flip_keeps_more_energy_than_pic() {
+    let swirl = |ratio: f32| {
+        let mut fluid = FlipFluid::new(32, 32);
+        fluid.gravity = 0.0;
+        fluid.flip_ratio = ratio;
+        fluid.add_liquid_box((1, 1), (31, 31));
+        let centre = Vec2::splat(16.0);
+        for particle in &mut fluid.particles {
+            let r = particle.position - centre;
+            particle.velocity = Vec2::new(-r.y, r.x) * 0.5;
+        }
+        let start = fluid.kinetic_energy();
+        for _ in 0..30 {
+            fluid.step();
+        }
+        fluid.kinetic_energy() / start
+    };
+    let (pic, flip) = (swirl(0.0), swirl(0.95));
+    assert!(flip > pic, "FLIP kept {} vs PIC {}", flip, pic);
+    assert!(flip <= 1.01, "FLIP gained energy: {}", flip);
+}
+
+#[test]
+fn test_flip_plugs_into_shared_traits() {
+    let mut fluid = FlipFluid::new(24, 24);
+    assert!(fluid.particles.is_empty());
+    // Dye pours liquid
+    fluid.add_dye(12, 6, (1.0, 0.0, 0.0));
+    assert_eq!(fluid.particles.len(), 4);
+    assert_eq!(fluid.density()[6 * 24 + 12], 1.0);
+    fluid.add_force(12, 6, Vec2::new(5.0, 0.0));
+    assert!(fluid.particles.iter().all(|p| p.velocity.x == 5.0));
+    FluidSimulation::step(&mut fluid);
+    assert!(fluid.density().iter().all(|&d| (0.0..=1.0).contains(&d)));
+
+    fluid.set_param(Param::Dt, 0.05).unwrap();
+    assert_eq!(fluid.dt, 0.05);
+    assert!(fluid.set_param(Param::Viscosity, 0.01).is_err());
+    assert_eq!(fluid.params().len(), 1);
+}
@@ -0,0 +1,82 @@
+use glam::Vec3;
+use itsliquid::fluid3d::Axis;
+use itsliquid::{Fluid3D, FluidSimulation3D};
+
+fn centroid(fluid: &Fluid3D) -> Vec3 {
+    let mut sum = Vec3::ZERO;
+    let mut total = 0.0;
+    for z in 0..fluid.depth {
+        for y in 0..fluid.height {
+            for x in 0..fluid.width {
+                let d = fluid.dye_r[fluid.index(x, y, z)];
+                sum += Vec3::new(x as f32, y as f32, z as f32) * d;
+                total += d;
+            }
+        }
+    }
+    sum * (1.0 / total)
+}
+
+#[test]
+fn test_dye_is_carried_and_conserved() {
+    let mut fluid = Fluid3D::new(24, 16, 16);
+    fluid.add_dye(6, 8, 8, (1.0, 0.0, 0.0));
+    let start = fluid.total_dye();
+    let start_x = centroid(&fluid).x;
+    for _ in 0..20 {
+        fluid.add_force(6, 8, 8, Vec3::new(10.0, 0.0, 0.0));
+        fluid.step();
+    }
+    assert!(centroid(&fluid).x > start_x + 2.0, "dye stayed at {:?}", centroid(&fluid));
+    let total = fluid.total_dye();
+    assert!((total[0] - start[0]).abs() < 1e-3 * start[0], "{} of {}", total[0], start[0]);
+    assert_eq!(total[1], 0.0);
+}
+
+#[test]
+fn test_projection_removes_divergence() {
+    let mut fluid = Fluid3D::new(16, 16, 16);
+    fluid.add_force(8, 8, 8, Vec3::new(0.0, 0.0, 10.0));
+    let before = fluid.max_divergence();
+    fluid.step();
+    let after = fluid.max_divergence();
+    assert!(after < 0.2 * before, "divergence {} -> {}", before, after);
+    assert!(fluid.velocity_z.iter().all(|v| v.is_finite()));
+}
+
+#[test]
+fn test_buoyant_dye_rises() {
+    let mut fluid = Fluid3D::new(16, 24, 16);
+    fluid.buoyancy = 5.0;
+    fluid.add_dye(8, 18, 8, (1.0, 1.0, 1.0));
+    let start_y = centroid(&fluid).y;
+    for _ in 0..20 {
+        fluid.step();
+    }
+    assert!(centroid(&fluid).y < start_y - 2.0, "dye at {:?}", centroid(&fluid));
+}
+
+#[test]
+fn test_previews_slice_and_raymarch() {
+    let mut fluid = Fluid3D::new(12, 10, 8);
+    assert_eq!(fluid.slice_image(Axis::Z, 4).dimensions(), (12, 10));
+    assert_eq!(fluid.slice_image(Axis::X, 4).dimensions(), (8, 10));
+    assert_eq!(fluid.slice_image(Axis::Y, 4).dimensions(), (12, 8));
+    assert!(fluid.volume_image(Axis::Z, 1.0).pixels().all(|p| p.0 == [0, 0, 0]));
+
+    // A red cell in front of a blue one along z
+    let (front, back) = (fluid.index(5, 5, 2), fluid.index(5, 5, 5));
+    fluid.dye_r[front] = 1.0;
+    fluid.dye_b[back] = 1.0;
+    assert_eq!(fluid.slice_image(Axis::Z, 2).get_pixel(5, 5).0, [255, 0, 0]);
+    assert_eq!(fluid.slice_image(Axis::X, 5).get_pixel(5, 5).0, [0, 0, 255]);
+
+    let dense = fluid.volume_image(Axis::Z, 5.0).get_pixel(5, 5).0;
+    assert!(dense[0] > 200 && dense[2] < 10, "{:?}", dense);
+    let thin = fluid.volume_image(Axis::Z, 0.5).get_pixel(5, 5).0;
+    assert!(thin[2] > 0 && thin[0] < dense[0], "{:?}", thin);
+    // From the side both are visible and nothing is in the way
+    let side = fluid.volume_image(Axis::X, 5.0);
+    assert!(side.get_pixel(2, 5).0[0] > 200 && side.get_pixel(5, 5).0[2] > 200);
+    assert_eq!(fluid.depth(), 8);
+}
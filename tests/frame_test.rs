@@ -0,0 +1,66 @@
+use itsliquid::frame::{icon_rgba, write_rgba};
+use itsliquid::{FrameBuffer, InteractiveFluid};
+
+#[test]
+fn test_frame_is_tone_mapped_rgba() {
+    let mut sim = InteractiveFluid::new(16, 16);
+    sim.dye_r[5 * 16 + 4] = 1.0;
+    sim.dye_g[5 * 16 + 4] = 3.0;
+
+    let mut frame = FrameBuffer::new(16, 16);
+    let bytes = frame.update(&sim);
+    assert_eq!(bytes.len(), 16 * 16 * 4);
+
+    let i = (5 * 16 + 4) * 4;
+    // Reinhard: 1 -> 0.5, 3 -> 0.75
+    assert_eq!(&bytes[i..i + 4], &[127, 191, 0, 255]);
+    assert_eq!(&bytes[0..4], &[0, 0, 0, 255]);
+}
+
+#[test]
+fn test_frame_buffer_is_reused_across_updates() {
+    let mut sim = InteractiveFluid::new(32, 32);
+    sim.add_dye(16, 16, (5.0, 1.0, 0.5));
+
+    let mut frame = FrameBuffer::new(64, 64);
+    let first = frame.update(&sim).as_ptr();
+    for _ in 0..5 {
+        sim.step();
+        assert_eq!(frame.update(&sim).as_ptr(), first);
+    }
+
+    // Shrinking keeps the allocation
+    frame.resize(32, 32);
+    assert_eq!(frame.update(&sim).as_ptr(), first);
+    assert_eq!(frame.as_bytes().len(), 32 * 32 * 4);
+}
+
+#[test]
+fn test_write_rgba_scales_and_checks_buffer_size() {
+    let mut sim = InteractiveFluid::new(8, 8);
+    sim.dye_b[2 * 8 + 3] = 1.0;
+
+    let mut out = vec![0u8; 16 * 16 * 4];
+    write_rgba(&sim, 16, 16, &mut out).unwrap();
+
+    // Grid cell (3, 2) covers output pixels (6..8, 4..6)
+    for (x, y) in [(6, 4), (7, 4), (6, 5), (7, 5)] {
+        assert_eq!(out[(y * 16 + x) * 4 + 2], 127);
+    }
+    assert_eq!(out[(4 * 16 + 8) * 4 + 2], 0);
+
+    let mut short = vec![0u8; 10];
+    assert!(write_rgba(&sim, 16, 16, &mut short).is_err());
+}
+
+#[test]
+fn test_icon_is_round_and_colored() {
+    let icon = icon_rgba(64);
+    assert_eq!(icon.len(), 64 * 64 * 4);
+    // Corners are transparent, the centre is opaque
+    assert_eq!(icon[3], 0);
+    let centre = (32 * 64 + 32) * 4;
+    assert_eq!(icon[centre + 3], 255);
+    // Some dye made it into the image
+    assert!(icon.chunks_exact(4).any(|p| p[3] == 255 && p[0..3].iter().any(|&c| c > 64)));
+}
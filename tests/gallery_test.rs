@@ -0,0 +1,70 @@
+use itsliquid::gallery::qr_modules;
+use itsliquid::{GalleryCard, InteractiveFluid, SessionStats};
+
+#[test]
+fn test_session_stats_count_strokes_and_distinct_colors() {
+    let mut stats = SessionStats::new();
+    stats.record_stroke(Some((1.0, 0.0, 0.0)));
+    stats.record_stroke(Some((0.0, 0.0, 1.0)));
+    stats.record_stroke(Some((1.0, 0.0, 0.0)));
+    stats.record_stroke(None);
+    for _ in 0..650 {
+        stats.record_step(0.1);
+    }
+
+    assert_eq!(stats.strokes, 4);
+    assert_eq!(stats.colors, vec![(1.0, 0.0, 0.0), (0.0, 0.0, 1.0)]);
+    assert_eq!(stats.steps, 650);
+    assert_eq!(stats.summary(), "4 strokes · 2 colors · 1:04");
+
+    stats.reset();
+    assert_eq!(stats, SessionStats::default());
+}
+
+#[test]
+fn test_card_frames_canvas_with_footer() {
+    let mut sim = InteractiveFluid::new(40, 30);
+    sim.add_dye(20, 15, (5.0, 1.0, 0.5));
+    let stats = SessionStats::new();
+
+    let card = GalleryCard::default().render(&sim, &stats).unwrap();
+    assert!(card.width() > 40 * 4);
+    assert!(card.height() > 30 * 4 + 40);
+
+    // Dye shows up somewhere on the card
+    assert!(card.pixels().any(|p| p[0] > 100 && p[1] < p[0]));
+}
+
+#[test]
+fn test_card_link_adds_qr_code() {
+    let sim = InteractiveFluid::new(64, 48);
+    let mut stats = SessionStats::new();
+    stats.record_stroke(Some((0.2, 0.9, 0.4)));
+
+    // Tone-mapped dye never reaches full white, so only the QR code's light
+    // modules are pure white when there is no caption
+    let plain = GalleryCard::default().render(&sim, &stats).unwrap();
+    assert!(!plain.pixels().any(|p| p.0 == [255, 255, 255, 255]));
+
+    let card = GalleryCard {
+        link: Some("https://hypervolu.me/~erik/itsliquid/#s=e30".to_string()),
+        ..GalleryCard::default()
+    };
+    let image = card.render(&sim, &stats).unwrap();
+    assert!(image.pixels().any(|p| p.0 == [255, 255, 255, 255]));
+    assert!(image.pixels().any(|p| p.0 == [0, 0, 0, 255]));
+
+    // The swatch for the color used is drawn
+    assert!(image.pixels().any(|p| p.0 == [51, 229, 102, 255]));
+}
+
+#[test]
+fn test_qr_modules_cover_square_code() {
+    let (modules, dark) = qr_modules("https://hypervolu.me/~erik/itsliquid/#s=e30").unwrap();
+    assert!(modules >= 21);
+    assert_eq!(dark.len(), modules * modules);
+    assert!(dark.iter().any(|&d| d) && dark.iter().any(|&d| !d));
+
+    // Beyond the largest QR version
+    assert!(qr_modules(&"x".repeat(5000)).is_err());
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::uniform_flow;
+use glam::Vec2;
+use itsliquid::GateSet;
+
+#[test]
+fn test_volume_flux_through_vertical_gate() {
+    let sim = uniform_flow(32, 1.0, 0.0);
+    let mut gates = GateSet::new();
+    // Gate drawn bottom-to-top so its normal points in +x
+    gates.add("channel", Vec2::new(16.0, 20.0), Vec2::new(16.0, 10.0));
+    gates.measure(&sim, 0);
+
+    let flux = gates.gates[0].latest().unwrap();
+    // u·n * length * dt = 1.0 * 10.0 * 0.1
+    assert!((flux.volume - 1.0).abs() < 1e-4, "volume flux {}", flux.volume);
+}
+
+#[test]
+fn test_flux_sign_follows_gate_orientation() {
+    let sim = uniform_flow(32, 1.0, 0.0);
+    let mut gates = GateSet::new();
+    gates.add("forward", Vec2::new(16.0, 20.0), Vec2::new(16.0, 10.0));
+    gates.add("reverse", Vec2::new(16.0, 10.0), Vec2::new(16.0, 20.0));
+    gates.add("parallel", Vec2::new(10.0, 16.0), Vec2::new(20.0, 16.0));
+    gates.measure(&sim, 0);
+
+    let forward = gates.gates[0].latest().unwrap().volume;
+    let reverse = gates.gates[1].latest().unwrap().volume;
+    let parallel = gates.gates[2].latest().unwrap().volume;
+    assert!(forward > 0.0);
+    assert!((forward + reverse).abs() < 1e-5);
+    assert!(parallel.abs() < 1e-5);
+}
+
+#[test]
+fn test_dye_flux_accumulates() {
+    let mut sim = uniform_flow(32, 1.0, 0.0);
+    sim.dye_g.fill(0.5);
+    let mut gates = GateSet::new();
+    gates.add("channel", Vec2::new(16.0, 20.0), Vec2::new(16.0, 10.0));
+
+    for frame in 0..5 {
+        gates.measure(&sim, frame);
+    }
+
+    let gate = &gates.gates[0];
+    assert_eq!(gate.history.len(), 5);
+    assert!(gate.total_dye[0].abs() < 1e-6);
+    assert!((gate.total_dye[1] - 2.5).abs() < 1e-3, "dye total {}", gate.total_dye[1]);
+    assert!((gate.total_volume - 5.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_history_is_capped() {
+    let sim = uniform_flow(32, 0.0, 1.0);
+    let mut gates = GateSet::new();
+    gates.max_history = 3;
+    gates.add("g", Vec2::new(5.0, 16.0), Vec2::new(25.0, 16.0));
+
+    for frame in 0..10 {
+        gates.measure(&sim, frame);
+    }
+
+    let gate = &gates.gates[0];
+    assert_eq!(gate.history.len(), 3);
+    assert_eq!(gate.history[0].frame, 7);
+}
+
+#[test]
+fn test_residence_time_between_gates() {
+    let mut sim = uniform_flow(32, 1.0, 0.0);
+    let mut gates = GateSet::new();
+    gates.add("upstream", Vec2::new(10.0, 24.0), Vec2::new(10.0, 8.0));
+    gates.add("downstream", Vec2::new(20.0, 24.0), Vec2::new(20.0, 8.0));
+
+    // A stripe of dye moving right at 0.2 cells per frame reaches the second
+    // gate 50 frames after the first
+    for frame in 0..150 {
+        let center = 2.0 + 0.2 * frame as f32;
+        for y in 0..sim.height {
+            for x in 0..sim.width {
+                let d = x as f32 - center;
+                sim.dye_r[y * sim.width + x] = (-d * d / 8.0).exp();
+            }
+        }
+        gates.measure(&sim, frame);
+    }
+
+    let residence = gates.residence_frames("upstream", "downstream").unwrap();
+    assert!((residence - 50.0).abs() < 1.0, "residence {}", residence);
+    assert_eq!(gates.residence_frames("upstream", "missing"), None);
+}
+
+#[test]
+fn test_no_residence_before_dye_crosses() {
+    let sim = uniform_flow(32, 1.0, 0.0);
+    let mut gates = GateSet::new();
+    gates.add("a", Vec2::new(10.0, 20.0), Vec2::new(10.0, 10.0));
+    gates.add("b", Vec2::new(20.0, 20.0), Vec2::new(20.0, 10.0));
+    gates.measure(&sim, 0);
+    assert_eq!(gates.residence_frames("a", "b"), None);
+}
@@ -0,0 +1,85 @@
+use egui::{Event, Pos2, Rect, TouchDeviceId, TouchId, TouchPhase, Vec2};
+use itsliquid::brush::Brush;
+use itsliquid::gestures::{CanvasView, Touches, MAX_ZOOM};
+
+fn touch(id: u64, phase: TouchPhase, x: f32, y: f32) -> Event {
+    Event::Touch { device_id: TouchDeviceId(0), id: TouchId(id), phase, pos: Pos2::new(x, y), force: None }
+}
+
+fn canvas() -> Rect {
+    Rect::from_min_size(Pos2::ZERO, Vec2::new(400.0, 200.0))
+}
+
+#[test]
+fn test_zoom_keeps_anchor_in_place() {
+    let mut view = CanvasView::default();
+    assert_eq!(view.apply(canvas()), canvas());
+
+    let anchor = Pos2::new(100.0, 50.0);
+    view.gesture(canvas(), anchor, 2.0, Vec2::ZERO);
+    let rect = view.apply(canvas());
+    assert_eq!(rect.size(), Vec2::new(800.0, 400.0));
+    // The point under the anchor is the same fraction into the grid
+    assert_eq!((anchor - rect.min) / rect.size(), Vec2::new(0.25, 0.25));
+
+    view.gesture(canvas(), anchor, 100.0, Vec2::ZERO);
+    assert_eq!(view.zoom, MAX_ZOOM);
+    view.gesture(canvas(), anchor, 0.01, Vec2::ZERO);
+    assert!(view.is_identity());
+}
+
+#[test]
+fn test_pan_stays_over_canvas() {
+    let mut view = CanvasView::default();
+    // Nothing to pan at zoom 1
+    view.gesture(canvas(), canvas().center(), 1.0, Vec2::new(50.0, 0.0));
+    assert!(view.is_identity());
+
+    view.gesture(canvas(), canvas().center(), 2.0, Vec2::new(1000.0, -30.0));
+    let rect = view.apply(canvas());
+    assert_eq!(rect.left(), canvas().left());
+    assert_eq!(rect.center().y, canvas().center().y - 30.0);
+
+    // A smaller canvas pulls the pan back in
+    view.fit(Rect::from_min_size(Pos2::ZERO, Vec2::new(100.0, 50.0)));
+    assert_eq!(view.pan, Vec2::new(50.0, -25.0));
+}
+
+#[test]
+fn test_two_fingers_pinch() {
+    let mut touches = Touches::default();
+    touches.update(&[touch(1, TouchPhase::Start, 100.0, 100.0)]);
+    assert!(!touches.multi());
+    assert_eq!(touches.pinch(), None);
+
+    touches.update(&[touch(2, TouchPhase::Start, 200.0, 100.0)]);
+    assert!(touches.multi());
+    // Spread doubles and the midpoint moves 10 right
+    touches.update(&[touch(1, TouchPhase::Move, 60.0, 100.0), touch(2, TouchPhase::Move, 260.0, 100.0)]);
+    let (anchor, factor, translation) = touches.pinch().unwrap();
+    assert_eq!(anchor, Pos2::new(150.0, 100.0));
+    assert_eq!(factor, 2.0);
+    assert_eq!(translation, Vec2::new(10.0, 0.0));
+
+    // The finger left over stays a gesture until both lift
+    touches.update(&[touch(2, TouchPhase::End, 260.0, 100.0)]);
+    assert_eq!(touches.len(), 1);
+    assert!(touches.multi());
+    touches.update(&[touch(1, TouchPhase::Cancel, 60.0, 100.0)]);
+    assert!(touches.is_empty() && !touches.multi());
+}
+
+#[test]
+fn test_each_finger_strokes_apart() {
+    let brush = Brush { radius: 2.0, spacing: 0.5, ..Brush::default() };
+    let mut touches = Touches::default();
+    let to_grid = |pos: Pos2| glam::Vec2::new(pos.x / 10.0, pos.y / 10.0);
+    touches.update(&[touch(1, TouchPhase::Start, 100.0, 100.0), touch(2, TouchPhase::Start, 300.0, 100.0)]);
+    assert_eq!(touches.dabs(&brush, to_grid), vec![glam::Vec2::new(10.0, 10.0), glam::Vec2::new(30.0, 10.0)]);
+
+    // Both move 4 cells down: four dabs each, with no stroke between them
+    touches.update(&[touch(1, TouchPhase::Move, 100.0, 140.0), touch(2, TouchPhase::Move, 300.0, 140.0)]);
+    let dabs = touches.dabs(&brush, to_grid);
+    assert_eq!(dabs.len(), 8);
+    assert!(dabs.iter().all(|dab| dab.x == 10.0 || dab.x == 30.0));
+}
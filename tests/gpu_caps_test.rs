@@ -0,0 +1,62 @@
+//! Capability matrix selection and field encoding for the GPU solver
+
+#![cfg(feature = "gpu")]
+
+use itsliquid::gpu_caps::{FieldFormat, FormatSupport, GpuCapabilities, PipelineConfig};
+use itsliquid::gpu_functional::FunctionalGPUFluid;
+
+fn capabilities(rgba32_read_write: bool, max_invocations: u32) -> GpuCapabilities {
+    GpuCapabilities {
+        adapter_name: "Test Adapter".to_string(),
+        backend: "Vulkan".to_string(),
+        device_type: "IntegratedGpu".to_string(),
+        adapter_specific_formats: true,
+        formats: vec![
+            FormatSupport { format: FieldFormat::Rgba32Float, storage: true, storage_read_write: rgba32_read_write },
+            FormatSupport { format: FieldFormat::Rgba16Float, storage: true, storage_read_write: true },
+        ],
+        max_workgroup_size_x: 256,
+        max_workgroup_size_y: 256,
+        max_invocations_per_workgroup: max_invocations,
+        max_texture_dimension_2d: 8192,
+    }
+}
+
+#[test]
+fn test_candidates_prefer_full_precision_and_large_workgroups() {
+    let caps = capabilities(true, 256);
+    assert_eq!(caps.candidates()[0], PipelineConfig::default());
+    assert_eq!(caps.candidates().len(), 4);
+}
+
+#[test]
+fn test_candidates_fall_back_on_weaker_adapters() {
+    let caps = capabilities(false, 32);
+    assert_eq!(
+        caps.candidates(),
+        vec![PipelineConfig { format: FieldFormat::Rgba16Float, workgroup_size: 4 }]
+    );
+
+    let mut none = capabilities(true, 256);
+    none.adapter_specific_formats = false;
+    assert!(none.candidates().is_empty());
+    assert!(none.report().contains("adapter-specific formats: no"));
+}
+
+#[test]
+fn test_half_precision_round_trip() {
+    let values = [0.0, 1.0, -2.5, 0.125];
+    for format in FieldFormat::ALL {
+        let bytes = format.encode(&values);
+        assert_eq!(bytes.len(), format.bytes_per_texel());
+        assert_eq!(format.decode(&bytes), values);
+    }
+}
+
+#[tokio::test]
+async fn test_functional_gpu_reports_selected_pipeline() {
+    let fluid = FunctionalGPUFluid::new(16, 16).await.unwrap();
+    let report = fluid.capability_report();
+    assert!(report.contains("selected:"));
+    assert!(fluid.capabilities().candidates().contains(&fluid.pipeline_config()));
+}
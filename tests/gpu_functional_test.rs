@@ -1,5 +1,7 @@
 //! Automated tests for functional GPU fluid simulation
 
+#![cfg(feature = "gpu")]
+
 use itsliquid::{FluidSimulation, gpu_functional::FunctionalGPUFluid};
 
 #[tokio::test]
@@ -136,3 +138,123 @@ async fn test_functional_gpu_performance_characteristics() {
         assert_eq!(fluid.height(), height as usize);
     }
 }
+
+#[tokio::test]
+async fn test_functional_gpu_shader_reload() {
+    let mut fluid = FunctionalGPUFluid::new(16, 16).await.unwrap();
+    let source = include_str!("../src/shaders/functional.wgsl");
+    assert!(fluid.reload_shader(source).is_ok());
+
+    // A broken shader is reported and the running pipelines are kept
+    let broken = source.replace("fn advect_dye(", "fn advect_dye(oops");
+    let error = fluid.reload_shader(&broken).unwrap_err().to_string();
+    assert!(error.contains("failed"), "{}", error);
+    fluid.add_dye(8, 8, (1.0, 0.0, 0.0));
+    fluid.step();
+    let dye = fluid.read_dye_data().await.unwrap();
+    assert!(dye.iter().any(|&d| d > 0.0));
+}
+
+#[tokio::test]
+async fn test_functional_gpu_set_params() {
+    use itsliquid::{ConfigurableSimulation, Param};
+
+    let mut fluid = FunctionalGPUFluid::new(16, 16).await.unwrap();
+    fluid.set_params(0.05, 0.001, 0.0001).unwrap();
+    assert_eq!(fluid.param(Param::Dt), Some(0.05));
+    assert_eq!(fluid.param(Param::Viscosity), Some(0.001));
+    assert_eq!(fluid.gpu_dt(), 0.05);
+
+    // One value out of range leaves all three as they were
+    assert!(fluid.set_params(0.1, 5.0, 0.0).is_err());
+    assert_eq!(fluid.param(Param::Dt), Some(0.05));
+    assert!(fluid.set_param(Param::Dissipation, 0.1).is_err());
+
+    fluid.set_param(Param::Diffusion, 0.002).unwrap();
+    fluid.add_dye(8, 8, (1.0, 0.0, 0.0));
+    fluid.step();
+    let dye = fluid.read_dye_data().await.unwrap();
+    assert!(dye.iter().all(|d| d.is_finite()));
+}
+
+#[tokio::test]
+async fn test_functional_gpu_batch_injection_adds() {
+    let mut fluid = FunctionalGPUFluid::new(16, 16).await.unwrap();
+    let texel = |x: usize, y: usize| (y * 16 + x) * 4;
+
+    // Splats on the same cell sum, and a second batch adds to the first
+    fluid.add_dye_batch(&[(3, 4, (0.5, 0.0, 0.0)), (3, 4, (0.25, 0.0, 0.0)), (5, 5, (0.0, 1.0, 0.0)), (99, 2, (1.0, 1.0, 1.0))]);
+    fluid.add_dye_batch(&[(3, 4, (0.25, 0.0, 0.0))]);
+    fluid.add_force_batch(&[(8, 8, glam::Vec2::new(2.0, -1.0)), (8, 8, glam::Vec2::new(1.0, 0.0))]);
+
+    let dye = fluid.read_dye_data().await.unwrap();
+    assert!((dye[texel(3, 4)] - 1.0).abs() < 1e-3, "{}", dye[texel(3, 4)]);
+    assert!((dye[texel(5, 5) + 1] - 1.0).abs() < 1e-3);
+    assert_eq!(dye[texel(6, 5) + 1], 0.0);
+    let velocity = fluid.read_velocity_data().await.unwrap();
+    assert!((velocity[texel(8, 8)] - 3.0).abs() < 1e-3 && (velocity[texel(8, 8) + 1] + 1.0).abs() < 1e-3);
+}
+
+#[tokio::test]
+async fn test_functional_gpu_injection_matches_cpu() {
+    use itsliquid::fluid_interactive::InteractiveFluid;
+
+    let mut gpu = FunctionalGPUFluid::new(16, 16).await.unwrap();
+    let mut cpu = InteractiveFluid::new(16, 16);
+    let texel = |x: usize, y: usize| (y * 16 + x) * 4;
+
+    // Dye accumulates rather than overwriting
+    for sim in [&mut gpu as &mut dyn FluidSimulation, &mut cpu] {
+        sim.add_dye(4, 4, (0.5, 0.25, 0.0));
+        sim.add_dye(4, 4, (0.5, 0.25, 0.0));
+    }
+    let dye = gpu.read_dye_data().await.unwrap();
+    assert!((dye[texel(4, 4)] - cpu.dye_r[4 * 16 + 4]).abs() < 1e-3, "{}", dye[texel(4, 4)]);
+    assert!((dye[texel(4, 4) + 1] - cpu.dye_g[4 * 16 + 4]).abs() < 1e-3);
+
+    // A brush force falls off with distance the same way on both
+    gpu.add_force_radius(8, 8, glam::Vec2::new(2.0, 0.0), 3.0);
+    cpu.add_force(8, 8, glam::Vec2::new(2.0, 0.0), 3.0);
+    let velocity = gpu.read_velocity_data().await.unwrap();
+    for (x, y) in [(8, 8), (9, 8), (10, 9), (11, 8), (12, 8)] {
+        let expected = cpu.velocity_x[y * 16 + x];
+        assert!((velocity[texel(x, y)] - expected).abs() < 1e-3, "({}, {})", x, y);
+    }
+}
+
+#[tokio::test]
+async fn test_functional_gpu_cell_aspect() {
+    let mut fluid = FunctionalGPUFluid::new(32, 16).await.unwrap();
+    assert_eq!(fluid.cell_aspect(), 1.0);
+    assert!(fluid.set_cell_aspect(0.0).is_err());
+    assert!(fluid.set_cell_aspect(f32::NAN).is_err());
+    fluid.set_cell_aspect(2.0).unwrap();
+    assert_eq!(fluid.cell_aspect(), 2.0);
+
+    fluid.add_dye(16, 8, (1.0, 0.0, 0.0));
+    fluid.gpu_add_force(16, 8, glam::Vec2::new(0.0, 4.0));
+    for _ in 0..5 {
+        fluid.step();
+    }
+    let dye = fluid.read_dye_data().await.unwrap();
+    assert!(dye.iter().all(|d| d.is_finite()));
+}
+
+#[tokio::test]
+async fn test_functional_gpu_viscosity_field() {
+    let peak = |factor: f32| async move {
+        let mut fluid = FunctionalGPUFluid::new(16, 16).await.unwrap();
+        fluid.set_params(0.1, 0.1, 0.0001).unwrap();
+        fluid.set_viscosity_field(&[factor; 256]).unwrap();
+        fluid.gpu_add_force(8, 8, glam::Vec2::new(4.0, 0.0));
+        fluid.step();
+        fluid.read_velocity_data().await.unwrap()[(8 * 16 + 8) * 4]
+    };
+    // Thick cells spread the push to their neighbours faster
+    let (plain, thick) = (peak(1.0).await, peak(100.0).await);
+    assert!(thick < plain, "{} vs {}", thick, plain);
+
+    let mut fluid = FunctionalGPUFluid::new(16, 16).await.unwrap();
+    assert!(fluid.set_viscosity_field(&[2.0; 10]).is_err());
+    fluid.set_viscosity_field(&[]).unwrap();
+}
@@ -1,5 +1,7 @@
 //! Automated tests for GPU fluid simulation
 
+#![cfg(feature = "gpu")]
+
 use itsliquid::{FluidSimulation, gpu_minimal::MinimalGPUFluid};
 
 #[tokio::test]
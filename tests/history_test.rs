@@ -0,0 +1,57 @@
+use itsliquid::{FluidSnapshot, InteractiveFluid, UndoStack};
+
+#[test]
+fn test_undo_redo_walks_edits() {
+    let mut history = UndoStack::new(10);
+    let mut state = 0;
+    for edit in 1..=3 {
+        history.push(state);
+        state = edit;
+    }
+
+    state = history.undo(state).unwrap();
+    assert_eq!(state, 2);
+    state = history.undo(state).unwrap();
+    assert_eq!(state, 1);
+    state = history.redo(state).unwrap();
+    assert_eq!(state, 2);
+
+    // A new edit drops what was undone
+    history.push(state);
+    state = 7;
+    assert!(!history.can_redo());
+    assert!(history.redo(state).is_none());
+    assert_eq!(history.undo(state), Some(2));
+}
+
+#[test]
+fn test_depth_drops_oldest() {
+    let mut history = UndoStack::new(2);
+    for state in 0..5 {
+        history.push(state);
+    }
+    assert_eq!(history.undo(5), Some(4));
+    assert_eq!(history.undo(4), Some(3));
+    assert_eq!(history.undo(3), None);
+}
+
+#[test]
+fn test_snapshot_restores_fluid() {
+    let mut fluid = InteractiveFluid::new(24, 24);
+    fluid.add_dye(12, 12, (1.0, 0.5, 0.0));
+    fluid.step();
+    let before = FluidSnapshot::capture(&fluid);
+
+    // An accidental stroke, then time passes
+    fluid.add_dye(6, 6, (5.0, 5.0, 5.0));
+    fluid.add_force(6, 6, glam::Vec2::new(3.0, 0.0), 3.0);
+    fluid.step();
+    assert_ne!(FluidSnapshot::capture(&fluid), before);
+
+    before.restore(&mut fluid).unwrap();
+    assert_eq!(FluidSnapshot::capture(&fluid), before);
+
+    let mut resized = InteractiveFluid::new(30, 24);
+    assert!(before.restore(&mut resized).is_err());
+    assert!(resized.dye_r.iter().all(|&v| v == 0.0));
+}
@@ -0,0 +1,83 @@
+use itsliquid::ingest::Rejection;
+use itsliquid::{IngestLimits, Ingestor, SimCommand};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_sanitize_clamps_out_of_range_values() {
+    let ingest = Ingestor::default();
+
+    let dye = ingest.sanitize(SimCommand::Dye { x: -0.5, y: 2.0, color: (4.0, -1.0, 0.5) }).unwrap();
+    assert_eq!(dye, SimCommand::Dye { x: 0.0, y: 1.0, color: (1.0, 0.0, 0.5) });
+
+    let force = ingest.sanitize(SimCommand::Force { x: 0.5, y: 0.5, dx: 30.0, dy: 40.0 }).unwrap();
+    let SimCommand::Force { dx, dy, .. } = force else { panic!("expected force") };
+    assert!((dx - 0.6).abs() < 1e-6 && (dy - 0.8).abs() < 1e-6);
+
+    let heat = ingest.sanitize(SimCommand::Heat { x: 0.5, y: 0.5, amount: -1e9 }).unwrap();
+    assert_eq!(heat, SimCommand::Heat { x: 0.5, y: 0.5, amount: -5.0 });
+
+    assert_eq!(ingest.sanitize(SimCommand::Heat { x: f32::NAN, y: 0.5, amount: 1.0 }), Err(Rejection::NonFinite));
+    assert_eq!(ingest.sanitize(SimCommand::Clear), Err(Rejection::ClearDisabled));
+}
+
+#[test]
+fn test_rate_limit_is_per_source_and_refills() {
+    let mut ingest = Ingestor::new(IngestLimits {
+        per_source_rate: 2.0,
+        per_source_burst: 2.0,
+        ..IngestLimits::default()
+    });
+    let start = Instant::now();
+
+    assert!(ingest.submit_text("alice", "!heat 0.5 0.5", start).is_ok());
+    assert!(ingest.submit_text("alice", "!heat 0.5 0.5", start).is_ok());
+    assert_eq!(ingest.submit_text("alice", "!heat 0.5 0.5", start), Err(Rejection::RateLimited));
+    // Another source has its own budget
+    assert!(ingest.submit_text("bob", "!heat 0.5 0.5", start).is_ok());
+
+    // Half a second at 2/s buys one more command
+    let later = start + Duration::from_millis(500);
+    assert!(ingest.submit_text("alice", "!heat 0.5 0.5", later).is_ok());
+    assert_eq!(ingest.submit_text("alice", "!heat 0.5 0.5", later), Err(Rejection::RateLimited));
+
+    assert_eq!(ingest.accepted, 4);
+    assert_eq!(ingest.rejected, 2);
+}
+
+#[test]
+fn test_global_limit_caps_many_sources() {
+    let mut ingest = Ingestor::new(IngestLimits {
+        global_rate: 1.0,
+        global_burst: 5.0,
+        ..IngestLimits::default()
+    });
+    let now = Instant::now();
+
+    let accepted = (0..20)
+        .filter(|i| ingest.submit_text(&format!("bot{}", i), "!dye red 0.5 0.5", now).is_ok())
+        .count();
+    assert_eq!(accepted, 5);
+    assert_eq!(ingest.rejected, 15);
+}
+
+#[test]
+fn test_rejects_are_logged_once_per_streak() {
+    let mut ingest = Ingestor::new(IngestLimits {
+        per_source_burst: 1.0,
+        ..IngestLimits::default()
+    });
+    let now = Instant::now();
+
+    assert!(matches!(ingest.submit_text("eve", "!explode", now), Err(Rejection::Parse(_))));
+    assert!(ingest.submit_text("eve", "!dye red 0.5 0.5", now).is_ok());
+    for _ in 0..10 {
+        assert_eq!(ingest.submit_text("eve", "!dye red 0.5 0.5", now), Err(Rejection::RateLimited));
+    }
+
+    let log: Vec<_> = ingest.recent_rejects().collect();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].input, "!explode");
+    assert_eq!(log[1].reason, Rejection::RateLimited);
+    assert_eq!(log[1].source, "eve");
+    assert_eq!(ingest.rejected, 11);
+}
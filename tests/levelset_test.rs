@@ -0,0 +1,76 @@
+use glam::Vec2;
+use itsliquid::export::FluidData;
+use itsliquid::{ConfigurableSimulation, FluidSimulation, LevelSetFluid, Param};
+
+fn max_speed(fluid: &LevelSetFluid) -> f32 {
+    (0..fluid.phi.len())
+        .filter(|&i| fluid.phi[i] < 0.0)
+        .map(|i| Vec2::new(fluid.velocity_x[i], fluid.velocity_y[i]).length())
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn test_still_water_stays_level() {
+    let mut fluid = LevelSetFluid::new(32, 32);
+    fluid.fill_below(0.4);
+    let volume = fluid.volume();
+    let level = fluid.surface_heights()[16];
+    for _ in 0..100 {
+        fluid.step();
+    }
+    assert!((fluid.volume() - volume).abs() < 0.01 * volume, "{} of {}", fluid.volume(), volume);
+    let heights = fluid.surface_heights();
+    assert!(heights[1..31].iter().all(|&y| y.abs_diff(level) <= 1), "{:?} from {}", heights, level);
+    assert!(max_speed(&fluid) < 1.0, "still water moving at {}", max_speed(&fluid));
+}
+
+#[test]
+fn test_dam_break_spreads_across_the_tank() {
+    let mut fluid = LevelSetFluid::new(48, 32);
+    fluid.add_liquid_box((1, 8), (14, 31));
+    let volume = fluid.volume();
+    assert!(!fluid.is_liquid(40, 29));
+
+    for _ in 0..150 {
+        fluid.step();
+    }
+    // The column collapsed and water reached the far wall
+    assert!(fluid.is_liquid(40, 29), "heights {:?}", fluid.surface_heights());
+    assert!(fluid.surface_heights()[5] > 12);
+    assert!((fluid.volume() - volume).abs() < 0.02 * volume, "{} of {}", fluid.volume(), volume);
+    assert!(fluid.phi.iter().all(|p| p.is_finite()));
+}
+
+#[test]
+fn test_drop_falls_into_pool() {
+    let mut fluid = LevelSetFluid::new(32, 40);
+    fluid.fill_below(0.25);
+    fluid.add_liquid_disc(16.0, 8.0, 3.0);
+    let depth = |fluid: &LevelSetFluid| {
+        let cells: Vec<usize> = (0..fluid.phi.len()).filter(|&i| fluid.phi[i] < 0.0 && i / 32 < 20).collect();
+        cells.len()
+    };
+    assert!(depth(&fluid) > 0);
+    for _ in 0..60 {
+        fluid.step();
+    }
+    // Nothing is left hanging in the upper half
+    assert_eq!(depth(&fluid), 0, "heights {:?}", fluid.surface_heights());
+}
+
+#[test]
+fn test_levelset_plugs_into_shared_traits() {
+    let mut fluid = LevelSetFluid::new(24, 24);
+    assert_eq!(fluid.volume(), 0.0);
+    // Dye pours liquid
+    fluid.add_dye(12, 6, (1.0, 0.0, 0.0));
+    assert!(fluid.volume() > 0.0);
+    assert!(fluid.density()[6 * 24 + 12] > 0.99);
+    fluid.add_force(12, 6, Vec2::new(5.0, 0.0));
+    FluidSimulation::step(&mut fluid);
+
+    fluid.set_param(Param::Dt, 0.05).unwrap();
+    assert_eq!(fluid.dt, 0.05);
+    assert!(fluid.set_param(Param::Viscosity, 0.01).is_err());
+    assert_eq!(fluid.params().len(), 1);
+}
@@ -0,0 +1,84 @@
+use itsliquid::InteractiveFluid;
+
+const FRAMES: usize = 5000;
+
+fn kinetic_energy(sim: &InteractiveFluid) -> f32 {
+    sim.velocity_x
+        .iter()
+        .zip(&sim.velocity_y)
+        .map(|(&u, &v)| 0.5 * (u * u + v * v))
+        .sum()
+}
+
+fn dye_mass(sim: &InteractiveFluid) -> [f32; 3] {
+    [
+        sim.dye_r.iter().sum(),
+        sim.dye_g.iter().sum(),
+        sim.dye_b.iter().sum(),
+    ]
+}
+
+fn all_finite(sim: &InteractiveFluid) -> bool {
+    [
+        &sim.velocity_x,
+        &sim.velocity_y,
+        &sim.dye_r,
+        &sim.dye_g,
+        &sim.dye_b,
+        &sim.pressure,
+    ]
+    .iter()
+    .all(|field| field.iter().all(|v| v.is_finite()))
+}
+
+/// Standard scenario: three dye blobs stirred by opposing impulses, then left
+/// to run unforced so any energy gain or mass drift comes from the solver.
+fn standard_scenario() -> InteractiveFluid {
+    let mut sim = InteractiveFluid::new(32, 32);
+    sim.add_dye(10, 16, (4.0, 0.0, 0.0));
+    sim.add_dye(22, 16, (0.0, 4.0, 0.0));
+    sim.add_dye(16, 10, (0.0, 0.0, 4.0));
+    sim.add_force(10, 16, glam::Vec2::new(20.0, 8.0), 3.0);
+    sim.add_force(22, 16, glam::Vec2::new(-20.0, -8.0), 3.0);
+    sim.add_force(16, 10, glam::Vec2::new(0.0, 15.0), 3.0);
+    sim
+}
+
+#[test]
+fn test_long_run_numerical_drift() {
+    let mut sim = standard_scenario();
+    let initial_mass = dye_mass(&sim);
+    let mut energy = kinetic_energy(&sim);
+
+    for frame in 0..FRAMES {
+        sim.step();
+
+        assert!(all_finite(&sim), "Frame {}: non-finite value in fields", frame);
+
+        // Unforced flow may only lose energy; allow a small relative and
+        // absolute slack for float rounding in the iterative solvers
+        let next = kinetic_energy(&sim);
+        assert!(
+            next <= energy * 1.001 + 1e-6,
+            "Frame {}: kinetic energy grew from {:.6} to {:.6}",
+            frame,
+            energy,
+            next
+        );
+        energy = next;
+
+        if frame % 500 == 0 || frame == FRAMES - 1 {
+            let mass = dye_mass(&sim);
+            for c in 0..3 {
+                let drift = (mass[c] - initial_mass[c]).abs() / initial_mass[c];
+                assert!(
+                    drift < 0.01,
+                    "Frame {}: channel {} mass drifted {:.4}%",
+                    frame,
+                    c,
+                    drift * 100.0
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use image::{GrayImage, Luma};
+use itsliquid::{InteractiveFluid, Mask};
+
+// White square in the middle of a black 40x40 image
+fn square_image() -> GrayImage {
+    GrayImage::from_fn(40, 40, |x, y| {
+        let inside = (10..30).contains(&x) && (10..30).contains(&y);
+        Luma([if inside { 255 } else { 0 }])
+    })
+}
+
+#[test]
+fn test_resamples_to_grid() {
+    let mask = Mask::from_gray(&square_image(), 20, 20, 0.5);
+    assert_eq!(mask.count(), 100);
+    assert!(mask.get(5, 5) && mask.get(14, 14));
+    assert!(!mask.get(4, 10) && !mask.get(15, 10));
+    assert!(!mask.get(99, 0));
+
+    // Grids finer than the image still cover it
+    let fine = Mask::from_gray(&square_image(), 80, 80, 0.5);
+    assert_eq!(fine.count(), 40 * 40);
+
+    let mut inverted = mask.clone();
+    inverted.invert();
+    assert_eq!(inverted.count(), 400 - 100);
+}
+
+#[test]
+fn test_obstacle_blocks_flow_and_dye() {
+    let mut fluid = InteractiveFluid::new(20, 20);
+    fluid.obstacles = Some(Mask::from_gray(&square_image(), 20, 20, 0.5));
+    // A jet along the open lane above the obstacle
+    for step in 0..50 {
+        fluid.add_force(2, 2, glam::Vec2::new(20.0, 0.0), 1.0);
+        fluid.add_dye(2, 2, (1.0, 0.0, 0.0));
+        fluid.step();
+        for y in 5..15 {
+            for x in 5..15 {
+                let idx = y * 20 + x;
+                assert_eq!((fluid.velocity_x[idx], fluid.velocity_y[idx]), (0.0, 0.0), "step {}", step);
+                assert_eq!(fluid.dye_r[idx], 0.0);
+            }
+        }
+    }
+    // Dye still carries along the lane past the obstacle's far side
+    assert!(fluid.dye_r[2 * 20 + 15] > 0.01, "{}", fluid.dye_r[2 * 20 + 15]);
+}
+
+#[test]
+fn test_emitter_pours_dye_from_white_cells() {
+    let mut fluid = InteractiveFluid::new(20, 20);
+    let mask = Mask::from_gray(&square_image(), 20, 20, 0.5);
+    mask.emit_dye(&mut fluid, (0.5, 0.0, 0.25));
+    let total: f32 = fluid.dye_r.iter().sum();
+    assert!((total - 50.0).abs() < 1e-3);
+    assert_eq!(fluid.dye_r[0], 0.0);
+    assert_eq!(fluid.dye_b[10 * 20 + 10], 0.25);
+}
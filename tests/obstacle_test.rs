@@ -0,0 +1,96 @@
+use glam::Vec2;
+use itsliquid::{BoundaryMode, InteractiveFluid, MovingObstacle, ObstacleShape};
+
+#[test]
+fn test_dragged_disc_pushes_the_fluid() {
+    let mut fluid = InteractiveFluid::new(48, 32);
+    let mut disc = MovingObstacle::new(ObstacleShape::Disc { radius: 4.0 }, Vec2::new(12.0, 16.0));
+    disc.steer_to(Vec2::new(14.0, 16.0), fluid.dt);
+    fluid.moving_obstacles.push(disc);
+    fluid.step();
+
+    let disc = fluid.moving_obstacles[0];
+    assert!((disc.center - Vec2::new(14.0, 16.0)).length() < 1e-4);
+    // The covered cells move with the disc, the fluid ahead is pushed along
+    assert!((fluid.velocity_x[16 * 48 + 14] - disc.velocity.x).abs() < 1e-3);
+    assert!(fluid.velocity_x[16 * 48 + 20] > 0.0);
+}
+
+#[test]
+fn test_spinning_paddle_stirs() {
+    let mut fluid = InteractiveFluid::new(40, 40);
+    let paddle = MovingObstacle {
+        spin: 1.0,
+        ..MovingObstacle::new(ObstacleShape::Paddle { length: 16.0, width: 2.0 }, Vec2::new(20.0, 20.0))
+    };
+    fluid.moving_obstacles.push(paddle);
+    for _ in 0..10 {
+        fluid.step();
+    }
+    let paddle = fluid.moving_obstacles[0];
+    assert!((paddle.angle - 1.0).abs() < 1e-4);
+    let tip = paddle.center + 6.0 * Vec2::from_angle(paddle.angle);
+    let idx = tip.y.round() as usize * 40 + tip.x.round() as usize;
+    let expected = paddle.surface_velocity(Vec2::new(tip.x.round(), tip.y.round()));
+    assert!((fluid.velocity_y[idx] - expected.y).abs() < 1e-3);
+    // The fluid beyond the paddle's reach is set turning too
+    let outside: f32 = (0..40 * 40)
+        .filter(|i| Vec2::new((i % 40) as f32, (i / 40) as f32).distance(paddle.center) > 10.0)
+        .map(|i| fluid.velocity_x[i].abs() + fluid.velocity_y[i].abs())
+        .sum();
+    assert!(outside > 0.0);
+}
+
+#[test]
+fn test_paddle_turns_with_its_angle() {
+    let mut paddle = MovingObstacle::new(ObstacleShape::Paddle { length: 10.0, width: 2.0 }, Vec2::ZERO);
+    assert!(paddle.contains(Vec2::new(4.0, 0.0)));
+    assert!(!paddle.contains(Vec2::new(0.0, 4.0)));
+    paddle.angle = std::f32::consts::FRAC_PI_2;
+    assert!(paddle.contains(Vec2::new(0.0, 4.0)));
+    assert!(!paddle.contains(Vec2::new(4.0, 0.0)));
+
+    let disc = MovingObstacle::new(ObstacleShape::Disc { radius: 2.0 }, Vec2::new(4.0, 4.0));
+    assert_eq!(disc.cells(8, 8).len(), 13);
+    let scaled = disc.rescaled(2.0, 2.0);
+    assert_eq!(scaled.center, Vec2::new(8.0, 8.0));
+    assert_eq!(scaled.shape, ObstacleShape::Disc { radius: 4.0 });
+}
+
+// A steady current across a periodic grid, with one floating disc in it
+fn current_carrying(density: f32) -> MovingObstacle {
+    let mut fluid = InteractiveFluid::new(48, 32);
+    fluid.boundary = BoundaryMode::Periodic;
+    fluid.velocity_x.fill(5.0);
+    let disc = MovingObstacle::floating(ObstacleShape::Disc { radius: 3.0 }, Vec2::new(16.0, 16.0), density);
+    fluid.moving_obstacles.push(disc);
+    for _ in 0..5 {
+        fluid.step();
+    }
+    fluid.moving_obstacles[0]
+}
+
+#[test]
+fn test_current_carries_floating_disc() {
+    let light = current_carrying(0.5);
+    assert!(light.center.x > 16.0);
+    assert!(light.velocity.x > 1.0);
+    assert!(light.velocity.y.abs() < 0.5);
+    // Heavier bodies pick up the current more slowly
+    let heavy = current_carrying(5.0);
+    assert!(heavy.velocity.x > 0.0 && heavy.velocity.x < light.velocity.x);
+}
+
+#[test]
+fn test_floating_body_stays_on_the_grid() {
+    let mut disc = MovingObstacle::floating(ObstacleShape::Disc { radius: 3.0 }, Vec2::new(2.0, 10.0), 1.0);
+    disc.velocity = Vec2::new(-4.0, 1.0);
+    disc.keep_inside(20, 20);
+    assert_eq!(disc.center, Vec2::new(3.0, 10.0));
+    assert_eq!(disc.velocity, Vec2::new(0.0, 1.0));
+
+    // Without a density the flow leaves it alone
+    let mut driven = MovingObstacle::new(ObstacleShape::Disc { radius: 3.0 }, Vec2::new(10.0, 10.0));
+    driven.float(&[5.0; 400], &[0.0; 400], 20, 20);
+    assert_eq!(driven.velocity, Vec2::ZERO);
+}
@@ -0,0 +1,77 @@
+use itsliquid::palette::{color_from_sample, parse_hex, to_hex, DEFAULT_COLORS, MAX_COLORS};
+use itsliquid::Palette;
+
+#[test]
+fn test_add_reuses_matching_slot() {
+    let mut palette = Palette::default();
+    palette.add((0.0, 0.0, 1.0));
+    assert_eq!((palette.colors.len(), palette.current), (8, 2));
+
+    palette.add((1.0, 0.5, 0.0));
+    assert_eq!((palette.colors.len(), palette.current), (9, 8));
+    assert_eq!(palette.current(), (1.0, 0.5, 0.0));
+
+    // A copy to edit, even though the color is already there
+    palette.insert(palette.current());
+    assert_eq!((palette.colors.len(), palette.current), (10, 9));
+}
+
+#[test]
+fn test_full_palette_overwrites_current() {
+    let mut palette = Palette::default();
+    while palette.colors.len() < MAX_COLORS {
+        palette.insert((0.5, 0.5, 0.5));
+    }
+    palette.current = 3;
+    palette.insert((0.1, 0.2, 0.3));
+    assert_eq!(palette.colors.len(), MAX_COLORS);
+    assert_eq!(palette.colors[3], (0.1, 0.2, 0.3));
+}
+
+#[test]
+fn test_remove_keeps_selection() {
+    let mut palette = Palette { current: 5, ..Palette::default() };
+    palette.remove(1);
+    assert_eq!(palette.current(), DEFAULT_COLORS[5]);
+    palette.remove(palette.current);
+    assert_eq!(palette.current(), DEFAULT_COLORS[6]);
+
+    // Removing the last slot while it is selected moves to the one before
+    palette.current = palette.colors.len() - 1;
+    palette.remove(palette.current);
+    assert_eq!(palette.current(), DEFAULT_COLORS[6]);
+
+    let mut single = Palette { colors: vec![(1.0, 1.0, 1.0)], current: 0 };
+    single.remove(0);
+    assert_eq!(single.colors.len(), 1);
+}
+
+#[test]
+fn test_sanitized_repairs_stored_palette() {
+    let empty = Palette { colors: Vec::new(), current: 4 };
+    assert_eq!(empty.sanitized(), Palette::default());
+    let stale = Palette { colors: vec![(1.0, 0.0, 0.0); 3], current: 7 };
+    assert_eq!(stale.sanitized().current, 2);
+}
+
+#[test]
+fn test_sample_keeps_hue() {
+    assert_eq!(color_from_sample((4.0, 2.0, 0.0)), (1.0, 0.5, 0.0));
+    assert_eq!(color_from_sample((0.25, 0.0, 0.125)), (1.0, 0.0, 0.5));
+    assert_eq!(color_from_sample((0.0, -1.0, 0.0)), (0.0, 0.0, 0.0));
+    assert_eq!(color_from_sample((f32::INFINITY, 0.0, 0.0)), (0.0, 0.0, 0.0));
+}
+
+#[test]
+fn test_hex_round_trip() {
+    assert_eq!(to_hex((1.0, 0.5, 0.0)), "#FF8000");
+    assert_eq!(to_hex((2.0, -1.0, 0.2)), "#FF0033");
+    assert_eq!(parse_hex("#FF8000"), Some((1.0, 128.0 / 255.0, 0.0)));
+    assert_eq!(parse_hex(" 00ff33 "), Some((0.0, 1.0, 0.2)));
+    assert_eq!(parse_hex("#f0a"), parse_hex("#FF00AA"));
+    for bad in ["", "#", "#12345", "#GG0000", "+12345", "#ÿÿÿ"] {
+        assert_eq!(parse_hex(bad), None, "{}", bad);
+    }
+    let color = parse_hex("#3A7FC2").unwrap();
+    assert_eq!(to_hex(color), "#3A7FC2");
+}
@@ -0,0 +1,156 @@
+use glam::Vec2;
+use itsliquid::{ConfigurableSimulation, InteractiveFluid, Param, Scheme, Solver};
+
+#[test]
+fn test_interactive_params_round_trip() {
+    let mut fluid = InteractiveFluid::new(20, 20);
+    assert_eq!(fluid.params().len(), Param::ALL.len());
+    assert_eq!(fluid.param(Param::Dt), Some(fluid.dt));
+
+    fluid.set_param(Param::Viscosity, 0.01).unwrap();
+    fluid.set_param(Param::Diffusion, 0.0).unwrap();
+    assert_eq!(fluid.viscosity, 0.01);
+    assert_eq!(fluid.dye_diffusion, 0.0);
+    assert_eq!(fluid.param(Param::Viscosity), Some(0.01));
+
+    // Out of range values are rejected and leave the solver unchanged
+    assert!(fluid.set_param(Param::Dt, 0.0).is_err());
+    assert!(fluid.set_param(Param::Viscosity, f32::NAN).is_err());
+    assert_eq!(fluid.dt, 0.1);
+}
+
+#[test]
+fn test_builder_sets_params() {
+    let fluid = InteractiveFluid::new(20, 20)
+        .with_param(Param::Dt, 0.05)
+        .and_then(|f| f.with_param(Param::Viscosity, 0.002))
+        .unwrap();
+    assert_eq!(fluid.dt, 0.05);
+    assert_eq!(fluid.viscosity, 0.002);
+
+    assert!(InteractiveFluid::new(20, 20).with_param(Param::Diffusion, 1.0).is_err());
+}
+
+#[test]
+fn test_viscosity_changes_the_running_simulation() {
+    let energy = |viscosity: f32| {
+        let mut fluid = InteractiveFluid::new(32, 32);
+        fluid.set_param(Param::Viscosity, viscosity).unwrap();
+        for _ in 0..20 {
+            fluid.add_force(16, 16, Vec2::new(4.0, 0.0), 3.0);
+            fluid.step();
+        }
+        fluid.velocity_x.iter().zip(&fluid.velocity_y).map(|(x, y)| x * x + y * y).sum::<f32>()
+    };
+    assert!(energy(0.05) < energy(0.0001));
+}
+
+#[test]
+fn test_solver_params_follow_scheme() {
+    let mut solver = Solver::with_scheme(Scheme::ForwardMove, 20, 20);
+    assert_eq!(solver.params(), vec![(Param::Dt, 1.0)]);
+    assert!(solver.set_param(Param::Viscosity, 0.01).is_err());
+
+    solver.set_param(Param::Dt, 0.5).unwrap();
+    assert_eq!(solver.config().dt, 0.5);
+    assert_eq!(solver.param(Param::Dt), Some(0.5));
+
+    let mut solver = Solver::with_scheme(Scheme::Interactive, 20, 20);
+    solver.set_param(Param::Diffusion, 0.001).unwrap();
+    assert_eq!(solver.as_interactive().unwrap().dye_diffusion, 0.001);
+}
+
+#[test]
+fn test_dissipation_fades_dye() {
+    let total = |dissipation: f32| {
+        let mut fluid = InteractiveFluid::new(32, 32);
+        fluid.set_param(Param::Dissipation, dissipation).unwrap();
+        fluid.add_dye(16, 16, (1.0, 1.0, 1.0));
+        for _ in 0..10 {
+            fluid.step();
+        }
+        fluid.dye_r.iter().sum::<f32>()
+    };
+    let kept = total(0.0);
+    let faded = total(0.5);
+    // Ten steps of dt = 0.1 at rate 0.5 keep exp(-0.5) of the dye
+    assert!((faded / kept - (-0.5f32).exp()).abs() < 1e-3, "{} vs {}", faded, kept);
+    assert!(InteractiveFluid::new(8, 8).set_param(Param::Dissipation, -1.0).is_err());
+}
+
+#[test]
+fn test_dye_decay_fades_channels_separately() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    fluid.set_param(Param::Dissipation, 0.2).unwrap();
+    fluid.dye_decay = [0.0, 0.3, 1.0];
+    assert_eq!(fluid.dye_fade_rates(), [0.2, 0.5, 1.2]);
+
+    fluid.add_dye(16, 16, (1.0, 1.0, 1.0));
+    let start: [f32; 3] = [&fluid.dye_r, &fluid.dye_g, &fluid.dye_b].map(|c| c.iter().sum());
+    for _ in 0..10 {
+        fluid.step();
+    }
+    let end: [f32; 3] = [&fluid.dye_r, &fluid.dye_g, &fluid.dye_b].map(|c| c.iter().sum());
+    // Ten steps of dt = 0.1 keep exp(-rate) of each channel
+    for (c, rate) in fluid.dye_fade_rates().into_iter().enumerate() {
+        assert!((end[c] / start[c] - (-rate).exp()).abs() < 1e-3, "channel {}: {} of {}", c, end[c], start[c]);
+    }
+}
+
+#[test]
+fn test_velocity_damping_settles_the_flow() {
+    let speed = |damping: f32| {
+        let mut fluid = InteractiveFluid::new(32, 32);
+        fluid.velocity_damping = damping;
+        fluid.add_force(16, 16, Vec2::new(20.0, 5.0), 4.0);
+        for _ in 0..10 {
+            fluid.step();
+        }
+        fluid.velocity_x.iter().chain(&fluid.velocity_y).map(|v| v.abs()).sum::<f32>()
+    };
+    let free = speed(0.0);
+    let damped = speed(1.0);
+    // Ten steps of dt = 0.1 at rate 1 keep about exp(-1) of the flow
+    assert!(damped > 0.0 && damped < 0.5 * free, "{} vs {}", damped, free);
+
+    let mut fluid = InteractiveFluid::new(8, 8);
+    fluid.velocity_damping = 0.4;
+    let params = itsliquid::SceneParams::capture(&fluid);
+    let mut restored = InteractiveFluid::new(8, 8);
+    params.apply(&mut restored);
+    assert_eq!(restored.velocity_damping, 0.4);
+}
+
+#[test]
+fn test_surface_tension_rounds_up_a_streak() {
+    // Spread of the red dye along x over its spread along y
+    let elongation = |tension: f32| {
+        let mut fluid = InteractiveFluid::new(48, 48);
+        fluid.surface_tension = tension;
+        for y in 21..27 {
+            for x in 12..36 {
+                fluid.dye_r[y * 48 + x] = 1.0;
+            }
+        }
+        for _ in 0..40 {
+            fluid.step();
+        }
+        let cells = (0..48 * 48).map(|i| ((i % 48) as f32, (i / 48) as f32, fluid.dye_r[i]));
+        let total: f32 = cells.clone().map(|(_, _, d)| d).sum();
+        let (mx, my) = cells.clone().fold((0.0, 0.0), |(sx, sy), (x, y, d)| (sx + x * d, sy + y * d));
+        let (mx, my) = (mx / total, my / total);
+        let spread = cells.fold((0.0, 0.0), |(sx, sy), (x, y, d)| {
+            (sx + (x - mx).powi(2) * d, sy + (y - my).powi(2) * d)
+        });
+        spread.0 / spread.1
+    };
+    let still = elongation(0.0);
+    let rounded = elongation(4.0);
+    assert!(rounded < 0.95 * still, "{} vs {}", rounded, still);
+
+    let mut fluid = InteractiveFluid::new(8, 8);
+    fluid.surface_tension = 1.5;
+    let mut restored = InteractiveFluid::new(8, 8);
+    itsliquid::SceneParams::capture(&fluid).apply(&mut restored);
+    assert_eq!(restored.surface_tension, 1.5);
+}
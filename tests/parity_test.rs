@@ -0,0 +1,95 @@
+use itsliquid::conformance;
+use itsliquid::parity::{self, FieldDiff, Tolerance, Tolerances};
+use itsliquid::InteractiveFluid;
+
+#[test]
+fn test_field_diff_measures_l2_and_linf() {
+    let reference = [1.0, 2.0, 3.0, 4.0];
+    let candidate = [1.0, 2.0, 3.0, 6.0];
+    let diff = FieldDiff::scalar(&reference, &candidate, 2);
+    assert_eq!(diff.linf, 2.0);
+    assert_eq!(diff.worst_cell, (1, 1));
+    // sqrt(4 / 4 cells)
+    assert!((diff.l2 - 1.0).abs() < 1e-6);
+    assert!((diff.relative_l2 - 1.0 / 7.5f32.sqrt()).abs() < 1e-6);
+    assert!(diff.within(Tolerance::new(1.0, 2.0)));
+    assert!(!diff.within(Tolerance::new(0.5, 2.0)));
+
+    let diff = FieldDiff::vector((&[0.0, 0.0], &[0.0, 0.0]), (&[3.0, 0.0], &[4.0, 0.0]), 2);
+    assert_eq!(diff.linf, 5.0);
+    assert_eq!(diff.relative_l2, 0.0);
+
+    // NaN is never within tolerance
+    let diff = FieldDiff::scalar(&[0.0], &[f32::NAN], 1);
+    assert!(!diff.within(Tolerance::new(f32::MAX, f32::MAX)));
+}
+
+#[test]
+fn test_identical_backends_match_exactly() {
+    let reports = parity::compare_all(
+        |w, h| Ok(InteractiveFluid::new(w, h)),
+        |w, h| Ok(InteractiveFluid::new(w, h)),
+        20,
+        Tolerances::exact(),
+    )
+    .unwrap();
+    assert_eq!(reports.len(), conformance::scenarios().len());
+    for (report, scenario) in reports.iter().zip(conformance::scenarios()) {
+        assert!(report.passed(), "{}", report.failures().join("\n"));
+        assert_eq!(report.samples.last().unwrap().step, scenario.steps);
+    }
+}
+
+#[test]
+fn test_divergence_is_reported() {
+    let scenario = conformance::scenarios().into_iter().find(|s| s.name == "jet").unwrap();
+    let mut reference = InteractiveFluid::new(scenario.width, scenario.height);
+    let mut candidate = InteractiveFluid::new(scenario.width, scenario.height);
+    candidate.dt *= 2.0;
+
+    let report = parity::compare(&mut reference, &mut candidate, &scenario, 50, Tolerances::default()).unwrap();
+    assert_eq!(report.samples.iter().map(|s| s.step).collect::<Vec<_>>(), vec![50, 100, 150]);
+    assert!(!report.passed());
+    assert!(!report.failures().is_empty());
+    assert!(report.summary().contains("DIVERGED"));
+    let (dye, velocity) = report.worst();
+    assert!(dye.linf > 0.0 && velocity.linf > 0.0);
+
+    // Grids that don't fit the scenario are an error
+    let mut small = InteractiveFluid::new(8, 8);
+    assert!(parity::compare(&mut reference, &mut small, &scenario, 10, Tolerances::default()).is_err());
+}
+
+#[cfg(feature = "f16-dye")]
+#[test]
+fn test_half_dye_stays_within_default_tolerances() {
+    let reports = parity::compare_all(
+        |w, h| Ok(InteractiveFluid::new(w, h)),
+        |w, h| Ok(itsliquid::HalfDyeFluid::new(w, h)),
+        25,
+        Tolerances::default(),
+    )
+    .unwrap();
+    for report in &reports {
+        assert!(report.passed(), "{}", report.failures().join("\n"));
+    }
+}
+
+// The GPU port is known to diverge, so this records how far rather than
+// failing on it; a non-finite or missing field still fails
+#[cfg(feature = "gpu")]
+#[test]
+fn test_gpu_parity_is_measured() {
+    let reports = match parity::compare_gpu(25, Tolerances::default()) {
+        Ok(reports) => reports,
+        Err(e) => {
+            eprintln!("Skipping GPU parity, no usable adapter: {}", e);
+            return;
+        }
+    };
+    for report in &reports {
+        println!("{}", report.summary());
+        let (dye, velocity) = report.worst();
+        assert!(dye.l2.is_finite() && velocity.l2.is_finite(), "{}", report.summary());
+    }
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::uniform_flow;
+use itsliquid::{InteractiveFluid, Integrator, ParticleSystem, VelocityGrid};
+
+// Solid-body rotation about the grid center with unit angular velocity
+fn rotation_grid(size: usize) -> VelocityGrid {
+    let c = size as f32 / 2.0;
+    let mut data = vec![0.0; size * size * 4];
+    for y in 0..size {
+        for x in 0..size {
+            let i = (y * size + x) * 4;
+            data[i] = -(y as f32 - c);
+            data[i + 1] = x as f32 - c;
+        }
+    }
+    VelocityGrid::from_rgba(size, size, &data, 0.1)
+}
+
+#[test]
+fn test_particles_follow_uniform_flow() {
+    let sim = uniform_flow(40, 2.0, -1.0);
+
+    for integrator in [Integrator::Euler, Integrator::Rk2, Integrator::Rk4] {
+        let mut particles = ParticleSystem::new(10);
+        particles.integrator = integrator;
+        particles.seed(10.0, 20.0, (1.0, 1.0, 1.0));
+
+        for _ in 0..10 {
+            particles.step(&sim);
+        }
+
+        // dt = 0.1 -> (0.2, -0.1) cells per step
+        let p = particles.particles[0];
+        assert!((p.x - 12.0).abs() < 1e-4, "{:?} x was {}", integrator, p.x);
+        assert!((p.y - 19.0).abs() < 1e-4, "{:?} y was {}", integrator, p.y);
+        assert!((p.age - 1.0).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_rk4_preserves_orbit_radius() {
+    let field = rotation_grid(64);
+    let radius_after = |integrator: Integrator| {
+        let mut particles = ParticleSystem::new(1);
+        particles.integrator = integrator;
+        particles.seed(42.0, 32.0, (1.0, 1.0, 1.0));
+        for _ in 0..100 {
+            particles.step(&field);
+        }
+        let p = particles.particles[0];
+        ((p.x - 32.0).powi(2) + (p.y - 32.0).powi(2)).sqrt()
+    };
+
+    let euler = radius_after(Integrator::Euler);
+    let rk2 = radius_after(Integrator::Rk2);
+    let rk4 = radius_after(Integrator::Rk4);
+
+    // Forward Euler spirals outward; higher orders stay on the circle
+    assert!(euler > 12.0, "euler radius {}", euler);
+    assert!((rk2 - 10.0).abs() < 0.1, "rk2 radius {}", rk2);
+    assert!((rk4 - 10.0).abs() < 1e-3, "rk4 radius {}", rk4);
+}
+
+#[test]
+fn test_particles_stay_inside_grid() {
+    let sim = uniform_flow(32, 50.0, 0.0);
+    let mut particles = ParticleSystem::new(100);
+    particles.seed_grid(32, 32, 4.0, (1.0, 0.0, 0.0));
+    assert!(!particles.is_empty());
+
+    for _ in 0..20 {
+        particles.step(&sim);
+    }
+
+    assert!(particles.positions().all(|p| p.x >= 0.5 && p.x <= 30.5 && p.y >= 0.5 && p.y <= 30.5));
+}
+
+#[test]
+fn test_lifetime_and_capacity_limits() {
+    let sim = InteractiveFluid::new(32, 32);
+    let mut particles = ParticleSystem::with_seed(50, 7);
+
+    particles.emit(16.0, 16.0, 4.0, 80, (0.0, 1.0, 0.0));
+    assert_eq!(particles.len(), 50);
+    assert!(particles.positions().all(|p| (p - glam::Vec2::new(16.0, 16.0)).length() <= 4.0 + 1e-4));
+
+    particles.lifetime = Some(0.5);
+    for _ in 0..5 {
+        particles.step(&sim);
+    }
+    assert_eq!(particles.len(), 50);
+
+    particles.step(&sim);
+    assert!(particles.is_empty());
+}
@@ -0,0 +1,81 @@
+use itsliquid::fluid_interactive::ADAPTIVE_PRESSURE_LIMIT;
+use itsliquid::InteractiveFluid;
+
+// A swirl plus a source, so the projection has something to remove
+fn stirred(size: usize) -> InteractiveFluid {
+    let mut fluid = InteractiveFluid::new(size, size);
+    let c = size as f32 / 2.0;
+    for y in 1..size - 1 {
+        for x in 1..size - 1 {
+            let (dx, dy) = (x as f32 - c, y as f32 - c);
+            let fade = (-(dx * dx + dy * dy) / (size as f32 * 2.0)).exp();
+            let idx = y * size + x;
+            fluid.velocity_x[idx] = (-dy + 0.8 * dx) * fade;
+            fluid.velocity_y[idx] = (dx + 0.8 * dy) * fade;
+        }
+    }
+    fluid
+}
+
+#[test]
+fn divergence_stats_of_uniform_expansion() {
+    let size = 16;
+    let mut fluid = InteractiveFluid::new(size, size);
+    for y in 0..size {
+        for x in 0..size {
+            fluid.velocity_x[y * size + x] = 0.5 * x as f32;
+        }
+    }
+    let stats = fluid.divergence_stats();
+    assert!((stats.max - 0.5).abs() < 1e-5, "{:?}", stats);
+    assert!((stats.mean - 0.5).abs() < 1e-5, "{:?}", stats);
+    assert_eq!(InteractiveFluid::new(size, size).divergence_stats().max, 0.0);
+}
+
+#[test]
+fn fixed_projection_reports_its_sweeps() {
+    let mut fluid = stirred(32);
+    let before = fluid.divergence_stats();
+    fluid.pressure_iterations = 10;
+    fluid.project_velocity();
+    assert!(fluid.pressure_iterations_used >= 1 && fluid.pressure_iterations_used <= 10);
+    let after = fluid.divergence_stats();
+    assert!(after.mean < before.mean, "{:?} {:?}", before, after);
+}
+
+#[test]
+fn adaptive_projection_sweeps_until_tolerance() {
+    let mut fixed = stirred(32);
+    fixed.pressure_iterations = 10;
+    fixed.project_velocity();
+    let fixed_divergence = fixed.divergence_stats().max;
+
+    let tolerance = fixed_divergence * 0.7;
+    let mut adaptive = stirred(32);
+    adaptive.pressure_iterations = 10;
+    adaptive.divergence_tolerance = Some(tolerance);
+    adaptive.project_velocity();
+    let divergence = adaptive.divergence_stats().max;
+    assert!(divergence <= tolerance, "{} > {}", divergence, tolerance);
+    assert!(adaptive.pressure_iterations_used > 10, "{}", adaptive.pressure_iterations_used);
+    assert_eq!(adaptive.pressure_iterations_used % 10, 0);
+}
+
+#[test]
+fn adaptive_projection_stops_at_first_check_when_loose() {
+    let mut fluid = stirred(32);
+    fluid.pressure_iterations = 10;
+    fluid.divergence_tolerance = Some(1e3);
+    fluid.project_velocity();
+    assert_eq!(fluid.pressure_iterations_used, 10);
+}
+
+#[test]
+fn adaptive_projection_is_bounded() {
+    let mut fluid = stirred(32);
+    fluid.pressure_iterations = 7;
+    fluid.divergence_tolerance = Some(0.0);
+    fluid.project_velocity();
+    assert!(fluid.pressure_iterations_used <= ADAPTIVE_PRESSURE_LIMIT);
+    assert!(fluid.velocity_x.iter().chain(&fluid.velocity_y).all(|v| v.is_finite()));
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::uniform_flow;
+use glam::Vec2;
+use itsliquid::render::trace_streamline;
+use itsliquid::{ImageExporter, InteractiveFluid, Mask, RenderMode, Renderer};
+
+// Mean absolute difference between neighbouring pixels along one axis
+fn neighbour_difference(img: &image::RgbImage, horizontal: bool) -> f32 {
+    let (w, h) = img.dimensions();
+    let mut total = 0.0;
+    let mut count = 0.0;
+    for y in 0..h - 1 {
+        for x in 0..w - 1 {
+            let (nx, ny) = if horizontal { (x + 1, y) } else { (x, y + 1) };
+            total += (img.get_pixel(x, y)[0] as f32 - img.get_pixel(nx, ny)[0] as f32).abs();
+            count += 1.0;
+        }
+    }
+    total / count
+}
+
+#[test]
+fn test_streamline_follows_uniform_flow() {
+    let sim = uniform_flow(32, 3.0, 0.0);
+    let line = trace_streamline(&sim, Vec2::new(16.0, 10.0), 10);
+
+    assert_eq!(line.len(), 21);
+    assert!(line.iter().all(|p| (p.y - 10.0).abs() < 1e-5));
+    // Upstream to downstream, in half-cell steps
+    for pair in line.windows(2) {
+        assert!((pair[1].x - pair[0].x - 0.5).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_streamline_stops_at_walls_and_still_fluid() {
+    let sim = uniform_flow(32, 3.0, 0.0);
+    let line = trace_streamline(&sim, Vec2::new(29.0, 10.0), 100);
+    assert!(line.iter().all(|p| p.x <= 30.5 && p.x >= 0.5));
+
+    let still = InteractiveFluid::new(32, 32);
+    assert_eq!(trace_streamline(&still, Vec2::new(16.0, 16.0), 100), vec![Vec2::new(16.0, 16.0)]);
+}
+
+#[test]
+fn test_streamlines_render_only_where_flow_exists() {
+    let renderer = Renderer::new(64, 64);
+
+    let still = InteractiveFluid::new(32, 32);
+    let img = renderer.render_interactive(&still, RenderMode::Streamlines);
+    assert!(img.pixels().all(|p| p.0 == [0, 0, 0]));
+
+    let sim = uniform_flow(32, 3.0, 0.0);
+    let img = renderer.render_interactive(&sim, RenderMode::Streamlines);
+    let lit = img.pixels().filter(|p| p.0 != [0, 0, 0]).count();
+    assert!(lit > 64, "only {} streamline pixels", lit);
+}
+
+#[test]
+fn test_lic_streaks_align_with_flow() {
+    let renderer = Renderer::new(96, 96);
+
+    let horizontal = renderer.render_interactive(&uniform_flow(48, 3.0, 0.0), RenderMode::Lic);
+    assert!(neighbour_difference(&horizontal, true) < neighbour_difference(&horizontal, false) * 0.5);
+
+    let vertical = renderer.render_interactive(&uniform_flow(48, 0.0, 3.0), RenderMode::Lic);
+    assert!(neighbour_difference(&vertical, false) < neighbour_difference(&vertical, true) * 0.5);
+}
+
+#[test]
+fn test_dye_mode_uses_reinhard_tone_mapping() {
+    let mut sim = InteractiveFluid::new(8, 8);
+    sim.dye_r[3 * 8 + 2] = 1.0;
+    sim.dye_b[3 * 8 + 2] = 3.0;
+
+    let img = Renderer::new(8, 8).render_interactive(&sim, RenderMode::Dye);
+    assert_eq!(img.get_pixel(2, 3).0, [127, 0, 191]);
+    assert_eq!(img.get_pixel(0, 0).0, [0, 0, 0]);
+}
+
+#[test]
+fn test_pressure_mode_uses_diverging_colormap() {
+    let mut sim = InteractiveFluid::new(8, 8);
+    sim.pressure[8 + 1] = -2.0;
+    sim.pressure[2 * 8 + 2] = 1.0;
+
+    let img = Renderer::new(8, 8).render_interactive(&sim, RenderMode::Pressure);
+    // Most negative is saturated blue, half of it is light red, zero is white
+    assert_eq!(img.get_pixel(1, 1).0, [0, 0, 255]);
+    assert_eq!(img.get_pixel(2, 2).0, [255, 127, 127]);
+    assert_eq!(img.get_pixel(5, 5).0, [255, 255, 255]);
+}
+
+#[test]
+fn test_canvas_png_upscales_with_obstacles() {
+    let mut sim = InteractiveFluid::new(8, 4);
+    sim.dye_r[2 * 8 + 5] = 1.0;
+    let mut obstacles = Mask::new(8, 4);
+    obstacles.cells[0] = true;
+    sim.obstacles = Some(obstacles);
+
+    let png = ImageExporter::new(32, 16).canvas_png(&sim, RenderMode::Dye).unwrap();
+    let img = image::load_from_memory(&png).unwrap().to_rgb8();
+    assert_eq!(img.dimensions(), (32, 16));
+    // Each cell is a 4x4 block
+    assert_eq!(img.get_pixel(21, 9).0, [127, 0, 0]);
+    assert_eq!(img.get_pixel(23, 11).0, [127, 0, 0]);
+    assert_eq!(img.get_pixel(3, 3).0, [90, 90, 90]);
+    assert_eq!(img.get_pixel(4, 0).0, [0, 0, 0]);
+}
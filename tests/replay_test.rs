@@ -0,0 +1,108 @@
+use glam::Vec2;
+use itsliquid::export::FluidData;
+use itsliquid::replay::Input;
+use itsliquid::{BlendMode, FluidSnapshot, InteractiveFluid, Recording, ReplayTarget, Scheme, Solver, SolverConfig};
+
+// Every kind of input, a settings change mid-run and inputs after the last step
+fn play_session(fluid: &mut InteractiveFluid) {
+    for frame in 0..40 {
+        let x = 8 + frame % 16;
+        fluid.add_dye(x, 12, (0.8, 0.3, 0.1));
+        fluid.add_force(x, 12, Vec2::new(3.0, 1.0), 3.0);
+        if frame % 5 == 0 {
+            fluid.add_heat(16, 20, 0.5, 2.0);
+            fluid.remove_dye(x, 12, 0.2);
+            fluid.blend_dye(x, 14, (0.2, 0.4, 0.6), 0.5, BlendMode::Over { opacity: 0.5 });
+            fluid.fade_dye(x + 1, 12, 0.5, true);
+        }
+        if frame == 20 {
+            fluid.dye_dissipation = 0.3;
+            fluid.add_attractor(16.0, 16.0, 8.0, 40.0);
+            fluid.absorb_dye(Vec2::new(16.0, 16.0), 8.0, 2.0);
+        }
+        fluid.step();
+    }
+    fluid.add_dye(4, 4, (1.0, 1.0, 1.0));
+}
+
+#[test]
+fn test_replay_reproduces_session_bitwise() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    // Recording starts from whatever the fields hold
+    fluid.add_dye(10, 10, (0.5, 0.5, 0.5));
+    fluid.step();
+    fluid.recording = Some(Recording::start(&fluid));
+    play_session(&mut fluid);
+    let recording = fluid.recording.take().unwrap();
+
+    assert_eq!(recording.frames, 40);
+    assert_eq!(recording.events_at(20).filter(|input| matches!(input, Input::Attractor { .. })).count(), 1);
+    // The dissipation change is logged once, before the step that used it
+    let param_changes = recording.events.iter().filter(|event| matches!(event.input, Input::Params(_))).count();
+    assert_eq!(param_changes, 1);
+
+    let mut replayed = InteractiveFluid::new(32, 32);
+    recording.replay(&mut replayed);
+    assert_eq!(FluidSnapshot::capture(&replayed), FluidSnapshot::capture(&fluid));
+    assert_eq!(replayed.dye_dissipation, 0.3);
+
+    assert_eq!(FluidSnapshot::capture(&recording.simulation()), recording.start);
+}
+
+#[test]
+fn test_restore_and_clear_are_recorded() {
+    let mut fluid = InteractiveFluid::new(24, 24);
+    fluid.recording = Some(Recording::start(&fluid));
+    fluid.add_dye(12, 12, (1.0, 0.0, 0.0));
+    let before = FluidSnapshot::capture(&fluid);
+    fluid.step();
+    fluid.clear();
+    fluid.step();
+    before.restore(&mut fluid).unwrap();
+    fluid.step();
+    fluid.clear_velocity();
+    fluid.step();
+    fluid.clear_dye();
+    let recording = fluid.recording.take().unwrap();
+
+    assert!(matches!(recording.events_at(1).next(), Some(Input::Clear)));
+    assert!(matches!(recording.events_at(2).next(), Some(Input::Restore(_))));
+    assert!(matches!(recording.events_at(3).next(), Some(Input::ClearVelocity)));
+    assert!(matches!(recording.events_at(4).next(), Some(Input::ClearDye)));
+
+    let mut replayed = recording.simulation();
+    recording.replay(&mut replayed);
+    assert_eq!(FluidSnapshot::capture(&replayed), FluidSnapshot::capture(&fluid));
+}
+
+#[test]
+fn test_replay_drives_other_solvers() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    fluid.recording = Some(Recording::start(&fluid));
+    play_session(&mut fluid);
+    let recording = fluid.recording.take().unwrap();
+
+    // The interactive scheme reproduces the session through `Solver` too
+    let mut solver = Solver::from_interactive(InteractiveFluid::new(32, 32));
+    recording.replay(&mut solver);
+    assert_eq!(FluidSnapshot::capture(solver.as_interactive().unwrap()), FluidSnapshot::capture(&fluid));
+
+    // Other schemes take the dye and forces they support
+    let mut stable = Solver::new(SolverConfig::new(Scheme::Stable), 32, 32).unwrap();
+    recording.replay(&mut stable);
+    let total: f32 = stable.density().iter().sum();
+    assert!(total.is_finite() && total > 0.0);
+
+    let mut other = Solver::new(SolverConfig::new(Scheme::Stable), 32, 32).unwrap();
+    other.apply_input(&Input::Attractor { x: 4.0, y: 4.0, radius: 3.0, strength: 1.0 });
+    assert!(other.density().iter().all(|&d| d == 0.0));
+}
+
+#[test]
+fn test_recording_rejects_newer_versions() {
+    let mut recording = Recording::start(&InteractiveFluid::new(8, 8));
+    recording.version = 99;
+    let json = recording.to_json().unwrap();
+    assert!(Recording::from_json(&json).is_err());
+    assert!(Recording::from_json("not json").is_err());
+}
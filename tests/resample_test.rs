@@ -0,0 +1,80 @@
+use itsliquid::{InteractiveFluid, PersistentElement, PersistentElementType, ScalarField};
+
+// Dye-weighted center of the red channel, as a fraction of the grid
+fn red_centroid(fluid: &InteractiveFluid) -> (f32, f32) {
+    let (mut total, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for (i, &v) in fluid.dye_r.iter().enumerate() {
+        total += v;
+        cx += v * ((i % fluid.width) as f32 + 0.5);
+        cy += v * ((i / fluid.width) as f32 + 0.5);
+    }
+    (cx / total / fluid.width as f32, cy / total / fluid.height as f32)
+}
+
+#[test]
+fn test_resampling_keeps_the_picture() {
+    let mut source = InteractiveFluid::new(40, 30);
+    for y in 8..14 {
+        for x in 24..32 {
+            source.add_dye(x, y, (1.0, 0.0, 0.5));
+        }
+    }
+    source.velocity_x.fill(2.0);
+    source.velocity_y.fill(-1.0);
+    let mut salt = ScalarField::new("salt", 40, 30);
+    salt.values.fill(0.25);
+    source.add_scalar(salt).unwrap();
+
+    let mut doubled = InteractiveFluid::new(80, 45);
+    doubled.resample_from(&source);
+
+    let (before, after) = (red_centroid(&source), red_centroid(&doubled));
+    assert!((before.0 - after.0).abs() < 0.01 && (before.1 - after.1).abs() < 0.01, "{:?} {:?}", before, after);
+    // Concentration, not mass, carries over
+    let peak = doubled.dye_r.iter().cloned().fold(0.0, f32::max);
+    assert!((peak - 1.0).abs() < 1e-5);
+    assert!(doubled.dye_b.iter().zip(&doubled.dye_r).all(|(b, r)| (b - r * 0.5).abs() < 1e-5));
+
+    // Cells per unit time: twice the cells across, twice the speed
+    let center = 22 * 80 + 40;
+    assert!((doubled.velocity_x[center] - 4.0).abs() < 1e-5);
+    assert!((doubled.velocity_y[center] + 1.5).abs() < 1e-5);
+
+    let salt = doubled.scalar("salt").unwrap();
+    assert_eq!((salt.width, salt.height, salt.values.len()), (80, 45, 80 * 45));
+    assert!(salt.values.iter().all(|&v| (v - 0.25).abs() < 1e-6));
+}
+
+#[test]
+fn test_shrinking_then_growing_stays_close() {
+    let mut source = InteractiveFluid::new(64, 64);
+    for i in 0..64 * 64 {
+        // A smooth gradient survives a round trip almost exactly
+        source.dye_g[i] = (i % 64) as f32 / 63.0;
+    }
+    let mut small = InteractiveFluid::new(32, 32);
+    small.resample_from(&source);
+    let mut back = InteractiveFluid::new(64, 64);
+    back.resample_from(&small);
+    let row = 32 * 64;
+    for x in 4..60 {
+        assert!((back.dye_g[row + x] - source.dye_g[row + x]).abs() < 0.03, "x={}", x);
+    }
+}
+
+#[test]
+fn test_elements_rescale_with_the_grid() {
+    let fan = PersistentElement {
+        element_type: PersistentElementType::FanStrip { span: (10.0, 4.0), velocity: (1.0, -2.0) },
+        x: 10.0,
+        y: 6.0,
+        radius: 3.0,
+        audio: None,
+    };
+    let scaled = fan.rescaled(2.0, 1.5);
+    assert_eq!((scaled.x, scaled.y, scaled.radius), (20.0, 9.0, 6.0));
+    assert_eq!(scaled.element_type, PersistentElementType::FanStrip { span: (20.0, 6.0), velocity: (2.0, -3.0) });
+
+    let heat = PersistentElement { element_type: PersistentElementType::HeatSource { amount: 2.0 }, ..fan };
+    assert_eq!(heat.rescaled(2.0, 2.0).element_type, heat.element_type);
+}
@@ -0,0 +1,83 @@
+use itsliquid::rheology::{shear_rates, VISCOSITY_FACTOR_RANGE};
+use itsliquid::{InteractiveFluid, PowerLaw, SceneParams};
+
+#[test]
+fn test_power_law_factors() {
+    let thinning = PowerLaw { index: 0.5, reference_shear: 1.0 };
+    assert!((thinning.factor(4.0) - 0.5).abs() < 1e-6);
+    // At rest it is as thick as allowed
+    assert_eq!(thinning.factor(0.0), VISCOSITY_FACTOR_RANGE.1);
+
+    let thickening = PowerLaw { index: 2.0, reference_shear: 1.0 };
+    assert!((thickening.factor(3.0) - 3.0).abs() < 1e-6);
+    assert_eq!(thickening.factor(0.0), VISCOSITY_FACTOR_RANGE.0);
+
+    assert_eq!(PowerLaw::default().factor(0.0), 1.0);
+    assert_eq!(PowerLaw::default().factor(42.0), 1.0);
+}
+
+#[test]
+fn test_shear_rate_of_a_simple_shear() {
+    let (w, h) = (8, 8);
+    let vx: Vec<f32> = (0..w * h).map(|i| 2.0 * (i / w) as f32).collect();
+    let rates = shear_rates(&vx, &vec![0.0; w * h], w, h, 1.0);
+    assert_eq!(rates[3 * w + 4], 2.0);
+    assert_eq!(rates[0], 0.0);
+}
+
+#[test]
+fn test_shear_thinning_keeps_a_jet_sharp() {
+    let peak = |model: Option<PowerLaw>| {
+        let mut fluid = InteractiveFluid::new(33, 33);
+        fluid.shear_model = model;
+        fluid.velocity_x[16 * 33 + 16] = 10.0;
+        fluid.velocity_x_prev.copy_from_slice(&fluid.velocity_x);
+        assert_eq!(fluid.viscosity_factors().is_some(), model.is_some());
+        fluid.diffuse_velocity();
+        fluid.velocity_x[16 * 33 + 16]
+    };
+    let newtonian = peak(None);
+    let thinning = peak(Some(PowerLaw { index: 0.4, reference_shear: 0.1 }));
+    let thickening = peak(Some(PowerLaw { index: 1.8, reference_shear: 0.1 }));
+    assert!(thinning > newtonian + 1.0, "{} vs {}", thinning, newtonian);
+    assert!(thickening < newtonian, "{} vs {}", thickening, newtonian);
+}
+
+#[test]
+fn test_scenes_keep_the_shear_model() {
+    let mut fluid = InteractiveFluid::new(8, 8);
+    let (_, viscosity, law) = PowerLaw::presets()[1];
+    fluid.viscosity = viscosity;
+    fluid.shear_model = Some(law);
+    let json = serde_json::to_string(&SceneParams::capture(&fluid)).unwrap();
+    let mut restored = InteractiveFluid::new(8, 8);
+    serde_json::from_str::<SceneParams>(&json).unwrap().apply(&mut restored);
+    assert_eq!(restored.shear_model, Some(law));
+    assert_eq!(restored.viscosity, viscosity);
+}
+
+#[test]
+fn test_painted_viscosity_thickens_a_region() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    assert!(fluid.viscosity_factors().is_none());
+    let brush = itsliquid::Brush { radius: 4.0, ..Default::default() };
+    fluid.paint_viscosity(&brush, 8.0, 16.0, 50.0);
+    assert_eq!(fluid.viscosity_field.len(), 32 * 32);
+    assert!(fluid.viscosity_field[16 * 32 + 8] > 10.0);
+    assert_eq!(fluid.viscosity_field[16 * 32 + 24], 1.0);
+    assert_eq!(fluid.viscosity_factors().unwrap(), fluid.viscosity_field);
+
+    // The same jet spreads faster in the thick region than the runny one
+    for x in [8, 24] {
+        fluid.velocity_x[16 * 32 + x] = 10.0;
+    }
+    fluid.velocity_x_prev.copy_from_slice(&fluid.velocity_x);
+    fluid.diffuse_velocity();
+    assert!(fluid.velocity_x[16 * 32 + 8] < fluid.velocity_x[16 * 32 + 24]);
+
+    // With a shear model too, the two factors multiply
+    fluid.shear_model = Some(PowerLaw { index: 0.5, reference_shear: 1.0 });
+    let factors = fluid.viscosity_factors().unwrap();
+    let shear = PowerLaw { index: 0.5, reference_shear: 1.0 }.factor(0.0);
+    assert_eq!(factors[0], shear * fluid.viscosity_field[0]);
+}
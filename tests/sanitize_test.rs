@@ -0,0 +1,78 @@
+use glam::Vec2;
+use itsliquid::solver::{Scheme, Solver, SolverConfig};
+use itsliquid::stress::{StressConfig, StressRun};
+use itsliquid::{FluidMetrics, InteractiveFluid, SanitizeEvents};
+use std::time::Duration;
+
+fn max_speed(fluid: &InteractiveFluid) -> f32 {
+    fluid.velocity_x.iter().zip(&fluid.velocity_y).map(|(x, y)| x.hypot(*y)).fold(0.0, f32::max)
+}
+
+#[test]
+fn test_non_finite_cells_are_reset() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    fluid.add_dye(16, 16, (1.0, 1.0, 1.0));
+    fluid.velocity_x[10 * 32 + 10] = f32::NAN;
+    fluid.velocity_y[12 * 32 + 12] = f32::INFINITY;
+    fluid.dye_g[20 * 32 + 5] = f32::NAN;
+    for _ in 0..5 {
+        fluid.step();
+    }
+    assert!(fluid.velocity_x.iter().chain(&fluid.velocity_y).all(|v| v.is_finite()));
+    assert!(fluid.dye_r.iter().chain(&fluid.dye_g).chain(&fluid.dye_b).all(|v| v.is_finite()));
+    assert_eq!(fluid.sanitize_events.reset, 3);
+    // The rest of the canvas survived
+    assert!(fluid.dye_r.iter().sum::<f32>() > 0.99);
+}
+
+#[test]
+fn test_runaway_forces_are_clamped() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    fluid.add_dye(16, 16, (1.0, 0.0, 0.0));
+    for _ in 0..10 {
+        fluid.add_force(16, 16, Vec2::new(1.0e30, -1.0e30), 4.0);
+        fluid.step();
+    }
+    assert!(max_speed(&fluid) <= fluid.max_velocity * 1.0001, "{}", max_speed(&fluid));
+    assert!(fluid.sanitize_events.clamped > 0);
+    assert!(fluid.dye_r.iter().all(|v| v.is_finite()));
+
+    // Lowering the limit takes effect on the next pass
+    fluid.max_velocity = 5.0;
+    let events = fluid.sanitize();
+    assert!(events.clamped > 0 && events.reset == 0);
+    assert!(max_speed(&fluid) <= 5.0 * 1.0001);
+}
+
+#[test]
+fn test_infinite_limit_only_resets() {
+    let mut fluid = InteractiveFluid::new(16, 16);
+    fluid.max_velocity = f32::INFINITY;
+    fluid.velocity_x[40] = 1.0e20;
+    fluid.temperature[41] = f32::NEG_INFINITY;
+    assert_eq!(fluid.sanitize(), SanitizeEvents { clamped: 0, reset: 1 });
+    assert_eq!(fluid.velocity_x[40], 1.0e20);
+    assert_eq!(fluid.temperature[41], fluid.ambient_temperature);
+    assert_eq!(fluid.sanitize_events.total(), 1);
+}
+
+#[test]
+fn test_events_reach_metrics_and_stress_checks() {
+    let mut solver = Solver::new(SolverConfig::new(Scheme::Interactive), 16, 16).unwrap();
+    solver.as_interactive_mut().unwrap().velocity_y[5 * 16 + 5] = f32::NAN;
+    solver.step();
+    let metrics = FluidMetrics::analyze(&solver, 1);
+    assert_eq!(metrics.sanitize_events.map(|e| e.reset), Some(1));
+
+    let mut run = StressRun::new(StressConfig {
+        seed: 0,
+        duration: Duration::from_secs(1),
+        max_frames: Some(0),
+        check_every: 1,
+        width: 16,
+        height: 16,
+    });
+    run.simulation.dye_b[20] = f32::NAN;
+    run.simulation.sanitize();
+    assert!(run.check().unwrap_err().contains("sanitize reset 1"));
+}
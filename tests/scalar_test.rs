@@ -0,0 +1,80 @@
+use glam::Vec2;
+use itsliquid::{Colormap, InteractiveFluid, RenderMode, Renderer, ScalarField};
+
+fn centroid_x(field: &ScalarField) -> f32 {
+    let weighted: f32 = field.values.iter().enumerate().map(|(i, v)| (i % field.width) as f32 * v).sum();
+    weighted / field.total()
+}
+
+#[test]
+fn test_scalars_are_carried_by_the_flow() {
+    let mut fluid = InteractiveFluid::new(40, 40);
+    let fuel = fluid.add_scalar(ScalarField::new("fuel", 40, 40)).unwrap();
+    fluid.scalars[fuel].add(12, 20, 1.0, 3.0);
+    let start = fluid.scalars[fuel].total();
+    let start_x = centroid_x(&fluid.scalars[fuel]);
+
+    for _ in 0..20 {
+        fluid.add_force(12, 20, Vec2::new(30.0, 0.0), 4.0);
+        fluid.step();
+    }
+    let fuel = fluid.scalar("fuel").unwrap();
+    assert!(centroid_x(fuel) > start_x + 2.0, "fuel stayed at {}", centroid_x(fuel));
+    // Closed walls keep all of it
+    assert!((fuel.total() - start).abs() < 1e-3 * start, "{} of {}", fuel.total(), start);
+    // The dye channels are untouched
+    assert!(fluid.dye_r.iter().all(|&v| v == 0.0));
+
+    fluid.clear();
+    assert_eq!(fluid.scalar("fuel").unwrap().total(), 0.0);
+}
+
+#[test]
+fn test_each_scalar_fades_at_its_own_rate() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    fluid.add_scalar(ScalarField::new("salinity", 32, 32)).unwrap();
+    fluid.add_scalar(ScalarField::new("smoke", 32, 32).with_dissipation(0.5)).unwrap();
+    for field in &mut fluid.scalars {
+        field.add(16, 16, 1.0, 2.0);
+    }
+    for _ in 0..10 {
+        fluid.step();
+    }
+    // Ten steps of dt = 0.1 at rate 0.5 keep exp(-0.5) of the smoke
+    let kept = fluid.scalar("salinity").unwrap().total();
+    let faded = fluid.scalar("smoke").unwrap().total();
+    assert!((faded / kept - (-0.5f32).exp()).abs() < 1e-3, "{} vs {}", faded, kept);
+}
+
+#[test]
+fn test_add_scalar_rejects_mismatches() {
+    let mut fluid = InteractiveFluid::new(16, 16);
+    assert_eq!(fluid.add_scalar(ScalarField::new("fuel", 16, 16)).unwrap(), 0);
+    assert!(fluid.add_scalar(ScalarField::new("fuel", 16, 16)).is_err());
+    assert!(fluid.add_scalar(ScalarField::new("salinity", 8, 16)).is_err());
+    assert!(fluid.scalar("salinity").is_none());
+    fluid.scalar_mut("fuel").unwrap().range = 2.0;
+    assert_eq!(fluid.scalars[0].range, 2.0);
+}
+
+#[test]
+fn test_renderer_selects_or_composites_scalars() {
+    let mut fluid = InteractiveFluid::new(8, 8);
+    let red = fluid.add_scalar(ScalarField::new("a", 8, 8).with_colormap(Colormap::Custom(vec![[255, 0, 0]]))).unwrap();
+    let blue = fluid.add_scalar(ScalarField::new("b", 8, 8).with_colormap(Colormap::Custom(vec![[0, 0, 255]]))).unwrap();
+    fluid.scalars[red].values[3 * 8 + 3] = 1.0;
+    fluid.scalars[blue].values[3 * 8 + 3] = 0.5;
+    fluid.scalars[blue].values[5 * 8 + 5] = 2.0;
+
+    let renderer = Renderer::new(8, 8);
+    let one = renderer.render_interactive(&fluid, RenderMode::Scalar(blue));
+    assert_eq!(one.get_pixel(5, 5).0, [0, 0, 255]);
+
+    let both = renderer.render_interactive(&fluid, RenderMode::Scalars);
+    assert_eq!(both.get_pixel(3, 3).0, [255, 0, 127]);
+    assert_eq!(both.get_pixel(5, 5).0, [0, 0, 255]);
+    assert_eq!(both.get_pixel(0, 0).0, [0, 0, 0]);
+
+    let missing = renderer.render_interactive(&fluid, RenderMode::Scalar(9));
+    assert!(missing.pixels().all(|p| p.0 == [0, 0, 0]));
+}
@@ -0,0 +1,80 @@
+use itsliquid::scenarios::dam_break_tank;
+use itsliquid::{BoundaryMode, InteractiveFluid, Scenario};
+
+// Center of the red and blue dye, in cells
+fn dye_center(sim: &InteractiveFluid) -> (f32, f32) {
+    let (mut total, mut x, mut y) = (0.0, 0.0, 0.0);
+    for (i, (r, b)) in sim.dye_r.iter().zip(&sim.dye_b).enumerate() {
+        total += r + b;
+        x += (i % sim.width) as f32 * (r + b);
+        y += (i / sim.width) as f32 * (r + b);
+    }
+    (x / total, y / total)
+}
+
+fn speed(sim: &InteractiveFluid) -> f32 {
+    sim.velocity_x.iter().zip(&sim.velocity_y).map(|(u, v)| u.hypot(*v)).fold(0.0, f32::max)
+}
+
+#[test]
+fn test_every_scenario_starts_moving() {
+    for scenario in Scenario::ALL {
+        let mut sim = InteractiveFluid::new(64, 48);
+        sim.add_dye(5, 5, (0.0, 1.0, 0.0));
+        scenario.setup(&mut sim);
+        assert_eq!(sim.dye_g.iter().sum::<f32>(), 0.0, "{}", scenario.name());
+        assert!(sim.dye_r.iter().sum::<f32>() > 0.0, "{}", scenario.name());
+        for _ in 0..10 {
+            sim.step();
+        }
+        assert!(speed(&sim) > 0.0, "{}", scenario.name());
+        assert!(sim.velocity_x.iter().chain(&sim.dye_r).all(|v| v.is_finite()), "{}", scenario.name());
+    }
+}
+
+#[test]
+fn test_shear_layer_streams_oppose() {
+    let mut sim = InteractiveFluid::new(64, 64);
+    Scenario::ShearLayer.setup(&mut sim);
+    assert_eq!(sim.boundary, BoundaryMode::Periodic);
+    assert!(sim.velocity_x[32 * 64 + 10] > 0.0 && sim.velocity_x[2 * 64 + 10] < 0.0);
+    assert!(sim.dye_r[32 * 64 + 10] > 0.99 && sim.dye_b[2 * 64 + 10] > 0.99);
+    // Heavy scenarios set their own dye weights, the others clear them
+    Scenario::RayleighTaylor.setup(&mut sim);
+    assert!(sim.dye_density[0] > 0.0 && sim.dye_density[2] < 0.0);
+    Scenario::VortexPair.setup(&mut sim);
+    assert_eq!(sim.dye_density, [0.0; 3]);
+}
+
+#[test]
+fn test_vortex_pair_swims_up() {
+    let mut sim = InteractiveFluid::new(64, 64);
+    Scenario::VortexPair.setup(&mut sim);
+    // Between the two cores the flow runs up the screen
+    let between = (0.7 * 64.0) as usize * 64 + 32;
+    assert!(sim.velocity_y[between] < 0.0);
+    assert!(sim.velocity_x[between].abs() < 1e-4);
+    let (_, start) = dye_center(&sim);
+    for _ in 0..20 {
+        sim.step();
+    }
+    let (_, end) = dye_center(&sim);
+    assert!(end < start - 1.0, "{} -> {}", start, end);
+}
+
+#[test]
+fn test_dam_break_spreads_across_the_floor() {
+    let mut sim = InteractiveFluid::new(64, 48);
+    Scenario::DamBreak.setup(&mut sim);
+    let (start, _) = dye_center(&sim);
+    for _ in 0..40 {
+        sim.step();
+    }
+    let (end, _) = dye_center(&sim);
+    assert!(end > start + 0.5, "{} -> {}", start, end);
+
+    let tank = dam_break_tank(40, 30);
+    assert!(tank.is_liquid(3, 27));
+    assert!(!tank.is_liquid(3, 3));
+    assert!(!tank.is_liquid(30, 27));
+}
@@ -0,0 +1,379 @@
+use glam::Vec2;
+use itsliquid::{BlendMode, ConfigurableSimulation, Emission, Falloff, InteractiveFluid, Param, PersistentElement, PersistentElementType, Scene, SceneParams};
+
+fn elements() -> Vec<PersistentElement> {
+    vec![
+        PersistentElement {
+            element_type: PersistentElementType::DyeSource {
+                color: (1.0, 0.2, 0.0),
+                intensity: 2.0,
+                blend: BlendMode::Add,
+                emission: Emission::default(),
+            },
+            x: 10.0,
+            y: 20.0,
+            radius: 3.0,
+            audio: None,
+        },
+        PersistentElement {
+            element_type: PersistentElementType::ForceSource { direction: (16.0, -8.0), intensity: 0.5 },
+            x: 30.0,
+            y: 5.0,
+            radius: 3.0,
+            audio: None,
+        },
+        PersistentElement {
+            element_type: PersistentElementType::AttractorSource { strength: 20.0, absorb: false },
+            x: 20.0,
+            y: 20.0,
+            radius: 8.0,
+            audio: None,
+        },
+        PersistentElement {
+            element_type: PersistentElementType::RepulsorSource { strength: 10.0 },
+            x: 8.0,
+            y: 8.0,
+            radius: 6.0,
+            audio: None,
+        },
+        PersistentElement {
+            element_type: PersistentElementType::VortexSource { strength: -3.0, falloff: Falloff::Gaussian },
+            x: 25.0,
+            y: 12.0,
+            radius: 10.0,
+            audio: None,
+        },
+        PersistentElement {
+            element_type: PersistentElementType::FanStrip { span: (10.0, 0.0), velocity: (0.0, -5.0) },
+            x: 20.0,
+            y: 25.0,
+            radius: 2.0,
+            audio: None,
+        },
+    ]
+}
+
+#[test]
+fn test_scene_rebuilds_simulation() {
+    let mut fluid = InteractiveFluid::new(40, 30);
+    fluid.set_param(Param::Viscosity, 0.02).unwrap();
+    fluid.pressure_iterations = 7;
+    fluid.add_dye(12, 12, (1.0, 1.0, 0.5));
+
+    let scene = Scene::capture(&fluid, &elements(), true);
+    scene.validate().unwrap();
+    let rebuilt = scene.simulation();
+    assert_eq!((rebuilt.width, rebuilt.height), (40, 30));
+    assert_eq!(SceneParams::capture(&rebuilt), SceneParams::capture(&fluid));
+    assert_eq!(rebuilt.dye_r, fluid.dye_r);
+
+    // Without dye the rebuilt grid starts empty
+    let scene = Scene::capture(&fluid, &elements(), false);
+    assert!(scene.simulation().dye_r.iter().all(|&v| v == 0.0));
+}
+
+#[test]
+fn test_invalid_scenes_are_rejected() {
+    let fluid = InteractiveFluid::new(20, 20);
+    let mut scene = Scene::capture(&fluid, &[], true);
+    scene.version += 1;
+    assert!(scene.validate().is_err());
+
+    let mut scene = Scene::capture(&fluid, &[], true);
+    scene.dye.as_mut().unwrap().g.pop();
+    assert!(scene.validate().is_err());
+}
+
+#[test]
+fn test_elements_follow_grid_size() {
+    let scene = Scene::capture(&InteractiveFluid::new(40, 30), &elements(), false);
+    let scaled = scene.elements_for(80, 60);
+    assert_eq!((scaled[0].x, scaled[0].y, scaled[0].radius), (20.0, 40.0, 6.0));
+    assert_eq!(scaled[1].element_type, elements()[1].element_type);
+}
+
+#[test]
+fn test_elements_drive_the_fluid() {
+    let mut fluid = InteractiveFluid::new(40, 30);
+    for _ in 0..5 {
+        for elem in &elements() {
+            elem.apply(&mut fluid, None);
+        }
+        fluid.step();
+    }
+    assert!(fluid.dye_r.iter().sum::<f32>() > 0.0);
+    assert!(fluid.dye_b.iter().all(|&v| v == 0.0));
+    assert!(fluid.velocity_x.iter().any(|&v| v != 0.0));
+}
+
+#[test]
+fn test_scene_round_trips() {
+    let mut fluid = InteractiveFluid::new(40, 30);
+    fluid.add_dye(5, 5, (0.3, 0.0, 1.0));
+    let scene = Scene::capture(&fluid, &elements(), true);
+    assert_eq!(Scene::from_json(&scene.to_json().unwrap()).unwrap(), scene);
+
+    // Links drop the dye and keep positions to within rounding
+    let linked = Scene::from_link(&format!("#{}", scene.to_link())).unwrap();
+    assert!(linked.dye.is_none());
+    assert_eq!(linked.params, scene.params);
+    for (a, b) in linked.elements.iter().zip(&scene.elements) {
+        assert!((a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3 && (a.radius - b.radius).abs() < 1e-3);
+        assert_eq!(a.element_type, b.element_type);
+    }
+}
+
+#[test]
+fn test_repulsor_pushes_outward() {
+    let mut fluid = InteractiveFluid::new(30, 30);
+    fluid.add_repulsor(15.0, 15.0, 10.0, 20.0);
+    // Right of the center flows right, above it flows up
+    assert!(fluid.velocity_x[15 * 30 + 20] > 0.0);
+    assert!(fluid.velocity_y[10 * 30 + 15] < 0.0);
+
+    let mut attracted = InteractiveFluid::new(30, 30);
+    attracted.add_attractor(15.0, 15.0, 10.0, 20.0);
+    assert_eq!(attracted.velocity_x[15 * 30 + 20], -fluid.velocity_x[15 * 30 + 20]);
+}
+
+#[test]
+fn test_attractor_can_absorb_dye() {
+    let attractor = |absorb| PersistentElement {
+        element_type: PersistentElementType::AttractorSource { strength: 20.0, absorb },
+        x: 15.0,
+        y: 15.0,
+        radius: 10.0,
+        audio: None,
+    };
+    let held = |absorb| {
+        let mut fluid = InteractiveFluid::new(30, 30);
+        fluid.add_dye(15, 15, (1.0, 1.0, 1.0));
+        fluid.add_dye(25, 15, (1.0, 1.0, 1.0));
+        attractor(absorb).apply(&mut fluid, None);
+        fluid
+    };
+    let kept = held(false);
+    assert_eq!(total_dye(&kept), [2.0; 3]);
+    // Only the dye at the center goes
+    let absorbed = held(true);
+    assert!(absorbed.dye_r[15 * 30 + 15] < 1.0);
+    assert_eq!(absorbed.dye_r[15 * 30 + 25], 1.0);
+    assert_eq!(absorbed.velocity_x, kept.velocity_x);
+
+    let scene = Scene::capture(&absorbed, &[attractor(true)], false);
+    assert_eq!(Scene::from_link(&scene.to_link()).unwrap().elements[0].element_type, attractor(true).element_type);
+}
+
+#[test]
+fn test_radial_force_stays_within_its_radius() {
+    let mut fluid = InteractiveFluid::new(30, 30);
+    fluid.apply_radial_force(Vec2::new(15.0, 15.0), 5.0, 20.0);
+    assert!(fluid.velocity_x[15 * 30 + 18] < 0.0);
+    assert_eq!(fluid.velocity_x[15 * 30 + 21], 0.0);
+    assert_eq!(fluid.velocity_x[15 * 30 + 9], 0.0);
+}
+
+#[test]
+fn test_vortex_spin_direction() {
+    let mut fluid = InteractiveFluid::new(30, 30);
+    fluid.add_vortex(15.0, 15.0, 10.0, 2.0, Falloff::Linear);
+    // Clockwise on screen: right of the center flows down, above it flows right
+    assert!(fluid.velocity_y[15 * 30 + 20] > 0.0);
+    assert!(fluid.velocity_x[10 * 30 + 15] > 0.0);
+    // Tangential only, no pull toward the center
+    assert_eq!(fluid.velocity_x[15 * 30 + 20], 0.0);
+    // Nothing beyond the radius
+    assert_eq!(fluid.velocity_y[15 * 30 + 27], 0.0);
+
+    let mut reversed = InteractiveFluid::new(30, 30);
+    reversed.add_vortex(15.0, 15.0, 10.0, -2.0, Falloff::Linear);
+    assert_eq!(reversed.velocity_y[15 * 30 + 20], -fluid.velocity_y[15 * 30 + 20]);
+
+    // The falloff shapes the speed between the core and the edge
+    let mut soft = InteractiveFluid::new(30, 30);
+    soft.add_vortex(15.0, 15.0, 10.0, 2.0, Falloff::Gaussian);
+    assert!(soft.velocity_y[15 * 30 + 20] != fluid.velocity_y[15 * 30 + 20]);
+}
+
+#[test]
+fn test_fan_strip_holds_velocity() {
+    let mut fluid = InteractiveFluid::new(30, 30);
+    fluid.velocity_x[10 * 30 + 15] = 50.0;
+    fluid.add_fan(Vec2::new(10.0, 10.0), Vec2::new(20.0, 10.0), 1.0, Vec2::new(0.0, 4.0));
+    // Every cell in the strip is set, not added to
+    for x in 10..=20 {
+        assert_eq!((fluid.velocity_x[10 * 30 + x], fluid.velocity_y[10 * 30 + x]), (0.0, 4.0));
+        assert_eq!(fluid.velocity_y[11 * 30 + x], 4.0);
+    }
+    // Past the half-width and beyond the ends are untouched
+    assert_eq!(fluid.velocity_y[12 * 30 + 15], 0.0);
+    assert_eq!(fluid.velocity_y[10 * 30 + 22], 0.0);
+
+    // The element drives a steady current through the solver
+    let fan = PersistentElement {
+        element_type: PersistentElementType::FanStrip { span: (10.0, 0.0), velocity: (0.0, 4.0) },
+        x: 15.0,
+        y: 10.0,
+        radius: 1.0,
+        audio: None,
+    };
+    let mut fluid = InteractiveFluid::new(30, 30);
+    for _ in 0..20 {
+        fan.apply(&mut fluid, None);
+        fluid.step();
+    }
+    assert!(fluid.velocity_y[13 * 30 + 15] > 0.0);
+}
+
+#[test]
+fn test_dye_source_blend_modes() {
+    let source = |color, blend| PersistentElement {
+        element_type: PersistentElementType::DyeSource { color, intensity: 1.0, blend, emission: Emission::default() },
+        x: 10.0,
+        y: 10.0,
+        radius: 2.0,
+        audio: None,
+    };
+    let mut fluid = InteractiveFluid::new(20, 20);
+    fluid.dye_r.fill(2.0);
+    fluid.dye_b.fill(2.0);
+
+    // Replace sets the whole small area, leaving the rest
+    source((0.0, 1.0, 0.0), BlendMode::Replace).apply(&mut fluid, None);
+    let center = 10 * 20 + 10;
+    assert_eq!((fluid.dye_r[center], fluid.dye_g[center], fluid.dye_b[center]), (0.0, 1.0, 0.0));
+    assert_eq!(fluid.dye_r[10 * 20 + 11], 0.0);
+    assert_eq!(fluid.dye_r[10 * 20 + 12], 2.0);
+
+    // Multiplying by black darkens without removing a fixed amount
+    let mut fluid = InteractiveFluid::new(20, 20);
+    fluid.dye_b.fill(2.0);
+    source((0.0, 0.0, 0.0), BlendMode::Multiply).apply(&mut fluid, None);
+    assert!((fluid.dye_b[center] - 1.4).abs() < 1e-6);
+
+    // Other modes survive files and links; adding stays out of both
+    let elements = [source((0.5, 0.5, 0.5), BlendMode::Over { opacity: 0.25 }), source((1.0, 0.0, 0.0), BlendMode::Add)];
+    let scene = Scene::capture(&fluid, &elements, false);
+    assert_eq!(Scene::from_json(&scene.to_json().unwrap()).unwrap().elements, scene.elements);
+    let linked = Scene::from_link(&scene.to_link()).unwrap();
+    assert_eq!(linked.elements[0].element_type, elements[0].element_type);
+    assert!(!scene.to_json().unwrap().contains("\"Add\""));
+}
+
+// Dye in each channel over the whole grid
+fn total_dye(fluid: &InteractiveFluid) -> [f32; 3] {
+    [&fluid.dye_r, &fluid.dye_g, &fluid.dye_b].map(|channel| channel.iter().sum())
+}
+
+fn dye_source(emission: Emission) -> PersistentElement {
+    PersistentElement {
+        element_type: PersistentElementType::DyeSource { color: (1.0, 0.0, 0.0), intensity: 1.0, blend: BlendMode::Add, emission },
+        x: 8.0,
+        y: 8.0,
+        radius: 3.0,
+        audio: None,
+    }
+}
+
+// Red dye a source adds over `steps` steps of `dt`, without moving the fluid
+fn emitted(mut elem: PersistentElement, dt: f32, steps: usize) -> f32 {
+    let mut fluid = InteractiveFluid::new(16, 16);
+    fluid.dt = dt;
+    for _ in 0..steps {
+        elem.apply(&mut fluid, None);
+        elem.advance(dt);
+    }
+    total_dye(&fluid)[0]
+}
+
+#[test]
+fn test_steady_sources_emit_full_intensity_every_step() {
+    let steady = Emission::default();
+    assert!(steady.is_steady());
+    assert_eq!(steady.scale(0.1), 1.0);
+    assert!((emitted(dye_source(steady), 0.1, 10) - 10.0).abs() < 1e-4);
+    assert!((emitted(dye_source(steady), 0.05, 10) - 10.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_emission_rate_ignores_the_timestep() {
+    let emission = Emission { rate: Some(3.0), ..Emission::default() };
+    let coarse = emitted(dye_source(emission), 0.1, 10);
+    let fine = emitted(dye_source(emission), 0.025, 40);
+    assert!((coarse - 3.0).abs() < 1e-3, "{}", coarse);
+    assert!((fine - 3.0).abs() < 1e-3, "{}", fine);
+}
+
+#[test]
+fn test_pulses_follow_the_duty_cycle() {
+    let emission = Emission { rate: Some(1.0), period: 2.0, duty: 0.25, ..Emission::default() };
+    assert_eq!(Emission { age: 1.0, ..emission }.scale(0.1), 0.0);
+    assert!((Emission { age: 0.2, ..emission }.scale(0.1) - 0.1).abs() < 1e-6);
+    // A step straddling the end of the on part only counts the on part
+    assert!((Emission { age: 0.45, ..emission }.emitting_time(0.1) - 0.05).abs() < 1e-5);
+    // Four cycles, each on for half a unit of time
+    let total = emitted(dye_source(emission), 0.05, 160);
+    assert!((total - 2.0).abs() < 1e-2, "{}", total);
+}
+
+#[test]
+fn test_sources_expire_after_their_lifetime() {
+    let mut elem = dye_source(Emission { lifetime: Some(1.0), ..Emission::default() });
+    assert!(!elem.expired());
+    // Ten full steps, then nothing
+    assert!((emitted(elem, 0.1, 15) - 10.0).abs() < 1e-3);
+    elem.advance(1.05);
+    assert!(elem.expired());
+    let mut fluid = InteractiveFluid::new(16, 16);
+    elem.apply(&mut fluid, None);
+    assert_eq!(total_dye(&fluid), [0.0; 3]);
+    // Other elements never expire
+    let mut force = elements()[1];
+    force.advance(100.0);
+    assert!(!force.expired());
+}
+
+#[test]
+fn test_emission_round_trips_through_scenes_and_links() {
+    let fluid = InteractiveFluid::new(40, 30);
+    let emission = Emission { rate: Some(5.0), period: 3.0, duty: 0.4, lifetime: Some(20.0), age: 2.5 };
+    let scene = Scene::capture(&fluid, &[dye_source(emission)], false);
+    assert_eq!(Scene::from_json(&scene.to_json().unwrap()).unwrap().elements[0], dye_source(emission));
+    let linked = Scene::from_link(&scene.to_link()).unwrap();
+    assert!(matches!(
+        linked.elements[0].element_type,
+        PersistentElementType::DyeSource { emission: e, .. } if e == emission
+    ));
+
+    // Steady sources are saved as before
+    let steady = Scene::capture(&fluid, &elements(), false).to_json().unwrap();
+    assert!(!steady.contains("emission"));
+}
+
+#[test]
+fn test_forces_split_evenly_across_substeps() {
+    let force = elements()[1];
+    assert!(force.is_force());
+    let push = |dt: f32, substeps: usize| {
+        let mut fluid = InteractiveFluid::new(40, 30);
+        fluid.dt = dt;
+        for _ in 0..substeps {
+            force.apply_force(&mut fluid);
+        }
+        fluid.velocity_x.iter().sum::<f32>()
+    };
+    let whole = push(0.1, 1);
+    assert!(whole > 0.0);
+    assert!((push(0.025, 4) - whole).abs() < 1e-3 * whole);
+    // Twice the step, twice the push
+    assert!((push(0.2, 1) - 2.0 * whole).abs() < 1e-3 * whole);
+
+    // Dye sources only inject in `apply_source`
+    let source = elements()[0];
+    assert!(!source.is_force());
+    let mut fluid = InteractiveFluid::new(40, 30);
+    source.apply_force(&mut fluid);
+    assert_eq!(total_dye(&fluid), [0.0; 3]);
+    source.apply_source(&mut fluid, None);
+    assert!(total_dye(&fluid)[0] > 0.0);
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "scripting")]
+
+use itsliquid::script::Script;
+use itsliquid::{InteractiveFluid, PersistentElement, PersistentElementType};
+
+#[test]
+fn test_setup_places_elements_and_paints() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    let mut elements = Vec::new();
+    let mut script = Script::compile(
+        r#"
+        add_dye(width() / 2, height() / 2, 1.0, 0.5, 0);
+        place_element(#{
+            element_type: #{ type: "AttractorSource", strength: 12.0 },
+            x: 8, y: 9.5, radius: 4,
+        });
+        set_viscosity(0.002);
+        print(`placed in ${width()}x${height()}`);
+        "#,
+    )
+    .unwrap();
+    script.setup(&mut fluid, &mut elements).unwrap();
+
+    assert_eq!(fluid.dye_r[16 * 32 + 16], 1.0);
+    assert_eq!(fluid.viscosity, 0.002);
+    assert_eq!(
+        elements,
+        vec![PersistentElement {
+            element_type: PersistentElementType::AttractorSource { strength: 12.0, absorb: false },
+            x: 8.0,
+            y: 9.5,
+            radius: 4.0,
+            audio: None,
+        }]
+    );
+    assert_eq!(script.take_output(), vec!["placed in 32x32".to_string()]);
+    assert!(script.take_output().is_empty());
+    assert!(!script.animates());
+}
+
+#[test]
+fn test_frame_runs_each_step_with_count() {
+    let mut fluid = InteractiveFluid::new(16, 16);
+    let mut elements = Vec::new();
+    let mut script = Script::compile("fn frame(n) { add_dye(n, 0, 1, 0, 0); }").unwrap();
+    script.setup(&mut fluid, &mut elements).unwrap();
+    assert!(script.animates());
+    for _ in 0..3 {
+        script.run_frame(&mut fluid, &mut elements).unwrap();
+    }
+    assert_eq!(script.frame, 3);
+    assert_eq!(&fluid.dye_r[..4], &[1.0, 1.0, 1.0, 0.0]);
+}
+
+#[test]
+fn test_errors_are_reported() {
+    assert!(Script::compile("fn frame(n) {").is_err());
+
+    let mut fluid = InteractiveFluid::new(16, 16);
+    let mut elements = Vec::new();
+    let dt = fluid.dt;
+    let mut script = Script::compile("add_dye(1, 1, 1, 1, 1); set_dt(-1.0);").unwrap();
+    assert!(script.setup(&mut fluid, &mut elements).is_err());
+    assert_eq!(fluid.dt, dt);
+    // Calls before the error still happened
+    assert_eq!(fluid.dye_r[16 + 1], 1.0);
+
+    let mut script = Script::compile(r#"place_element(#{ element_type: #{ type: "Nope" }, x: 0, y: 0, radius: 1 });"#).unwrap();
+    assert!(script.setup(&mut fluid, &mut elements).is_err());
+    assert!(elements.is_empty());
+
+    let mut script = Script::compile("loop {}").unwrap();
+    assert!(script.setup(&mut fluid, &mut elements).is_err());
+}
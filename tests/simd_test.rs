@@ -0,0 +1,58 @@
+#![cfg(feature = "simd")]
+
+use itsliquid::kernels;
+
+// Widths around multiples of the lane count, so the scalar tail is covered
+const WIDTHS: [usize; 4] = [9, 10, 17, 37];
+const HEIGHT: usize = 12;
+
+// Deterministic noise in [-scale, scale)
+fn noise(len: usize, seed: u32, scale: f32) -> Vec<f32> {
+    let mut state = seed.wrapping_mul(2_654_435_761).wrapping_add(1);
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * scale
+        })
+        .collect()
+}
+
+#[test]
+fn test_relax_row_matches_scalar() {
+    for width in WIDTHS {
+        let snapshot = noise(width * HEIGHT, 1, 1.0);
+        let source = noise(width * HEIGHT, 2, 0.5);
+        for parity in 0..2 {
+            for y in 1..HEIGHT - 1 {
+                let mut scalar = snapshot[y * width..(y + 1) * width].to_vec();
+                let mut simd = scalar.clone();
+                // Unequal weights, as on a grid of tall cells
+                let a = kernels::relax_row_scalar(&mut scalar, &snapshot, &source, y, width, [0.7, 0.3], 3.0, parity);
+                let b = kernels::relax_row_simd(&mut simd, &snapshot, &source, y, width, [0.7, 0.3], 3.0, parity);
+                assert_eq!(scalar, simd, "width {} row {} parity {}", width, y, parity);
+                assert_eq!(a.to_bits(), b.to_bits(), "max change, width {} row {}", width, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_advect_row_matches_scalar() {
+    for width in WIDTHS {
+        // Large velocities so some backtraces hit the clamp
+        let vx = noise(width * HEIGHT, 3, 40.0);
+        let vy = noise(width * HEIGHT, 4, 40.0);
+        let r = noise(width * HEIGHT, 5, 1.0);
+        let g = noise(width * HEIGHT, 6, 1.0);
+        for (y, dt) in (1..HEIGHT - 1).zip([[0.1, 0.1], [0.1, 0.05]].into_iter().cycle()) {
+            let (mut scalar_r, mut scalar_g) = (vec![0.0; width], vec![0.0; width]);
+            let (mut simd_r, mut simd_g) = (vec![0.0; width], vec![0.0; width]);
+            kernels::advect_row_scalar([&mut scalar_r, &mut scalar_g], [&r, &g], &vx, &vy, y, width, HEIGHT, dt);
+            kernels::advect_row_simd([&mut simd_r, &mut simd_g], [&r, &g], &vx, &vy, y, width, HEIGHT, dt);
+            assert_eq!(scalar_r, simd_r, "width {} row {}", width, y);
+            assert_eq!(scalar_g, simd_g, "width {} row {}", width, y);
+        }
+    }
+}
@@ -222,3 +222,41 @@ async fn test_gpu_simulation_force_application() {
         "GPU: Dye should exist after force application"
     );
 }
+
+#[test]
+fn test_fade_dye_clears_cell_and_optionally_velocity() {
+    let mut sim = InteractiveFluid::new(16, 16);
+    sim.add_dye(8, 8, (2.0, 1.0, 0.5));
+    sim.velocity_x.fill(1.0);
+    let idx = 8 * sim.width + 8;
+
+    sim.fade_dye(8, 8, 0.5, false);
+    assert_eq!((sim.dye_r[idx], sim.dye_g[idx], sim.dye_b[idx]), (1.0, 0.5, 0.25));
+    assert_eq!(sim.velocity_x[idx], 1.0);
+
+    sim.fade_dye(8, 8, 1.0, true);
+    assert_eq!((sim.dye_r[idx], sim.dye_g[idx], sim.dye_b[idx]), (0.0, 0.0, 0.0));
+    assert_eq!(sim.velocity_x[idx], 0.0);
+    assert_eq!(sim.velocity_x[idx + 1], 1.0);
+
+    // Off the grid does nothing
+    sim.fade_dye(16, 3, 1.0, true);
+}
+
+#[test]
+fn test_clear_dye_and_velocity_separately() {
+    let mut sim = InteractiveFluid::new(16, 16);
+    sim.add_dye(8, 8, (1.0, 0.5, 0.0));
+    sim.velocity_y.fill(-2.0);
+    sim.temperature.fill(sim.ambient_temperature + 1.0);
+
+    sim.clear_velocity();
+    assert!(sim.velocity_y.iter().all(|&v| v == 0.0));
+    assert!(sim.temperature.iter().all(|&t| t == sim.ambient_temperature));
+    assert_eq!(sim.dye_r[8 * 16 + 8], 1.0);
+
+    sim.velocity_x.fill(1.0);
+    sim.clear_dye();
+    assert!(sim.dye_r.iter().chain(&sim.dye_g).all(|&v| v == 0.0));
+    assert!(sim.velocity_x.iter().all(|&v| v == 1.0));
+}
@@ -0,0 +1,178 @@
+// Compares the facade against the deprecated solvers it replaces
+#![allow(deprecated)]
+
+use glam::Vec2;
+use itsliquid::conformance::{self, Report};
+use itsliquid::export::FluidData;
+use itsliquid::fluid_simple::FluidSimulation as SimpleFluid;
+use itsliquid::{
+    BoundaryMode, FluidFinal, FluidSimulation, FluidSolver, InteractiveFluid, PoissonSolver, Scheme, Solver,
+    SolverConfig, WorkingFluid,
+};
+
+const W: usize = 32;
+const H: usize = 24;
+
+// The same strokes as `drive_legacy`, through the facade
+fn drive(solver: &mut Solver, steps: usize) {
+    for step in 0..steps {
+        if step < 5 {
+            for i in 0..6 {
+                solver.add_density(10 + i, 12, 1.0);
+                solver.add_velocity(10 + i, 12, Vec2::new(2.0, -1.0));
+            }
+        }
+        solver.step();
+    }
+}
+
+macro_rules! drive_legacy {
+    ($fluid:expr, $steps:expr) => {{
+        let mut fluid = $fluid;
+        for step in 0..$steps {
+            if step < 5 {
+                for i in 0..6 {
+                    fluid.add_density(10 + i, 12, 1.0);
+                    fluid.add_velocity(10 + i, 12, Vec2::new(2.0, -1.0));
+                }
+            }
+            fluid.step();
+        }
+        fluid
+    }};
+}
+
+fn assert_same(solver: &Solver, density: &[f32], velocity_x: &[f32], velocity_y: &[f32]) {
+    let scheme = solver.config().scheme.name();
+    assert_eq!(solver.density(), density, "{} density differs", scheme);
+    assert_eq!(solver.velocity_x(), velocity_x, "{} velocity_x differs", scheme);
+    assert_eq!(solver.velocity_y(), velocity_y, "{} velocity_y differs", scheme);
+}
+
+#[test]
+fn test_schemes_reproduce_legacy_solvers() {
+    let steps = 20;
+    for scheme in Scheme::ALL {
+        let mut solver = Solver::with_scheme(scheme, W, H);
+        drive(&mut solver, steps);
+
+        match scheme {
+            Scheme::Explicit => {
+                let f = drive_legacy!(SimpleFluid::new(W, H), steps);
+                assert_same(&solver, &f.density, &f.velocity_x, &f.velocity_y);
+            }
+            Scheme::ForwardMove => {
+                let f = drive_legacy!(FluidFinal::new(W, H), steps);
+                assert_same(&solver, &f.density, &f.velocity_x, &f.velocity_y);
+            }
+            Scheme::Stable => {
+                let f = drive_legacy!(WorkingFluid::new(W, H), steps);
+                assert_same(&solver, &f.density, &f.velocity_x, &f.velocity_y);
+            }
+            Scheme::Buoyant => {
+                let f = drive_legacy!(FluidSolver::new(W, H), steps);
+                assert_same(&solver, &f.density, &f.velocity_x, &f.velocity_y);
+            }
+            Scheme::Interactive => {
+                let mut f = InteractiveFluid::new(W, H);
+                for step in 0..steps {
+                    if step < 5 {
+                        for i in 0..6 {
+                            let third = 1.0 / 3.0;
+                            f.add_dye(10 + i, 12, (third, third, third));
+                            let idx = 12 * W + 10 + i;
+                            f.velocity_x[idx] += 2.0;
+                            f.velocity_y[idx] -= 1.0;
+                        }
+                    }
+                    f.step();
+                }
+                let total: Vec<f32> = (0..W * H).map(|i| f.dye_r[i] + f.dye_g[i] + f.dye_b[i]).collect();
+                assert_same(&solver, &total, &f.velocity_x, &f.velocity_y);
+                assert_eq!(solver.channel(0), Some(&f.dye_r[..]));
+                assert_eq!(solver.boundary_leak(), Some(f.boundary_leak));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_config_overrides_reach_solver() {
+    let config = SolverConfig::new(Scheme::Buoyant).with_dt(0.02).with_iterations(30);
+    let mut solver = Solver::new(config, W, H).unwrap();
+    drive(&mut solver, 10);
+
+    let mut legacy = FluidSolver::new(W, H);
+    legacy.dt = 0.02;
+    legacy.iterations = 30;
+    let legacy = drive_legacy!(legacy, 10);
+    assert_same(&solver, &legacy.density, &legacy.velocity_x, &legacy.velocity_y);
+
+    let config = SolverConfig::new(Scheme::Interactive).with_iterations(4);
+    let solver = Solver::new(config, W, H).unwrap();
+    assert_eq!(solver.as_interactive().unwrap().pressure_iterations, 4);
+}
+
+#[test]
+fn test_invalid_configs_are_rejected() {
+    let mut config = SolverConfig::new(Scheme::Stable);
+    config.boundary = BoundaryMode::FreeSlip;
+    assert!(Solver::new(config, W, H).is_err());
+
+    let mut config = SolverConfig::new(Scheme::Buoyant);
+    config.channels = 3;
+    assert!(Solver::new(config, W, H).is_err());
+
+    assert!(Solver::new(SolverConfig::new(Scheme::Interactive).with_iterations(0), W, H).is_err());
+    assert!(Solver::new(SolverConfig::new(Scheme::Explicit).with_dt(0.0), W, H).is_err());
+    assert!(Solver::new(SolverConfig::default(), 2, 2).is_err());
+
+    // FFT solves need the spectral feature
+    let spectral = SolverConfig::new(Scheme::Interactive)
+        .with_boundary(BoundaryMode::Periodic)
+        .with_poisson(PoissonSolver::Spectral);
+    assert_eq!(spectral.validate().is_ok(), cfg!(feature = "spectral"));
+
+    for scheme in Scheme::ALL {
+        assert!(SolverConfig::new(scheme).validate().is_ok(), "{} defaults invalid", scheme.name());
+    }
+}
+
+#[test]
+fn test_dye_on_one_channel_schemes_adds_up() {
+    let mut solver = Solver::with_scheme(Scheme::Stable, W, H);
+    FluidSimulation::add_dye(&mut solver, 5, 5, (0.5, 0.25, 0.25));
+    assert_eq!(solver.density()[5 * W + 5], 1.0);
+    assert_eq!(solver.channel(0).unwrap()[5 * W + 5], 1.0);
+    assert!(solver.channel(1).is_none());
+
+    let mut solver = Solver::with_scheme(Scheme::Interactive, W, H);
+    FluidSimulation::add_dye(&mut solver, 5, 5, (0.5, 0.25, 0.25));
+    assert_eq!(solver.density()[5 * W + 5], 1.0);
+    assert_eq!(solver.channel(2).unwrap()[5 * W + 5], 0.25);
+}
+
+#[test]
+fn test_interactive_scheme_conforms() {
+    let reports = conformance::run_all(|w, h| Ok(Solver::with_scheme(Scheme::Interactive, w, h))).unwrap();
+    let failures: Vec<String> = reports.iter().flat_map(Report::failures).collect();
+    assert!(failures.is_empty(), "Solver is not conformant:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_from_interactive_keeps_state() {
+    let mut fluid = InteractiveFluid::new(16, 16);
+    fluid.dt = 0.05;
+    fluid.add_dye(8, 8, (1.0, 2.0, 3.0));
+
+    let mut solver = Solver::from_interactive(fluid.clone());
+    assert_eq!(solver.config().scheme, Scheme::Interactive);
+    assert_eq!(solver.config().dt, 0.05);
+    assert_eq!(solver.density()[8 * 16 + 8], 6.0);
+
+    solver.as_interactive_mut().unwrap().add_force(8, 8, Vec2::new(2.0, 0.0), 3.0);
+    fluid.add_force(8, 8, Vec2::new(2.0, 0.0), 3.0);
+    solver.step();
+    fluid.step();
+    assert_eq!(solver.as_interactive().unwrap().dye_r, fluid.dye_r);
+}
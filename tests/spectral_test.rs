@@ -0,0 +1,119 @@
+#![cfg(feature = "spectral")]
+
+use itsliquid::spectral::SpectralSolver;
+use itsliquid::{BoundaryMode, InteractiveFluid, PoissonSolver, Scheme, Solver, SolverConfig};
+
+const W: usize = 34;
+const H: usize = 26;
+
+// Deterministic rough values in [-1, 1)
+fn noise(i: usize, seed: usize) -> f32 {
+    let n = (i * 7919 + seed * 104_729) % 2003;
+    n as f32 / 1001.5 - 1.0
+}
+
+fn rough_periodic_fluid() -> InteractiveFluid {
+    let mut fluid = InteractiveFluid::new(W, H);
+    fluid.boundary = BoundaryMode::Periodic;
+    for i in 0..W * H {
+        fluid.velocity_x[i] = noise(i, 1);
+        fluid.velocity_y[i] = noise(i, 2);
+    }
+    fluid
+}
+
+// Central-difference divergence over the interior; the ring holds the wrap
+fn max_divergence(fluid: &InteractiveFluid) -> f32 {
+    let (vx, vy) = (&fluid.velocity_x, &fluid.velocity_y);
+    let mut max = 0.0f32;
+    for y in 1..H - 1 {
+        for x in 1..W - 1 {
+            let i = y * W + x;
+            max = max.max((0.5 * (vx[i + 1] - vx[i - 1] + vy[i + W] - vy[i - W])).abs());
+        }
+    }
+    max
+}
+
+#[test]
+fn test_spectral_projection_is_exact() {
+    let mut iterative = rough_periodic_fluid();
+    let mut spectral = rough_periodic_fluid();
+    spectral.enable_spectral();
+    let before = max_divergence(&spectral);
+
+    iterative.project_velocity();
+    spectral.project_velocity();
+    let (sweeps, exact) = (max_divergence(&iterative), max_divergence(&spectral));
+    assert!(exact < 1e-4 * before, "{} left of {}", exact, before);
+    assert!(exact < 0.01 * sweeps, "spectral {} vs sweeps {}", exact, sweeps);
+
+    // Projecting again changes nothing
+    let projected = spectral.velocity_x.clone();
+    spectral.project_velocity();
+    let drift = projected.iter().zip(&spectral.velocity_x).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+    assert!(drift < 1e-4, "second projection moved {}", drift);
+}
+
+#[test]
+fn test_spectral_diffusion_matches_converged_sweeps() {
+    let a = 0.8;
+    let (nx, ny) = (W - 2, H - 2);
+    let start: Vec<f32> = (0..W * H).map(|i| noise(i, 3).max(0.0)).collect();
+
+    let mut exact = start.clone();
+    SpectralSolver::new(W, H).diffuse(&mut exact, a);
+
+    // Jacobi on the periodic interior, run far past convergence
+    let interior = |f: &[f32], x: usize, y: usize| f[(y % ny + 1) * W + x % nx + 1];
+    let mut x0 = vec![0.0; nx * ny];
+    for y in 0..ny {
+        for x in 0..nx {
+            x0[y * nx + x] = interior(&start, x, y);
+        }
+    }
+    let mut relaxed = x0.clone();
+    for _ in 0..2000 {
+        let prev = relaxed.clone();
+        for y in 0..ny {
+            for x in 0..nx {
+                let at = |dx: usize, dy: usize| prev[((y + dy) % ny) * nx + (x + dx) % nx];
+                let sum = at(1, 0) + at(nx - 1, 0) + at(0, 1) + at(0, ny - 1);
+                relaxed[y * nx + x] = (x0[y * nx + x] + a * sum) / (1.0 + 4.0 * a);
+            }
+        }
+    }
+    for y in 0..ny {
+        for x in 0..nx {
+            let (want, got) = (relaxed[y * nx + x], interior(&exact, x, y));
+            assert!((want - got).abs() < 1e-4, "({}, {}): {} vs {}", x, y, got, want);
+        }
+    }
+    let total = |f: &[f32]| (0..ny).flat_map(|y| (0..nx).map(move |x| (x, y))).map(|(x, y)| interior(f, x, y)).sum::<f32>();
+    assert!((total(&exact) - total(&start)).abs() < 1e-3);
+}
+
+#[test]
+fn test_solver_config_selects_spectral() {
+    let config = SolverConfig::new(Scheme::Interactive)
+        .with_boundary(BoundaryMode::Periodic)
+        .with_poisson(PoissonSolver::Spectral);
+    let mut solver = Solver::new(config, 40, 40).unwrap();
+    let fluid = solver.as_interactive_mut().unwrap();
+    assert!(fluid.spectral.is_some());
+    fluid.add_dye(20, 20, (1.0, 0.5, 0.0));
+    fluid.add_force(20, 20, glam::Vec2::new(40.0, 10.0), 4.0);
+    for _ in 0..20 {
+        solver.step();
+    }
+    let fluid = solver.as_interactive().unwrap();
+    let total: f32 = fluid.dye_r.iter().skip(40).take(38 * 40).sum();
+    assert!(total > 0.0 && fluid.velocity_x.iter().all(|v| v.is_finite()));
+    assert_eq!(Solver::from_interactive(fluid.clone()).config().poisson, PoissonSolver::Spectral);
+
+    // Only periodic edges have a spectral solve
+    let walled = SolverConfig::new(Scheme::Interactive).with_poisson(PoissonSolver::Spectral);
+    assert!(walled.validate().is_err());
+    let other = SolverConfig::new(Scheme::Stable).with_poisson(PoissonSolver::Spectral);
+    assert!(other.validate().is_err());
+}
@@ -0,0 +1,77 @@
+use itsliquid::still::sample_bicubic;
+use itsliquid::{Bloom, InteractiveFluid, Mask, StillExport};
+
+#[test]
+fn test_bicubic_passes_through_cells() {
+    let (w, h) = (5, 4);
+    let field: Vec<f32> = (0..w * h).map(|i| (i * 7 % 11) as f32).collect();
+    for y in 0..h {
+        for x in 0..w {
+            let v = sample_bicubic(&field, w, h, x as f32, y as f32);
+            assert!((v - field[y * w + x]).abs() < 1e-5, "cell ({x}, {y}): {v}");
+        }
+    }
+    // A linear ramp stays linear between cells
+    let ramp: Vec<f32> = (0..w * h).map(|i| (i % w) as f32).collect();
+    let v = sample_bicubic(&ramp, w, h, 1.25, 2.0);
+    assert!((v - 1.25).abs() < 1e-5, "{v}");
+}
+
+#[test]
+fn test_still_dimensions_follow_scale() {
+    let sim = InteractiveFluid::new(30, 20);
+    for scale in [1, 2, 4, 8] {
+        let still = StillExport { scale, ..Default::default() };
+        assert_eq!(still.dimensions(&sim), (30 * scale, 20 * scale));
+        assert_eq!(still.render(&sim).dimensions(), (30 * scale, 20 * scale));
+    }
+    let png = StillExport::default().png(&sim).unwrap();
+    assert_eq!(&png[1..4], b"PNG");
+}
+
+#[test]
+fn test_still_smooths_gradient() {
+    // A ramp of dye: blocky upscaling steps once per cell, bicubic every pixel
+    let mut sim = InteractiveFluid::new(16, 4);
+    for y in 0..4 {
+        for x in 0..16 {
+            sim.dye_r[y * 16 + x] = x as f32 * 0.02;
+        }
+    }
+    let img = StillExport { scale: 8, ..Default::default() }.render(&sim);
+    let row: Vec<u8> = (0..img.width()).map(|x| img.get_pixel(x, 16)[0]).collect();
+    let largest_step = row.windows(2).map(|p| p[1] as i32 - p[0] as i32).max().unwrap();
+    assert!(largest_step <= 1, "step of {largest_step}");
+    assert!(row.windows(2).all(|p| p[1] >= p[0]), "ramp should rise monotonically");
+}
+
+#[test]
+fn test_still_draws_obstacles() {
+    let mut sim = InteractiveFluid::new(10, 10);
+    let mut mask = Mask::new(10, 10);
+    mask.cells[5 * 10 + 5] = true;
+    sim.obstacles = Some(mask);
+    let img = StillExport { scale: 4, ..Default::default() }.render(&sim);
+    assert_eq!(img.get_pixel(22, 22).0, [90, 90, 90]);
+    assert_eq!(img.get_pixel(2, 2).0, [0, 0, 0]);
+}
+
+#[test]
+fn test_bloom_glows_around_bright_dye() {
+    let mut sim = InteractiveFluid::new(20, 20);
+    sim.dye_g[10 * 20 + 10] = 6.0;
+    let plain = StillExport { scale: 2, ..Default::default() }.render(&sim);
+    let bloomed = StillExport { scale: 2, bloom: Some(Bloom::default()), ..Default::default() }.render(&sim);
+    // Three cells away is dark without bloom and lit with it
+    let (x, y) = (2 * 13 + 1, 2 * 10 + 1);
+    assert_eq!(plain.get_pixel(x, y)[1], 0);
+    assert!(bloomed.get_pixel(x, y)[1] > 0);
+    assert_eq!(bloomed.get_pixel(x, y)[0], 0, "glow keeps the dye's color");
+
+    // Nothing over the threshold, nothing changes
+    let mut dim = InteractiveFluid::new(20, 20);
+    dim.dye_r.fill(0.5);
+    let plain = StillExport { scale: 2, ..Default::default() }.render(&dim);
+    let bloomed = StillExport { scale: 2, bloom: Some(Bloom::default()), ..Default::default() }.render(&dim);
+    assert_eq!(plain, bloomed);
+}
@@ -0,0 +1,48 @@
+use itsliquid::stress::{StressConfig, StressRun};
+use std::time::Duration;
+
+fn config(seed: u64, frames: usize) -> StressConfig {
+    StressConfig {
+        seed,
+        duration: Duration::from_secs(60),
+        max_frames: Some(frames),
+        check_every: 5,
+        width: 32,
+        height: 32,
+    }
+}
+
+#[test]
+fn test_stress_run_keeps_invariants() {
+    for seed in 0..3 {
+        let report = StressRun::new(config(seed, 200)).run().unwrap_or_else(|failure| panic!("{}", failure));
+        assert_eq!(report.frames, 200);
+        assert_eq!(report.checks, 40);
+        assert!(report.actions > 0);
+    }
+}
+
+#[test]
+fn test_stress_run_is_reproducible() {
+    let mut a = StressRun::new(config(7, 50));
+    let mut b = StressRun::new(config(7, 50));
+    a.run().unwrap();
+    b.run().unwrap();
+    assert_eq!(a.simulation.width, b.simulation.width);
+    assert_eq!(a.simulation.dye_r, b.simulation.dye_r);
+    assert_eq!(a.simulation.velocity_x, b.simulation.velocity_x);
+}
+
+#[test]
+fn test_check_reports_broken_state() {
+    let mut run = StressRun::new(config(0, 0));
+    assert!(run.check().is_ok());
+
+    run.simulation.velocity_y[5 * 32 + 3] = f32::NAN;
+    let violation = run.check().unwrap_err();
+    assert!(violation.contains("velocity_y") && violation.contains("(3, 5)"), "{}", violation);
+
+    run.simulation.velocity_y[5 * 32 + 3] = 0.0;
+    run.simulation.dye_b.pop();
+    assert!(run.check().unwrap_err().contains("dye_b has 1023 cells"));
+}
@@ -0,0 +1,134 @@
+use itsliquid::{BlendMode, Emission, InteractiveFluid, Interpolation, PersistentElement, PersistentElementType, Scene, Timeline};
+
+fn dye_source(x: f32, color: (f32, f32, f32), intensity: f32) -> PersistentElement {
+    PersistentElement {
+        element_type: PersistentElementType::DyeSource { color, intensity, blend: BlendMode::Add, emission: Emission::default() },
+        x,
+        y: 10.0,
+        radius: 2.0,
+        audio: None,
+    }
+}
+
+// Keys at frames 0 and 10 moving from x=0 to x=20, brightening and
+// switching on halfway
+fn keyed() -> Timeline {
+    let mut timeline = Timeline::default();
+    timeline.set_key(0, &dye_source(0.0, (1.0, 0.0, 0.0), 1.0), true);
+    timeline.frame = 10;
+    timeline.set_key(0, &dye_source(20.0, (0.0, 0.0, 1.0), 3.0), true);
+    timeline.frame = 5;
+    timeline.set_key(0, &dye_source(10.0, (0.5, 0.0, 0.5), 2.0), false);
+    timeline
+}
+
+#[test]
+fn test_keys_interpolate_between_frames() {
+    let mut timeline = keyed();
+    let mut elements = vec![dye_source(99.0, (1.0, 1.0, 1.0), 9.0)];
+
+    timeline.frame = 2;
+    timeline.pose(&mut elements);
+    assert!((elements[0].x - 4.0).abs() < 1e-5);
+    assert!((elements[0].strength() - 1.4).abs() < 1e-5);
+    assert_eq!(elements[0].radius, 2.0);
+    assert!(timeline.enabled(0));
+    match elements[0].element_type {
+        PersistentElementType::DyeSource { color, .. } => assert!((color.0 - 0.8).abs() < 1e-5 && (color.2 - 0.2).abs() < 1e-5),
+        other => panic!("{:?}", other),
+    }
+
+    // On/off switches at its key rather than blending
+    timeline.frame = 7;
+    assert!(!timeline.enabled(0));
+    timeline.frame = 10;
+    assert!(timeline.enabled(0));
+
+    // Held before the first key and after the last
+    timeline.frame = 50;
+    timeline.pose(&mut elements);
+    assert_eq!(elements[0].x, 20.0);
+    assert_eq!(timeline.key_frames(), vec![0, 5, 10]);
+    // Elements without a track are always on
+    assert!(timeline.enabled(1));
+}
+
+#[test]
+fn test_interpolation_modes() {
+    let mut timeline = keyed();
+    let mut elements = vec![dye_source(0.0, (1.0, 1.0, 1.0), 1.0)];
+    timeline.tracks[0].keys[0].interpolation = Interpolation::Hold;
+    timeline.frame = 4;
+    timeline.pose(&mut elements);
+    assert_eq!(elements[0].x, 0.0);
+
+    timeline.tracks[0].keys[1].interpolation = Interpolation::Smooth;
+    timeline.frame = 6;
+    timeline.pose(&mut elements);
+    // Eases out of the key, so behind the linear 12
+    assert!(elements[0].x > 10.0 && elements[0].x < 12.0, "{}", elements[0].x);
+    timeline.frame = 10;
+    timeline.pose(&mut elements);
+    assert_eq!(elements[0].x, 20.0);
+}
+
+#[test]
+fn test_playhead_loops_or_stops() {
+    let mut timeline = Timeline { length: 3, playing: true, ..Timeline::default() };
+    for _ in 0..3 {
+        timeline.advance();
+    }
+    assert_eq!((timeline.frame, timeline.playing), (0, true));
+
+    timeline.looping = false;
+    for _ in 0..5 {
+        timeline.advance();
+    }
+    assert_eq!((timeline.frame, timeline.playing), (2, false));
+}
+
+#[test]
+fn test_removing_elements_renumbers_tracks() {
+    let mut timeline = Timeline::default();
+    for element in 0..3 {
+        timeline.set_key(element, &dye_source(element as f32, (1.0, 0.0, 0.0), 1.0), true);
+    }
+    timeline.element_removed(1);
+    let elements: Vec<usize> = timeline.tracks.iter().map(|track| track.element).collect();
+    assert_eq!(elements, vec![0, 1]);
+    assert_eq!(timeline.tracks[1].keys[0].x, 2.0);
+
+    assert!(timeline.remove_key(0));
+    assert!(!timeline.remove_key(0));
+    assert_eq!(timeline.tracks.len(), 1);
+}
+
+#[test]
+fn test_fan_keeps_direction_when_keyed() {
+    let fan = PersistentElement {
+        element_type: PersistentElementType::FanStrip { span: (10.0, 0.0), velocity: (0.0, -3.0) },
+        x: 5.0,
+        y: 5.0,
+        radius: 2.0,
+        audio: None,
+    };
+    assert_eq!(fan.strength(), 3.0);
+    assert_eq!(fan.with_strength(6.0).element_type, PersistentElementType::FanStrip { span: (10.0, 0.0), velocity: (0.0, -6.0) });
+}
+
+#[test]
+fn test_scene_saves_timeline() {
+    let fluid = InteractiveFluid::new(40, 20);
+    let mut scene = Scene::capture(&fluid, &[dye_source(0.0, (1.0, 0.0, 0.0), 1.0)], false);
+    assert!(!scene.to_json().unwrap().contains("timeline"));
+
+    scene.timeline = Some(keyed());
+    let loaded = Scene::from_json(&scene.to_json().unwrap()).unwrap();
+    let timeline = loaded.timeline.as_ref().unwrap();
+    assert_eq!(timeline.tracks, scene.timeline.as_ref().unwrap().tracks);
+    // The playhead isn't saved
+    assert_eq!(timeline.frame, 0);
+
+    let doubled = loaded.timeline_for(80, 40).unwrap();
+    assert_eq!(doubled.tracks[0].keys[2].x, 40.0);
+}
@@ -0,0 +1,114 @@
+use itsliquid::timing::MAX_CATCH_UP_STEPS;
+use itsliquid::{InteractiveFluid, PerfStats, ScalarField, Stage, StageTimes, StepClock};
+
+#[test]
+fn test_step_times_each_stage() {
+    let mut sim = InteractiveFluid::new(64, 64);
+    sim.add_dye(32, 32, (1.0, 0.5, 0.2));
+    sim.add_force(32, 32, glam::Vec2::new(5.0, 0.0), 4.0);
+    sim.step();
+
+    let times = sim.stage_times;
+    assert!(times.total > 0.0);
+    for stage in [times.diffuse, times.advect, times.project] {
+        assert!(stage > 0.0 && stage <= times.total, "{:?}", times);
+    }
+    let parts = times.diffuse + times.advect + times.project + times.other();
+    assert!((parts - times.total).abs() < 1e-9);
+}
+
+#[test]
+fn test_stage_times_add_up() {
+    let mut times = StageTimes::default();
+    times.add(Stage::Diffuse, 0.002);
+    times.add(Stage::Project, 0.003);
+    times.add(Stage::Project, 0.001);
+    times.total = 0.01;
+    assert!((times.project - 0.004).abs() < 1e-12);
+    assert!((times.other() - 0.004).abs() < 1e-12);
+
+    let mut frame = StageTimes::default();
+    frame.accumulate(&times);
+    frame.accumulate(&times);
+    assert_eq!(frame.total, 0.02);
+    assert_eq!(frame.diffuse, 0.004);
+}
+
+#[test]
+fn test_perf_stats_average_frames() {
+    let mut perf = PerfStats::new();
+    let step = StageTimes { diffuse: 0.001, advect: 0.002, project: 0.003, total: 0.008 };
+    perf.frame(10.0, step);
+    assert_eq!(perf.fps(), 0.0, "one frame has no rate yet");
+    for i in 1..=200 {
+        perf.frame(10.0 + i as f64 * 0.02, step);
+    }
+    assert!((perf.fps() - 50.0).abs() < 1e-6, "{}", perf.fps());
+    assert!((perf.frame_ms() - 20.0).abs() < 1e-6);
+    assert!((perf.solver().project - 0.003).abs() < 1e-9);
+
+    // A slower frame moves the average only part of the way
+    perf.frame(10.0 + 200.0 * 0.02 + 0.1, step);
+    assert!(perf.frame_ms() > 20.0 && perf.frame_ms() < 100.0);
+    assert!(perf.summary().contains("project"));
+}
+
+#[test]
+fn test_memory_counts_fields() {
+    let mut sim = InteractiveFluid::new(40, 30);
+    let base = sim.memory_bytes();
+    assert_eq!(base % (40 * 30 * 4), 0);
+    assert!(base >= 12 * 40 * 30 * 4);
+    sim.add_scalar(ScalarField::new("smoke", 40, 30)).unwrap();
+    assert_eq!(sim.memory_bytes(), base + 2 * 40 * 30 * 4);
+}
+
+// Steps run over `seconds` of frames drawn at `hz`
+fn steps_over(clock: &mut StepClock, hz: f64, seconds: f64) -> usize {
+    let frames = (hz * seconds).round() as usize;
+    (0..=frames).map(|frame| clock.steps(frame as f64 / hz)).sum()
+}
+
+#[test]
+fn test_step_clock_ignores_the_refresh_rate() {
+    for hz in [30.0, 60.0, 144.0] {
+        let steps = steps_over(&mut StepClock::new(60.0), hz, 2.0);
+        assert!((119..=121).contains(&steps), "{} Hz ran {} steps", hz, steps);
+    }
+}
+
+#[test]
+fn test_step_clock_slow_motion() {
+    let mut clock = StepClock::new(60.0);
+    clock.speed = 0.25;
+    let steps = steps_over(&mut clock, 60.0, 4.0);
+    assert!((59..=61).contains(&steps), "{}", steps);
+    assert!((clock.time_scale(0.1) - 1.5).abs() < 1e-6);
+}
+
+#[test]
+fn test_step_clock_caps_catch_up_and_pauses() {
+    let mut clock = StepClock::new(60.0);
+    assert_eq!(clock.steps(0.0), 0);
+    // A frame that took a whole second runs only a few steps
+    assert_eq!(clock.steps(1.0), MAX_CATCH_UP_STEPS);
+    // and leaves no backlog behind
+    assert_eq!(clock.steps(1.0 + 1.5 / 60.0), 1);
+
+    clock.pause();
+    assert_eq!(clock.steps(10.0), 0);
+    assert_eq!(clock.steps(10.0 + 1.5 / 60.0), 1);
+}
+
+#[test]
+fn test_step_clock_fixed_steps_per_frame() {
+    let mut clock = StepClock::new(60.0);
+    clock.per_frame = Some(8);
+    // Past the catch-up cap, however fast the frames come
+    assert_eq!(clock.steps(0.0), 8);
+    assert_eq!(clock.steps(0.001), 8);
+
+    // Back on the clock without catching up on the fast-forward
+    clock.per_frame = None;
+    assert_eq!(clock.steps(0.001 + 1.5 / 60.0), 1);
+}
@@ -0,0 +1,64 @@
+use itsliquid::{Brush, InteractiveFluid, Turbulence};
+
+#[test]
+fn test_turbulence_is_divergence_free() {
+    let turbulence = Turbulence { time: 0.3, ..Turbulence::default() };
+    let h = 0.05;
+    for &(x, y) in &[(5.0, 7.0), (20.5, 3.25), (40.0, 41.0)] {
+        let dvx = (turbulence.velocity_at(x + h, y).0 - turbulence.velocity_at(x - h, y).0) / (2.0 * h);
+        let dvy = (turbulence.velocity_at(x, y + h).1 - turbulence.velocity_at(x, y - h).1) / (2.0 * h);
+        assert!((dvx + dvy).abs() < 0.05, "divergence {} at ({}, {})", dvx + dvy, x, y);
+    }
+    // And it does move something
+    assert!((0..20).any(|i| turbulence.velocity_at(i as f32 * 1.7, 4.0) != (0.0, 0.0)));
+}
+
+#[test]
+fn test_turbulence_animates() {
+    let mut turbulence = Turbulence::default();
+    let before = turbulence.velocity_at(6.0, 9.0);
+    let (mut vx, mut vy) = (vec![0.0; 16 * 16], vec![0.0; 16 * 16]);
+    for _ in 0..10 {
+        turbulence.apply(&mut vx, &mut vy, 16, 0.1);
+    }
+    assert!((turbulence.time - 0.5).abs() < 1e-5);
+    assert_ne!(turbulence.velocity_at(6.0, 9.0), before);
+
+    // Frozen at speed 0
+    let mut frozen = Turbulence { speed: 0.0, ..Turbulence::default() };
+    frozen.apply(&mut vx, &mut vy, 16, 0.1);
+    assert_eq!(frozen.time, 0.0);
+}
+
+#[test]
+fn test_painted_turbulence_stays_in_region() {
+    let mut turbulence = Turbulence { everywhere: false, ..Turbulence::default() };
+    let brush = Brush { radius: 3.0, ..Brush::default() };
+    turbulence.paint(&brush, 8.0, 8.0, 32, 32, 1.0);
+    assert!(turbulence.has_region());
+
+    let (mut vx, mut vy) = (vec![0.0; 32 * 32], vec![0.0; 32 * 32]);
+    turbulence.apply(&mut vx, &mut vy, 32, 0.1);
+    // Untouched far from the paint
+    for y in 16..32 {
+        for x in 16..32 {
+            assert_eq!((vx[y * 32 + x], vy[y * 32 + x]), (0.0, 0.0));
+        }
+    }
+    assert!((0..32 * 32).any(|i| vx[i] != 0.0 || vy[i] != 0.0));
+
+    // Erasing clears the region
+    turbulence.paint(&brush, 8.0, 8.0, 32, 32, -1.0);
+    assert!(!turbulence.has_region());
+}
+
+#[test]
+fn test_fluid_applies_turbulence_each_step() {
+    let mut fluid = InteractiveFluid::new(32, 32);
+    fluid.turbulence = Some(Turbulence::default());
+    for _ in 0..5 {
+        fluid.step();
+    }
+    assert!(fluid.velocity_x.iter().chain(&fluid.velocity_y).any(|&v| v != 0.0));
+    assert!(fluid.turbulence.unwrap().time > 0.0);
+}
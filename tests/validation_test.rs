@@ -0,0 +1,59 @@
+use itsliquid::validation::{ErrorNorms, LidDrivenCavity, TaylorGreen, GHIA_RE100_U};
+
+#[test]
+fn test_error_norms() {
+    let norms = ErrorNorms::from_pairs([(1.0, 1.0), (2.0, 1.0), (0.0, 1.0), (1.0, 1.0)]);
+    assert!((norms.l2 - 0.5f32.sqrt()).abs() < 1e-6);
+    assert_eq!(norms.linf, 1.0);
+    assert!((norms.relative_l2 - norms.l2).abs() < 1e-6, "reference RMS is 1");
+
+    assert_eq!(ErrorNorms::from_pairs([]), ErrorNorms::default());
+    assert_eq!(ErrorNorms::from_pairs([(0.5, 0.0)]).relative_l2, 0.0);
+}
+
+#[test]
+fn test_taylor_green_setup_is_exact() {
+    let vortex = TaylorGreen::default();
+    let fluid = vortex.setup();
+    let errors = vortex.errors(&fluid, 0.0);
+    assert_eq!(errors.linf, 0.0);
+    assert!((vortex.amplitude_of(&fluid) - vortex.amplitude).abs() < 1e-5);
+
+    // The exact field repeats over one period
+    let period = vortex.period();
+    let (u0, v0) = vortex.velocity(3, 5, 1.0);
+    let (u1, v1) = vortex.velocity(3 + period, 5 + period, 1.0);
+    assert!((u0 - u1).abs() < 1e-5 && (v0 - v1).abs() < 1e-5);
+}
+
+#[test]
+fn test_taylor_green_decays_at_the_viscous_rate() {
+    let vortex = TaylorGreen::default();
+    let report = vortex.run(40);
+    // Measured at about 0.3% and 0.1%
+    assert!(report.velocity.relative_l2 < 0.01, "{:?}", report);
+    let rate_error = (report.measured_decay - report.exact_decay).abs() / report.exact_decay;
+    assert!(rate_error < 0.02, "{:?}", report);
+
+    // Doubling the viscosity doubles the decay
+    let thicker = TaylorGreen { viscosity: vortex.viscosity * 2.0, ..vortex.clone() };
+    assert!((thicker.decay_rate() / vortex.decay_rate() - 2.0).abs() < 1e-5);
+    assert!(thicker.run(40).measured_decay > report.measured_decay * 1.7);
+}
+
+#[test]
+fn test_lid_driven_cavity_recirculates() {
+    let cavity = LidDrivenCavity::default();
+    let report = cavity.run(600);
+    assert_eq!(report.u_profile.len(), GHIA_RE100_U.len());
+
+    // Dragged along under the lid, back along the middle
+    let lid = report.u_profile.last().unwrap();
+    assert!((lid.1 - 1.0).abs() < 1e-5);
+    let middle = report.u_profile.iter().find(|(height, _)| *height == 0.5).unwrap();
+    assert!(middle.1 < 0.0, "{:?}", report.u_profile);
+
+    // Closer to the reference than still fluid, which is ~0.45 off
+    assert!(report.u.l2 < 0.3, "{:?}", report.u);
+    assert!(report.v.l2 < 0.3, "{:?}", report.v);
+}
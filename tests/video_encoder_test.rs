@@ -0,0 +1,86 @@
+// Drives the encoder with small shell scripts in place of ffmpeg, so these
+// run without ffmpeg installed
+#![cfg(unix)]
+
+use image::RgbImage;
+use itsliquid::export::{VideoEncoder, VideoOptions};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Write an executable stand-in for ffmpeg that runs `body`
+fn stub_ffmpeg(name: &str, body: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("itsliquid_stub_ffmpeg_{}", name));
+    std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn options(ffmpeg: &Path) -> VideoOptions {
+    VideoOptions {
+        ffmpeg_path: ffmpeg.to_string_lossy().into_owned(),
+        ..VideoOptions::default()
+    }
+}
+
+#[test]
+fn test_missing_ffmpeg_fails_to_start() {
+    let opts = options(Path::new("/nonexistent/itsliquid-ffmpeg"));
+    let err = VideoEncoder::start(Path::new("out.mp4"), 8, 8, &opts).err().unwrap();
+    assert!(err.to_string().contains("failed to launch"), "{}", err);
+}
+
+#[test]
+fn test_frames_are_piped_and_finish_succeeds() {
+    let stub = stub_ffmpeg("cat", "cat > /dev/null");
+    let mut encoder = VideoEncoder::start(Path::new("out.mp4"), 8, 6, &options(&stub)).unwrap();
+    for _ in 0..3 {
+        encoder.write_frame(&RgbImage::new(8, 6)).unwrap();
+    }
+    assert!(encoder.write_frame(&RgbImage::new(6, 8)).is_err());
+    encoder.finish().unwrap();
+}
+
+#[test]
+fn test_finish_reports_ffmpeg_failure() {
+    let stub = stub_ffmpeg("fail", "cat > /dev/null; exit 1");
+    let mut encoder = VideoEncoder::start(Path::new("out.mp4"), 4, 4, &options(&stub)).unwrap();
+    encoder.write_frame(&RgbImage::new(4, 4)).unwrap();
+    let err = encoder.finish().err().unwrap();
+    assert!(err.to_string().contains("ffmpeg exited"), "{}", err);
+}
+
+#[test]
+fn test_write_to_exited_ffmpeg_fails() {
+    let stub = stub_ffmpeg("exit", "exit 0");
+    let mut encoder = VideoEncoder::start(Path::new("out.mp4"), 512, 512, &options(&stub)).unwrap();
+    // Larger than a pipe buffer, so the write can't complete before the
+    // reader is gone
+    let result = (0..4).try_for_each(|_| encoder.write_frame(&RgbImage::new(512, 512)));
+    assert!(result.is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_dropped_encoder_kills_and_reaps_ffmpeg() {
+    let pid_file = std::env::temp_dir().join("itsliquid_stub_ffmpeg_hang.pid");
+    std::fs::remove_file(&pid_file).ok();
+    // Ignores its stdin closing, like an ffmpeg stuck on a slow disk
+    let stub = stub_ffmpeg("hang", &format!("echo $$ > {}\nexec sleep 60", pid_file.display()));
+    let encoder = VideoEncoder::start(Path::new("out.mp4"), 4, 4, &options(&stub)).unwrap();
+
+    let started = Instant::now();
+    let pid = loop {
+        if let Some(pid) = std::fs::read_to_string(&pid_file).ok().and_then(|s| s.trim().parse::<u32>().ok()) {
+            break pid;
+        }
+        assert!(started.elapsed() < Duration::from_secs(10), "stub never started");
+        std::thread::sleep(Duration::from_millis(10));
+    };
+
+    drop(encoder);
+    std::fs::remove_file(&pid_file).ok();
+    assert!(started.elapsed() < Duration::from_secs(30));
+    // A zombie keeps its /proc entry until it is waited on
+    assert!(!Path::new(&format!("/proc/{}", pid)).exists(), "ffmpeg {} was left behind", pid);
+}
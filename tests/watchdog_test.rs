@@ -0,0 +1,88 @@
+use itsliquid::watchdog::Fault;
+use itsliquid::{InteractiveFluid, SimCommand, Watchdog, WatchdogConfig};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+fn config(checkpoint_interval: u64) -> WatchdogConfig {
+    WatchdogConfig {
+        deadline: Duration::from_millis(200),
+        checkpoint_interval,
+        ..WatchdogConfig::default()
+    }
+}
+
+#[test]
+fn test_healthy_steps_advance_and_apply_commands() {
+    let mut watchdog = Watchdog::new(InteractiveFluid::new(16, 16), config(2));
+    watchdog.queue(SimCommand::Dye { x: 0.5, y: 0.5, color: (1.0, 0.0, 0.0) });
+    for _ in 0..5 {
+        assert!(watchdog.step().is_none());
+    }
+
+    assert_eq!(watchdog.step_count(), 5);
+    assert_eq!(watchdog.checkpoint_step(), 4);
+    assert!(watchdog.simulation().dye_r.iter().sum::<f32>() > 0.0);
+    assert!(watchdog.incidents().is_empty());
+}
+
+#[test]
+fn test_panic_restores_last_checkpoint() {
+    let fail = Arc::new(AtomicBool::new(false));
+    let trigger = fail.clone();
+    let mut watchdog = Watchdog::with_step(
+        InteractiveFluid::new(16, 16),
+        config(3),
+        Arc::new(move |sim: &mut InteractiveFluid| {
+            if trigger.load(Ordering::SeqCst) {
+                panic!("solver blew up");
+            }
+            sim.step();
+        }),
+    );
+
+    for _ in 0..4 {
+        watchdog.step();
+    }
+    fail.store(true, Ordering::SeqCst);
+    watchdog.queue(SimCommand::Heat { x: 0.5, y: 0.5, amount: 2.0 });
+
+    let incident = watchdog.step().expect("panic should be reported").clone();
+    let Fault::Panic(message) = &incident.fault else { panic!("expected panic fault") };
+    assert!(message.contains("solver blew up"), "{}", message);
+    assert!(message.contains("watchdog_test.rs"), "{}", message);
+    assert_eq!(incident.step, 5);
+    assert_eq!(incident.restored_step, 3);
+    assert_eq!(incident.recent_commands, vec![SimCommand::Heat { x: 0.5, y: 0.5, amount: 2.0 }]);
+    assert!(incident.report().contains("restored to step: 3"));
+    assert_eq!(watchdog.step_count(), 3);
+
+    // A fresh worker picks up once the fault clears
+    fail.store(false, Ordering::SeqCst);
+    assert!(watchdog.step().is_none());
+    assert_eq!(watchdog.step_count(), 4);
+}
+
+#[test]
+fn test_missed_deadline_abandons_hung_worker() {
+    let hang = Arc::new(AtomicBool::new(true));
+    let trigger = hang.clone();
+    let mut watchdog = Watchdog::with_step(
+        InteractiveFluid::new(16, 16),
+        config(10),
+        Arc::new(move |sim: &mut InteractiveFluid| {
+            if trigger.swap(false, Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(1000));
+            }
+            sim.step();
+        }),
+    );
+
+    let incident = watchdog.step().expect("hang should be reported");
+    assert_eq!(incident.fault, Fault::DeadlineMissed(Duration::from_millis(200)));
+    assert_eq!(watchdog.step_count(), 0);
+
+    assert!(watchdog.step().is_none());
+    assert_eq!(watchdog.step_count(), 1);
+    assert_eq!(watchdog.incidents().len(), 1);
+}